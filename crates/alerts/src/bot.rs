@@ -0,0 +1,233 @@
+use anyhow::{Context, Result, bail};
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+use std::fmt::Write;
+
+/// Longest text Telegram accepts in one message
+pub const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Reply to `/help` and to unknown commands
+pub const BOT_HELP: &str = "Commands:\n\
+    /link <code> - follow an owner's positions in this chat, with a code from the API\n\
+    /portfolio - value, fees and IL of the owner's positions\n\
+    /position <nft_id> - P&L of one position\n\
+    /health - health of the owner's open positions\n\
+    /mute <rule_id> - disable an alert rule that notifies this chat\n\
+    /unmute <rule_id> - enable it again";
+
+/// A command sent to the bot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotCommand {
+    Help,
+    /// Follow the owner a link code was issued for from this chat
+    Link(String),
+    Portfolio,
+    /// A position by NFT ID
+    Position(String),
+    Health,
+    /// Disable an alert rule by ID
+    Mute(i64),
+    /// Enable an alert rule by ID
+    Unmute(i64),
+}
+
+impl BotCommand {
+    /// Parse a chat message: None for plain text, an error for a command used wrongly
+    ///
+    /// Accepts the `/command@BotName` form Telegram uses in group chats.
+    pub fn parse(text: &str) -> Option<Result<Self, String>> {
+        let mut words = text.split_whitespace();
+        let command = words.next()?.strip_prefix('/')?;
+        let command = command.split_once('@').map_or(command, |(command, _)| command);
+        let argument = words.next();
+
+        let rule_id = |usage: &str| match argument.map(str::parse::<i64>) {
+            Some(Ok(id)) => Ok(id),
+            _ => Err(format!("Usage: /{} <rule_id>", usage)),
+        };
+        Some(match command.to_lowercase().as_str() {
+            "start" | "help" => Ok(BotCommand::Help),
+            "link" => argument
+                .map(|code| BotCommand::Link(code.to_string()))
+                .ok_or_else(|| "Usage: /link <code>".to_string()),
+            "portfolio" => Ok(BotCommand::Portfolio),
+            "position" => argument
+                .map(|id| BotCommand::Position(id.to_string()))
+                .ok_or_else(|| "Usage: /position <nft_id>".to_string()),
+            "health" => Ok(BotCommand::Health),
+            "mute" => rule_id("mute").map(BotCommand::Mute),
+            "unmute" => rule_id("unmute").map(BotCommand::Unmute),
+            _ => Err(format!("Unknown command /{}\n\n{}", command, BOT_HELP)),
+        })
+    }
+}
+
+/// A text message the bot received
+#[derive(Debug, Clone)]
+pub struct BotMessage {
+    pub chat_id: String,
+    pub text: String,
+}
+
+/// Telegram bot reading its messages by long polling `getUpdates`
+///
+/// Updates are acknowledged by the offset of the next poll, so messages
+/// received while the bot was down are answered when it comes back (Telegram
+/// keeps them for a day).
+pub struct TelegramBot {
+    client: Client,
+    bot_token: String,
+    offset: Option<i64>,
+}
+
+impl TelegramBot {
+    pub fn new(bot_token: &str) -> Self {
+        Self { client: Client::new(), bot_token: bot_token.to_string(), offset: None }
+    }
+
+    fn url(&self, method: &str) -> String {
+        format!("https://api.telegram.org/bot{}/{}", self.bot_token, method)
+    }
+
+    /// Wait up to `timeout_seconds` for new messages and return their text ones
+    pub async fn poll(&mut self, timeout_seconds: u64) -> Result<Vec<BotMessage>> {
+        let response: Value = self
+            .client
+            .post(self.url("getUpdates"))
+            .json(&json!({
+                "offset": self.offset,
+                "timeout": timeout_seconds,
+                "allowed_updates": ["message"],
+            }))
+            .timeout(std::time::Duration::from_secs(timeout_seconds + 10))
+            .send()
+            .await
+            .context("Failed to poll Telegram")?
+            .json()
+            .await
+            .context("Invalid Telegram response")?;
+        if response["ok"].as_bool() != Some(true) {
+            bail!("Telegram getUpdates failed: {}", response["description"]);
+        }
+
+        let mut messages = Vec::new();
+        for update in response["result"].as_array().into_iter().flatten() {
+            if let Some(id) = update["update_id"].as_i64() {
+                self.offset = Some(self.offset.map_or(id + 1, |offset| offset.max(id + 1)));
+            }
+            let message = &update["message"];
+            if let (Some(chat_id), Some(text)) =
+                (message["chat"]["id"].as_i64(), message["text"].as_str())
+            {
+                messages.push(BotMessage { chat_id: chat_id.to_string(), text: text.to_string() });
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Send a message to a chat, cut to the length Telegram accepts
+    pub async fn reply(&self, chat_id: &str, text: &str) -> Result<()> {
+        let text = match text.char_indices().nth(MAX_MESSAGE_LEN - 1) {
+            Some((cut, _)) => format!("{}…", &text[..cut]),
+            None => text.to_string(),
+        };
+        let response = self
+            .client
+            .post(self.url("sendMessage"))
+            .json(&json!({ "chat_id": chat_id, "text": text }))
+            .send()
+            .await
+            .context("Failed to send Telegram message")?;
+        if !response.status().is_success() {
+            bail!("Telegram sendMessage returned {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+/// A decimal figure from an API response, rounded for chat
+fn figure(value: &Value) -> String {
+    let parsed = match value {
+        Value::String(s) => s.parse::<Decimal>().ok(),
+        Value::Number(n) => n.to_string().parse::<Decimal>().ok(),
+        _ => None,
+    };
+    match parsed {
+        Some(d) => d.round_dp(4).normalize().to_string(),
+        None => "n/a".to_string(),
+    }
+}
+
+/// Reply to `/portfolio` from `GET /portfolio/{owner}/totals`
+pub fn format_portfolio(totals: &Value) -> String {
+    let sums = &totals["totals"];
+    let currency = sums["currency"].as_str().unwrap_or_default().to_uppercase();
+    let mut text = format!(
+        "Portfolio of {}\nValue: {} {}\nFees: {} {}\nIL: {} {}",
+        totals["owner"].as_str().unwrap_or_default(),
+        figure(&sums["value"]),
+        currency,
+        figure(&sums["fees"]),
+        currency,
+        figure(&sums["impermanent_loss"]),
+        currency,
+    );
+    if let Some(unpriced) = sums["unpriced_positions"].as_u64().filter(|n| *n > 0) {
+        let _ = write!(text, "\n{} positions without a price are left out", unpriced);
+    }
+    let positions = totals["positions"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if !positions.is_empty() {
+        text.push('\n');
+    }
+    for position in positions {
+        let _ = write!(text, "\n#{}: ", position["nft_id"].as_str().unwrap_or_default());
+        match position["missing_price"].as_str() {
+            Some(token) => {
+                let _ = write!(text, "no price for {}", token);
+            }
+            None => {
+                let _ = write!(
+                    text,
+                    "{} {} (fees {})",
+                    figure(&position["value"]),
+                    currency,
+                    figure(&position["fees"])
+                );
+            }
+        }
+    }
+    text
+}
+
+/// Reply to `/position` from `GET /positions/{owner}/{nft_id}`
+pub fn format_position(position: &Value) -> String {
+    let pnl = &position["pnl"];
+    let range =
+        if position["in_range"].as_bool() == Some(true) { "in range" } else { "out of range" };
+    format!(
+        "Position #{} ({})\nPool: {}\nRange: {} - {}, {} at tick {}\n\
+         Fees: {}\nIL: {}\nGas: {}\nNet P&L: {}",
+        position["nft_id"].as_str().unwrap_or_default(),
+        position["lifecycle"].as_str().unwrap_or_default(),
+        position["pool_id"].as_str().unwrap_or_default(),
+        figure(&position["price_lower"]),
+        figure(&position["price_upper"]),
+        range,
+        position["current_tick"],
+        figure(&pnl["fees_earned"]),
+        figure(&pnl["impermanent_loss"]),
+        figure(&pnl["gas_spent"]),
+        figure(&pnl["net_pnl"]),
+    )
+}
+
+/// One line of the reply to `/health`, for a position from `GET /portfolio/{owner}/health`
+pub fn format_health(health: &Value) -> String {
+    format!(
+        "#{}: {} - {}",
+        health["nft_id"].as_str().unwrap_or_default(),
+        health["status"].as_str().unwrap_or("unknown"),
+        health["details"].as_str().unwrap_or_default()
+    )
+}
@@ -0,0 +1,216 @@
+mod bot;
+mod pools;
+mod retry;
+mod rules;
+mod sinks;
+mod templates;
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use reqwest::Client;
+use sqlx::PgPool;
+use stillwater_db::{
+    claim_due_alerts, enqueue_alert, mark_alert_delivered, mark_alert_failed, mark_alert_retry,
+};
+use stillwater_models::{Alert, PendingAlert};
+use tracing::{info, warn};
+
+pub use bot::{
+    BOT_HELP, BotCommand, BotMessage, MAX_MESSAGE_LEN, TelegramBot, format_health,
+    format_portfolio, format_position,
+};
+pub use pools::notify_new_pool;
+pub use retry::RetryPolicy;
+pub use rules::{AlertRules, RuleTest, TestDelivery, validate_rule};
+pub use sinks::{AlertSink, DeliveryError, validate_sink_name};
+pub use templates::{
+    AlertVariables, MAX_RENDERED_LEN, MAX_TEMPLATE_LEN, apply_alert_template, format_pool_param,
+    pool_name, render_alert, template_context, test_alert, validate_template,
+};
+
+/// How long a queued alert stays claimed by the worker delivering it
+///
+/// Longer than a delivery can take (see `DELIVERY_TIMEOUT`), so a lease only
+/// expires when its worker died mid-delivery.
+const CLAIM_LEASE: Duration = Duration::minutes(5);
+
+/// Timeout for a single delivery request
+const DELIVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Delivers alerts to configured sinks through the `pending_alerts` queue
+///
+/// Every alert is queued once per sink before delivery is attempted, so a
+/// crash or sink failure leaves a row to retry, and a sink that already
+/// received the alert is never notified again. Rows are claimed while a
+/// delivery is in flight, so the immediate attempt and `retry_pending` (or two
+/// retry workers) never send the same alert concurrently.
+pub struct AlertDispatcher {
+    client: Client,
+    sinks: Vec<AlertSink>,
+    /// Bot token for Telegram sinks named by alert rules
+    telegram_bot_token: Option<String>,
+    policy: RetryPolicy,
+}
+
+impl AlertDispatcher {
+    /// Create a dispatcher for the given sinks
+    pub fn new(sinks: Vec<AlertSink>) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .unwrap_or_else(|_| Client::new()),
+            sinks,
+            telegram_bot_token: None,
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Create dispatcher from environment variables
+    ///
+    /// - `TELEGRAM_BOT_TOKEN` + `TELEGRAM_CHAT_ID`: Telegram sink (the token
+    ///   alone lets alert rules name Telegram chats)
+    /// - `ALERT_WEBHOOK_URL`: webhook sink
+    pub fn from_env() -> Self {
+        let mut sinks = Vec::new();
+        let telegram_bot_token = std::env::var("TELEGRAM_BOT_TOKEN").ok();
+
+        if let (Some(bot_token), Ok(chat_id)) =
+            (&telegram_bot_token, std::env::var("TELEGRAM_CHAT_ID"))
+        {
+            sinks.push(AlertSink::Telegram { bot_token: bot_token.clone(), chat_id });
+        }
+
+        if let Ok(url) = std::env::var("ALERT_WEBHOOK_URL") {
+            sinks.push(AlertSink::Webhook { url });
+        }
+
+        Self { telegram_bot_token, ..Self::new(sinks) }
+    }
+
+    /// Override the retry policy
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Configured sinks
+    pub fn sinks(&self) -> &[AlertSink] {
+        &self.sinks
+    }
+
+    /// A configured sink by spec, or one an alert rule names
+    fn sink_named(&self, spec: &str) -> Result<AlertSink, String> {
+        match self.sinks.iter().find(|s| s.spec() == spec) {
+            Some(sink) => Ok(sink.clone()),
+            None => AlertSink::from_spec(spec, self.telegram_bot_token.as_deref()),
+        }
+    }
+
+    /// Idempotency key for an alert on a given sink
+    pub fn idempotency_key(sink: &AlertSink, alert: &Alert) -> String {
+        format!("{}|{}", sink.name(), alert.key)
+    }
+
+    /// Queue an alert for every configured sink and attempt immediate delivery
+    ///
+    /// Sinks that already have this alert queued (or delivered) are skipped.
+    pub async fn dispatch(&self, db_pool: &PgPool, alert: &Alert) -> Result<()> {
+        self.dispatch_to(db_pool, alert, &self.sinks).await
+    }
+
+    /// Queue an alert for the given sinks and attempt immediate delivery
+    pub async fn dispatch_to(
+        &self,
+        db_pool: &PgPool,
+        alert: &Alert,
+        sinks: &[AlertSink],
+    ) -> Result<()> {
+        for sink in sinks {
+            let key = Self::idempotency_key(sink, alert);
+            let claimed_until = Utc::now() + CLAIM_LEASE;
+            match enqueue_alert(db_pool, &key, &sink.name(), &sink.spec(), alert, claimed_until)
+                .await?
+            {
+                Some(pending) => {
+                    self.attempt(db_pool, sink, &pending).await?;
+                }
+                None => info!("Alert {} already queued for {}, skipping", alert.key, sink.name()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Claim and retry all due pending alerts, returning the number delivered
+    ///
+    /// An alert whose sink can't be resolved any more fails permanently. One
+    /// that can't be recorded is logged and left to its lease expiring, so
+    /// the rest of the batch still goes out.
+    pub async fn retry_pending(&self, db_pool: &PgPool) -> Result<usize> {
+        let now = Utc::now();
+        let due = claim_due_alerts(db_pool, now, now + CLAIM_LEASE, 100).await?;
+        let mut delivered = 0;
+
+        for pending in due {
+            // Rows queued before `target` existed kept the spec in `sink`
+            let spec = pending.target.as_deref().unwrap_or(&pending.sink);
+            let sink = match self.sink_named(spec) {
+                Ok(sink) => sink,
+                Err(e) => {
+                    warn!("No sink {} for alert {}, failing it: {}", pending.sink, pending.id, e);
+                    let error = format!("Sink no longer resolves: {}", e);
+                    if let Err(e) = mark_alert_failed(db_pool, pending.id, &error).await {
+                        warn!("Could not mark alert {} failed: {:#}", pending.id, e);
+                    }
+                    continue;
+                }
+            };
+
+            match self.attempt(db_pool, &sink, &pending).await {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Could not record delivery of alert {}: {:#}", pending.id, e),
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Attempt delivery of a queued alert and record the outcome
+    async fn attempt(
+        &self,
+        db_pool: &PgPool,
+        sink: &AlertSink,
+        pending: &PendingAlert,
+    ) -> Result<bool> {
+        match sink.deliver(&self.client, &pending.alert, &pending.idempotency_key).await {
+            Ok(()) => {
+                mark_alert_delivered(db_pool, pending.id)
+                    .await
+                    .context("Alert delivered but could not be marked")?;
+                info!("Delivered alert {} to {}", pending.alert.key, sink.name());
+                Ok(true)
+            }
+            Err(DeliveryError::Retryable { message, retry_after })
+                if self.policy.should_retry(pending.attempts + 1) =>
+            {
+                let next = Utc::now() + self.policy.next_delay(pending.attempts, retry_after);
+                warn!(
+                    "Alert {} to {} failed, retrying at {}: {}",
+                    pending.id,
+                    sink.name(),
+                    next,
+                    message
+                );
+                mark_alert_retry(db_pool, pending.id, next, &message).await?;
+                Ok(false)
+            }
+            Err(e) => {
+                warn!("Alert {} to {} failed permanently: {}", pending.id, sink.name(), e);
+                mark_alert_failed(db_pool, pending.id, &e.to_string()).await?;
+                Ok(false)
+            }
+        }
+    }
+}
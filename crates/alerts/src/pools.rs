@@ -0,0 +1,91 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use stillwater_analytics::{PriceDisplay, tick_to_price};
+use stillwater_db::{get_pair_watchers, get_token_watchers};
+use stillwater_models::{Alert, AlertSeverity, Pool, PoolInitialization};
+use tracing::info;
+
+use crate::AlertDispatcher;
+use crate::sinks::AlertSink;
+use crate::templates::pool_name;
+
+/// Significant figures of the starting price in new pool alerts
+const PRICE_SIGNIFICANT_FIGURES: u32 = 6;
+
+/// Tell owners watching either of a new pool's tokens, or its pair, that it exists
+///
+/// Pair watches apply when they cover `chain_id`, the chain the pool was
+/// created on. Each watcher gets one alert through the sinks of their watches
+/// (the configured sinks when one names none). Returns how many owners were told.
+pub async fn notify_new_pool(
+    db_pool: &PgPool,
+    dispatcher: &AlertDispatcher,
+    pool: &Pool,
+    initialization: &PoolInitialization,
+    chain_id: u64,
+) -> Result<usize> {
+    let tokens = [pool.token0.clone(), pool.token1.clone()];
+    let token_watchers = get_token_watchers(db_pool, &tokens).await?;
+    let pair_watchers =
+        get_pair_watchers(db_pool, &pool.token0, &pool.token1, chain_id as i64).await?;
+    let watches: Vec<(&str, &[String])> = token_watchers
+        .iter()
+        .map(|w| (w.owner.as_str(), w.sinks.as_slice()))
+        .chain(pair_watchers.iter().map(|w| (w.owner.as_str(), w.sinks.as_slice())))
+        .collect();
+
+    // An owner with several matching watches is told once, through all their sinks
+    let mut owners: Vec<&str> = watches.iter().map(|(owner, _)| *owner).collect();
+    owners.sort_unstable();
+    owners.dedup();
+    for owner in &owners {
+        let mut sinks = Vec::new();
+        for (_, names) in watches.iter().filter(|(o, _)| o == owner) {
+            for sink in dispatcher.sinks_named(names) {
+                if !sinks.iter().any(|s: &AlertSink| s.name() == sink.name()) {
+                    sinks.push(sink);
+                }
+            }
+        }
+        let alert = new_pool_alert(owner, pool, initialization, chain_id);
+        info!("{} for {}: {}", alert.title, owner, alert.message);
+        dispatcher.dispatch_to(db_pool, &alert, &sinks).await?;
+    }
+    Ok(owners.len())
+}
+
+fn new_pool_alert(
+    owner: &str,
+    pool: &Pool,
+    initialization: &PoolInitialization,
+    chain_id: u64,
+) -> Alert {
+    let price = PriceDisplay::for_pool(pool, "token1")
+        .map(|display| display.to_display(tick_to_price(initialization.tick)))
+        .and_then(|price| price.round_sf(PRICE_SIGNIFICANT_FIGURES))
+        .map_or_else(|| "unknown".to_string(), |price| price.normalize().to_string());
+    let hooks =
+        if pool.has_hooks() { format!("hooks {}", pool.hooks) } else { "no hooks".to_string() };
+
+    Alert {
+        key: format!("pool_created:{}:{}", pool.pool_id, owner),
+        severity: AlertSeverity::Info,
+        title: "New pool".to_string(),
+        message: format!(
+            "Pool {} ({}) was created on chain {} at block {}, starting at {} token1 per token0 \
+             (tick {}), tick spacing {}, {}",
+            pool_name(pool),
+            pool.pool_id,
+            chain_id,
+            initialization.block_number,
+            price,
+            initialization.tick,
+            pool.tick_spacing,
+            hooks
+        ),
+        position_id: None,
+        owner: Some(owner.to_string()),
+        pool_id: Some(pool.pool_id.clone()),
+        created_at: initialization.created_at,
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::Duration;
+
+/// Exponential backoff policy for failed alert deliveries
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound on any single delay
+    pub max_delay: Duration,
+    /// Attempts after which an alert is marked failed
+    pub max_attempts: i32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { base_delay: Duration::seconds(30), max_delay: Duration::hours(1), max_attempts: 8 }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the next attempt, given attempts made so far
+    ///
+    /// A sink-provided `retry_after` (e.g. Telegram 429) is honoured when it
+    /// is longer than the computed backoff.
+    pub fn next_delay(&self, attempts: i32, retry_after: Option<Duration>) -> Duration {
+        let exponent = attempts.clamp(0, 20) as u32;
+        let backoff = self
+            .base_delay
+            .checked_mul(2i32.saturating_pow(exponent))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        match retry_after {
+            Some(after) if after > backoff => after,
+            _ => backoff,
+        }
+    }
+
+    /// Whether another attempt is allowed after `attempts` failures
+    pub fn should_retry(&self, attempts: i32) -> bool {
+        attempts < self.max_attempts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_from_base_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.next_delay(0, None), Duration::seconds(30));
+        assert_eq!(policy.next_delay(1, None), Duration::seconds(60));
+        assert_eq!(policy.next_delay(3, None), Duration::seconds(240));
+    }
+
+    #[test]
+    fn test_backoff_is_capped_at_max_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.next_delay(7, None), Duration::hours(1));
+        // Exponents past the clamp and i32 overflow still cap instead of panicking
+        assert_eq!(policy.next_delay(50, None), Duration::hours(1));
+        assert_eq!(policy.next_delay(i32::MAX, None), Duration::hours(1));
+    }
+
+    #[test]
+    fn test_negative_attempts_use_base_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.next_delay(-1, None), Duration::seconds(30));
+    }
+
+    #[test]
+    fn test_longer_retry_after_wins() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.next_delay(0, Some(Duration::seconds(90))), Duration::seconds(90));
+        // A retry_after above max_delay is honoured: the sink asked for it
+        assert_eq!(policy.next_delay(0, Some(Duration::hours(2))), Duration::hours(2));
+    }
+
+    #[test]
+    fn test_shorter_retry_after_is_ignored() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.next_delay(2, Some(Duration::seconds(5))), Duration::seconds(120));
+    }
+
+    #[test]
+    fn test_should_retry_stops_at_max_attempts() {
+        let policy = RetryPolicy { max_attempts: 3, ..RetryPolicy::default() };
+        assert!(policy.should_retry(0));
+        assert!(policy.should_retry(2));
+        assert!(!policy.should_retry(3));
+        assert!(!policy.should_retry(4));
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::PgPool;
+use stillwater_db::{claim_alert_rule_firing, get_alert_rules};
+use stillwater_models::{Alert, AlertKind, AlertRule, AlertRuleSpec, Position};
+use tracing::{debug, warn};
+
+use crate::AlertDispatcher;
+use crate::sinks::{AlertSink, validate_sink_name};
+use crate::templates::test_alert;
+
+/// Check an alert rule before it's saved: its fields and every sink name
+pub fn validate_rule(spec: &AlertRuleSpec) -> Result<(), String> {
+    spec.validate()?;
+    spec.sinks.iter().try_for_each(|sink| validate_sink_name(sink))
+}
+
+/// The enabled alert rules, as loaded at one point in time
+///
+/// Long-running jobs reload them periodically so edits apply without a restart.
+#[derive(Debug, Clone, Default)]
+pub struct AlertRules {
+    rules: Vec<AlertRule>,
+}
+
+impl AlertRules {
+    /// Load the enabled rules
+    pub async fn load(db_pool: &PgPool) -> Result<Self> {
+        let rules = get_alert_rules(db_pool, None).await?;
+        Ok(Self { rules: rules.into_iter().filter(|r| r.spec.enabled).collect() })
+    }
+
+    /// Whether any rule has this condition
+    pub fn has(&self, kind: AlertKind) -> bool {
+        self.rules.iter().any(|r| r.spec.condition == kind)
+    }
+
+    /// Rules with this condition that cover the position
+    pub fn matching<'a>(
+        &'a self,
+        kind: AlertKind,
+        position: &'a Position,
+    ) -> impl Iterator<Item = &'a AlertRule> + 'a {
+        self.rules.iter().filter(move |r| r.spec.condition == kind && r.spec.matches(position))
+    }
+}
+
+/// A synthetic alert sent (or, in a dry run, not sent) through a rule's sinks
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTest {
+    pub rule_id: i64,
+    pub dry_run: bool,
+    pub alert: Alert,
+    pub deliveries: Vec<TestDelivery>,
+}
+
+/// Outcome of a test alert at one sink
+#[derive(Debug, Clone, Serialize)]
+pub struct TestDelivery {
+    pub sink: String,
+    /// Always false in a dry run
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+impl AlertDispatcher {
+    /// Sinks a rule sends to: its own, or the configured ones when it names none
+    pub fn sinks_for(&self, rule: &AlertRule) -> Vec<AlertSink> {
+        self.sinks_named(&rule.spec.sinks)
+    }
+
+    /// Sinks by name, or the configured ones for no names; unusable names are skipped
+    pub fn sinks_named(&self, names: &[String]) -> Vec<AlertSink> {
+        if names.is_empty() {
+            return self.sinks().to_vec();
+        }
+        names
+            .iter()
+            .filter_map(|name| match self.sink_named(name) {
+                Ok(sink) => Some(sink),
+                Err(e) => {
+                    warn!("Skipping alert sink {}: {}", name, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Send a synthetic alert for a rule's condition straight to its sinks
+    ///
+    /// Rendered like a real alert (in the rule owner's template, if any) from
+    /// sample values. Delivery bypasses the queue, cooldowns and retries so each
+    /// sink's outcome is known at once; a dry run only renders the alert and
+    /// resolves the sinks.
+    pub async fn test_rule(
+        &self,
+        db_pool: &PgPool,
+        rule: &AlertRule,
+        dry_run: bool,
+    ) -> Result<RuleTest> {
+        let alert =
+            test_alert(db_pool, rule.spec.condition, rule.spec.owner.as_deref(), Utc::now())
+                .await?;
+        // Unlike `sinks_for`, report unusable sink names instead of skipping them
+        let sinks: Vec<(String, Result<AlertSink, String>)> = if rule.spec.sinks.is_empty() {
+            self.sinks().iter().map(|sink| (sink.name(), Ok(sink.clone()))).collect()
+        } else {
+            rule.spec.sinks.iter().map(|name| (name.clone(), self.sink_named(name))).collect()
+        };
+
+        let mut deliveries = Vec::new();
+        for (name, sink) in sinks {
+            let outcome = match sink {
+                Ok(_) if dry_run => Ok(()),
+                Ok(sink) => {
+                    let key = Self::idempotency_key(&sink, &alert);
+                    sink.deliver(&self.client, &alert, &key).await.map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e),
+            };
+            deliveries.push(TestDelivery {
+                sink: name,
+                delivered: !dry_run && outcome.is_ok(),
+                error: outcome.err(),
+            });
+        }
+        Ok(RuleTest { rule_id: rule.id, dry_run, alert, deliveries })
+    }
+
+    /// Send an alert about a position through a rule, unless the rule is cooling down
+    ///
+    /// Returns whether the rule fired. Alerts sent through several rules reach
+    /// each sink once, as delivery is idempotent per sink and alert key.
+    pub async fn dispatch_for_rule(
+        &self,
+        db_pool: &PgPool,
+        rule: &AlertRule,
+        position_id: i64,
+        alert: &Alert,
+    ) -> Result<bool> {
+        if !claim_alert_rule_firing(db_pool, rule, position_id, Utc::now()).await? {
+            debug!("Alert rule {} cooling down for position {}", rule.id, position_id);
+            return Ok(false);
+        }
+        self.dispatch_to(db_pool, alert, &self.sinks_for(rule)).await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,207 @@
+use alloy::primitives::keccak256;
+use chrono::Duration;
+use reqwest::{Client, StatusCode};
+use serde_json::json;
+use std::fmt;
+use stillwater_models::Alert;
+
+/// Destination an alert can be delivered to
+#[derive(Debug, Clone)]
+pub enum AlertSink {
+    /// Telegram bot message to a chat
+    Telegram { bot_token: String, chat_id: String },
+    /// JSON POST to an arbitrary HTTP endpoint
+    Webhook { url: String },
+}
+
+/// Outcome of a failed delivery attempt
+#[derive(Debug, Clone)]
+pub enum DeliveryError {
+    /// Transient failure (rate limit, 5xx, network); retry later
+    Retryable { message: String, retry_after: Option<Duration> },
+    /// Failure that will not succeed on retry (bad token, 4xx)
+    Permanent(String),
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::Retryable { message, .. } => write!(f, "retryable: {}", message),
+            DeliveryError::Permanent(message) => write!(f, "permanent: {}", message),
+        }
+    }
+}
+
+impl AlertSink {
+    /// Stable identifier for this sink, used in idempotency keys, the queue and logs
+    ///
+    /// Never includes secrets: webhooks are identified by a hash of their URL,
+    /// which may carry a token, and Telegram sinks leave out the bot token.
+    pub fn name(&self) -> String {
+        match self {
+            AlertSink::Telegram { chat_id, .. } => format!("telegram:{}", chat_id),
+            AlertSink::Webhook { url } => {
+                format!("webhook:{}", alloy::hex::encode(&keccak256(url.as_bytes())[..8]))
+            }
+        }
+    }
+
+    /// The name alert rules use for this sink (`telegram:<chat_id>` or `webhook:<url>`)
+    ///
+    /// Unlike `name`, this includes the webhook URL, so it can rebuild the sink.
+    pub fn spec(&self) -> String {
+        match self {
+            AlertSink::Telegram { chat_id, .. } => format!("telegram:{}", chat_id),
+            AlertSink::Webhook { url } => format!("webhook:{}", url),
+        }
+    }
+
+    /// Rebuild a sink from its spec (`telegram:<chat_id>` or `webhook:<url>`)
+    ///
+    /// Telegram sinks need the bot token, which specs leave out.
+    pub fn from_spec(spec: &str, telegram_bot_token: Option<&str>) -> Result<Self, String> {
+        validate_sink_name(spec)?;
+        match spec.split_once(':') {
+            Some(("telegram", chat_id)) => {
+                let bot_token =
+                    telegram_bot_token.ok_or("TELEGRAM_BOT_TOKEN is not set for telegram sinks")?;
+                Ok(AlertSink::Telegram {
+                    bot_token: bot_token.to_string(),
+                    chat_id: chat_id.to_string(),
+                })
+            }
+            Some(("webhook", url)) => Ok(AlertSink::Webhook { url: url.to_string() }),
+            _ => unreachable!("validated above"),
+        }
+    }
+
+    /// Deliver an alert once, classifying any failure as retryable or permanent
+    pub async fn deliver(
+        &self,
+        client: &Client,
+        alert: &Alert,
+        idempotency_key: &str,
+    ) -> Result<(), DeliveryError> {
+        let request = match self {
+            AlertSink::Telegram { bot_token, chat_id } => client
+                .post(format!("https://api.telegram.org/bot{}/sendMessage", bot_token))
+                .json(&json!({
+                    "chat_id": chat_id,
+                    "text": format!("{}\n\n{}", alert.title, alert.message),
+                })),
+            AlertSink::Webhook { url } => {
+                client.post(url).header("Idempotency-Key", idempotency_key).json(alert)
+            }
+        };
+
+        let response = request.send().await.map_err(|e| DeliveryError::Retryable {
+            // The URL can hold the bot token or a webhook secret
+            message: format!("request failed: {}", e.without_url()),
+            retry_after: None,
+        })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let header_retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<i64>().ok())
+            .map(Duration::seconds);
+        let body = response.text().await.unwrap_or_default();
+
+        // Telegram reports its backoff in the body rather than a header
+        let retry_after = header_retry_after.or_else(|| {
+            serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v["parameters"]["retry_after"].as_i64())
+                .map(Duration::seconds)
+        });
+
+        let message = format!("{} returned {}: {}", self.name(), status, body);
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status.is_server_error()
+        {
+            Err(DeliveryError::Retryable { message, retry_after })
+        } else {
+            Err(DeliveryError::Permanent(message))
+        }
+    }
+}
+
+/// Check a sink name is `telegram:<chat_id>` or `webhook:<http(s) url>`
+pub fn validate_sink_name(name: &str) -> Result<(), String> {
+    match name.split_once(':') {
+        Some(("telegram", chat_id)) if !chat_id.trim().is_empty() => Ok(()),
+        Some(("webhook", url)) if url.starts_with("https://") || url.starts_with("http://") => {
+            Ok(())
+        }
+        _ => Err(format!(
+            "Invalid sink {:?}, expected telegram:<chat_id> or webhook:<http(s) url>",
+            name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_name_hides_url() {
+        let sink = AlertSink::Webhook { url: "https://example.com/hook?token=secret".to_string() };
+        let name = sink.name();
+        assert!(name.starts_with("webhook:"));
+        assert!(!name.contains("secret"));
+        assert_eq!(name.len(), "webhook:".len() + 16);
+    }
+
+    #[test]
+    fn test_webhook_name_is_stable_and_fits_queue() {
+        let long_url = format!("https://example.com/{}", "a".repeat(1000));
+        let a = AlertSink::Webhook { url: long_url.clone() };
+        let b = AlertSink::Webhook { url: long_url };
+        let other = AlertSink::Webhook { url: "https://example.com/other".to_string() };
+        assert_eq!(a.name(), b.name());
+        assert_ne!(a.name(), other.name());
+        assert!(a.name().len() < 255);
+    }
+
+    #[test]
+    fn test_telegram_name_leaves_out_bot_token() {
+        let sink =
+            AlertSink::Telegram { bot_token: "123:secret".to_string(), chat_id: "42".to_string() };
+        assert_eq!(sink.name(), "telegram:42");
+        assert_eq!(sink.spec(), "telegram:42");
+    }
+
+    #[test]
+    fn test_spec_round_trips() {
+        let spec = "webhook:https://example.com/hook?token=secret";
+        let sink = AlertSink::from_spec(spec, None).unwrap();
+        assert_eq!(sink.spec(), spec);
+
+        let sink = AlertSink::from_spec("telegram:42", Some("123:secret")).unwrap();
+        assert!(
+            matches!(sink, AlertSink::Telegram { ref bot_token, .. } if bot_token == "123:secret")
+        );
+    }
+
+    #[test]
+    fn test_telegram_spec_needs_bot_token() {
+        assert!(AlertSink::from_spec("telegram:42", None).is_err());
+    }
+
+    #[test]
+    fn test_validate_sink_name() {
+        assert!(validate_sink_name("telegram:42").is_ok());
+        assert!(validate_sink_name("webhook:https://example.com").is_ok());
+        assert!(validate_sink_name("telegram: ").is_err());
+        assert!(validate_sink_name("webhook:ftp://example.com").is_err());
+        assert!(validate_sink_name("email:someone@example.com").is_err());
+    }
+}
@@ -0,0 +1,543 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use minijinja::value::{Serde, Value};
+use minijinja::{Environment, Error, ErrorKind, UndefinedBehavior};
+use rust_decimal::Decimal;
+use serde_json::{Value as JsonValue, json};
+use sqlx::PgPool;
+use std::io;
+use stillwater_analytics::{
+    CompoundRecommendation, FeeModelRegistry, FeeVelocity, FeeVelocityTrend, PnlHistory,
+    SwapImpact, TickRange, calculate_position_pnl_at, fee_to_rate, recommend_compound,
+};
+use stillwater_db::{
+    get_alert_template, get_gas_expenses_for_position, get_pool_by_id, get_snapshots_for_position,
+    get_swaps_for_pool_between,
+};
+use stillwater_models::{
+    Address, Alert, AlertKind, AlertSeverity, AlertTemplate, Pool, PoolParam, PoolParamChange,
+    Position, PositionPnL,
+};
+use tracing::warn;
+
+/// Longest title or body template accepted, in bytes
+pub const MAX_TEMPLATE_LEN: usize = 4096;
+
+/// Longest rendered title or message, in bytes; Telegram won't send more
+pub const MAX_RENDERED_LEN: usize = 4096;
+
+/// Instructions one render may execute, so a runaway loop can't stall alerting
+const TEMPLATE_FUEL: u64 = 50_000;
+
+/// Variables only some kinds of alert have
+#[derive(Debug, Clone, Copy)]
+pub enum AlertVariables<'a> {
+    /// A range crossing, seen at `tick` in `block`
+    Range { tick: i32, block: u64 },
+    /// A compound recommendation
+    Compound(&'a CompoundRecommendation),
+    /// A position's fee velocity against its baseline
+    FeeVelocity(&'a FeeVelocityTrend),
+    /// A large swap in the position's pool
+    LargeSwap(&'a SwapImpact),
+    /// A change to the position's pool's fees
+    PoolParamChange(&'a PoolParamChange),
+}
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    // Typos fail validation instead of rendering as blanks
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_fuel(Some(TEMPLATE_FUEL));
+    env
+}
+
+/// Render output that fails once it grows past `MAX_RENDERED_LEN`
+///
+/// Fuel bounds the instructions a template runs, not what they print, so a
+/// short template (e.g. `{{ 'a' * 100000000 }}`) could otherwise render megabytes.
+#[derive(Default)]
+struct CappedOutput {
+    buf: Vec<u8>,
+    overflowed: bool,
+}
+
+impl io::Write for CappedOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + data.len() > MAX_RENDERED_LEN {
+            self.overflowed = true;
+            return Err(io::Error::other("rendered alert is too long"));
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Render one template, failing if the output passes `MAX_RENDERED_LEN`
+fn render_capped(env: &Environment, source: &str, context: Value) -> Result<String, Error> {
+    let mut output = CappedOutput::default();
+    let result = env.template_from_str(source)?.render_captured_to(context, &mut output);
+    if output.overflowed {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("template rendered more than {} bytes", MAX_RENDERED_LEN),
+        ));
+    }
+    result?;
+    String::from_utf8(output.buf)
+        .map_err(|_| Error::new(ErrorKind::InvalidOperation, "template rendered invalid UTF-8"))
+}
+
+/// Short form of an address for display (0x1234…abcd)
+fn short_address(address: &str) -> String {
+    match (address.get(..6), address.get(address.len().saturating_sub(4)..)) {
+        (Some(head), Some(tail)) if address.len() > 10 => format!("{}…{}", head, tail),
+        _ => address.to_string(),
+    }
+}
+
+/// Human-readable pool name, e.g. "0x1234…abcd/0x5678…ef01 0.3%"
+pub fn pool_name(pool: &Pool) -> String {
+    let fee = if pool.is_dynamic_fee() {
+        "dynamic".to_string()
+    } else {
+        format!("{}%", (fee_to_rate(pool.fee_tier) * Decimal::from(100)).normalize())
+    };
+    format!("{}/{} {}", short_address(&pool.token0), short_address(&pool.token1), fee)
+}
+
+/// A pool fee as a percentage, e.g. "0.3%"; each direction's for the packed protocol fee
+pub fn format_pool_param(param: PoolParam, value: i32) -> String {
+    let percent = |fee: i32| format!("{}%", (fee_to_rate(fee) * Decimal::from(100)).normalize());
+    match param {
+        PoolParam::LpFee => percent(value),
+        PoolParam::ProtocolFee => {
+            format!("{} / {}", percent(value & 0xfff), percent((value >> 12) & 0xfff))
+        }
+    }
+}
+
+/// Variables a template for `alert` renders against
+///
+/// Every template gets `alert`, `position` and `pnl`; `pnl` fields and the
+/// pool-derived `position` fields are null when they couldn't be computed.
+/// Range alerts add `tick`, `in_range`, `distance_to_edge` and `block`;
+/// compound alerts add `compound`, fee velocity alerts `fee_velocity`,
+/// large swap alerts `swap` and pool parameter alerts `change`.
+pub fn template_context(
+    alert: &Alert,
+    kind: AlertKind,
+    position: &Position,
+    pool: Option<&Pool>,
+    pnl: Option<&PositionPnL>,
+    variables: AlertVariables,
+) -> JsonValue {
+    let mut context = json!({
+        "alert": {
+            "kind": kind.as_str(),
+            "key": alert.key,
+            "severity": alert.severity,
+            "title": alert.title,
+            "message": alert.message,
+        },
+        "position": {
+            "id": position.id,
+            "nft_id": position.nft_id,
+            "owner": position.owner,
+            "pool_id": position.pool_id,
+            "pool_name": pool.map(pool_name),
+            "token0": pool.map(|p| p.token0.as_str()),
+            "token1": pool.map(|p| p.token1.as_str()),
+            "tick_lower": position.tick_lower,
+            "tick_upper": position.tick_upper,
+            "liquidity": position.liquidity.to_string(),
+            "created_at": position.created_at.to_rfc3339(),
+        },
+        "pnl": {
+            "fees": pnl.map(|p| p.fees_earned),
+            "il": pnl.map(|p| p.impermanent_loss),
+            "gas": pnl.map(|p| p.gas_spent),
+            "net": pnl.map(|p| p.net_pnl),
+        },
+    });
+
+    match variables {
+        AlertVariables::Range { tick, block } => {
+            let range = TickRange::of(position).ok();
+            context["tick"] = json!(tick);
+            context["in_range"] = json!(range.map(|r| r.contains(tick)));
+            context["distance_to_edge"] = json!(range.map(|r| r.distance_to_edge(tick)));
+            context["block"] = json!(block);
+        }
+        AlertVariables::Compound(recommendation) => {
+            context["compound"] = json!(recommendation);
+        }
+        AlertVariables::FeeVelocity(trend) => {
+            context["fee_velocity"] = json!(trend);
+        }
+        AlertVariables::LargeSwap(impact) => {
+            context["swap"] = json!(impact);
+        }
+        AlertVariables::PoolParamChange(change) => {
+            context["change"] = json!(change);
+            context["change"]["old"] = json!(format_pool_param(change.param, change.old_value));
+            context["change"]["new"] = json!(format_pool_param(change.param, change.new_value));
+        }
+    }
+    context
+}
+
+/// Render `template` with `context`, returning the alert with its title and message replaced
+///
+/// The default title is kept when the template has none. A blank message, or a
+/// title or message rendering past `MAX_RENDERED_LEN`, is an error.
+pub fn render_alert(
+    template: &AlertTemplate,
+    alert: &Alert,
+    context: &JsonValue,
+) -> Result<Alert, Error> {
+    let env = environment();
+    let context = Value::from(Serde(context));
+
+    let message = render_capped(&env, &template.body, context.clone())?.trim().to_string();
+    if message.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidOperation, "template rendered an empty message"));
+    }
+    let title = match &template.title {
+        Some(title) => render_capped(&env, title, context)?.trim().to_string(),
+        None => alert.title.clone(),
+    };
+
+    Ok(Alert { title, message, ..alert.clone() })
+}
+
+/// Check a template against placeholder variables for its kind of alert
+///
+/// Returns the rendered sample alert as a preview, or why the template can't
+/// be used (too long, rendering too long, bad syntax, unknown variables).
+pub fn validate_template(
+    kind: AlertKind,
+    title: Option<&str>,
+    body: &str,
+) -> Result<Alert, String> {
+    if body.len() > MAX_TEMPLATE_LEN || title.is_some_and(|t| t.len() > MAX_TEMPLATE_LEN) {
+        return Err(format!("templates must be at most {} bytes", MAX_TEMPLATE_LEN));
+    }
+
+    let template = AlertTemplate {
+        owner: String::new(),
+        kind,
+        title: title.map(str::to_string),
+        body: body.to_string(),
+        updated_at: Utc::now(),
+    };
+    let (alert, context) = sample_context(kind);
+    render_alert(&template, &alert, &context).map_err(|e| e.to_string())
+}
+
+/// Placeholder alert and variables for previewing a kind of alert
+fn sample_context(kind: AlertKind) -> (Alert, JsonValue) {
+    let created_at = DateTime::from_timestamp(1_735_689_600, 0).unwrap_or_default();
+    let position = Position {
+        id: 42,
+        nft_id: "12345".to_string(),
+        owner: Address::repeat_byte(1),
+        pool_id: format!("0x{}", "ab".repeat(32)),
+        tick_lower: -600,
+        tick_upper: 600,
+        liquidity: 1_000_000_000u64.try_into().unwrap_or_default(),
+        created_at,
+        manual: false,
+        closed_at: None,
+        archived_at: None,
+    };
+    let pool = Pool {
+        pool_id: position.pool_id.clone(),
+        token0: "0x4200000000000000000000000000000000000006".to_string(),
+        token1: "0x078d782b760474a361dda0af3839290b0ef57ad6".to_string(),
+        token0_decimals: 18,
+        token1_decimals: 6,
+        fee_tier: 3000,
+        tick_spacing: 60,
+        hooks: "0x0000000000000000000000000000000000000000".to_string(),
+        protocol_fee: 0,
+        created_at: Some(created_at),
+        created_at_block: None,
+        fee_override: None,
+    };
+    let pnl = PositionPnL {
+        fees_earned: Decimal::new(12_50, 2),
+        impermanent_loss: Decimal::new(-4_20, 2),
+        gas_spent: Decimal::new(1_10, 2),
+        net_pnl: Decimal::new(7_20, 2),
+    };
+
+    let (severity, title) = match kind {
+        AlertKind::RangeExited => (AlertSeverity::Critical, "Position out of range"),
+        AlertKind::RangeEntered => (AlertSeverity::Info, "Position back in range"),
+        AlertKind::Compound => (AlertSeverity::Info, "Compound now"),
+        AlertKind::FeeVelocityDrop => (AlertSeverity::Warning, "Fee velocity dropped"),
+        AlertKind::LargeSwap => (AlertSeverity::Warning, "Large swap"),
+        AlertKind::PoolParamChange => (AlertSeverity::Warning, "Pool fee changed"),
+    };
+    let alert = Alert {
+        key: format!("sample:{}", kind.as_str()),
+        severity,
+        title: title.to_string(),
+        message: "Default alert message".to_string(),
+        position_id: Some(position.id),
+        owner: Some(position.owner.canonical()),
+        pool_id: Some(position.pool_id.clone()),
+        created_at,
+    };
+
+    let recommendation = recommend_compound(Decimal::from(30), Decimal::from(2), Decimal::from(5));
+    let velocity = |days: i64, per_day: i64| FeeVelocity {
+        from: created_at + Duration::days(days),
+        to: created_at + Duration::days(days + 1),
+        fees: Decimal::from(per_day),
+        fees_per_day: Decimal::from(per_day),
+    };
+    let trend = FeeVelocityTrend {
+        recent: velocity(8, 4),
+        baseline: Some(velocity(1, 10)),
+        change: Some(Decimal::new(-6, 1)),
+        drop_threshold: Decimal::new(5, 1),
+        dropping: true,
+    };
+    let impact = SwapImpact {
+        pool_id: position.pool_id.clone(),
+        tx_hash: Some(format!("0x{}", "cd".repeat(32))),
+        block_number: 12_345_678,
+        log_index: 3,
+        amount0: Decimal::from(-80),
+        amount1: Decimal::from(250_000),
+        value: Some(Decimal::from(250_000)),
+        tick_before: Some(-196_100),
+        tick_after: -195_850,
+        tick_move: Some(250),
+    };
+    let change = PoolParamChange {
+        id: 1,
+        pool_id: position.pool_id.clone(),
+        param: PoolParam::LpFee,
+        old_value: 3000,
+        new_value: 10_000,
+        detected_at: created_at,
+    };
+    let variables = match kind {
+        AlertKind::RangeExited => AlertVariables::Range { tick: 720, block: 12_345_678 },
+        AlertKind::RangeEntered => AlertVariables::Range { tick: 540, block: 12_345_678 },
+        AlertKind::Compound => AlertVariables::Compound(&recommendation),
+        AlertKind::FeeVelocityDrop => AlertVariables::FeeVelocity(&trend),
+        AlertKind::LargeSwap => AlertVariables::LargeSwap(&impact),
+        AlertKind::PoolParamChange => AlertVariables::PoolParamChange(&change),
+    };
+    let context = template_context(&alert, kind, &position, Some(&pool), Some(&pnl), variables);
+    (alert, context)
+}
+
+/// Rewrite an alert with its owner's template for `kind`, if they've set one
+///
+/// The pool and P&L are only loaded when a template exists. A template that
+/// fails to render (e.g. a variable this alert doesn't have) is logged and the
+/// default text is kept, so the alert still goes out.
+pub async fn apply_alert_template(
+    db_pool: &PgPool,
+    fee_models: &FeeModelRegistry,
+    alert: &mut Alert,
+    kind: AlertKind,
+    position: &Position,
+    variables: AlertVariables<'_>,
+) -> Result<()> {
+    let Some(template) = get_alert_template(db_pool, &position.owner.canonical(), kind).await?
+    else {
+        return Ok(());
+    };
+
+    let pool = get_pool_by_id(db_pool, &position.pool_id).await?;
+    let pnl = match &pool {
+        Some(pool) => current_pnl(db_pool, fee_models, position, pool).await?,
+        None => None,
+    };
+
+    let context = template_context(alert, kind, position, pool.as_ref(), pnl.as_ref(), variables);
+    match render_alert(&template, alert, &context) {
+        Ok(rendered) => *alert = rendered,
+        Err(e) => warn!(
+            "Template {} of {} failed for alert {}, sending the default: {}",
+            kind.as_str(),
+            position.owner,
+            alert.key,
+            e
+        ),
+    }
+    Ok(())
+}
+
+/// A placeholder alert of `kind` for test-firing, in `owner`'s template if they've set one
+///
+/// Rendered from the same sample values template previews use. The title is
+/// marked as a test, and the key is unique to `sent_at` so the alert is never
+/// deduplicated against a real one or an earlier test.
+pub async fn test_alert(
+    db_pool: &PgPool,
+    kind: AlertKind,
+    owner: Option<&str>,
+    sent_at: DateTime<Utc>,
+) -> Result<Alert> {
+    let (mut alert, context) = sample_context(kind);
+    if let Some(owner) = owner
+        && let Some(template) = get_alert_template(db_pool, owner, kind).await?
+    {
+        match render_alert(&template, &alert, &context) {
+            Ok(rendered) => alert = rendered,
+            Err(e) => {
+                warn!("Template {} of {} failed, testing the default: {}", kind.as_str(), owner, e)
+            }
+        }
+    }
+
+    Ok(Alert {
+        key: format!("test:{}:{}", kind.as_str(), sent_at.timestamp_millis()),
+        title: format!("[Test] {}", alert.title),
+        owner: owner.map(str::to_string).or(alert.owner),
+        created_at: sent_at,
+        ..alert
+    })
+}
+
+/// A position's P&L as of now, None without enough history
+async fn current_pnl(
+    db_pool: &PgPool,
+    fee_models: &FeeModelRegistry,
+    position: &Position,
+    pool: &Pool,
+) -> Result<Option<PositionPnL>> {
+    let now = Utc::now();
+    let swaps =
+        get_swaps_for_pool_between(db_pool, &position.pool_id, position.created_at, now).await?;
+    let snapshots =
+        get_snapshots_for_position(db_pool, position.id, position.created_at, now).await?;
+    let gas = get_gas_expenses_for_position(db_pool, position.id, now).await?;
+
+    let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &gas };
+    Ok(calculate_position_pnl_at(position, pool, fee_models.model_for(pool), &history, now)
+        .ok()
+        .flatten())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_template(title: Option<&str>, body: &str) -> AlertTemplate {
+        AlertTemplate {
+            owner: String::new(),
+            kind: AlertKind::RangeExited,
+            title: title.map(str::to_string),
+            body: body.to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_render_alert_fills_variables() {
+        let (alert, context) = sample_context(AlertKind::RangeExited);
+        let template =
+            create_test_template(Some("{{ alert.title }}!"), "Tick {{ tick }} in {{ block }}");
+        let rendered = render_alert(&template, &alert, &context).unwrap();
+        assert_eq!(rendered.title, "Position out of range!");
+        assert_eq!(rendered.message, "Tick 720 in 12345678");
+        assert_eq!(rendered.key, alert.key);
+    }
+
+    #[test]
+    fn test_render_alert_keeps_default_title() {
+        let (alert, context) = sample_context(AlertKind::RangeExited);
+        let rendered = render_alert(&create_test_template(None, "body"), &alert, &context).unwrap();
+        assert_eq!(rendered.title, alert.title);
+    }
+
+    #[test]
+    fn test_render_alert_rejects_blank_message() {
+        let (alert, context) = sample_context(AlertKind::RangeExited);
+        let template = create_test_template(None, "{{ '  ' }}");
+        assert!(render_alert(&template, &alert, &context).is_err());
+    }
+
+    #[test]
+    fn test_render_alert_caps_output() {
+        let (alert, context) = sample_context(AlertKind::RangeExited);
+        let huge = create_test_template(None, "{{ 'a' * 100000000 }}");
+        let err = render_alert(&huge, &alert, &context).unwrap_err();
+        assert!(err.to_string().contains("more than 4096 bytes"), "{}", err);
+
+        let looped =
+            create_test_template(None, "{% for i in range(1000) %}{{ 'abcdefgh' }}{% endfor %}");
+        assert!(render_alert(&looped, &alert, &context).is_err());
+
+        let huge_title = create_test_template(Some("{{ 'a' * 5000 }}"), "body");
+        assert!(render_alert(&huge_title, &alert, &context).is_err());
+    }
+
+    #[test]
+    fn test_render_alert_allows_output_up_to_cap() {
+        let (alert, context) = sample_context(AlertKind::RangeExited);
+        let template = create_test_template(None, "{{ 'a' * 4096 }}");
+        let rendered = render_alert(&template, &alert, &context).unwrap();
+        assert_eq!(rendered.message.len(), MAX_RENDERED_LEN);
+    }
+
+    #[test]
+    fn test_validate_template_previews_every_kind() {
+        for kind in [
+            AlertKind::RangeExited,
+            AlertKind::RangeEntered,
+            AlertKind::Compound,
+            AlertKind::FeeVelocityDrop,
+            AlertKind::LargeSwap,
+            AlertKind::PoolParamChange,
+        ] {
+            let preview =
+                validate_template(kind, Some("{{ alert.title }}"), "{{ position.pool_name }}")
+                    .unwrap();
+            assert_eq!(preview.message, "0x4200…0006/0x078d…7ad6 0.3%");
+        }
+    }
+
+    #[test]
+    fn test_validate_template_rejects_unknown_variables() {
+        assert!(validate_template(AlertKind::RangeExited, None, "{{ positon.id }}").is_err());
+        // Only range alerts have a tick
+        assert!(validate_template(AlertKind::Compound, None, "{{ tick }}").is_err());
+    }
+
+    #[test]
+    fn test_validate_template_rejects_bad_syntax() {
+        assert!(validate_template(AlertKind::RangeExited, None, "{{ position.id ").is_err());
+    }
+
+    #[test]
+    fn test_validate_template_rejects_long_templates() {
+        let long = "a".repeat(MAX_TEMPLATE_LEN + 1);
+        assert!(validate_template(AlertKind::RangeExited, None, &long).is_err());
+        assert!(validate_template(AlertKind::RangeExited, Some(&long), "body").is_err());
+    }
+
+    #[test]
+    fn test_validate_template_rejects_long_output() {
+        let err = validate_template(AlertKind::RangeExited, None, "{{ 'a' * 100000000 }}");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_runaway_loops_run_out_of_fuel() {
+        let body = "{% for i in range(100000) %}{% endfor %}x";
+        assert!(validate_template(AlertKind::RangeExited, None, body).is_err());
+    }
+}
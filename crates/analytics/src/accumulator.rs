@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{FeeAccumulator, Pool, Position, Swap};
+
+use crate::fees::{FeeModel, calculate_fees_earned_with_model};
+use crate::pnl::swap_volume;
+
+/// Relative difference between accumulated and recomputed totals tolerated as rounding
+pub const ACCUMULATOR_TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 9);
+
+/// Fold a pool's newly recorded swaps into a position's running totals
+///
+/// Only swaps of the position's pool with IDs above `last_swap_id` that
+/// happened since the position was created count, matching what a full
+/// recomputation over its history would include. Fees are linear in swaps,
+/// so adding each batch's fees gives the same total as recomputing.
+pub fn advance_accumulator(
+    accumulator: &mut FeeAccumulator,
+    position: &Position,
+    pool: &Pool,
+    swaps: &[Swap],
+    model: &dyn FeeModel,
+    now: DateTime<Utc>,
+) {
+    let new: Vec<Swap> = swaps
+        .iter()
+        .filter(|s| {
+            s.id > accumulator.last_swap_id
+                && s.pool_id == position.pool_id
+                && s.timestamp >= position.created_at
+        })
+        .cloned()
+        .collect();
+    let Some(last_swap_id) = new.iter().map(|s| s.id).max() else {
+        return;
+    };
+
+    accumulator.last_swap_id = last_swap_id;
+    accumulator.swap_count += new.len() as i64;
+    accumulator.volume += new.iter().map(swap_volume).sum::<Decimal>();
+    accumulator.fees_earned += calculate_fees_earned_with_model(position, pool, &new, model);
+    accumulator.updated_at = now;
+}
+
+/// Where an accumulator disagrees with a full recomputation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccumulatorDrift {
+    pub position_id: i64,
+    /// Totals recomputed from the full swap history up to the accumulator's last swap
+    pub expected: FeeAccumulator,
+    pub swap_count: i64,
+    pub volume: Decimal,
+    pub fees_earned: Decimal,
+}
+
+/// Recompute an accumulator from a position's full swap history and compare
+///
+/// `swaps` should hold every swap of the pool since the position was created;
+/// swaps after the accumulator's `last_swap_id` are ignored. Returns None
+/// when the totals agree (volume and fees within `ACCUMULATOR_TOLERANCE`).
+pub fn check_accumulator(
+    accumulator: &FeeAccumulator,
+    position: &Position,
+    pool: &Pool,
+    swaps: &[Swap],
+    model: &dyn FeeModel,
+) -> Option<AccumulatorDrift> {
+    let history: Vec<Swap> =
+        swaps.iter().filter(|s| s.id <= accumulator.last_swap_id).cloned().collect();
+    let mut expected = FeeAccumulator::empty(position.id);
+    advance_accumulator(&mut expected, position, pool, &history, model, accumulator.updated_at);
+    expected.last_swap_id = accumulator.last_swap_id;
+    expected.verified_at = accumulator.verified_at;
+
+    let agrees = expected.swap_count == accumulator.swap_count
+        && within_tolerance(accumulator.volume, expected.volume)
+        && within_tolerance(accumulator.fees_earned, expected.fees_earned);
+    if agrees {
+        return None;
+    }
+
+    Some(AccumulatorDrift {
+        position_id: position.id,
+        swap_count: accumulator.swap_count,
+        volume: accumulator.volume,
+        fees_earned: accumulator.fees_earned,
+        expected,
+    })
+}
+
+fn within_tolerance(actual: Decimal, expected: Decimal) -> bool {
+    let scale = expected.abs().max(Decimal::ONE);
+    (actual - expected).abs() <= scale * ACCUMULATOR_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::StaticFeeModel;
+    use crate::testing::{self, create_test_pool};
+    use chrono::Duration;
+
+    fn create_test_position(created_at: DateTime<Utc>) -> Position {
+        Position { created_at, ..testing::create_test_position() }
+    }
+
+    fn create_test_swap(id: i64, timestamp: DateTime<Utc>) -> Swap {
+        testing::create_test_swap(id, 1_000_000, -1_000_000, timestamp)
+    }
+
+    #[test]
+    fn test_incremental_matches_full_recomputation() {
+        let now = Utc::now();
+        let position = create_test_position(now - Duration::days(2));
+        let pool = create_test_pool();
+        let swaps: Vec<Swap> =
+            (1..=6).map(|id| create_test_swap(id, now - Duration::hours(id))).collect();
+
+        let mut incremental = FeeAccumulator::empty(1);
+        advance_accumulator(&mut incremental, &position, &pool, &swaps[..2], &StaticFeeModel, now);
+        advance_accumulator(&mut incremental, &position, &pool, &swaps[..4], &StaticFeeModel, now);
+        advance_accumulator(&mut incremental, &position, &pool, &swaps, &StaticFeeModel, now);
+
+        assert_eq!(incremental.swap_count, 6);
+        assert_eq!(incremental.last_swap_id, 6);
+        let full = calculate_fees_earned_with_model(&position, &pool, &swaps, &StaticFeeModel);
+        assert_eq!(incremental.fees_earned, full);
+        assert!(
+            check_accumulator(&incremental, &position, &pool, &swaps, &StaticFeeModel).is_none()
+        );
+    }
+
+    #[test]
+    fn test_late_swap_counted_and_pre_position_swaps_skipped() {
+        let now = Utc::now();
+        let position = create_test_position(now - Duration::hours(10));
+        let pool = create_test_pool();
+
+        let mut accumulator = FeeAccumulator::empty(1);
+        let swaps = vec![create_test_swap(1, now - Duration::hours(1))];
+        advance_accumulator(&mut accumulator, &position, &pool, &swaps, &StaticFeeModel, now);
+
+        // Arrives later with an older timestamp; one from before the position is ignored
+        let late = vec![
+            create_test_swap(2, now - Duration::hours(5)),
+            create_test_swap(3, now - Duration::days(1)),
+        ];
+        advance_accumulator(&mut accumulator, &position, &pool, &late, &StaticFeeModel, now);
+        assert_eq!(accumulator.swap_count, 2);
+        assert_eq!(accumulator.last_swap_id, 2);
+    }
+
+    #[test]
+    fn test_detects_drift() {
+        let now = Utc::now();
+        let position = create_test_position(now - Duration::days(1));
+        let pool = create_test_pool();
+        let swaps: Vec<Swap> =
+            (1..=3).map(|id| create_test_swap(id, now - Duration::hours(id))).collect();
+
+        let mut accumulator = FeeAccumulator::empty(1);
+        advance_accumulator(&mut accumulator, &position, &pool, &swaps, &StaticFeeModel, now);
+        accumulator.fees_earned += Decimal::ONE;
+
+        let drift =
+            check_accumulator(&accumulator, &position, &pool, &swaps, &StaticFeeModel).unwrap();
+        assert_eq!(drift.expected.swap_count, 3);
+        assert_eq!(drift.fees_earned - drift.expected.fees_earned, Decimal::ONE);
+    }
+}
@@ -0,0 +1,548 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use stillwater_models::Swap;
+
+use crate::liquidity::{amounts_for_liquidity, liquidity_for_value, range_prices};
+use crate::utils::TickRange;
+
+/// One observation of market state used to drive a backtest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketPoint {
+    pub timestamp: DateTime<Utc>,
+    /// Pool price (token1 per token0) at the end of the interval
+    pub price: Decimal,
+    /// Swap volume (in token1) since the previous point
+    pub volume: Decimal,
+    /// Pool in-range liquidity from other LPs during the interval
+    pub active_liquidity: Decimal,
+    /// Gas cost of a transaction at this point, in token1, when gas price
+    /// history priced it; the config's flat cost applies otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_cost_per_tx: Option<Decimal>,
+}
+
+impl MarketPoint {
+    /// Gas cost of a transaction sent at this point
+    pub fn gas_cost(&self, flat_cost: Decimal) -> Decimal {
+        self.gas_cost_per_tx.unwrap_or(flat_cost)
+    }
+}
+
+/// Periodically collect fees and re-add them as liquidity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CompoundingRule {
+    /// How often to compound
+    pub interval_days: i64,
+}
+
+/// Parameters of a backtest run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestConfig {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Capital deployed at the first point, in token1
+    pub initial_capital: Decimal,
+    /// Pool fee rate as a fraction (0.003 = 0.3%)
+    pub fee_rate: Decimal,
+    /// Gas cost of a single transaction, in token1, unless the market
+    /// points carry their own
+    pub gas_cost_per_tx: Decimal,
+    pub compounding: Option<CompoundingRule>,
+}
+
+/// Position state at one point of the backtest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub timestamp: DateTime<Utc>,
+    /// Position value plus uncollected fees, net of gas
+    pub equity: Decimal,
+    pub in_range: bool,
+}
+
+/// Outcome of a backtest run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestResult {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub initial_capital: Decimal,
+    pub final_equity: Decimal,
+    /// Total fees earned, including fees later compounded
+    pub fees_earned: Decimal,
+    pub gas_spent: Decimal,
+    /// Value of the initial token amounts simply held to the end
+    pub hodl_value: Decimal,
+    /// HODL value minus LP principal value at the end (excluding fees)
+    pub impermanent_loss: Decimal,
+    pub net_pnl: Decimal,
+    /// Annualized, compounded return of `final_equity` over `initial_capital`
+    pub apy: Decimal,
+    pub compounds: u32,
+    pub time_in_range: Decimal,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+/// Settings a stored backtest ran with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestParams {
+    #[serde(flatten)]
+    pub config: BacktestConfig,
+    /// Share of in-range swap fees the position was sized to earn at the start
+    pub pool_share: Decimal,
+    /// Market point spacing, in seconds
+    pub interval_secs: i64,
+    /// Whether gas cost followed the chain's recorded gas prices
+    #[serde(default)]
+    pub historical_gas: bool,
+}
+
+/// Headline figures of a backtest run, without its equity curve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestMetrics {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub initial_capital: Decimal,
+    pub final_equity: Decimal,
+    pub fees_earned: Decimal,
+    pub gas_spent: Decimal,
+    pub hodl_value: Decimal,
+    pub impermanent_loss: Decimal,
+    pub net_pnl: Decimal,
+    pub apy: Decimal,
+    pub compounds: u32,
+    pub time_in_range: Decimal,
+}
+
+impl BacktestResult {
+    /// The run's figures without the equity curve, as listings show them
+    pub fn metrics(&self) -> BacktestMetrics {
+        BacktestMetrics {
+            start: self.start,
+            end: self.end,
+            initial_capital: self.initial_capital,
+            final_equity: self.final_equity,
+            fees_earned: self.fees_earned,
+            gas_spent: self.gas_spent,
+            hodl_value: self.hodl_value,
+            impermanent_loss: self.impermanent_loss,
+            net_pnl: self.net_pnl,
+            apy: self.apy,
+            compounds: self.compounds,
+            time_in_range: self.time_in_range,
+        }
+    }
+}
+
+/// A setting two backtests ran with different values of
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamChange {
+    pub name: String,
+    pub a: serde_json::Value,
+    pub b: serde_json::Value,
+}
+
+/// One figure of two backtests side by side
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricDelta {
+    pub name: &'static str,
+    pub a: Decimal,
+    pub b: Decimal,
+    /// `b - a`
+    pub change: Decimal,
+}
+
+/// What differs between two backtest runs
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestDiff {
+    /// Settings that differ, by name (unchanged ones are left out)
+    pub params: Vec<ParamChange>,
+    pub metrics: Vec<MetricDelta>,
+}
+
+/// Settings and figures of run `b` against run `a`
+pub fn diff_backtests(
+    (params_a, metrics_a): (&BacktestParams, &BacktestMetrics),
+    (params_b, metrics_b): (&BacktestParams, &BacktestMetrics),
+) -> BacktestDiff {
+    let fields = |params: &BacktestParams| match serde_json::to_value(params) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let (fields_a, fields_b) = (fields(params_a), fields(params_b));
+    let mut names: Vec<&String> = fields_a.keys().chain(fields_b.keys()).collect();
+    names.sort();
+    names.dedup();
+    let params = names
+        .into_iter()
+        .filter_map(|name| {
+            let a = fields_a.get(name).cloned().unwrap_or_default();
+            let b = fields_b.get(name).cloned().unwrap_or_default();
+            (a != b).then(|| ParamChange { name: name.clone(), a, b })
+        })
+        .collect();
+
+    let figures = |m: &BacktestMetrics| {
+        [
+            ("final_equity", m.final_equity),
+            ("fees_earned", m.fees_earned),
+            ("gas_spent", m.gas_spent),
+            ("hodl_value", m.hodl_value),
+            ("impermanent_loss", m.impermanent_loss),
+            ("net_pnl", m.net_pnl),
+            ("apy", m.apy),
+            ("compounds", Decimal::from(m.compounds)),
+            ("time_in_range", m.time_in_range),
+        ]
+    };
+    let metrics = figures(metrics_a)
+        .into_iter()
+        .zip(figures(metrics_b))
+        .map(|((name, a), (_, b))| MetricDelta { name, a, b, change: b - a })
+        .collect();
+
+    BacktestDiff { params, metrics }
+}
+
+/// Compounding vs. no-compounding comparison for the same market data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompoundingComparison {
+    pub baseline: BacktestResult,
+    pub compounded: BacktestResult,
+    /// `compounded.apy - baseline.apy`, already net of compounding gas
+    pub apy_uplift: Decimal,
+}
+
+/// Simulate a concentrated liquidity position over a market series
+///
+/// The position is minted at the first point. For each following interval,
+/// if the closing price is in range the position earns
+/// `volume * fee_rate * L / (L + active_liquidity)`. With a compounding rule,
+/// uncollected fees are collected and re-added as liquidity every interval,
+/// but only when they exceed the two transactions of gas it costs.
+pub fn run_backtest(config: &BacktestConfig, points: &[MarketPoint]) -> Result<BacktestResult> {
+    TickRange::new(config.tick_lower, config.tick_upper)?;
+    if config.initial_capital <= Decimal::ZERO {
+        bail!("Initial capital must be positive");
+    }
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        bail!("Backtest requires at least one market point");
+    };
+
+    let (price_lower, price_upper) = range_prices(config.tick_lower, config.tick_upper);
+    let in_range = |price: Decimal| price >= price_lower && price < price_upper;
+
+    // Mint: the initial capital, less mint gas, becomes liquidity
+    let mut gas_spent = first.gas_cost(config.gas_cost_per_tx);
+    let deployed = (config.initial_capital - gas_spent).max(Decimal::ZERO);
+    let initial_liquidity = liquidity_for_value(deployed, first.price, price_lower, price_upper);
+    let initial_amounts =
+        amounts_for_liquidity(initial_liquidity, first.price, price_lower, price_upper);
+    let mut liquidity = initial_liquidity;
+
+    let mut fees_earned = Decimal::ZERO;
+    let mut uncollected = Decimal::ZERO;
+    let mut compounds = 0u32;
+    let mut last_compound = first.timestamp;
+    let mut in_range_count = 0usize;
+    let mut equity_curve = Vec::with_capacity(points.len());
+
+    for (i, point) in points.iter().enumerate() {
+        let active = in_range(point.price);
+        if active {
+            in_range_count += 1;
+        }
+
+        if i > 0 && active && liquidity > Decimal::ZERO {
+            let share = liquidity / (liquidity + point.active_liquidity.max(Decimal::ZERO));
+            let fees = point.volume * config.fee_rate * share;
+            fees_earned += fees;
+            uncollected += fees;
+        }
+
+        if let Some(rule) = config.compounding {
+            let compound_cost = point.gas_cost(config.gas_cost_per_tx) * Decimal::TWO;
+            if point.timestamp - last_compound >= Duration::days(rule.interval_days.max(1))
+                && uncollected > compound_cost
+            {
+                let reinvested = uncollected - compound_cost;
+                liquidity += liquidity_for_value(reinvested, point.price, price_lower, price_upper);
+                gas_spent += compound_cost;
+                uncollected = Decimal::ZERO;
+                compounds += 1;
+                last_compound = point.timestamp;
+            }
+        }
+
+        let position_value =
+            amounts_for_liquidity(liquidity, point.price, price_lower, price_upper)
+                .value_in_token1(point.price);
+        equity_curve.push(EquityPoint {
+            timestamp: point.timestamp,
+            equity: position_value + uncollected,
+            in_range: active,
+        });
+    }
+
+    let final_equity = equity_curve.last().map(|p| p.equity).unwrap_or(deployed);
+    let hodl_value = initial_amounts.value_in_token1(last.price);
+    // IL is measured on the initial principal only, so compounded fees don't
+    // distort the comparison with holding
+    let principal_value =
+        amounts_for_liquidity(initial_liquidity, last.price, price_lower, price_upper)
+            .value_in_token1(last.price);
+    let impermanent_loss = hodl_value - principal_value;
+    let net_pnl = final_equity - config.initial_capital;
+    let apy = annualize(config.initial_capital, final_equity, last.timestamp - first.timestamp);
+    let time_in_range = Decimal::from(in_range_count) / Decimal::from(points.len());
+
+    Ok(BacktestResult {
+        start: first.timestamp,
+        end: last.timestamp,
+        initial_capital: config.initial_capital,
+        final_equity,
+        fees_earned,
+        gas_spent,
+        hodl_value,
+        impermanent_loss,
+        net_pnl,
+        apy,
+        compounds,
+        time_in_range,
+        equity_curve,
+    })
+}
+
+/// Run the same backtest with and without a compounding rule
+pub fn compare_compounding(
+    config: &BacktestConfig,
+    rule: CompoundingRule,
+    points: &[MarketPoint],
+) -> Result<CompoundingComparison> {
+    let baseline = run_backtest(&BacktestConfig { compounding: None, ..config.clone() }, points)?;
+    let compounded =
+        run_backtest(&BacktestConfig { compounding: Some(rule), ..config.clone() }, points)?;
+    let apy_uplift = compounded.apy - baseline.apy;
+
+    Ok(CompoundingComparison { baseline, compounded, apy_uplift })
+}
+
+/// Market series from indexed swaps, one point per `interval` in [from, to)
+///
+/// Each point's price is the execution price (`|amount1| / |amount0|`) of the
+/// interval's last swap, carried forward through quiet intervals, and its volume
+/// the interval's absolute token1 volume. Intervals before the first priced swap
+/// are skipped. Swaps don't record pool liquidity, so every point gets
+/// `active_liquidity`.
+pub fn market_points_from_swaps(
+    swaps: &[Swap],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    interval: Duration,
+    active_liquidity: Decimal,
+) -> Vec<MarketPoint> {
+    let interval_secs = interval.num_seconds().max(1);
+    let intervals = ((to - from).num_seconds().max(0) + interval_secs - 1) / interval_secs;
+
+    let mut ordered: Vec<&Swap> =
+        swaps.iter().filter(|s| s.timestamp >= from && s.timestamp < to).collect();
+    ordered.sort_by_key(|s| s.timestamp);
+    let mut ordered = ordered.into_iter().peekable();
+
+    let mut points = Vec::new();
+    let mut price = None;
+    for i in 0..intervals {
+        let end = from + Duration::seconds((i + 1) * interval_secs);
+        let mut volume = Decimal::ZERO;
+        while let Some(swap) = ordered.next_if(|s| s.timestamp < end) {
+            let amount0 = Decimal::from_str(&swap.amount0.unsigned_abs().to_string()).ok();
+            let amount1 = Decimal::from_str(&swap.amount1.unsigned_abs().to_string()).ok();
+            if let (Some(amount0), Some(amount1)) = (amount0, amount1) {
+                volume += amount1;
+                if let Some(p) = amount1.checked_div(amount0).filter(|p| !p.is_zero()) {
+                    price = Some(p);
+                }
+            }
+        }
+        if let Some(price) = price {
+            points.push(MarketPoint {
+                timestamp: end.min(to),
+                price,
+                volume,
+                active_liquidity,
+                gas_cost_per_tx: None,
+            });
+        }
+    }
+    points
+}
+
+/// Compound annual growth from `start_value` to `end_value` over `period`
+///
+/// Falls back to a simple annualized return when the exponent is too large
+/// for Decimal (very short periods).
+fn annualize(start_value: Decimal, end_value: Decimal, period: Duration) -> Decimal {
+    let seconds = period.num_seconds();
+    if seconds <= 0 || start_value.is_zero() {
+        return Decimal::ZERO;
+    }
+
+    let growth = end_value / start_value;
+    let years = Decimal::from(seconds) / Decimal::from(365 * 24 * 3600);
+    let simple = (growth - Decimal::ONE) / years;
+
+    if growth <= Decimal::ZERO {
+        return simple;
+    }
+
+    growth.checked_powd(Decimal::ONE / years).map(|g| g - Decimal::ONE).unwrap_or(simple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_points(days: i64, price: Decimal) -> Vec<MarketPoint> {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        (0..=days)
+            .map(|d| MarketPoint {
+                timestamp: start + Duration::days(d),
+                price,
+                volume: Decimal::from(1_000_000),
+                active_liquidity: Decimal::from(1_000_000),
+                gas_cost_per_tx: None,
+            })
+            .collect()
+    }
+
+    fn create_test_config() -> BacktestConfig {
+        BacktestConfig {
+            tick_lower: -1000,
+            tick_upper: 1000,
+            initial_capital: Decimal::from(10_000),
+            fee_rate: Decimal::from_str("0.003").unwrap(),
+            gas_cost_per_tx: Decimal::ONE,
+            compounding: None,
+        }
+    }
+
+    #[test]
+    fn test_backtest_earns_fees_in_range() {
+        let points = create_test_points(30, Decimal::ONE);
+        let result = run_backtest(&create_test_config(), &points).unwrap();
+
+        assert!(result.fees_earned > Decimal::ZERO);
+        assert_eq!(result.time_in_range, Decimal::ONE);
+        assert_eq!(result.equity_curve.len(), points.len());
+        assert!(result.impermanent_loss.abs() < Decimal::from_str("0.0001").unwrap());
+        assert!(result.net_pnl > Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_backtest_no_fees_out_of_range() {
+        let points = create_test_points(30, Decimal::from(2));
+        let result = run_backtest(&create_test_config(), &points).unwrap();
+
+        assert_eq!(result.fees_earned, Decimal::ZERO);
+        assert_eq!(result.time_in_range, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_compounding_uplift() {
+        let points = create_test_points(90, Decimal::ONE);
+        let comparison = compare_compounding(
+            &create_test_config(),
+            CompoundingRule { interval_days: 7 },
+            &points,
+        )
+        .unwrap();
+
+        assert!(comparison.compounded.compounds > 0);
+        assert_eq!(comparison.baseline.compounds, 0);
+        assert!(comparison.compounded.gas_spent > comparison.baseline.gas_spent);
+        assert!(comparison.apy_uplift > Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_compounding_skipped_when_gas_exceeds_fees() {
+        let points = create_test_points(30, Decimal::ONE);
+        let config = BacktestConfig {
+            gas_cost_per_tx: Decimal::from(5_000),
+            compounding: Some(CompoundingRule { interval_days: 1 }),
+            ..create_test_config()
+        };
+        let result = run_backtest(&config, &points).unwrap();
+
+        assert_eq!(result.compounds, 0);
+    }
+
+    #[test]
+    fn test_market_points_from_swaps() {
+        use alloy::primitives::I256;
+
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let swap = |minutes: i64, amount0: i64, amount1: i64| Swap {
+            id: minutes,
+            tx_hash: format!("0x{}", minutes),
+            pool_id: "0xpool".to_string(),
+            amount0: I256::try_from(amount0).unwrap(),
+            amount1: I256::try_from(amount1).unwrap(),
+            fee: None,
+            timestamp: start + Duration::minutes(minutes),
+        };
+        // Quiet first hour, two swaps in the second, none in the third
+        let swaps = vec![swap(70, 100, -200), swap(80, -100, 300)];
+
+        let points = market_points_from_swaps(
+            &swaps,
+            start,
+            start + Duration::hours(3),
+            Duration::hours(1),
+            Decimal::ONE,
+        );
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].price, Decimal::from(3));
+        assert_eq!(points[0].volume, Decimal::from(500));
+        assert_eq!(points[1].price, Decimal::from(3));
+        assert_eq!(points[1].volume, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_backtest_rejects_invalid_input() {
+        let config = BacktestConfig { tick_lower: 10, tick_upper: 10, ..create_test_config() };
+        assert!(run_backtest(&config, &create_test_points(1, Decimal::ONE)).is_err());
+        assert!(run_backtest(&create_test_config(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_diff_backtests() {
+        let points = create_test_points(30, Decimal::ONE);
+        let params_a = BacktestParams {
+            config: create_test_config(),
+            pool_share: Decimal::new(1, 2),
+            interval_secs: 3600,
+            historical_gas: false,
+        };
+        let params_b = BacktestParams {
+            config: BacktestConfig {
+                compounding: Some(CompoundingRule { interval_days: 7 }),
+                ..create_test_config()
+            },
+            ..params_a.clone()
+        };
+        let a = run_backtest(&params_a.config, &points).unwrap().metrics();
+        let b = run_backtest(&params_b.config, &points).unwrap().metrics();
+
+        let diff = diff_backtests((&params_a, &a), (&params_b, &b));
+        assert_eq!(diff.params.len(), 1);
+        assert_eq!(diff.params[0].name, "compounding");
+        assert_eq!(diff.params[0].a, serde_json::Value::Null);
+
+        let compounds = diff.metrics.iter().find(|m| m.name == "compounds").unwrap();
+        assert_eq!(compounds.change, Decimal::from(b.compounds));
+        let net_pnl = diff.metrics.iter().find(|m| m.name == "net_pnl").unwrap();
+        assert_eq!(net_pnl.change, b.net_pnl - a.net_pnl);
+    }
+}
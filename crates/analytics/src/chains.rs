@@ -0,0 +1,244 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use stillwater_models::{Address, GasExpense, Position, PositionSnapshot};
+
+use crate::holding::holding_period;
+use crate::liquidity::{amounts_for_liquidity, range_prices};
+
+/// A position opened this long before or after another one closed (same owner
+/// and pool) is treated as its rebalance
+pub const DEFAULT_CHAIN_WINDOW_MINUTES: i64 = 60;
+
+/// One position's lifetime and value, as a link in a rebalance chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainLink {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Position value in token1 at its first snapshot with liquidity
+    pub entry_value: Decimal,
+    /// Position value in token1 at its last snapshot with liquidity (current value while open)
+    pub exit_value: Decimal,
+    pub fees_earned: Decimal,
+    pub gas_spent: Decimal,
+}
+
+/// Positions linked by rebalances: each one opened as the previous one closed
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceChain {
+    pub owner: Address,
+    pub pool_id: String,
+    /// Oldest first
+    pub links: Vec<ChainLink>,
+    pub rebalances: usize,
+    /// Entry value of the first position; capital rolled into later positions isn't new basis
+    pub cost_basis: Decimal,
+    /// Exit value of the last position
+    pub current_value: Decimal,
+    pub fees_earned: Decimal,
+    pub gas_spent: Decimal,
+    /// `current_value - cost_basis + fees_earned - gas_spent`
+    pub net_pnl: Decimal,
+    /// `net_pnl` as a percentage of `cost_basis` (None without an entry value)
+    pub return_pct: Option<Decimal>,
+}
+
+/// A position's link from its snapshots (sorted by timestamp) and gas expenses
+pub fn chain_link(
+    position: &Position,
+    snapshots: &[PositionSnapshot],
+    gas_expenses: &[GasExpense],
+    now: DateTime<Utc>,
+) -> ChainLink {
+    let period = holding_period(position, snapshots, now);
+    let (price_lower, price_upper) = range_prices(position.tick_lower, position.tick_upper);
+    let value = |s: &PositionSnapshot| {
+        let liquidity = Decimal::from_str(&s.liquidity.to_string()).unwrap_or_default();
+        amounts_for_liquidity(liquidity, s.price, price_lower, price_upper).value_in_token1(s.price)
+    };
+    let first_held = snapshots.iter().find(|s| !s.liquidity.is_zero());
+    let last_held = snapshots.iter().rev().find(|s| !s.liquidity.is_zero());
+
+    ChainLink {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        tick_lower: position.tick_lower,
+        tick_upper: position.tick_upper,
+        opened_at: period.opened_at,
+        closed_at: period.closed_at,
+        entry_value: first_held.map(value).unwrap_or_default(),
+        exit_value: last_held.map(value).unwrap_or_default(),
+        fees_earned: period.fees_earned,
+        gas_spent: gas_expenses.iter().map(|g| g.gas_cost).sum(),
+    }
+}
+
+/// Link an owner's positions into rebalance chains
+///
+/// A closed position is followed by the position in the same pool opened
+/// closest to its close, within `window` either side. Each position has at
+/// most one predecessor and one successor. `positions` pairs every position
+/// with its link; only chains of two or more positions are returned, oldest first.
+pub fn detect_rebalance_chains(
+    positions: &[(&Position, ChainLink)],
+    window: Duration,
+) -> Vec<RebalanceChain> {
+    let mut closed: Vec<usize> =
+        (0..positions.len()).filter(|&i| positions[i].1.closed_at.is_some()).collect();
+    closed.sort_by_key(|&i| positions[i].1.closed_at);
+
+    let mut next: Vec<Option<usize>> = vec![None; positions.len()];
+    let mut has_previous: HashSet<usize> = HashSet::new();
+
+    for i in closed {
+        let (position, link) = &positions[i];
+        let Some(closed_at) = link.closed_at else {
+            continue;
+        };
+        let successor = (0..positions.len())
+            .filter(|&j| j != i && !has_previous.contains(&j))
+            .filter(|&j| {
+                let (candidate, candidate_link) = &positions[j];
+                candidate.pool_id == position.pool_id
+                    && candidate.owner == position.owner
+                    && candidate_link.opened_at > link.opened_at
+                    && (candidate_link.opened_at - closed_at).abs() <= window
+            })
+            .min_by_key(|&j| (positions[j].1.opened_at - closed_at).abs());
+
+        if let Some(j) = successor {
+            next[i] = Some(j);
+            has_previous.insert(j);
+        }
+    }
+
+    let mut chains: Vec<RebalanceChain> = (0..positions.len())
+        .filter(|i| next[*i].is_some() && !has_previous.contains(i))
+        .map(|start| {
+            let mut links = vec![positions[start].1.clone()];
+            let mut current = start;
+            while let Some(j) = next[current] {
+                links.push(positions[j].1.clone());
+                current = j;
+            }
+            let (position, _) = &positions[start];
+            summarize_chain(position.owner, &position.pool_id, links)
+        })
+        .collect();
+    chains.sort_by_key(|c| c.links[0].opened_at);
+    chains
+}
+
+/// Strategy-level P&L over a chain's links
+///
+/// Capital is assumed to roll from each position into the next, so value lost
+/// swapping to a new range's ratio shows up in `net_pnl` instead of resetting the basis.
+fn summarize_chain(owner: Address, pool_id: &str, links: Vec<ChainLink>) -> RebalanceChain {
+    let cost_basis = links.first().map(|l| l.entry_value).unwrap_or_default();
+    let current_value = links.last().map(|l| l.exit_value).unwrap_or_default();
+    let fees_earned: Decimal = links.iter().map(|l| l.fees_earned).sum();
+    let gas_spent: Decimal = links.iter().map(|l| l.gas_spent).sum();
+    let net_pnl = current_value - cost_basis + fees_earned - gas_spent;
+
+    RebalanceChain {
+        owner,
+        pool_id: pool_id.to_string(),
+        rebalances: links.len().saturating_sub(1),
+        links,
+        cost_basis,
+        current_value,
+        fees_earned,
+        gas_spent,
+        net_pnl,
+        return_pct: net_pnl.checked_div(cost_basis).map(|r| r * Decimal::from(100)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use alloy::primitives::U256;
+
+    fn create_test_position(id: i64, pool_id: &str, opened_hours: i64) -> Position {
+        Position {
+            id,
+            nft_id: id.to_string(),
+            owner: Address::repeat_byte(1),
+            pool_id: pool_id.to_string(),
+            liquidity: U256::ZERO,
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+                + Duration::hours(opened_hours),
+            ..testing::create_test_position()
+        }
+    }
+
+    fn create_test_link(
+        position: &Position,
+        closed_hours: Option<i64>,
+        entry: i64,
+        exit: i64,
+        fees: i64,
+    ) -> ChainLink {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        ChainLink {
+            position_id: position.id,
+            nft_id: position.nft_id.clone(),
+            tick_lower: position.tick_lower,
+            tick_upper: position.tick_upper,
+            opened_at: position.created_at,
+            closed_at: closed_hours.map(|h| start + Duration::hours(h)),
+            entry_value: Decimal::from(entry),
+            exit_value: Decimal::from(exit),
+            fees_earned: Decimal::from(fees),
+            gas_spent: Decimal::ONE,
+        }
+    }
+
+    #[test]
+    fn test_links_rebalances_in_same_pool() {
+        let first = create_test_position(1, "0xpool", 0);
+        let second = create_test_position(2, "0xpool", 10);
+        let third = create_test_position(3, "0xpool", 20);
+        let other_pool = create_test_position(4, "0xother", 10);
+        let unrelated = create_test_position(5, "0xpool", 100);
+        let positions = vec![
+            (&first, create_test_link(&first, Some(10), 1000, 900, 50)),
+            (&second, create_test_link(&second, Some(20), 900, 950, 30)),
+            (&third, create_test_link(&third, None, 950, 1020, 10)),
+            (&other_pool, create_test_link(&other_pool, None, 500, 500, 0)),
+            (&unrelated, create_test_link(&unrelated, None, 500, 500, 0)),
+        ];
+
+        let chains = detect_rebalance_chains(&positions, Duration::minutes(60));
+
+        assert_eq!(chains.len(), 1);
+        let ids: Vec<i64> = chains[0].links.iter().map(|l| l.position_id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(chains[0].rebalances, 2);
+    }
+
+    #[test]
+    fn test_chain_pnl_keeps_first_cost_basis() {
+        let first = create_test_position(1, "0xpool", 0);
+        let second = create_test_position(2, "0xpool", 10);
+        let positions = vec![
+            (&first, create_test_link(&first, Some(10), 1000, 900, 50)),
+            (&second, create_test_link(&second, None, 890, 1020, 10)),
+        ];
+
+        let chain = &detect_rebalance_chains(&positions, Duration::minutes(60))[0];
+
+        assert_eq!(chain.cost_basis, Decimal::from(1000));
+        assert_eq!(chain.current_value, Decimal::from(1020));
+        // 1020 - 1000 + 60 fees - 2 gas
+        assert_eq!(chain.net_pnl, Decimal::from(78));
+        assert_eq!(chain.return_pct, Some(Decimal::from_str("7.8").unwrap()));
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{Position, PositionSnapshot};
+
+use crate::utils::tick_to_price;
+
+/// A single point of a range-band chart: pool price plus the position's bounds
+#[derive(Debug, Clone, Serialize)]
+pub struct RangeBandPoint {
+    pub timestamp: DateTime<Utc>,
+    pub price: Decimal,
+    pub lower: Decimal,
+    pub upper: Decimal,
+    pub in_range: bool,
+}
+
+/// Build the range-band series for a position from its snapshots
+///
+/// Bounds are derived from the position's ticks, so every point carries the
+/// same lower/upper values; this keeps the series directly plottable as three
+/// lines (or a band plus a line) without a client-side join.
+pub fn build_range_band(
+    position: &Position,
+    snapshots: &[PositionSnapshot],
+) -> Vec<RangeBandPoint> {
+    let lower = tick_to_price(position.tick_lower);
+    let upper = tick_to_price(position.tick_upper);
+
+    snapshots
+        .iter()
+        .map(|s| RangeBandPoint {
+            timestamp: s.timestamp,
+            price: s.price,
+            lower,
+            upper,
+            in_range: s.price >= lower && s.price < upper,
+        })
+        .collect()
+}
+
+/// Fraction of points (0-1) where the position was in range
+pub fn time_in_range(points: &[RangeBandPoint]) -> Decimal {
+    if points.is_empty() {
+        return Decimal::ZERO;
+    }
+
+    let in_range = points.iter().filter(|p| p.in_range).count();
+    Decimal::from(in_range) / Decimal::from(points.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::create_test_position;
+    use alloy::primitives::U256;
+
+    fn create_test_snapshot(price: Decimal) -> PositionSnapshot {
+        PositionSnapshot {
+            id: 1,
+            position_id: 1,
+            timestamp: Utc::now(),
+            fees_earned: Decimal::ZERO,
+            liquidity: U256::from(1000000u64),
+            price,
+        }
+    }
+
+    #[test]
+    fn test_build_range_band() {
+        let position = create_test_position();
+        let snapshots = vec![
+            create_test_snapshot(Decimal::ONE),
+            create_test_snapshot(Decimal::from(2)),
+            create_test_snapshot(Decimal::new(5, 1)),
+        ];
+
+        let points = build_range_band(&position, &snapshots);
+        assert_eq!(points.len(), 3);
+        assert!(points[0].in_range);
+        assert!(!points[1].in_range); // Above ~1.105
+        assert!(!points[2].in_range); // Below ~0.905
+        assert!(points.iter().all(|p| p.lower < Decimal::ONE && p.upper > Decimal::ONE));
+    }
+
+    #[test]
+    fn test_time_in_range() {
+        let position = create_test_position();
+        let snapshots =
+            vec![create_test_snapshot(Decimal::ONE), create_test_snapshot(Decimal::from(2))];
+
+        let points = build_range_band(&position, &snapshots);
+        assert_eq!(time_in_range(&points), Decimal::new(5, 1));
+        assert_eq!(time_in_range(&[]), Decimal::ZERO);
+    }
+}
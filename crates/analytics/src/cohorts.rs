@@ -0,0 +1,192 @@
+use chrono::{DateTime, Datelike, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::leaderboard::PositionPerformance;
+use crate::utils::range_width_percent;
+
+/// Ranges narrower than this (upper price over lower, in %) are `narrow`
+pub const NARROW_MAX_WIDTH_PERCENT: u32 = 5;
+
+/// Ranges narrower than this are `medium`
+pub const MEDIUM_MAX_WIDTH_PERCENT: u32 = 25;
+
+/// Ranges narrower than this are `wide`; anything wider is `full`
+pub const WIDE_MAX_WIDTH_PERCENT: u32 = 100;
+
+/// Range width bucket of a position, by how far its upper price is above its lower price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WidthBucket {
+    Narrow,
+    Medium,
+    Wide,
+    Full,
+}
+
+impl WidthBucket {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WidthBucket::Narrow => "narrow",
+            WidthBucket::Medium => "medium",
+            WidthBucket::Wide => "wide",
+            WidthBucket::Full => "full",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "narrow" => Some(WidthBucket::Narrow),
+            "medium" => Some(WidthBucket::Medium),
+            "wide" => Some(WidthBucket::Wide),
+            "full" => Some(WidthBucket::Full),
+            _ => None,
+        }
+    }
+
+    /// Bucket of a range width in percent (see `range_width_percent`)
+    pub fn of_width(width_percent: Decimal) -> Self {
+        if width_percent < Decimal::from(NARROW_MAX_WIDTH_PERCENT) {
+            WidthBucket::Narrow
+        } else if width_percent < Decimal::from(MEDIUM_MAX_WIDTH_PERCENT) {
+            WidthBucket::Medium
+        } else if width_percent < Decimal::from(WIDE_MAX_WIDTH_PERCENT) {
+            WidthBucket::Wide
+        } else {
+            WidthBucket::Full
+        }
+    }
+}
+
+/// A pool's positions entered in the same month with similar range widths
+#[derive(Debug, Clone, Serialize)]
+pub struct Cohort {
+    /// Entry month as `YYYY-MM`
+    pub entry_month: String,
+    pub width: WidthBucket,
+    pub positions: usize,
+    /// Median annualized net P&L over capital, across positions with known capital
+    pub median_apr: Option<Decimal>,
+    /// Median annualized fees over full capital
+    pub median_fee_apr: Option<Decimal>,
+    /// Median annualized fees over in-range capital
+    pub median_in_range_fee_apr: Option<Decimal>,
+    /// Median impermanent loss as a percentage of capital
+    pub median_il_percent: Option<Decimal>,
+    pub median_fees_earned: Option<Decimal>,
+}
+
+/// Group positions into cohorts by entry month and range width bucket
+///
+/// Each performance is paired with its position's entry time and ticks;
+/// positions with invalid ranges are skipped. Cohorts are sorted by month,
+/// then narrowest width first.
+pub fn build_cohorts(
+    performances: &[(DateTime<Utc>, i32, i32, PositionPerformance)],
+) -> Vec<Cohort> {
+    let mut groups: BTreeMap<(String, WidthBucket), Vec<&PositionPerformance>> = BTreeMap::new();
+    for (opened_at, tick_lower, tick_upper, performance) in performances {
+        let Ok(width) = range_width_percent(*tick_lower, *tick_upper) else {
+            continue;
+        };
+        let month = format!("{:04}-{:02}", opened_at.year(), opened_at.month());
+        groups.entry((month, WidthBucket::of_width(width))).or_default().push(performance);
+    }
+
+    groups
+        .into_iter()
+        .map(|((entry_month, width), members)| {
+            let aprs = members.iter().filter_map(|p| p.apr).collect();
+            let fee_aprs = members.iter().filter_map(|p| p.fee_apr.principal).collect();
+            let in_range_fee_aprs = members.iter().filter_map(|p| p.fee_apr.in_range).collect();
+            let il_percents = members
+                .iter()
+                .filter_map(|p| p.impermanent_loss.checked_div(p.capital))
+                .map(|r| r * Decimal::from(100))
+                .collect();
+            let fees = members.iter().map(|p| p.fees_earned).collect();
+
+            Cohort {
+                entry_month,
+                width,
+                positions: members.len(),
+                median_apr: median(aprs),
+                median_fee_apr: median(fee_aprs),
+                median_in_range_fee_apr: median(in_range_fee_aprs),
+                median_il_percent: median(il_percents),
+                median_fees_earned: median(fees),
+            }
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<Decimal>) -> Option<Decimal> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / Decimal::TWO)
+    } else {
+        Some(values[mid])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaderboard::FeeApr;
+    use chrono::TimeZone;
+    use stillwater_models::Address;
+
+    fn create_test_performance(apr: Option<i64>, il: i64, capital: i64) -> PositionPerformance {
+        PositionPerformance {
+            position_id: 1,
+            nft_id: "1".to_string(),
+            owner: Address::ZERO,
+            pool_id: "0xpool".to_string(),
+            fees_earned: Decimal::from(10),
+            impermanent_loss: Decimal::from(il),
+            net_pnl: Decimal::ZERO,
+            capital: Decimal::from(capital),
+            in_range_capital: Decimal::from(capital),
+            apr: apr.map(Decimal::from),
+            fee_apr: FeeApr::default(),
+        }
+    }
+
+    #[test]
+    fn test_width_buckets() {
+        assert_eq!(WidthBucket::of_width(Decimal::ONE), WidthBucket::Narrow);
+        assert_eq!(WidthBucket::of_width(Decimal::from(10)), WidthBucket::Medium);
+        assert_eq!(WidthBucket::of_width(Decimal::from(50)), WidthBucket::Wide);
+        assert_eq!(WidthBucket::of_width(Decimal::from(500)), WidthBucket::Full);
+    }
+
+    #[test]
+    fn test_cohorts_group_by_month_and_width() {
+        let march = Utc.with_ymd_and_hms(2024, 3, 5, 0, 0, 0).unwrap();
+        let april = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+        // ±100 ticks is ~2% wide, ±5000 ticks ~172% wide
+        let performances = vec![
+            (march, -100, 100, create_test_performance(Some(10), 5, 100)),
+            (march, -100, 100, create_test_performance(Some(30), 1, 100)),
+            (march, -100, 100, create_test_performance(None, 2, 0)),
+            (march, -5000, 5000, create_test_performance(Some(4), 0, 100)),
+            (april, -100, 100, create_test_performance(Some(50), 3, 100)),
+        ];
+
+        let cohorts = build_cohorts(&performances);
+
+        assert_eq!(cohorts.len(), 3);
+        assert_eq!(cohorts[0].entry_month, "2024-03");
+        assert_eq!(cohorts[0].width, WidthBucket::Narrow);
+        assert_eq!(cohorts[0].positions, 3);
+        assert_eq!(cohorts[0].median_apr, Some(Decimal::from(20)));
+        assert_eq!(cohorts[0].median_il_percent, Some(Decimal::from(3)));
+        assert_eq!(cohorts[1].width, WidthBucket::Full);
+        assert_eq!(cohorts[2].entry_month, "2024-04");
+    }
+}
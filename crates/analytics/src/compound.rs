@@ -0,0 +1,161 @@
+use anyhow::{Context, Result, bail};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
+use stillwater_models::{GasExpense, PositionSnapshot};
+
+/// Transactions per compound: collect fees, re-deposit them
+pub const COMPOUND_TXS: u32 = 2;
+
+/// Unclaimed fees must exceed the compound's gas this many times over by default
+pub const DEFAULT_COMPOUND_GAS_MULTIPLE: u32 = 3;
+
+/// When compounding is worth its gas
+#[derive(Debug, Clone, Serialize)]
+pub struct CompoundConfig {
+    /// Required ratio of unclaimed fees to compound gas
+    pub gas_multiple: Decimal,
+    /// Gas cost of a single transaction, in the units fees are tracked in (raw token1).
+    /// Compound alerts are only raised when it's set.
+    pub gas_cost_per_tx: Option<Decimal>,
+}
+
+impl Default for CompoundConfig {
+    fn default() -> Self {
+        Self { gas_multiple: Decimal::from(DEFAULT_COMPOUND_GAS_MULTIPLE), gas_cost_per_tx: None }
+    }
+}
+
+impl CompoundConfig {
+    /// Config from `COMPOUND_GAS_MULTIPLE` and `COMPOUND_GAS_COST`
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(multiple) = std::env::var("COMPOUND_GAS_MULTIPLE") {
+            let multiple = Decimal::from_str(multiple.trim())
+                .context("COMPOUND_GAS_MULTIPLE must be a number")?;
+            if multiple <= Decimal::ZERO {
+                bail!("COMPOUND_GAS_MULTIPLE must be positive");
+            }
+            config.gas_multiple = multiple;
+        }
+        if let Ok(cost) = std::env::var("COMPOUND_GAS_COST") {
+            let cost =
+                Decimal::from_str(cost.trim()).context("COMPOUND_GAS_COST must be a number")?;
+            if cost < Decimal::ZERO {
+                bail!("COMPOUND_GAS_COST must be non-negative");
+            }
+            config.gas_cost_per_tx = Some(cost);
+        }
+        Ok(config)
+    }
+}
+
+/// Whether a position's unclaimed fees are worth collecting and re-depositing
+#[derive(Debug, Clone, Serialize)]
+pub struct CompoundRecommendation {
+    pub unclaimed_fees: Decimal,
+    /// Gas of the `COMPOUND_TXS` transactions a compound takes
+    pub compound_gas: Decimal,
+    pub gas_multiple: Decimal,
+    /// Unclaimed fees at which compounding is recommended
+    pub threshold: Decimal,
+    /// Unclaimed fees over compound gas (None when gas is free)
+    pub fees_to_gas: Option<Decimal>,
+    pub compound_now: bool,
+}
+
+/// Fees accrued since the position's last transaction
+///
+/// Snapshot fees are cumulative and every collect is a transaction with a
+/// recorded gas expense, so fees earned before the latest expense are taken to
+/// be claimed. Snapshots must be sorted by timestamp.
+pub fn unclaimed_fees(snapshots: &[PositionSnapshot], gas_expenses: &[GasExpense]) -> Decimal {
+    let Some(latest) = snapshots.last() else {
+        return Decimal::ZERO;
+    };
+    let claimed = gas_expenses
+        .iter()
+        .map(|g| g.timestamp)
+        .max()
+        .and_then(|last_tx| snapshots.iter().rev().find(|s| s.timestamp <= last_tx))
+        .map(|s| s.fees_earned)
+        .unwrap_or(Decimal::ZERO);
+    (latest.fees_earned - claimed).max(Decimal::ZERO)
+}
+
+/// Recommend compounding once unclaimed fees reach `gas_multiple` times the compound's gas
+pub fn recommend_compound(
+    unclaimed_fees: Decimal,
+    gas_cost_per_tx: Decimal,
+    gas_multiple: Decimal,
+) -> CompoundRecommendation {
+    let compound_gas = gas_cost_per_tx * Decimal::from(COMPOUND_TXS);
+    let threshold = compound_gas * gas_multiple;
+
+    CompoundRecommendation {
+        unclaimed_fees,
+        compound_gas,
+        gas_multiple,
+        threshold,
+        fees_to_gas: unclaimed_fees.checked_div(compound_gas),
+        compound_now: unclaimed_fees > Decimal::ZERO && unclaimed_fees >= threshold,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use chrono::{DateTime, Duration, Utc};
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap() + Duration::hours(hours)
+    }
+
+    fn create_test_snapshot(hours: i64, fees: i64) -> PositionSnapshot {
+        PositionSnapshot {
+            id: hours,
+            position_id: 1,
+            timestamp: at(hours),
+            fees_earned: Decimal::from(fees),
+            liquidity: U256::from(1000),
+            price: Decimal::ONE,
+        }
+    }
+
+    fn create_test_expense(hours: i64) -> GasExpense {
+        GasExpense {
+            id: hours,
+            position_id: 1,
+            tx_hash: format!("0x{}", hours),
+            gas_cost: Decimal::ONE,
+            timestamp: at(hours),
+        }
+    }
+
+    #[test]
+    fn test_unclaimed_fees_since_last_transaction() {
+        let snapshots: Vec<_> =
+            [(0, 0), (1, 40), (2, 100), (3, 130)].map(|(h, f)| create_test_snapshot(h, f)).into();
+
+        assert_eq!(unclaimed_fees(&snapshots, &[]), Decimal::from(130));
+        let expenses = [create_test_expense(0), create_test_expense(2)];
+        assert_eq!(unclaimed_fees(&snapshots, &expenses), Decimal::from(30));
+    }
+
+    #[test]
+    fn test_compound_when_fees_exceed_gas_multiple() {
+        // Compound gas is 2 txs × 5 = 10, threshold 30
+        let wait = recommend_compound(Decimal::from(29), Decimal::from(5), Decimal::from(3));
+        assert!(!wait.compound_now);
+        assert_eq!(wait.threshold, Decimal::from(30));
+
+        let now = recommend_compound(Decimal::from(30), Decimal::from(5), Decimal::from(3));
+        assert!(now.compound_now);
+        assert_eq!(now.fees_to_gas, Some(Decimal::from(3)));
+
+        let free = recommend_compound(Decimal::ZERO, Decimal::ZERO, Decimal::from(3));
+        assert!(!free.compound_now);
+        assert_eq!(free.fees_to_gas, None);
+    }
+}
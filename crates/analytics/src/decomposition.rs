@@ -0,0 +1,293 @@
+use alloy::primitives::Address;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
+use stillwater_models::{GasExpense, Pool, Position, PositionSnapshot};
+
+use crate::liquidity::{amounts_for_liquidity, range_prices};
+use crate::rates::ExchangeRates;
+use crate::timerange::TimeRange;
+
+/// Where a change in P&L came from
+///
+/// `net = fee_income + price_appreciation + il_delta - gas`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PnlComponents {
+    /// Fees earned
+    pub fee_income: Decimal,
+    /// Change in value of the tokens held at the start, had they been held outside the pool
+    pub price_appreciation: Decimal,
+    /// Value of the position at the end minus those held tokens (negative: IL incurred)
+    pub il_delta: Decimal,
+    /// Gas paid
+    pub gas: Decimal,
+    pub net: Decimal,
+}
+
+impl PnlComponents {
+    fn new(
+        fee_income: Decimal,
+        price_appreciation: Decimal,
+        il_delta: Decimal,
+        gas: Decimal,
+    ) -> Self {
+        Self {
+            fee_income,
+            price_appreciation,
+            il_delta,
+            gas,
+            net: fee_income + price_appreciation + il_delta - gas,
+        }
+    }
+}
+
+/// A position's P&L change between two snapshots, split into its components
+///
+/// `components` are in raw token1, except `gas` which is in the native token
+/// (and counted in `net` as it is, like the P&L endpoints do); `quote`
+/// restates them in a quote currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PnlDecomposition {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    /// Snapshots the change is measured between
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub price_from: Decimal,
+    pub price_to: Decimal,
+    pub components: PnlComponents,
+    /// Value of liquidity added (positive) or removed over the window at the closing
+    /// price: money moved in or out, not P&L
+    pub net_flows: Decimal,
+    /// `components` in the quote currency (None without rates for token1 or gas)
+    pub quote: Option<PnlComponents>,
+}
+
+/// Split a position's P&L change over `range` into fees, price, IL and gas
+///
+/// Measured from the last snapshot at or before `range.from` (the first one
+/// after it for positions created later) to the last one at or before
+/// `range.to`. The tokens backing the starting liquidity at the starting price
+/// are revalued at the closing price for `price_appreciation`; what that
+/// liquidity is worth in the pool at the closing price beyond them is
+/// `il_delta`. Liquidity added or removed in between is reported as
+/// `net_flows` at the closing price instead of as P&L. Fees are the growth of
+/// the snapshots' cumulative fees, gas what was paid after the first snapshot
+/// up to the last. None without two distinct snapshots.
+pub fn decompose_pnl(
+    position: &Position,
+    snapshots: &[PositionSnapshot],
+    gas: &[GasExpense],
+    range: TimeRange,
+) -> Option<PnlDecomposition> {
+    let snapshots: Vec<&PositionSnapshot> =
+        snapshots.iter().filter(|s| s.position_id == position.id).collect();
+    let start = snapshots
+        .iter()
+        .filter(|s| s.timestamp <= range.from)
+        .max_by_key(|s| s.timestamp)
+        .or_else(|| {
+            snapshots.iter().filter(|s| s.timestamp <= range.to).min_by_key(|s| s.timestamp)
+        })?;
+    let end = snapshots.iter().filter(|s| s.timestamp <= range.to).max_by_key(|s| s.timestamp)?;
+    if end.timestamp <= start.timestamp {
+        return None;
+    }
+
+    let liquidity_from = Decimal::from_str(&start.liquidity.to_string()).ok()?;
+    let liquidity_to = Decimal::from_str(&end.liquidity.to_string()).ok()?;
+    let (price_lower, price_upper) = range_prices(position.tick_lower, position.tick_upper);
+    let (price_from, price_to) = (start.price, end.price);
+
+    let held = amounts_for_liquidity(liquidity_from, price_from, price_lower, price_upper);
+    let value_from = held.value_in_token1(price_from);
+    let hodl_to = held.value_in_token1(price_to);
+    let value_at_close = |liquidity: Decimal| {
+        amounts_for_liquidity(liquidity, price_to, price_lower, price_upper)
+            .value_in_token1(price_to)
+    };
+    let value_to = value_at_close(liquidity_from);
+    let net_flows = value_at_close(liquidity_to - liquidity_from);
+
+    let fee_income = (end.fees_earned - start.fees_earned).max(Decimal::ZERO);
+    let gas_paid: Decimal = gas
+        .iter()
+        .filter(|g| g.position_id == position.id)
+        .filter(|g| g.timestamp > start.timestamp && g.timestamp <= end.timestamp)
+        .map(|g| g.gas_cost)
+        .sum();
+
+    Some(PnlDecomposition {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        pool_id: position.pool_id.clone(),
+        from: start.timestamp,
+        to: end.timestamp,
+        price_from,
+        price_to,
+        components: PnlComponents::new(
+            fee_income,
+            hodl_to - value_from,
+            value_to - hodl_to,
+            gas_paid,
+        ),
+        net_flows,
+        quote: None,
+    })
+}
+
+impl PnlDecomposition {
+    /// Fill in `quote` from the rates of the pool's token1 and the native token
+    ///
+    /// Gas needs a native token rate only when some was paid.
+    pub fn price_in(&mut self, pool: &Pool, rates: &ExchangeRates) {
+        let token1 = |raw: Decimal| rates.convert(&pool.token1, raw, pool.token1_decimals);
+        let components = &self.components;
+        let gas = if components.gas.is_zero() {
+            Some(Decimal::ZERO)
+        } else {
+            rates.convert(&Address::ZERO.to_string(), components.gas, 0)
+        };
+        let priced = (
+            token1(components.fee_income),
+            token1(components.price_appreciation),
+            token1(components.il_delta),
+            gas,
+        );
+        self.quote = match priced {
+            (Some(fees), Some(price), Some(il), Some(gas)) => {
+                Some(PnlComponents::new(fees, price, il, gas))
+            }
+            _ => None,
+        };
+    }
+}
+
+/// A portfolio's P&L change split into components, in one quote currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecompositionTotals {
+    pub currency: String,
+    pub positions: usize,
+    pub components: PnlComponents,
+    /// Positions left out of `components` for lack of a rate
+    pub unpriced_positions: usize,
+}
+
+/// Add up positions' decompositions priced in `currency`
+pub fn sum_decompositions(positions: &[PnlDecomposition], currency: &str) -> DecompositionTotals {
+    let mut components = PnlComponents::default();
+    let mut unpriced_positions = 0;
+    for quote in positions.iter().map(|p| p.quote) {
+        match quote {
+            Some(quote) => {
+                components = PnlComponents::new(
+                    components.fee_income + quote.fee_income,
+                    components.price_appreciation + quote.price_appreciation,
+                    components.il_delta + quote.il_delta,
+                    components.gas + quote.gas,
+                );
+            }
+            None => unpriced_positions += 1,
+        }
+    }
+    DecompositionTotals {
+        currency: currency.to_lowercase(),
+        positions: positions.len(),
+        components,
+        unpriced_positions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liquidity::value_per_liquidity;
+    use alloy::primitives::U256;
+    use chrono::TimeZone;
+    use stillwater_models::Address;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap()
+    }
+
+    fn position() -> Position {
+        Position {
+            id: 1,
+            nft_id: "1".to_string(),
+            owner: Address::ZERO,
+            pool_id: "0xpool".to_string(),
+            tick_lower: -20_000,
+            tick_upper: 20_000,
+            liquidity: U256::from(1_000_000u64),
+            created_at: at(1),
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        }
+    }
+
+    fn snapshot(day: u32, price: Decimal, fees: i64, liquidity: u64) -> PositionSnapshot {
+        PositionSnapshot {
+            id: day as i64,
+            position_id: 1,
+            timestamp: at(day),
+            fees_earned: Decimal::from(fees),
+            liquidity: U256::from(liquidity),
+            price,
+        }
+    }
+
+    fn gas(day: u32, cost: Decimal) -> GasExpense {
+        GasExpense {
+            id: day as i64,
+            position_id: 1,
+            tx_hash: format!("0x{}", day),
+            gas_cost: cost,
+            timestamp: at(day),
+        }
+    }
+
+    #[test]
+    fn test_components_add_up_to_the_change_in_value() {
+        let snapshots = vec![
+            snapshot(1, Decimal::ONE, 0, 1_000_000),
+            snapshot(3, Decimal::ONE, 100, 1_000_000),
+            snapshot(6, Decimal::new(12, 1), 400, 1_000_000),
+        ];
+        let expenses = vec![gas(2, Decimal::new(5, 1)), gas(4, Decimal::ONE)];
+        let range = TimeRange { from: at(3), to: at(7) };
+
+        let decomposition = decompose_pnl(&position(), &snapshots, &expenses, range).unwrap();
+        assert_eq!((decomposition.from, decomposition.to), (at(3), at(6)));
+        let c = decomposition.components;
+        assert_eq!(c.fee_income, Decimal::from(300));
+        assert_eq!(c.gas, Decimal::ONE);
+        // Price went up: held tokens gained, the pool position gained less
+        assert!(c.price_appreciation > Decimal::ZERO);
+        assert!(c.il_delta < Decimal::ZERO);
+
+        let (lower, upper) = range_prices(-20_000, 20_000);
+        let value =
+            |price: Decimal| Decimal::from(1_000_000) * value_per_liquidity(price, lower, upper);
+        let change = value(Decimal::new(12, 1)) - value(Decimal::ONE);
+        assert!((c.price_appreciation + c.il_delta - change).abs() < Decimal::new(1, 6));
+        assert_eq!(c.net, c.fee_income + c.price_appreciation + c.il_delta - c.gas);
+        assert_eq!(decomposition.net_flows, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_liquidity_moved_in_the_window_is_a_flow() {
+        let snapshots =
+            vec![snapshot(1, Decimal::ONE, 0, 1_000_000), snapshot(2, Decimal::ONE, 0, 3_000_000)];
+        let range = TimeRange { from: at(1), to: at(2) };
+        let decomposition = decompose_pnl(&position(), &snapshots, &[], range).unwrap();
+        assert!(decomposition.net_flows > Decimal::ZERO);
+        assert_eq!(decomposition.components, PnlComponents::default());
+
+        // One snapshot in the window isn't a change
+        let range = TimeRange { from: at(2), to: at(5) };
+        assert!(decompose_pnl(&position(), &snapshots[1..], &[], range).is_none());
+    }
+}
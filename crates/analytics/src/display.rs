@@ -0,0 +1,268 @@
+use anyhow::{Context, Result, bail};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Serialize;
+use stillwater_models::Pool;
+
+use crate::utils::tick_to_price;
+
+/// How pool prices are presented to a user
+///
+/// Pool prices are raw token1-per-token0 amounts (`1.0001^tick`). Display
+/// prices are in whole tokens of `quote_token` per `base_token`: the raw price
+/// is scaled by `10^(decimals0 - decimals1)` and inverted when the user quotes
+/// in token0 (e.g. ETH/USDC for a USDC/ETH pool).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PriceDisplay {
+    pub base_token: String,
+    pub quote_token: String,
+    /// Whether prices are inverted relative to the pool's orientation
+    pub inverted: bool,
+    #[serde(skip)]
+    scale: Decimal,
+}
+
+impl PriceDisplay {
+    /// Display settings quoting prices in `quote_token`
+    ///
+    /// `quote_token` may be a token address or `token0`/`token1`. Returns `None`
+    /// if it isn't one of the pool's tokens.
+    pub fn for_pool(pool: &Pool, quote_token: &str) -> Option<Self> {
+        let inverted = if quote_token == "token0" || quote_token.eq_ignore_ascii_case(&pool.token0)
+        {
+            true
+        } else if quote_token == "token1" || quote_token.eq_ignore_ascii_case(&pool.token1) {
+            false
+        } else {
+            return None;
+        };
+
+        let scale = decimal_scale(pool.token0_decimals - pool.token1_decimals)?;
+        let (base_token, quote_token) = if inverted {
+            (pool.token1.clone(), pool.token0.clone())
+        } else {
+            (pool.token0.clone(), pool.token1.clone())
+        };
+
+        Some(Self { base_token, quote_token, inverted, scale })
+    }
+
+    /// Convert a raw pool price to a display price
+    pub fn to_display(&self, raw: Decimal) -> Decimal {
+        let adjusted = raw.checked_mul(self.scale).unwrap_or(Decimal::MAX);
+        if self.inverted { invert(adjusted) } else { adjusted }
+    }
+
+    /// Convert a display price back to a raw pool price
+    pub fn to_raw(&self, display: Decimal) -> Decimal {
+        let adjusted = if self.inverted { invert(display) } else { display };
+        adjusted.checked_div(self.scale).unwrap_or(Decimal::ZERO)
+    }
+
+    /// Display prices of a tick range as (lower, upper)
+    ///
+    /// Inversion flips the order: the upper tick becomes the lower display price.
+    pub fn range(&self, tick_lower: i32, tick_upper: i32) -> (Decimal, Decimal) {
+        let a = self.to_display(tick_to_price(tick_lower));
+        let b = self.to_display(tick_to_price(tick_upper));
+        if a <= b { (a, b) } else { (b, a) }
+    }
+}
+
+/// Significant figures shown when `DISPLAY_SIGNIFICANT_FIGURES` isn't set
+pub const DEFAULT_SIGNIFICANT_FIGURES: u32 = 10;
+
+/// How values are rounded to their displayed precision
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Ties round to the even digit (banker's rounding)
+    #[default]
+    HalfEven,
+    /// Ties round away from zero
+    HalfUp,
+    /// Truncate toward zero
+    Down,
+}
+
+impl RoundingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingMode::HalfEven => "half_even",
+            RoundingMode::HalfUp => "half_up",
+            RoundingMode::Down => "down",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "half_even" => Some(RoundingMode::HalfEven),
+            "half_up" => Some(RoundingMode::HalfUp),
+            "down" => Some(RoundingMode::Down),
+            _ => None,
+        }
+    }
+
+    fn strategy(&self) -> RoundingStrategy {
+        match self {
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::Down => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+/// Precision and currency presentation of analytics values (P&L, prices, APRs)
+///
+/// Values are rounded to `significant_figures`, but never lose integer digits:
+/// with 4 figures `1234567.8` shows as `1234568` and `0.000123456` as `0.0001235`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DisplayOptions {
+    /// `None` leaves values unrounded
+    pub significant_figures: Option<u32>,
+    pub rounding: RoundingMode,
+    /// Prefixed to amounts in text output (e.g. `$`)
+    pub currency_symbol: Option<String>,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            significant_figures: Some(DEFAULT_SIGNIFICANT_FIGURES),
+            rounding: RoundingMode::default(),
+            currency_symbol: None,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// Options from `DISPLAY_SIGNIFICANT_FIGURES` (`0` disables rounding),
+    /// `DISPLAY_ROUNDING` and `DISPLAY_CURRENCY_SYMBOL`
+    pub fn from_env() -> Result<Self> {
+        let mut options = Self::default();
+        if let Ok(figures) = std::env::var("DISPLAY_SIGNIFICANT_FIGURES") {
+            let figures: u32 = figures
+                .trim()
+                .parse()
+                .context("DISPLAY_SIGNIFICANT_FIGURES must be a non-negative integer")?;
+            options.significant_figures = (figures > 0).then_some(figures);
+        }
+        if let Ok(rounding) = std::env::var("DISPLAY_ROUNDING") {
+            let Some(rounding) = RoundingMode::parse(rounding.trim()) else {
+                bail!("DISPLAY_ROUNDING must be half_even, half_up or down");
+            };
+            options.rounding = rounding;
+        }
+        options.currency_symbol =
+            std::env::var("DISPLAY_CURRENCY_SYMBOL").ok().filter(|s| !s.trim().is_empty());
+        Ok(options)
+    }
+
+    /// Round a value to the configured precision, dropping trailing zeros
+    pub fn round(&self, value: Decimal) -> Decimal {
+        let Some(figures) = self.significant_figures else {
+            return value;
+        };
+        if value.is_zero() {
+            return Decimal::ZERO;
+        }
+        // Digits before the decimal point (<= 0 for values below 1)
+        let digits = value.mantissa().unsigned_abs().to_string().len() as i64;
+        let integer_digits = digits - i64::from(value.scale());
+        let dp = (i64::from(figures) - integer_digits).clamp(0, 28) as u32;
+        value.round_dp_with_strategy(dp, self.rounding.strategy()).normalize()
+    }
+
+    /// Rounded value as text, prefixed with the currency symbol when set (`-$12.5`)
+    pub fn format(&self, value: Decimal) -> String {
+        let rounded = self.round(value);
+        match &self.currency_symbol {
+            Some(symbol) if rounded.is_sign_negative() => format!("-{}{}", symbol, rounded.abs()),
+            Some(symbol) => format!("{}{}", symbol, rounded),
+            None => rounded.to_string(),
+        }
+    }
+}
+
+fn invert(price: Decimal) -> Decimal {
+    if price.is_zero() {
+        return Decimal::ZERO;
+    }
+    Decimal::ONE.checked_div(price).unwrap_or(Decimal::ZERO)
+}
+
+/// `10^exponent` as a Decimal, for decimal differences Decimal can represent
+pub(crate) fn decimal_scale(exponent: i16) -> Option<Decimal> {
+    let power = 10i128.checked_pow(exponent.unsigned_abs().into())?;
+    let magnitude = Decimal::try_from_i128_with_scale(power, 0).ok()?;
+    if exponent >= 0 { Some(magnitude) } else { Decimal::ONE.checked_div(magnitude) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::str::FromStr;
+
+    fn create_test_pool() -> Pool {
+        Pool {
+            token0: "0xUSDC".to_string(),
+            token1: "0xWETH".to_string(),
+            token0_decimals: 6,
+            ..testing::create_test_pool()
+        }
+    }
+
+    #[test]
+    fn test_quote_in_token0_inverts() {
+        let pool = create_test_pool();
+        // 1 USDC = 0.0005 WETH  =>  raw = 0.0005 * 10^12
+        let raw = Decimal::from_str("500000000").unwrap();
+
+        let eth_usdc = PriceDisplay::for_pool(&pool, "0xusdc").unwrap();
+        assert!(eth_usdc.inverted);
+        assert_eq!(eth_usdc.base_token, "0xWETH");
+        assert_eq!(eth_usdc.to_display(raw), Decimal::from(2000));
+        assert_eq!(eth_usdc.to_raw(Decimal::from(2000)), raw);
+
+        let usdc_eth = PriceDisplay::for_pool(&pool, "token1").unwrap();
+        assert!(!usdc_eth.inverted);
+        assert_eq!(usdc_eth.to_display(raw), Decimal::from_str("0.0005").unwrap());
+    }
+
+    #[test]
+    fn test_inverted_range_is_reordered() {
+        let pool = create_test_pool();
+        let display = PriceDisplay::for_pool(&pool, "token0").unwrap();
+
+        let (lower, upper) = display.range(-1000, 1000);
+        assert!(lower < upper);
+        assert_eq!(lower, display.to_display(tick_to_price(1000)));
+    }
+
+    #[test]
+    fn test_unknown_quote_token() {
+        assert!(PriceDisplay::for_pool(&create_test_pool(), "0xdai").is_none());
+    }
+
+    #[test]
+    fn test_display_options_round_to_significant_figures() {
+        let options = DisplayOptions {
+            significant_figures: Some(4),
+            rounding: RoundingMode::HalfUp,
+            currency_symbol: Some("$".to_string()),
+        };
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+
+        assert_eq!(options.round(d("1234567.8")), d("1234568"));
+        assert_eq!(options.round(d("0.000123456")), d("0.0001235"));
+        assert_eq!(options.round(d("12.5000")), d("12.5"));
+        assert_eq!(options.round(d("0.0030000000000000000000000001")), d("0.003"));
+        assert_eq!(options.format(d("-12.34567")), "-$12.35");
+
+        let truncating = DisplayOptions { rounding: RoundingMode::Down, ..options.clone() };
+        assert_eq!(truncating.round(d("1.99999")), d("1.999"));
+
+        let unrounded = DisplayOptions { significant_figures: None, ..options };
+        assert_eq!(unrounded.round(d("1.99999")), d("1.99999"));
+    }
+}
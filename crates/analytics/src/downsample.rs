@@ -0,0 +1,188 @@
+use alloy::primitives::{I256, U256};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use std::collections::{BTreeMap, HashMap};
+use stillwater_models::Swap;
+
+/// Transaction hash prefix of the rows standing in for a minute of swaps (`minute:<unix seconds>`)
+pub const AGGREGATE_TX_PREFIX: &str = "minute:";
+
+/// Largest swaps per day a downsampled pool keeps unless configured otherwise
+pub const DEFAULT_KEEP_LARGEST: i32 = 100;
+
+/// A row standing in for several swaps of one minute
+#[derive(Debug, Clone)]
+pub struct MinuteAggregate {
+    pub swap: Swap,
+    /// How many swaps it stands for
+    pub swap_count: i32,
+}
+
+/// What downsampling one day of a pool's swaps changes
+#[derive(Debug, Clone, Default)]
+pub struct DownsamplePlan {
+    /// Rows to delete: the swaps folded into aggregates and the aggregates they replace
+    pub removed: Vec<i64>,
+    pub aggregates: Vec<MinuteAggregate>,
+}
+
+impl DownsamplePlan {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+/// Whether a swap row is a minute aggregate rather than a swap
+pub fn is_minute_aggregate(swap: &Swap) -> bool {
+    swap.tx_hash.starts_with(AGGREGATE_TX_PREFIX)
+}
+
+/// Fold a day of a pool's swaps into per-minute rows, keeping the largest swaps as they are
+///
+/// The `keep_largest` swaps with the largest token1 amount stay; the rest are
+/// grouped by minute, together with aggregates from an earlier run (whose
+/// counts are in `counts`, by minute). A minute with a single row is left
+/// alone. Each aggregate:
+///
+/// - adds up the minute's absolute amounts, signed by its net token1 flow, so
+///   volume in either token is exact and the implied price is the minute's
+///   volume-weighted average
+/// - takes the minute's token1-volume-weighted fee, rounded (None without any)
+/// - is timestamped at the start of the minute
+/// - keeps the lowest ID of the rows it replaces, so accumulators that already
+///   folded those swaps in don't count them again
+pub fn plan_downsampling(
+    swaps: &[Swap],
+    counts: &HashMap<DateTime<Utc>, i32>,
+    keep_largest: usize,
+) -> DownsamplePlan {
+    let mut raw: Vec<&Swap> = swaps.iter().filter(|s| !is_minute_aggregate(s)).collect();
+    raw.sort_by(|a, b| {
+        b.amount1.unsigned_abs().cmp(&a.amount1.unsigned_abs()).then(a.id.cmp(&b.id))
+    });
+    let folded = raw.iter().skip(keep_largest).copied();
+    let aggregated = swaps.iter().filter(|s| is_minute_aggregate(s));
+
+    let mut minutes: BTreeMap<DateTime<Utc>, Vec<&Swap>> = BTreeMap::new();
+    for swap in folded.chain(aggregated) {
+        minutes.entry(minute_of(swap.timestamp)).or_default().push(swap);
+    }
+
+    let mut plan = DownsamplePlan::default();
+    for (minute, rows) in minutes {
+        if rows.len() < 2 {
+            continue;
+        }
+        let aggregated = rows.iter().filter(|s| is_minute_aggregate(s)).count();
+        let earlier = counts.get(&minute).copied().unwrap_or(1);
+        let swap_count = (rows.len() - aggregated) as i32 + aggregated as i32 * earlier;
+        plan.removed.extend(rows.iter().map(|s| s.id));
+        plan.aggregates.push(MinuteAggregate { swap: aggregate(minute, &rows), swap_count });
+    }
+    plan.removed.sort_unstable();
+    plan
+}
+
+fn minute_of(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    timestamp.duration_trunc(TimeDelta::minutes(1)).unwrap_or(timestamp)
+}
+
+fn aggregate(minute: DateTime<Utc>, rows: &[&Swap]) -> Swap {
+    let volume0 = rows.iter().fold(I256::ZERO, |sum, s| sum.saturating_add(s.amount0.abs()));
+    let volume1 = rows.iter().fold(I256::ZERO, |sum, s| sum.saturating_add(s.amount1.abs()));
+    let net1 = rows.iter().fold(I256::ZERO, |sum, s| sum.saturating_add(s.amount1));
+    let (amount0, amount1) =
+        if net1.is_negative() { (volume0, -volume1) } else { (-volume0, volume1) };
+
+    let (mut weighted, mut weight) = (U256::ZERO, U256::ZERO);
+    for swap in rows {
+        let Some(fee) = swap.fee.and_then(|f| u64::try_from(f).ok()) else {
+            continue;
+        };
+        let volume = swap.amount1.unsigned_abs();
+        weighted = weighted.saturating_add(volume.saturating_mul(U256::from(fee)));
+        weight = weight.saturating_add(volume);
+    }
+    let fee = (!weight.is_zero())
+        .then(|| (weighted + weight / U256::from(2u8)) / weight)
+        .and_then(|fee| i32::try_from(fee).ok());
+
+    Swap {
+        id: rows.iter().map(|s| s.id).min().unwrap_or_default(),
+        tx_hash: format!("{}{}", AGGREGATE_TX_PREFIX, minute.timestamp()),
+        pool_id: rows.first().map(|s| s.pool_id.clone()).unwrap_or_default(),
+        amount0,
+        amount1,
+        fee,
+        timestamp: minute,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap() + Duration::seconds(seconds)
+    }
+
+    fn swap(id: i64, seconds: i64, amount0: i64, amount1: i64, fee: Option<i32>) -> Swap {
+        Swap {
+            id,
+            tx_hash: format!("0x{}", id),
+            pool_id: "0xpool".to_string(),
+            amount0: I256::try_from(amount0).unwrap(),
+            amount1: I256::try_from(amount1).unwrap(),
+            fee,
+            timestamp: at(seconds),
+        }
+    }
+
+    #[test]
+    fn test_minutes_fold_into_volume_preserving_aggregates() {
+        let swaps = vec![
+            swap(1, 5, -100, 200, Some(3000)),
+            swap(2, 20, 300, -500, Some(1000)),
+            swap(3, 40, -1_000, 9_000, Some(3000)),
+            swap(4, 70, -50, 100, None),
+            swap(5, 130, -10, 20, Some(500)),
+            swap(6, 150, -10, 20, Some(500)),
+        ];
+        let plan = plan_downsampling(&swaps, &HashMap::new(), 1);
+
+        // Swap 3 is kept; swap 4 is alone in its minute
+        assert_eq!(plan.removed, vec![1, 2, 5, 6]);
+        assert_eq!(plan.aggregates.len(), 2);
+        let first = &plan.aggregates[0];
+        assert_eq!(first.swap_count, 2);
+        assert_eq!(first.swap.id, 1);
+        assert_eq!(first.swap.timestamp, at(0));
+        assert_eq!(first.swap.tx_hash, format!("minute:{}", at(0).timestamp()));
+        // Net token1 flowed out, so the aggregate sells token0 for token1 at the summed volume
+        assert_eq!(first.swap.amount0, I256::try_from(400).unwrap());
+        assert_eq!(first.swap.amount1, I256::try_from(-700).unwrap());
+        // (200 x 3000 + 500 x 1000) / 700
+        assert_eq!(first.swap.fee, Some(1571));
+        assert_eq!(plan.aggregates[1].swap.timestamp, at(120));
+        assert!(is_minute_aggregate(&plan.aggregates[1].swap));
+    }
+
+    #[test]
+    fn test_earlier_aggregates_are_merged_with_late_swaps() {
+        let mut earlier = swap(2, 0, -400, 700, Some(1571));
+        earlier.tx_hash = format!("minute:{}", at(0).timestamp());
+        let swaps = vec![earlier, swap(9, 30, -100, 300, None)];
+        let counts = HashMap::from([(at(0), 5)]);
+
+        let plan = plan_downsampling(&swaps, &counts, 0);
+        assert_eq!(plan.removed, vec![2, 9]);
+        let merged = &plan.aggregates[0];
+        assert_eq!((merged.swap.id, merged.swap_count), (2, 6));
+        assert_eq!(merged.swap.amount1, I256::try_from(1_000).unwrap());
+        // Only the earlier aggregate carried a fee
+        assert_eq!(merged.swap.fee, Some(1571));
+
+        // Nothing to fold when everything is kept
+        assert!(plan_downsampling(&swaps[1..], &counts, 10).is_empty());
+    }
+}
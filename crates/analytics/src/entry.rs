@@ -0,0 +1,129 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use stillwater_models::{LiquidityChange, PricedLiquidityEvent};
+
+use crate::utils::{price_to_tick, tick_to_price};
+
+/// Where a position built up over several deposits entered, averaged by liquidity
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EntryMetrics {
+    /// Liquidity-weighted average of the deposits' pool ticks
+    pub average_tick: i32,
+    /// Raw pool price at the average tick, the entry price for IL
+    pub price: Decimal,
+    /// Priced deposits making up the liquidity still held
+    pub deposits: usize,
+    /// Deposits still held without a recorded price, left out of the average
+    pub unpriced_deposits: usize,
+}
+
+/// Average the pool tick of the deposits behind the liquidity still held
+///
+/// `events` are the liquidity events of one owner's range, oldest first.
+/// Removals take liquidity out of every deposit pro rata, so they leave the
+/// average unchanged (as average-cost accounting does); removing everything
+/// starts over, so a range that was emptied and refilled is averaged over the
+/// refill only. Averaging ticks rather than prices weights deposits
+/// geometrically, which keeps one deposit at a far price from dominating.
+/// None when no liquidity with a recorded price is left.
+pub fn weighted_entry(events: &[PricedLiquidityEvent]) -> Option<EntryMetrics> {
+    let mut priced = 0.0_f64;
+    let mut unpriced = 0.0_f64;
+    let mut tick_weight = 0.0_f64;
+    let (mut deposits, mut unpriced_deposits) = (0, 0);
+
+    for recorded in events {
+        let liquidity: f64 =
+            recorded.event.liquidity_delta.unsigned_abs().to_string().parse().unwrap_or(0.0);
+        match (recorded.event.change(), recorded.price) {
+            (LiquidityChange::Add, Some(price)) if price > Decimal::ZERO => {
+                priced += liquidity;
+                tick_weight += liquidity * f64::from(price_to_tick(price));
+                deposits += 1;
+            }
+            (LiquidityChange::Add, _) => {
+                unpriced += liquidity;
+                unpriced_deposits += 1;
+            }
+            (LiquidityChange::Remove, _) => {
+                let held = priced + unpriced;
+                let kept = if held > liquidity { 1.0 - liquidity / held } else { 0.0 };
+                if kept == 0.0 {
+                    (priced, unpriced, tick_weight) = (0.0, 0.0, 0.0);
+                    (deposits, unpriced_deposits) = (0, 0);
+                } else {
+                    priced *= kept;
+                    unpriced *= kept;
+                    tick_weight *= kept;
+                }
+            }
+        }
+    }
+
+    if priced <= 0.0 {
+        return None;
+    }
+    let average_tick = (tick_weight / priced).round().to_i32()?;
+    Some(EntryMetrics {
+        average_tick,
+        price: tick_to_price(average_tick),
+        deposits,
+        unpriced_deposits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::I256;
+    use chrono::{TimeZone, Utc};
+    use stillwater_models::{Address, LiquidityEvent};
+
+    fn event(day: u32, delta: i64, tick: Option<i32>) -> PricedLiquidityEvent {
+        PricedLiquidityEvent {
+            event: LiquidityEvent {
+                event_id: format!("e{}", day),
+                owner: Address::ZERO,
+                pool_id: "0xpool".to_string(),
+                tick_lower: -6000,
+                tick_upper: 6000,
+                liquidity_delta: I256::try_from(delta).unwrap(),
+                timestamp: Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap(),
+            },
+            price: tick.map(tick_to_price),
+        }
+    }
+
+    #[test]
+    fn test_deposits_are_weighted_by_liquidity() {
+        let events = vec![event(1, 1_000, Some(0)), event(2, 3_000, Some(400))];
+        let entry = weighted_entry(&events).unwrap();
+        assert!((entry.average_tick - 300).abs() <= 1);
+        assert_eq!(entry.deposits, 2);
+        assert_eq!(entry.price, tick_to_price(entry.average_tick));
+
+        // Partial removals keep the average; unpriced deposits are counted, not averaged
+        let events = vec![
+            event(1, 1_000, Some(0)),
+            event(2, 3_000, Some(400)),
+            event(3, -2_000, None),
+            event(4, 500, None),
+        ];
+        let entry = weighted_entry(&events).unwrap();
+        assert!((entry.average_tick - 300).abs() <= 1);
+        assert_eq!(entry.unpriced_deposits, 1);
+    }
+
+    #[test]
+    fn test_emptied_range_starts_over() {
+        let events =
+            vec![event(1, 1_000, Some(-2000)), event(2, -1_000, None), event(3, 500, Some(1000))];
+        let entry = weighted_entry(&events).unwrap();
+        assert!((entry.average_tick - 1000).abs() <= 1);
+        assert_eq!(entry.deposits, 1);
+
+        assert_eq!(weighted_entry(&[event(1, 1_000, None)]), None);
+        assert_eq!(weighted_entry(&[]), None);
+    }
+}
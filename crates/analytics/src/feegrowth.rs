@@ -0,0 +1,243 @@
+use alloy::primitives::U256;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{Pool, PoolFeeGrowth, Position, Swap, TokenAmount};
+
+use crate::fees::{FeeModel, calculate_fees_earned_with_model};
+use crate::utils::tick_to_price;
+
+/// Relative gap between attributed fees and the fee growth bounds flagged by default
+pub const DEFAULT_FEE_GROWTH_TOLERANCE: Decimal = Decimal::from_parts(10, 0, 0, false, 2);
+
+/// How a position's attributed fees compare with its pool's fee growth
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeGrowthCheck {
+    pub position_id: i64,
+    /// First and last fee growth sample of the window compared
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Fees the pool's fee model attributed to the position over the window, raw token1
+    pub attributed: Decimal,
+    /// Fees earned over intervals the pool tick was in range at both samples
+    pub expected_min: Decimal,
+    /// Fees earned over intervals the pool tick was in range at either sample
+    pub expected_max: Decimal,
+    /// Relative distance of `attributed` beyond the nearest bound (negative: below it),
+    /// 0 between them; None when fee growth says the position earned nothing
+    pub divergence: Option<Decimal>,
+    /// Whether `attributed` is further outside the bounds than the tolerance
+    pub diverged: bool,
+}
+
+/// Token0 and token1 fees `liquidity` earns in `pool` while in range between two samples
+///
+/// Fee growth is Q128.128 fees per unit of liquidity and wraps, so the
+/// difference is taken modulo 2^256 as the pool does.
+pub fn fees_from_growth(
+    pool: &Pool,
+    liquidity: U256,
+    start: &PoolFeeGrowth,
+    end: &PoolFeeGrowth,
+) -> (TokenAmount, TokenAmount) {
+    let earned = |from: U256, to: U256| -> U256 {
+        let growth = to.wrapping_sub(from);
+        let (high, low): (U256, U256) = (growth >> 128usize, growth & U256::from(u128::MAX));
+        high.saturating_mul(liquidity).saturating_add(low.saturating_mul(liquidity) >> 128usize)
+    };
+    (
+        pool.amount0(earned(start.fee_growth_global0, end.fee_growth_global0)),
+        pool.amount1(earned(start.fee_growth_global1, end.fee_growth_global1)),
+    )
+}
+
+/// Compare the fees attributed to a position with what its pool's fee growth implies
+///
+/// `samples` are the pool's fee growth samples, oldest first, and `swaps`
+/// its swaps over them. Only samples while the position existed count. An
+/// in-range position earns `liquidity × Δ fee growth` between two samples;
+/// samples only show the tick at their ends, so intervals in range at both
+/// ends bound the fees from below and those in range at either end from
+/// above. Token0 fees are valued at each interval's closing tick. Uses the
+/// position's current liquidity throughout. None with fewer than two samples.
+pub fn reconcile_fee_growth(
+    position: &Position,
+    pool: &Pool,
+    samples: &[PoolFeeGrowth],
+    swaps: &[Swap],
+    model: &dyn FeeModel,
+    tolerance: Decimal,
+) -> Option<FeeGrowthCheck> {
+    let window: Vec<&PoolFeeGrowth> = samples
+        .iter()
+        .filter(|s| s.sampled_at >= position.created_at)
+        .filter(|s| position.closed_at.is_none_or(|closed| s.sampled_at <= closed))
+        .collect();
+    let (first, last) = (*window.first()?, *window.last()?);
+    if window.len() < 2 {
+        return None;
+    }
+
+    let in_range = |tick: i32| position.tick_lower <= tick && tick < position.tick_upper;
+    let (mut expected_min, mut expected_max) = (Decimal::ZERO, Decimal::ZERO);
+    for pair in window.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let (fees0, fees1) = fees_from_growth(pool, position.liquidity, start, end);
+        let value = fees0.raw_decimal() * tick_to_price(end.tick) + fees1.raw_decimal();
+        match (in_range(start.tick), in_range(end.tick)) {
+            (true, true) => {
+                expected_min += value;
+                expected_max += value;
+            }
+            (true, false) | (false, true) => expected_max += value,
+            (false, false) => {}
+        }
+    }
+
+    let window_swaps: Vec<Swap> = swaps
+        .iter()
+        .filter(|s| s.timestamp > first.sampled_at && s.timestamp <= last.sampled_at)
+        .cloned()
+        .collect();
+    let attributed = calculate_fees_earned_with_model(position, pool, &window_swaps, model);
+
+    let (divergence, diverged) = if attributed > expected_max {
+        let divergence =
+            (expected_max > Decimal::ZERO).then(|| (attributed - expected_max) / expected_max);
+        (divergence, attributed > expected_max * (Decimal::ONE + tolerance))
+    } else if attributed < expected_min {
+        let divergence = (attributed - expected_min) / expected_min;
+        (Some(divergence), attributed < expected_min * (Decimal::ONE - tolerance))
+    } else {
+        (Some(Decimal::ZERO), false)
+    };
+
+    Some(FeeGrowthCheck {
+        position_id: position.id,
+        from: first.sampled_at,
+        to: last.sampled_at,
+        attributed,
+        expected_min,
+        expected_max,
+        divergence,
+        diverged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::StaticFeeModel;
+    use alloy::primitives::I256;
+    use chrono::{Duration, TimeZone};
+    use stillwater_models::{Address, NO_HOOKS};
+
+    fn at(hour: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap() + Duration::hours(hour)
+    }
+
+    fn sample(hour: i64, growth1: U256, tick: i32) -> PoolFeeGrowth {
+        PoolFeeGrowth {
+            pool_id: "0xpool".to_string(),
+            sampled_at: at(hour),
+            fee_growth_global0: U256::ZERO,
+            fee_growth_global1: growth1,
+            tick,
+        }
+    }
+
+    fn pool() -> Pool {
+        Pool {
+            pool_id: "0xpool".to_string(),
+            token0: "0xtoken0".to_string(),
+            token1: "0xtoken1".to_string(),
+            token0_decimals: 18,
+            token1_decimals: 18,
+            fee_tier: 3000,
+            tick_spacing: 60,
+            hooks: NO_HOOKS.to_string(),
+            protocol_fee: 0,
+            created_at: None,
+            created_at_block: None,
+            fee_override: None,
+        }
+    }
+
+    fn position() -> Position {
+        Position {
+            id: 1,
+            nft_id: "1".to_string(),
+            owner: Address::ZERO,
+            pool_id: "0xpool".to_string(),
+            tick_lower: -600,
+            tick_upper: 600,
+            liquidity: U256::from(1_000u64),
+            created_at: at(0),
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        }
+    }
+
+    fn swap(hour: i64, amount: i64) -> Swap {
+        Swap {
+            id: hour,
+            tx_hash: format!("0x{}", hour),
+            pool_id: "0xpool".to_string(),
+            amount0: I256::try_from(-amount).unwrap(),
+            amount1: I256::try_from(amount).unwrap(),
+            fee: None,
+            timestamp: at(hour),
+        }
+    }
+
+    #[test]
+    fn test_fees_from_growth_scale_with_liquidity_and_wrap() {
+        let q128 = U256::from(1u8) << 128;
+        let start = sample(0, q128 * U256::from(2u8), 0);
+        let end = sample(1, q128 * U256::from(7u8), 0);
+        let (_, fees1) = fees_from_growth(&pool(), U256::from(3u8), &start, &end);
+        assert_eq!(fees1.raw, U256::from(15u8));
+
+        // Growth that wrapped past 2^256 still differences correctly
+        let start = sample(0, U256::MAX - q128 + U256::from(1u8), 0);
+        let end = sample(1, q128 * U256::from(4u8), 0);
+        let (_, fees1) = fees_from_growth(&pool(), U256::from(2u8), &start, &end);
+        assert_eq!(fees1.raw, U256::from(10u8));
+    }
+
+    #[test]
+    fn test_attributed_fees_are_checked_against_growth_bounds() {
+        let swaps = vec![swap(1, 1_000_000), swap(2, 1_000_000)];
+        // 2 swaps x 2,000,000 volume x 0.3% x 1% share = 120 raw token1
+        let q128 = U256::from(1u8) << 128;
+        let per_liquidity = |fees: u64| q128 * U256::from(fees) / U256::from(1_000u64);
+        let model = StaticFeeModel;
+        let tolerance = DEFAULT_FEE_GROWTH_TOLERANCE;
+
+        let samples = vec![sample(0, U256::ZERO, 0), sample(3, per_liquidity(125), 0)];
+        let check = reconcile_fee_growth(&position(), &pool(), &samples, &swaps, &model, tolerance)
+            .unwrap();
+        assert_eq!(check.attributed, Decimal::from(120));
+        assert!(!check.diverged);
+        assert!(check.divergence.unwrap() < Decimal::ZERO);
+
+        // Out of range at one end only loosens the lower bound
+        let samples = vec![sample(0, U256::ZERO, 0), sample(3, per_liquidity(500), 900)];
+        let check = reconcile_fee_growth(&position(), &pool(), &samples, &swaps, &model, tolerance)
+            .unwrap();
+        assert_eq!(check.expected_min, Decimal::ZERO);
+        assert!(!check.diverged);
+
+        let samples = vec![sample(0, U256::ZERO, 0), sample(3, per_liquidity(50), 0)];
+        let check = reconcile_fee_growth(&position(), &pool(), &samples, &swaps, &model, tolerance)
+            .unwrap();
+        assert!(check.diverged);
+        assert!(check.divergence.unwrap() > Decimal::ONE);
+
+        assert!(
+            reconcile_fee_growth(&position(), &pool(), &samples[..1], &swaps, &model, tolerance)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use stillwater_models::{Pool, Position, Swap};
+
+use crate::hooks::HookRegistry;
+use crate::pnl::swap_volume;
+
+/// Fee units per 1.0 (v4 fees are expressed in hundredths of a bip)
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+/// Highest LP fee a pool can charge, in hundredths of a bip (100%)
+pub const MAX_LP_FEE: i32 = FEE_DENOMINATOR as i32;
+
+/// Share of the pool's LP fees attributed to a position, pending per-swap liquidity data
+pub(crate) const ESTIMATED_POSITION_SHARE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// Convert a v4 fee in hundredths of a bip to a rate (3000 -> 0.003)
+pub fn fee_to_rate(fee: i32) -> Decimal {
+    Decimal::from(fee.max(0)) / Decimal::from(FEE_DENOMINATOR)
+}
+
+/// Share of a swap's LP fee taken by the protocol, as a fraction
+///
+/// v4 sets a protocol fee per swap direction. A positive `amount0` means
+/// token0 went into the pool, i.e. a zeroForOne swap.
+pub fn protocol_fee_share(pool: &Pool, swap: &Swap) -> Decimal {
+    let zero_for_one = swap.amount0.is_positive();
+    fee_to_rate(pool.protocol_fee_for(zero_for_one))
+}
+
+/// Fee rate that reaches LPs after the protocol's cut
+pub fn lp_fee_rate(model: &dyn FeeModel, pool: &Pool, swap: &Swap) -> Decimal {
+    model.fee_rate(pool, swap) * (Decimal::ONE - protocol_fee_share(pool, swap))
+}
+
+/// Determines the LP fee rate charged on a swap
+///
+/// v4 hooks can implement arbitrary fee logic, so the rate is resolved per
+/// swap rather than assumed from the pool's fee tier.
+pub trait FeeModel: Send + Sync {
+    /// Short identifier for reporting
+    fn name(&self) -> &'static str;
+
+    /// Fee rate (as a fraction) charged on `swap` in `pool`
+    fn fee_rate(&self, pool: &Pool, swap: &Swap) -> Decimal;
+}
+
+/// Fixed fee from the pool's fee tier, or its fee override when set
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticFeeModel;
+
+impl FeeModel for StaticFeeModel {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn fee_rate(&self, pool: &Pool, _swap: &Swap) -> Decimal {
+        fee_to_rate(pool.fee_override.unwrap_or(pool.fee_tier))
+    }
+}
+
+/// Fee set by the hook per swap
+///
+/// Uses the fee recorded on each swap; swaps without one fall back to the
+/// pool's fee override, then to `fallback_rate` (e.g. the hook's typical or
+/// last observed fee).
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicFeeModel {
+    pub fallback_rate: Decimal,
+}
+
+impl Default for DynamicFeeModel {
+    fn default() -> Self {
+        Self { fallback_rate: Decimal::from_str("0.003").unwrap() }
+    }
+}
+
+impl FeeModel for DynamicFeeModel {
+    fn name(&self) -> &'static str {
+        "dynamic"
+    }
+
+    fn fee_rate(&self, pool: &Pool, swap: &Swap) -> Decimal {
+        swap.fee.or(pool.fee_override).map(fee_to_rate).unwrap_or(self.fallback_rate)
+    }
+}
+
+/// Maps hook addresses to fee models
+///
+/// Pools with a registered hook use that hook's model. Other pools use the
+/// dynamic model if flagged as dynamic-fee, otherwise the static model.
+#[derive(Clone)]
+pub struct FeeModelRegistry {
+    hooks: HashMap<String, Arc<dyn FeeModel>>,
+    static_model: Arc<dyn FeeModel>,
+    dynamic_model: Arc<dyn FeeModel>,
+}
+
+impl Default for FeeModelRegistry {
+    fn default() -> Self {
+        Self {
+            hooks: HashMap::new(),
+            static_model: Arc::new(StaticFeeModel),
+            dynamic_model: Arc::new(DynamicFeeModel::default()),
+        }
+    }
+}
+
+impl FeeModelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the dynamic model registered for every hook in
+    /// `DYNAMIC_FEE_HOOKS` (comma-separated addresses) and every `dynamic_fee`
+    /// hook in `KNOWN_HOOKS` (see `HookRegistry::from_env`)
+    pub fn from_env() -> Self {
+        let mut registry = Self::new();
+        for hook in std::env::var("DYNAMIC_FEE_HOOKS").unwrap_or_default().split(',') {
+            let hook = hook.trim();
+            if !hook.is_empty() {
+                registry.register(hook, Arc::new(DynamicFeeModel::default()));
+            }
+        }
+        if let Ok(hooks) = HookRegistry::from_env() {
+            hooks.register_fee_models(&mut registry);
+        }
+        registry
+    }
+
+    /// Register the fee model for a hook contract
+    pub fn register(&mut self, hook_address: &str, model: Arc<dyn FeeModel>) {
+        self.hooks.insert(hook_address.to_lowercase(), model);
+    }
+
+    /// Override the model used for unregistered dynamic-fee pools
+    pub fn with_dynamic_default(mut self, model: Arc<dyn FeeModel>) -> Self {
+        self.dynamic_model = model;
+        self
+    }
+
+    /// Fee model for a pool
+    pub fn model_for(&self, pool: &Pool) -> &dyn FeeModel {
+        if let Some(model) = self.hooks.get(&pool.hooks.to_lowercase()) {
+            return model.as_ref();
+        }
+        if pool.is_dynamic_fee() {
+            return self.dynamic_model.as_ref();
+        }
+        self.static_model.as_ref()
+    }
+}
+
+/// Calculate fees earned from swaps using a pool's fee model
+///
+/// Same volume and pool-share approximations as `calculate_fees_earned`, but
+/// each swap is charged the rate the model resolves for it, less the pool's
+/// protocol fee.
+pub fn calculate_fees_earned_with_model(
+    _position: &Position,
+    pool: &Pool,
+    swaps: &[Swap],
+    model: &dyn FeeModel,
+) -> Decimal {
+    let total_fees: Decimal =
+        swaps.iter().map(|swap| swap_volume(swap) * lp_fee_rate(model, pool, swap)).sum();
+
+    total_fees * ESTIMATED_POSITION_SHARE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, create_test_position};
+    use chrono::Utc;
+    use stillwater_models::{DYNAMIC_FEE_FLAG, NO_HOOKS};
+
+    fn create_test_pool(fee_tier: i32, hooks: &str) -> Pool {
+        Pool { fee_tier, hooks: hooks.to_string(), ..testing::create_test_pool() }
+    }
+
+    fn create_test_swap(amount: i64, fee: Option<i32>) -> Swap {
+        Swap { fee, ..testing::create_test_swap(1, amount, -amount, Utc::now()) }
+    }
+
+    struct ZeroFeeModel;
+
+    impl FeeModel for ZeroFeeModel {
+        fn name(&self) -> &'static str {
+            "zero"
+        }
+
+        fn fee_rate(&self, _pool: &Pool, _swap: &Swap) -> Decimal {
+            Decimal::ZERO
+        }
+    }
+
+    #[test]
+    fn test_fee_to_rate() {
+        assert_eq!(fee_to_rate(3000), Decimal::from_str("0.003").unwrap());
+        assert_eq!(fee_to_rate(500), Decimal::from_str("0.0005").unwrap());
+    }
+
+    #[test]
+    fn test_registry_selects_model() {
+        let mut registry = FeeModelRegistry::new();
+        registry.register("0xABC", Arc::new(ZeroFeeModel));
+
+        assert_eq!(registry.model_for(&create_test_pool(3000, NO_HOOKS)).name(), "static");
+        assert_eq!(
+            registry.model_for(&create_test_pool(DYNAMIC_FEE_FLAG, "0xdef")).name(),
+            "dynamic"
+        );
+        assert_eq!(registry.model_for(&create_test_pool(3000, "0xabc")).name(), "zero");
+    }
+
+    #[test]
+    fn test_dynamic_model_uses_swap_fee() {
+        let pool = create_test_pool(DYNAMIC_FEE_FLAG, "0xdef");
+        let model = DynamicFeeModel::default();
+
+        assert_eq!(
+            model.fee_rate(&pool, &create_test_swap(100, Some(10_000))),
+            Decimal::from_str("0.01").unwrap()
+        );
+        assert_eq!(model.fee_rate(&pool, &create_test_swap(100, None)), model.fallback_rate);
+    }
+
+    #[test]
+    fn test_fee_override_replaces_unresolved_fee() {
+        let mut pool = create_test_pool(DYNAMIC_FEE_FLAG, "0xdef");
+        pool.fee_override = Some(500);
+        let model = DynamicFeeModel::default();
+
+        // Recorded swap fees still win; the override replaces the 0.3% fallback
+        assert_eq!(model.fee_rate(&pool, &create_test_swap(100, Some(10_000))), fee_to_rate(10_000));
+        assert_eq!(model.fee_rate(&pool, &create_test_swap(100, None)), fee_to_rate(500));
+
+        let mut pool = create_test_pool(3000, NO_HOOKS);
+        pool.fee_override = Some(100);
+        assert_eq!(StaticFeeModel.fee_rate(&pool, &create_test_swap(100, None)), fee_to_rate(100));
+    }
+
+    #[test]
+    fn test_calculate_fees_with_model() {
+        let position = create_test_position();
+        let swaps = vec![create_test_swap(1000, None)];
+
+        let static_fees = calculate_fees_earned_with_model(
+            &position,
+            &create_test_pool(500, NO_HOOKS),
+            &swaps,
+            &StaticFeeModel,
+        );
+        // 2000 volume * 0.0005 * 1% share
+        assert_eq!(static_fees, Decimal::from_str("0.01").unwrap());
+
+        let zero_fees = calculate_fees_earned_with_model(
+            &position,
+            &create_test_pool(500, NO_HOOKS),
+            &swaps,
+            &ZeroFeeModel,
+        );
+        assert_eq!(zero_fees, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_protocol_fee_reduces_lp_fees() {
+        // 0.1% (1000) protocol fee on zeroForOne swaps, none on oneForZero
+        let mut pool = create_test_pool(3000, NO_HOOKS);
+        pool.protocol_fee = 1000;
+
+        let zero_for_one = create_test_swap(1000, None);
+        let one_for_zero = create_test_swap(-1000, None);
+        assert_eq!(protocol_fee_share(&pool, &zero_for_one), Decimal::from_str("0.001").unwrap());
+        assert_eq!(protocol_fee_share(&pool, &one_for_zero), Decimal::ZERO);
+        assert_eq!(
+            lp_fee_rate(&StaticFeeModel, &pool, &zero_for_one),
+            Decimal::from_str("0.002997").unwrap()
+        );
+
+        pool.protocol_fee = 500 << 12;
+        assert_eq!(pool.protocol_fee_for(false), 500);
+        assert_eq!(lp_fee_rate(&StaticFeeModel, &pool, &zero_for_one), fee_to_rate(3000));
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use stillwater_models::Swap;
+
+use crate::utils::{TickRange, price_to_tick};
+
+/// Days of swap history forecasts are fitted to by default
+pub const FORECAST_LOOKBACK_DAYS: i64 = 28;
+
+/// Weekly seasonality is only fitted with at least this many days of history
+pub const MIN_SEASONAL_HISTORY_DAYS: usize = 14;
+
+/// Label attached to every forecast, so no client mistakes it for realized figures
+pub const PROJECTION_DISCLAIMER: &str =
+    "Projection from past volume; actual volume and fees will differ";
+
+/// Weight of the newest day in the exponentially weighted moving average
+pub fn default_ewma_alpha() -> Decimal {
+    Decimal::new(3, 1)
+}
+
+/// How a forecast was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastMethod {
+    /// Flat EWMA level
+    Ewma,
+    /// EWMA level of deseasonalized volume, scaled by day-of-week factors
+    SeasonalEwma,
+}
+
+/// Absolute token1 swap volume of one UTC day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyVolume {
+    pub date: NaiveDate,
+    pub volume: Decimal,
+}
+
+/// Projected daily volume of a pool
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeForecast {
+    /// Always true; forecasts are projections, not measurements
+    pub projection: bool,
+    pub disclaimer: &'static str,
+    pub method: ForecastMethod,
+    pub history_days: usize,
+    /// Smoothed daily volume at the end of the history (deseasonalized for `seasonal_ewma`)
+    pub level: Decimal,
+    pub daily: Vec<DailyVolume>,
+    /// Mean of the projected days
+    pub projected_daily_volume: Decimal,
+}
+
+/// Absolute token1 volume per UTC day in [from, to), including days without swaps
+pub fn daily_volumes(swaps: &[Swap], from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DailyVolume> {
+    if to <= from {
+        return Vec::new();
+    }
+    let first = from.date_naive();
+    let last = (to - Duration::nanoseconds(1)).date_naive();
+    let mut days: Vec<DailyVolume> = first
+        .iter_days()
+        .take_while(|d| *d <= last)
+        .map(|date| DailyVolume { date, volume: Decimal::ZERO })
+        .collect();
+
+    for swap in swaps.iter().filter(|s| s.timestamp >= from && s.timestamp < to) {
+        let index = (swap.timestamp.date_naive() - first).num_days() as usize;
+        if let (Some(day), Ok(amount1)) =
+            (days.get_mut(index), Decimal::from_str(&swap.amount1.unsigned_abs().to_string()))
+        {
+            day.volume += amount1;
+        }
+    }
+    days
+}
+
+/// Forecast the next `horizon_days` of volume from daily history (oldest first)
+///
+/// With two weeks or more of history, each weekday's factor is its mean
+/// volume over the overall mean, and the EWMA runs on volume divided by those
+/// factors. Returns None without history.
+pub fn forecast_volume(
+    history: &[DailyVolume],
+    horizon_days: usize,
+    alpha: Decimal,
+) -> Option<VolumeForecast> {
+    let last = history.last()?;
+    let alpha = alpha.clamp(Decimal::ZERO, Decimal::ONE);
+
+    let factors = weekday_factors(history);
+    let factor = |date: NaiveDate| {
+        factors.map(|f| f[date.weekday().num_days_from_monday() as usize]).unwrap_or(Decimal::ONE)
+    };
+
+    let mut level: Option<Decimal> = None;
+    for day in history {
+        let f = factor(day.date);
+        let adjusted = if f.is_zero() { Decimal::ZERO } else { day.volume / f };
+        level = Some(match level {
+            Some(previous) => alpha * adjusted + (Decimal::ONE - alpha) * previous,
+            None => adjusted,
+        });
+    }
+    let level = level.unwrap_or_default();
+
+    let daily: Vec<DailyVolume> = (1..=horizon_days as i64)
+        .map(|i| {
+            let date = last.date + Duration::days(i);
+            DailyVolume { date, volume: level * factor(date) }
+        })
+        .collect();
+    let projected_daily_volume = if daily.is_empty() {
+        level
+    } else {
+        daily.iter().map(|d| d.volume).sum::<Decimal>() / Decimal::from(daily.len())
+    };
+
+    Some(VolumeForecast {
+        projection: true,
+        disclaimer: PROJECTION_DISCLAIMER,
+        method: if factors.is_some() { ForecastMethod::SeasonalEwma } else { ForecastMethod::Ewma },
+        history_days: history.len(),
+        level,
+        daily,
+        projected_daily_volume,
+    })
+}
+
+/// Mean volume per weekday (Monday first) over the overall mean
+fn weekday_factors(history: &[DailyVolume]) -> Option<[Decimal; 7]> {
+    if history.len() < MIN_SEASONAL_HISTORY_DAYS {
+        return None;
+    }
+    let overall = history.iter().map(|d| d.volume).sum::<Decimal>() / Decimal::from(history.len());
+    if overall.is_zero() {
+        return None;
+    }
+
+    let mut sums = [Decimal::ZERO; 7];
+    let mut counts = [0u32; 7];
+    for day in history {
+        let i = day.date.weekday().num_days_from_monday() as usize;
+        sums[i] += day.volume;
+        counts[i] += 1;
+    }
+    let mut factors = [Decimal::ONE; 7];
+    for i in 0..7 {
+        if counts[i] > 0 {
+            factors[i] = sums[i] / Decimal::from(counts[i]) / overall;
+        }
+    }
+    Some(factors)
+}
+
+/// Share of swap volume that executed inside a range, None without volume
+///
+/// Execution ticks come from `|amount1| / |amount0|`, as in the heatmap.
+pub fn in_range_volume_share(swaps: &[Swap], range: TickRange) -> Option<Decimal> {
+    let mut total = Decimal::ZERO;
+    let mut inside = Decimal::ZERO;
+    for swap in swaps {
+        let amount0 = Decimal::from_str(&swap.amount0.unsigned_abs().to_string()).ok();
+        let amount1 = Decimal::from_str(&swap.amount1.unsigned_abs().to_string()).ok();
+        let (Some(amount0), Some(amount1)) = (amount0, amount1) else {
+            continue;
+        };
+        let Some(price) = amount1.checked_div(amount0).filter(|p| !p.is_zero()) else {
+            continue;
+        };
+        total += amount1;
+        if range.contains(price_to_tick(price)) {
+            inside += amount1;
+        }
+    }
+    inside.checked_div(total)
+}
+
+/// Fee APR (as a fraction) a position would earn at a projected daily volume
+///
+/// `pool_share` is the position's share of in-range liquidity and
+/// `in_range_share` the share of volume expected inside its range.
+pub fn projected_fee_apr(
+    projected_daily_volume: Decimal,
+    fee_rate: Decimal,
+    pool_share: Decimal,
+    in_range_share: Decimal,
+    capital: Decimal,
+) -> Option<Decimal> {
+    if capital <= Decimal::ZERO {
+        return None;
+    }
+    let daily_fees = projected_daily_volume * fee_rate * pool_share * in_range_share;
+    (daily_fees * Decimal::from(365)).checked_div(capital)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_history(volumes: &[i64]) -> Vec<DailyVolume> {
+        // 2024-01-01 is a Monday
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        volumes
+            .iter()
+            .enumerate()
+            .map(|(i, v)| DailyVolume {
+                date: start + Duration::days(i as i64),
+                volume: Decimal::from(*v),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flat_ewma_with_short_history() {
+        let history = create_test_history(&[100, 100, 200]);
+
+        let forecast = forecast_volume(&history, 3, Decimal::new(5, 1)).unwrap();
+
+        assert_eq!(forecast.method, ForecastMethod::Ewma);
+        assert!(forecast.projection);
+        // 100 -> 100 -> 0.5 * 200 + 0.5 * 100
+        assert_eq!(forecast.level, Decimal::from(150));
+        assert_eq!(forecast.daily.len(), 3);
+        assert_eq!(forecast.projected_daily_volume, Decimal::from(150));
+    }
+
+    #[test]
+    fn test_seasonal_forecast_follows_weekdays() {
+        // Weekdays trade 120, weekends 50
+        let week = [120, 120, 120, 120, 120, 50, 50];
+        let volumes: Vec<i64> = week.iter().cycle().take(28).copied().collect();
+        let history = create_test_history(&volumes);
+
+        let forecast = forecast_volume(&history, 7, default_ewma_alpha()).unwrap();
+
+        assert_eq!(forecast.method, ForecastMethod::SeasonalEwma);
+        // The forecast starts on a Monday
+        assert_eq!(forecast.daily[0].volume.round(), Decimal::from(120));
+        assert_eq!(forecast.daily[5].volume.round(), Decimal::from(50));
+        assert_eq!(forecast.projected_daily_volume.round(), Decimal::from(100));
+    }
+
+    #[test]
+    fn test_projected_fee_apr() {
+        // 1000/day × 0.3% × 10% share × all in range = 0.3/day, 109.5/yr on 1000
+        let apr = projected_fee_apr(
+            Decimal::from(1000),
+            Decimal::new(3, 3),
+            Decimal::new(1, 1),
+            Decimal::ONE,
+            Decimal::from(1000),
+        );
+        assert_eq!(apr, Some(Decimal::new(1095, 4)));
+        assert_eq!(
+            projected_fee_apr(
+                Decimal::ONE,
+                Decimal::ONE,
+                Decimal::ONE,
+                Decimal::ONE,
+                Decimal::ZERO
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use alloy::primitives::U256;
+use anyhow::{Context, Result, anyhow};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{GasPriceSample, TokenAmount, TransactionFees};
+
+use crate::backtest::MarketPoint;
+
+/// Chain assumed when neither `GAS_ACCOUNTING` nor `CHAIN_ID` is set (Unichain Sepolia)
+const DEFAULT_CHAIN_ID: u64 = 1301;
+
+/// OP-stack chains: Optimism, Base, Unichain, Zora and their testnets
+const OP_STACK_CHAIN_IDS: &[u64] = &[10, 11155420, 8453, 84532, 130, 1301, 7777777, 999999999];
+
+/// Arbitrum One, Nova and Sepolia
+const ARBITRUM_CHAIN_IDS: &[u64] = &[42161, 42170, 421614];
+
+/// How a chain charges for a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasAccounting {
+    /// gasUsed × effectiveGasPrice
+    Standard,
+    /// L2 execution plus the receipt's `l1Fee` for posting data to L1
+    OpStack,
+    /// gasUsed × effectiveGasPrice, where gasUsed already includes `gasUsedForL1`
+    Arbitrum,
+}
+
+impl GasAccounting {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GasAccounting::Standard => "standard",
+            GasAccounting::OpStack => "op_stack",
+            GasAccounting::Arbitrum => "arbitrum",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "standard" => Some(GasAccounting::Standard),
+            "op_stack" => Some(GasAccounting::OpStack),
+            "arbitrum" => Some(GasAccounting::Arbitrum),
+            _ => None,
+        }
+    }
+
+    /// Accounting for a known chain, standard for any other
+    pub fn for_chain(chain_id: u64) -> Self {
+        if OP_STACK_CHAIN_IDS.contains(&chain_id) {
+            GasAccounting::OpStack
+        } else if ARBITRUM_CHAIN_IDS.contains(&chain_id) {
+            GasAccounting::Arbitrum
+        } else {
+            GasAccounting::Standard
+        }
+    }
+
+    /// `GAS_ACCOUNTING` if set, otherwise the accounting of `CHAIN_ID`
+    pub fn from_env() -> Result<Self> {
+        if let Ok(accounting) = std::env::var("GAS_ACCOUNTING") {
+            return Self::parse(accounting.trim()).ok_or_else(|| {
+                anyhow!("GAS_ACCOUNTING must be one of: standard, op_stack, arbitrum")
+            });
+        }
+        let chain_id = match std::env::var("CHAIN_ID") {
+            Ok(id) => id.trim().parse().context("CHAIN_ID must be a number")?,
+            Err(_) => DEFAULT_CHAIN_ID,
+        };
+        Ok(Self::for_chain(chain_id))
+    }
+}
+
+/// What a transaction cost, in the chain's native token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GasCost {
+    /// L2 (or L1) execution
+    pub execution: Decimal,
+    /// Data posted to L1; zero on L1 chains
+    pub l1_data: Decimal,
+    pub total: Decimal,
+}
+
+/// Cost of a transaction from its receipt under a chain's accounting
+///
+/// OP-stack receipts charge `l1Fee` on top of gasUsed × effectiveGasPrice.
+/// Arbitrum folds the L1 component into gasUsed (`gasUsedForL1` of it), so
+/// the total is unchanged and only the split differs. Receipts missing the
+/// L2 fields count no L1 data fee. None if the cost overflows.
+pub fn transaction_gas_cost(fees: &TransactionFees, accounting: GasAccounting) -> Option<GasCost> {
+    let gas_wei = fees.gas_used.checked_mul(fees.effective_gas_price)?;
+    let (execution_wei, l1_wei) = match accounting {
+        GasAccounting::Standard => (gas_wei, 0),
+        GasAccounting::OpStack => (gas_wei, fees.l1_fee.unwrap_or(0)),
+        GasAccounting::Arbitrum => {
+            let l1_gas = fees.gas_used_for_l1.unwrap_or(0).min(fees.gas_used);
+            let l1_wei = l1_gas.checked_mul(fees.effective_gas_price)?;
+            (gas_wei - l1_wei, l1_wei)
+        }
+    };
+
+    let execution = wei_to_native(execution_wei)?;
+    let l1_data = wei_to_native(l1_wei)?;
+    Some(GasCost { execution, l1_data, total: execution.checked_add(l1_data)? })
+}
+
+/// Wei as a decimal amount of the native token (18 decimals)
+fn wei_to_native(wei: u128) -> Option<Decimal> {
+    Some(TokenAmount::native(U256::from(wei)).to_decimal()).filter(|native| *native != Decimal::MAX)
+}
+
+/// Price each market point's gas from recorded gas prices
+///
+/// `gas_cost_per_tx` is what a transaction costs at `current_gas_price` (wei
+/// per gas); each point pays it scaled by the gas price of the last of
+/// `samples` (oldest first) at or before it. Points before the first sample
+/// keep the flat cost. Returns how many points were priced, zero when the
+/// current price is unknown.
+pub fn apply_gas_price_history(
+    points: &mut [MarketPoint],
+    gas_cost_per_tx: Decimal,
+    samples: &[GasPriceSample],
+    current_gas_price: Decimal,
+) -> usize {
+    if current_gas_price <= Decimal::ZERO {
+        return 0;
+    }
+
+    let mut samples = samples.iter().filter_map(|s| Some((s.timestamp, s.gas_price()?))).peekable();
+    let mut price = None;
+    let mut priced = 0;
+    for point in points {
+        while let Some((_, p)) = samples.next_if(|(t, _)| *t <= point.timestamp) {
+            price = Some(p);
+        }
+        if let Some(price) = price {
+            point.gas_cost_per_tx = Some(gas_cost_per_tx * price / current_gas_price);
+            priced += 1;
+        }
+    }
+    priced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+
+    fn create_test_fees() -> TransactionFees {
+        TransactionFees {
+            gas_used: 200_000,
+            effective_gas_price: 1_000_000_000, // 1 gwei
+            l1_fee: Some(50_000_000_000_000),   // 0.00005
+            gas_used_for_l1: Some(50_000),
+        }
+    }
+
+    #[test]
+    fn test_op_stack_adds_l1_data_fee() {
+        let fees = create_test_fees();
+
+        let standard = transaction_gas_cost(&fees, GasAccounting::Standard).unwrap();
+        assert_eq!(standard.total, Decimal::new(2, 4));
+        assert_eq!(standard.l1_data, Decimal::ZERO);
+
+        let op = transaction_gas_cost(&fees, GasAccounting::OpStack).unwrap();
+        assert_eq!(op.execution, Decimal::new(2, 4));
+        assert_eq!(op.l1_data, Decimal::new(5, 5));
+        assert_eq!(op.total, Decimal::new(25, 5));
+    }
+
+    #[test]
+    fn test_arbitrum_splits_gas_used() {
+        let cost = transaction_gas_cost(&create_test_fees(), GasAccounting::Arbitrum).unwrap();
+
+        // gasUsed already covers L1 calldata, so the total matches standard accounting
+        assert_eq!(cost.total, Decimal::new(2, 4));
+        assert_eq!(cost.l1_data, Decimal::new(5, 5));
+        assert_eq!(cost.execution, Decimal::new(15, 5));
+    }
+
+    #[test]
+    fn test_accounting_by_chain() {
+        assert_eq!(GasAccounting::for_chain(8453), GasAccounting::OpStack);
+        assert_eq!(GasAccounting::for_chain(42161), GasAccounting::Arbitrum);
+        assert_eq!(GasAccounting::for_chain(1), GasAccounting::Standard);
+        assert_eq!(GasAccounting::parse("op_stack"), Some(GasAccounting::OpStack));
+    }
+
+    #[test]
+    fn test_gas_history_scales_flat_cost() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut points: Vec<MarketPoint> = (0..4)
+            .map(|h| MarketPoint {
+                timestamp: start + Duration::hours(h),
+                price: Decimal::ONE,
+                volume: Decimal::ZERO,
+                active_liquidity: Decimal::ZERO,
+                gas_cost_per_tx: None,
+            })
+            .collect();
+        let sample = |hours: i64, base_fee: i64, tip: Option<i64>| GasPriceSample {
+            chain_id: 1,
+            block_number: hours,
+            timestamp: start + Duration::minutes(hours * 60 + 30),
+            base_fee_per_gas: Some(Decimal::from(base_fee)),
+            priority_fee_per_gas: tip.map(Decimal::from),
+        };
+        // 20 gwei, then 5 gwei, against 10 gwei today
+        let samples = [sample(0, 19, Some(1)), sample(2, 5, None)];
+
+        let priced = apply_gas_price_history(&mut points, Decimal::TEN, &samples, Decimal::TEN);
+        assert_eq!(priced, 3);
+        assert_eq!(points[0].gas_cost(Decimal::TEN), Decimal::TEN);
+        assert_eq!(points[1].gas_cost_per_tx, Some(Decimal::from(20)));
+        assert_eq!(points[2].gas_cost_per_tx, Some(Decimal::from(20)));
+        assert_eq!(points[3].gas_cost_per_tx, Some(Decimal::from(5)));
+
+        assert_eq!(apply_gas_price_history(&mut points, Decimal::TEN, &samples, Decimal::ZERO), 0);
+    }
+}
@@ -0,0 +1,170 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::utils::{TickRange, tick_to_price};
+
+/// Price sensitivity of an LP position, like an option's greeks
+///
+/// `delta` and `gamma` are in raw token0 units per unit of price (token1 per
+/// token0, raw). The `_value` fields restate them in token1 for a move
+/// proportional to the price: `delta_value` = delta × price is the token1 P&L
+/// of a 100% linear move, `gamma_value` = gamma × price² how much
+/// `delta_value` shifts over the same move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PositionGreeks {
+    /// d(value)/d(price): the token0 the position is effectively long
+    pub delta: Decimal,
+    /// d²(value)/d(price)²: negative while in range, zero outside it
+    pub gamma: Decimal,
+    pub delta_value: Decimal,
+    pub gamma_value: Decimal,
+}
+
+impl PositionGreeks {
+    /// Sum exposures in the same pool
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            delta: self.delta + other.delta,
+            gamma: self.gamma + other.gamma,
+            delta_value: self.delta_value + other.delta_value,
+            gamma_value: self.gamma_value + other.gamma_value,
+        }
+    }
+}
+
+/// Greeks of `liquidity` in `range` at `price` (token1 per token0, raw)
+///
+/// A position's token1 value in range is L(2√P − √Pa − P/√Pb), so delta is
+/// L(1/√P − 1/√Pb) (its token0 balance) and gamma is −L/(2P^1.5). Below the
+/// range the position is all token0, a fixed delta of L(1/√Pa − 1/√Pb);
+/// above it, all token1 with no exposure. Gamma is zero outside the range.
+pub fn position_greeks(liquidity: Decimal, range: TickRange, price: Decimal) -> PositionGreeks {
+    let (Some(sp), Some(sa), Some(sb)) =
+        (price.sqrt(), tick_to_price(range.lower).sqrt(), tick_to_price(range.upper).sqrt())
+    else {
+        return PositionGreeks::default();
+    };
+    if sp.is_zero() || sa.is_zero() || sb <= sa {
+        return PositionGreeks::default();
+    }
+
+    let (delta, gamma) = if sp <= sa {
+        (liquidity * (Decimal::ONE / sa - Decimal::ONE / sb), Decimal::ZERO)
+    } else if sp >= sb {
+        (Decimal::ZERO, Decimal::ZERO)
+    } else {
+        (
+            liquidity * (Decimal::ONE / sp - Decimal::ONE / sb),
+            -liquidity / (Decimal::TWO * price * sp),
+        )
+    };
+
+    PositionGreeks { delta, gamma, delta_value: delta * price, gamma_value: gamma * price * price }
+}
+
+/// Combined greeks of a portfolio's positions in one pool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolExposure {
+    pub pool_id: String,
+    /// Price the greeks were evaluated at
+    pub price: Decimal,
+    pub positions: usize,
+    pub greeks: PositionGreeks,
+}
+
+/// Aggregate per-position greeks by pool, ordered by pool ID
+///
+/// Greeks only add up within a pool: other pools trade other pairs.
+pub fn summarize_exposure(
+    positions: impl IntoIterator<Item = (String, Decimal, PositionGreeks)>,
+) -> Vec<PoolExposure> {
+    let mut pools: BTreeMap<String, PoolExposure> = BTreeMap::new();
+    for (pool_id, price, greeks) in positions {
+        let exposure = pools.entry(pool_id.clone()).or_insert_with(|| PoolExposure {
+            pool_id,
+            price,
+            positions: 0,
+            greeks: PositionGreeks::default(),
+        });
+        exposure.positions += 1;
+        exposure.greeks = exposure.greeks.combine(greeks);
+    }
+    pools.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liquidity::amounts_for_liquidity;
+
+    #[test]
+    fn test_delta_matches_token0_balance_and_finite_difference() {
+        let range = TickRange::new(-1000, 1000).unwrap();
+        let liquidity = Decimal::from(1_000_000);
+        let price = Decimal::ONE;
+        let greeks = position_greeks(liquidity, range, price);
+
+        let amounts = amounts_for_liquidity(
+            liquidity,
+            price,
+            tick_to_price(range.lower),
+            tick_to_price(range.upper),
+        );
+        assert!((greeks.delta - amounts.amount0).abs() < Decimal::new(1, 6));
+        assert!(greeks.gamma < Decimal::ZERO);
+
+        // Value change over a small move matches delta
+        let value = |p: Decimal| {
+            amounts_for_liquidity(
+                liquidity,
+                p,
+                tick_to_price(range.lower),
+                tick_to_price(range.upper),
+            )
+            .value_in_token1(p)
+        };
+        let h = Decimal::new(1, 4);
+        let slope = (value(price + h) - value(price - h)) / (Decimal::TWO * h);
+        assert!(
+            (slope - greeks.delta).abs() < Decimal::ONE,
+            "slope {} delta {}",
+            slope,
+            greeks.delta
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_has_no_gamma() {
+        let range = TickRange::new(1000, 2000).unwrap();
+        let liquidity = Decimal::from(1_000_000);
+
+        let below = position_greeks(liquidity, range, Decimal::ONE);
+        assert!(below.delta > Decimal::ZERO);
+        assert_eq!(below.gamma, Decimal::ZERO);
+
+        let above = position_greeks(liquidity, range, Decimal::from(2));
+        assert_eq!(above, PositionGreeks::default());
+    }
+
+    #[test]
+    fn test_exposure_sums_within_pools() {
+        let greeks = PositionGreeks {
+            delta: Decimal::ONE,
+            gamma: Decimal::NEGATIVE_ONE,
+            delta_value: Decimal::ONE,
+            gamma_value: Decimal::NEGATIVE_ONE,
+        };
+        let exposure = summarize_exposure([
+            ("0xb".to_string(), Decimal::ONE, greeks),
+            ("0xa".to_string(), Decimal::ONE, greeks),
+            ("0xb".to_string(), Decimal::ONE, greeks),
+        ]);
+
+        assert_eq!(exposure.len(), 2);
+        assert_eq!(exposure[0].pool_id, "0xa");
+        assert_eq!(exposure[1].positions, 2);
+        assert_eq!(exposure[1].greeks.delta, Decimal::TWO);
+    }
+}
@@ -1,39 +1,404 @@
+use chrono::Duration;
 use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use std::fmt;
 use stillwater_models::{HealthStatus, Position, PositionPnL};
 
-use crate::utils::{distance_to_range_edge, is_in_range};
+use crate::utils::{RangeError, TickRange};
 
-/// Determine position health status based on current tick and P&L
+/// Rules used when none are configured
 ///
-/// Rules:
-/// - Healthy: in range + positive P&L
-/// - Warning: within 10% of range edge
-/// - Critical: out of range OR negative P&L
-pub fn get_position_health(
-    position: &Position,
-    current_tick: i32,
-    pnl: &PositionPnL,
-) -> HealthStatus {
-    // Critical if out of range
-    if !is_in_range(current_tick, position.tick_lower, position.tick_upper) {
-        return HealthStatus::Critical;
+/// Only losing more than 2% of the entry capital is critical; a smaller loss,
+/// usually the gas of opening the position, is a warning.
+pub const DEFAULT_HEALTH_RULES: &str = "out_of_range => critical; pnl_pct < -2% => critical; \
+     net_pnl < 0 => warning; edge_distance < 10% => warning";
+
+/// How long a new position's P&L is left out of its health by default
+pub const DEFAULT_PNL_GRACE: Duration = Duration::days(3);
+
+/// A value a health condition can test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthMetric {
+    /// Distance to the nearest range edge as a fraction of range width
+    EdgeDistance,
+    /// Estimated time until the price reaches a range edge, in seconds
+    TtlToEdge,
+    NetPnl,
+    /// Net P&L as a fraction of the capital deployed at entry
+    PnlPct,
+    FeesEarned,
+    /// Annualized net P&L over capital
+    Apr,
+    /// Annualized gas spend over capital
+    GasRunrate,
+    /// Time since the position was opened, in seconds
+    Age,
+}
+
+impl HealthMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthMetric::EdgeDistance => "edge_distance",
+            HealthMetric::TtlToEdge => "ttl_to_edge",
+            HealthMetric::NetPnl => "net_pnl",
+            HealthMetric::PnlPct => "pnl_pct",
+            HealthMetric::FeesEarned => "fees_earned",
+            HealthMetric::Apr => "apr",
+            HealthMetric::GasRunrate => "gas_runrate",
+            HealthMetric::Age => "age",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "edge_distance" => Some(HealthMetric::EdgeDistance),
+            "ttl_to_edge" => Some(HealthMetric::TtlToEdge),
+            "net_pnl" => Some(HealthMetric::NetPnl),
+            "pnl_pct" => Some(HealthMetric::PnlPct),
+            "fees_earned" => Some(HealthMetric::FeesEarned),
+            "apr" => Some(HealthMetric::Apr),
+            "gas_runrate" => Some(HealthMetric::GasRunrate),
+            "age" => Some(HealthMetric::Age),
+            _ => None,
+        }
+    }
+
+    /// Whether the metric measures P&L, and so waits out a new position's grace period
+    pub fn is_pnl(&self) -> bool {
+        matches!(self, HealthMetric::NetPnl | HealthMetric::PnlPct | HealthMetric::Apr)
+    }
+}
+
+/// Either side of a comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Metric(HealthMetric),
+    /// Literal; percentages are stored as fractions and durations as seconds
+    Value(Decimal),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds(&self, left: Decimal, right: Decimal) -> bool {
+        match self {
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+/// Condition of a health rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthCondition {
+    OutOfRange,
+    InRange,
+    /// True only when both sides are known (e.g. `apr` needs capital and age)
+    Compare(Operand, Comparison, Operand),
+}
+
+/// `condition => status`; the first matching rule decides a position's health
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthRule {
+    pub condition: HealthCondition,
+    pub status: HealthStatus,
+    /// Rule as written, reported with evaluation results
+    pub source: String,
+}
+
+/// A rule that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthRuleError {
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for HealthRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid health rule {:?}: {}", self.rule, self.message)
     }
+}
 
-    // Critical if negative P&L
-    if pnl.net_pnl < Decimal::ZERO {
-        return HealthStatus::Critical;
+impl std::error::Error for HealthRuleError {}
+
+/// Ordered health rules, evaluated per position
+///
+/// Rules are written as `condition => status` separated by `;` or newlines:
+///
+/// ```text
+/// out_of_range => critical
+/// ttl_to_edge < 2d => warning
+/// apr < gas_runrate => warning
+/// ```
+///
+/// Conditions are `out_of_range`, `in_range`, or a comparison (`<`, `<=`, `>`,
+/// `>=`) between metrics (`edge_distance`, `ttl_to_edge`, `net_pnl`, `pnl_pct`,
+/// `fees_earned`, `apr`, `gas_runrate`, `age`) and numbers, percentages (`10%`)
+/// or durations (`12h`, `2d`). Positions matching no rule get `default_status`.
+///
+/// Comparisons of P&L metrics (`net_pnl`, `pnl_pct`, `apr`) don't match
+/// positions younger than `pnl_grace`, which haven't had time to earn back
+/// the gas of opening them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthRules {
+    pub rules: Vec<HealthRule>,
+    pub default_status: HealthStatus,
+    pub pnl_grace: Duration,
+}
+
+impl Default for HealthRules {
+    fn default() -> Self {
+        Self::parse(DEFAULT_HEALTH_RULES).expect("default health rules are valid")
     }
+}
 
-    // Warning if within 10% of range edge
-    let distance = distance_to_range_edge(current_tick, position.tick_lower, position.tick_upper);
-    let range_width = position.tick_upper - position.tick_lower;
+impl HealthRules {
+    pub fn parse(spec: &str) -> Result<Self, HealthRuleError> {
+        let rules = spec
+            .split([';', '\n'])
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(parse_rule)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules, default_status: HealthStatus::Healthy, pnl_grace: DEFAULT_PNL_GRACE })
+    }
 
-    if distance < range_width / 10 {
-        return HealthStatus::Warning;
+    /// Status of the first matching rule, and that rule
+    pub fn evaluate(&self, inputs: &HealthInputs) -> (HealthStatus, Option<&HealthRule>) {
+        let in_grace = inputs.age.is_some_and(|age| age < self.pnl_grace);
+        match self.rules.iter().find(|r| inputs.matches(&r.condition, in_grace)) {
+            Some(rule) => (rule.status, Some(rule)),
+            None => (self.default_status, None),
+        }
     }
 
-    // Otherwise healthy
-    HealthStatus::Healthy
+    /// `evaluate` for many positions, one rule at a time
+    ///
+    /// Each rule is tested against every position no earlier rule decided, so
+    /// the positions still undecided shrink as the rules go and a large batch
+    /// is walked once per rule rather than the rule list once per position.
+    pub fn evaluate_batch(
+        &self,
+        inputs: &[&HealthInputs],
+    ) -> Vec<(HealthStatus, Option<&HealthRule>)> {
+        let in_grace: Vec<bool> =
+            inputs.iter().map(|i| i.age.is_some_and(|age| age < self.pnl_grace)).collect();
+        let mut decided = vec![(self.default_status, None); inputs.len()];
+        let mut pending: Vec<usize> = (0..inputs.len()).collect();
+        for rule in &self.rules {
+            if pending.is_empty() {
+                break;
+            }
+            pending.retain(|&i| {
+                let matched = inputs[i].matches(&rule.condition, in_grace[i]);
+                if matched {
+                    decided[i] = (rule.status, Some(rule));
+                }
+                !matched
+            });
+        }
+        decided
+    }
+}
+
+/// Everything health rules can look at for one position
+#[derive(Debug, Clone)]
+pub struct HealthInputs {
+    pub range: TickRange,
+    pub current_tick: i32,
+    pub net_pnl: Decimal,
+    pub fees_earned: Decimal,
+    pub ttl_to_edge: Option<Duration>,
+    /// Capital deployed at entry, which `pnl_pct` is relative to
+    pub capital: Option<Decimal>,
+    pub apr: Option<Decimal>,
+    pub gas_runrate: Option<Decimal>,
+    /// Time since the position was opened; unknown ages get no grace period
+    pub age: Option<Duration>,
+}
+
+impl HealthInputs {
+    /// Inputs from a position and its P&L; optional metrics start unknown
+    pub fn new(
+        position: &Position,
+        current_tick: i32,
+        pnl: &PositionPnL,
+    ) -> Result<Self, RangeError> {
+        Ok(Self {
+            range: TickRange::of(position)?,
+            current_tick,
+            net_pnl: pnl.net_pnl,
+            fees_earned: pnl.fees_earned,
+            ttl_to_edge: None,
+            capital: None,
+            apr: None,
+            gas_runrate: None,
+            age: None,
+        })
+    }
+
+    /// One-line description of the inputs and the status they produced
+    pub fn summary(&self, status: HealthStatus) -> String {
+        format!(
+            "Status: {:?}, In Range: {}, Distance to Edge: {}, Net P&L: {}",
+            status,
+            self.range.contains(self.current_tick),
+            self.range.distance_to_edge(self.current_tick),
+            self.net_pnl
+        )
+    }
+
+    fn metric(&self, metric: HealthMetric) -> Option<Decimal> {
+        match metric {
+            HealthMetric::EdgeDistance => {
+                Decimal::from(self.range.distance_to_edge(self.current_tick))
+                    .checked_div(Decimal::from(self.range.width()))
+            }
+            HealthMetric::TtlToEdge => self.ttl_to_edge.map(|ttl| Decimal::from(ttl.num_seconds())),
+            HealthMetric::NetPnl => Some(self.net_pnl),
+            HealthMetric::PnlPct => self
+                .capital
+                .filter(|c| *c > Decimal::ZERO)
+                .and_then(|c| self.net_pnl.checked_div(c)),
+            HealthMetric::FeesEarned => Some(self.fees_earned),
+            HealthMetric::Apr => self.apr,
+            HealthMetric::GasRunrate => self.gas_runrate,
+            HealthMetric::Age => self.age.map(|age| Decimal::from(age.num_seconds())),
+        }
+    }
+
+    /// A metric's value; P&L metrics are unknown during the grace period
+    fn operand(&self, operand: Operand, in_grace: bool) -> Option<Decimal> {
+        match operand {
+            Operand::Metric(metric) if in_grace && metric.is_pnl() => None,
+            Operand::Metric(metric) => self.metric(metric),
+            Operand::Value(value) => Some(value),
+        }
+    }
+
+    fn matches(&self, condition: &HealthCondition, in_grace: bool) -> bool {
+        match condition {
+            HealthCondition::OutOfRange => !self.range.contains(self.current_tick),
+            HealthCondition::InRange => self.range.contains(self.current_tick),
+            HealthCondition::Compare(left, op, right) => {
+                match (self.operand(*left, in_grace), self.operand(*right, in_grace)) {
+                    (Some(left), Some(right)) => op.holds(left, right),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// Estimated time until the price reaches the edge it's moving toward
+///
+/// Extrapolates the tick movement from `earlier_tick` to `current_tick` over
+/// `elapsed`. Zero when already out of range; `None` when the price hasn't moved.
+pub fn estimate_ttl_to_edge(
+    range: TickRange,
+    current_tick: i32,
+    earlier_tick: i32,
+    elapsed: Duration,
+) -> Option<Duration> {
+    if !range.contains(current_tick) {
+        return Some(Duration::zero());
+    }
+    let moved = i64::from(current_tick) - i64::from(earlier_tick);
+    if moved == 0 || elapsed <= Duration::zero() {
+        return None;
+    }
+    let remaining = if moved > 0 {
+        i64::from(range.upper) - i64::from(current_tick)
+    } else {
+        i64::from(current_tick) - i64::from(range.lower)
+    };
+    let seconds = remaining.checked_mul(elapsed.num_seconds())? / moved.abs();
+    Some(Duration::seconds(seconds))
+}
+
+fn parse_rule(rule: &str) -> Result<HealthRule, HealthRuleError> {
+    let error =
+        |message: &str| HealthRuleError { rule: rule.to_string(), message: message.to_string() };
+
+    let (condition, status) =
+        rule.split_once("=>").ok_or_else(|| error("expected `condition => status`"))?;
+    let status = match status.trim() {
+        "healthy" => HealthStatus::Healthy,
+        "warning" => HealthStatus::Warning,
+        "critical" => HealthStatus::Critical,
+        _ => return Err(error("status must be healthy, warning or critical")),
+    };
+
+    let condition = condition.trim();
+    let condition = match condition {
+        "out_of_range" => HealthCondition::OutOfRange,
+        "in_range" => HealthCondition::InRange,
+        _ => {
+            // Two-character operators first so `<=` isn't read as `<`
+            let (left, op, right) = [
+                ("<=", Comparison::Le),
+                (">=", Comparison::Ge),
+                ("<", Comparison::Lt),
+                (">", Comparison::Gt),
+            ]
+            .into_iter()
+            .find_map(|(token, op)| {
+                condition.split_once(token).map(|(left, right)| (left, op, right))
+            })
+            .ok_or_else(|| error("unknown condition"))?;
+            let left = parse_operand(left.trim()).ok_or_else(|| error("unknown left operand"))?;
+            let right =
+                parse_operand(right.trim()).ok_or_else(|| error("unknown right operand"))?;
+            HealthCondition::Compare(left, op, right)
+        }
+    };
+
+    Ok(HealthRule { condition, status, source: rule.to_string() })
+}
+
+fn parse_operand(s: &str) -> Option<Operand> {
+    if let Some(metric) = HealthMetric::parse(s) {
+        return Some(Operand::Metric(metric));
+    }
+    let (number, multiplier) = if let Some(n) = s.strip_suffix('%') {
+        (n, Decimal::new(1, 2))
+    } else if let Some(n) = s.strip_suffix('d') {
+        (n, Decimal::from(24 * 3600))
+    } else if let Some(n) = s.strip_suffix('h') {
+        (n, Decimal::from(3600))
+    } else {
+        (s, Decimal::ONE)
+    };
+    let value = Decimal::from_str(number.trim()).ok()?;
+    Some(Operand::Value(value.checked_mul(multiplier)?))
+}
+
+/// Determine position health status based on current tick and P&L
+///
+/// Uses the default rules:
+/// - Critical: out of range
+/// - Warning: negative P&L or within 10% of range edge
+/// - Healthy: otherwise
+///
+/// Losses relative to capital need `HealthInputs::capital`, which a bare
+/// `PositionPnL` doesn't carry, so they aren't Critical here.
+///
+/// Positions with an invalid tick range have no meaningful health and yield a `RangeError`.
+pub fn get_position_health(
+    position: &Position,
+    current_tick: i32,
+    pnl: &PositionPnL,
+) -> Result<HealthStatus, RangeError> {
+    let inputs = HealthInputs::new(position, current_tick, pnl)?;
+    Ok(HealthRules::default().evaluate(&inputs).0)
 }
 
 /// Get detailed health information as a string
@@ -41,34 +406,18 @@ pub fn get_health_details(
     position: &Position,
     current_tick: i32,
     pnl: &PositionPnL,
-) -> String {
-    let status = get_position_health(position, current_tick, pnl);
-    let in_range = is_in_range(current_tick, position.tick_lower, position.tick_upper);
-    let distance = distance_to_range_edge(current_tick, position.tick_lower, position.tick_upper);
-
-    format!(
-        "Status: {:?}, In Range: {}, Distance to Edge: {}, Net P&L: {}",
-        status, in_range, distance, pnl.net_pnl
-    )
+) -> Result<String, RangeError> {
+    let inputs = HealthInputs::new(position, current_tick, pnl)?;
+    Ok(inputs.summary(HealthRules::default().evaluate(&inputs).0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy::primitives::U256;
-    use chrono::Utc;
+    use crate::testing;
 
     fn create_test_position(tick_lower: i32, tick_upper: i32) -> Position {
-        Position {
-            id: 1,
-            nft_id: "1".to_string(),
-            owner: "0xtest".to_string(),
-            pool_id: "0xpool".to_string(),
-            tick_lower,
-            tick_upper,
-            liquidity: U256::from(1000000u64),
-            created_at: Utc::now(),
-        }
+        Position { tick_lower, tick_upper, ..testing::create_test_position() }
     }
 
     fn create_test_pnl(net_pnl: i64) -> PositionPnL {
@@ -86,7 +435,7 @@ mod tests {
         let pnl = create_test_pnl(70); // Positive P&L
         let current_tick = 0; // Center of range
 
-        let health = get_position_health(&position, current_tick, &pnl);
+        let health = get_position_health(&position, current_tick, &pnl).unwrap();
         assert_eq!(health, HealthStatus::Healthy);
     }
 
@@ -96,7 +445,7 @@ mod tests {
         let pnl = create_test_pnl(70); // Positive P&L
         let current_tick = 950; // Within 10% of upper edge
 
-        let health = get_position_health(&position, current_tick, &pnl);
+        let health = get_position_health(&position, current_tick, &pnl).unwrap();
         assert_eq!(health, HealthStatus::Warning);
     }
 
@@ -106,18 +455,52 @@ mod tests {
         let pnl = create_test_pnl(70); // Positive P&L but out of range
         let current_tick = 1500; // Out of range
 
-        let health = get_position_health(&position, current_tick, &pnl);
+        let health = get_position_health(&position, current_tick, &pnl).unwrap();
         assert_eq!(health, HealthStatus::Critical);
     }
 
     #[test]
-    fn test_critical_status_negative_pnl() {
+    fn test_negative_pnl_status_depends_on_size_of_loss() {
         let position = create_test_position(-1000, 1000);
-        let pnl = create_test_pnl(-10); // Negative P&L
-        let current_tick = 0; // In range but negative P&L
+        let pnl = create_test_pnl(-10); // In range but negative P&L
 
-        let health = get_position_health(&position, current_tick, &pnl);
-        assert_eq!(health, HealthStatus::Critical);
+        // Without capital the loss can't be sized
+        let health = get_position_health(&position, 0, &pnl).unwrap();
+        assert_eq!(health, HealthStatus::Warning);
+
+        let rules = HealthRules::default();
+        let mut inputs = HealthInputs::new(&position, 0, &pnl).unwrap();
+        inputs.capital = Some(Decimal::from(1000)); // 1% loss
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Warning);
+        inputs.capital = Some(Decimal::from(100)); // 10% loss
+        let (status, rule) = rules.evaluate(&inputs);
+        assert_eq!(status, HealthStatus::Critical);
+        assert_eq!(rule.unwrap().source, "pnl_pct < -2% => critical");
+    }
+
+    #[test]
+    fn test_pnl_rules_wait_out_the_grace_period() {
+        let position = create_test_position(-1000, 1000);
+        let mut inputs = HealthInputs::new(&position, 0, &create_test_pnl(-10)).unwrap();
+        inputs.capital = Some(Decimal::from(100));
+        let rules = HealthRules::default();
+
+        inputs.age = Some(Duration::hours(6));
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Healthy);
+        // Range rules still apply
+        inputs.current_tick = 1500;
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Critical);
+
+        inputs.current_tick = 0;
+        inputs.age = Some(DEFAULT_PNL_GRACE);
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Critical);
+
+        let rules = HealthRules::parse("age < 1d => healthy; net_pnl < 0 => critical").unwrap();
+        let rules = HealthRules { pnl_grace: Duration::zero(), ..rules };
+        inputs.age = Some(Duration::hours(6));
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Healthy);
+        inputs.age = Some(Duration::days(2));
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Critical);
     }
 
     #[test]
@@ -126,8 +509,69 @@ mod tests {
         let pnl = create_test_pnl(70);
         let current_tick = 0;
 
-        let details = get_health_details(&position, current_tick, &pnl);
+        let details = get_health_details(&position, current_tick, &pnl).unwrap();
         assert!(details.contains("Healthy"));
         assert!(details.contains("In Range: true"));
     }
+
+    #[test]
+    fn test_invalid_range_is_an_error() {
+        let pnl = create_test_pnl(70);
+
+        let inverted = create_test_position(1000, -1000);
+        assert!(matches!(
+            get_position_health(&inverted, 0, &pnl),
+            Err(RangeError::Inverted { .. })
+        ));
+
+        let zero_width = create_test_position(60, 60);
+        assert_eq!(
+            get_health_details(&zero_width, 60, &pnl),
+            Err(RangeError::ZeroWidth { tick: 60 })
+        );
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = HealthRules::parse(
+            "out_of_range => critical\nttl_to_edge < 2d => warning; apr < gas_runrate => warning",
+        )
+        .unwrap();
+        assert_eq!(rules.rules.len(), 3);
+        assert_eq!(
+            rules.rules[1].condition,
+            HealthCondition::Compare(
+                Operand::Metric(HealthMetric::TtlToEdge),
+                Comparison::Lt,
+                Operand::Value(Decimal::from(2 * 24 * 3600)),
+            )
+        );
+
+        assert!(HealthRules::parse("out_of_range => fine").is_err());
+        assert!(HealthRules::parse("volume > 5 => warning").is_err());
+    }
+
+    #[test]
+    fn test_custom_rules_in_order() {
+        let position = create_test_position(-1000, 1000);
+        let rules =
+            HealthRules::parse("ttl_to_edge < 2d => warning; apr < gas_runrate => critical")
+                .unwrap();
+        let mut inputs = HealthInputs::new(&position, 0, &create_test_pnl(70)).unwrap();
+
+        // Unknown metrics never match
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Healthy);
+
+        inputs.apr = Some(Decimal::new(5, 2));
+        inputs.gas_runrate = Some(Decimal::new(8, 2));
+        let (status, rule) = rules.evaluate(&inputs);
+        assert_eq!(status, HealthStatus::Critical);
+        assert_eq!(rule.unwrap().source, "apr < gas_runrate => critical");
+
+        // Moving 500 ticks/day toward the upper edge 1000 ticks away: 2 days left
+        inputs.ttl_to_edge = estimate_ttl_to_edge(inputs.range, 0, -500, Duration::days(1));
+        assert_eq!(inputs.ttl_to_edge, Some(Duration::days(2)));
+        inputs.ttl_to_edge = estimate_ttl_to_edge(inputs.range, 200, -500, Duration::days(1));
+        assert_eq!(rules.evaluate(&inputs).0, HealthStatus::Warning);
+    }
 }
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use stillwater_models::{HealthStatus, PositionHealthRow, PositionPnL};
+
+use crate::health::{HealthInputs, estimate_ttl_to_edge};
+use crate::leaderboard::annualized_return;
+use crate::liquidity::value_per_liquidity;
+use crate::pnl::{calculate_impermanent_loss, calculate_net_pnl};
+use crate::risk::{RiskCategory, RiskHealthRules, classify_risk};
+use crate::utils::{price_to_tick, tick_to_price};
+
+/// How far back the snapshot time to the range edge is extrapolated from may be
+pub const HEALTH_TTL_LOOKBACK: Duration = Duration::hours(24);
+
+/// Health of one position evaluated in a batch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionHealth {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    /// None when the position couldn't be evaluated; `details` says why
+    pub status: Option<HealthStatus>,
+    pub details: String,
+    /// The configured rule that decided the status (None when no rule matched)
+    pub rule: Option<String>,
+    /// Risk bucket whose rules were applied (None without the pool's volatility)
+    pub risk: Option<RiskCategory>,
+}
+
+/// Health inputs for a batch row, built as the single-position health check builds them
+///
+/// The entry price falls back to 1.0 without a priced deposit. Gas isn't
+/// counted: the batch has no per-position gas figure in the pool's units.
+pub fn health_inputs_from_row(
+    row: &PositionHealthRow,
+    now: DateTime<Utc>,
+) -> Result<HealthInputs, String> {
+    let position = &row.position;
+    let current_tick = row.current_tick.ok_or("No tick known for the pool yet")?;
+    let current_price = tick_to_price(current_tick);
+    let initial_price = row.entry_tick.map_or(Decimal::ONE, tick_to_price);
+
+    let impermanent_loss = calculate_impermanent_loss(position, initial_price, current_price)
+        .map_err(|e| e.to_string())?;
+    let pnl = PositionPnL {
+        fees_earned: row.fees_earned,
+        impermanent_loss,
+        gas_spent: Decimal::ZERO,
+        net_pnl: calculate_net_pnl(row.fees_earned, impermanent_loss, Decimal::ZERO),
+    };
+    let inputs = HealthInputs::new(position, current_tick, &pnl).map_err(|e| e.to_string())?;
+
+    // Annualize P&L and gas over the capital deployed at entry
+    let capital = Decimal::from_str(&position.liquidity.to_string())
+        .ok()
+        .and_then(|l| {
+            l.checked_mul(value_per_liquidity(
+                initial_price,
+                tick_to_price(position.tick_lower),
+                tick_to_price(position.tick_upper),
+            ))
+        })
+        .unwrap_or(Decimal::ZERO);
+    let age = now - position.created_at;
+    let ttl_to_edge = row.earlier_price.and_then(|(price, taken_at)| {
+        estimate_ttl_to_edge(inputs.range, current_tick, price_to_tick(price), now - taken_at)
+    });
+    Ok(HealthInputs {
+        capital: Some(capital),
+        apr: annualized_return(pnl.net_pnl, capital, age),
+        gas_runrate: annualized_return(pnl.gas_spent, capital, age),
+        age: Some(age),
+        ttl_to_edge,
+        ..inputs
+    })
+}
+
+/// Evaluate the health of every row at once
+///
+/// Positions are grouped by risk bucket, from `pool_volatility` (daily tick
+/// volatility by pool ID), and each bucket's rules are applied to the whole
+/// group with `HealthRules::evaluate_batch`. Results keep the rows' order.
+pub fn evaluate_health_batch(
+    rules: &RiskHealthRules,
+    rows: &[PositionHealthRow],
+    pool_volatility: &HashMap<String, Decimal>,
+    now: DateTime<Utc>,
+) -> Vec<PositionHealth> {
+    let inputs: Vec<Result<HealthInputs, String>> =
+        rows.iter().map(|row| health_inputs_from_row(row, now)).collect();
+    let risks: Vec<Option<RiskCategory>> = rows
+        .iter()
+        .zip(&inputs)
+        .map(|(row, inputs)| {
+            let range = inputs.as_ref().ok()?.range;
+            pool_volatility.get(&row.position.pool_id).map(|v| classify_risk(range, *v))
+        })
+        .collect();
+
+    let mut groups: HashMap<Option<RiskCategory>, Vec<usize>> = HashMap::new();
+    for (i, inputs) in inputs.iter().enumerate() {
+        if inputs.is_ok() {
+            groups.entry(risks[i]).or_default().push(i);
+        }
+    }
+    let mut decided = vec![None; rows.len()];
+    for (risk, indices) in groups {
+        let group: Vec<&HealthInputs> =
+            indices.iter().filter_map(|&i| inputs[i].as_ref().ok()).collect();
+        let results = rules.for_category(risk).evaluate_batch(&group);
+        for ((i, inputs), (status, rule)) in indices.iter().zip(&group).zip(results) {
+            decided[*i] = Some((status, inputs.summary(status), rule.map(|r| r.source.clone())));
+        }
+    }
+
+    rows.iter()
+        .zip(inputs)
+        .zip(decided)
+        .zip(risks)
+        .map(|(((row, inputs), decided), risk)| {
+            let (status, details, rule) = match decided {
+                Some((status, details, rule)) => (Some(status), details, rule),
+                None => (None, inputs.err().unwrap_or_default(), None),
+            };
+            PositionHealth {
+                position_id: row.position.id,
+                nft_id: row.position.nft_id.clone(),
+                pool_id: row.position.pool_id.clone(),
+                status,
+                details,
+                rule,
+                risk,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use stillwater_models::{Address, Position};
+
+    fn create_test_row(id: i64, tick_lower: i32, tick_upper: i32, tick: i32) -> PositionHealthRow {
+        PositionHealthRow {
+            position: Position {
+                id,
+                nft_id: id.to_string(),
+                owner: Address::ZERO,
+                pool_id: "0xpool".to_string(),
+                tick_lower,
+                tick_upper,
+                liquidity: U256::from(1_000_000u64),
+                created_at: Utc::now() - Duration::days(30),
+                manual: false,
+                closed_at: None,
+                archived_at: None,
+            },
+            current_tick: Some(tick),
+            entry_tick: Some(0),
+            fees_earned: Decimal::from(1000),
+            earlier_price: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_single_evaluation() {
+        let now = Utc::now();
+        let rows: Vec<PositionHealthRow> =
+            (0..50).map(|i| create_test_row(i, -1000, 1000, (i as i32 - 25) * 50)).collect();
+        let rules = RiskHealthRules::default();
+        let volatility = HashMap::from([("0xpool".to_string(), Decimal::from(300))]);
+
+        let batch = evaluate_health_batch(&rules, &rows, &volatility, now);
+        assert_eq!(batch.len(), rows.len());
+        for (row, health) in rows.iter().zip(&batch) {
+            let inputs = health_inputs_from_row(row, now).unwrap();
+            let risk = Some(classify_risk(inputs.range, Decimal::from(300)));
+            let (status, rule) = rules.for_category(risk).evaluate(&inputs);
+            assert_eq!(health.position_id, row.position.id);
+            assert_eq!(health.status, Some(status));
+            assert_eq!(health.rule, rule.map(|r| r.source.clone()));
+            assert_eq!(health.risk, risk);
+        }
+        assert!(batch.iter().any(|h| h.status == Some(HealthStatus::Critical)));
+        assert!(batch.iter().any(|h| h.status == Some(HealthStatus::Healthy)));
+    }
+
+    #[test]
+    fn test_batch_reports_unevaluated_positions() {
+        let mut no_tick = create_test_row(1, -1000, 1000, 0);
+        no_tick.current_tick = None;
+        let inverted = create_test_row(2, 1000, -1000, 0);
+        let valid = create_test_row(3, -1000, 1000, 0);
+
+        let batch = evaluate_health_batch(
+            &RiskHealthRules::default(),
+            &[no_tick, inverted, valid],
+            &HashMap::new(),
+            Utc::now(),
+        );
+        assert_eq!(batch[0].status, None);
+        assert_eq!(batch[0].details, "No tick known for the pool yet");
+        assert_eq!(batch[1].status, None);
+        assert_eq!(batch[2].status, Some(HealthStatus::Healthy));
+        assert_eq!(batch[2].risk, None);
+    }
+}
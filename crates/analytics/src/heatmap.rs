@@ -0,0 +1,186 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use stillwater_models::Swap;
+
+use crate::utils::{MAX_TICK, MIN_TICK, TickRange, price_to_tick};
+
+/// Grid resolution of a liquidity heatmap
+#[derive(Debug, Clone, Copy)]
+pub struct HeatmapConfig {
+    /// Width of each time row
+    pub interval: Duration,
+    /// Number of tick columns
+    pub tick_buckets: usize,
+}
+
+impl Default for HeatmapConfig {
+    fn default() -> Self {
+        Self { interval: Duration::hours(1), tick_buckets: 20 }
+    }
+}
+
+/// Swap activity in one time × tick-bucket cell
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HeatmapCell {
+    pub swaps: u32,
+    /// Sum of absolute token1 amounts swapped, in raw units
+    pub volume: Decimal,
+}
+
+/// Activity during one time interval
+#[derive(Debug, Clone, Serialize)]
+pub struct HeatmapRow {
+    pub start: DateTime<Utc>,
+    /// One cell per tick bucket
+    pub cells: Vec<HeatmapCell>,
+    /// Activity below the first / above the last bucket
+    pub below: HeatmapCell,
+    pub above: HeatmapCell,
+}
+
+/// Where swaps happened over time, relative to a tick range
+///
+/// The tick axis spans the range plus one range-width on either side, so a
+/// UI can show at a glance whether activity sat inside the range (earning
+/// fees) or drifted past its edges.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidityHeatmap {
+    /// Bucket boundaries, `tick_buckets + 1` ascending ticks
+    pub tick_edges: Vec<i32>,
+    /// Whether each bucket overlaps the range
+    pub bucket_in_range: Vec<bool>,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub rows: Vec<HeatmapRow>,
+    /// Share (0-1) of swap volume that executed inside the range
+    pub volume_in_range: Decimal,
+}
+
+/// Tick a swap executed at, from its token amounts (`|amount1| / |amount0|`)
+///
+/// Swaps don't record the pool price, so the execution price stands in for it;
+/// the average price of a large swap sits between its start and end ticks.
+pub fn swap_tick(swap: &Swap) -> Option<i32> {
+    let amount0 = Decimal::from_str(&swap.amount0.unsigned_abs().to_string()).ok()?;
+    let amount1 = Decimal::from_str(&swap.amount1.unsigned_abs().to_string()).ok()?;
+    if amount0.is_zero() || amount1.is_zero() {
+        return None;
+    }
+    let price = amount1.checked_div(amount0)?;
+    Some(price_to_tick(price).clamp(MIN_TICK, MAX_TICK))
+}
+
+/// Bucket swaps in [from, to) into a time × tick grid around `range`
+pub fn build_liquidity_heatmap(
+    range: TickRange,
+    swaps: &[Swap],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    config: &HeatmapConfig,
+) -> LiquidityHeatmap {
+    let buckets = config.tick_buckets.max(1);
+    let interval_secs = config.interval.num_seconds().max(1);
+
+    // Axis: the range padded by its own width on each side, split into equal buckets
+    let width = i64::from(range.width());
+    let axis_lower = (i64::from(range.lower) - width).max(i64::from(MIN_TICK));
+    let axis_upper = (i64::from(range.upper) + width).min(i64::from(MAX_TICK));
+    let bucket_width = ((axis_upper - axis_lower) / buckets as i64).max(1);
+    let tick_edges: Vec<i32> = (0..=buckets)
+        .map(|i| (axis_lower + bucket_width * i as i64).min(axis_upper) as i32)
+        .collect();
+    let bucket_in_range =
+        tick_edges.windows(2).map(|edge| edge[0] < range.upper && edge[1] > range.lower).collect();
+
+    let row_count = ((to - from).num_seconds().max(0) + interval_secs - 1) / interval_secs;
+    let mut rows: Vec<HeatmapRow> = (0..row_count)
+        .map(|i| HeatmapRow {
+            start: from + Duration::seconds(i * interval_secs),
+            cells: vec![HeatmapCell::default(); buckets],
+            below: HeatmapCell::default(),
+            above: HeatmapCell::default(),
+        })
+        .collect();
+
+    let mut total_volume = Decimal::ZERO;
+    let mut in_range_volume = Decimal::ZERO;
+
+    for swap in swaps.iter().filter(|s| s.timestamp >= from && s.timestamp < to) {
+        let Some(tick) = swap_tick(swap) else {
+            continue;
+        };
+        let volume =
+            Decimal::from_str(&swap.amount1.unsigned_abs().to_string()).unwrap_or(Decimal::ZERO);
+        let row = &mut rows[((swap.timestamp - from).num_seconds() / interval_secs) as usize];
+
+        let cell = if tick < tick_edges[0] {
+            &mut row.below
+        } else if tick >= tick_edges[buckets] {
+            &mut row.above
+        } else {
+            let index = ((i64::from(tick) - axis_lower) / bucket_width) as usize;
+            &mut row.cells[index.min(buckets - 1)]
+        };
+        cell.swaps += 1;
+        cell.volume += volume;
+
+        total_volume += volume;
+        if range.contains(tick) {
+            in_range_volume += volume;
+        }
+    }
+
+    LiquidityHeatmap {
+        tick_edges,
+        bucket_in_range,
+        tick_lower: range.lower,
+        tick_upper: range.upper,
+        rows,
+        volume_in_range: in_range_volume.checked_div(total_volume).unwrap_or(Decimal::ZERO),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn create_test_swap(minutes: i64, amount0: i64, amount1: i64, from: DateTime<Utc>) -> Swap {
+        testing::create_test_swap(minutes, amount0, amount1, from + Duration::minutes(minutes))
+    }
+
+    #[test]
+    fn test_swap_tick_from_execution_price() {
+        let from = Utc::now();
+        // 1:1 execution price is tick 0
+        assert_eq!(swap_tick(&create_test_swap(0, 1000, -1000, from)), Some(0));
+        assert_eq!(swap_tick(&create_test_swap(0, 0, 5, from)), None);
+    }
+
+    #[test]
+    fn test_heatmap_grid() {
+        let from = Utc::now();
+        let range = TickRange::new(-1000, 1000).unwrap();
+        let swaps = vec![
+            // In range, first hour
+            create_test_swap(10, 1000, -1000, from),
+            // Price ~1.65 (tick ~5000): above the padded axis, second hour
+            create_test_swap(70, -1000, 1650, from),
+            // Outside the window
+            create_test_swap(200, 1000, -1000, from),
+        ];
+        let config = HeatmapConfig { interval: Duration::hours(1), tick_buckets: 6 };
+
+        let heatmap =
+            build_liquidity_heatmap(range, &swaps, from, from + Duration::hours(2), &config);
+
+        assert_eq!(heatmap.tick_edges, vec![-3000, -2000, -1000, 0, 1000, 2000, 3000]);
+        assert_eq!(heatmap.bucket_in_range, vec![false, false, true, true, false, false]);
+        assert_eq!(heatmap.rows.len(), 2);
+        assert_eq!(heatmap.rows[0].cells[3].swaps, 1);
+        assert_eq!(heatmap.rows[1].above.swaps, 1);
+        assert!(heatmap.volume_in_range > Decimal::ZERO && heatmap.volume_in_range < Decimal::ONE);
+    }
+}
@@ -0,0 +1,297 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use stillwater_models::{Position, PositionSnapshot};
+
+/// Positions held shorter than this count as short-lived (e.g. rebalances)
+pub const SHORT_HOLD_DAYS: i64 = 7;
+
+/// Positions held at least this long count as long holds
+pub const LONG_HOLD_DAYS: i64 = 30;
+
+/// How long a position has been (or was) held, and what it earned
+#[derive(Debug, Clone, Serialize)]
+pub struct HoldingPeriod {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub opened_at: DateTime<Utc>,
+    /// When liquidity was fully withdrawn; `None` while the position is open
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Seconds from opening until close (or `now` for open positions)
+    pub held_seconds: i64,
+    pub fees_earned: Decimal,
+    /// Fees per day held (None for positions younger than a second)
+    pub daily_fees: Option<Decimal>,
+}
+
+/// Fee income earned during one week of positions' lives (week 0 = first 7 days)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeeklyFees {
+    pub week: i64,
+    pub fees: Decimal,
+}
+
+/// Positions grouped by how long they were held
+#[derive(Debug, Clone, Serialize)]
+pub struct HoldBucket {
+    /// `short` (< 7 days), `medium` or `long` (>= 30 days)
+    pub label: &'static str,
+    pub positions: usize,
+    pub fees_earned: Decimal,
+    /// Total fees over total days held, comparable across buckets
+    pub daily_fees: Option<Decimal>,
+}
+
+/// Holding-period metrics across an owner's positions
+#[derive(Debug, Clone, Serialize)]
+pub struct HoldingSummary {
+    pub positions: usize,
+    pub open_positions: usize,
+    /// Mean hold time of closed positions, in seconds
+    pub average_closed_hold_seconds: Option<i64>,
+    /// Mean age of all positions (open ones counted up to now), in seconds
+    pub average_age_seconds: Option<i64>,
+    pub buckets: Vec<HoldBucket>,
+    pub fees_by_week: Vec<WeeklyFees>,
+    pub periods: Vec<HoldingPeriod>,
+}
+
+/// Holding period of a position from its snapshots (sorted by timestamp)
+///
+/// A position is closed at its first zero-liquidity snapshot, or at its last
+/// snapshot if its current liquidity is zero but no snapshot recorded the exit.
+pub fn holding_period(
+    position: &Position,
+    snapshots: &[PositionSnapshot],
+    now: DateTime<Utc>,
+) -> HoldingPeriod {
+    let closed_at = snapshots
+        .iter()
+        .find(|s| s.liquidity.is_zero())
+        .or_else(|| snapshots.last().filter(|_| position.liquidity.is_zero()))
+        .map(|s| s.timestamp)
+        .or_else(|| position.liquidity.is_zero().then_some(position.created_at));
+
+    let end = closed_at.unwrap_or(now);
+    let held = (end - position.created_at).max(Duration::zero());
+    let fees_earned = snapshots
+        .iter()
+        .filter(|s| closed_at.is_none_or(|c| s.timestamp <= c))
+        .map(|s| s.fees_earned)
+        .max()
+        .unwrap_or(Decimal::ZERO);
+
+    HoldingPeriod {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        opened_at: position.created_at,
+        closed_at,
+        held_seconds: held.num_seconds(),
+        fees_earned,
+        daily_fees: daily_rate(fees_earned, held.num_seconds()),
+    }
+}
+
+/// Fee income by week of a position's life
+///
+/// Snapshots carry cumulative fees, so each snapshot's increase over the
+/// previous one is attributed to the week of life it was recorded in.
+pub fn fees_by_week_of_life(
+    position: &Position,
+    snapshots: &[PositionSnapshot],
+) -> Vec<WeeklyFees> {
+    let mut weeks: BTreeMap<i64, Decimal> = BTreeMap::new();
+    let mut previous = Decimal::ZERO;
+
+    for snapshot in snapshots {
+        let earned = (snapshot.fees_earned - previous).max(Decimal::ZERO);
+        previous = previous.max(snapshot.fees_earned);
+
+        let week = (snapshot.timestamp - position.created_at).num_weeks().max(0);
+        *weeks.entry(week).or_insert(Decimal::ZERO) += earned;
+    }
+
+    weeks.into_iter().map(|(week, fees)| WeeklyFees { week, fees }).collect()
+}
+
+/// Summarize holding periods for a set of positions
+///
+/// `snapshots` may cover all positions in any order; they're grouped by
+/// position and sorted by timestamp here.
+pub fn summarize_holding(
+    positions: &[Position],
+    snapshots: &[PositionSnapshot],
+    now: DateTime<Utc>,
+) -> HoldingSummary {
+    let mut by_position: BTreeMap<i64, Vec<PositionSnapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        by_position.entry(snapshot.position_id).or_default().push(snapshot.clone());
+    }
+    for series in by_position.values_mut() {
+        series.sort_by_key(|s| s.timestamp);
+    }
+
+    let mut periods = Vec::with_capacity(positions.len());
+    let mut weeks: BTreeMap<i64, Decimal> = BTreeMap::new();
+
+    for position in positions {
+        let series = by_position.get(&position.id).map(Vec::as_slice).unwrap_or(&[]);
+        periods.push(holding_period(position, series, now));
+
+        let closed_at = periods.last().and_then(|p| p.closed_at);
+        let held: Vec<PositionSnapshot> =
+            series.iter().filter(|s| closed_at.is_none_or(|c| s.timestamp <= c)).cloned().collect();
+        for w in fees_by_week_of_life(position, &held) {
+            *weeks.entry(w.week).or_insert(Decimal::ZERO) += w.fees;
+        }
+    }
+
+    let closed: Vec<i64> =
+        periods.iter().filter(|p| p.closed_at.is_some()).map(|p| p.held_seconds).collect();
+    let ages: Vec<i64> = periods.iter().map(|p| p.held_seconds).collect();
+
+    HoldingSummary {
+        positions: periods.len(),
+        open_positions: periods.len() - closed.len(),
+        average_closed_hold_seconds: average(&closed),
+        average_age_seconds: average(&ages),
+        buckets: bucket_by_hold(&periods),
+        fees_by_week: weeks.into_iter().map(|(week, fees)| WeeklyFees { week, fees }).collect(),
+        periods,
+    }
+}
+
+fn bucket_by_hold(periods: &[HoldingPeriod]) -> Vec<HoldBucket> {
+    let short = Duration::days(SHORT_HOLD_DAYS).num_seconds();
+    let long = Duration::days(LONG_HOLD_DAYS).num_seconds();
+
+    [("short", 0, short), ("medium", short, long), ("long", long, i64::MAX)]
+        .into_iter()
+        .map(|(label, from, to)| {
+            let members: Vec<&HoldingPeriod> =
+                periods.iter().filter(|p| p.held_seconds >= from && p.held_seconds < to).collect();
+            let fees_earned: Decimal = members.iter().map(|p| p.fees_earned).sum();
+            let seconds: i64 = members.iter().map(|p| p.held_seconds).sum();
+
+            HoldBucket {
+                label,
+                positions: members.len(),
+                fees_earned,
+                daily_fees: daily_rate(fees_earned, seconds),
+            }
+        })
+        .collect()
+}
+
+fn daily_rate(fees: Decimal, seconds: i64) -> Option<Decimal> {
+    if seconds <= 0 {
+        return None;
+    }
+    (fees * Decimal::from(24 * 3600)).checked_div(Decimal::from(seconds))
+}
+
+fn average(values: &[i64]) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<i64>() / values.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use alloy::primitives::U256;
+
+    fn create_test_position(id: i64, created_at: DateTime<Utc>, liquidity: u64) -> Position {
+        Position {
+            id,
+            nft_id: id.to_string(),
+            liquidity: U256::from(liquidity),
+            created_at,
+            ..testing::create_test_position()
+        }
+    }
+
+    fn create_test_snapshot(
+        position_id: i64,
+        timestamp: DateTime<Utc>,
+        fees: i64,
+        liquidity: u64,
+    ) -> PositionSnapshot {
+        PositionSnapshot {
+            id: 0,
+            position_id,
+            timestamp,
+            fees_earned: Decimal::from(fees),
+            liquidity: U256::from(liquidity),
+            price: Decimal::ONE,
+        }
+    }
+
+    #[test]
+    fn test_holding_period_closed_at_zero_liquidity() {
+        let now = Utc::now();
+        let opened = now - Duration::days(10);
+        let position = create_test_position(1, opened, 0);
+        let snapshots = vec![
+            create_test_snapshot(1, opened + Duration::days(1), 10, 1000),
+            create_test_snapshot(1, opened + Duration::days(3), 30, 0),
+            create_test_snapshot(1, opened + Duration::days(5), 30, 0),
+        ];
+
+        let period = holding_period(&position, &snapshots, now);
+        assert_eq!(period.closed_at, Some(opened + Duration::days(3)));
+        assert_eq!(period.held_seconds, Duration::days(3).num_seconds());
+        assert_eq!(period.fees_earned, Decimal::from(30));
+        assert_eq!(period.daily_fees, Some(Decimal::from(10)));
+    }
+
+    #[test]
+    fn test_fees_by_week_of_life() {
+        let opened = Utc::now() - Duration::days(20);
+        let position = create_test_position(1, opened, 1000);
+        let snapshots = vec![
+            create_test_snapshot(1, opened + Duration::days(2), 5, 1000),
+            create_test_snapshot(1, opened + Duration::days(6), 12, 1000),
+            create_test_snapshot(1, opened + Duration::days(15), 20, 1000),
+        ];
+
+        let weeks = fees_by_week_of_life(&position, &snapshots);
+        assert_eq!(
+            weeks,
+            vec![
+                WeeklyFees { week: 0, fees: Decimal::from(12) },
+                WeeklyFees { week: 2, fees: Decimal::from(8) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_holding_buckets() {
+        let now = Utc::now();
+        let opened = now - Duration::days(40);
+        let positions =
+            vec![create_test_position(1, opened, 0), create_test_position(2, opened, 1000)];
+        let snapshots = vec![
+            create_test_snapshot(2, opened + Duration::days(20), 40, 1000),
+            create_test_snapshot(1, opened + Duration::days(2), 4, 0),
+            create_test_snapshot(1, opened + Duration::days(1), 3, 1000),
+        ];
+
+        let summary = summarize_holding(&positions, &snapshots, now);
+        assert_eq!(summary.positions, 2);
+        assert_eq!(summary.open_positions, 1);
+        assert_eq!(summary.average_closed_hold_seconds, Some(Duration::days(2).num_seconds()));
+
+        let short = &summary.buckets[0];
+        assert_eq!(short.positions, 1);
+        assert_eq!(short.daily_fees, Some(Decimal::from(2)));
+        let long = &summary.buckets[2];
+        assert_eq!(long.positions, 1);
+        assert_eq!(long.daily_fees, Some(Decimal::ONE));
+
+        assert_eq!(summary.fees_by_week[0], WeeklyFees { week: 0, fees: Decimal::from(4) });
+    }
+}
@@ -0,0 +1,314 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use stillwater_models::Pool;
+
+use crate::fees::{DynamicFeeModel, FeeModelRegistry};
+
+/// v4 hook permissions, encoded in the lowest 14 bits of the hook's address
+const HOOK_PERMISSIONS: &[(u16, &str)] = &[
+    (1 << 13, "before_initialize"),
+    (1 << 12, "after_initialize"),
+    (1 << 11, "before_add_liquidity"),
+    (1 << 10, "after_add_liquidity"),
+    (1 << 9, "before_remove_liquidity"),
+    (1 << 8, "after_remove_liquidity"),
+    (1 << 7, "before_swap"),
+    (1 << 6, "after_swap"),
+    (1 << 5, "before_donate"),
+    (1 << 4, "after_donate"),
+    (1 << 3, "before_swap_returns_delta"),
+    (1 << 2, "after_swap_returns_delta"),
+    (1 << 1, "after_add_liquidity_returns_delta"),
+    (1 << 0, "after_remove_liquidity_returns_delta"),
+];
+
+/// Permissions that change LP economics, and what they mean for the figures
+const PERMISSION_CAVEATS: &[(&str, &str)] = &[
+    (
+        "before_swap_returns_delta",
+        "The hook can fill swaps itself, so recorded volume may not have traded against LP \
+         liquidity and fee income may be overstated",
+    ),
+    (
+        "after_swap_returns_delta",
+        "The hook can take a share of each swap's output, which recorded volumes don't reflect",
+    ),
+    (
+        "after_add_liquidity_returns_delta",
+        "The hook can charge or credit LPs on deposit; those amounts aren't in P&L",
+    ),
+    (
+        "after_remove_liquidity_returns_delta",
+        "The hook can charge or credit LPs on withdrawal; those amounts aren't in P&L",
+    ),
+    ("before_remove_liquidity", "The hook can block or delay withdrawals"),
+];
+
+/// Permissions of the hook at `address`, by name (None if it isn't an address)
+pub fn hook_permissions(address: &str) -> Option<Vec<&'static str>> {
+    let hex = address.strip_prefix("0x")?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bits = u16::from_str_radix(&hex[36..], 16).ok()?;
+    Some(
+        HOOK_PERMISSIONS
+            .iter()
+            .filter(|(flag, _)| bits & flag != 0)
+            .map(|(_, name)| *name)
+            .collect(),
+    )
+}
+
+/// Well-known families of v4 hooks whose effect on LPs is understood
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookKind {
+    /// Limit orders held as single-tick positions by the hook
+    LimitOrder,
+    /// Time-weighted AMM executing long-term orders against the pool
+    Twamm,
+    /// Sets the LP fee per swap
+    DynamicFee,
+}
+
+impl HookKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookKind::LimitOrder => "limit_order",
+            HookKind::Twamm => "twamm",
+            HookKind::DynamicFee => "dynamic_fee",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "limit_order" => Some(HookKind::LimitOrder),
+            "twamm" => Some(HookKind::Twamm),
+            "dynamic_fee" => Some(HookKind::DynamicFee),
+            _ => None,
+        }
+    }
+
+    /// How pools with this kind of hook differ from what the analytics assume
+    pub fn caveats(&self) -> &'static [&'static str] {
+        match self {
+            HookKind::LimitOrder => LIMIT_ORDER_CAVEATS,
+            HookKind::Twamm => TWAMM_CAVEATS,
+            HookKind::DynamicFee => DYNAMIC_FEE_CAVEATS,
+        }
+    }
+}
+
+const LIMIT_ORDER_CAVEATS: &[&str] = &[
+    "Resting limit orders are single-tick positions the hook withdraws once filled, so in-range \
+     liquidity and a position's fee share swing as orders fill",
+];
+
+const TWAMM_CAVEATS: &[&str] = &[
+    "Long-term orders execute inside the hook before each swap; that volume isn't recorded as \
+     swaps, so fee income is understated",
+    "Large long-term orders push the price steadily one way, adding impermanent loss",
+];
+
+const DYNAMIC_FEE_CAVEATS: &[&str] = &[
+    "The hook sets the LP fee per swap; fees use each swap's recorded fee and fall back to the \
+     pool's fee override, or 0.3% without one, where none was recorded",
+];
+
+/// A hook contract identified by the operator
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KnownHook {
+    pub address: String,
+    pub kind: HookKind,
+    pub name: Option<String>,
+}
+
+/// What a pool's hook means for its positions' figures
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookAnnotation {
+    pub address: String,
+    /// Set when the hook is in the registry
+    pub kind: Option<HookKind>,
+    pub name: Option<String>,
+    /// Callbacks the hook is allowed to run, from its address
+    pub permissions: Vec<&'static str>,
+    /// Fee model used for the pool's fee figures
+    pub fee_model: &'static str,
+    pub caveats: Vec<String>,
+}
+
+/// Registry of known hook contracts by address
+#[derive(Debug, Clone, Default)]
+pub struct HookRegistry {
+    hooks: HashMap<String, KnownHook>,
+}
+
+impl HookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `address=kind[:name]` entries, comma-separated
+    ///
+    /// e.g. `0xabc...=twamm:Paradigm TWAMM,0xdef...=limit_order`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut registry = Self::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (address, rest) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Hook entry must be address=kind: {}", entry))?;
+            let (kind, name) = match rest.split_once(':') {
+                Some((kind, name)) => (kind, Some(name.trim().to_string())),
+                None => (rest, None),
+            };
+            let kind = HookKind::parse(kind.trim())
+                .ok_or_else(|| anyhow!("Unknown hook kind in {}", entry))?;
+            if hook_permissions(address.trim()).is_none() {
+                return Err(anyhow!("Invalid hook address: {}", address));
+            }
+            registry.register(KnownHook { address: address.trim().to_string(), kind, name });
+        }
+        Ok(registry)
+    }
+
+    /// Registry from `KNOWN_HOOKS`, plus every `DYNAMIC_FEE_HOOKS` address as a dynamic-fee hook
+    pub fn from_env() -> Result<Self> {
+        let mut registry = match std::env::var("KNOWN_HOOKS") {
+            Ok(spec) => Self::parse(&spec)?,
+            Err(_) => Self::new(),
+        };
+        for hook in std::env::var("DYNAMIC_FEE_HOOKS").unwrap_or_default().split(',') {
+            let hook = hook.trim();
+            if !hook.is_empty() && registry.get(hook).is_none() {
+                registry.register(KnownHook {
+                    address: hook.to_string(),
+                    kind: HookKind::DynamicFee,
+                    name: None,
+                });
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, hook: KnownHook) {
+        self.hooks.insert(hook.address.to_lowercase(), hook);
+    }
+
+    pub fn get(&self, address: &str) -> Option<&KnownHook> {
+        self.hooks.get(&address.to_lowercase())
+    }
+
+    /// Use the dynamic fee model for every registered dynamic-fee hook
+    pub fn register_fee_models(&self, fee_models: &mut FeeModelRegistry) {
+        for hook in self.hooks.values().filter(|h| h.kind == HookKind::DynamicFee) {
+            fee_models.register(&hook.address, Arc::new(DynamicFeeModel::default()));
+        }
+    }
+
+    /// Caveats and adjustments for positions in `pool`; None for pools without hooks
+    ///
+    /// Registered hooks contribute their kind's caveats. Any hook, known or
+    /// not, adds caveats for the permissions its address grants that change
+    /// LP economics.
+    pub fn annotate(&self, pool: &Pool, fee_models: &FeeModelRegistry) -> Option<HookAnnotation> {
+        if !pool.has_hooks() {
+            return None;
+        }
+        let known = self.get(&pool.hooks);
+        let permissions = hook_permissions(&pool.hooks).unwrap_or_default();
+
+        let mut caveats: Vec<String> = Vec::new();
+        if let Some(hook) = known {
+            caveats.extend(hook.kind.caveats().iter().map(|c| c.to_string()));
+        }
+        if pool.is_dynamic_fee() && known.is_none_or(|h| h.kind != HookKind::DynamicFee) {
+            caveats.extend(HookKind::DynamicFee.caveats().iter().map(|c| c.to_string()));
+        }
+        for (permission, caveat) in PERMISSION_CAVEATS {
+            if permissions.contains(permission) {
+                caveats.push(caveat.to_string());
+            }
+        }
+
+        Some(HookAnnotation {
+            address: pool.hooks.clone(),
+            kind: known.map(|h| h.kind),
+            name: known.and_then(|h| h.name.clone()),
+            permissions,
+            fee_model: fee_models.model_for(pool).name(),
+            caveats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use stillwater_models::{DYNAMIC_FEE_FLAG, NO_HOOKS};
+
+    const TWAMM_HOOK: &str = "0x1111111111111111111111111111111111112888";
+
+    fn create_test_pool(fee_tier: i32, hooks: &str) -> Pool {
+        Pool { fee_tier, hooks: hooks.to_string(), ..testing::create_test_pool() }
+    }
+
+    #[test]
+    fn test_permissions_from_address_bits() {
+        // 0x2888: before_initialize, before_add_liquidity, before_swap, before_swap_returns_delta
+        assert_eq!(
+            hook_permissions(TWAMM_HOOK).unwrap(),
+            vec![
+                "before_initialize",
+                "before_add_liquidity",
+                "before_swap",
+                "before_swap_returns_delta"
+            ]
+        );
+        assert!(hook_permissions(NO_HOOKS).unwrap().is_empty());
+        assert!(hook_permissions("0x1234").is_none());
+    }
+
+    #[test]
+    fn test_annotates_registered_hook() {
+        let spec = format!("{}=twamm:Test TWAMM", TWAMM_HOOK.to_uppercase().replace("0X", "0x"));
+        let registry = HookRegistry::parse(&spec).unwrap();
+        let fee_models = FeeModelRegistry::new();
+
+        let annotation = registry.annotate(&create_test_pool(3000, TWAMM_HOOK), &fee_models);
+        let annotation = annotation.unwrap();
+        assert_eq!(annotation.kind, Some(HookKind::Twamm));
+        assert_eq!(annotation.name.as_deref(), Some("Test TWAMM"));
+        assert_eq!(annotation.fee_model, "static");
+        // Two TWAMM caveats plus before_swap_returns_delta
+        assert_eq!(annotation.caveats.len(), 3);
+
+        assert!(registry.annotate(&create_test_pool(3000, NO_HOOKS), &fee_models).is_none());
+        assert!(HookRegistry::parse("0xabc=twamm").is_err());
+        assert!(HookRegistry::parse(&format!("{}=unknown", TWAMM_HOOK)).is_err());
+    }
+
+    #[test]
+    fn test_dynamic_fee_hooks_use_dynamic_model() {
+        let hook = "0x2222222222222222222222222222222222220000";
+        let registry = HookRegistry::parse(&format!("{}=dynamic_fee", hook)).unwrap();
+        let mut fee_models = FeeModelRegistry::new();
+        registry.register_fee_models(&mut fee_models);
+
+        // Registered by address even without the pool's dynamic fee flag
+        let annotation = registry.annotate(&create_test_pool(3000, hook), &fee_models).unwrap();
+        assert_eq!(annotation.fee_model, "dynamic");
+        assert_eq!(annotation.caveats.len(), 1);
+        assert!(annotation.permissions.is_empty());
+
+        // Unknown hook on a flagged pool still gets the dynamic fee caveat
+        let unknown = "0x3333333333333333333333333333333333330000";
+        let annotation =
+            registry.annotate(&create_test_pool(DYNAMIC_FEE_FLAG, unknown), &fee_models).unwrap();
+        assert_eq!(annotation.kind, None);
+        assert_eq!(annotation.caveats.len(), 1);
+    }
+}
@@ -0,0 +1,380 @@
+use alloy::primitives::I256;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use stillwater_models::{Address, LiquidityChange, LiquidityEvent, Pool, RangeLiquidity, Swap};
+
+use crate::fees::{FeeModel, lp_fee_rate};
+use crate::heatmap::swap_tick;
+use crate::pnl::swap_volume;
+use crate::units::{convert_signed_amount, raw_to_units};
+use crate::utils::is_in_range;
+
+/// Longest liquidity may stay in and still count as JIT by default (the same
+/// or the adjacent block on mainnet)
+pub const DEFAULT_JIT_MAX_LIFETIME_SECS: i64 = 24;
+
+/// Share of in-range liquidity an add must reach during a swap to count as JIT by default
+pub const DEFAULT_JIT_MIN_LIQUIDITY_SHARE: Decimal = Decimal::from_parts(5, 0, 0, false, 1);
+
+/// What counts as just-in-time liquidity
+#[derive(Debug, Clone, Serialize)]
+pub struct JitConfig {
+    /// Longest time between an add and its removal
+    pub max_lifetime_secs: i64,
+    /// Share (0-1) of in-range liquidity the add must hold during at least one swap
+    pub min_liquidity_share: Decimal,
+}
+
+impl Default for JitConfig {
+    fn default() -> Self {
+        Self {
+            max_lifetime_secs: DEFAULT_JIT_MAX_LIFETIME_SECS,
+            min_liquidity_share: DEFAULT_JIT_MIN_LIQUIDITY_SHARE,
+        }
+    }
+}
+
+impl JitConfig {
+    /// Config from `JIT_MAX_LIFETIME_SECS` and `JIT_MIN_LIQUIDITY_SHARE`
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(secs) = std::env::var("JIT_MAX_LIFETIME_SECS") {
+            config.max_lifetime_secs =
+                secs.trim().parse().context("JIT_MAX_LIFETIME_SECS must be a number")?;
+            if config.max_lifetime_secs < 0 {
+                bail!("JIT_MAX_LIFETIME_SECS must not be negative");
+            }
+        }
+        if let Ok(share) = std::env::var("JIT_MIN_LIQUIDITY_SHARE") {
+            config.min_liquidity_share = Decimal::from_str(share.trim())
+                .context("JIT_MIN_LIQUIDITY_SHARE must be a number")?;
+            if config.min_liquidity_share < Decimal::ZERO
+                || config.min_liquidity_share > Decimal::ONE
+            {
+                bail!("JIT_MIN_LIQUIDITY_SHARE must be between 0 and 1");
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Liquidity added right before swaps and removed right after them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JitEpisode {
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Liquidity added, raw
+    pub liquidity: Decimal,
+    pub add_event_id: String,
+    pub remove_event_id: String,
+    pub added_at: DateTime<Utc>,
+    pub removed_at: DateTime<Utc>,
+    /// Swaps that executed inside the range while the liquidity was in
+    pub swaps: usize,
+    /// Largest share of in-range liquidity it held during one of them
+    pub peak_liquidity_share: Decimal,
+    /// LP fees it took from those swaps, in whole token1; the liquidity
+    /// already in range would have split them otherwise
+    pub fees_captured: Decimal,
+}
+
+/// JIT activity in a pool over a window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JitReport {
+    pub episodes: Vec<JitEpisode>,
+    /// LP fees of every swap in the window, in whole token1
+    pub total_fees: Decimal,
+    /// Of those, the fees JIT liquidity captured
+    pub jit_fees: Decimal,
+    /// `jit_fees` over `total_fees`, None without fees
+    pub jit_fee_share: Option<Decimal>,
+}
+
+/// An add paired with the removal that took it back out
+struct Candidate<'a> {
+    add: &'a LiquidityEvent,
+    remove: &'a LiquidityEvent,
+    liquidity: Decimal,
+    swaps: usize,
+    peak_share: Decimal,
+    fees: Decimal,
+}
+
+impl Candidate<'_> {
+    fn active_at(&self, time: DateTime<Utc>, tick: i32) -> bool {
+        self.add.timestamp <= time
+            && time <= self.remove.timestamp
+            && is_in_range(tick, self.add.tick_lower, self.add.tick_upper)
+    }
+}
+
+/// Find JIT liquidity in a pool by diffing its recorded tick liquidity around each swap
+///
+/// An add is JIT when the same owner removes at least as much from the same
+/// range within `max_lifetime_secs`, a swap executes inside the range in
+/// between, and the add held at least `min_liquidity_share` of the range's
+/// liquidity at that swap. Each swap's LP fees are split pro rata between the
+/// liquidity in range at its execution tick: `ranges_before` (the pool's
+/// liquidity at the start of the window) plus the events before the swap.
+///
+/// Only recorded liquidity events are known, so liquidity added before the
+/// first sync is missing and JIT shares are upper bounds.
+pub fn detect_jit(
+    pool: &Pool,
+    model: &dyn FeeModel,
+    ranges_before: &[RangeLiquidity],
+    events: &[LiquidityEvent],
+    swaps: &[Swap],
+    config: &JitConfig,
+) -> JitReport {
+    let mut events: Vec<&LiquidityEvent> = events.iter().collect();
+    events.sort_by_key(|e| e.timestamp);
+    let mut swaps: Vec<&Swap> = swaps.iter().collect();
+    swaps.sort_by_key(|s| s.timestamp);
+
+    let (mut candidates, passive_events) = pair_adds_with_removals(&events, config);
+
+    // Replay the other events swap by swap to know the liquidity already in range
+    let mut ranges: HashMap<(i32, i32), I256> =
+        ranges_before.iter().map(|r| ((r.tick_lower, r.tick_upper), r.liquidity)).collect();
+    let mut next_event = 0;
+    let mut total_fees = Decimal::ZERO;
+
+    for swap in swaps {
+        let fee =
+            raw_to_units(swap_volume(swap) * lp_fee_rate(model, pool, swap), pool.token1_decimals);
+        total_fees += fee;
+
+        while let Some(event) =
+            passive_events.get(next_event).filter(|e| e.timestamp < swap.timestamp)
+        {
+            let liquidity = ranges.entry((event.tick_lower, event.tick_upper)).or_default();
+            *liquidity = liquidity.saturating_add(event.liquidity_delta);
+            next_event += 1;
+        }
+
+        let Some(tick) = swap_tick(swap) else {
+            continue;
+        };
+        let active: Vec<usize> = (0..candidates.len())
+            .filter(|i| candidates[*i].active_at(swap.timestamp, tick))
+            .collect();
+        if active.is_empty() {
+            continue;
+        }
+
+        let passive: Decimal = ranges
+            .iter()
+            .filter(|((lower, upper), liquidity)| {
+                is_in_range(tick, *lower, *upper) && liquidity.is_positive()
+            })
+            .map(|(_, liquidity)| convert_signed_amount(*liquidity, 0))
+            .sum();
+        let jit: Decimal = active.iter().map(|i| candidates[*i].liquidity).sum();
+        let in_range = passive + jit;
+
+        for i in active {
+            let candidate = &mut candidates[i];
+            let share = candidate.liquidity.checked_div(in_range).unwrap_or(Decimal::ZERO);
+            candidate.swaps += 1;
+            candidate.peak_share = candidate.peak_share.max(share);
+            candidate.fees += fee * share;
+        }
+    }
+
+    let episodes: Vec<JitEpisode> = candidates
+        .into_iter()
+        .filter(|c| c.swaps > 0 && c.peak_share >= config.min_liquidity_share)
+        .map(|c| JitEpisode {
+            owner: c.add.owner,
+            tick_lower: c.add.tick_lower,
+            tick_upper: c.add.tick_upper,
+            liquidity: c.liquidity,
+            add_event_id: c.add.event_id.clone(),
+            remove_event_id: c.remove.event_id.clone(),
+            added_at: c.add.timestamp,
+            removed_at: c.remove.timestamp,
+            swaps: c.swaps,
+            peak_liquidity_share: c.peak_share,
+            fees_captured: c.fees,
+        })
+        .collect();
+
+    let jit_fees: Decimal = episodes.iter().map(|e| e.fees_captured).sum();
+    JitReport { episodes, total_fees, jit_fees, jit_fee_share: jit_fees.checked_div(total_fees) }
+}
+
+/// Pair each add with the first removal undoing it in time; the rest are passive
+fn pair_adds_with_removals<'a>(
+    events: &[&'a LiquidityEvent],
+    config: &JitConfig,
+) -> (Vec<Candidate<'a>>, Vec<&'a LiquidityEvent>) {
+    let max_lifetime = Duration::seconds(config.max_lifetime_secs);
+    let mut paired = vec![false; events.len()];
+    let mut candidates = Vec::new();
+
+    for (i, add) in events.iter().enumerate() {
+        if add.change() != LiquidityChange::Add || paired[i] {
+            continue;
+        }
+        let removal = (i + 1..events.len())
+            .take_while(|j| events[*j].timestamp - add.timestamp <= max_lifetime)
+            .find(|j| {
+                let remove = events[*j];
+                !paired[*j]
+                    && remove.change() == LiquidityChange::Remove
+                    && remove.owner == add.owner
+                    && remove.tick_lower == add.tick_lower
+                    && remove.tick_upper == add.tick_upper
+                    && remove.liquidity_delta.unsigned_abs() >= add.liquidity_delta.unsigned_abs()
+            });
+        if let Some(j) = removal {
+            paired[i] = true;
+            paired[j] = true;
+            candidates.push(Candidate {
+                add,
+                remove: events[j],
+                liquidity: convert_signed_amount(add.liquidity_delta, 0),
+                swaps: 0,
+                peak_share: Decimal::ZERO,
+                fees: Decimal::ZERO,
+            });
+        }
+    }
+
+    let passive = events.iter().zip(paired).filter(|(_, p)| !p).map(|(e, _)| *e).collect();
+    (candidates, passive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::StaticFeeModel;
+    use crate::testing;
+    use chrono::TimeZone;
+
+    fn create_test_pool() -> Pool {
+        Pool { token0_decimals: 0, token1_decimals: 0, ..testing::create_test_pool() }
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap() + Duration::seconds(seconds)
+    }
+
+    const JIT: u8 = 0x1;
+    const LP: u8 = 0x2;
+    const OTHER: u8 = 0x3;
+
+    fn event(id: &str, owner: u8, delta: i64, seconds: i64) -> LiquidityEvent {
+        LiquidityEvent {
+            event_id: id.to_string(),
+            owner: Address::repeat_byte(owner),
+            pool_id: "0xpool".to_string(),
+            tick_lower: -600,
+            tick_upper: 600,
+            liquidity_delta: I256::try_from(delta).unwrap(),
+            timestamp: at(seconds),
+        }
+    }
+
+    /// A swap at price 1 (tick 0) paying 6 token1 in LP fees at 0.3%
+    fn swap(seconds: i64) -> Swap {
+        Swap {
+            id: seconds,
+            tx_hash: format!("0x{}", seconds),
+            pool_id: "0xpool".to_string(),
+            amount0: I256::try_from(1000).unwrap(),
+            amount1: I256::try_from(-1000).unwrap(),
+            fee: None,
+            timestamp: at(seconds),
+        }
+    }
+
+    fn passive(liquidity: i64) -> Vec<RangeLiquidity> {
+        vec![RangeLiquidity {
+            tick_lower: -6000,
+            tick_upper: 6000,
+            liquidity: I256::try_from(liquidity).unwrap(),
+        }]
+    }
+
+    #[test]
+    fn test_detects_jit_around_swap() {
+        let events = [event("add", JIT, 3000, 10), event("remove", JIT, -3000, 12)];
+        let swaps = [swap(11), swap(100)];
+        let report = detect_jit(
+            &create_test_pool(),
+            &StaticFeeModel,
+            &passive(1000),
+            &events,
+            &swaps,
+            &JitConfig::default(),
+        );
+
+        assert_eq!(report.episodes.len(), 1);
+        let episode = &report.episodes[0];
+        assert_eq!(episode.swaps, 1);
+        assert_eq!(episode.peak_liquidity_share, Decimal::new(75, 2));
+        // 3000 of 4000 in range takes 3/4 of the 6 token1 fee
+        assert_eq!(episode.fees_captured, Decimal::new(45, 1));
+        assert_eq!(report.total_fees, Decimal::from(12));
+        assert_eq!(report.jit_fee_share, Some(Decimal::new(375, 3)));
+    }
+
+    #[test]
+    fn test_long_lived_liquidity_is_not_jit() {
+        let events = [event("add", LP, 3000, 10), event("remove", LP, -3000, 3600)];
+        let report = detect_jit(
+            &create_test_pool(),
+            &StaticFeeModel,
+            &passive(1000),
+            &events,
+            &[swap(11)],
+            &JitConfig::default(),
+        );
+        assert!(report.episodes.is_empty());
+        assert_eq!(report.jit_fees, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_small_adds_and_other_owners_are_ignored() {
+        // Too small a share of the range's liquidity
+        let events = [event("add", JIT, 100, 10), event("remove", JIT, -100, 12)];
+        let config = JitConfig::default();
+        let pool = create_test_pool();
+        let report =
+            detect_jit(&pool, &StaticFeeModel, &passive(1000), &events, &[swap(11)], &config);
+        assert!(report.episodes.is_empty());
+
+        // A removal by someone else doesn't undo the add
+        let events = [event("add", JIT, 3000, 10), event("remove", OTHER, -3000, 12)];
+        let report =
+            detect_jit(&pool, &StaticFeeModel, &passive(1000), &events, &[swap(11)], &config);
+        assert!(report.episodes.is_empty());
+    }
+
+    #[test]
+    fn test_passive_events_count_toward_in_range_liquidity() {
+        // Liquidity added during the window, before the JIT add, dilutes its share
+        let events = [
+            event("lp", LP, 2000, 1),
+            event("add", JIT, 3000, 10),
+            event("remove", JIT, -3000, 12),
+        ];
+        let report = detect_jit(
+            &create_test_pool(),
+            &StaticFeeModel,
+            &passive(1000),
+            &events,
+            &[swap(11)],
+            &JitConfig::default(),
+        );
+        assert_eq!(report.episodes[0].peak_liquidity_share, Decimal::new(5, 1));
+        assert_eq!(report.jit_fees, Decimal::from(3));
+    }
+}
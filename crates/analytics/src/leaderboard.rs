@@ -0,0 +1,285 @@
+use chrono::Duration;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use stillwater_models::{Address, Position, SnapshotWindow};
+
+use crate::liquidity::{range_prices, value_per_liquidity};
+use crate::pnl::{calculate_impermanent_loss, calculate_net_pnl};
+use crate::utils::RangeError;
+
+/// Performance of a single position over a window
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionPerformance {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub owner: Address,
+    pub pool_id: String,
+    pub fees_earned: Decimal,
+    pub impermanent_loss: Decimal,
+    pub net_pnl: Decimal,
+    /// Position value at the start of the window
+    pub capital: Decimal,
+    /// Capital times the share of the window it spent in range
+    pub in_range_capital: Decimal,
+    /// Annualized net P&L over capital (None when capital is unknown)
+    pub apr: Option<Decimal>,
+    pub fee_apr: FeeApr,
+}
+
+/// Aggregated performance of an owner's positions over a window
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerPerformance {
+    pub owner: Address,
+    pub positions: usize,
+    pub fees_earned: Decimal,
+    pub impermanent_loss: Decimal,
+    pub net_pnl: Decimal,
+    pub capital: Decimal,
+    pub in_range_capital: Decimal,
+    pub apr: Option<Decimal>,
+    pub fee_apr: FeeApr,
+}
+
+/// Annualized fees over all the capital deposited and over the capital actually earning
+///
+/// Concentrated liquidity only earns while the price is in range, so fees
+/// over the full principal understate what the deployed capital returned.
+/// `in_range` divides by the capital weighted by its time in range instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct FeeApr {
+    /// Fees over the full principal (None when capital is unknown)
+    pub principal: Option<Decimal>,
+    /// Fees over the in-range capital (None when never seen in range)
+    pub in_range: Option<Decimal>,
+}
+
+impl FeeApr {
+    pub fn new(
+        fees: Decimal,
+        capital: Decimal,
+        in_range_capital: Decimal,
+        period: Duration,
+    ) -> Self {
+        Self {
+            principal: annualized_return(fees, capital, period),
+            in_range: annualized_return(fees, in_range_capital, period),
+        }
+    }
+}
+
+/// Metric to rank leaderboard entries by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankBy {
+    NetPnl,
+    Apr,
+}
+
+/// Compute a position's performance between the first and last snapshot of a window
+///
+/// Gas isn't attributed to windows, so net P&L here is fees minus IL.
+pub fn summarize_performance(
+    position: &Position,
+    window: &SnapshotWindow,
+) -> Result<PositionPerformance, RangeError> {
+    let fees_earned = (window.end_fees - window.start_fees).max(Decimal::ZERO);
+    let impermanent_loss =
+        calculate_impermanent_loss(position, window.start_price, window.end_price)?;
+    let net_pnl = calculate_net_pnl(fees_earned, impermanent_loss, Decimal::ZERO);
+
+    let (price_lower, price_upper) = range_prices(position.tick_lower, position.tick_upper);
+    let capital = Decimal::from_str(&position.liquidity.to_string())
+        .ok()
+        .and_then(|l| {
+            l.checked_mul(value_per_liquidity(window.start_price, price_lower, price_upper))
+        })
+        .unwrap_or(Decimal::ZERO);
+    let in_range_capital = capital * window.in_range_share;
+    let period = window.end_time - window.start_time;
+
+    Ok(PositionPerformance {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        owner: position.owner,
+        pool_id: position.pool_id.clone(),
+        fees_earned,
+        impermanent_loss,
+        net_pnl,
+        capital,
+        in_range_capital,
+        apr: annualized_return(net_pnl, capital, period),
+        fee_apr: FeeApr::new(fees_earned, capital, in_range_capital, period),
+    })
+}
+
+/// Aggregate position performance per owner
+pub fn aggregate_by_owner(
+    performances: &[PositionPerformance],
+    window: Duration,
+) -> Vec<OwnerPerformance> {
+    let mut by_owner: HashMap<Address, OwnerPerformance> = HashMap::new();
+
+    for p in performances {
+        let entry = by_owner.entry(p.owner).or_insert_with(|| OwnerPerformance {
+            owner: p.owner,
+            positions: 0,
+            fees_earned: Decimal::ZERO,
+            impermanent_loss: Decimal::ZERO,
+            net_pnl: Decimal::ZERO,
+            capital: Decimal::ZERO,
+            in_range_capital: Decimal::ZERO,
+            apr: None,
+            fee_apr: FeeApr::default(),
+        });
+        entry.positions += 1;
+        entry.fees_earned += p.fees_earned;
+        entry.impermanent_loss += p.impermanent_loss;
+        entry.net_pnl += p.net_pnl;
+        entry.capital += p.capital;
+        entry.in_range_capital += p.in_range_capital;
+    }
+
+    by_owner
+        .into_values()
+        .map(|mut o| {
+            o.apr = annualized_return(o.net_pnl, o.capital, window);
+            o.fee_apr = FeeApr::new(o.fees_earned, o.capital, o.in_range_capital, window);
+            o
+        })
+        .collect()
+}
+
+/// Rank positions by a metric (descending = top gainers first)
+pub fn rank_positions(
+    mut performances: Vec<PositionPerformance>,
+    by: RankBy,
+    descending: bool,
+) -> Vec<PositionPerformance> {
+    performances.sort_by(|a, b| {
+        let ord = match by {
+            RankBy::NetPnl => a.net_pnl.cmp(&b.net_pnl),
+            RankBy::Apr => a.apr.cmp(&b.apr),
+        };
+        if descending { ord.reverse() } else { ord }
+    });
+    performances
+}
+
+/// Rank owners by a metric (descending = top gainers first)
+pub fn rank_owners(
+    mut owners: Vec<OwnerPerformance>,
+    by: RankBy,
+    descending: bool,
+) -> Vec<OwnerPerformance> {
+    owners.sort_by(|a, b| {
+        let ord = match by {
+            RankBy::NetPnl => a.net_pnl.cmp(&b.net_pnl),
+            RankBy::Apr => a.apr.cmp(&b.apr),
+        };
+        if descending { ord.reverse() } else { ord }
+    });
+    owners
+}
+
+/// Simple annualized return of `pnl` on `capital` over `period`
+pub fn annualized_return(pnl: Decimal, capital: Decimal, period: Duration) -> Option<Decimal> {
+    let seconds = period.num_seconds();
+    if capital <= Decimal::ZERO || seconds <= 0 {
+        return None;
+    }
+
+    let year = Decimal::from(365 * 24 * 3600);
+    (pnl / capital).checked_mul(year / Decimal::from(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use chrono::Utc;
+
+    fn create_test_position(id: i64, owner: Address) -> Position {
+        Position { id, nft_id: id.to_string(), owner, ..testing::create_test_position() }
+    }
+
+    fn create_test_window(position_id: i64, fees: i64) -> SnapshotWindow {
+        let end = Utc::now();
+        SnapshotWindow {
+            position_id,
+            start_time: end - Duration::days(7),
+            end_time: end,
+            start_fees: Decimal::ZERO,
+            end_fees: Decimal::from(fees),
+            start_price: Decimal::ONE,
+            end_price: Decimal::ONE,
+            in_range_share: Decimal::ONE,
+        }
+    }
+
+    #[test]
+    fn test_summarize_performance() {
+        let position = create_test_position(1, Address::repeat_byte(0xa));
+        let perf = summarize_performance(&position, &create_test_window(1, 100)).unwrap();
+
+        assert_eq!(perf.fees_earned, Decimal::from(100));
+        assert_eq!(perf.impermanent_loss, Decimal::ZERO);
+        assert_eq!(perf.net_pnl, Decimal::from(100));
+        assert!(perf.capital > Decimal::ZERO);
+        assert!(perf.apr.unwrap() > Decimal::ZERO);
+        assert_eq!(perf.fee_apr.principal, perf.fee_apr.in_range);
+    }
+
+    #[test]
+    fn test_fee_apr_on_in_range_capital() {
+        let position = create_test_position(1, Address::repeat_byte(0xa));
+        let mut window = create_test_window(1, 100);
+        window.in_range_share = Decimal::new(25, 2);
+        let perf = summarize_performance(&position, &window).unwrap();
+
+        // In range a quarter of the time: the capital at work earned four times the rate
+        assert_eq!(perf.in_range_capital, perf.capital / Decimal::from(4));
+        let principal = perf.fee_apr.principal.unwrap();
+        assert_eq!(
+            perf.fee_apr.in_range.unwrap().round_dp(10),
+            (principal * Decimal::from(4)).round_dp(10)
+        );
+
+        window.in_range_share = Decimal::ZERO;
+        let never = summarize_performance(&position, &window).unwrap();
+        assert!(never.fee_apr.principal.is_some());
+        assert!(never.fee_apr.in_range.is_none());
+    }
+
+    #[test]
+    fn test_rank_and_aggregate() {
+        let perfs: Vec<PositionPerformance> = [(1, 0xa, 50), (2, 0xb, 200), (3, 0xa, 100)]
+            .iter()
+            .map(|(id, owner, fees)| {
+                summarize_performance(
+                    &create_test_position(*id, Address::repeat_byte(*owner)),
+                    &create_test_window(*id, *fees),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let ranked = rank_positions(perfs.clone(), RankBy::NetPnl, true);
+        assert_eq!(ranked[0].position_id, 2);
+        assert_eq!(ranked[2].position_id, 1);
+
+        let owners =
+            rank_owners(aggregate_by_owner(&perfs, Duration::days(7)), RankBy::NetPnl, true);
+        assert_eq!(owners.len(), 2);
+        assert_eq!(owners[0].owner, Address::repeat_byte(0xb));
+        assert_eq!(owners[1].positions, 2);
+        assert_eq!(owners[1].net_pnl, Decimal::from(150));
+    }
+
+    #[test]
+    fn test_annualized_return() {
+        let apr = annualized_return(Decimal::ONE, Decimal::from(100), Duration::days(365)).unwrap();
+        assert_eq!(apr, Decimal::new(1, 2));
+        assert!(annualized_return(Decimal::ONE, Decimal::ZERO, Duration::days(1)).is_none());
+    }
+}
@@ -0,0 +1,435 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use stillwater_models::{GasExpense, Pool, Position, PositionSnapshot};
+
+use crate::liquidity::{amounts_for_liquidity, range_prices};
+use crate::units::raw_to_units;
+
+/// Plain-text accounting format to export to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerFormat {
+    Beancount,
+    /// ledger-cli (also read by hledger)
+    Ledger,
+}
+
+impl LedgerFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerFormat::Beancount => "beancount",
+            LedgerFormat::Ledger => "ledger",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "beancount" => Some(LedgerFormat::Beancount),
+            "ledger" => Some(LedgerFormat::Ledger),
+            _ => None,
+        }
+    }
+}
+
+/// Account names and commodities used in the export
+#[derive(Debug, Clone)]
+pub struct LedgerConfig {
+    /// Wallet holding tokens outside of positions
+    pub wallet_account: String,
+    /// Parent account of per-position accounts (`<parent>:Position<id>`)
+    pub positions_account: String,
+    pub fee_income_account: String,
+    /// Books the difference between withdrawn and deposited tokens on close
+    pub position_pnl_account: String,
+    pub gas_account: String,
+    /// Commodity gas is paid in
+    pub native_commodity: String,
+    /// Commodity names by lowercase token address; unknown tokens get `TKN<address prefix>`
+    pub symbols: HashMap<String, String>,
+}
+
+impl Default for LedgerConfig {
+    fn default() -> Self {
+        Self {
+            wallet_account: "Assets:Crypto:Wallet".to_string(),
+            positions_account: "Assets:Crypto:UniswapV4".to_string(),
+            fee_income_account: "Income:Crypto:UniswapV4:Fees".to_string(),
+            position_pnl_account: "Income:Crypto:UniswapV4:PositionPnL".to_string(),
+            gas_account: "Expenses:Crypto:Gas".to_string(),
+            native_commodity: "ETH".to_string(),
+            symbols: HashMap::new(),
+        }
+    }
+}
+
+/// Everything recorded about one position, as input to the export
+#[derive(Debug, Clone)]
+pub struct PositionActivity<'a> {
+    pub position: &'a Position,
+    pub pool: &'a Pool,
+    /// Snapshots sorted by timestamp
+    pub snapshots: &'a [PositionSnapshot],
+    pub gas_expenses: &'a [GasExpense],
+}
+
+/// One leg of a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posting {
+    pub account: String,
+    pub amount: Decimal,
+    pub commodity: String,
+}
+
+/// A balanced double-entry transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub date: NaiveDate,
+    pub narration: String,
+    /// Transaction hash, when the entry corresponds to one transaction
+    pub tx_hash: Option<String>,
+    pub postings: Vec<Posting>,
+}
+
+/// Build ledger entries from positions' cash flows, fee income and gas
+///
+/// - Opening a position moves the tokens its liquidity was worth at the first
+///   snapshot's price from the wallet into the position's account.
+/// - Fee income is booked daily from the growth of snapshot fees, in token1
+///   (the unit snapshot fees are recorded in), into `<position>:Fees`.
+/// - Closing (first zero-liquidity snapshot) returns the tokens the liquidity
+///   was worth at that price; the change against the deposit is position P&L.
+/// - Gas is an expense paid from the wallet in the native commodity.
+///
+/// Positions without snapshots have no known entry price, so only their gas
+/// is exported. Entries are sorted by date.
+pub fn build_ledger_entries(
+    activity: &[PositionActivity],
+    config: &LedgerConfig,
+) -> Vec<LedgerEntry> {
+    let mut entries = Vec::new();
+
+    for a in activity {
+        let position = a.position;
+        let account = format!("{}:Position{}", config.positions_account, position.id);
+        let token0 = commodity(config, &a.pool.token0);
+        let token1 = commodity(config, &a.pool.token1);
+        let deposit = a.snapshots.first().and_then(|s| token_amounts(a, s.price));
+        let close = a.snapshots.iter().find(|s| s.liquidity.is_zero());
+
+        if let Some((amount0, amount1)) = deposit {
+            entries.push(LedgerEntry {
+                date: position.created_at.date_naive(),
+                narration: format!("Open position {} (nft {})", position.id, position.nft_id),
+                tx_hash: None,
+                postings: transfer(
+                    &config.wallet_account,
+                    &account,
+                    &[(amount0, &token0), (amount1, &token1)],
+                ),
+            });
+        }
+
+        // Cumulative fees -> daily income
+        let mut daily_fees: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+        let mut previous = Decimal::ZERO;
+        for s in a.snapshots.iter().filter(|s| close.is_none_or(|c| s.timestamp <= c.timestamp)) {
+            let earned = (s.fees_earned - previous).max(Decimal::ZERO);
+            previous = previous.max(s.fees_earned);
+            if !earned.is_zero() {
+                *daily_fees.entry(s.timestamp.date_naive()).or_insert(Decimal::ZERO) += earned;
+            }
+        }
+        for (date, raw) in daily_fees {
+            let fees = raw_to_units(raw, a.pool.token1_decimals);
+            entries.push(LedgerEntry {
+                date,
+                narration: format!("Fees earned by position {}", position.id),
+                tx_hash: None,
+                postings: transfer(
+                    &config.fee_income_account,
+                    &format!("{}:Fees", account),
+                    &[(fees, &token1)],
+                ),
+            });
+        }
+
+        if let (Some(close), Some((deposit0, deposit1))) = (close, deposit)
+            && let Some((amount0, amount1)) = token_amounts(a, close.price)
+        {
+            let mut postings = vec![
+                Posting {
+                    account: config.wallet_account.clone(),
+                    amount: amount0,
+                    commodity: token0.clone(),
+                },
+                Posting {
+                    account: config.wallet_account.clone(),
+                    amount: amount1,
+                    commodity: token1.clone(),
+                },
+                Posting { account: account.clone(), amount: -deposit0, commodity: token0.clone() },
+                Posting { account: account.clone(), amount: -deposit1, commodity: token1.clone() },
+            ];
+            for (change, token) in [(amount0 - deposit0, &token0), (amount1 - deposit1, &token1)] {
+                if !change.is_zero() {
+                    postings.push(Posting {
+                        account: config.position_pnl_account.clone(),
+                        amount: -change,
+                        commodity: token.clone(),
+                    });
+                }
+            }
+            postings.retain(|p| !p.amount.is_zero());
+            entries.push(LedgerEntry {
+                date: close.timestamp.date_naive(),
+                narration: format!("Close position {} (nft {})", position.id, position.nft_id),
+                tx_hash: None,
+                postings,
+            });
+        }
+
+        for gas in a.gas_expenses {
+            entries.push(LedgerEntry {
+                date: gas.timestamp.date_naive(),
+                narration: format!("Gas for position {}", position.id),
+                tx_hash: Some(gas.tx_hash.clone()),
+                postings: transfer(
+                    &config.wallet_account,
+                    &config.gas_account,
+                    &[(gas.gas_cost, &config.native_commodity)],
+                ),
+            });
+        }
+    }
+
+    entries.retain(|e| !e.postings.is_empty());
+    entries.sort_by_key(|e| e.date);
+    entries
+}
+
+/// Render entries as a Beancount file, including `open` directives for every account
+pub fn render_beancount(entries: &[LedgerEntry]) -> String {
+    let mut out = String::new();
+
+    if let Some(first) = entries.first() {
+        let accounts: BTreeSet<&str> =
+            entries.iter().flat_map(|e| e.postings.iter().map(|p| p.account.as_str())).collect();
+        for account in accounts {
+            let _ = writeln!(out, "{} open {}", first.date, account);
+        }
+    }
+
+    for entry in entries {
+        let _ = writeln!(out, "\n{} * \"{}\"", entry.date, escape(&entry.narration));
+        if let Some(tx_hash) = &entry.tx_hash {
+            let _ = writeln!(out, "  tx_hash: \"{}\"", escape(tx_hash));
+        }
+        for p in &entry.postings {
+            let _ = writeln!(out, "  {:<48} {} {}", p.account, p.amount.normalize(), p.commodity);
+        }
+    }
+
+    out
+}
+
+/// Render entries as a ledger-cli journal
+pub fn render_ledger(entries: &[LedgerEntry]) -> String {
+    let mut out = String::new();
+
+    for entry in entries {
+        let _ = writeln!(out, "{} {}", entry.date.format("%Y/%m/%d"), entry.narration);
+        if let Some(tx_hash) = &entry.tx_hash {
+            let _ = writeln!(out, "    ; tx_hash: {}", tx_hash);
+        }
+        for p in &entry.postings {
+            // Commodities containing digits must be quoted
+            let _ = writeln!(
+                out,
+                "    {:<48}  {} \"{}\"",
+                p.account,
+                p.amount.normalize(),
+                p.commodity
+            );
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Render entries in the requested format
+pub fn render_entries(entries: &[LedgerEntry], format: LedgerFormat) -> String {
+    match format {
+        LedgerFormat::Beancount => render_beancount(entries),
+        LedgerFormat::Ledger => render_ledger(entries),
+    }
+}
+
+/// Postings moving `amounts` from `from` to `to`, skipping zero amounts
+fn transfer(from: &str, to: &str, amounts: &[(Decimal, &String)]) -> Vec<Posting> {
+    amounts
+        .iter()
+        .filter(|(amount, _)| !amount.is_zero())
+        .flat_map(|(amount, commodity)| {
+            [
+                Posting {
+                    account: to.to_string(),
+                    amount: *amount,
+                    commodity: commodity.to_string(),
+                },
+                Posting {
+                    account: from.to_string(),
+                    amount: -*amount,
+                    commodity: commodity.to_string(),
+                },
+            ]
+        })
+        .collect()
+}
+
+/// Token amounts (in whole tokens) backing the position's liquidity at a raw pool price
+fn token_amounts(a: &PositionActivity, price: Decimal) -> Option<(Decimal, Decimal)> {
+    let liquidity = Decimal::from_str(&a.position.liquidity.to_string()).ok()?;
+    let (price_lower, price_upper) = range_prices(a.position.tick_lower, a.position.tick_upper);
+    let amounts = amounts_for_liquidity(liquidity, price, price_lower, price_upper);
+    Some((
+        raw_to_units(amounts.amount0, a.pool.token0_decimals),
+        raw_to_units(amounts.amount1, a.pool.token1_decimals),
+    ))
+}
+
+/// Commodity name for a token: configured symbol or `TKN` + the address' first 8 hex digits
+fn commodity(config: &LedgerConfig, token: &str) -> String {
+    if let Some(symbol) = config.symbols.get(&token.to_lowercase()) {
+        return symbol.clone();
+    }
+    let hex = token.trim_start_matches("0x").trim_start_matches("0X");
+    format!("TKN{}", hex.chars().take(8).collect::<String>().to_uppercase())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use alloy::primitives::U256;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn create_test_pool() -> Pool {
+        Pool {
+            token0: "0xaaaa000000000000000000000000000000000001".to_string(),
+            token1: "0xbbbb000000000000000000000000000000000002".to_string(),
+            token0_decimals: 0,
+            token1_decimals: 0,
+            ..testing::create_test_pool()
+        }
+    }
+
+    fn create_test_position() -> Position {
+        Position {
+            id: 7,
+            nft_id: "42".to_string(),
+            created_at: Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap(),
+            ..testing::create_test_position()
+        }
+    }
+
+    fn create_test_snapshot(
+        days: i64,
+        fees: i64,
+        liquidity: u64,
+        price: Decimal,
+    ) -> PositionSnapshot {
+        PositionSnapshot {
+            id: 0,
+            position_id: 7,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap() + Duration::days(days),
+            fees_earned: Decimal::from(fees),
+            liquidity: U256::from(liquidity),
+            price,
+        }
+    }
+
+    fn balances(entries: &[LedgerEntry]) -> bool {
+        entries.iter().all(|e| {
+            let mut sums: HashMap<&str, Decimal> = HashMap::new();
+            for p in &e.postings {
+                *sums.entry(p.commodity.as_str()).or_insert(Decimal::ZERO) += p.amount;
+            }
+            sums.values().all(|s| s.is_zero())
+        })
+    }
+
+    #[test]
+    fn test_entries_balance_and_cover_lifecycle() {
+        let pool = create_test_pool();
+        let position = create_test_position();
+        let snapshots = vec![
+            create_test_snapshot(0, 0, 1_000_000, Decimal::ONE),
+            create_test_snapshot(1, 5, 1_000_000, Decimal::ONE),
+            create_test_snapshot(3, 9, 0, Decimal::from_str("1.05").unwrap()),
+        ];
+        let gas = vec![GasExpense {
+            id: 1,
+            position_id: 7,
+            tx_hash: "0xabc".to_string(),
+            gas_cost: Decimal::from_str("0.002").unwrap(),
+            timestamp: position.created_at,
+        }];
+        let activity = [PositionActivity {
+            position: &position,
+            pool: &pool,
+            snapshots: &snapshots,
+            gas_expenses: &gas,
+        }];
+
+        let entries = build_ledger_entries(&activity, &LedgerConfig::default());
+        let narrations: Vec<&str> = entries.iter().map(|e| e.narration.as_str()).collect();
+        assert_eq!(
+            narrations,
+            vec![
+                "Open position 7 (nft 42)",
+                "Gas for position 7",
+                "Fees earned by position 7",
+                "Fees earned by position 7",
+                "Close position 7 (nft 42)",
+            ]
+        );
+        assert!(balances(&entries));
+
+        let fees: Decimal = entries
+            .iter()
+            .flat_map(|e| &e.postings)
+            .filter(|p| p.account == "Income:Crypto:UniswapV4:Fees")
+            .map(|p| p.amount)
+            .sum();
+        assert_eq!(fees, Decimal::from(-9));
+    }
+
+    #[test]
+    fn test_render_beancount() {
+        let pool = create_test_pool();
+        let position = create_test_position();
+        let snapshots = vec![create_test_snapshot(0, 3, 1_000_000, Decimal::ONE)];
+        let activity = [PositionActivity {
+            position: &position,
+            pool: &pool,
+            snapshots: &snapshots,
+            gas_expenses: &[],
+        }];
+        let mut config = LedgerConfig::default();
+        config.symbols.insert(pool.token1.clone(), "USDC".to_string());
+
+        let output = render_beancount(&build_ledger_entries(&activity, &config));
+        assert!(output.starts_with("2026-01-01 open Assets:Crypto:UniswapV4:Position7\n"));
+        assert!(output.contains("2026-01-01 * \"Fees earned by position 7\""));
+        assert!(output.contains(" 3 USDC\n"));
+        assert!(output.contains(" TKNAAAA0000\n"));
+        assert_eq!(LedgerFormat::parse("ledger"), Some(LedgerFormat::Ledger));
+    }
+}
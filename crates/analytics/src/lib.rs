@@ -1,6 +1,54 @@
 pub mod pnl;
 pub mod health;
 pub mod utils;
+pub mod chart;
+pub mod liquidity;
+pub mod backtest;
+pub mod fees;
+pub mod leaderboard;
+pub mod quality;
+pub mod display;
+pub mod heatmap;
+pub mod holding;
+pub mod ledger;
+pub mod rebalance;
+pub mod risk;
+pub mod chains;
+pub mod cohorts;
+pub mod compound;
+pub mod forecast;
+pub mod quote;
+pub mod retention;
+pub mod twap;
+pub mod planner;
+pub mod greeks;
+pub mod gas;
+pub mod hooks;
+pub mod returns;
+pub mod accumulator;
+pub mod ownership;
+pub mod units;
+pub mod velocity;
+pub mod timerange;
+pub mod jit;
+pub mod rates;
+pub mod whales;
+pub mod stress;
+pub mod tickseries;
+pub mod timeline;
+pub mod entry;
+pub mod feegrowth;
+pub mod decomposition;
+pub mod downsample;
+pub mod volatility;
+pub mod healthbatch;
+pub mod series;
+pub mod replay;
+pub mod tickmath;
+pub mod vaults;
+
+#[cfg(test)]
+mod testing;
 
 // Re-export main functions
 pub use pnl::{
@@ -8,17 +56,449 @@ pub use pnl::{
     calculate_impermanent_loss,
     calculate_net_pnl,
     calculate_position_pnl,
+    calculate_position_pnl_at,
+    calculate_position_pnl_with_model,
+    fees_to_break_even,
+    PnlHistory,
+};
+
+pub use fees::{
+    calculate_fees_earned_with_model,
+    fee_to_rate,
+    lp_fee_rate,
+    protocol_fee_share,
+    MAX_LP_FEE,
+    DynamicFeeModel,
+    FeeModel,
+    FeeModelRegistry,
+    StaticFeeModel,
 };
 
 pub use health::{
+    estimate_ttl_to_edge,
     get_position_health,
     get_health_details,
+    Comparison,
+    HealthCondition,
+    HealthInputs,
+    HealthMetric,
+    HealthRule,
+    HealthRuleError,
+    HealthRules,
+    Operand,
+    DEFAULT_HEALTH_RULES,
+    DEFAULT_PNL_GRACE,
+};
+
+pub use chart::{
+    build_range_band,
+    time_in_range,
+    RangeBandPoint,
+};
+
+pub use liquidity::{
+    amounts_for_liquidity,
+    liquidity_for_value,
+    value_per_liquidity,
+    TokenAmounts,
+};
+
+pub use backtest::{
+    compare_compounding,
+    diff_backtests,
+    run_backtest,
+    BacktestConfig,
+    BacktestDiff,
+    BacktestMetrics,
+    BacktestParams,
+    BacktestResult,
+    CompoundingComparison,
+    CompoundingRule,
+    EquityPoint,
+    MarketPoint,
+    MetricDelta,
+    ParamChange,
+    market_points_from_swaps,
+};
+
+pub use rebalance::{
+    optimize_rebalance_trigger,
+    simulate_rebalancing,
+    RebalanceConfig,
+    RebalanceOptimization,
+    RebalanceOutcome,
+    RebalancePolicy,
+    REBALANCE_TXS,
+};
+
+pub use leaderboard::{
+    aggregate_by_owner,
+    annualized_return,
+    rank_owners,
+    rank_positions,
+    summarize_performance,
+    FeeApr,
+    OwnerPerformance,
+    PositionPerformance,
+    RankBy,
+};
+
+pub use display::{
+    DisplayOptions,
+    PriceDisplay,
+    RoundingMode,
+    DEFAULT_SIGNIFICANT_FIGURES,
+};
+
+pub use ledger::{
+    build_ledger_entries,
+    render_beancount,
+    render_entries,
+    render_ledger,
+    LedgerConfig,
+    LedgerEntry,
+    LedgerFormat,
+    PositionActivity,
+    Posting,
+};
+
+pub use heatmap::{
+    build_liquidity_heatmap,
+    swap_tick,
+    HeatmapCell,
+    HeatmapConfig,
+    HeatmapRow,
+    LiquidityHeatmap,
+};
+
+pub use holding::{
+    fees_by_week_of_life,
+    holding_period,
+    summarize_holding,
+    HoldBucket,
+    HoldingPeriod,
+    HoldingSummary,
+    WeeklyFees,
+};
+
+pub use chains::{
+    chain_link,
+    detect_rebalance_chains,
+    ChainLink,
+    RebalanceChain,
+    DEFAULT_CHAIN_WINDOW_MINUTES,
+};
+
+pub use cohorts::{
+    build_cohorts,
+    Cohort,
+    WidthBucket,
+    MEDIUM_MAX_WIDTH_PERCENT,
+    NARROW_MAX_WIDTH_PERCENT,
+    WIDE_MAX_WIDTH_PERCENT,
+};
+
+pub use compound::{
+    recommend_compound,
+    unclaimed_fees,
+    CompoundConfig,
+    CompoundRecommendation,
+    COMPOUND_TXS,
+    DEFAULT_COMPOUND_GAS_MULTIPLE,
+};
+
+pub use forecast::{
+    daily_volumes,
+    default_ewma_alpha,
+    forecast_volume,
+    in_range_volume_share,
+    projected_fee_apr,
+    DailyVolume,
+    ForecastMethod,
+    VolumeForecast,
+    FORECAST_LOOKBACK_DAYS,
+    MIN_SEASONAL_HISTORY_DAYS,
+    PROJECTION_DISCLAIMER,
+};
+
+pub use quote::{
+    simulate_swap,
+    PoolTicks,
+    QuoteError,
+    SwapQuote,
+    TickLiquidity,
+};
+
+pub use retention::{
+    check_retention,
+    RetentionPolicy,
+    RetentionWarning,
+    DEFAULT_DELETED_POSITION_RETENTION_DAYS,
+};
+
+pub use planner::{
+    expected_tick_move,
+    fee_per_liquidity_per_day,
+    frontier_widths,
+    range_frontier,
+    size_for_target_income,
+    FrontierPoint,
+    PositionSize,
+    SizingError,
+    FRONTIER_WIDTH_MOVES,
+    PLANNER_LOOKBACK_DAYS,
+};
+
+pub use twap::{
+    calculate_twap,
+    time_weighted_tick,
+    Twap,
+    TwapConfig,
+    DEFAULT_TWAP_WINDOW_MINUTES,
+};
+
+pub use greeks::{
+    position_greeks,
+    summarize_exposure,
+    PoolExposure,
+    PositionGreeks,
+};
+
+pub use gas::{
+    apply_gas_price_history,
+    transaction_gas_cost,
+    GasAccounting,
+    GasCost,
+};
+
+pub use hooks::{
+    hook_permissions,
+    HookAnnotation,
+    HookKind,
+    HookRegistry,
+    KnownHook,
+};
+
+pub use returns::{
+    combine_daily_returns,
+    daily_returns,
+    risk_adjusted_returns,
+    DailyReturn,
+    RiskAdjustedReturns,
+    MIN_RETURN_DAYS,
+};
+
+pub use accumulator::{
+    advance_accumulator,
+    check_accumulator,
+    AccumulatorDrift,
+    ACCUMULATOR_TOLERANCE,
+};
+
+pub use ownership::{
+    attribute_pnl,
+    ownership_periods,
+    OwnerPnl,
+    OwnershipPeriod,
+};
+
+pub use units::{
+    convert_amount,
+    convert_amounts,
+    convert_signed_amount,
+    convert_swap_amounts,
+    pool_decimals,
+    raw_to_units,
+    signed_units,
+    SwapAmounts,
+};
+
+pub use velocity::{
+    fee_velocity,
+    fee_velocity_trend,
+    FeeVelocity,
+    FeeVelocityConfig,
+    FeeVelocityTrend,
+    DEFAULT_FEE_VELOCITY_BASELINE_DAYS,
+    DEFAULT_FEE_VELOCITY_DROP,
+    DEFAULT_FEE_VELOCITY_RECENT_HOURS,
+};
+
+pub use jit::{
+    detect_jit,
+    JitConfig,
+    JitEpisode,
+    JitReport,
+    DEFAULT_JIT_MAX_LIFETIME_SECS,
+    DEFAULT_JIT_MIN_LIQUIDITY_SHARE,
+};
+
+pub use rates::{
+    normalize_position,
+    sum_normalized,
+    ExchangeRates,
+    NormalizedPosition,
+    NormalizedTotals,
+    QuoteCurrencies,
+    TokenRate,
+    DEFAULT_QUOTE_CURRENCY,
+    MAX_RATE_HOPS,
+};
+
+pub use whales::{
+    swap_impacts,
+    swap_value,
+    LargeSwapConfig,
+    SwapImpact,
+    DEFAULT_LARGE_SWAP_TICKS,
+    DEFAULT_LARGE_SWAP_USD,
+};
+
+pub use stress::{
+    parse_price_move,
+    stress_position,
+    sum_stress,
+    PositionStress,
+    StressTotals,
+};
+
+pub use timeline::{
+    build_timeline,
+    TimelineEvent,
+    TimelineEventKind,
+    DEFAULT_PNL_SWING,
+};
+
+pub use entry::{
+    weighted_entry,
+    EntryMetrics,
+};
+
+pub use downsample::{
+    is_minute_aggregate,
+    plan_downsampling,
+    DownsamplePlan,
+    MinuteAggregate,
+    AGGREGATE_TX_PREFIX,
+    DEFAULT_KEEP_LARGEST,
+};
+
+pub use feegrowth::{
+    fees_from_growth,
+    reconcile_fee_growth,
+    FeeGrowthCheck,
+    DEFAULT_FEE_GROWTH_TOLERANCE,
+};
+
+pub use tickseries::{
+    decode_tick_series,
+    encode_tick_series,
+    tick_points_from_swaps,
+    TickPoint,
+    TickSeriesDecoder,
+    TickSeriesError,
+    TICK_SERIES_VERSION,
+};
+
+pub use timerange::{
+    format_duration,
+    parse_duration,
+    parse_time,
+    TimeRange,
+    TimeRangeError,
+    TimeRangeLimits,
+};
+
+pub use risk::{
+    classify_risk,
+    daily_tick_volatility,
+    summarize_risk,
+    RiskBucket,
+    RiskCategory,
+    RiskDistribution,
+    RiskHealthRules,
+    BALANCED_MAX_WIDTH_SIGMAS,
+    CONSERVATIVE_HEALTH_RULES,
+    DEGEN_HEALTH_RULES,
+    DEGEN_MAX_WIDTH_SIGMAS,
+    VOLATILITY_LOOKBACK_DAYS,
+};
+
+pub use volatility::{
+    annualize_tick_volatility,
+    compare_volatility,
+    parse_volatility,
+    rolling_realized_volatility,
+    RealizedVolatilityPoint,
+    VolatilityComparison,
+    VolatilityRegime,
+    ELEVATED_IV_RATIO,
+    SUBDUED_IV_RATIO,
+};
+
+pub use healthbatch::{
+    evaluate_health_batch,
+    health_inputs_from_row,
+    PositionHealth,
+    HEALTH_TTL_LOOKBACK,
+};
+
+pub use series::{
+    cumulative_fee_series,
+    series_step,
+    SeriesPoint,
+    MIN_SERIES_STEP,
+};
+
+pub use quality::{
+    check_swap_quality,
+    QualityConfig,
 };
 
 pub use utils::{
     is_in_range,
+    RangeCrossing,
+    RangeError,
+    TickRange,
+    MAX_TICK,
+    MIN_TICK,
     distance_to_range_edge,
     tick_to_price,
     price_to_tick,
     range_width_percent,
 };
+
+pub use decomposition::{
+    decompose_pnl,
+    sum_decompositions,
+    DecompositionTotals,
+    PnlComponents,
+    PnlDecomposition,
+};
+
+pub use replay::{
+    replay_position,
+    GasStep,
+    PriceStep,
+    ReplayTrace,
+    SwapStep,
+};
+
+pub use tickmath::{
+    get_sqrt_ratio_at_tick,
+    get_tick_at_sqrt_ratio,
+    price_to_sqrt_ratio,
+    sqrt_price_at_tick,
+    sqrt_ratio_to_price,
+    sqrt_ratio_to_sqrt_price,
+    MAX_SQRT_RATIO,
+    MIN_SQRT_RATIO,
+};
+
+pub use vaults::{
+    scale_position,
+    share_fraction,
+    vault_exposure,
+    VaultExposure,
+    VaultStake,
+};
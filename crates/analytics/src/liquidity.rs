@@ -0,0 +1,113 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+
+use crate::utils::tick_to_price;
+
+/// Token amounts backing a concentrated liquidity position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAmounts {
+    pub amount0: Decimal,
+    pub amount1: Decimal,
+}
+
+impl TokenAmounts {
+    /// Total value denominated in token1 at the given price (token1 per token0)
+    pub fn value_in_token1(&self, price: Decimal) -> Decimal {
+        self.amount0 * price + self.amount1
+    }
+}
+
+/// Token amounts for `liquidity` in [price_lower, price_upper) at `price`
+///
+/// Standard Uniswap v3/v4 formulas, using sqrt prices:
+/// - below range: all token0
+/// - above range: all token1
+/// - in range: a mix depending on where the price sits
+pub fn amounts_for_liquidity(
+    liquidity: Decimal,
+    price: Decimal,
+    price_lower: Decimal,
+    price_upper: Decimal,
+) -> TokenAmounts {
+    let (Some(sp), Some(sa), Some(sb)) = (price.sqrt(), price_lower.sqrt(), price_upper.sqrt())
+    else {
+        return TokenAmounts { amount0: Decimal::ZERO, amount1: Decimal::ZERO };
+    };
+
+    if sa.is_zero() || sb <= sa {
+        return TokenAmounts { amount0: Decimal::ZERO, amount1: Decimal::ZERO };
+    }
+
+    if sp <= sa {
+        TokenAmounts { amount0: liquidity * (sb - sa) / (sa * sb), amount1: Decimal::ZERO }
+    } else if sp >= sb {
+        TokenAmounts { amount0: Decimal::ZERO, amount1: liquidity * (sb - sa) }
+    } else {
+        TokenAmounts { amount0: liquidity * (sb - sp) / (sp * sb), amount1: liquidity * (sp - sa) }
+    }
+}
+
+/// Value (in token1) of one unit of liquidity at `price`
+pub fn value_per_liquidity(price: Decimal, price_lower: Decimal, price_upper: Decimal) -> Decimal {
+    amounts_for_liquidity(Decimal::ONE, price, price_lower, price_upper).value_in_token1(price)
+}
+
+/// Liquidity that `value` (in token1) buys in [price_lower, price_upper) at `price`
+pub fn liquidity_for_value(
+    value: Decimal,
+    price: Decimal,
+    price_lower: Decimal,
+    price_upper: Decimal,
+) -> Decimal {
+    let unit = value_per_liquidity(price, price_lower, price_upper);
+    if unit.is_zero() {
+        return Decimal::ZERO;
+    }
+    value / unit
+}
+
+/// Price bounds for a tick range
+pub fn range_prices(tick_lower: i32, tick_upper: i32) -> (Decimal, Decimal) {
+    (tick_to_price(tick_lower), tick_to_price(tick_upper))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amounts_for_liquidity_out_of_range() {
+        let (lower, upper) = (Decimal::from(1), Decimal::from(4));
+
+        let below = amounts_for_liquidity(Decimal::ONE, Decimal::new(5, 1), lower, upper);
+        assert!(below.amount0 > Decimal::ZERO);
+        assert_eq!(below.amount1, Decimal::ZERO);
+
+        let above = amounts_for_liquidity(Decimal::ONE, Decimal::from(9), lower, upper);
+        assert_eq!(above.amount0, Decimal::ZERO);
+        assert_eq!(above.amount1, Decimal::ONE); // L * (2 - 1)
+    }
+
+    #[test]
+    fn test_amounts_for_liquidity_in_range() {
+        // sqrt prices: 1, 1.5 (approx), 2
+        let amounts = amounts_for_liquidity(
+            Decimal::from(100),
+            Decimal::from(2),
+            Decimal::ONE,
+            Decimal::from(4),
+        );
+        assert!(amounts.amount0 > Decimal::ZERO);
+        assert!(amounts.amount1 > Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_liquidity_for_value_round_trip() {
+        let (lower, upper) = (Decimal::ONE, Decimal::from(4));
+        let price = Decimal::from(2);
+
+        let liquidity = liquidity_for_value(Decimal::from(1000), price, lower, upper);
+        let value = amounts_for_liquidity(liquidity, price, lower, upper).value_in_token1(price);
+        assert!((value - Decimal::from(1000)).abs() < Decimal::from_str("0.000001").unwrap());
+    }
+}
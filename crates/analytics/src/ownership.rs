@@ -0,0 +1,238 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{Address, Pool, Position, PositionPnL, PositionTransfer, Swap};
+
+use crate::fees::{FeeModel, calculate_fees_earned_with_model};
+use crate::pnl::{PnlHistory, calculate_impermanent_loss, calculate_net_pnl};
+use crate::utils::{RangeError, TickRange};
+
+/// One owner's hold on a position
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwnershipPeriod {
+    pub owner: Address,
+    /// When the owner received the position (its creation, for the first owner)
+    pub effective_from: DateTime<Utc>,
+    /// When the owner transferred it on (None for the current owner)
+    pub effective_to: Option<DateTime<Utc>>,
+}
+
+/// P&L earned by one owner of a position while they held it
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerPnl {
+    #[serde(flatten)]
+    pub period: OwnershipPeriod,
+    /// Snapshot prices at the start and end of the hold (None without snapshots)
+    pub entry_price: Option<Decimal>,
+    pub exit_price: Option<Decimal>,
+    pub pnl: PositionPnL,
+}
+
+/// Owners of a position over time, from its transfers
+///
+/// The first owner is the sender of the earliest transfer (the position's
+/// recorded owner if it was never transferred) and holds it from creation.
+/// Each transfer ends one period and starts the recipient's.
+pub fn ownership_periods(
+    position: &Position,
+    transfers: &[PositionTransfer],
+) -> Vec<OwnershipPeriod> {
+    let mut transfers: Vec<&PositionTransfer> =
+        transfers.iter().filter(|t| t.position_id == position.id).collect();
+    transfers.sort_by_key(|t| t.effective_from);
+
+    let mut current = OwnershipPeriod {
+        owner: transfers.first().map_or(position.owner, |t| t.from_owner),
+        effective_from: position.created_at,
+        effective_to: None,
+    };
+    let mut periods = Vec::with_capacity(transfers.len() + 1);
+    for transfer in transfers {
+        let effective_from = transfer.effective_from.max(position.created_at);
+        current.effective_to = Some(effective_from);
+        periods.push(current);
+        current = OwnershipPeriod { owner: transfer.to_owner, effective_from, effective_to: None };
+    }
+    periods.push(current);
+    periods
+}
+
+/// Split a position's P&L between the owners who held it, up to `as_of`
+///
+/// Each owner is credited with the fees of swaps and the gas paid while they
+/// held the position, and the impermanent loss from the snapshot price when
+/// they received it to the one when they passed it on (or `as_of`). Fees and
+/// gas add up to the position's; impermanent loss doesn't, as it isn't
+/// additive over price moves. Owners who received it after `as_of` are left out.
+pub fn attribute_pnl(
+    position: &Position,
+    pool: &Pool,
+    model: &dyn FeeModel,
+    history: &PnlHistory,
+    transfers: &[PositionTransfer],
+    as_of: DateTime<Utc>,
+) -> Result<Vec<OwnerPnl>, RangeError> {
+    TickRange::of(position)?;
+
+    let mut attribution = Vec::new();
+    for period in ownership_periods(position, transfers) {
+        if period.effective_from > as_of {
+            break;
+        }
+        let held = |timestamp: DateTime<Utc>| {
+            timestamp >= period.effective_from
+                && match period.effective_to {
+                    Some(to) if to <= as_of => timestamp < to,
+                    _ => timestamp <= as_of,
+                }
+        };
+        let end = period.effective_to.unwrap_or(as_of).min(as_of);
+
+        let swaps: Vec<Swap> =
+            history.swaps.iter().filter(|s| held(s.timestamp)).cloned().collect();
+        let fees_earned = calculate_fees_earned_with_model(position, pool, &swaps, model);
+        let gas_spent: Decimal =
+            history.gas.iter().filter(|g| held(g.timestamp)).map(|g| g.gas_cost).sum();
+
+        let entry_price = price_at(history, period.effective_from).or_else(|| {
+            history
+                .snapshots
+                .iter()
+                .filter(|s| held(s.timestamp))
+                .min_by_key(|s| s.timestamp)
+                .map(|s| s.price)
+        });
+        let exit_price = price_at(history, end);
+        let impermanent_loss = match (entry_price, exit_price) {
+            (Some(entry), Some(exit)) => calculate_impermanent_loss(position, entry, exit)?,
+            _ => Decimal::ZERO,
+        };
+
+        attribution.push(OwnerPnl {
+            period,
+            entry_price,
+            exit_price,
+            pnl: PositionPnL {
+                fees_earned,
+                impermanent_loss,
+                gas_spent,
+                net_pnl: calculate_net_pnl(fees_earned, impermanent_loss, gas_spent),
+            },
+        });
+    }
+    Ok(attribution)
+}
+
+/// Price of the latest snapshot at or before `at`
+fn price_at(history: &PnlHistory, at: DateTime<Utc>) -> Option<Decimal> {
+    history
+        .snapshots
+        .iter()
+        .filter(|s| s.timestamp <= at)
+        .max_by_key(|s| s.timestamp)
+        .map(|s| s.price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::StaticFeeModel;
+    use crate::pnl::calculate_position_pnl_at;
+    use crate::testing::{self, create_test_pool};
+    use alloy::primitives::U256;
+    use chrono::Duration;
+    use stillwater_models::{GasExpense, PositionSnapshot};
+
+    fn create_test_position(created_at: DateTime<Utc>) -> Position {
+        Position { owner: Address::repeat_byte(0xc), created_at, ..testing::create_test_position() }
+    }
+
+    fn create_test_transfer(from: u8, to: u8, at: DateTime<Utc>) -> PositionTransfer {
+        PositionTransfer {
+            transfer_id: format!("{}-{}", from, to),
+            position_id: 1,
+            from_owner: Address::repeat_byte(from),
+            to_owner: Address::repeat_byte(to),
+            tx_hash: None,
+            effective_from: at,
+        }
+    }
+
+    fn create_test_swap(id: i64, timestamp: DateTime<Utc>) -> Swap {
+        testing::create_test_swap(id, 1_000_000, -1_000_000, timestamp)
+    }
+
+    #[test]
+    fn test_ownership_periods_follow_transfers() {
+        let created = Utc::now() - Duration::days(10);
+        let position = create_test_position(created);
+        let transfers = vec![
+            create_test_transfer(0xb, 0xc, created + Duration::days(6)),
+            create_test_transfer(0xa, 0xb, created + Duration::days(2)),
+        ];
+
+        let periods = ownership_periods(&position, &transfers);
+        let owners: Vec<Address> = periods.iter().map(|p| p.owner).collect();
+        assert_eq!(owners, [0xa, 0xb, 0xc].map(Address::repeat_byte));
+        assert_eq!(periods[0].effective_from, created);
+        assert_eq!(periods[1].effective_to, Some(created + Duration::days(6)));
+        assert_eq!(periods[2].effective_to, None);
+
+        // Never transferred: the recorded owner held it throughout
+        let untransferred = ownership_periods(&position, &[]);
+        assert_eq!(untransferred.len(), 1);
+        assert_eq!(untransferred[0].owner, Address::repeat_byte(0xc));
+    }
+
+    #[test]
+    fn test_fees_and_gas_split_at_transfer_time() {
+        let created = Utc::now() - Duration::days(10);
+        let transferred = created + Duration::days(4);
+        let position = create_test_position(created);
+        let pool = create_test_pool();
+        let transfers = vec![create_test_transfer(0xa, 0xc, transferred)];
+
+        // Three swaps before the transfer, two after, one exactly at it (the recipient's)
+        let mut swaps: Vec<Swap> =
+            (1..=3).map(|i| create_test_swap(i, created + Duration::days(i))).collect();
+        swaps.push(create_test_swap(4, transferred));
+        swaps.extend((5..=6).map(|i| create_test_swap(i, created + Duration::days(i))));
+        let snapshots: Vec<PositionSnapshot> = [0, 4, 8]
+            .iter()
+            .map(|d| PositionSnapshot {
+                id: 0,
+                position_id: 1,
+                timestamp: created + Duration::days(*d),
+                fees_earned: Decimal::ZERO,
+                liquidity: U256::from(1_000_000u64),
+                price: Decimal::ONE + Decimal::new(*d, 2),
+            })
+            .collect();
+        let gas = vec![GasExpense {
+            id: 1,
+            position_id: 1,
+            tx_hash: "0xmint".to_string(),
+            gas_cost: Decimal::from(3),
+            timestamp: created,
+        }];
+        let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &gas };
+        let as_of = created + Duration::days(9);
+
+        let split =
+            attribute_pnl(&position, &pool, &StaticFeeModel, &history, &transfers, as_of).unwrap();
+        assert_eq!(split.len(), 2);
+        let one_swap =
+            calculate_fees_earned_with_model(&position, &pool, &swaps[..1], &StaticFeeModel);
+        assert_eq!(split[0].pnl.fees_earned, one_swap * Decimal::from(3));
+        assert_eq!(split[1].pnl.fees_earned, one_swap * Decimal::from(3));
+        assert_eq!(split[0].pnl.gas_spent, Decimal::from(3));
+        assert_eq!(split[1].pnl.gas_spent, Decimal::ZERO);
+        assert_eq!(split[0].exit_price, split[1].entry_price);
+
+        // Fees add up to the whole position's
+        let whole =
+            calculate_position_pnl_at(&position, &pool, &StaticFeeModel, &history, as_of).unwrap();
+        let total: Decimal = split.iter().map(|o| o.pnl.fees_earned).sum();
+        assert_eq!(total, whole.unwrap().fees_earned);
+    }
+}
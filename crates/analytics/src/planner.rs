@@ -0,0 +1,380 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use stillwater_models::{Position, SnapshotWindow, Swap};
+
+use crate::forecast::in_range_volume_share;
+use crate::liquidity::{amounts_for_liquidity, range_prices, value_per_liquidity};
+use crate::rebalance::centered_range;
+use crate::risk::{RiskCategory, classify_risk};
+use crate::utils::{TickRange, price_to_tick, tick_to_price};
+
+/// Days of fee history the planner measures fee rates over by default
+pub const PLANNER_LOOKBACK_DAYS: i64 = 7;
+
+/// Default frontier candidate widths, as multiples of the expected price move
+pub const FRONTIER_WIDTH_MOVES: [i32; 6] = [1, 2, 3, 5, 8, 13];
+
+/// Why a position couldn't be sized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizingError {
+    /// The target income is zero or negative
+    NonPositiveTarget,
+    /// The pool price isn't positive
+    InvalidPrice,
+    /// No fee rate could be measured for the pool
+    NoFeeData,
+    /// No recent volume executed inside the range, so no liquidity earns the target
+    RangeNeverActive,
+}
+
+impl fmt::Display for SizingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizingError::NonPositiveTarget => write!(f, "target income must be positive"),
+            SizingError::InvalidPrice => write!(f, "pool price must be positive"),
+            SizingError::NoFeeData => write!(f, "no fee history recorded for in-range positions"),
+            SizingError::RangeNeverActive => {
+                write!(f, "no recent volume executed inside the range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SizingError {}
+
+/// Liquidity and token amounts needed to earn a target fee income
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionSize {
+    pub target_monthly_fees: Decimal,
+    pub required_liquidity: Decimal,
+    pub amount0: Decimal,
+    pub amount1: Decimal,
+    /// Value of both amounts in token1 at `price`
+    pub capital: Decimal,
+    pub price: Decimal,
+    /// Fees earned per unit of liquidity per day while in range
+    pub fee_per_liquidity_per_day: Decimal,
+    /// Share of recent volume that executed inside the range
+    pub in_range_share: Decimal,
+    /// Target income annualized over the capital, as a fraction
+    pub projected_apr: Option<Decimal>,
+}
+
+/// Fees earned per unit of liquidity per day while in range, from tracked positions
+///
+/// Only windows whose first and last snapshot prices are both inside the
+/// position's range count, so time spent out of range doesn't dilute the
+/// rate. Fee growth inside a range is shared per unit of liquidity, so the
+/// rate carries over to ranges of any width. Liquidity-weighted across
+/// positions; None without a qualifying window.
+pub fn fee_per_liquidity_per_day(windows: &[(Position, SnapshotWindow)]) -> Option<Decimal> {
+    let mut fees = Decimal::ZERO;
+    let mut liquidity_days = Decimal::ZERO;
+    for (position, window) in windows {
+        let Ok(range) = TickRange::of(position) else {
+            continue;
+        };
+        let in_range = [window.start_price, window.end_price]
+            .iter()
+            .all(|price| *price > Decimal::ZERO && range.contains(price_to_tick(*price)));
+        let Ok(liquidity) = Decimal::from_str(&position.liquidity.to_string()) else {
+            continue;
+        };
+        let days = Decimal::from((window.end_time - window.start_time).num_seconds())
+            / Decimal::from(86_400);
+        if !in_range || liquidity.is_zero() || days <= Decimal::ZERO {
+            continue;
+        }
+
+        let Some(weight) = liquidity.checked_mul(days) else {
+            continue;
+        };
+        fees += (window.end_fees - window.start_fees).max(Decimal::ZERO);
+        liquidity_days += weight;
+    }
+    fees.checked_div(liquidity_days).filter(|rate| *rate > Decimal::ZERO)
+}
+
+/// Size a position in `range` to earn `target_monthly_fees` at recent fee rates
+///
+/// Daily income is the liquidity times the in-range fee rate times the share
+/// of volume the range catches, so the required liquidity is the daily target
+/// over their product. Token amounts are the ones that liquidity holds at
+/// `price` (token1 per token0, raw units). A month is 365/12 days.
+pub fn size_for_target_income(
+    target_monthly_fees: Decimal,
+    range: TickRange,
+    price: Decimal,
+    fee_per_liquidity_per_day: Decimal,
+    in_range_share: Decimal,
+) -> Result<PositionSize, SizingError> {
+    if target_monthly_fees <= Decimal::ZERO {
+        return Err(SizingError::NonPositiveTarget);
+    }
+    if price <= Decimal::ZERO {
+        return Err(SizingError::InvalidPrice);
+    }
+    if fee_per_liquidity_per_day <= Decimal::ZERO {
+        return Err(SizingError::NoFeeData);
+    }
+    if in_range_share <= Decimal::ZERO {
+        return Err(SizingError::RangeNeverActive);
+    }
+
+    let daily_target = target_monthly_fees * Decimal::from(12) / Decimal::from(365);
+    let required_liquidity = daily_target / (fee_per_liquidity_per_day * in_range_share);
+    let amounts = amounts_for_liquidity(
+        required_liquidity,
+        price,
+        tick_to_price(range.lower),
+        tick_to_price(range.upper),
+    );
+    let capital = amounts.value_in_token1(price);
+
+    Ok(PositionSize {
+        target_monthly_fees,
+        required_liquidity,
+        amount0: amounts.amount0,
+        amount1: amounts.amount1,
+        capital,
+        price,
+        fee_per_liquidity_per_day,
+        in_range_share,
+        projected_apr: (target_monthly_fees * Decimal::from(12)).checked_div(capital),
+    })
+}
+
+/// One candidate range on the fee/IL frontier
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrontierPoint {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// `tick_upper - tick_lower`
+    pub width: i32,
+    pub price_lower: Decimal,
+    pub price_upper: Decimal,
+    pub risk: RiskCategory,
+    /// Share of recent volume that executed inside the range
+    pub in_range_share: Decimal,
+    /// Fees a year over the capital the range holds, as a fraction (None without a fee rate)
+    pub projected_apr: Option<Decimal>,
+    /// Loss against holding after the expected move, the worse of down and up
+    pub projected_il: Decimal,
+}
+
+/// Ticks a price with `daily_volatility` is expected to move over `horizon_days`: `σ × √days`
+pub fn expected_tick_move(daily_volatility: Decimal, horizon_days: u32) -> Decimal {
+    daily_volatility * Decimal::from(horizon_days).sqrt().unwrap_or(Decimal::ZERO)
+}
+
+/// Candidate widths in ticks for a frontier, narrow to wide
+///
+/// Multiples of the expected move from `FRONTIER_WIDTH_MOVES`, rounded up to
+/// the tick spacing; multiples that round to the same width appear once.
+pub fn frontier_widths(expected_move: Decimal, tick_spacing: i32) -> Vec<i32> {
+    let spacing = tick_spacing.max(1);
+    let mut widths: Vec<i32> = FRONTIER_WIDTH_MOVES
+        .iter()
+        .filter_map(|multiple| (expected_move * Decimal::from(*multiple)).ceil().to_i32())
+        .map(|width| ((width + spacing - 1) / spacing).max(1) * spacing)
+        .collect();
+    widths.dedup();
+    widths
+}
+
+/// Impermanent loss of a range once the price moves `move_ticks` down or up, the worse one
+///
+/// Compares what one unit of liquidity provided at `price` is worth after the
+/// move with holding the amounts it started with, as a fraction of the latter.
+fn projected_range_il(range: TickRange, price: Decimal, move_ticks: i32) -> Decimal {
+    let (price_lower, price_upper) = range_prices(range.lower, range.upper);
+    let entry = amounts_for_liquidity(Decimal::ONE, price, price_lower, price_upper);
+    [-move_ticks, move_ticks]
+        .into_iter()
+        .filter_map(|ticks| {
+            let moved = price * tick_to_price(ticks);
+            let held = entry.value_in_token1(moved);
+            let provided = value_per_liquidity(moved, price_lower, price_upper);
+            (held - provided).checked_div(held)
+        })
+        .fold(Decimal::ZERO, Decimal::max)
+}
+
+/// Ranges of increasing width centered on `price`, each with projected APR and IL
+///
+/// Every candidate is a `width`-tick range around the price's tick, aligned to
+/// the tick spacing. APR is the in-range fee rate per unit of liquidity times
+/// the share of `swaps` volume the range caught, over the capital a unit of
+/// liquidity takes at `price`: narrower ranges need less capital per unit and
+/// catch less volume. IL is after the `expected_tick_move` over the horizon.
+/// Widths that align to the same range appear once, narrow first.
+pub fn range_frontier(
+    swaps: &[Swap],
+    price: Decimal,
+    tick_spacing: i32,
+    widths: &[i32],
+    fee_per_liquidity_per_day: Option<Decimal>,
+    daily_volatility: Decimal,
+    horizon_days: u32,
+) -> Vec<FrontierPoint> {
+    let tick = price_to_tick(price);
+    let move_ticks =
+        expected_tick_move(daily_volatility, horizon_days).round().to_i32().unwrap_or(i32::MAX);
+
+    let mut ranges: Vec<TickRange> = widths
+        .iter()
+        .filter_map(|width| {
+            let (lower, upper) = centered_range(tick, *width, tick_spacing);
+            TickRange::new(lower, upper).ok()
+        })
+        .collect();
+    ranges.sort_by_key(|r| (r.width(), r.lower));
+    ranges.dedup();
+
+    ranges
+        .into_iter()
+        .map(|range| {
+            let (price_lower, price_upper) = range_prices(range.lower, range.upper);
+            let in_range_share = in_range_volume_share(swaps, range).unwrap_or(Decimal::ZERO);
+            let capital = value_per_liquidity(price, price_lower, price_upper);
+            let projected_apr = fee_per_liquidity_per_day
+                .and_then(|rate| (rate * in_range_share * Decimal::from(365)).checked_div(capital));
+            FrontierPoint {
+                tick_lower: range.lower,
+                tick_upper: range.upper,
+                width: range.width(),
+                price_lower,
+                price_upper,
+                risk: classify_risk(range, daily_volatility),
+                in_range_share,
+                projected_apr,
+                projected_il: projected_range_il(range, price, move_ticks),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use alloy::primitives::U256;
+    use chrono::{DateTime, Duration};
+    use stillwater_models::Address;
+
+    fn create_test_window(
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u64,
+        days: i64,
+        fees: i64,
+    ) -> (Position, SnapshotWindow) {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let position = Position {
+            id: 1,
+            nft_id: "1".to_string(),
+            owner: Address::ZERO,
+            pool_id: "0xpool".to_string(),
+            tick_lower,
+            tick_upper,
+            liquidity: U256::from(liquidity),
+            created_at: start,
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        };
+        let window = SnapshotWindow {
+            position_id: 1,
+            start_time: start,
+            end_time: start + Duration::days(days),
+            start_fees: Decimal::ZERO,
+            end_fees: Decimal::from(fees),
+            start_price: Decimal::ONE,
+            end_price: Decimal::ONE,
+            in_range_share: Decimal::ONE,
+        };
+        (position, window)
+    }
+
+    #[test]
+    fn test_fee_rate_skips_out_of_range_positions() {
+        let windows = vec![
+            // 1000 liquidity over 2 days earned 20: 0.01 per liquidity per day
+            create_test_window(-100, 100, 1000, 2, 20),
+            // Price (tick 0) sits outside this range; its fees don't count
+            create_test_window(100, 200, 1000, 2, 500),
+        ];
+
+        assert_eq!(fee_per_liquidity_per_day(&windows), Some(Decimal::new(1, 2)));
+        assert_eq!(fee_per_liquidity_per_day(&windows[1..]), None);
+    }
+
+    #[test]
+    fn test_size_scales_with_target_and_range_activity() {
+        let range = TickRange::new(-1000, 1000).unwrap();
+        let rate = Decimal::new(1, 2);
+
+        let full =
+            size_for_target_income(Decimal::from(365), range, Decimal::ONE, rate, Decimal::ONE)
+                .unwrap();
+        // 365 a month is 12 a day; at 0.01 per liquidity that takes 1200 liquidity
+        assert_eq!(full.required_liquidity, Decimal::from(1200));
+        assert!(full.amount0 > Decimal::ZERO && full.amount1 > Decimal::ZERO);
+
+        // Catching half the volume takes twice the liquidity
+        let half = size_for_target_income(
+            Decimal::from(365),
+            range,
+            Decimal::ONE,
+            rate,
+            Decimal::new(5, 1),
+        )
+        .unwrap();
+        assert_eq!(half.required_liquidity, Decimal::from(2400));
+
+        assert_eq!(
+            size_for_target_income(Decimal::from(365), range, Decimal::ONE, rate, Decimal::ZERO)
+                .unwrap_err(),
+            SizingError::RangeNeverActive
+        );
+    }
+
+    fn create_test_swap(id: i64, amount0: i64, amount1: i64) -> Swap {
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        testing::create_test_swap(id, amount0, amount1, timestamp)
+    }
+
+    #[test]
+    fn test_frontier_widths_align_to_spacing() {
+        // 25, 50, 75, 125, 200 and 325 ticks, rounded up to the 60-tick spacing
+        assert_eq!(frontier_widths(Decimal::from(25), 60), vec![60, 120, 180, 240, 360]);
+        assert_eq!(frontier_widths(Decimal::ZERO, 10), vec![10]);
+    }
+
+    #[test]
+    fn test_frontier_trades_fees_for_il() {
+        // Volume at ticks 0 and about 600
+        let swaps = vec![create_test_swap(1, -1000, 1000), create_test_swap(2, -1000, 1062)];
+        let rate = Some(Decimal::new(1, 2));
+
+        let frontier =
+            range_frontier(&swaps, Decimal::ONE, 10, &[2000, 200, 200], rate, Decimal::from(50), 4);
+
+        let widths: Vec<i32> = frontier.iter().map(|p| p.width).collect();
+        assert_eq!(widths, vec![200, 2000]);
+        let (narrow, wide) = (&frontier[0], &frontier[1]);
+        assert_eq!((narrow.tick_lower, narrow.tick_upper), (-100, 100));
+        assert_eq!(narrow.in_range_share, Decimal::from(1000) / Decimal::from(2062));
+        assert_eq!(wide.in_range_share, Decimal::ONE);
+        // The narrow range earns more on its capital but loses more to a 100-tick move
+        assert!(narrow.projected_apr.unwrap() > wide.projected_apr.unwrap());
+        assert!(narrow.projected_il > wide.projected_il);
+        assert!(wide.projected_il > Decimal::ZERO);
+
+        let unpriced = range_frontier(&swaps, Decimal::ONE, 10, &[200], None, Decimal::ZERO, 4);
+        assert_eq!(unpriced[0].projected_apr, None);
+        assert_eq!(unpriced[0].projected_il, Decimal::ZERO);
+    }
+}
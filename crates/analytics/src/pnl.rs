@@ -1,8 +1,11 @@
 use rust_decimal::Decimal;
 use rust_decimal::prelude::*;
-use stillwater_models::{Position, PositionPnL, Swap};
+use chrono::{DateTime, Utc};
+use stillwater_models::{GasExpense, Pool, Position, PositionPnL, PositionSnapshot, Swap};
 
-use crate::utils::tick_to_price;
+use crate::fees::{calculate_fees_earned_with_model, FeeModel};
+use crate::units::convert_amount;
+use crate::utils::{tick_to_price, RangeError, TickRange};
 
 /// Calculate fees earned from swaps
 ///
@@ -25,18 +28,7 @@ pub fn calculate_fees_earned(_position: &Position, swaps: &[Swap]) -> Decimal {
     // For MVP, estimate based on swap volumes and assume 0.3% fee tier
     let fee_rate = Decimal::from_str("0.003").unwrap(); // 0.3%
 
-    let total_volume: Decimal = swaps
-        .iter()
-        .map(|swap| {
-            // Use absolute values and convert to decimal
-            // This is a rough approximation
-            let amt0 = swap.amount0.abs().to_string();
-            let amt1 = swap.amount1.abs().to_string();
-
-            Decimal::from_str(&amt0).unwrap_or(Decimal::ZERO)
-                + Decimal::from_str(&amt1).unwrap_or(Decimal::ZERO)
-        })
-        .sum();
+    let total_volume: Decimal = swaps.iter().map(swap_volume).sum();
 
     // Estimate fees as a fraction of total volume
     // In production, would calculate exact share based on liquidity
@@ -45,6 +37,16 @@ pub fn calculate_fees_earned(_position: &Position, swaps: &[Swap]) -> Decimal {
     total_volume * fee_rate * estimated_position_share
 }
 
+/// Approximate volume of a swap in raw token1 units, counting both legs
+///
+/// The token0 leg is valued at the swap's execution price, where it's worth
+/// `|amount1|`. Adding raw token0 amounts as they are would mix in a token with
+/// its own decimals, overstating fees by up to `10^(decimals0 - decimals1)`.
+pub(crate) fn swap_volume(swap: &Swap) -> Decimal {
+    let amount1 = convert_amount(swap.amount1.unsigned_abs(), 0);
+    amount1.checked_mul(Decimal::TWO).unwrap_or(Decimal::ZERO)
+}
+
 /// Calculate impermanent loss for concentrated liquidity position
 ///
 /// IL for concentrated liquidity is different from full-range (v2) positions:
@@ -53,18 +55,22 @@ pub fn calculate_fees_earned(_position: &Position, swaps: &[Swap]) -> Decimal {
 ///
 /// Simplified formula:
 /// IL = (value_if_held - current_value) / value_if_held
+///
+/// Returns a `RangeError` for inverted or zero-width ranges rather than a made-up figure.
 pub fn calculate_impermanent_loss(
     position: &Position,
     initial_price: Decimal,
     current_price: Decimal,
-) -> Decimal {
+) -> Result<Decimal, RangeError> {
+    let range = TickRange::of(position)?;
+
     if initial_price.is_zero() || current_price.is_zero() {
-        return Decimal::ZERO;
+        return Ok(Decimal::ZERO);
     }
 
     // For positions with extreme tick ranges (like full-range positions),
     // use a simplified calculation to avoid overflow
-    let tick_range = (position.tick_upper - position.tick_lower).abs();
+    let tick_range = range.width();
 
     if tick_range > 1_000_000 {
         // This is likely a full-range position (e.g., ±887220)
@@ -72,7 +78,7 @@ pub fn calculate_impermanent_loss(
 
         // If price hasn't moved, no IL
         if (current_price - initial_price).abs() < Decimal::from_str("0.0001").unwrap() {
-            return Decimal::ZERO;
+            return Ok(Decimal::ZERO);
         }
 
         // For full-range positions, IL ≈ 2*sqrt(price_ratio) - price_ratio - 1
@@ -81,16 +87,16 @@ pub fn calculate_impermanent_loss(
 
         // Cap IL at reasonable value
         let il = price_change_pct * Decimal::from_str("0.2").unwrap(); // Max ~20% for moderate price changes
-        return il.min(Decimal::from_str("0.5").unwrap()); // Cap at 50%
+        return Ok(il.min(Decimal::from_str("0.5").unwrap())); // Cap at 50%
     }
 
     // For normal range positions, use tick-based calculation
-    let price_lower = tick_to_price(position.tick_lower);
-    let price_upper = tick_to_price(position.tick_upper);
+    let price_lower = tick_to_price(range.lower);
+    let price_upper = tick_to_price(range.upper);
 
     // If price hasn't moved, no IL
     if (current_price - initial_price).abs() < Decimal::from_str("0.0001").unwrap() {
-        return Decimal::ZERO;
+        return Ok(Decimal::ZERO);
     }
 
     // Simplified IL calculation for concentrated liquidity
@@ -102,7 +108,7 @@ pub fn calculate_impermanent_loss(
     // Range width factor: wider range = less IL (approaching v2 behavior)
     // Add safety check to avoid division by zero
     if price_lower.is_zero() {
-        return Decimal::ZERO;
+        return Ok(Decimal::ZERO);
     }
 
     let range_width = (price_upper - price_lower) / price_lower;
@@ -111,7 +117,7 @@ pub fn calculate_impermanent_loss(
     let il_factor = price_change_pct / (Decimal::ONE + range_width);
 
     // Simplified IL formula (in production, use exact Uniswap v3 math)
-    il_factor * Decimal::from_str("0.5").unwrap()
+    Ok(il_factor * Decimal::from_str("0.5").unwrap())
 }
 
 /// Calculate net P&L
@@ -119,6 +125,93 @@ pub fn calculate_net_pnl(fees: Decimal, il: Decimal, gas: Decimal) -> Decimal {
     fees - il - gas
 }
 
+/// Further fees a position needs to earn to cover its IL and gas (zero once it's in profit)
+pub fn fees_to_break_even(pnl: &PositionPnL) -> Decimal {
+    (-pnl.net_pnl).max(Decimal::ZERO)
+}
+
+/// Calculate complete position P&L using the pool's fee model
+pub fn calculate_position_pnl_with_model(
+    position: &Position,
+    pool: &Pool,
+    swaps: &[Swap],
+    model: &dyn FeeModel,
+    initial_price: Decimal,
+    current_price: Decimal,
+    gas_spent: Decimal,
+) -> Result<PositionPnL, RangeError> {
+    let fees_earned = calculate_fees_earned_with_model(position, pool, swaps, model);
+    let impermanent_loss = calculate_impermanent_loss(position, initial_price, current_price)?;
+    let net_pnl = calculate_net_pnl(fees_earned, impermanent_loss, gas_spent);
+
+    Ok(PositionPnL {
+        fees_earned,
+        impermanent_loss,
+        gas_spent,
+        net_pnl,
+    })
+}
+
+/// Recorded history used to reconstruct P&L at a point in time
+#[derive(Debug, Clone, Copy)]
+pub struct PnlHistory<'a> {
+    /// Pool swaps (may extend past `as_of`; later swaps are ignored)
+    pub swaps: &'a [Swap],
+    /// Position snapshots, providing the price series
+    pub snapshots: &'a [PositionSnapshot],
+    /// Gas paid for the position's transactions
+    pub gas: &'a [GasExpense],
+}
+
+/// Reconstruct a position's P&L as it stood at `as_of`
+///
+/// Only data stamped at or before `as_of` is used: swaps since the position
+/// was created, the earliest snapshot price as entry price, the latest
+/// snapshot price as current price, and gas paid so far. Returns `None` if the
+/// position didn't exist yet or no price had been recorded by then.
+pub fn calculate_position_pnl_at(
+    position: &Position,
+    pool: &Pool,
+    model: &dyn FeeModel,
+    history: &PnlHistory,
+    as_of: DateTime<Utc>,
+) -> Result<Option<PositionPnL>, RangeError> {
+    TickRange::of(position)?;
+
+    if position.created_at > as_of {
+        return Ok(None);
+    }
+
+    let prices = history.snapshots.iter().filter(|s| s.timestamp <= as_of);
+    let (Some(first), Some(last)) = (
+        prices.clone().min_by_key(|s| s.timestamp),
+        prices.max_by_key(|s| s.timestamp),
+    ) else {
+        return Ok(None);
+    };
+
+    let swaps: Vec<Swap> = history
+        .swaps
+        .iter()
+        .filter(|s| s.timestamp >= position.created_at && s.timestamp <= as_of)
+        .cloned()
+        .collect();
+
+    let gas_spent: Decimal =
+        history.gas.iter().filter(|g| g.timestamp <= as_of).map(|g| g.gas_cost).sum();
+
+    calculate_position_pnl_with_model(
+        position,
+        pool,
+        &swaps,
+        model,
+        first.price,
+        last.price,
+        gas_spent,
+    )
+    .map(Some)
+}
+
 /// Calculate complete position P&L
 pub fn calculate_position_pnl(
     position: &Position,
@@ -126,47 +219,28 @@ pub fn calculate_position_pnl(
     initial_price: Decimal,
     current_price: Decimal,
     gas_spent: Decimal,
-) -> PositionPnL {
+) -> Result<PositionPnL, RangeError> {
     let fees_earned = calculate_fees_earned(position, swaps);
-    let impermanent_loss = calculate_impermanent_loss(position, initial_price, current_price);
+    let impermanent_loss = calculate_impermanent_loss(position, initial_price, current_price)?;
     let net_pnl = calculate_net_pnl(fees_earned, impermanent_loss, gas_spent);
 
-    PositionPnL {
+    Ok(PositionPnL {
         fees_earned,
         impermanent_loss,
         gas_spent,
         net_pnl,
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy::primitives::{I256, U256};
+    use crate::testing::{self, create_test_position};
+    use alloy::primitives::U256;
     use chrono::Utc;
 
-    fn create_test_position() -> Position {
-        Position {
-            id: 1,
-            nft_id: "1".to_string(),
-            owner: "0xtest".to_string(),
-            pool_id: "0xpool".to_string(),
-            tick_lower: -1000,
-            tick_upper: 1000,
-            liquidity: U256::from(1000000u64),
-            created_at: Utc::now(),
-        }
-    }
-
     fn create_test_swap(amount0: i64, amount1: i64) -> Swap {
-        Swap {
-            id: 1,
-            tx_hash: "0xtx".to_string(),
-            pool_id: "0xpool".to_string(),
-            amount0: I256::try_from(amount0).unwrap(),
-            amount1: I256::try_from(amount1).unwrap(),
-            timestamp: Utc::now(),
-        }
+        testing::create_test_swap(1, amount0, amount1, Utc::now())
     }
 
     #[test]
@@ -187,10 +261,27 @@ mod tests {
         let initial_price = Decimal::from(100);
         let current_price = Decimal::from(110);
 
-        let il = calculate_impermanent_loss(&position, initial_price, current_price);
+        let il = calculate_impermanent_loss(&position, initial_price, current_price).unwrap();
         assert!(il >= Decimal::ZERO);
     }
 
+    #[test]
+    fn test_impermanent_loss_rejects_invalid_range() {
+        let mut position = create_test_position();
+        position.tick_lower = 1000;
+        position.tick_upper = -1000;
+        assert!(matches!(
+            calculate_impermanent_loss(&position, Decimal::from(100), Decimal::from(110)),
+            Err(RangeError::Inverted { .. })
+        ));
+
+        position.tick_upper = 1000;
+        assert!(matches!(
+            calculate_position_pnl(&position, &[], Decimal::ONE, Decimal::ONE, Decimal::ZERO),
+            Err(RangeError::ZeroWidth { tick: 1000 })
+        ));
+    }
+
     #[test]
     fn test_calculate_net_pnl() {
         let fees = Decimal::from(100);
@@ -201,6 +292,91 @@ mod tests {
         assert_eq!(net, Decimal::from(70));
     }
 
+    fn create_test_snapshot(timestamp: DateTime<Utc>, price: i64) -> PositionSnapshot {
+        PositionSnapshot {
+            id: 1,
+            position_id: 1,
+            timestamp,
+            fees_earned: Decimal::ZERO,
+            liquidity: U256::from(1000000u64),
+            price: Decimal::from(price),
+        }
+    }
+
+    #[test]
+    fn test_calculate_position_pnl_at() {
+        use crate::fees::StaticFeeModel;
+        use chrono::Duration;
+
+        let now = Utc::now();
+        let mut position = create_test_position();
+        position.created_at = now - Duration::days(10);
+
+        let pool = Pool {
+            pool_id: "0xpool".to_string(),
+            token0: "0xtoken0".to_string(),
+            token1: "0xtoken1".to_string(),
+            token0_decimals: 18,
+            token1_decimals: 18,
+            fee_tier: 3000,
+            tick_spacing: 60,
+            hooks: stillwater_models::NO_HOOKS.to_string(),
+            protocol_fee: 0,
+            created_at: Some(now - Duration::days(30)),
+            created_at_block: None,
+            fee_override: None,
+        };
+
+        let mut early_swap = create_test_swap(1000, 1000);
+        early_swap.timestamp = now - Duration::days(5);
+        let mut late_swap = create_test_swap(1000, 1000);
+        late_swap.timestamp = now - Duration::days(1);
+
+        let snapshots = vec![
+            create_test_snapshot(now - Duration::days(10), 100),
+            create_test_snapshot(now - Duration::days(5), 100),
+            create_test_snapshot(now - Duration::days(1), 150),
+        ];
+        let gas = vec![GasExpense {
+            id: 1,
+            position_id: 1,
+            tx_hash: "0xtx".to_string(),
+            gas_cost: Decimal::from(3),
+            timestamp: now - Duration::days(10),
+        }];
+        let history = PnlHistory { swaps: &[early_swap, late_swap], snapshots: &snapshots, gas: &gas };
+
+        let before_late = calculate_position_pnl_at(
+            &position,
+            &pool,
+            &StaticFeeModel,
+            &history,
+            now - Duration::days(3),
+        )
+        .unwrap()
+        .unwrap();
+        let latest = calculate_position_pnl_at(&position, &pool, &StaticFeeModel, &history, now)
+            .unwrap()
+            .unwrap();
+
+        // Price hadn't moved yet and only one swap had happened
+        assert_eq!(before_late.impermanent_loss, Decimal::ZERO);
+        assert_eq!(before_late.gas_spent, Decimal::from(3));
+        assert!(latest.fees_earned > before_late.fees_earned);
+        assert!(latest.impermanent_loss > Decimal::ZERO);
+
+        // Position didn't exist yet
+        assert!(calculate_position_pnl_at(
+            &position,
+            &pool,
+            &StaticFeeModel,
+            &history,
+            now - Duration::days(20)
+        )
+        .unwrap()
+        .is_none());
+    }
+
     #[test]
     fn test_calculate_position_pnl() {
         let position = create_test_position();
@@ -209,7 +385,8 @@ mod tests {
         let current_price = Decimal::from(105);
         let gas_spent = Decimal::from(5);
 
-        let pnl = calculate_position_pnl(&position, &swaps, initial_price, current_price, gas_spent);
+        let pnl = calculate_position_pnl(&position, &swaps, initial_price, current_price, gas_spent)
+            .unwrap();
 
         assert!(pnl.fees_earned >= Decimal::ZERO);
         assert!(pnl.impermanent_loss >= Decimal::ZERO);
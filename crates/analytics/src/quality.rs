@@ -0,0 +1,188 @@
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
+use stillwater_models::{DataQualityIssue, IssueKind, Swap};
+
+/// v4 swap deltas are int128, so no real amount needs more than 127 magnitude bits
+const MAX_AMOUNT_BITS: usize = 127;
+
+/// Thresholds for the swap data quality checks
+#[derive(Debug, Clone, Copy)]
+pub struct QualityConfig {
+    /// Longest quiet period between consecutive swaps before it is reported as a gap
+    pub max_gap: Duration,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self { max_gap: Duration::hours(24) }
+    }
+}
+
+/// Check one pool's swaps for gaps, impossible amounts, duplicates and ordering problems
+///
+/// `swaps` must be in insertion (id) order: a timestamp that goes backwards in
+/// that order means events were indexed out of sequence. Gaps are measured
+/// on the time-sorted series so a single out-of-order swap isn't also
+/// reported as a gap.
+pub fn check_swap_quality(
+    pool_id: &str,
+    swaps: &[Swap],
+    config: &QualityConfig,
+) -> Vec<DataQualityIssue> {
+    let detected_at = Utc::now();
+    let issue = |kind: IssueKind, swap: &Swap, detail: String| DataQualityIssue {
+        pool_id: pool_id.to_string(),
+        kind,
+        swap_id: swap.id,
+        tx_hash: swap.tx_hash.clone(),
+        detail,
+        detected_at,
+    };
+
+    let mut issues = Vec::new();
+    let mut seen: HashMap<String, i64> = HashMap::new();
+
+    for (i, swap) in swaps.iter().enumerate() {
+        if let Some(reason) = impossible_amount(swap) {
+            issues.push(issue(IssueKind::ImpossibleAmount, swap, reason.to_string()));
+        }
+
+        // Hashes differing only in case slip past the (tx_hash, pool_id) constraint
+        match seen.get(&swap.tx_hash.to_lowercase()) {
+            Some(first_id) => issues.push(issue(
+                IssueKind::DuplicateEvent,
+                swap,
+                format!("same transaction as swap {}", first_id),
+            )),
+            None => {
+                seen.insert(swap.tx_hash.to_lowercase(), swap.id);
+            }
+        }
+
+        if i > 0 && swap.timestamp < swaps[i - 1].timestamp {
+            issues.push(issue(
+                IssueKind::NonMonotonic,
+                swap,
+                format!(
+                    "timestamp {} precedes previous swap {} at {}",
+                    swap.timestamp.to_rfc3339(),
+                    swaps[i - 1].id,
+                    swaps[i - 1].timestamp.to_rfc3339()
+                ),
+            ));
+        }
+    }
+
+    let mut by_time: Vec<&Swap> = swaps.iter().collect();
+    by_time.sort_by_key(|s| (s.timestamp, s.id));
+    for pair in by_time.windows(2) {
+        let gap = pair[1].timestamp - pair[0].timestamp;
+        if gap > config.max_gap {
+            issues.push(issue(
+                IssueKind::TimestampGap,
+                pair[1],
+                format!("no swaps for {}h before this one", gap.num_hours()),
+            ));
+        }
+    }
+
+    issues
+}
+
+fn impossible_amount(swap: &Swap) -> Option<&'static str> {
+    if swap.amount0.is_zero() && swap.amount1.is_zero() {
+        return Some("both amounts are zero");
+    }
+    if swap.amount0.is_positive() && swap.amount1.is_positive() {
+        return Some("both amounts flow into the pool");
+    }
+    if swap.amount0.is_negative() && swap.amount1.is_negative() {
+        return Some("both amounts flow out of the pool");
+    }
+    if swap.amount0.unsigned_abs().bit_len() > MAX_AMOUNT_BITS
+        || swap.amount1.unsigned_abs().bit_len() > MAX_AMOUNT_BITS
+    {
+        return Some("amount exceeds int128 range");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::I256;
+    use chrono::{DateTime, TimeZone};
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::hours(hours)
+    }
+
+    fn swap(id: i64, tx_hash: &str, amount0: i64, amount1: i64, hours: i64) -> Swap {
+        Swap {
+            id,
+            tx_hash: tx_hash.to_string(),
+            pool_id: "0xpool".to_string(),
+            amount0: I256::try_from(amount0).unwrap(),
+            amount1: I256::try_from(amount1).unwrap(),
+            fee: None,
+            timestamp: at(hours),
+        }
+    }
+
+    fn kinds(issues: &[DataQualityIssue]) -> Vec<(IssueKind, i64)> {
+        issues.iter().map(|i| (i.kind, i.swap_id)).collect()
+    }
+
+    #[test]
+    fn test_clean_swaps_have_no_issues() {
+        let swaps = vec![swap(1, "0xa", 100, -99, 0), swap(2, "0xb", -50, 51, 1)];
+        assert!(check_swap_quality("0xpool", &swaps, &QualityConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_impossible_amounts() {
+        let swaps = vec![
+            swap(1, "0xa", 0, 0, 0),
+            swap(2, "0xb", 10, 10, 1),
+            swap(3, "0xc", -10, -10, 2),
+        ];
+        let issues = check_swap_quality("0xpool", &swaps, &QualityConfig::default());
+        assert_eq!(
+            kinds(&issues),
+            vec![
+                (IssueKind::ImpossibleAmount, 1),
+                (IssueKind::ImpossibleAmount, 2),
+                (IssueKind::ImpossibleAmount, 3),
+            ]
+        );
+
+        let mut huge = swap(4, "0xd", 1, -1, 3);
+        huge.amount0 = I256::MAX;
+        assert_eq!(impossible_amount(&huge), Some("amount exceeds int128 range"));
+    }
+
+    #[test]
+    fn test_duplicate_and_out_of_order() {
+        let swaps = vec![
+            swap(1, "0xAB", 10, -9, 5),
+            swap(2, "0xab", 10, -9, 5),
+            swap(3, "0xcd", 10, -9, 4),
+        ];
+        let issues = check_swap_quality("0xpool", &swaps, &QualityConfig::default());
+        assert_eq!(
+            kinds(&issues),
+            vec![(IssueKind::DuplicateEvent, 2), (IssueKind::NonMonotonic, 3)]
+        );
+    }
+
+    #[test]
+    fn test_gap_reported_once_on_later_swap() {
+        let swaps = vec![swap(1, "0xa", 10, -9, 0), swap(2, "0xb", 10, -9, 30)];
+        let issues = check_swap_quality("0xpool", &swaps, &QualityConfig::default());
+        assert_eq!(kinds(&issues), vec![(IssueKind::TimestampGap, 2)]);
+        assert!(issues[0].detail.contains("30h"));
+
+        let relaxed = QualityConfig { max_gap: Duration::hours(48) };
+        assert!(check_swap_quality("0xpool", &swaps, &relaxed).is_empty());
+    }
+}
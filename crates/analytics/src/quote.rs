@@ -0,0 +1,284 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::fmt;
+
+use crate::tickmath::sqrt_price_at_tick;
+
+/// An initialized tick and the liquidity added when it's crossed upward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickLiquidity {
+    pub tick: i32,
+    /// Liquidity of ranges starting here minus liquidity of ranges ending here
+    pub liquidity_net: Decimal,
+}
+
+/// Pool state a swap walks through
+#[derive(Debug, Clone)]
+pub struct PoolTicks {
+    /// Current price (token1 per token0, raw units), within `tick`
+    pub price: Decimal,
+    pub tick: i32,
+    /// Liquidity active at the current tick
+    pub liquidity: Decimal,
+    /// Initialized ticks, in any order
+    pub ticks: Vec<TickLiquidity>,
+    /// LP fee charged on the input, as a fraction
+    pub fee_rate: Decimal,
+}
+
+/// Why a swap couldn't be simulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteError {
+    /// amount_in is zero or negative
+    NonPositiveAmount,
+    /// The pool price isn't positive
+    InvalidPrice,
+    /// The fee rate isn't in [0, 1)
+    InvalidFee,
+    /// Active liquidity went negative at a tick, so the tick data is inconsistent
+    NegativeLiquidity { tick: i32 },
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteError::NonPositiveAmount => write!(f, "amount_in must be positive"),
+            QuoteError::InvalidPrice => write!(f, "pool price must be positive"),
+            QuoteError::InvalidFee => write!(f, "fee rate must be at least 0 and below 1"),
+            QuoteError::NegativeLiquidity { tick } => {
+                write!(f, "active liquidity is negative at tick {}", tick)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+/// Estimated outcome of a swap against a pool's tick liquidity
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapQuote {
+    pub zero_for_one: bool,
+    /// Input the pool absorbed, fee included
+    pub amount_in: Decimal,
+    pub amount_out: Decimal,
+    pub fee_paid: Decimal,
+    /// Input left over once liquidity ran out (zero when fully filled)
+    pub unfilled: Decimal,
+    /// Pool price before the swap (token1 per token0)
+    pub spot_price: Decimal,
+    /// Average price of the swap, fee included (token1 per token0)
+    pub execution_price: Option<Decimal>,
+    pub price_after: Decimal,
+    /// How much worse the execution price is than spot, in percent
+    pub price_impact_percent: Option<Decimal>,
+    pub ticks_crossed: usize,
+}
+
+/// Estimate the output of swapping `amount_in` through a pool's tick liquidity
+///
+/// Token0 is sold for token1 when `zero_for_one`, token1 for token0
+/// otherwise. The walk uses the constant-product formulas within each tick
+/// interval and applies `liquidity_net` at every initialized tick it
+/// crosses, charging the fee on the input absorbed. Decimal math makes this an
+/// estimate rather than an exact quote. Liquidity going negative on the way
+/// means the tick data doesn't add up and is reported as an error.
+pub fn simulate_swap(
+    pool: &PoolTicks,
+    amount_in: Decimal,
+    zero_for_one: bool,
+) -> Result<SwapQuote, QuoteError> {
+    if amount_in <= Decimal::ZERO {
+        return Err(QuoteError::NonPositiveAmount);
+    }
+    if pool.fee_rate < Decimal::ZERO || pool.fee_rate >= Decimal::ONE {
+        return Err(QuoteError::InvalidFee);
+    }
+    let Some(start) = pool.price.sqrt().filter(|p| *p > Decimal::ZERO) else {
+        return Err(QuoteError::InvalidPrice);
+    };
+    if pool.liquidity < Decimal::ZERO {
+        return Err(QuoteError::NegativeLiquidity { tick: pool.tick });
+    }
+
+    // Initialized ticks in the direction of travel, nearest first
+    let mut ticks: Vec<TickLiquidity> = pool
+        .ticks
+        .iter()
+        .filter(|t| if zero_for_one { t.tick <= pool.tick } else { t.tick > pool.tick })
+        .copied()
+        .collect();
+    ticks.sort_by_key(|t| t.tick);
+    if zero_for_one {
+        ticks.reverse();
+    }
+
+    let fee_factor = Decimal::ONE - pool.fee_rate;
+    let mut remaining = amount_in * fee_factor;
+    let mut sqrt_price = start;
+    let mut liquidity = pool.liquidity;
+    let mut amount_out = Decimal::ZERO;
+    let mut ticks_crossed = 0;
+
+    let mut boundaries = ticks.iter();
+    while remaining > Decimal::ZERO {
+        let Some(boundary) = boundaries.next() else {
+            // Past the last initialized tick liquidity stays constant
+            if liquidity > Decimal::ZERO {
+                let reached = price_after_input(liquidity, sqrt_price, remaining, zero_for_one);
+                amount_out += output_between(liquidity, sqrt_price, reached, zero_for_one);
+                sqrt_price = reached;
+                remaining = Decimal::ZERO;
+            }
+            break;
+        };
+        let target = sqrt_price_at_tick(boundary.tick);
+
+        if liquidity > Decimal::ZERO {
+            let needed = input_to_reach(liquidity, sqrt_price, target, zero_for_one);
+            if remaining < needed {
+                let reached = price_after_input(liquidity, sqrt_price, remaining, zero_for_one);
+                amount_out += output_between(liquidity, sqrt_price, reached, zero_for_one);
+                sqrt_price = reached;
+                remaining = Decimal::ZERO;
+                break;
+            }
+            amount_out += output_between(liquidity, sqrt_price, target, zero_for_one);
+            remaining -= needed;
+        }
+        sqrt_price = target;
+
+        liquidity = if zero_for_one {
+            liquidity - boundary.liquidity_net
+        } else {
+            liquidity + boundary.liquidity_net
+        };
+        if liquidity < Decimal::ZERO {
+            return Err(QuoteError::NegativeLiquidity { tick: boundary.tick });
+        }
+        ticks_crossed += 1;
+    }
+
+    let absorbed = amount_in * fee_factor - remaining;
+    let consumed = absorbed / fee_factor;
+    let execution_price = if zero_for_one {
+        amount_out.checked_div(consumed)
+    } else {
+        consumed.checked_div(amount_out)
+    }
+    .filter(|p| *p > Decimal::ZERO);
+    let price_impact_percent = execution_price.and_then(|exec| {
+        let worse = if zero_for_one { pool.price - exec } else { exec - pool.price };
+        worse.checked_div(pool.price).map(|r| r * Decimal::from(100))
+    });
+
+    Ok(SwapQuote {
+        zero_for_one,
+        amount_in: consumed,
+        amount_out,
+        fee_paid: consumed - absorbed,
+        unfilled: amount_in - consumed,
+        spot_price: pool.price,
+        execution_price,
+        price_after: sqrt_price * sqrt_price,
+        price_impact_percent,
+        ticks_crossed,
+    })
+}
+
+/// Input (net of fee) that moves the sqrt price from `from` to `to`
+fn input_to_reach(liquidity: Decimal, from: Decimal, to: Decimal, zero_for_one: bool) -> Decimal {
+    if zero_for_one { liquidity * (from - to) / (from * to) } else { liquidity * (to - from) }
+}
+
+/// Output paid out while the sqrt price moves from `from` to `to`
+fn output_between(liquidity: Decimal, from: Decimal, to: Decimal, zero_for_one: bool) -> Decimal {
+    input_to_reach(liquidity, from, to, !zero_for_one).abs()
+}
+
+/// Sqrt price after `amount` (net of fee) is swapped in at constant liquidity
+fn price_after_input(
+    liquidity: Decimal,
+    sqrt_price: Decimal,
+    amount: Decimal,
+    zero_for_one: bool,
+) -> Decimal {
+    if zero_for_one {
+        liquidity * sqrt_price / (liquidity + amount * sqrt_price)
+    } else {
+        sqrt_price + amount / liquidity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_pool(liquidity: i64, ticks: &[(i32, i64)], fee_rate: Decimal) -> PoolTicks {
+        PoolTicks {
+            price: Decimal::ONE,
+            tick: 0,
+            liquidity: Decimal::from(liquidity),
+            ticks: ticks
+                .iter()
+                .map(|(tick, net)| TickLiquidity {
+                    tick: *tick,
+                    liquidity_net: Decimal::from(*net),
+                })
+                .collect(),
+            fee_rate,
+        }
+    }
+
+    #[test]
+    fn test_swap_within_one_range() {
+        let pool =
+            create_test_pool(1_000_000, &[(-1000, 1_000_000), (1000, -1_000_000)], Decimal::ZERO);
+
+        let quote = simulate_swap(&pool, Decimal::from(1000), true).unwrap();
+
+        // At sqrt price 1 the output is L·x / (L + x)
+        assert_eq!(quote.amount_out.round_dp(6), Decimal::from_str("999.000999").unwrap());
+        assert_eq!(quote.ticks_crossed, 0);
+        assert!(quote.unfilled.is_zero());
+        assert!(quote.price_after < Decimal::ONE);
+        let impact = quote.price_impact_percent.unwrap();
+        assert!(impact > Decimal::ZERO && impact < Decimal::ONE);
+    }
+
+    #[test]
+    fn test_swap_crosses_ticks_and_charges_fee() {
+        // Deep liquidity up to tick 100, thin above
+        let ticks = [(-1000, 1_000_000), (100, -900_000), (1000, -100_000)];
+        let pool = create_test_pool(1_000_000, &ticks, Decimal::new(3, 3));
+
+        let small = simulate_swap(&pool, Decimal::from(1000), false).unwrap();
+        let large = simulate_swap(&pool, Decimal::from(8_000), false).unwrap();
+
+        assert_eq!(small.ticks_crossed, 0);
+        assert_eq!(small.fee_paid, Decimal::from(3));
+        assert_eq!(large.ticks_crossed, 1);
+        assert!(large.price_impact_percent.unwrap() > small.price_impact_percent.unwrap());
+
+        // Selling more than the pool holds leaves the rest unfilled
+        let huge = simulate_swap(&pool, Decimal::from(1_000_000), false).unwrap();
+        assert_eq!(huge.ticks_crossed, 2);
+        assert!(huge.unfilled > Decimal::ZERO);
+        assert_eq!(huge.amount_in + huge.unfilled, Decimal::from(1_000_000));
+    }
+
+    #[test]
+    fn test_inconsistent_ticks_are_reported() {
+        // Crossing tick 100 upward removes more liquidity than is active
+        let pool = create_test_pool(1_000, &[(100, -5_000)], Decimal::ZERO);
+
+        assert_eq!(
+            simulate_swap(&pool, Decimal::from(1_000_000), false).unwrap_err(),
+            QuoteError::NegativeLiquidity { tick: 100 }
+        );
+        assert_eq!(
+            simulate_swap(&pool, Decimal::ZERO, true).unwrap_err(),
+            QuoteError::NonPositiveAmount
+        );
+    }
+}
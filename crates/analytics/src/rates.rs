@@ -0,0 +1,435 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use stillwater_models::{Pool, Position, TokenAmount};
+
+use crate::display::PriceDisplay;
+use crate::liquidity::{amounts_for_liquidity, range_prices};
+use crate::pnl::calculate_impermanent_loss;
+use crate::units::raw_to_units;
+
+/// Quote currency portfolio totals use unless `QUOTE_CURRENCY` is set
+pub const DEFAULT_QUOTE_CURRENCY: &str = "usd";
+
+/// Most pools between a token and a quote anchor when deriving its price
+pub const MAX_RATE_HOPS: usize = 2;
+
+/// Mainnet stablecoins counted as one USD: USDC, USDT, DAI
+const DEFAULT_USD_TOKENS: [&str; 3] = [
+    "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+    "0xdac17f958d2ee523a2206206994597c13d831ec7",
+    "0x6b175474e89094c44da98b954eedeac495271d0f",
+];
+
+/// Tokens counted as one ETH: native ETH (the zero address in v4 pools) and WETH
+const DEFAULT_ETH_TOKENS: [&str; 2] =
+    ["0x0000000000000000000000000000000000000000", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"];
+
+/// Currencies values can be normalized into, each anchored by tokens worth one unit of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuoteCurrencies {
+    /// Currency used when a request names none
+    pub default_currency: String,
+    /// Lowercase anchor token addresses per currency
+    pub anchors: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for QuoteCurrencies {
+    fn default() -> Self {
+        let tokens = |list: &[&str]| list.iter().map(|t| t.to_string()).collect();
+        Self {
+            default_currency: DEFAULT_QUOTE_CURRENCY.to_string(),
+            anchors: BTreeMap::from([
+                ("usd".to_string(), tokens(&DEFAULT_USD_TOKENS)),
+                ("eth".to_string(), tokens(&DEFAULT_ETH_TOKENS)),
+            ]),
+        }
+    }
+}
+
+impl QuoteCurrencies {
+    /// Currencies from `QUOTE_CURRENCY`, `QUOTE_USD_TOKENS` and `QUOTE_ETH_TOKENS`
+    ///
+    /// The token lists are comma-separated addresses replacing the mainnet defaults,
+    /// e.g. for another chain's stablecoins.
+    pub fn from_env() -> Result<Self> {
+        let mut currencies = Self::default();
+        for (currency, var) in [("usd", "QUOTE_USD_TOKENS"), ("eth", "QUOTE_ETH_TOKENS")] {
+            let Ok(tokens) = std::env::var(var) else {
+                continue;
+            };
+            let tokens: Vec<String> = tokens
+                .split(',')
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            if tokens.is_empty() {
+                bail!("{} must list at least one token address", var);
+            }
+            currencies.anchors.insert(currency.to_string(), tokens);
+        }
+        if let Ok(currency) = std::env::var("QUOTE_CURRENCY") {
+            let currency = currency.trim().to_lowercase();
+            if !currencies.anchors.contains_key(&currency) {
+                bail!("QUOTE_CURRENCY must be one of: {}", currencies.names().join(", "));
+            }
+            currencies.default_currency = currency;
+        }
+        Ok(currencies)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.anchors.keys().map(String::as_str).collect()
+    }
+
+    /// Anchor tokens of a currency (case-insensitive), None if it isn't known
+    pub fn anchors_for(&self, currency: &str) -> Option<&[String]> {
+        self.anchors.get(&currency.to_lowercase()).map(Vec::as_slice)
+    }
+}
+
+/// A token's price in the quote currency and how it was derived
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenRate {
+    /// Quote currency per whole token
+    pub price: Decimal,
+    /// Pools the price was carried through from an anchor, nearest the token first;
+    /// empty for anchors
+    pub route: Vec<String>,
+}
+
+/// Token prices in one quote currency, all taken from pool prices at the same time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExchangeRates {
+    pub currency: String,
+    /// When the pool prices were taken
+    pub as_of: DateTime<Utc>,
+    /// Lowercase token address to its rate
+    pub rates: BTreeMap<String, TokenRate>,
+}
+
+impl ExchangeRates {
+    /// Rates knowing only the anchors, each worth one unit of `currency`
+    pub fn new(currency: &str, anchors: &[String], as_of: DateTime<Utc>) -> Self {
+        let rates = anchors
+            .iter()
+            .map(|token| (token.to_lowercase(), TokenRate { price: Decimal::ONE, route: vec![] }))
+            .collect();
+        Self { currency: currency.to_lowercase(), as_of, rates }
+    }
+
+    /// Price tokens through pools whose other token already has a rate
+    ///
+    /// `pools` pairs each pool with its raw price (token1 per token0) at
+    /// `as_of`. Tokens are priced in rounds, one pool further from an anchor
+    /// each round, so a token takes the shortest route; between routes of the
+    /// same length the earlier pool wins. Returns how many tokens were priced.
+    pub fn link(&mut self, pools: &[(Pool, Decimal)]) -> usize {
+        let mut priced = 0;
+        for _ in 0..=pools.len() {
+            let mut found: HashMap<String, TokenRate> = HashMap::new();
+            for (pool, raw_price) in pools {
+                let Some(display) = PriceDisplay::for_pool(pool, "token1") else {
+                    continue;
+                };
+                // Whole token1 per whole token0
+                let price = display.to_display(*raw_price);
+                let Some(inverse) = Decimal::ONE.checked_div(price) else {
+                    continue;
+                };
+                let (token0, token1) = (pool.token0.to_lowercase(), pool.token1.to_lowercase());
+                let sides = [(&token0, &token1, price), (&token1, &token0, inverse)];
+                for (token, counterpart, in_counterpart) in sides {
+                    if self.rates.contains_key(token) || found.contains_key(token) {
+                        continue;
+                    }
+                    let Some(known) = self.rates.get(counterpart) else {
+                        continue;
+                    };
+                    if known.route.len() >= MAX_RATE_HOPS {
+                        continue;
+                    }
+                    let Some(price) = known.price.checked_mul(in_counterpart) else {
+                        continue;
+                    };
+                    let mut route = vec![pool.pool_id.clone()];
+                    route.extend(known.route.iter().cloned());
+                    found.insert(token.clone(), TokenRate { price, route });
+                }
+            }
+            if found.is_empty() {
+                break;
+            }
+            priced += found.len();
+            self.rates.extend(found);
+        }
+        priced
+    }
+
+    pub fn rate(&self, token: &str) -> Option<&TokenRate> {
+        self.rates.get(&token.to_lowercase())
+    }
+
+    /// A raw amount of `token` in the quote currency, None without a rate
+    pub fn convert(&self, token: &str, raw: Decimal, decimals: i16) -> Option<Decimal> {
+        let rate = self.rate(token)?;
+        raw_to_units(raw, decimals).checked_mul(rate.price)
+    }
+
+    /// A token amount in the quote currency, None without a rate for its token
+    pub fn value(&self, amount: &TokenAmount) -> Option<Decimal> {
+        let rate = self.rate(&amount.token.to_string())?;
+        amount.to_decimal().checked_mul(rate.price)
+    }
+}
+
+/// A position's value, fees and IL in the quote currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NormalizedPosition {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    /// Token amounts backing the liquidity at the pool price
+    pub value: Option<Decimal>,
+    pub fees: Option<Decimal>,
+    /// Impermanent loss since entry, as its fraction of `value`
+    pub impermanent_loss: Option<Decimal>,
+    /// Set when the pool's tokens have no rate, leaving the figures above unset
+    pub missing_price: Option<String>,
+}
+
+/// Normalize a position's figures, all of which are denominated in its pool's token1
+///
+/// `raw_price` is the pool price (token1 per token0, raw) at the rates'
+/// `as_of`; without one, the price implied by both tokens' rates is used.
+/// `fees` are raw token1 and IL is measured from `entry_price` (raw). A
+/// token without a rate that the figures need leaves them unset.
+pub fn normalize_position(
+    position: &Position,
+    pool: &Pool,
+    raw_price: Option<Decimal>,
+    entry_price: Option<Decimal>,
+    fees: Decimal,
+    rates: &ExchangeRates,
+) -> NormalizedPosition {
+    let mut normalized = NormalizedPosition {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        pool_id: pool.pool_id.clone(),
+        value: None,
+        fees: None,
+        impermanent_loss: None,
+        missing_price: None,
+    };
+    let Some(rate1) = rates.rate(&pool.token1) else {
+        normalized.missing_price = Some(pool.token1.to_lowercase());
+        return normalized;
+    };
+    let implied_price = || {
+        let rate0 = rates.rate(&pool.token0)?;
+        let display = PriceDisplay::for_pool(pool, "token1")?;
+        Some(display.to_raw(rate0.price.checked_div(rate1.price)?))
+    };
+    let Some(price) = raw_price.or_else(implied_price) else {
+        normalized.missing_price = Some(pool.token0.to_lowercase());
+        return normalized;
+    };
+
+    let liquidity = position.liquidity.to_string().parse::<Decimal>().unwrap_or(Decimal::ZERO);
+    let (price_lower, price_upper) = range_prices(position.tick_lower, position.tick_upper);
+    let value_raw =
+        amounts_for_liquidity(liquidity, price, price_lower, price_upper).value_in_token1(price);
+
+    normalized.value = rates.convert(&pool.token1, value_raw, pool.token1_decimals);
+    normalized.fees = rates.convert(&pool.token1, fees, pool.token1_decimals);
+    let il = entry_price.and_then(|entry| calculate_impermanent_loss(position, entry, price).ok());
+    normalized.impermanent_loss =
+        il.zip(normalized.value).and_then(|(il, value)| il.checked_mul(value));
+    normalized
+}
+
+/// Sums of normalized positions in one quote currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NormalizedTotals {
+    pub currency: String,
+    pub as_of: DateTime<Utc>,
+    pub value: Decimal,
+    pub fees: Decimal,
+    pub impermanent_loss: Decimal,
+    pub priced_positions: usize,
+    /// Positions left out of the totals for lack of a price
+    pub unpriced_positions: usize,
+    /// Tokens without a rate, so totals are incomplete when non-empty
+    pub missing_prices: Vec<String>,
+}
+
+/// Add up normalized positions, counting the ones that couldn't be priced separately
+pub fn sum_normalized(positions: &[NormalizedPosition], rates: &ExchangeRates) -> NormalizedTotals {
+    let mut totals = NormalizedTotals {
+        currency: rates.currency.clone(),
+        as_of: rates.as_of,
+        value: Decimal::ZERO,
+        fees: Decimal::ZERO,
+        impermanent_loss: Decimal::ZERO,
+        priced_positions: 0,
+        unpriced_positions: 0,
+        missing_prices: vec![],
+    };
+    for position in positions {
+        if let Some(token) = &position.missing_price {
+            totals.unpriced_positions += 1;
+            if !totals.missing_prices.contains(token) {
+                totals.missing_prices.push(token.clone());
+            }
+            continue;
+        }
+        totals.priced_positions += 1;
+        totals.value += position.value.unwrap_or_default();
+        totals.fees += position.fees.unwrap_or_default();
+        totals.impermanent_loss += position.impermanent_loss.unwrap_or_default();
+    }
+    totals.missing_prices.sort();
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use chrono::TimeZone;
+    use stillwater_models::Address;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    fn pool(pool_id: &str, token0: &str, token1: &str, decimals0: i16, decimals1: i16) -> Pool {
+        Pool {
+            pool_id: pool_id.to_string(),
+            token0: token0.to_string(),
+            token1: token1.to_string(),
+            token0_decimals: decimals0,
+            token1_decimals: decimals1,
+            fee_tier: 3000,
+            tick_spacing: 60,
+            hooks: "0x0000000000000000000000000000000000000000".to_string(),
+            protocol_fee: 0,
+            created_at: None,
+            created_at_block: None,
+            fee_override: None,
+        }
+    }
+
+    fn position(id: i64, pool_id: &str, liquidity: u64) -> Position {
+        Position {
+            id,
+            nft_id: id.to_string(),
+            owner: Address::ZERO,
+            pool_id: pool_id.to_string(),
+            tick_lower: -600,
+            tick_upper: 600,
+            liquidity: U256::from(liquidity),
+            created_at: Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        }
+    }
+
+    fn usd_rates() -> ExchangeRates {
+        let as_of = Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap();
+        ExchangeRates::new("USD", &[USDC.to_string()], as_of)
+    }
+
+    #[test]
+    fn test_rates_follow_shortest_route() {
+        let mut rates = usd_rates();
+        // 1 WETH = 2000 USDC: raw USDC per raw WETH is 2000 / 10^(18 - 6)
+        let weth_usdc = pool("0xa", "0xweth", USDC, 18, 6);
+        let weth_price = Decimal::from(2000) / Decimal::from(1_000_000_000_000u64);
+        // 1 TKN = 0.5 WETH, both 18 decimals
+        let tkn_weth = pool("0xb", "0xtkn", "0xweth", 18, 18);
+
+        let priced = rates.link(&[(tkn_weth, Decimal::new(5, 1)), (weth_usdc, weth_price)]);
+
+        assert_eq!(priced, 2);
+        assert_eq!(rates.rate("0xWETH").unwrap().price, Decimal::from(2000));
+        assert_eq!(rates.rate("0xweth").unwrap().route, vec!["0xa".to_string()]);
+        let tkn = rates.rate("0xtkn").unwrap();
+        assert_eq!(tkn.price, Decimal::from(1000));
+        assert_eq!(tkn.route, vec!["0xb".to_string(), "0xa".to_string()]);
+        assert_eq!(
+            rates.convert("0xtkn", Decimal::from(3_000_000_000_000_000_000u64), 18),
+            Some(Decimal::from(3000))
+        );
+    }
+
+    #[test]
+    fn test_rates_stop_after_max_hops() {
+        let mut rates = usd_rates();
+        let pools = [
+            (pool("0xa", "0xt1", USDC, 6, 6), Decimal::ONE),
+            (pool("0xb", "0xt2", "0xt1", 6, 6), Decimal::ONE),
+            (pool("0xc", "0xt3", "0xt2", 6, 6), Decimal::ONE),
+        ];
+        rates.link(&pools);
+        assert!(rates.rate("0xt2").is_some());
+        assert!(rates.rate("0xt3").is_none());
+        assert_eq!(rates.convert("0xt3", Decimal::ONE, 6), None);
+    }
+
+    #[test]
+    fn test_totals_leave_out_unpriced_positions() {
+        let mut rates = usd_rates();
+        let priced_pool = pool("0xa", "0xtkn", USDC, 6, 6);
+        let orphan_pool = pool("0xb", "0xfoo", "0xbar", 6, 6);
+        rates.link(&[(priced_pool.clone(), Decimal::ONE), (orphan_pool.clone(), Decimal::ONE)]);
+
+        let fees = Decimal::from(2_000_000);
+        let entry = Some(Decimal::new(11, 1));
+        let positions = [
+            normalize_position(
+                &position(1, "0xa", 1_000_000),
+                &priced_pool,
+                Some(Decimal::ONE),
+                entry,
+                fees,
+                &rates,
+            ),
+            normalize_position(
+                &position(2, "0xb", 1_000_000),
+                &orphan_pool,
+                Some(Decimal::ONE),
+                entry,
+                fees,
+                &rates,
+            ),
+            // No pool price: valued at the price both tokens' rates imply
+            normalize_position(
+                &position(3, "0xa", 1_000_000),
+                &priced_pool,
+                None,
+                None,
+                fees,
+                &rates,
+            ),
+        ];
+
+        assert_eq!(positions[0].fees, Some(Decimal::from(2)));
+        assert!(positions[0].value.unwrap() > Decimal::ZERO);
+        assert!(positions[0].impermanent_loss.unwrap() > Decimal::ZERO);
+        assert_eq!(positions[1].missing_price.as_deref(), Some("0xbar"));
+        assert_eq!(positions[1].value, None);
+        assert_eq!(positions[2].value, positions[0].value);
+        assert_eq!(positions[2].impermanent_loss, None);
+
+        let totals = sum_normalized(&positions, &rates);
+        assert_eq!(totals.currency, "usd");
+        assert_eq!(totals.priced_positions, 2);
+        assert_eq!(totals.unpriced_positions, 1);
+        assert_eq!(totals.missing_prices, vec!["0xbar".to_string()]);
+        assert_eq!(totals.fees, Decimal::from(4));
+        assert_eq!(totals.value, positions[0].value.unwrap() * Decimal::TWO);
+        assert_eq!(totals.impermanent_loss, positions[0].impermanent_loss.unwrap());
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::MarketPoint;
+use crate::liquidity::{amounts_for_liquidity, liquidity_for_value, range_prices};
+use crate::utils::{MAX_TICK, MIN_TICK, price_to_tick};
+
+/// Transactions per rebalance: withdraw, swap to the new ratio, mint
+pub const REBALANCE_TXS: u32 = 3;
+
+/// Trigger distances tried by default, as fractions of the range width
+const DEFAULT_TRIGGER_FRACTIONS: [&str; 6] = ["0", "0.1", "0.25", "0.5", "1", "2"];
+
+/// Inputs of a rebalance trigger optimization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalanceConfig {
+    /// Width of the range kept around the price, in ticks
+    pub range_width: i32,
+    /// Range bounds are aligned to the pool's tick spacing
+    pub tick_spacing: i32,
+    /// Capital deployed at the first point, in token1
+    pub initial_capital: Decimal,
+    /// Pool fee rate as a fraction (0.003 = 0.3%)
+    pub fee_rate: Decimal,
+    /// Gas cost of a single transaction, in token1, unless the market
+    /// points carry their own
+    pub gas_cost_per_tx: Decimal,
+    /// Candidate trigger distances in ticks beyond the range edge; `None` never rebalances.
+    /// Empty uses fractions of the range width from 0 (as soon as it exits) to 2 widths.
+    pub triggers: Vec<Option<u32>>,
+}
+
+/// A rebalancing rule to adopt or hand to automation
+///
+/// Once the price sits `trigger_ticks` or more beyond either edge, withdraw and
+/// re-mint a `range_width` range centered on the current tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebalancePolicy {
+    pub range_width: i32,
+    pub tick_spacing: i32,
+    /// `None` means never rebalance: the simulated gas outweighed the extra fees
+    pub trigger_ticks: Option<u32>,
+    /// `trigger_ticks` as a share of `range_width`
+    pub trigger_width_fraction: Option<Decimal>,
+}
+
+/// Simulated result of one trigger distance
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceOutcome {
+    pub trigger_ticks: Option<u32>,
+    pub rebalances: u32,
+    pub fees_earned: Decimal,
+    pub gas_spent: Decimal,
+    /// Position value plus uncollected fees at the last point
+    pub final_equity: Decimal,
+    pub net_pnl: Decimal,
+    pub time_in_range: Decimal,
+}
+
+/// Best trigger distance over a market series, and how every candidate fared
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceOptimization {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub policy: RebalancePolicy,
+    pub best: RebalanceOutcome,
+    pub candidates: Vec<RebalanceOutcome>,
+}
+
+/// Range of `width` ticks centered on `tick`, aligned to `spacing`
+pub(crate) fn centered_range(tick: i32, width: i32, spacing: i32) -> (i32, i32) {
+    let spacing = spacing.max(1);
+    let width = ((width + spacing - 1) / spacing).max(1) * spacing;
+    let lower = (tick - width / 2).div_euclid(spacing) * spacing;
+    let lower = lower.clamp(MIN_TICK, MAX_TICK - width);
+    (lower, lower + width)
+}
+
+/// Ticks the price sits beyond the range, zero when inside
+fn ticks_outside(tick: i32, lower: i32, upper: i32) -> u32 {
+    if tick < lower {
+        (lower - tick) as u32
+    } else if tick >= upper {
+        (tick - upper) as u32
+    } else {
+        0
+    }
+}
+
+/// Simulate keeping a range around the price with one trigger distance
+///
+/// Fees accrue as in `run_backtest`. When the price is out of range by at
+/// least `trigger` ticks, the position and its uncollected fees are withdrawn
+/// and, less `REBALANCE_TXS` transactions of gas, re-minted around the price.
+pub fn simulate_rebalancing(
+    config: &RebalanceConfig,
+    trigger: Option<u32>,
+    points: &[MarketPoint],
+) -> Result<RebalanceOutcome> {
+    if config.range_width <= 0 {
+        bail!("Range width must be positive");
+    }
+    if config.initial_capital <= Decimal::ZERO {
+        bail!("Initial capital must be positive");
+    }
+    let Some(first) = points.first() else {
+        bail!("Rebalance simulation requires at least one market point");
+    };
+
+    let mint = |value: Decimal, price: Decimal| {
+        let (lower, upper) =
+            centered_range(price_to_tick(price), config.range_width, config.tick_spacing);
+        let (price_lower, price_upper) = range_prices(lower, upper);
+        (lower, upper, liquidity_for_value(value, price, price_lower, price_upper))
+    };
+    let value_of = |liquidity: Decimal, lower: i32, upper: i32, price: Decimal| {
+        let (price_lower, price_upper) = range_prices(lower, upper);
+        amounts_for_liquidity(liquidity, price, price_lower, price_upper).value_in_token1(price)
+    };
+
+    let mut gas_spent = first.gas_cost(config.gas_cost_per_tx);
+    let deployed = (config.initial_capital - gas_spent).max(Decimal::ZERO);
+    let (mut lower, mut upper, mut liquidity) = mint(deployed, first.price);
+
+    let mut fees_earned = Decimal::ZERO;
+    let mut uncollected = Decimal::ZERO;
+    let mut rebalances = 0u32;
+    let mut in_range_count = 0usize;
+
+    for (i, point) in points.iter().enumerate() {
+        let tick = price_to_tick(point.price);
+        let outside = ticks_outside(tick, lower, upper);
+        if outside == 0 {
+            in_range_count += 1;
+            if i > 0 && liquidity > Decimal::ZERO {
+                let share = liquidity / (liquidity + point.active_liquidity.max(Decimal::ZERO));
+                let fees = point.volume * config.fee_rate * share;
+                fees_earned += fees;
+                uncollected += fees;
+            }
+        } else if trigger.is_some_and(|t| outside >= t) {
+            let value = value_of(liquidity, lower, upper, point.price) + uncollected;
+            let rebalance_cost =
+                point.gas_cost(config.gas_cost_per_tx) * Decimal::from(REBALANCE_TXS);
+            if value > rebalance_cost {
+                (lower, upper, liquidity) = mint(value - rebalance_cost, point.price);
+                gas_spent += rebalance_cost;
+                uncollected = Decimal::ZERO;
+                rebalances += 1;
+            }
+        }
+    }
+
+    let last = points.last().unwrap_or(first);
+    let final_equity = value_of(liquidity, lower, upper, last.price) + uncollected;
+
+    Ok(RebalanceOutcome {
+        trigger_ticks: trigger,
+        rebalances,
+        fees_earned,
+        gas_spent,
+        final_equity,
+        net_pnl: final_equity - config.initial_capital,
+        time_in_range: Decimal::from(in_range_count) / Decimal::from(points.len()),
+    })
+}
+
+/// Find the trigger distance that ends with the most equity
+///
+/// Candidates are tried tightest first with never-rebalance last; ties go to
+/// the looser trigger, which never rebalances more often.
+pub fn optimize_rebalance_trigger(
+    config: &RebalanceConfig,
+    points: &[MarketPoint],
+) -> Result<RebalanceOptimization> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        bail!("Rebalance optimization requires at least one market point");
+    };
+
+    let mut triggers = if config.triggers.is_empty() {
+        DEFAULT_TRIGGER_FRACTIONS
+            .iter()
+            .map(|f| {
+                let ticks = Decimal::from(config.range_width) * Decimal::from_str(f).unwrap();
+                ticks.round().to_u32()
+            })
+            .chain([None])
+            .collect()
+    } else {
+        config.triggers.clone()
+    };
+    triggers.sort_by_key(|t| t.unwrap_or(u32::MAX));
+    triggers.dedup();
+
+    let candidates = triggers
+        .into_iter()
+        .map(|trigger| simulate_rebalancing(config, trigger, points))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut best = &candidates[0];
+    for candidate in &candidates[1..] {
+        if candidate.final_equity >= best.final_equity {
+            best = candidate;
+        }
+    }
+
+    let policy = RebalancePolicy {
+        range_width: config.range_width,
+        tick_spacing: config.tick_spacing,
+        trigger_ticks: best.trigger_ticks,
+        trigger_width_fraction: best
+            .trigger_ticks
+            .map(|t| (Decimal::from(t) / Decimal::from(config.range_width)).round_dp(4)),
+    };
+
+    Ok(RebalanceOptimization {
+        start: first.timestamp,
+        end: last.timestamp,
+        policy,
+        best: best.clone(),
+        candidates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tick_to_price;
+    use chrono::Duration;
+
+    /// Hourly points at the given ticks, each with the same volume
+    fn create_test_points(ticks: &[i32], volume: i64) -> Vec<MarketPoint> {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        ticks
+            .iter()
+            .enumerate()
+            .map(|(i, tick)| MarketPoint {
+                timestamp: start + Duration::hours(i as i64),
+                price: tick_to_price(*tick),
+                volume: Decimal::from(volume),
+                active_liquidity: Decimal::from(1_000_000),
+                gas_cost_per_tx: None,
+            })
+            .collect()
+    }
+
+    fn create_test_config(gas_cost_per_tx: Decimal) -> RebalanceConfig {
+        RebalanceConfig {
+            range_width: 200,
+            tick_spacing: 10,
+            initial_capital: Decimal::from(10_000),
+            fee_rate: Decimal::from_str("0.003").unwrap(),
+            gas_cost_per_tx,
+            triggers: vec![],
+        }
+    }
+
+    #[test]
+    fn test_centered_range() {
+        assert_eq!(centered_range(0, 200, 10), (-100, 100));
+        assert_eq!(centered_range(1005, 200, 10), (900, 1100));
+        // Width rounds up to the spacing
+        assert_eq!(centered_range(0, 15, 10), (-10, 10));
+    }
+
+    #[test]
+    fn test_rebalancing_pays_off_when_price_trends_away() {
+        // Price leaves the range early and stays out
+        let mut ticks = vec![0, 50];
+        ticks.extend(std::iter::repeat_n(1000, 48));
+        let points = create_test_points(&ticks, 1_000_000);
+
+        let result =
+            optimize_rebalance_trigger(&create_test_config(Decimal::ONE), &points).unwrap();
+
+        assert!(result.policy.trigger_ticks.is_some());
+        assert!(result.best.rebalances >= 1);
+        let never = result.candidates.iter().find(|c| c.trigger_ticks.is_none()).unwrap();
+        assert_eq!(never.rebalances, 0);
+        assert!(result.best.final_equity > never.final_equity);
+    }
+
+    #[test]
+    fn test_never_rebalance_when_gas_outweighs_fees() {
+        // Price oscillates just outside the range edge
+        let ticks: Vec<i32> = (0..50).map(|i| if i % 2 == 0 { 0 } else { 150 }).collect();
+        let points = create_test_points(&ticks, 1_000);
+
+        let result =
+            optimize_rebalance_trigger(&create_test_config(Decimal::from(100)), &points).unwrap();
+
+        assert_eq!(result.policy.trigger_ticks, None);
+        assert_eq!(result.best.rebalances, 0);
+    }
+}
@@ -0,0 +1,271 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{GasExpense, Pool, Position, PositionPnL, PositionSnapshot, Swap};
+
+use crate::fees::{ESTIMATED_POSITION_SHARE, FeeModel, lp_fee_rate, protocol_fee_share};
+use crate::heatmap::swap_tick;
+use crate::pnl::{PnlHistory, calculate_impermanent_loss, calculate_position_pnl_at, swap_volume};
+use crate::utils::{RangeError, TickRange, price_to_tick};
+
+/// Fee a position was credited from one swap
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SwapStep {
+    pub timestamp: DateTime<Utc>,
+    pub tx_hash: String,
+    /// Execution tick, None when a leg of the swap is zero
+    pub tick: Option<i32>,
+    /// Whether `tick` was inside the position's range (fees are credited either way)
+    pub in_range: Option<bool>,
+    pub volume: Decimal,
+    /// Rate the fee model charged, before the protocol's cut
+    pub fee_rate: Decimal,
+    pub protocol_share: Decimal,
+    pub lp_fee_rate: Decimal,
+    pub position_share: Decimal,
+    pub position_fees: Decimal,
+    /// Fees earned since the position was created, up to and including this swap
+    pub cumulative_fees: Decimal,
+}
+
+/// Impermanent loss against the entry price at one snapshot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceStep {
+    pub timestamp: DateTime<Utc>,
+    pub price: Decimal,
+    pub tick: i32,
+    pub in_range: bool,
+    pub impermanent_loss: Decimal,
+}
+
+/// Gas paid by one transaction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GasStep {
+    pub timestamp: DateTime<Utc>,
+    pub tx_hash: String,
+    pub gas_cost: Decimal,
+    /// Gas paid since the position was created, up to and including this transaction
+    pub cumulative_gas: Decimal,
+}
+
+/// Every intermediate value of a position's P&L over a window, for debugging analytics
+///
+/// Built only from the recorded data handed in, so replaying the same data
+/// always gives the same trace.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayTrace {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    pub fee_model: &'static str,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Earliest recorded price up to `to`, against which IL is measured
+    pub entry_price: Option<Decimal>,
+    /// P&L as it stood at `from`, None if the position or a price didn't exist yet
+    pub start: Option<PositionPnL>,
+    pub swaps: Vec<SwapStep>,
+    pub prices: Vec<PriceStep>,
+    pub gas: Vec<GasStep>,
+    /// P&L as it stood at `to`
+    pub end: Option<PositionPnL>,
+}
+
+/// Re-run a position's P&L over [from, to] from recorded data, keeping every step
+///
+/// Swaps, snapshots and gas are ordered by time then id before use, and steps
+/// carry running totals from the position's creation so the last swap and gas
+/// steps match `end`.
+pub fn replay_position(
+    position: &Position,
+    pool: &Pool,
+    model: &dyn FeeModel,
+    history: &PnlHistory,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<ReplayTrace, RangeError> {
+    let range = TickRange::of(position)?;
+
+    let mut swaps: Vec<&Swap> = history
+        .swaps
+        .iter()
+        .filter(|s| s.timestamp >= position.created_at && s.timestamp <= to)
+        .collect();
+    swaps.sort_by_key(|s| (s.timestamp, s.id));
+    let mut snapshots: Vec<&PositionSnapshot> =
+        history.snapshots.iter().filter(|s| s.timestamp <= to).collect();
+    snapshots.sort_by_key(|s| (s.timestamp, s.id));
+    let mut gas: Vec<&GasExpense> = history.gas.iter().filter(|g| g.timestamp <= to).collect();
+    gas.sort_by_key(|g| (g.timestamp, g.id));
+
+    let mut cumulative_fees = Decimal::ZERO;
+    let mut swap_steps = Vec::new();
+    for swap in swaps {
+        let fee_rate = model.fee_rate(pool, swap);
+        let lp_fee_rate = lp_fee_rate(model, pool, swap);
+        let volume = swap_volume(swap);
+        let position_fees = volume * lp_fee_rate * ESTIMATED_POSITION_SHARE;
+        cumulative_fees += position_fees;
+        if swap.timestamp < from {
+            continue;
+        }
+        let tick = swap_tick(swap);
+        swap_steps.push(SwapStep {
+            timestamp: swap.timestamp,
+            tx_hash: swap.tx_hash.clone(),
+            tick,
+            in_range: tick.map(|t| range.contains(t)),
+            volume,
+            fee_rate,
+            protocol_share: protocol_fee_share(pool, swap),
+            lp_fee_rate,
+            position_share: ESTIMATED_POSITION_SHARE,
+            position_fees,
+            cumulative_fees,
+        });
+    }
+
+    let entry_price = snapshots.first().map(|s| s.price);
+    let mut price_steps = Vec::new();
+    if let Some(entry_price) = entry_price {
+        for snapshot in snapshots.iter().filter(|s| s.timestamp >= from) {
+            let tick = price_to_tick(snapshot.price);
+            price_steps.push(PriceStep {
+                timestamp: snapshot.timestamp,
+                price: snapshot.price,
+                tick,
+                in_range: range.contains(tick),
+                impermanent_loss: calculate_impermanent_loss(
+                    position,
+                    entry_price,
+                    snapshot.price,
+                )?,
+            });
+        }
+    }
+
+    let mut cumulative_gas = Decimal::ZERO;
+    let mut gas_steps = Vec::new();
+    for expense in gas {
+        cumulative_gas += expense.gas_cost;
+        if expense.timestamp < from {
+            continue;
+        }
+        gas_steps.push(GasStep {
+            timestamp: expense.timestamp,
+            tx_hash: expense.tx_hash.clone(),
+            gas_cost: expense.gas_cost,
+            cumulative_gas,
+        });
+    }
+
+    Ok(ReplayTrace {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        pool_id: pool.pool_id.clone(),
+        fee_model: model.name(),
+        from,
+        to,
+        tick_lower: range.lower,
+        tick_upper: range.upper,
+        entry_price,
+        start: calculate_position_pnl_at(position, pool, model, history, from)?,
+        swaps: swap_steps,
+        prices: price_steps,
+        gas: gas_steps,
+        end: calculate_position_pnl_at(position, pool, model, history, to)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::StaticFeeModel;
+    use crate::testing::{self, create_test_pool};
+    use alloy::primitives::U256;
+    use chrono::{Duration, TimeZone};
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::hours(hours)
+    }
+
+    fn create_test_position() -> Position {
+        Position { created_at: at(0), ..testing::create_test_position() }
+    }
+
+    fn create_test_swap(id: i64, hours: i64, amount0: i64, amount1: i64) -> Swap {
+        testing::create_test_swap(id, amount0, amount1, at(hours))
+    }
+
+    fn create_test_snapshot(id: i64, hours: i64, price: &str) -> PositionSnapshot {
+        PositionSnapshot {
+            id,
+            position_id: 1,
+            timestamp: at(hours),
+            fees_earned: Decimal::ZERO,
+            liquidity: U256::from(1000000u64),
+            price: price.parse().unwrap(),
+        }
+    }
+
+    fn create_test_gas(id: i64, hours: i64, cost: i64) -> GasExpense {
+        GasExpense {
+            id,
+            position_id: 1,
+            tx_hash: format!("0xgas{}", id),
+            gas_cost: Decimal::from(cost),
+            timestamp: at(hours),
+        }
+    }
+
+    #[test]
+    fn test_replay_steps_add_up_to_pipeline() {
+        let position = create_test_position();
+        let pool = create_test_pool();
+        let swaps = vec![
+            create_test_swap(3, 5, 1000, -1100),
+            create_test_swap(1, 1, -1000, 1000),
+            create_test_swap(2, 3, 2000, -2000),
+        ];
+        let snapshots = vec![create_test_snapshot(1, 0, "1.0"), create_test_snapshot(2, 4, "1.05")];
+        let gas = vec![create_test_gas(1, 0, 5), create_test_gas(2, 4, 3)];
+        let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &gas };
+
+        let trace =
+            replay_position(&position, &pool, &StaticFeeModel, &history, at(2), at(6)).unwrap();
+
+        // The swap before `from` counts towards the totals but isn't a step
+        let hashes: Vec<&str> = trace.swaps.iter().map(|s| s.tx_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["0x2", "0x3"]);
+        assert_eq!(trace.swaps[0].in_range, Some(true));
+
+        let end = trace.end.unwrap();
+        assert_eq!(trace.swaps.last().unwrap().cumulative_fees, end.fees_earned);
+        assert_eq!(trace.gas.last().unwrap().cumulative_gas, end.gas_spent);
+        assert_eq!(trace.prices.last().unwrap().impermanent_loss, end.impermanent_loss);
+        assert_eq!(trace.entry_price, Some(Decimal::ONE));
+        assert!(trace.start.unwrap().fees_earned < end.fees_earned);
+    }
+
+    #[test]
+    fn test_replay_is_deterministic() {
+        let position = create_test_position();
+        let pool = create_test_pool();
+        let mut swaps =
+            vec![create_test_swap(1, 1, -1000, 1000), create_test_swap(2, 1, 2000, -2000)];
+        let snapshots = vec![create_test_snapshot(1, 0, "1.0")];
+        let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &[] };
+        let first =
+            replay_position(&position, &pool, &StaticFeeModel, &history, at(0), at(2)).unwrap();
+
+        swaps.reverse();
+        let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &[] };
+        let second =
+            replay_position(&position, &pool, &StaticFeeModel, &history, at(0), at(2)).unwrap();
+
+        assert_eq!(first.swaps, second.swaps);
+        assert_eq!(serde_json::to_string(&first).unwrap(), serde_json::to_string(&second).unwrap());
+    }
+}
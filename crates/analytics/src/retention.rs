@@ -0,0 +1,123 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use stillwater_models::RetainedData;
+
+/// Soft-deleted positions are purged after this many days by default
+pub const DEFAULT_DELETED_POSITION_RETENTION_DAYS: i64 = 30;
+
+/// How long raw data is kept before the sync's cleanup job deletes it
+///
+/// Unset horizons keep data forever. Daily swap aggregates are always kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RetentionPolicy {
+    pub swap_days: Option<i64>,
+    pub snapshot_days: Option<i64>,
+    pub deleted_position_days: i64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            swap_days: None,
+            snapshot_days: None,
+            deleted_position_days: DEFAULT_DELETED_POSITION_RETENTION_DAYS,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Policy from `SWAP_RETENTION_DAYS`, `SNAPSHOT_RETENTION_DAYS` and
+    /// `DELETED_POSITION_RETENTION_DAYS`
+    pub fn from_env() -> Result<Self> {
+        let days = |var: &str| -> Result<Option<i64>> {
+            let Ok(value) = std::env::var(var) else {
+                return Ok(None);
+            };
+            let days: i64 = value
+                .trim()
+                .parse()
+                .with_context(|| format!("{} must be a number of days", var))?;
+            if days <= 0 {
+                bail!("{} must be positive", var);
+            }
+            Ok(Some(days))
+        };
+
+        Ok(Self {
+            swap_days: days("SWAP_RETENTION_DAYS")?,
+            snapshot_days: days("SNAPSHOT_RETENTION_DAYS")?,
+            deleted_position_days: days("DELETED_POSITION_RETENTION_DAYS")?
+                .unwrap_or(DEFAULT_DELETED_POSITION_RETENTION_DAYS),
+        })
+    }
+
+    /// Time before which `data` is deleted as of `now`, None if it's kept forever
+    pub fn cutoff(&self, data: RetainedData, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let days = match data {
+            RetainedData::Swaps => self.swap_days,
+            RetainedData::PositionSnapshots => self.snapshot_days,
+        };
+        days.map(|d| now - Duration::days(d))
+    }
+}
+
+/// A requested window reaching back past the data retention kept
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionWarning {
+    pub data: RetainedData,
+    pub requested_from: DateTime<Utc>,
+    pub retained_from: DateTime<Utc>,
+    pub message: String,
+}
+
+/// Warn when a window starting at `requested_from` needs data older than `retained_from`
+///
+/// `retained_from` is the data set's retention horizon (None while nothing
+/// has been deleted). Results over such a window only reflect the retained part.
+pub fn check_retention(
+    data: RetainedData,
+    requested_from: DateTime<Utc>,
+    retained_from: Option<DateTime<Utc>>,
+) -> Option<RetentionWarning> {
+    let retained_from = retained_from.filter(|horizon| requested_from < *horizon)?;
+    Some(RetentionWarning {
+        data,
+        requested_from,
+        retained_from,
+        message: format!(
+            "{} before {} are no longer retained; results only cover the data since",
+            data.as_str(),
+            retained_from.to_rfc3339()
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(days: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap() + Duration::days(days)
+    }
+
+    #[test]
+    fn test_policy_cutoffs() {
+        let policy = RetentionPolicy { swap_days: Some(90), ..Default::default() };
+
+        assert_eq!(policy.cutoff(RetainedData::Swaps, at(100)), Some(at(10)));
+        assert_eq!(policy.cutoff(RetainedData::PositionSnapshots, at(100)), None);
+    }
+
+    #[test]
+    fn test_window_beyond_retention_is_reported() {
+        let horizon = Some(at(10));
+
+        let warning = check_retention(RetainedData::Swaps, at(5), horizon).unwrap();
+        assert_eq!(warning.retained_from, at(10));
+        assert_eq!(warning.requested_from, at(5));
+
+        assert!(check_retention(RetainedData::Swaps, at(10), horizon).is_none());
+        assert!(check_retention(RetainedData::Swaps, at(5), None).is_none());
+    }
+}
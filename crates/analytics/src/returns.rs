@@ -0,0 +1,250 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use stillwater_models::{Position, PositionSnapshot};
+
+use crate::liquidity::{range_prices, value_per_liquidity};
+
+/// Fewest daily returns Sharpe and Sortino ratios are reported for
+pub const MIN_RETURN_DAYS: usize = 7;
+
+/// Days per year returns are annualized over (pools trade every day)
+const TRADING_DAYS: i64 = 365;
+
+/// One day's P&L on the capital held at the start of the day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DailyReturn {
+    pub date: NaiveDate,
+    /// Position value at the previous day's close (token1)
+    pub capital: Decimal,
+    /// Fees earned plus the change in value of that capital over the day
+    pub pnl: Decimal,
+}
+
+impl DailyReturn {
+    /// P&L as a fraction of capital (None without capital)
+    pub fn rate(&self) -> Option<Decimal> {
+        if self.capital <= Decimal::ZERO {
+            return None;
+        }
+        self.pnl.checked_div(self.capital)
+    }
+}
+
+/// Risk-adjusted return metrics over a series of daily returns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskAdjustedReturns {
+    /// Daily returns the metrics are computed from
+    pub days: usize,
+    pub mean_daily_return: Option<Decimal>,
+    /// Standard deviation of daily returns, annualized
+    pub volatility: Option<Decimal>,
+    /// Root mean square of daily returns below the risk-free rate, annualized
+    pub downside_deviation: Option<Decimal>,
+    /// Annualized excess return over volatility (None under `MIN_RETURN_DAYS` days or without
+    /// any variation)
+    pub sharpe: Option<Decimal>,
+    /// Annualized excess return over downside deviation (None under `MIN_RETURN_DAYS` days or
+    /// without a losing day)
+    pub sortino: Option<Decimal>,
+}
+
+/// Daily returns of a position from its snapshots (sorted by timestamp)
+///
+/// Each UTC day is closed by its last snapshot. A day's P&L is the fees
+/// earned since the previous close plus the change in value of the previous
+/// close's liquidity at the new price, so deposits and withdrawals don't count
+/// as returns. Days without snapshots are skipped.
+pub fn daily_returns(position: &Position, snapshots: &[PositionSnapshot]) -> Vec<DailyReturn> {
+    let mut closes: BTreeMap<NaiveDate, &PositionSnapshot> = BTreeMap::new();
+    for snapshot in snapshots.iter().filter(|s| s.position_id == position.id) {
+        closes.insert(snapshot.timestamp.date_naive(), snapshot);
+    }
+
+    let (price_lower, price_upper) = range_prices(position.tick_lower, position.tick_upper);
+    let closes: Vec<&PositionSnapshot> = closes.into_values().collect();
+    closes
+        .windows(2)
+        .filter_map(|pair| {
+            let (previous, close) = (pair[0], pair[1]);
+            let liquidity = Decimal::from_str(&previous.liquidity.to_string()).ok()?;
+            let capital = liquidity.checked_mul(value_per_liquidity(
+                previous.price,
+                price_lower,
+                price_upper,
+            ))?;
+            let value = liquidity.checked_mul(value_per_liquidity(
+                close.price,
+                price_lower,
+                price_upper,
+            ))?;
+            let fees = (close.fees_earned - previous.fees_earned).max(Decimal::ZERO);
+            Some(DailyReturn {
+                date: close.timestamp.date_naive(),
+                capital,
+                pnl: value - capital + fees,
+            })
+        })
+        .filter(|r| r.capital > Decimal::ZERO)
+        .collect()
+}
+
+/// Combine positions' daily returns into a portfolio's, summing capital and P&L per day
+pub fn combine_daily_returns(series: impl IntoIterator<Item = DailyReturn>) -> Vec<DailyReturn> {
+    let mut days: BTreeMap<NaiveDate, DailyReturn> = BTreeMap::new();
+    for r in series {
+        let day = days.entry(r.date).or_insert(DailyReturn {
+            date: r.date,
+            capital: Decimal::ZERO,
+            pnl: Decimal::ZERO,
+        });
+        day.capital += r.capital;
+        day.pnl += r.pnl;
+    }
+    days.into_values().collect()
+}
+
+/// Sharpe and Sortino ratios of daily returns against an annual risk-free rate
+///
+/// Both are annualized by √365. Volatility is the sample standard deviation;
+/// downside deviation counts every day, with days above the risk-free rate
+/// as zero.
+pub fn risk_adjusted_returns(
+    returns: &[DailyReturn],
+    risk_free_rate: Decimal,
+) -> RiskAdjustedReturns {
+    let rates: Vec<Decimal> = returns.iter().filter_map(DailyReturn::rate).collect();
+    let days = rates.len();
+    let mut metrics = RiskAdjustedReturns {
+        days,
+        mean_daily_return: None,
+        volatility: None,
+        downside_deviation: None,
+        sharpe: None,
+        sortino: None,
+    };
+    if days < 2 {
+        return metrics;
+    }
+
+    let n = Decimal::from(days);
+    let annualizer = Decimal::from(TRADING_DAYS).sqrt().unwrap_or(Decimal::ONE);
+    let daily_risk_free = risk_free_rate / Decimal::from(TRADING_DAYS);
+    let mean = rates.iter().sum::<Decimal>() / n;
+    let excess = mean - daily_risk_free;
+
+    let variance =
+        rates.iter().map(|r| (*r - mean) * (*r - mean)).sum::<Decimal>() / Decimal::from(days - 1);
+    let downside = rates
+        .iter()
+        .map(|r| (*r - daily_risk_free).min(Decimal::ZERO))
+        .map(|d| d * d)
+        .sum::<Decimal>()
+        / n;
+    let stdev = variance.sqrt();
+    let downside_deviation = downside.sqrt();
+
+    metrics.mean_daily_return = Some(mean);
+    metrics.volatility = stdev.map(|s| s * annualizer);
+    metrics.downside_deviation = downside_deviation.map(|d| d * annualizer);
+    if days >= MIN_RETURN_DAYS {
+        metrics.sharpe =
+            stdev.filter(|s| !s.is_zero()).and_then(|s| (excess / s).checked_mul(annualizer));
+        metrics.sortino = downside_deviation
+            .filter(|d| !d.is_zero())
+            .and_then(|d| (excess / d).checked_mul(annualizer));
+    }
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::create_test_position;
+    use alloy::primitives::U256;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    fn create_test_snapshot(
+        timestamp: DateTime<Utc>,
+        fees: i64,
+        price: Decimal,
+    ) -> PositionSnapshot {
+        PositionSnapshot {
+            id: 0,
+            position_id: 1,
+            timestamp,
+            fees_earned: Decimal::from(fees),
+            liquidity: U256::from(1_000_000u64),
+            price,
+        }
+    }
+
+    fn create_test_returns(pnls: &[i64]) -> Vec<DailyReturn> {
+        let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        pnls.iter()
+            .enumerate()
+            .map(|(i, pnl)| DailyReturn {
+                date: start + Duration::days(i as i64),
+                capital: Decimal::from(10_000),
+                pnl: Decimal::from(*pnl),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_daily_returns_use_last_snapshot_per_day() {
+        let day = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let snapshots = vec![
+            create_test_snapshot(day + Duration::hours(1), 0, Decimal::ONE),
+            create_test_snapshot(day + Duration::hours(23), 10, Decimal::ONE),
+            create_test_snapshot(day + Duration::hours(30), 25, Decimal::ONE),
+            // No snapshot on Jan 3
+            create_test_snapshot(day + Duration::hours(80), 40, Decimal::ONE),
+        ];
+
+        let returns = daily_returns(&create_test_position(), &snapshots);
+        assert_eq!(returns.len(), 2);
+        // Flat price: P&L is just the fees since the previous close
+        assert_eq!(returns[0].pnl, Decimal::from(15));
+        assert_eq!(returns[1].pnl, Decimal::from(15));
+        assert_eq!(returns[1].date, NaiveDate::from_ymd_opt(2026, 1, 4).unwrap());
+    }
+
+    #[test]
+    fn test_sharpe_and_sortino() {
+        let returns = create_test_returns(&[10, 20, -10, 30, 0, 20, -5, 15]);
+        let metrics = risk_adjusted_returns(&returns, Decimal::ZERO);
+
+        assert_eq!(metrics.days, 8);
+        let sharpe = metrics.sharpe.unwrap();
+        let sortino = metrics.sortino.unwrap();
+        assert!(sharpe > Decimal::ZERO);
+        // Only two losing days, so downside deviation is below volatility
+        assert!(sortino > sharpe);
+
+        // Too few days for ratios
+        let short = risk_adjusted_returns(&returns[..3], Decimal::ZERO);
+        assert!(short.mean_daily_return.is_some());
+        assert_eq!(short.sharpe, None);
+
+        // Never below the risk-free rate: no Sortino
+        let gains =
+            risk_adjusted_returns(&create_test_returns(&[5, 10, 5, 10, 5, 10, 5]), Decimal::ZERO);
+        assert!(gains.sharpe.is_some());
+        assert_eq!(gains.sortino, None);
+    }
+
+    #[test]
+    fn test_combine_daily_returns_weights_by_capital() {
+        let mut a = create_test_returns(&[100, 0]);
+        let b = create_test_returns(&[0, 0]);
+        a[0].capital = Decimal::from(30_000);
+
+        let combined = combine_daily_returns(a.into_iter().chain(b));
+        assert_eq!(combined.len(), 2);
+        assert_eq!(combined[0].capital, Decimal::from(40_000));
+        assert_eq!(combined[0].rate(), Some(Decimal::new(25, 4)));
+    }
+}
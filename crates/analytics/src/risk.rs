@@ -0,0 +1,263 @@
+use chrono::Duration;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use stillwater_models::Swap;
+
+use crate::health::HealthRules;
+use crate::heatmap::swap_tick;
+use crate::utils::TickRange;
+
+/// Days of swaps used to estimate a pair's volatility
+pub const VOLATILITY_LOOKBACK_DAYS: i64 = 7;
+
+/// Ranges narrower than this many daily standard deviations are degen
+pub const DEGEN_MAX_WIDTH_SIGMAS: u32 = 2;
+
+/// Ranges narrower than this many daily standard deviations are balanced
+pub const BALANCED_MAX_WIDTH_SIGMAS: u32 = 6;
+
+/// Built-in rules for degen positions: warn well before the price reaches an edge
+pub const DEGEN_HEALTH_RULES: &str = "out_of_range => critical; pnl_pct < -2% => critical; \
+     net_pnl < 0 => warning; edge_distance < 25% => warning";
+
+/// Built-in rules for conservative positions: only warn close to an edge
+pub const CONSERVATIVE_HEALTH_RULES: &str =
+    "out_of_range => critical; pnl_pct < -2% => critical; \
+     net_pnl < 0 => warning; edge_distance < 5% => warning";
+
+/// How much price movement a position's range can absorb
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskCategory {
+    /// Range spans many days of typical movement
+    Conservative,
+    Balanced,
+    /// Range is likely to be left within a day or two
+    Degen,
+}
+
+impl RiskCategory {
+    pub const ALL: [RiskCategory; 3] =
+        [RiskCategory::Conservative, RiskCategory::Balanced, RiskCategory::Degen];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskCategory::Conservative => "conservative",
+            RiskCategory::Balanced => "balanced",
+            RiskCategory::Degen => "degen",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "conservative" => Some(RiskCategory::Conservative),
+            "balanced" => Some(RiskCategory::Balanced),
+            "degen" => Some(RiskCategory::Degen),
+            _ => None,
+        }
+    }
+}
+
+/// Standard deviation of the pool's tick movement per day
+///
+/// Takes the last swap tick of each hour, scales the change between observed
+/// hours to a one-hour move and scales the hourly deviation to a day
+/// (`σ_hour × √24`). `None` with fewer than three observed hours.
+pub fn daily_tick_volatility(swaps: &[Swap]) -> Option<Decimal> {
+    let mut hourly: BTreeMap<i64, i32> = BTreeMap::new();
+    let mut ordered: Vec<&Swap> = swaps.iter().collect();
+    ordered.sort_by_key(|s| s.timestamp);
+    for swap in ordered {
+        if let Some(tick) = swap_tick(swap) {
+            hourly.insert(swap.timestamp.timestamp() / 3600, tick);
+        }
+    }
+
+    let moves: Vec<Decimal> = hourly
+        .iter()
+        .zip(hourly.iter().skip(1))
+        .filter_map(|((hour_a, tick_a), (hour_b, tick_b))| {
+            let gap = Decimal::from(hour_b - hour_a).sqrt()?;
+            Decimal::from(i64::from(*tick_b) - i64::from(*tick_a)).checked_div(gap)
+        })
+        .collect();
+    if moves.len() < 2 {
+        return None;
+    }
+
+    let count = Decimal::from(moves.len());
+    let mean = moves.iter().sum::<Decimal>() / count;
+    let variance = moves.iter().map(|m| (*m - mean) * (*m - mean)).sum::<Decimal>() / count;
+    Some(variance.sqrt()? * Decimal::from(24).sqrt()?)
+}
+
+/// Risk bucket of a range given the pair's daily tick volatility
+///
+/// A pair that hasn't moved can't push any range out, so zero volatility is conservative.
+pub fn classify_risk(range: TickRange, daily_volatility: Decimal) -> RiskCategory {
+    let Some(sigmas) = Decimal::from(range.width()).checked_div(daily_volatility) else {
+        return RiskCategory::Conservative;
+    };
+    if sigmas < Decimal::from(DEGEN_MAX_WIDTH_SIGMAS) {
+        RiskCategory::Degen
+    } else if sigmas < Decimal::from(BALANCED_MAX_WIDTH_SIGMAS) {
+        RiskCategory::Balanced
+    } else {
+        RiskCategory::Conservative
+    }
+}
+
+/// Positions in one risk bucket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskBucket {
+    pub category: RiskCategory,
+    pub positions: usize,
+}
+
+/// How a portfolio's positions spread across risk buckets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskDistribution {
+    /// Every bucket, conservative first, including empty ones
+    pub buckets: Vec<RiskBucket>,
+    /// Positions whose pair had too little swap history to classify
+    pub unclassified: usize,
+}
+
+/// Count positions per risk bucket (`None` = not classifiable)
+pub fn summarize_risk(
+    categories: impl IntoIterator<Item = Option<RiskCategory>>,
+) -> RiskDistribution {
+    let mut counts: HashMap<RiskCategory, usize> = HashMap::new();
+    let mut unclassified = 0;
+    for category in categories {
+        match category {
+            Some(category) => *counts.entry(category).or_default() += 1,
+            None => unclassified += 1,
+        }
+    }
+
+    RiskDistribution {
+        buckets: RiskCategory::ALL
+            .into_iter()
+            .map(|category| RiskBucket {
+                category,
+                positions: counts.get(&category).copied().unwrap_or(0),
+            })
+            .collect(),
+        unclassified,
+    }
+}
+
+/// Health rules per risk bucket, so tight ranges get earlier warnings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskHealthRules {
+    /// Rules for positions that couldn't be classified
+    pub default: HealthRules,
+    pub by_category: HashMap<RiskCategory, HealthRules>,
+}
+
+impl Default for RiskHealthRules {
+    /// Built-in rules: degen positions warn at 25% edge distance, balanced at 10%, conservative at 5%
+    fn default() -> Self {
+        let by_category = HashMap::from([
+            (RiskCategory::Conservative, HealthRules::parse(CONSERVATIVE_HEALTH_RULES).unwrap()),
+            (RiskCategory::Balanced, HealthRules::default()),
+            (RiskCategory::Degen, HealthRules::parse(DEGEN_HEALTH_RULES).unwrap()),
+        ]);
+        Self { default: HealthRules::default(), by_category }
+    }
+}
+
+impl RiskHealthRules {
+    /// The same rules for every bucket
+    pub fn uniform(rules: HealthRules) -> Self {
+        let by_category =
+            RiskCategory::ALL.into_iter().map(|category| (category, rules.clone())).collect();
+        Self { default: rules, by_category }
+    }
+
+    /// Use `grace` as every bucket's P&L grace period
+    pub fn with_pnl_grace(mut self, grace: Duration) -> Self {
+        self.default.pnl_grace = grace;
+        for rules in self.by_category.values_mut() {
+            rules.pnl_grace = grace;
+        }
+        self
+    }
+
+    /// Rules that apply to a position in `category`
+    pub fn for_category(&self, category: Option<RiskCategory>) -> &HealthRules {
+        category.and_then(|c| self.by_category.get(&c)).unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::HealthInputs;
+    use crate::testing;
+    use crate::utils::tick_to_price;
+    use chrono::{DateTime, Duration, Utc};
+    use stillwater_models::HealthStatus;
+
+    fn create_test_swap(hours: i64, tick: i32, from: DateTime<Utc>) -> Swap {
+        let amount1 = (tick_to_price(tick) * Decimal::from(1_000_000)).to_i64().unwrap();
+        testing::create_test_swap(hours, 1_000_000, -amount1, from + Duration::hours(hours))
+    }
+
+    #[test]
+    fn test_daily_tick_volatility() {
+        let from = Utc::now();
+        // Alternating ±100 tick hourly moves
+        let swaps: Vec<Swap> =
+            (0..25).map(|h| create_test_swap(h, if h % 2 == 0 { 0 } else { 100 }, from)).collect();
+        let volatility = daily_tick_volatility(&swaps).unwrap();
+        // σ_hour ≈ 100, so σ_day ≈ 100 × √24 ≈ 490
+        assert!(volatility > Decimal::from(470) && volatility < Decimal::from(510));
+
+        assert_eq!(daily_tick_volatility(&swaps[..2]), None);
+    }
+
+    #[test]
+    fn test_classify_risk() {
+        let volatility = Decimal::from(500);
+        let tight = TickRange::new(-400, 400).unwrap();
+        let medium = TickRange::new(-1000, 1000).unwrap();
+        let wide = TickRange::new(-5000, 5000).unwrap();
+
+        assert_eq!(classify_risk(tight, volatility), RiskCategory::Degen);
+        assert_eq!(classify_risk(medium, volatility), RiskCategory::Balanced);
+        assert_eq!(classify_risk(wide, volatility), RiskCategory::Conservative);
+        assert_eq!(classify_risk(tight, Decimal::ZERO), RiskCategory::Conservative);
+
+        let distribution =
+            summarize_risk([Some(RiskCategory::Degen), Some(RiskCategory::Degen), None]);
+        assert_eq!(distribution.buckets[2].positions, 2);
+        assert_eq!(distribution.unclassified, 1);
+    }
+
+    #[test]
+    fn test_risk_health_rules_warn_earlier_for_tight_ranges() {
+        let rules = RiskHealthRules::default();
+        // 15% of the range width from the upper edge
+        let inputs = HealthInputs {
+            range: TickRange::new(-1000, 1000).unwrap(),
+            current_tick: 700,
+            net_pnl: Decimal::ONE,
+            fees_earned: Decimal::ONE,
+            ttl_to_edge: None,
+            capital: None,
+            apr: None,
+            gas_runrate: None,
+            age: None,
+        };
+
+        let status = |category| rules.for_category(category).evaluate(&inputs).0;
+        assert_eq!(status(Some(RiskCategory::Degen)), HealthStatus::Warning);
+        assert_eq!(status(Some(RiskCategory::Balanced)), HealthStatus::Healthy);
+        assert_eq!(status(Some(RiskCategory::Conservative)), HealthStatus::Healthy);
+        assert_eq!(status(None), HealthStatus::Healthy);
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use stillwater_models::PositionSnapshot;
+
+/// Finest step between points of a dashboard series
+pub const MIN_SERIES_STEP: Duration = Duration::minutes(1);
+
+/// Value of a series at one time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SeriesPoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: Decimal,
+}
+
+/// Step between points covering `from` to `to` in at most `max_points`
+///
+/// Never finer than the requested `interval` nor `MIN_SERIES_STEP`.
+pub fn series_step(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    interval: Duration,
+    max_points: usize,
+) -> Duration {
+    let span = (to - from).num_milliseconds().max(0);
+    let points = max_points.max(1) as i64;
+    let spread = Duration::milliseconds((span + points - 1) / points);
+    interval.max(spread).max(MIN_SERIES_STEP)
+}
+
+/// Fees earned across positions every `step` from `from` to `to`
+///
+/// Snapshots carry cumulative fees, so each point adds up every position's
+/// latest snapshot at or before it; positions without one yet count as zero.
+/// Pass each position's last snapshot before `from` along with the window's
+/// so the first point starts from the fees already earned.
+pub fn cumulative_fee_series(
+    snapshots: &[PositionSnapshot],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    step: Duration,
+) -> Vec<SeriesPoint> {
+    if step <= Duration::zero() {
+        return vec![];
+    }
+    let mut ordered: Vec<&PositionSnapshot> = snapshots.iter().collect();
+    ordered.sort_by_key(|s| s.timestamp);
+
+    let mut latest: HashMap<i64, Decimal> = HashMap::new();
+    let mut total = Decimal::ZERO;
+    let mut next = 0;
+    let mut points = Vec::new();
+    let mut timestamp = from;
+    while timestamp <= to {
+        while let Some(snapshot) = ordered.get(next).filter(|s| s.timestamp <= timestamp) {
+            let previous = latest.insert(snapshot.position_id, snapshot.fees_earned);
+            total += snapshot.fees_earned - previous.unwrap_or(Decimal::ZERO);
+            next += 1;
+        }
+        points.push(SeriesPoint { timestamp, value: total });
+        timestamp += step;
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use chrono::TimeZone;
+
+    fn create_test_snapshot(
+        position_id: i64,
+        at: DateTime<Utc>,
+        fees_earned: i64,
+    ) -> PositionSnapshot {
+        PositionSnapshot {
+            id: 0,
+            position_id,
+            timestamp: at,
+            fees_earned: Decimal::from(fees_earned),
+            liquidity: U256::from(1_000_000u64),
+            price: Decimal::ONE,
+        }
+    }
+
+    #[test]
+    fn test_series_step() {
+        let from = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let to = from + Duration::days(1);
+        assert_eq!(series_step(from, to, Duration::minutes(5), 1000), Duration::minutes(5));
+        // A day in at most 24 points needs hourly steps
+        assert_eq!(series_step(from, to, Duration::minutes(5), 24), Duration::hours(1));
+        assert_eq!(series_step(from, to, Duration::seconds(1), 100_000), MIN_SERIES_STEP);
+        assert_eq!(series_step(from, to, Duration::zero(), 0), Duration::days(1));
+    }
+
+    #[test]
+    fn test_cumulative_fee_series() {
+        let from = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let snapshots = vec![
+            // Earned before the window
+            create_test_snapshot(1, from - Duration::hours(5), 100),
+            create_test_snapshot(2, from + Duration::minutes(90), 40),
+            create_test_snapshot(1, from + Duration::minutes(30), 150),
+            create_test_snapshot(1, from + Duration::hours(2), 180),
+        ];
+
+        let points =
+            cumulative_fee_series(&snapshots, from, from + Duration::hours(3), Duration::hours(1));
+        let values: Vec<Decimal> = points.iter().map(|p| p.value).collect();
+        assert_eq!(values, [100, 150, 220, 220].map(Decimal::from));
+        assert_eq!(points[3].timestamp, from + Duration::hours(3));
+
+        assert!(cumulative_fee_series(&snapshots, from, from, Duration::zero()).is_empty());
+        assert_eq!(
+            cumulative_fee_series(&[], from, from, Duration::hours(1))[0].value,
+            Decimal::ZERO
+        );
+    }
+}
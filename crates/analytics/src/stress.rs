@@ -0,0 +1,206 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
+use stillwater_models::{Pool, Position};
+
+use crate::liquidity::{amounts_for_liquidity, range_prices};
+use crate::rates::ExchangeRates;
+use crate::utils::{RangeError, TickRange, price_to_tick};
+
+/// Parse a price move in percent, e.g. `-15%`, `+10%` or `-15`, into a fraction
+///
+/// The move must leave the price positive, so it has to be above -100%.
+pub fn parse_price_move(s: &str) -> Result<Decimal, String> {
+    let trimmed = s.trim();
+    let number = trimmed.strip_suffix('%').unwrap_or(trimmed).trim();
+    let number = number.strip_prefix('+').unwrap_or(number);
+    let percent =
+        Decimal::from_str(number).map_err(|_| format!("invalid price move: {}", s.trim()))?;
+    if percent <= Decimal::from(-100) {
+        return Err("price move must be above -100%".to_string());
+    }
+    Ok(percent / Decimal::ONE_HUNDRED)
+}
+
+/// How one position fares if its pool's price moves by a given fraction
+///
+/// Prices are token1 per token0 (raw) and values raw token1; the `_quote`
+/// fields restate the values in the stress test's quote currency, at
+/// today's token1 rate (None without one).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionStress {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub price: Decimal,
+    pub stressed_price: Decimal,
+    pub in_range: bool,
+    pub in_range_after: bool,
+    /// In range now, out of range after the move: the position stops earning fees
+    pub exits_range: bool,
+    pub value: Decimal,
+    pub stressed_value: Decimal,
+    /// `stressed_value - value`
+    pub impact: Decimal,
+    pub value_quote: Option<Decimal>,
+    pub impact_quote: Option<Decimal>,
+}
+
+/// Revalue a position at its pool price moved by `price_move` (a fraction, e.g. -0.15)
+pub fn stress_position(
+    position: &Position,
+    price: Decimal,
+    price_move: Decimal,
+) -> Result<PositionStress, RangeError> {
+    let range = TickRange::of(position)?;
+    let stressed_price = price * (Decimal::ONE + price_move);
+    let liquidity = Decimal::from_str(&position.liquidity.to_string()).unwrap_or(Decimal::ZERO);
+    let (price_lower, price_upper) = range_prices(range.lower, range.upper);
+    let value_at = |price: Decimal| {
+        amounts_for_liquidity(liquidity, price, price_lower, price_upper).value_in_token1(price)
+    };
+
+    let in_range = range.contains(price_to_tick(price));
+    let in_range_after = range.contains(price_to_tick(stressed_price));
+    let value = value_at(price);
+    let stressed_value = value_at(stressed_price);
+
+    Ok(PositionStress {
+        position_id: position.id,
+        nft_id: position.nft_id.clone(),
+        pool_id: position.pool_id.clone(),
+        tick_lower: range.lower,
+        tick_upper: range.upper,
+        price,
+        stressed_price,
+        in_range,
+        in_range_after,
+        exits_range: in_range && !in_range_after,
+        value,
+        stressed_value,
+        impact: stressed_value - value,
+        value_quote: None,
+        impact_quote: None,
+    })
+}
+
+impl PositionStress {
+    /// Fill in the quote currency values from the rate of the pool's token1
+    pub fn price_in(&mut self, pool: &Pool, rates: &ExchangeRates) {
+        self.value_quote = rates.convert(&pool.token1, self.value, pool.token1_decimals);
+        self.impact_quote = rates.convert(&pool.token1, self.impact, pool.token1_decimals);
+    }
+}
+
+/// A portfolio's exposure to a price move, in one quote currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StressTotals {
+    pub currency: String,
+    pub price_move: Decimal,
+    pub positions: usize,
+    /// Positions leaving their range under the move
+    pub exiting: usize,
+    pub value: Decimal,
+    pub impact: Decimal,
+    /// `impact` as a fraction of `value` (None when nothing was priced)
+    pub impact_pct: Option<Decimal>,
+    /// Positions left out of `value` and `impact` for lack of a token1 rate
+    pub unpriced_positions: usize,
+}
+
+/// Add up stressed positions into portfolio totals
+pub fn sum_stress(
+    positions: &[PositionStress],
+    currency: &str,
+    price_move: Decimal,
+) -> StressTotals {
+    let mut totals = StressTotals {
+        currency: currency.to_lowercase(),
+        price_move,
+        positions: positions.len(),
+        exiting: positions.iter().filter(|p| p.exits_range).count(),
+        value: Decimal::ZERO,
+        impact: Decimal::ZERO,
+        impact_pct: None,
+        unpriced_positions: 0,
+    };
+    for position in positions {
+        match position.value_quote.zip(position.impact_quote) {
+            Some((value, impact)) => {
+                totals.value += value;
+                totals.impact += impact;
+            }
+            None => totals.unpriced_positions += 1,
+        }
+    }
+    totals.impact_pct = totals.impact.checked_div(totals.value);
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use chrono::Utc;
+    use stillwater_models::Address;
+
+    fn position(tick_lower: i32, tick_upper: i32) -> Position {
+        Position {
+            id: 1,
+            nft_id: "1".to_string(),
+            owner: Address::ZERO,
+            pool_id: "0xpool".to_string(),
+            tick_lower,
+            tick_upper,
+            liquidity: U256::from(1_000_000u64),
+            created_at: Utc::now(),
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_price_move() {
+        assert_eq!(parse_price_move("-15%"), Ok(Decimal::new(-15, 2)));
+        assert_eq!(parse_price_move("+10%"), Ok(Decimal::new(10, 2)));
+        assert_eq!(parse_price_move("2.5"), Ok(Decimal::new(25, 3)));
+        assert!(parse_price_move("-100%").is_err());
+        assert!(parse_price_move("down").is_err());
+    }
+
+    #[test]
+    fn test_move_past_the_edge_exits_the_range() {
+        // ±~10% around a price of 1
+        let narrow = position(-1000, 1000);
+        let stress = stress_position(&narrow, Decimal::ONE, Decimal::new(-15, 2)).unwrap();
+        assert!(stress.in_range);
+        assert!(!stress.in_range_after);
+        assert!(stress.exits_range);
+        // Falling price leaves an LP holding more of the cheaper token0: a loss in token1
+        assert!(stress.impact < Decimal::ZERO);
+
+        let wide = position(-5000, 5000);
+        let stress = stress_position(&wide, Decimal::ONE, Decimal::new(-15, 2)).unwrap();
+        assert!(stress.in_range_after);
+        assert!(!stress.exits_range);
+    }
+
+    #[test]
+    fn test_sum_stress_counts_unpriced_positions_apart() {
+        let mut priced =
+            stress_position(&position(-1000, 1000), Decimal::ONE, Decimal::new(-15, 2)).unwrap();
+        priced.value_quote = Some(Decimal::from(100));
+        priced.impact_quote = Some(Decimal::from(-10));
+        let unpriced =
+            stress_position(&position(-5000, 5000), Decimal::ONE, Decimal::new(-15, 2)).unwrap();
+
+        let totals = sum_stress(&[priced, unpriced], "USD", Decimal::new(-15, 2));
+        assert_eq!(totals.positions, 2);
+        assert_eq!(totals.exiting, 1);
+        assert_eq!(totals.unpriced_positions, 1);
+        assert_eq!(totals.impact_pct, Some(Decimal::new(-1, 1)));
+    }
+}
@@ -0,0 +1,61 @@
+//! Fixtures shared by the crate's tests
+//!
+//! Tests adjust them with struct update syntax, e.g.
+//! `Position { tick_lower, tick_upper, ..create_test_position() }`.
+
+use alloy::primitives::{I256, U256};
+use chrono::{DateTime, Utc};
+use stillwater_models::{Address, NO_HOOKS, Pool, Position, Swap};
+
+/// A hookless 0.3% pool (tick spacing 60) of two 18-decimal tokens
+pub(crate) fn create_test_pool() -> Pool {
+    Pool {
+        pool_id: "0xpool".to_string(),
+        token0: "0xtoken0".to_string(),
+        token1: "0xtoken1".to_string(),
+        token0_decimals: 18,
+        token1_decimals: 18,
+        fee_tier: 3000,
+        tick_spacing: 60,
+        hooks: NO_HOOKS.to_string(),
+        protocol_fee: 0,
+        created_at: None,
+        created_at_block: None,
+        fee_override: None,
+    }
+}
+
+/// An open position in the test pool with 1,000,000 liquidity across ticks -1000..1000
+pub(crate) fn create_test_position() -> Position {
+    Position {
+        id: 1,
+        nft_id: "1".to_string(),
+        owner: Address::ZERO,
+        pool_id: "0xpool".to_string(),
+        tick_lower: -1000,
+        tick_upper: 1000,
+        liquidity: U256::from(1_000_000u64),
+        created_at: Utc::now(),
+        manual: false,
+        closed_at: None,
+        archived_at: None,
+    }
+}
+
+/// A swap in the test pool moving `amount0` and `amount1` (the pool's side) at `timestamp`
+pub(crate) fn create_test_swap(
+    id: i64,
+    amount0: i64,
+    amount1: i64,
+    timestamp: DateTime<Utc>,
+) -> Swap {
+    Swap {
+        id,
+        tx_hash: format!("0x{}", id),
+        pool_id: "0xpool".to_string(),
+        amount0: I256::try_from(amount0).unwrap(),
+        amount1: I256::try_from(amount1).unwrap(),
+        fee: None,
+        timestamp,
+    }
+}
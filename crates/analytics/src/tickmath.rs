@@ -0,0 +1,192 @@
+use alloy::primitives::{U256, U512};
+use rust_decimal::Decimal;
+
+use crate::utils::{MAX_TICK, MIN_TICK, RangeError};
+
+/// Sqrt price (Q64.96) at `MIN_TICK`
+pub const MIN_SQRT_RATIO: U256 = U256::from_limbs([4295128739, 0, 0, 0]);
+/// Sqrt price (Q64.96) at `MAX_TICK`
+pub const MAX_SQRT_RATIO: U256 =
+    U256::from_limbs([0x5d951d5263988d26, 0xefd1fc6a50648849, 0xfffd8963, 0]);
+
+/// 2^128 / sqrt(1.0001)^(2^i) in Q128.128, for each bit `i` of an absolute tick
+const TICK_BIT_RATIOS: [u128; 20] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+];
+
+/// Most digits a Decimal can carry after the point
+const MAX_DECIMAL_SCALE: u32 = 28;
+
+/// Sqrt price (Q64.96) at a tick, bit for bit as Uniswap's `TickMath.getSqrtRatioAtTick`
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256, RangeError> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(RangeError::OutOfBounds { tick });
+    }
+    let abs_tick = tick.unsigned_abs();
+
+    let mut ratio =
+        if abs_tick & 1 != 0 { U256::from(TICK_BIT_RATIOS[0]) } else { U256::from(1) << 128 };
+    for (bit, bit_ratio) in TICK_BIT_RATIOS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * U256::from(*bit_ratio)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Q128.128 to Q64.96, rounding up so the price never undershoots the tick
+    let round_up = !(ratio & U256::from(u32::MAX)).is_zero();
+    Ok((ratio >> 32) + U256::from(round_up as u8))
+}
+
+/// Greatest tick whose sqrt price is at most `sqrt_ratio`, like `TickMath.getTickAtSqrtRatio`
+///
+/// Sqrt prices outside [MIN_SQRT_RATIO, MAX_SQRT_RATIO] clamp to the tick bounds
+/// rather than failing.
+pub fn get_tick_at_sqrt_ratio(sqrt_ratio: U256) -> i32 {
+    if sqrt_ratio <= MIN_SQRT_RATIO {
+        return MIN_TICK;
+    }
+    if sqrt_ratio >= MAX_SQRT_RATIO {
+        return MAX_TICK;
+    }
+
+    // Binary search over the exact ratios: ratio(low) <= sqrt_ratio < ratio(high)
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if exact_sqrt_ratio(mid) <= sqrt_ratio {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Price (token1 per token0, raw units) of a Q64.96 sqrt price
+///
+/// Exact to the 28 decimal places a Decimal holds; prices beyond Decimal's
+/// range saturate at `Decimal::MAX`, and those below its smallest step at that
+/// step, so a price is never zero.
+pub fn sqrt_ratio_to_price(sqrt_ratio: U256) -> Decimal {
+    let sqrt_ratio = U512::from(sqrt_ratio);
+    q_to_decimal(sqrt_ratio * sqrt_ratio, 192)
+}
+
+/// Square root of the price, as a Decimal, of a Q64.96 sqrt price
+pub fn sqrt_ratio_to_sqrt_price(sqrt_ratio: U256) -> Decimal {
+    q_to_decimal(U512::from(sqrt_ratio), 96)
+}
+
+/// Q64.96 sqrt price of a Decimal price, rounded down (None unless positive)
+pub fn price_to_sqrt_ratio(price: Decimal) -> Option<U256> {
+    if price <= Decimal::ZERO {
+        return None;
+    }
+    let mantissa = U512::from(price.mantissa().unsigned_abs());
+    let scaled: U512 = (mantissa << 192) / U512::from(10).pow(U512::from(price.scale()));
+    Some(scaled.root(2).to::<U256>())
+}
+
+/// Square root of the price at a tick, for walking liquidity between ticks
+pub fn sqrt_price_at_tick(tick: i32) -> Decimal {
+    sqrt_ratio_to_sqrt_price(exact_sqrt_ratio(tick))
+}
+
+/// Sqrt price at a tick clamped into bounds
+pub(crate) fn exact_sqrt_ratio(tick: i32) -> U256 {
+    get_sqrt_ratio_at_tick(tick.clamp(MIN_TICK, MAX_TICK)).expect("clamped tick is in bounds")
+}
+
+/// `value / 2^shift` as a Decimal, rounded half up at the finest scale that fits
+///
+/// Saturates at `Decimal::MAX`, and at the smallest positive Decimal for a
+/// positive value too small to show.
+fn q_to_decimal(value: U512, shift: usize) -> Decimal {
+    if value.is_zero() {
+        return Decimal::ZERO;
+    }
+    let half = U512::from(1) << (shift - 1);
+    let mantissa_limit = U512::from(1) << 96;
+    for scale in (0..=MAX_DECIMAL_SCALE).rev() {
+        let mantissa = (value * U512::from(10).pow(U512::from(scale)) + half) >> shift;
+        if mantissa < mantissa_limit {
+            let mantissa = mantissa.to::<i128>().max(1);
+            return Decimal::from_i128_with_scale(mantissa, scale).normalize();
+        }
+    }
+    Decimal::MAX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sqrt_ratio_matches_tick_math() {
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK).unwrap(), MAX_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(0).unwrap(), U256::from(1) << 96);
+        // Values from the Uniswap TickMath tests
+        assert_eq!(
+            get_sqrt_ratio_at_tick(1).unwrap(),
+            U256::from_str("79232123823359799118286999568").unwrap()
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(-1).unwrap(),
+            U256::from_str("79224201403219477170569942574").unwrap()
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(MAX_TICK + 1),
+            Err(RangeError::OutOfBounds { tick: MAX_TICK + 1 })
+        );
+    }
+
+    #[test]
+    fn test_tick_at_sqrt_ratio_round_trips() {
+        for tick in [MIN_TICK, -200_000, -60, -1, 0, 1, 60, 200_000, MAX_TICK] {
+            let sqrt_ratio = get_sqrt_ratio_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_ratio(sqrt_ratio), tick);
+            if tick > MIN_TICK {
+                assert_eq!(get_tick_at_sqrt_ratio(sqrt_ratio - U256::from(1)), tick - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_conversions() {
+        assert_eq!(sqrt_ratio_to_price(U256::from(1) << 96), Decimal::ONE);
+        assert_eq!(sqrt_ratio_to_price(U256::from(2) << 96), Decimal::from(4));
+        assert_eq!(
+            sqrt_ratio_to_sqrt_price(U256::from(3) << 95),
+            Decimal::from_str("1.5").unwrap()
+        );
+        assert_eq!(sqrt_ratio_to_price(MAX_SQRT_RATIO), Decimal::MAX);
+        assert_eq!(sqrt_ratio_to_price(MIN_SQRT_RATIO), Decimal::new(1, 28));
+
+        assert_eq!(price_to_sqrt_ratio(Decimal::from(4)), Some(U256::from(2) << 96));
+        assert_eq!(price_to_sqrt_ratio(Decimal::ZERO), None);
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use stillwater_models::Swap;
+
+use crate::heatmap::swap_tick;
+
+/// Format version written as the first byte of every encoded series
+pub const TICK_SERIES_VERSION: u8 = 1;
+
+/// A pool's execution tick at a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TickPoint {
+    pub timestamp: DateTime<Utc>,
+    pub tick: i32,
+}
+
+/// Why an encoded tick series couldn't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickSeriesError {
+    UnsupportedVersion(u8),
+    /// The data ended before the number of points in its header
+    Truncated,
+    /// A value doesn't fit its type, so the data is corrupt
+    Overflow,
+}
+
+impl fmt::Display for TickSeriesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TickSeriesError::UnsupportedVersion(v) => {
+                write!(f, "unsupported tick series version {}", v)
+            }
+            TickSeriesError::Truncated => write!(f, "tick series data is truncated"),
+            TickSeriesError::Overflow => write!(f, "tick series value out of range"),
+        }
+    }
+}
+
+impl std::error::Error for TickSeriesError {}
+
+/// Execution ticks of swaps, oldest first; swaps without a price are skipped
+pub fn tick_points_from_swaps(swaps: &[Swap]) -> Vec<TickPoint> {
+    let mut points: Vec<TickPoint> = swaps
+        .iter()
+        .filter_map(|s| swap_tick(s).map(|tick| TickPoint { timestamp: s.timestamp, tick }))
+        .collect();
+    points.sort_by_key(|p| p.timestamp);
+    points
+}
+
+/// Compress a tick series, sorted oldest first, at one-second resolution
+///
+/// Layout: version byte, point count, then per point the delta of its
+/// timestamp's delta (Gorilla-style, so regular spacing costs one byte) and
+/// its tick's delta from the previous point, all as zigzag varints. The
+/// first point's timestamp and tick are stored as deltas from zero.
+pub fn encode_tick_series(points: &[TickPoint]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + points.len() * 3);
+    out.push(TICK_SERIES_VERSION);
+    write_varint(&mut out, points.len() as u64);
+
+    let (mut prev_ts, mut prev_delta, mut prev_tick) = (0i64, 0i64, 0i64);
+    for (i, point) in points.iter().enumerate() {
+        let ts = point.timestamp.timestamp();
+        let delta = if i == 0 { ts } else { ts - prev_ts };
+        write_varint(&mut out, zigzag(delta - prev_delta));
+        write_varint(&mut out, zigzag(i64::from(point.tick) - prev_tick));
+        prev_ts = ts;
+        prev_delta = if i == 0 { 0 } else { delta };
+        prev_tick = i64::from(point.tick);
+    }
+    out
+}
+
+/// Iterate over an encoded tick series, checking its header first
+pub fn decode_tick_series(data: &[u8]) -> Result<TickSeriesDecoder<'_>, TickSeriesError> {
+    let (&version, rest) = data.split_first().ok_or(TickSeriesError::Truncated)?;
+    if version != TICK_SERIES_VERSION {
+        return Err(TickSeriesError::UnsupportedVersion(version));
+    }
+    let mut decoder = TickSeriesDecoder {
+        data: rest,
+        remaining: 0,
+        index: 0,
+        prev_ts: 0,
+        prev_delta: 0,
+        prev_tick: 0,
+    };
+    decoder.remaining = decoder.read_varint()?;
+    Ok(decoder)
+}
+
+/// Streaming decoder of `encode_tick_series` output, yielding points oldest first
+///
+/// Stops after the first error, so a corrupt chunk yields one `Err` at most.
+pub struct TickSeriesDecoder<'a> {
+    data: &'a [u8],
+    remaining: u64,
+    index: u64,
+    prev_ts: i64,
+    prev_delta: i64,
+    prev_tick: i64,
+}
+
+impl TickSeriesDecoder<'_> {
+    /// Points not yet decoded, per the header
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    fn read_varint(&mut self) -> Result<u64, TickSeriesError> {
+        let mut value = 0u64;
+        for (i, &byte) in self.data.iter().enumerate() {
+            let shift = 7 * i as u32;
+            if shift >= 64 {
+                return Err(TickSeriesError::Overflow);
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                self.data = &self.data[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(TickSeriesError::Truncated)
+    }
+
+    fn next_point(&mut self) -> Result<TickPoint, TickSeriesError> {
+        let delta_of_delta = unzigzag(self.read_varint()?);
+        let tick_delta = unzigzag(self.read_varint()?);
+
+        let delta = self.prev_delta.checked_add(delta_of_delta).ok_or(TickSeriesError::Overflow)?;
+        let ts = self.prev_ts.checked_add(delta).ok_or(TickSeriesError::Overflow)?;
+        let tick = self.prev_tick.checked_add(tick_delta).ok_or(TickSeriesError::Overflow)?;
+        self.prev_delta = if self.index == 0 { 0 } else { delta };
+        self.prev_ts = ts;
+        self.prev_tick = tick;
+        self.index += 1;
+
+        Ok(TickPoint {
+            timestamp: DateTime::from_timestamp(ts, 0).ok_or(TickSeriesError::Overflow)?,
+            tick: i32::try_from(tick).map_err(|_| TickSeriesError::Overflow)?,
+        })
+    }
+}
+
+impl Iterator for TickSeriesDecoder<'_> {
+    type Item = Result<TickPoint, TickSeriesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let point = self.next_point();
+        if point.is_err() {
+            self.remaining = 0;
+        }
+        Some(point)
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(ts: i64, tick: i32) -> TickPoint {
+        TickPoint { timestamp: DateTime::from_timestamp(ts, 0).unwrap(), tick }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let points = vec![
+            point(1_700_000_000, -201_000),
+            point(1_700_000_012, -200_990),
+            point(1_700_000_024, -201_005),
+            point(1_700_000_024, 887_272),
+            point(1_700_090_000, -887_272),
+        ];
+        let encoded = encode_tick_series(&points);
+        let decoded: Vec<TickPoint> =
+            decode_tick_series(&encoded).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(decoded, points);
+
+        let empty = encode_tick_series(&[]);
+        assert_eq!(decode_tick_series(&empty).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_regular_series_compresses_to_a_few_bytes_per_point() {
+        // One swap a block, ticks drifting by a few ticks each
+        let points: Vec<TickPoint> =
+            (0..10_000).map(|i| point(1_700_000_000 + i * 12, -200_000 + (i % 7) as i32)).collect();
+        let encoded = encode_tick_series(&points);
+        // 16 bytes per point as raw (i64 timestamp + i32 tick, padded)
+        assert!(encoded.len() < points.len() * 3);
+    }
+
+    #[test]
+    fn test_corrupt_data_is_reported_once() {
+        let encoded = encode_tick_series(&[point(1_700_000_000, 10), point(1_700_000_012, 20)]);
+
+        let mut truncated = decode_tick_series(&encoded[..encoded.len() - 1]).unwrap();
+        assert!(truncated.next().unwrap().is_ok());
+        assert_eq!(truncated.next(), Some(Err(TickSeriesError::Truncated)));
+        assert_eq!(truncated.next(), None);
+
+        let mut wrong_version = encoded.clone();
+        wrong_version[0] = 9;
+        assert_eq!(
+            decode_tick_series(&wrong_version).err(),
+            Some(TickSeriesError::UnsupportedVersion(9))
+        );
+    }
+}
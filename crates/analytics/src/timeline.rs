@@ -0,0 +1,275 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use stillwater_models::{
+    Address, LiquidityChange, Position, PositionLiquidityEvent, PositionSnapshot, PositionTransfer,
+};
+
+use crate::returns::daily_returns;
+use crate::timerange::TimeRange;
+
+/// Daily return, as a fraction of capital, that counts as a big P&L swing by default
+pub const DEFAULT_PNL_SWING: Decimal = Decimal::from_parts(10, 0, 0, false, 2);
+
+/// What happened to a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    /// The position's first liquidity
+    Mint,
+    Increase,
+    Decrease,
+    TransferIn,
+    TransferOut,
+    /// A day whose P&L moved by at least the swing threshold
+    PnlSwing,
+}
+
+/// One entry of an owner's activity feed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: TimelineEventKind,
+    pub position_id: Option<i64>,
+    /// None for transfers of positions the owner no longer holds
+    pub pool_id: Option<String>,
+    /// Signed liquidity change of mints, increases and decreases
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub liquidity_delta: Option<String>,
+    /// The other side of a transfer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    /// The day's P&L (token1) and its share of the capital held, for swings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pnl: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pnl_rate: Option<Decimal>,
+}
+
+impl TimelineEvent {
+    fn new(timestamp: DateTime<Utc>, kind: TimelineEventKind, position_id: Option<i64>) -> Self {
+        Self {
+            timestamp,
+            kind,
+            position_id,
+            pool_id: None,
+            liquidity_delta: None,
+            counterparty: None,
+            tx_hash: None,
+            pnl: None,
+            pnl_rate: None,
+        }
+    }
+}
+
+/// Merge owners' liquidity events, transfers and big P&L days into one feed, newest first
+///
+/// `positions` are the owners' current positions; their snapshots give the
+/// daily returns, and days in `range` whose return reaches `swing` either
+/// way (see `daily_returns`) become `pnl_swing` entries dated at the start of
+/// the day. Transfers are `transfer_in` or `transfer_out` from the owners'
+/// side; those between two of `owners` are left out.
+pub fn build_timeline(
+    owners: &[Address],
+    liquidity_events: &[PositionLiquidityEvent],
+    transfers: &[PositionTransfer],
+    positions: &[Position],
+    snapshots: &[PositionSnapshot],
+    swing: Decimal,
+    range: TimeRange,
+) -> Vec<TimelineEvent> {
+    let pools: HashMap<i64, &str> = positions.iter().map(|p| (p.id, p.pool_id.as_str())).collect();
+    let mut timeline = Vec::new();
+
+    for recorded in liquidity_events {
+        let event = &recorded.event;
+        let kind = match event.change() {
+            LiquidityChange::Add if recorded.first_for_position => TimelineEventKind::Mint,
+            LiquidityChange::Add => TimelineEventKind::Increase,
+            LiquidityChange::Remove => TimelineEventKind::Decrease,
+        };
+        let mut entry = TimelineEvent::new(event.timestamp, kind, recorded.position_id);
+        entry.pool_id = Some(event.pool_id.clone());
+        entry.liquidity_delta = Some(event.liquidity_delta.to_string());
+        timeline.push(entry);
+    }
+
+    for transfer in transfers {
+        if owners.contains(&transfer.from_owner) && owners.contains(&transfer.to_owner) {
+            continue;
+        }
+        let (kind, counterparty) = if owners.contains(&transfer.to_owner) {
+            (TimelineEventKind::TransferIn, transfer.from_owner)
+        } else {
+            (TimelineEventKind::TransferOut, transfer.to_owner)
+        };
+        let mut entry =
+            TimelineEvent::new(transfer.effective_from, kind, Some(transfer.position_id));
+        entry.pool_id = pools.get(&transfer.position_id).map(|p| p.to_string());
+        entry.counterparty = Some(counterparty);
+        entry.tx_hash = transfer.tx_hash.clone();
+        timeline.push(entry);
+    }
+
+    for position in positions {
+        for day in daily_returns(position, snapshots) {
+            let timestamp = day.date.and_time(NaiveTime::MIN).and_utc();
+            let Some(rate) = day.rate() else { continue };
+            if rate.abs() < swing || timestamp < range.from || timestamp > range.to {
+                continue;
+            }
+            let mut entry =
+                TimelineEvent::new(timestamp, TimelineEventKind::PnlSwing, Some(position.id));
+            entry.pool_id = Some(position.pool_id.clone());
+            entry.pnl = Some(day.pnl);
+            entry.pnl_rate = Some(rate);
+            timeline.push(entry);
+        }
+    }
+
+    timeline.sort_by_key(|e| Reverse(e.timestamp));
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{I256, U256};
+    use chrono::TimeZone;
+    use stillwater_models::LiquidityEvent;
+
+    const OWNER: Address = Address::repeat_byte(0x1);
+    const BUYER: Address = Address::repeat_byte(0x2);
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap()
+    }
+
+    fn days(from: u32, to: u32) -> TimeRange {
+        TimeRange { from: at(from), to: at(to) }
+    }
+
+    fn liquidity_event(day: u32, delta: i64, first: bool) -> PositionLiquidityEvent {
+        PositionLiquidityEvent {
+            event: LiquidityEvent {
+                event_id: format!("e{}", day),
+                owner: OWNER,
+                pool_id: "0xpool".to_string(),
+                tick_lower: -600,
+                tick_upper: 600,
+                liquidity_delta: I256::try_from(delta).unwrap(),
+                timestamp: at(day),
+            },
+            position_id: Some(1),
+            first_for_position: first,
+        }
+    }
+
+    fn position() -> Position {
+        Position {
+            id: 1,
+            nft_id: "1".to_string(),
+            owner: OWNER,
+            pool_id: "0xpool".to_string(),
+            tick_lower: -20_000,
+            tick_upper: 20_000,
+            liquidity: U256::from(1_000_000u64),
+            created_at: at(1),
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        }
+    }
+
+    fn snapshot(day: u32, price: Decimal) -> PositionSnapshot {
+        PositionSnapshot {
+            id: day as i64,
+            position_id: 1,
+            timestamp: at(day),
+            fees_earned: Decimal::ZERO,
+            liquidity: U256::from(1_000_000u64),
+            price,
+        }
+    }
+
+    #[test]
+    fn test_liquidity_and_transfer_events_are_merged_newest_first() {
+        let events = vec![liquidity_event(1, 1_000, true), liquidity_event(5, -400, false)];
+        let transfers = vec![PositionTransfer {
+            transfer_id: "t1".to_string(),
+            position_id: 1,
+            from_owner: OWNER,
+            to_owner: BUYER,
+            tx_hash: Some("0xtx".to_string()),
+            effective_from: at(3),
+        }];
+
+        let timeline =
+            build_timeline(&[OWNER], &events, &transfers, &[], &[], DEFAULT_PNL_SWING, days(1, 9));
+        let kinds: Vec<TimelineEventKind> = timeline.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TimelineEventKind::Decrease,
+                TimelineEventKind::TransferOut,
+                TimelineEventKind::Mint
+            ]
+        );
+        assert_eq!(timeline[0].liquidity_delta.as_deref(), Some("-400"));
+        assert_eq!(timeline[1].counterparty, Some(BUYER));
+        // The position isn't the owner's anymore, so its pool isn't known
+        assert_eq!(timeline[1].pool_id, None);
+
+        // Moving a position between an account's own wallets isn't a transfer
+        let timeline = build_timeline(
+            &[OWNER, BUYER],
+            &events,
+            &transfers,
+            &[],
+            &[],
+            DEFAULT_PNL_SWING,
+            days(1, 9),
+        );
+        assert_eq!(timeline.len(), 2);
+    }
+
+    #[test]
+    fn test_only_big_days_in_the_window_are_swings() {
+        let snapshots = vec![
+            snapshot(1, Decimal::ONE),
+            snapshot(2, Decimal::new(1001, 3)),
+            snapshot(3, Decimal::new(15, 1)),
+            snapshot(4, Decimal::new(1501, 3)),
+        ];
+        let positions = vec![position()];
+
+        let timeline = build_timeline(
+            &[OWNER],
+            &[],
+            &[],
+            &positions,
+            &snapshots,
+            DEFAULT_PNL_SWING,
+            days(1, 9),
+        );
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].kind, TimelineEventKind::PnlSwing);
+        assert_eq!(timeline[0].timestamp.date_naive(), at(3).date_naive());
+        assert!(timeline[0].pnl.unwrap() > Decimal::ZERO);
+
+        let outside = build_timeline(
+            &[OWNER],
+            &[],
+            &[],
+            &positions,
+            &snapshots,
+            DEFAULT_PNL_SWING,
+            days(4, 9),
+        );
+        assert!(outside.is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use std::fmt;
+
+/// Why a time, duration or time range parameter was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeRangeError {
+    /// Not a relative duration, unix timestamp, date or RFC3339 time
+    InvalidTime { value: String },
+    /// Not a positive count followed by `s`, `m`, `h`, `d` or `w`
+    InvalidDuration { value: String },
+    /// `from` is after `to`
+    Inverted,
+    /// The range spans more than the caller allows
+    TooLong { max: Duration },
+}
+
+impl fmt::Display for TimeRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeRangeError::InvalidTime { value } => write!(
+                f,
+                "invalid time '{}': expected a relative time like 7d, unix seconds, \
+                 YYYY-MM-DD or RFC3339",
+                value
+            ),
+            TimeRangeError::InvalidDuration { value } => {
+                write!(f, "invalid duration '{}': expected e.g. 30m, 24h, 7d or 2w", value)
+            }
+            TimeRangeError::Inverted => write!(f, "from must be before to"),
+            TimeRangeError::TooLong { max } => {
+                write!(f, "time range is longer than the maximum of {}", format_duration(*max))
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimeRangeError {}
+
+/// Parse a length of time like `90s`, `15m`, `24h`, `7d` or `2w`
+pub fn parse_duration(value: &str) -> Result<Duration, TimeRangeError> {
+    let invalid = || TimeRangeError::InvalidDuration { value: value.to_string() };
+    let value = value.trim();
+    // Split before the last character, which may be multibyte in bad input
+    let (unit_start, _) = value.char_indices().last().ok_or_else(invalid)?;
+    let (count, unit) = value.split_at(unit_start);
+    let count = count.parse::<i64>().ok().filter(|c| *c > 0).ok_or_else(invalid)?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(count),
+        "m" => Duration::try_minutes(count),
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        "w" => Duration::try_weeks(count),
+        _ => None,
+    };
+    duration.ok_or_else(invalid)
+}
+
+/// Write a duration the way `parse_duration` reads it, in the largest whole unit up to days
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    let units = [("d", 86_400), ("h", 3600), ("m", 60)];
+    match units.iter().find(|(_, size)| seconds != 0 && seconds % size == 0) {
+        Some((unit, size)) => format!("{}{}", seconds / size, unit),
+        None => format!("{}s", seconds),
+    }
+}
+
+/// Parse a point in time relative to `now`
+///
+/// Accepts `now`, a duration meaning that long ago (`7d`), unix seconds, a
+/// `YYYY-MM-DD` date (midnight UTC) or an RFC3339 timestamp.
+pub fn parse_time(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, TimeRangeError> {
+    let trimmed = value.trim();
+    if trimmed.eq_ignore_ascii_case("now") {
+        return Ok(now);
+    }
+    if let Ok(ago) = parse_duration(trimmed) {
+        return now.checked_sub_signed(ago).ok_or_else(|| invalid_time(value));
+    }
+    if !trimmed.is_empty() && trimmed.bytes().all(|b| b.is_ascii_digit()) {
+        let seconds = trimmed.parse::<i64>().map_err(|_| invalid_time(value))?;
+        return DateTime::from_timestamp(seconds, 0).ok_or_else(|| invalid_time(value));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    DateTime::parse_from_rfc3339(trimmed)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| invalid_time(value))
+}
+
+fn invalid_time(value: &str) -> TimeRangeError {
+    TimeRangeError::InvalidTime { value: value.to_string() }
+}
+
+/// Defaults and bounds an endpoint or command applies to `from`/`to`
+#[derive(Debug, Clone, Copy)]
+pub struct TimeRangeLimits {
+    /// How far back `from` is when only `to` (or neither) is given
+    pub default_span: Duration,
+    /// Longest range accepted
+    pub max_span: Duration,
+}
+
+impl TimeRangeLimits {
+    pub fn days(default_days: i64, max_days: i64) -> Self {
+        Self { default_span: Duration::days(default_days), max_span: Duration::days(max_days) }
+    }
+}
+
+/// A validated time window, `from` at or before `to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TimeRange {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl TimeRange {
+    /// Parse optional `from`/`to` parameters (see `parse_time`)
+    ///
+    /// `to` defaults to `now` and `from` to `limits.default_span` before `to`.
+    pub fn parse(
+        from: Option<&str>,
+        to: Option<&str>,
+        limits: TimeRangeLimits,
+        now: DateTime<Utc>,
+    ) -> Result<Self, TimeRangeError> {
+        let to = match to {
+            Some(to) => parse_time(to, now)?,
+            None => now,
+        };
+        let from = match from {
+            Some(from) => parse_time(from, now)?,
+            None => to - limits.default_span,
+        };
+        Self::new(from, to, limits.max_span)
+    }
+
+    /// Check a window against a maximum span
+    pub fn new(
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        max_span: Duration,
+    ) -> Result<Self, TimeRangeError> {
+        if from > to {
+            return Err(TimeRangeError::Inverted);
+        }
+        if to - from > max_span {
+            return Err(TimeRangeError::TooLong { max: max_span });
+        }
+        Ok(Self { from, to })
+    }
+
+    /// The window of `span` ending at `now`
+    pub fn last(span: Duration, now: DateTime<Utc>) -> Self {
+        Self { from: now - span, to: now }
+    }
+
+    pub fn span(&self) -> Duration {
+        self.to - self.from
+    }
+
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.from <= time && time <= self.to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::minutes(15)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::days(7)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::weeks(2)));
+
+        for invalid in ["", "d", "0d", "-3d", "7", "7y", "1.5h"] {
+            assert!(parse_duration(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_multibyte_units() {
+        for invalid in ["7€", "€", "3日", "1ｄ", "5d€"] {
+            assert!(parse_duration(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_format_duration_round_trips() {
+        for text in ["90s", "15m", "36h", "3d", "14d"] {
+            assert_eq!(format_duration(parse_duration(text).unwrap()), text);
+        }
+        assert_eq!(format_duration(Duration::weeks(2)), "14d");
+    }
+
+    #[test]
+    fn test_parse_time_forms() {
+        let now = now();
+        assert_eq!(parse_time("now", now), Ok(now));
+        assert_eq!(parse_time("7d", now), Ok(now - Duration::days(7)));
+        assert_eq!(
+            parse_time("1718409600", now),
+            Ok(Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_time("2024-06-01", now),
+            Ok(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_time("2024-06-01T10:00:00+02:00", now),
+            Ok(Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap())
+        );
+        assert!(matches!(parse_time("yesterday", now), Err(TimeRangeError::InvalidTime { .. })));
+    }
+
+    #[test]
+    fn test_range_defaults() {
+        let now = now();
+        let limits = TimeRangeLimits::days(7, 30);
+
+        let range = TimeRange::parse(None, None, limits, now).unwrap();
+        assert_eq!(range, TimeRange::last(Duration::days(7), now));
+
+        // from defaults relative to an explicit to
+        let range = TimeRange::parse(None, Some("2024-06-01"), limits, now).unwrap();
+        assert_eq!(range.to, Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+        assert_eq!(range.span(), Duration::days(7));
+    }
+
+    #[test]
+    fn test_range_validation() {
+        let now = now();
+        let limits = TimeRangeLimits::days(7, 30);
+
+        assert_eq!(
+            TimeRange::parse(Some("1d"), Some("2d"), limits, now),
+            Err(TimeRangeError::Inverted)
+        );
+        assert_eq!(
+            TimeRange::parse(Some("31d"), None, limits, now),
+            Err(TimeRangeError::TooLong { max: Duration::days(30) })
+        );
+        let range = TimeRange::parse(Some("30d"), Some("now"), limits, now).unwrap();
+        assert!(range.contains(now - Duration::days(30)));
+        assert!(!range.contains(now + Duration::seconds(1)));
+    }
+}
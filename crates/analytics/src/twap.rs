@@ -0,0 +1,164 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use stillwater_models::Swap;
+
+use crate::heatmap::swap_tick;
+use crate::utils::tick_to_price;
+
+/// TWAP window used for current prices unless `TWAP_WINDOW_MINUTES` is set
+pub const DEFAULT_TWAP_WINDOW_MINUTES: i64 = 30;
+
+/// Window the current price is averaged over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TwapConfig {
+    pub window_minutes: i64,
+}
+
+impl Default for TwapConfig {
+    fn default() -> Self {
+        Self { window_minutes: DEFAULT_TWAP_WINDOW_MINUTES }
+    }
+}
+
+impl TwapConfig {
+    /// Config from `TWAP_WINDOW_MINUTES`
+    pub fn from_env() -> Result<Self> {
+        let Ok(minutes) = std::env::var("TWAP_WINDOW_MINUTES") else {
+            return Ok(Self::default());
+        };
+        let window_minutes: i64 =
+            minutes.trim().parse().context("TWAP_WINDOW_MINUTES must be a number of minutes")?;
+        if window_minutes <= 0 {
+            bail!("TWAP_WINDOW_MINUTES must be positive");
+        }
+        Ok(Self { window_minutes })
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::minutes(self.window_minutes)
+    }
+}
+
+/// Time-weighted average price of a pool over a window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Twap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Time-weighted mean tick, rounded (the geometric mean of prices)
+    pub tick: i32,
+    /// Price at `tick` (token1 per token0, raw units)
+    pub price: Decimal,
+    /// Price at the last swap's tick, for comparison
+    pub last_price: Decimal,
+    /// Swaps within the window
+    pub swaps: usize,
+}
+
+/// Time-weighted mean tick over [from, to]
+///
+/// Each swap's execution tick holds until the next swap. The last swap before
+/// `from` sets the tick at the start of the window; without one the average
+/// starts at the first swap in the window. Swaps after `to` are ignored.
+/// `None` when no swap at or before `to` has a price.
+pub fn time_weighted_tick(
+    swaps: &[Swap],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Option<Decimal> {
+    let points = tick_points(swaps, from, to);
+    let (first, rest) = points.split_first()?;
+
+    let mut cursor = first.0.max(from);
+    let mut tick = first.1;
+    let mut weighted = Decimal::ZERO;
+    let mut elapsed = 0i64;
+    for (timestamp, next) in rest.iter().copied().chain([(to, first.1)]) {
+        let millis = (timestamp - cursor).num_milliseconds().max(0);
+        weighted += Decimal::from(tick) * Decimal::from(millis);
+        elapsed += millis;
+        cursor = timestamp;
+        tick = next;
+    }
+
+    // Every swap landed at `to`; the last one is the only price seen
+    if elapsed == 0 {
+        return points.last().map(|(_, tick)| Decimal::from(*tick));
+    }
+    weighted.checked_div(Decimal::from(elapsed))
+}
+
+/// TWAP of a pool over [from, to] from its swaps (see `time_weighted_tick`)
+pub fn calculate_twap(swaps: &[Swap], from: DateTime<Utc>, to: DateTime<Utc>) -> Option<Twap> {
+    let mean = time_weighted_tick(swaps, from, to)?;
+    let tick = mean.round().to_i32()?;
+    let last_tick = tick_points(swaps, from, to).last()?.1;
+
+    Some(Twap {
+        from,
+        to,
+        tick,
+        price: tick_to_price(tick),
+        last_price: tick_to_price(last_tick),
+        swaps: swaps.iter().filter(|s| s.timestamp >= from && s.timestamp <= to).count(),
+    })
+}
+
+/// Priced swaps at or before `to`, oldest first, starting with the last one before `from`
+fn tick_points(
+    swaps: &[Swap],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, i32)> {
+    let mut points: Vec<(DateTime<Utc>, i32)> = swaps
+        .iter()
+        .filter(|s| s.timestamp <= to)
+        .filter_map(|s| swap_tick(s).map(|tick| (s.timestamp, tick)))
+        .collect();
+    points.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let start = points.iter().rposition(|(timestamp, _)| *timestamp <= from).unwrap_or(0);
+    points.split_off(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::utils::price_to_tick;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn create_test_swap(minutes: i64, tick: i32) -> Swap {
+        let amount1 = (tick_to_price(tick) * Decimal::from(1_000_000_000)).round();
+        testing::create_test_swap(minutes, 1_000_000_000, -amount1.to_i64().unwrap(), at(minutes))
+    }
+
+    #[test]
+    fn test_single_large_swap_barely_moves_twap() {
+        // Price sat at tick 0 for the whole window, then one swap pushed it to 1000
+        let swaps = vec![create_test_swap(-5, 0), create_test_swap(29, 1000)];
+
+        let twap = calculate_twap(&swaps, at(0), at(30)).unwrap();
+
+        assert!(twap.tick.abs() < 50, "twap tick {}", twap.tick);
+        assert_eq!(price_to_tick(twap.last_price), 1000);
+        assert_eq!(twap.swaps, 1);
+    }
+
+    #[test]
+    fn test_ticks_weighted_by_time_held() {
+        let swaps = vec![create_test_swap(0, 100), create_test_swap(10, 400)];
+
+        // 10 minutes at 100, 20 at 400
+        let mean = time_weighted_tick(&swaps, at(0), at(30)).unwrap();
+        assert!((mean - Decimal::from(300)).abs() <= Decimal::ONE, "mean {}", mean);
+
+        // Swaps after the window are ignored; nothing before it means no price
+        assert!(time_weighted_tick(&swaps, at(-30), at(-1)).is_none());
+    }
+}
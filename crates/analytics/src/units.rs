@@ -0,0 +1,138 @@
+use alloy::primitives::{Address, I256, U256};
+use rust_decimal::Decimal;
+use stillwater_models::{Pool, Swap, TokenAmount};
+
+/// A swap's signed token amounts in whole tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapAmounts {
+    pub amount0: Decimal,
+    pub amount1: Decimal,
+}
+
+/// Raw amount (e.g. wei) in whole tokens, for amounts not tied to a token
+///
+/// See `TokenAmount::to_decimal`; prefer a `TokenAmount` (e.g. from
+/// `Pool::amount0`) wherever the token is known. `decimals = 0` keeps raw units.
+pub fn convert_amount(amount: U256, decimals: u8) -> Decimal {
+    TokenAmount::new(amount, decimals, Address::ZERO).to_decimal()
+}
+
+/// Signed raw token amount (e.g. a swap delta) in whole tokens
+pub fn convert_signed_amount(amount: I256, decimals: u8) -> Decimal {
+    let magnitude = convert_amount(amount.unsigned_abs(), decimals);
+    if amount.is_negative() { -magnitude } else { magnitude }
+}
+
+/// Raw amounts of one token in whole tokens
+pub fn convert_amounts(amounts: &[U256], decimals: u8) -> Vec<Decimal> {
+    amounts.iter().map(|amount| convert_amount(*amount, decimals)).collect()
+}
+
+/// Swaps' token amounts in whole tokens of the pool's tokens
+pub fn convert_swap_amounts(pool: &Pool, swaps: &[Swap]) -> Vec<SwapAmounts> {
+    swaps
+        .iter()
+        .map(|swap| SwapAmounts {
+            amount0: signed_units(pool.amount0(swap.amount0.unsigned_abs()), swap.amount0),
+            amount1: signed_units(pool.amount1(swap.amount1.unsigned_abs()), swap.amount1),
+        })
+        .collect()
+}
+
+/// A delta's magnitude as a token amount, in whole tokens with the delta's sign
+pub fn signed_units(magnitude: TokenAmount, delta: I256) -> Decimal {
+    let units = magnitude.to_decimal();
+    if delta.is_negative() { -units } else { units }
+}
+
+/// Raw token amount already in a Decimal (e.g. tracked fees) in whole tokens
+///
+/// Rounded to 18 places; amounts are left raw if the decimals are out of range.
+pub fn raw_to_units(raw: Decimal, decimals: i16) -> Decimal {
+    crate::display::decimal_scale(decimals)
+        .and_then(|scale| raw.checked_div(scale))
+        .unwrap_or(raw)
+        .round_dp(18)
+}
+
+/// A pool's token decimals, clamped to what ERC20 allows
+pub fn pool_decimals(pool: &Pool) -> (u8, u8) {
+    (pool.amount0(U256::ZERO).decimals, pool.amount1(U256::ZERO).decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::str::FromStr;
+    use stillwater_models::AmountError;
+
+    fn create_test_pool() -> Pool {
+        Pool {
+            token0: "0xusdc".to_string(),
+            token1: "0xweth".to_string(),
+            token0_decimals: 6,
+            ..testing::create_test_pool()
+        }
+    }
+
+    #[test]
+    fn test_convert_amount() {
+        let wei = U256::from(1_500_000_000_000_000_000u64);
+        assert_eq!(convert_amount(wei, 18), Decimal::from_str("1.5").unwrap());
+        assert_eq!(convert_amount(U256::from(1u8), 18), Decimal::new(1, 18));
+        assert_eq!(convert_amount(U256::from(2_500_000u64), 6), Decimal::from_str("2.5").unwrap());
+        assert_eq!(convert_amount(U256::from(42u8), 0), Decimal::from(42));
+
+        // 10^40 wei doesn't fit a Decimal raw, but 10^22 tokens does
+        let huge = U256::from(10u8).pow(U256::from(40u8));
+        assert_eq!(convert_amount(huge, 18), Decimal::from(10i128.pow(22)));
+        assert_eq!(convert_amount(huge, 0), Decimal::MAX);
+
+        // More decimals than Decimal holds: the last digits are truncated
+        let tiny = convert_amount(U256::from(123u8), 30);
+        assert_eq!(tiny, Decimal::new(1, 28));
+    }
+
+    #[test]
+    fn test_convert_swap_amounts_per_token_decimals() {
+        let swap = Swap {
+            id: 1,
+            tx_hash: "0x1".to_string(),
+            pool_id: "0xpool".to_string(),
+            amount0: I256::try_from(3_000_000_000i64).unwrap(),
+            amount1: I256::try_from(-1_000_000_000_000_000_000i64).unwrap(),
+            fee: None,
+            timestamp: chrono::Utc::now(),
+        };
+
+        let amounts = convert_swap_amounts(&create_test_pool(), &[swap]);
+        assert_eq!(amounts[0].amount0, Decimal::from(3000));
+        assert_eq!(amounts[0].amount1, -Decimal::ONE);
+
+        let batch = convert_amounts(&[U256::from(1_000_000u64), U256::ZERO], 6);
+        assert_eq!(batch, vec![Decimal::ONE, Decimal::ZERO]);
+    }
+
+    #[test]
+    fn test_token_amounts_keep_their_token_and_decimals() {
+        let pool = create_test_pool();
+        let usdc = pool.amount0(U256::from(2_500_000u64));
+        assert_eq!(usdc.decimals, 6);
+        assert_eq!(usdc.to_string(), "2.5");
+        assert_eq!(format!("{:.2}", usdc), "2.50");
+
+        let half = Decimal::from_str("0.5000001").unwrap();
+        let more = TokenAmount::from_decimal(half, 6, usdc.token);
+        assert_eq!(more.unwrap().raw, U256::from(500_000u64));
+        assert_eq!(usdc.checked_add(&more.unwrap()).unwrap().to_decimal(), Decimal::from(3));
+        assert_eq!(more.unwrap().checked_sub(&usdc), Err(AmountError::Underflow));
+        assert!(TokenAmount::from_decimal(-Decimal::ONE, 6, usdc.token).is_none());
+
+        // Same token with other decimals, or another token, never adds up
+        let misread = TokenAmount::new(U256::from(1u8), 18, usdc.token);
+        assert!(matches!(usdc.checked_add(&misread), Err(AmountError::DecimalsMismatch { .. })));
+        let weth = TokenAmount::new(U256::from(1u8), 6, Address::repeat_byte(1));
+        assert!(matches!(usdc.checked_sub(&weth), Err(AmountError::TokenMismatch { .. })));
+    }
+}
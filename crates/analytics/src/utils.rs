@@ -1,5 +1,101 @@
 use rust_decimal::Decimal;
-use rust_decimal::prelude::*;
+use std::fmt;
+use stillwater_models::Position;
+
+use crate::tickmath::{
+    exact_sqrt_ratio, get_tick_at_sqrt_ratio, price_to_sqrt_ratio, sqrt_ratio_to_price,
+};
+
+/// Lowest tick supported by Uniswap v4
+pub const MIN_TICK: i32 = -887272;
+/// Highest tick supported by Uniswap v4
+pub const MAX_TICK: i32 = 887272;
+
+/// Why a position's tick range can't be analyzed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// tick_lower is above tick_upper
+    Inverted { tick_lower: i32, tick_upper: i32 },
+    /// tick_lower equals tick_upper, so the range has no width
+    ZeroWidth { tick: i32 },
+    /// A bound lies outside [MIN_TICK, MAX_TICK]
+    OutOfBounds { tick: i32 },
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::Inverted { tick_lower, tick_upper } => {
+                write!(f, "inverted range: tick_lower {} > tick_upper {}", tick_lower, tick_upper)
+            }
+            RangeError::ZeroWidth { tick } => write!(f, "zero-width range at tick {}", tick),
+            RangeError::OutOfBounds { tick } => write!(f, "tick {} is out of bounds", tick),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// A validated tick range with tick_lower < tick_upper, both within tick bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickRange {
+    pub lower: i32,
+    pub upper: i32,
+}
+
+impl TickRange {
+    /// Validate raw ticks, rejecting ranges no real position can have
+    pub fn new(tick_lower: i32, tick_upper: i32) -> Result<Self, RangeError> {
+        for tick in [tick_lower, tick_upper] {
+            if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+                return Err(RangeError::OutOfBounds { tick });
+            }
+        }
+        if tick_lower > tick_upper {
+            return Err(RangeError::Inverted { tick_lower, tick_upper });
+        }
+        if tick_lower == tick_upper {
+            return Err(RangeError::ZeroWidth { tick: tick_lower });
+        }
+        Ok(Self { lower: tick_lower, upper: tick_upper })
+    }
+
+    /// Validated range of a position
+    pub fn of(position: &Position) -> Result<Self, RangeError> {
+        Self::new(position.tick_lower, position.tick_upper)
+    }
+
+    /// Width in ticks (always positive)
+    pub fn width(&self) -> i32 {
+        self.upper - self.lower
+    }
+
+    pub fn contains(&self, tick: i32) -> bool {
+        is_in_range(tick, self.lower, self.upper)
+    }
+
+    pub fn distance_to_edge(&self, tick: i32) -> i32 {
+        distance_to_range_edge(tick, self.lower, self.upper)
+    }
+
+    /// Whether moving from `previous_tick` to `current_tick` left or re-entered the range
+    pub fn crossing(&self, previous_tick: i32, current_tick: i32) -> Option<RangeCrossing> {
+        match (self.contains(previous_tick), self.contains(current_tick)) {
+            (true, false) => Some(RangeCrossing::Exited),
+            (false, true) => Some(RangeCrossing::Entered),
+            _ => None,
+        }
+    }
+}
+
+/// A price move across a range edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeCrossing {
+    /// Was in range, now out: the position stopped earning fees
+    Exited,
+    /// Was out of range, now back in
+    Entered,
+}
 
 /// Check if current tick is within position's range
 pub fn is_in_range(current_tick: i32, tick_lower: i32, tick_upper: i32) -> bool {
@@ -22,61 +118,45 @@ pub fn distance_to_range_edge(current_tick: i32, tick_lower: i32, tick_upper: i3
 }
 
 /// Convert tick to price using Uniswap v3/v4 formula: price = 1.0001^tick
+///
+/// Computed from the tick's exact Q64.96 sqrt price (see `tickmath`), so it
+/// agrees with the pool contracts and the Uniswap UI. Ticks beyond the bounds
+/// are clamped to them.
 pub fn tick_to_price(tick: i32) -> Decimal {
-    // For very large ticks, powi will overflow
-    // Use logarithmic calculation: price = e^(tick * ln(1.0001))
-    // This is more numerically stable for large tick values
-
-    // ln(1.0001) ≈ 0.00009999500033330834
-    let ln_base = Decimal::from_str("0.00009999500033330834").unwrap();
-
-    // Calculate tick * ln(1.0001)
-    let tick_decimal = Decimal::from(tick);
-    let exponent = tick_decimal * ln_base;
-
-    // Calculate e^exponent
-    // For safety, cap the result to avoid overflow
-    if exponent.abs() > Decimal::from(100) {
-        // For extremely large ticks, return a reasonable bound
-        if tick > 0 {
-            Decimal::from_str("1000000000").unwrap() // Cap at 1 billion
-        } else {
-            Decimal::from_str("0.000000001").unwrap() // Cap at 1 billionth
-        }
-    } else {
-        exponent.exp()
-    }
+    sqrt_ratio_to_price(exact_sqrt_ratio(tick))
 }
 
 /// Convert price to tick (inverse of tick_to_price)
+///
+/// The tick whose price interval contains `price`, i.e. the greatest tick whose
+/// price is at most `price`, like the Uniswap SDK's `priceToClosestTick`.
 pub fn price_to_tick(price: Decimal) -> i32 {
-    if price <= Decimal::ZERO {
+    let Some(sqrt_ratio) = price_to_sqrt_ratio(price) else {
         return 0;
-    }
+    };
+    let tick = get_tick_at_sqrt_ratio(sqrt_ratio);
 
-    // tick = log(price) / log(1.0001)
-    // Using approximation for now
-    let log_price = price.ln();
-    let log_base = Decimal::from_str("1.0001").unwrap().ln();
-
-    (log_price / log_base).round().to_i32().unwrap_or(0)
+    // The sqrt is rounded down, so a price exactly on a tick can land one below it
+    if tick < MAX_TICK && price >= tick_to_price(tick + 1) { tick + 1 } else { tick }
 }
 
 /// Calculate range width as a percentage
-pub fn range_width_percent(tick_lower: i32, tick_upper: i32) -> Decimal {
-    let price_lower = tick_to_price(tick_lower);
-    let price_upper = tick_to_price(tick_upper);
+pub fn range_width_percent(tick_lower: i32, tick_upper: i32) -> Result<Decimal, RangeError> {
+    let range = TickRange::new(tick_lower, tick_upper)?;
+    let price_lower = tick_to_price(range.lower);
+    let price_upper = tick_to_price(range.upper);
 
     if price_lower.is_zero() {
-        return Decimal::ZERO;
+        return Ok(Decimal::ZERO);
     }
 
-    ((price_upper - price_lower) / price_lower) * Decimal::from(100)
+    Ok(((price_upper - price_lower) / price_lower) * Decimal::from(100))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
 
     #[test]
     fn test_is_in_range() {
@@ -87,6 +167,16 @@ mod tests {
         assert!(!is_in_range(200, 50, 150));
     }
 
+    #[test]
+    fn test_range_crossing() {
+        let range = TickRange::new(50, 150).unwrap();
+        assert_eq!(range.crossing(100, 150), Some(RangeCrossing::Exited));
+        assert_eq!(range.crossing(30, 50), Some(RangeCrossing::Entered));
+        assert_eq!(range.crossing(100, 120), None);
+        // Jumping straight across the range is still out of range
+        assert_eq!(range.crossing(30, 200), None);
+    }
+
     #[test]
     fn test_distance_to_range_edge() {
         assert_eq!(distance_to_range_edge(100, 50, 150), 50);
@@ -109,4 +199,39 @@ mod tests {
         let price_neg100 = tick_to_price(-100);
         assert!(price_neg100 < Decimal::ONE);
     }
+
+    #[test]
+    fn test_price_to_tick_round_trips() {
+        assert_eq!(tick_to_price(0), Decimal::ONE);
+        assert_eq!(tick_to_price(1).round_dp(12), Decimal::from_str("1.0001").unwrap());
+
+        for tick in [-300_000, -200_000, -60, -1, 0, 1, 60, 200_000, 600_000] {
+            assert_eq!(price_to_tick(tick_to_price(tick)), tick);
+        }
+        // Prices between ticks belong to the tick below, as in the Uniswap UI
+        assert_eq!(price_to_tick(Decimal::from_str("1.00015").unwrap()), 1);
+        assert_eq!(price_to_tick(Decimal::from_str("0.99995").unwrap()), -1);
+        assert_eq!(price_to_tick(Decimal::ZERO), 0);
+        // Below Decimal's smallest step prices saturate rather than reach zero
+        assert_eq!(tick_to_price(MIN_TICK), Decimal::new(1, 28));
+    }
+
+    #[test]
+    fn test_tick_range_validation() {
+        let range = TickRange::new(-100, 100).unwrap();
+        assert_eq!(range.width(), 200);
+        assert!(range.contains(0));
+
+        assert_eq!(
+            TickRange::new(100, -100),
+            Err(RangeError::Inverted { tick_lower: 100, tick_upper: -100 })
+        );
+        assert_eq!(TickRange::new(60, 60), Err(RangeError::ZeroWidth { tick: 60 }));
+        assert_eq!(
+            TickRange::new(MIN_TICK - 1, 0),
+            Err(RangeError::OutOfBounds { tick: MIN_TICK - 1 })
+        );
+        assert!(range_width_percent(60, 60).is_err());
+        assert!(range_width_percent(-100, 100).unwrap() > Decimal::ZERO);
+    }
 }
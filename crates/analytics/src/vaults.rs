@@ -0,0 +1,154 @@
+use alloy::primitives::U256;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use stillwater_models::{Address, Vault};
+
+use crate::rates::{ExchangeRates, NormalizedPosition, NormalizedTotals, sum_normalized};
+use crate::units::convert_amount;
+
+/// Decimal places a share fraction is computed to
+const SHARE_FRACTION_DECIMALS: u8 = 18;
+
+/// A holder's shares in a vault
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VaultStake {
+    pub vault: Address,
+    pub share_token: Address,
+    pub name: Option<String>,
+    /// Raw share token units held, and the token's total supply
+    pub shares: String,
+    pub total_supply: String,
+    /// Fraction of every vault position the holder owns
+    pub fraction: Decimal,
+}
+
+/// Fraction `shares / total_supply`, None when the supply is zero
+///
+/// Computed in integers to `SHARE_FRACTION_DECIMALS` places (rounded down), so
+/// share tokens with 18 decimals and large supplies keep their precision.
+/// Balances above the supply, which a consistent token never reports, count as
+/// the whole vault.
+pub fn share_fraction(shares: U256, total_supply: U256) -> Option<Decimal> {
+    if total_supply.is_zero() {
+        return None;
+    }
+    let unit = U256::from(10).pow(U256::from(SHARE_FRACTION_DECIMALS));
+    let scaled = shares.min(total_supply).checked_mul(unit)? / total_supply;
+    Some(convert_amount(scaled, SHARE_FRACTION_DECIMALS).normalize())
+}
+
+impl VaultStake {
+    /// A holder's stake from their share balance, None without supply or shares
+    pub fn new(vault: &Vault, shares: U256, total_supply: U256) -> Option<Self> {
+        if shares.is_zero() {
+            return None;
+        }
+        Some(VaultStake {
+            vault: vault.address,
+            share_token: vault.share_token,
+            name: vault.name.clone(),
+            shares: shares.to_string(),
+            total_supply: total_supply.to_string(),
+            fraction: share_fraction(shares, total_supply)?,
+        })
+    }
+}
+
+/// A holder's effective part of a vault's positions
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultExposure {
+    #[serde(flatten)]
+    pub stake: VaultStake,
+    /// The holder's part of the vault's totals
+    pub totals: NormalizedTotals,
+    /// The vault's positions with value, fees and IL scaled to the holder's part
+    pub positions: Vec<NormalizedPosition>,
+}
+
+/// Scale a vault position's figures down to a holder's fraction of it
+pub fn scale_position(position: &NormalizedPosition, fraction: Decimal) -> NormalizedPosition {
+    NormalizedPosition {
+        value: position.value.map(|v| v * fraction),
+        fees: position.fees.map(|f| f * fraction),
+        impermanent_loss: position.impermanent_loss.map(|il| il * fraction),
+        ..position.clone()
+    }
+}
+
+/// A holder's exposure to a vault from the vault's normalized positions
+pub fn vault_exposure(
+    stake: VaultStake,
+    positions: &[NormalizedPosition],
+    rates: &ExchangeRates,
+) -> VaultExposure {
+    let positions: Vec<NormalizedPosition> =
+        positions.iter().map(|p| scale_position(p, stake.fraction)).collect();
+    VaultExposure { totals: sum_normalized(&positions, rates), stake, positions }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn vault() -> Vault {
+        Vault {
+            address: Address::parse("0x1111111111111111111111111111111111111111").unwrap(),
+            share_token: Address::parse("0x1111111111111111111111111111111111111111").unwrap(),
+            name: Some("arrakis-eth-usdc".to_string()),
+            created_at: Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        }
+    }
+
+    fn normalized(id: i64, value: i64, missing_price: Option<&str>) -> NormalizedPosition {
+        NormalizedPosition {
+            position_id: id,
+            nft_id: id.to_string(),
+            pool_id: "0xpool".to_string(),
+            value: missing_price.is_none().then(|| Decimal::from(value)),
+            fees: missing_price.is_none().then(|| Decimal::from(value / 10)),
+            impermanent_loss: missing_price.is_none().then_some(Decimal::ONE),
+            missing_price: missing_price.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_share_fraction() {
+        let ether = U256::from(10).pow(U256::from(18));
+        let supply = U256::from(1_000_000_000u64) * ether;
+
+        assert_eq!(
+            share_fraction(U256::from(250_000_000u64) * ether, supply),
+            Some(Decimal::new(25, 2))
+        );
+        assert_eq!(
+            share_fraction(U256::from(1), U256::from(3)),
+            Some("0.333333333333333333".parse().unwrap())
+        );
+        assert_eq!(share_fraction(supply * U256::from(2), supply), Some(Decimal::ONE));
+        assert_eq!(share_fraction(U256::from(1), U256::ZERO), None);
+
+        assert!(VaultStake::new(&vault(), U256::ZERO, supply).is_none());
+    }
+
+    #[test]
+    fn test_vault_exposure_scales_positions() {
+        let stake = VaultStake::new(&vault(), U256::from(1), U256::from(4)).unwrap();
+        let rates =
+            ExchangeRates::new("USD", &[], Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap());
+        let positions = vec![
+            normalized(1, 1000, None),
+            normalized(2, 600, None),
+            normalized(3, 50, Some("0xtkn")),
+        ];
+
+        let exposure = vault_exposure(stake, &positions, &rates);
+
+        assert_eq!(exposure.positions[0].value, Some(Decimal::from(250)));
+        assert_eq!(exposure.positions[1].fees, Some(Decimal::from(15)));
+        assert_eq!(exposure.totals.value, Decimal::from(400));
+        assert_eq!(exposure.totals.impermanent_loss, Decimal::new(5, 1));
+        assert_eq!(exposure.totals.unpriced_positions, 1);
+        assert_eq!(exposure.totals.missing_prices, vec!["0xtkn".to_string()]);
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
+use stillwater_models::PositionSnapshot;
+
+/// Hours of recent fee accrual compared against the baseline by default
+pub const DEFAULT_FEE_VELOCITY_RECENT_HOURS: i64 = 24;
+
+/// Days of fee accrual before the recent window forming the baseline by default
+pub const DEFAULT_FEE_VELOCITY_BASELINE_DAYS: i64 = 7;
+
+/// Fractional drop from the baseline rate flagged by default
+pub const DEFAULT_FEE_VELOCITY_DROP: Decimal = Decimal::from_parts(5, 0, 0, false, 1);
+
+/// Windows fee velocity is measured over, and the drop that counts as significant
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeVelocityConfig {
+    pub recent_hours: i64,
+    pub baseline_days: i64,
+    /// Fraction (0-1) the recent rate must fall below the baseline rate by
+    pub drop: Decimal,
+}
+
+impl Default for FeeVelocityConfig {
+    fn default() -> Self {
+        Self {
+            recent_hours: DEFAULT_FEE_VELOCITY_RECENT_HOURS,
+            baseline_days: DEFAULT_FEE_VELOCITY_BASELINE_DAYS,
+            drop: DEFAULT_FEE_VELOCITY_DROP,
+        }
+    }
+}
+
+impl FeeVelocityConfig {
+    /// Config from `FEE_VELOCITY_RECENT_HOURS`, `FEE_VELOCITY_BASELINE_DAYS` and `FEE_VELOCITY_DROP`
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(hours) = std::env::var("FEE_VELOCITY_RECENT_HOURS") {
+            config.recent_hours =
+                hours.trim().parse().context("FEE_VELOCITY_RECENT_HOURS must be a number")?;
+            if config.recent_hours <= 0 {
+                bail!("FEE_VELOCITY_RECENT_HOURS must be positive");
+            }
+        }
+        if let Ok(days) = std::env::var("FEE_VELOCITY_BASELINE_DAYS") {
+            config.baseline_days =
+                days.trim().parse().context("FEE_VELOCITY_BASELINE_DAYS must be a number")?;
+            if config.baseline_days <= 0 {
+                bail!("FEE_VELOCITY_BASELINE_DAYS must be positive");
+            }
+        }
+        if let Ok(drop) = std::env::var("FEE_VELOCITY_DROP") {
+            config.drop =
+                Decimal::from_str(drop.trim()).context("FEE_VELOCITY_DROP must be a number")?;
+            if config.drop <= Decimal::ZERO || config.drop >= Decimal::ONE {
+                bail!("FEE_VELOCITY_DROP must be between 0 and 1");
+            }
+        }
+        Ok(config)
+    }
+
+    /// The same windows with a different drop threshold
+    pub fn with_drop(&self, drop: Decimal) -> Self {
+        Self { drop, ..self.clone() }
+    }
+}
+
+/// Fees a position accrued over a window, and the rate they accrued at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FeeVelocity {
+    /// First and last snapshots the window's fees are measured between
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub fees: Decimal,
+    pub fees_per_day: Decimal,
+}
+
+/// Recent fee velocity against the baseline before it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeVelocityTrend {
+    pub recent: FeeVelocity,
+    /// None without snapshots before the recent window
+    pub baseline: Option<FeeVelocity>,
+    /// Recent rate relative to the baseline rate, minus one (None without a baseline rate)
+    pub change: Option<Decimal>,
+    /// The drop threshold `dropping` was decided with
+    pub drop_threshold: Decimal,
+    /// Whether the recent rate fell at least `drop_threshold` below the baseline
+    pub dropping: bool,
+}
+
+/// Fee velocity between `from` and `to`, from a position's cumulative fee snapshots
+///
+/// Fees are measured from the last snapshot at or before `from` (the first one
+/// after it when there is none) to the last one at or before `to`. None when
+/// fewer than two snapshots bound the window.
+pub fn fee_velocity(
+    snapshots: &[PositionSnapshot],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Option<FeeVelocity> {
+    let start =
+        snapshots.iter().filter(|s| s.timestamp <= from).max_by_key(|s| s.timestamp).or_else(
+            || snapshots.iter().filter(|s| s.timestamp > from).min_by_key(|s| s.timestamp),
+        )?;
+    let end = snapshots.iter().filter(|s| s.timestamp <= to).max_by_key(|s| s.timestamp)?;
+    if end.timestamp <= start.timestamp {
+        return None;
+    }
+
+    // Cumulative fees only fall when a position is rebuilt; that's no accrual
+    let fees = (end.fees_earned - start.fees_earned).max(Decimal::ZERO);
+    let days = Decimal::from((end.timestamp - start.timestamp).num_seconds())
+        / Decimal::from(Duration::days(1).num_seconds());
+    Some(FeeVelocity {
+        from: start.timestamp,
+        to: end.timestamp,
+        fees,
+        fees_per_day: fees.checked_div(days)?,
+    })
+}
+
+/// Compare a position's fee velocity over the recent window with the baseline before it
+///
+/// A position is `dropping` when its baseline rate was positive and the recent
+/// rate fell by at least the configured fraction of it, e.g. after volume moved
+/// to another fee tier or pool. None without a recent velocity.
+pub fn fee_velocity_trend(
+    snapshots: &[PositionSnapshot],
+    now: DateTime<Utc>,
+    config: &FeeVelocityConfig,
+) -> Option<FeeVelocityTrend> {
+    let recent_start = now - Duration::hours(config.recent_hours);
+    let baseline_start = recent_start - Duration::days(config.baseline_days);
+
+    let recent = fee_velocity(snapshots, recent_start, now)?;
+    let baseline = fee_velocity(snapshots, baseline_start, recent_start);
+    let change = baseline
+        .filter(|b| b.fees_per_day > Decimal::ZERO)
+        .and_then(|b| recent.fees_per_day.checked_div(b.fees_per_day))
+        .map(|ratio| ratio - Decimal::ONE);
+    let dropping = change.is_some_and(|c| c <= -config.drop);
+
+    Some(FeeVelocityTrend { recent, baseline, change, drop_threshold: config.drop, dropping })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    /// Hourly snapshots over `days` days earning `per_hour(hours_before_now)` each hour
+    fn create_test_snapshots(
+        now: DateTime<Utc>,
+        days: i64,
+        per_hour: impl Fn(i64) -> i64,
+    ) -> Vec<PositionSnapshot> {
+        let mut fees = Decimal::ZERO;
+        (0..=days * 24)
+            .rev()
+            .map(|hours_ago| {
+                fees += Decimal::from(per_hour(hours_ago));
+                PositionSnapshot {
+                    id: 0,
+                    position_id: 1,
+                    timestamp: now - Duration::hours(hours_ago),
+                    fees_earned: fees,
+                    liquidity: U256::from(1_000_000u64),
+                    price: Decimal::ONE,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_steady_accrual_is_not_dropping() {
+        let now = Utc::now();
+        let snapshots = create_test_snapshots(now, 8, |_| 10);
+
+        let trend = fee_velocity_trend(&snapshots, now, &FeeVelocityConfig::default()).unwrap();
+        assert_eq!(trend.recent.fees_per_day, Decimal::from(240));
+        assert_eq!(trend.baseline.unwrap().fees_per_day, Decimal::from(240));
+        assert_eq!(trend.change, Some(Decimal::ZERO));
+        assert!(!trend.dropping);
+    }
+
+    #[test]
+    fn test_detects_drop_after_volume_moves() {
+        let now = Utc::now();
+        // Earned 10 an hour until a day ago, then 2
+        let snapshots =
+            create_test_snapshots(now, 8, |hours_ago| if hours_ago < 24 { 2 } else { 10 });
+
+        let config = FeeVelocityConfig::default();
+        let trend = fee_velocity_trend(&snapshots, now, &config).unwrap();
+        assert_eq!(trend.recent.fees_per_day, Decimal::from(48));
+        assert_eq!(trend.change, Some(Decimal::new(-8, 1)));
+        assert!(trend.dropping);
+
+        // A stricter threshold doesn't flag an 80% drop
+        let strict = fee_velocity_trend(&snapshots, now, &config.with_drop(Decimal::new(9, 1)));
+        assert!(!strict.unwrap().dropping);
+    }
+
+    #[test]
+    fn test_new_position_has_no_baseline() {
+        let now = Utc::now();
+        let snapshots = create_test_snapshots(now, 0, |_| 10);
+        assert_eq!(fee_velocity_trend(&snapshots, now, &FeeVelocityConfig::default()), None);
+
+        // Half a day old: a recent rate, nothing before it to compare with
+        let young = create_test_snapshots(now, 1, |_| 10);
+        let trend = fee_velocity_trend(&young[12..], now, &FeeVelocityConfig::default()).unwrap();
+        assert_eq!(trend.baseline, None);
+        assert!(!trend.dropping);
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::str::FromStr;
+use stillwater_models::Swap;
+
+use crate::risk::daily_tick_volatility;
+
+/// Implied over realized volatility at or above which IV counts as elevated
+pub const ELEVATED_IV_RATIO: Decimal = Decimal::from_parts(125, 0, 0, false, 2);
+
+/// Implied over realized volatility at or below which IV counts as subdued
+pub const SUBDUED_IV_RATIO: Decimal = Decimal::from_parts(80, 0, 0, false, 2);
+
+/// Highest annualized volatility accepted as input (1000%)
+const MAX_VOLATILITY: Decimal = Decimal::from_parts(10, 0, 0, false, 0);
+
+/// Annualized volatility of a pool's price from its daily tick volatility
+///
+/// One tick is a `ln(1.0001)` move in log price, and pools trade every day,
+/// so a day's deviation scales to a year by `√365`. Comparable to option
+/// implied volatility, e.g. Deribit's DVOL (as a fraction, not percent).
+pub fn annualize_tick_volatility(daily_tick_volatility: Decimal) -> Option<Decimal> {
+    let log_tick = Decimal::new(10001, 4).checked_ln()?;
+    Some(daily_tick_volatility * log_tick * Decimal::from(365).sqrt()?)
+}
+
+/// Parse an annualized volatility: a fraction (`0.65`) or percent (`65%`)
+pub fn parse_volatility(s: &str) -> Result<Decimal> {
+    let trimmed = s.trim();
+    let volatility = match trimmed.strip_suffix('%') {
+        Some(percent) => Decimal::from_str(percent.trim()).ok().map(|p| p / Decimal::ONE_HUNDRED),
+        None => Decimal::from_str(trimmed).ok(),
+    };
+    match volatility {
+        Some(v) if v > Decimal::ZERO && v <= MAX_VOLATILITY => Ok(v),
+        Some(_) => bail!("volatility must be above 0 and at most 1000%: {}", trimmed),
+        None => bail!("invalid volatility: {}", trimmed),
+    }
+}
+
+/// How the options market's expectation compares with recent movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolatilityRegime {
+    /// Implied well below realized: calmer prices expected than of late
+    Subdued,
+    Normal,
+    /// Implied well above realized: bigger moves expected than of late
+    Elevated,
+}
+
+/// Realized pool volatility next to implied volatility for the pair
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VolatilityComparison {
+    /// Annualized, from the pool's recent swaps
+    pub realized: Decimal,
+    /// Annualized, from `source`
+    pub implied: Decimal,
+    /// Where the implied figure came from, e.g. `deribit:ETH` or `request`
+    pub source: String,
+    /// Implied over realized
+    pub ratio: Decimal,
+    pub regime: VolatilityRegime,
+    /// What the regime means for a range being entered or rebalanced (None when normal)
+    pub note: Option<String>,
+}
+
+/// Compare realized with implied volatility, both annualized fractions
+///
+/// None when the pool hasn't moved, as no ratio can be taken.
+pub fn compare_volatility(
+    realized: Decimal,
+    implied: Decimal,
+    source: &str,
+) -> Option<VolatilityComparison> {
+    let ratio = implied.checked_div(realized)?;
+    let (regime, note) = if ratio >= ELEVATED_IV_RATIO {
+        (
+            VolatilityRegime::Elevated,
+            Some(
+                "IV elevated: expected IL higher than usual; consider a wider range or a \
+                 looser rebalance trigger"
+                    .to_string(),
+            ),
+        )
+    } else if ratio <= SUBDUED_IV_RATIO {
+        (
+            VolatilityRegime::Subdued,
+            Some(
+                "IV below realized: the market expects calmer prices than of late, so IL \
+                 may be lower than recent history suggests"
+                    .to_string(),
+            ),
+        )
+    } else {
+        (VolatilityRegime::Normal, None)
+    };
+    Some(VolatilityComparison {
+        realized,
+        implied,
+        source: source.to_string(),
+        ratio: ratio.round_dp(4),
+        regime,
+        note,
+    })
+}
+
+/// Annualized realized volatility over the window ending at one time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RealizedVolatilityPoint {
+    pub timestamp: DateTime<Utc>,
+    /// None when the window has too few observed hours
+    pub volatility: Option<Decimal>,
+}
+
+/// Realized volatility over a trailing `window`, every `step` from `from` to `to`
+///
+/// Each point measures the swaps in `(timestamp - window, timestamp]`, so the
+/// swaps passed in should reach back a `window` before `from`.
+pub fn rolling_realized_volatility(
+    swaps: &[Swap],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    step: Duration,
+    window: Duration,
+) -> Vec<RealizedVolatilityPoint> {
+    if step <= Duration::zero() {
+        return vec![];
+    }
+    let mut ordered: Vec<&Swap> = swaps.iter().collect();
+    ordered.sort_by_key(|s| s.timestamp);
+
+    let mut points = Vec::new();
+    let mut timestamp = from;
+    while timestamp <= to {
+        let start = ordered.partition_point(|s| s.timestamp <= timestamp - window);
+        let end = ordered.partition_point(|s| s.timestamp <= timestamp);
+        let in_window: Vec<Swap> = ordered[start..end].iter().map(|s| (*s).clone()).collect();
+        let volatility = daily_tick_volatility(&in_window).and_then(annualize_tick_volatility);
+        points.push(RealizedVolatilityPoint { timestamp, volatility });
+        timestamp += step;
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::utils::tick_to_price;
+    use chrono::TimeZone;
+
+    fn create_test_swap(hours: i64, tick: i32, from: DateTime<Utc>) -> Swap {
+        let amount1 = (tick_to_price(tick) * Decimal::from(1_000_000)).to_i64().unwrap();
+        testing::create_test_swap(hours, 1_000_000, -amount1, from + Duration::hours(hours))
+    }
+
+    #[test]
+    fn test_annualize_tick_volatility() {
+        // 500 ticks a day is about 5% daily, about 95% a year
+        let annual = annualize_tick_volatility(Decimal::from(500)).unwrap();
+        assert!(annual > Decimal::new(94, 2) && annual < Decimal::new(97, 2));
+        assert_eq!(annualize_tick_volatility(Decimal::ZERO), Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn test_parse_volatility() {
+        assert_eq!(parse_volatility("0.65").unwrap(), Decimal::new(65, 2));
+        assert_eq!(parse_volatility(" 65% ").unwrap(), Decimal::new(65, 2));
+        assert!(parse_volatility("0").is_err());
+        assert!(parse_volatility("2000%").is_err());
+        assert!(parse_volatility("high").is_err());
+    }
+
+    #[test]
+    fn test_compare_volatility() {
+        let elevated = compare_volatility(Decimal::new(50, 2), Decimal::new(70, 2), "request");
+        let elevated = elevated.unwrap();
+        assert_eq!(elevated.regime, VolatilityRegime::Elevated);
+        assert_eq!(elevated.ratio, Decimal::new(14, 1));
+        assert!(elevated.note.unwrap().starts_with("IV elevated"));
+
+        let normal = compare_volatility(Decimal::new(50, 2), Decimal::new(55, 2), "request");
+        assert_eq!(normal.as_ref().unwrap().regime, VolatilityRegime::Normal);
+        assert_eq!(normal.unwrap().note, None);
+
+        let subdued = compare_volatility(Decimal::new(50, 2), Decimal::new(40, 2), "request");
+        assert_eq!(subdued.unwrap().regime, VolatilityRegime::Subdued);
+
+        assert_eq!(compare_volatility(Decimal::ZERO, Decimal::new(40, 2), "request"), None);
+    }
+
+    #[test]
+    fn test_rolling_realized_volatility() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        // A calm day of 10-tick hourly moves, then a day of 200-tick moves
+        let swaps: Vec<Swap> = (0..48)
+            .map(|hour| {
+                let size = if hour < 24 { 10 } else { 200 };
+                let tick = if hour % 2 == 0 { 0 } else { size };
+                create_test_swap(hour, tick, start)
+            })
+            .collect();
+
+        let points = rolling_realized_volatility(
+            &swaps,
+            start + Duration::hours(23),
+            start + Duration::hours(47),
+            Duration::hours(24),
+            Duration::hours(24),
+        );
+        assert_eq!(points.len(), 2);
+        let calm = points[0].volatility.unwrap();
+        let wild = points[1].volatility.unwrap();
+        assert!(wild > calm * Decimal::from(10));
+
+        // Too few hours in the window
+        let points = rolling_realized_volatility(
+            &swaps,
+            start,
+            start,
+            Duration::hours(1),
+            Duration::hours(24),
+        );
+        assert_eq!(points[0].volatility, None);
+        assert!(
+            rolling_realized_volatility(&swaps, start, start, Duration::zero(), Duration::hours(1))
+                .is_empty()
+        );
+    }
+}
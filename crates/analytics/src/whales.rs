@@ -0,0 +1,220 @@
+use alloy::primitives::I256;
+use anyhow::{Context, Result, bail};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
+use stillwater_models::{OnchainSwap, Pool};
+
+use crate::rates::ExchangeRates;
+use crate::units::signed_units;
+
+/// USD value of a single swap that counts as large by default
+pub const DEFAULT_LARGE_SWAP_USD: Decimal = Decimal::from_parts(100_000, 0, 0, false, 0);
+
+/// Ticks a single swap must move the price by to count as large by default (about 2%)
+pub const DEFAULT_LARGE_SWAP_TICKS: i32 = 200;
+
+/// What makes a single swap large enough to alert on
+#[derive(Debug, Clone, Serialize)]
+pub struct LargeSwapConfig {
+    /// Value, in USD, a swap must reach
+    pub min_value: Decimal,
+    /// Ticks a swap must move the pool's price by, either way
+    pub min_ticks: i32,
+}
+
+impl Default for LargeSwapConfig {
+    fn default() -> Self {
+        Self { min_value: DEFAULT_LARGE_SWAP_USD, min_ticks: DEFAULT_LARGE_SWAP_TICKS }
+    }
+}
+
+impl LargeSwapConfig {
+    /// Config from `LARGE_SWAP_USD` and `LARGE_SWAP_TICKS`
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("LARGE_SWAP_USD") {
+            config.min_value =
+                Decimal::from_str(value.trim()).context("LARGE_SWAP_USD must be a number")?;
+            if config.min_value <= Decimal::ZERO {
+                bail!("LARGE_SWAP_USD must be positive");
+            }
+        }
+        if let Ok(ticks) = std::env::var("LARGE_SWAP_TICKS") {
+            config.min_ticks = ticks.trim().parse().context("LARGE_SWAP_TICKS must be a number")?;
+            if config.min_ticks <= 0 {
+                bail!("LARGE_SWAP_TICKS must be positive");
+            }
+        }
+        Ok(config)
+    }
+
+    /// The config with a rule's thresholds in place of the defaults it sets
+    pub fn with_rule(&self, min_value: Option<Decimal>, min_ticks: Option<i32>) -> Self {
+        Self {
+            min_value: min_value.unwrap_or(self.min_value),
+            min_ticks: min_ticks.unwrap_or(self.min_ticks),
+        }
+    }
+
+    /// Whether a swap reached the value or moved the price far enough
+    ///
+    /// A swap without a value (neither token has a rate) or without a tick
+    /// move (the pool's earlier tick isn't known) can only meet the other test.
+    pub fn is_large(&self, impact: &SwapImpact) -> bool {
+        impact.value.is_some_and(|value| value >= self.min_value)
+            || impact.tick_move.is_some_and(|ticks| ticks.abs() >= self.min_ticks)
+    }
+}
+
+/// What a single swap did to its pool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SwapImpact {
+    pub pool_id: String,
+    pub tx_hash: Option<String>,
+    pub block_number: u64,
+    pub log_index: u64,
+    /// Token0 and token1 into the pool (negative: out of it), in whole tokens
+    pub amount0: Decimal,
+    pub amount1: Decimal,
+    /// Value in the rates' currency, None when neither token has a rate
+    pub value: Option<Decimal>,
+    pub tick_before: Option<i32>,
+    pub tick_after: i32,
+    /// `tick_after - tick_before` (positive: token0 got dearer)
+    pub tick_move: Option<i32>,
+}
+
+/// Value of a swap in the rates' currency: the larger of its two legs
+///
+/// Both legs are worth about the same, so either one priced is enough.
+pub fn swap_value(
+    pool: &Pool,
+    amount0: I256,
+    amount1: I256,
+    rates: &ExchangeRates,
+) -> Option<Decimal> {
+    let value0 = rates.value(&pool.amount0(amount0.unsigned_abs()));
+    let value1 = rates.value(&pool.amount1(amount1.unsigned_abs()));
+    value0.max(value1)
+}
+
+/// Value and tick move of each of `pool`'s swaps, in log order
+///
+/// `tick_before` is the pool's tick before the first swap (None if unknown);
+/// each later swap starts from the tick the previous one left. Swaps in other
+/// pools are skipped.
+pub fn swap_impacts(
+    pool: &Pool,
+    swaps: &[OnchainSwap],
+    tick_before: Option<i32>,
+    rates: &ExchangeRates,
+) -> Vec<SwapImpact> {
+    let mut tick_before = tick_before;
+    let mut impacts = Vec::new();
+    for swap in swaps.iter().filter(|s| s.pool_id.eq_ignore_ascii_case(&pool.pool_id)) {
+        impacts.push(SwapImpact {
+            pool_id: pool.pool_id.clone(),
+            tx_hash: swap.tx_hash.clone(),
+            block_number: swap.block_number,
+            log_index: swap.log_index,
+            amount0: signed_units(pool.amount0(swap.amount0.unsigned_abs()), swap.amount0),
+            amount1: signed_units(pool.amount1(swap.amount1.unsigned_abs()), swap.amount1),
+            value: swap_value(pool, swap.amount0, swap.amount1, rates),
+            tick_before,
+            tick_after: swap.tick,
+            tick_move: tick_before.map(|tick| swap.tick - tick),
+        });
+        tick_before = Some(swap.tick);
+    }
+    impacts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use chrono::Utc;
+
+    const USDC: &str = "0x078d782b760474a361dda0af3839290b0ef57ad6";
+    const WETH: &str = "0x4200000000000000000000000000000000000006";
+
+    fn create_test_pool() -> Pool {
+        Pool {
+            token0: WETH.to_string(),
+            token1: USDC.to_string(),
+            token1_decimals: 6,
+            ..testing::create_test_pool()
+        }
+    }
+
+    /// A swap selling `eth` WETH for `usdc` USDC, leaving the pool at `tick`
+    fn swap(pool_id: &str, eth: i64, usdc: i64, tick: i32) -> OnchainSwap {
+        let units = |amount: i64, decimals: usize| {
+            I256::try_from(amount).unwrap()
+                * I256::from_dec_str(&format!("1{}", "0".repeat(decimals))).unwrap()
+        };
+        OnchainSwap {
+            pool_id: pool_id.to_string(),
+            tx_hash: Some("0xtx".to_string()),
+            block_number: 100,
+            log_index: 0,
+            amount0: units(eth, 18),
+            amount1: -units(usdc, 6),
+            tick,
+            fee: 3000,
+        }
+    }
+
+    fn usd_rates() -> ExchangeRates {
+        ExchangeRates::new("usd", &[USDC.to_string()], Utc::now())
+    }
+
+    #[test]
+    fn test_swap_value_from_priced_leg() {
+        let pool = create_test_pool();
+        let swap = swap("0xpool", 40, 120_000, -200_000);
+
+        // Only USDC has a rate; the USDC leg prices the swap
+        let value = swap_value(&pool, swap.amount0, swap.amount1, &usd_rates());
+        assert_eq!(value, Some(Decimal::from(120_000)));
+
+        let unpriced = ExchangeRates::new("usd", &[], Utc::now());
+        assert_eq!(swap_value(&pool, swap.amount0, swap.amount1, &unpriced), None);
+    }
+
+    #[test]
+    fn test_tick_moves_chain_through_swaps() {
+        let pool = create_test_pool();
+        let swaps = [
+            swap("0xpool", 1, 3_000, -200_050),
+            swap("0xother", 1, 3_000, 5),
+            swap("0xpool", 50, 140_000, -200_400),
+        ];
+
+        let impacts = swap_impacts(&pool, &swaps, Some(-200_000), &usd_rates());
+        assert_eq!(impacts.len(), 2);
+        assert_eq!(impacts[0].tick_move, Some(-50));
+        assert_eq!(impacts[1].tick_before, Some(-200_050));
+        assert_eq!(impacts[1].tick_move, Some(-350));
+        assert_eq!(impacts[1].amount0, Decimal::from(50));
+
+        // Without the starting tick the first move is unknown
+        assert_eq!(swap_impacts(&pool, &swaps, None, &usd_rates())[0].tick_move, None);
+    }
+
+    #[test]
+    fn test_large_by_value_or_tick_move() {
+        let pool = create_test_pool();
+        let swaps = [swap("0xpool", 1, 3_000, -200_050), swap("0xpool", 50, 140_000, -200_100)];
+        let impacts = swap_impacts(&pool, &swaps, Some(-200_000), &usd_rates());
+        let config = LargeSwapConfig::default();
+
+        assert!(!config.is_large(&impacts[0]));
+        assert!(config.is_large(&impacts[1]));
+
+        // A rule's tick threshold catches the small swap's 50 tick move
+        assert!(config.with_rule(None, Some(50)).is_large(&impacts[0]));
+        assert!(!config.with_rule(Some(Decimal::from(500_000)), None).is_large(&impacts[1]));
+    }
+}
@@ -0,0 +1,82 @@
+use axum::{http::StatusCode, response::Json};
+use sqlx::PgPool;
+use std::fmt;
+use stillwater_db::get_account;
+use stillwater_models::Address;
+use tracing::error;
+
+use crate::timerange::ErrorResponse;
+
+/// Prefix of an `:owner` segment naming an account rather than an address
+pub const ACCOUNT_PREFIX: &str = "account:";
+
+/// Parse an `:owner` path segment, answering `400` when it isn't an address
+///
+/// Any case is accepted; mixed case must carry a valid EIP-55 checksum.
+pub fn owner_param(owner: &str) -> Result<Address, ErrorResponse> {
+    Address::parse(owner).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid owner {}: {}", owner, e) })),
+        )
+    })
+}
+
+/// The addresses an `:owner` segment stands for: one address, or an account's
+#[derive(Debug, Clone)]
+pub struct OwnerScope {
+    /// The checksummed address, or `account:<name>`; echoed as `owner` in responses
+    pub label: String,
+    pub addresses: Vec<Address>,
+}
+
+impl OwnerScope {
+    pub fn contains(&self, address: &Address) -> bool {
+        self.addresses.contains(address)
+    }
+
+    /// The address, unless the scope is an account
+    pub fn address(&self) -> Option<Address> {
+        match self.addresses.as_slice() {
+            [address] if !self.label.starts_with(ACCOUNT_PREFIX) => Some(*address),
+            _ => None,
+        }
+    }
+}
+
+impl From<Address> for OwnerScope {
+    fn from(address: Address) -> Self {
+        Self { label: address.to_string(), addresses: vec![address] }
+    }
+}
+
+impl fmt::Display for OwnerScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// Parse an `:owner` segment that may also be `account:<name>`
+///
+/// Answers `404` for an unknown account and `400` as `owner_param` does.
+pub async fn owner_scope_param(db_pool: &PgPool, owner: &str) -> Result<OwnerScope, ErrorResponse> {
+    let Some(name) = owner.strip_prefix(ACCOUNT_PREFIX) else {
+        return owner_param(owner).map(OwnerScope::from);
+    };
+    match get_account(db_pool, name).await {
+        Ok(Some(account)) => Ok(OwnerScope {
+            label: format!("{}{}", ACCOUNT_PREFIX, account.name),
+            addresses: account.addresses,
+        }),
+        Ok(None) => {
+            Err((StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Account not found" }))))
+        }
+        Err(e) => {
+            error!("Failed to get account {}: {}", name, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            ))
+        }
+    }
+}
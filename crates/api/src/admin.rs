@@ -0,0 +1,53 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+
+use crate::auth::{bearer_token, hash_api_key};
+use crate::state::AppState;
+
+/// Operator key guarding the `/admin` routes and `/query`, held as its hash
+pub struct AdminKey {
+    hash: String,
+}
+
+impl AdminKey {
+    pub fn new(key: &str) -> Self {
+        Self { hash: hash_api_key(key.trim()) }
+    }
+
+    /// Whether the request's bearer token is the admin key
+    pub fn accepts(&self, headers: &HeaderMap) -> bool {
+        bearer_token(headers).is_some_and(|token| hash_api_key(token) == self.hash)
+    }
+}
+
+/// Require `Authorization: Bearer <ADMIN_API_KEY>`
+///
+/// Without `ADMIN_API_KEY` the routes are disabled (503), so they're never
+/// open by accident; a missing or wrong key is 401.
+pub async fn require_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(admin) = &state.admin else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": "Admin routes are disabled, ADMIN_API_KEY is not set"
+            })),
+        )
+            .into_response();
+    };
+    if !admin.accepts(request.headers()) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Invalid admin key" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,290 @@
+use alloy::primitives::{Address, PrimitiveSignature, keccak256};
+use anyhow::{Context, Result, anyhow, bail};
+use axum::http::{HeaderMap, header::AUTHORIZATION};
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+const SIWE_PREAMBLE: &str = " wants you to sign in with your Ethereum account:";
+
+/// Sign-In with Ethereum settings for this deployment
+#[derive(Debug, Clone)]
+pub struct SiweConfig {
+    /// Domain users sign in to (must match the message's domain)
+    pub domain: String,
+    /// URI included in issued messages (must match the message's URI)
+    pub uri: String,
+    pub chain_id: u64,
+}
+
+/// An EIP-4361 (Sign-In with Ethereum) message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+}
+
+impl SiweMessage {
+    /// Render the message in the EIP-4361 text format that wallets sign
+    pub fn to_message(&self) -> String {
+        let mut message =
+            format!("{}{}\n{}\n\n", self.domain, SIWE_PREAMBLE, self.address.to_checksum(None));
+        if let Some(statement) = &self.statement {
+            message.push_str(&format!("{}\n\n", statement));
+        }
+        message.push_str(&format!(
+            "URI: {}\nVersion: {}\nChain ID: {}\nNonce: {}\nIssued At: {}",
+            self.uri,
+            self.version,
+            self.chain_id,
+            self.nonce,
+            self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ));
+        if let Some(expiration) = self.expiration_time {
+            message.push_str(&format!(
+                "\nExpiration Time: {}",
+                expiration.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ));
+        }
+        message
+    }
+
+    /// Parse an EIP-4361 message
+    pub fn parse(message: &str) -> Result<Self> {
+        let mut lines = message.lines();
+
+        let domain = lines
+            .next()
+            .and_then(|l| l.strip_suffix(SIWE_PREAMBLE))
+            .ok_or_else(|| anyhow!("Missing SIWE preamble"))?
+            .to_string();
+        let address = lines
+            .next()
+            .ok_or_else(|| anyhow!("Missing address"))?
+            .trim()
+            .parse::<Address>()
+            .context("Invalid address")?;
+
+        let mut statement = None;
+        let mut uri = None;
+        let mut version = None;
+        let mut chain_id = None;
+        let mut nonce = None;
+        let mut issued_at = None;
+        let mut expiration_time = None;
+
+        let parse_time = |v: &str| -> Result<DateTime<Utc>> {
+            Ok(DateTime::parse_from_rfc3339(v).context("Invalid timestamp")?.with_timezone(&Utc))
+        };
+
+        for line in lines {
+            if let Some(v) = line.strip_prefix("URI: ") {
+                uri = Some(v.to_string());
+            } else if let Some(v) = line.strip_prefix("Version: ") {
+                version = Some(v.to_string());
+            } else if let Some(v) = line.strip_prefix("Chain ID: ") {
+                chain_id = Some(v.parse::<u64>().context("Invalid chain ID")?);
+            } else if let Some(v) = line.strip_prefix("Nonce: ") {
+                nonce = Some(v.to_string());
+            } else if let Some(v) = line.strip_prefix("Issued At: ") {
+                issued_at = Some(parse_time(v)?);
+            } else if let Some(v) = line.strip_prefix("Expiration Time: ") {
+                expiration_time = Some(parse_time(v)?);
+            } else if !line.is_empty() && uri.is_none() {
+                statement = Some(line.to_string());
+            }
+        }
+
+        Ok(Self {
+            domain,
+            address,
+            statement,
+            uri: uri.ok_or_else(|| anyhow!("Missing URI"))?,
+            version: version.ok_or_else(|| anyhow!("Missing version"))?,
+            chain_id: chain_id.ok_or_else(|| anyhow!("Missing chain ID"))?,
+            nonce: nonce.ok_or_else(|| anyhow!("Missing nonce"))?,
+            issued_at: issued_at.ok_or_else(|| anyhow!("Missing issued at"))?,
+            expiration_time,
+        })
+    }
+
+    /// Check the message targets this deployment and is currently valid
+    pub fn validate(&self, config: &SiweConfig, now: DateTime<Utc>) -> Result<()> {
+        if self.domain != config.domain {
+            bail!("Message domain does not match");
+        }
+        if self.uri != config.uri {
+            bail!("Message URI does not match");
+        }
+        if self.chain_id != config.chain_id {
+            bail!("Message chain ID does not match");
+        }
+        if self.version != "1" {
+            bail!("Unsupported SIWE version");
+        }
+        if self.expiration_time.is_some_and(|exp| exp <= now) {
+            bail!("Message has expired");
+        }
+        Ok(())
+    }
+}
+
+/// Recover the signer of an EIP-191 personal signature over `message`
+///
+/// Must be given the exact text the wallet signed, not a re-rendered message.
+pub fn recover_signer(message: &str, signature: &str) -> Result<Address> {
+    let signature = signature.parse::<PrimitiveSignature>().context("Invalid signature")?;
+    signature.recover_address_from_msg(message).context("Failed to recover signer")
+}
+
+/// Generate a random nonce for a sign-in message
+pub fn generate_nonce() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Generate a new random API key
+pub fn generate_api_key() -> String {
+    format!("sw_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Hash an API key for storage and lookup
+pub fn hash_api_key(key: &str) -> String {
+    keccak256(key.as_bytes()).to_string()
+}
+
+/// Extract a bearer token from the Authorization header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{SignerSync, local::PrivateKeySigner};
+    use chrono::Duration;
+
+    /// Anvil's first development key, so the signer address is well known
+    const TEST_PRIVATE_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const TEST_SIGNER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn create_test_config() -> SiweConfig {
+        SiweConfig {
+            domain: "stillwater.example".to_string(),
+            uri: "https://stillwater.example".to_string(),
+            chain_id: 130,
+        }
+    }
+
+    fn create_test_message(now: DateTime<Utc>) -> SiweMessage {
+        SiweMessage {
+            domain: "stillwater.example".to_string(),
+            address: TEST_SIGNER.parse().unwrap(),
+            statement: Some("Sign in to Stillwater.".to_string()),
+            uri: "https://stillwater.example".to_string(),
+            version: "1".to_string(),
+            chain_id: 130,
+            nonce: "abc123".to_string(),
+            issued_at: DateTime::from_timestamp(now.timestamp(), 0).unwrap(),
+            expiration_time: Some(DateTime::from_timestamp(now.timestamp() + 600, 0).unwrap()),
+        }
+    }
+
+    fn sign(message: &str) -> String {
+        let signer: PrivateKeySigner = TEST_PRIVATE_KEY.parse().unwrap();
+        let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+        format!("0x{}", alloy::hex::encode(signature.as_bytes()))
+    }
+
+    #[test]
+    fn test_message_round_trips() {
+        let message = create_test_message(Utc::now());
+        assert_eq!(SiweMessage::parse(&message.to_message()).unwrap(), message);
+
+        let bare = SiweMessage { statement: None, expiration_time: None, ..message };
+        assert_eq!(SiweMessage::parse(&bare.to_message()).unwrap(), bare);
+    }
+
+    #[test]
+    fn test_parse_rejects_incomplete_messages() {
+        let text = create_test_message(Utc::now()).to_message();
+        assert!(SiweMessage::parse("").is_err());
+        assert!(SiweMessage::parse(&text.replace(SIWE_PREAMBLE, " wants a token")).is_err());
+        assert!(SiweMessage::parse(&text.replace("Nonce: abc123\n", "")).is_err());
+        assert!(SiweMessage::parse(&text.replace("Chain ID: 130", "Chain ID: x")).is_err());
+    }
+
+    #[test]
+    fn test_validate_accepts_matching_message() {
+        let now = Utc::now();
+        assert!(create_test_message(now).validate(&create_test_config(), now).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_other_domain() {
+        let now = Utc::now();
+        let message =
+            SiweMessage { domain: "evil.example".to_string(), ..create_test_message(now) };
+        assert!(message.validate(&create_test_config(), now).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_other_uri() {
+        let now = Utc::now();
+        let message =
+            SiweMessage { uri: "https://evil.example".to_string(), ..create_test_message(now) };
+        assert!(message.validate(&create_test_config(), now).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_other_chain() {
+        let now = Utc::now();
+        let message = SiweMessage { chain_id: 1, ..create_test_message(now) };
+        assert!(message.validate(&create_test_config(), now).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_expired_message() {
+        let now = Utc::now();
+        let message = create_test_message(now);
+        assert!(message.validate(&create_test_config(), now + Duration::minutes(11)).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_version() {
+        let now = Utc::now();
+        let message = SiweMessage { version: "2".to_string(), ..create_test_message(now) };
+        assert!(message.validate(&create_test_config(), now).is_err());
+    }
+
+    #[test]
+    fn test_recover_signer_of_signed_message() {
+        let text = create_test_message(Utc::now()).to_message();
+        let signer = recover_signer(&text, &sign(&text)).unwrap();
+        assert_eq!(signer, TEST_SIGNER.parse::<Address>().unwrap());
+    }
+
+    #[test]
+    fn test_recover_signer_of_altered_message_differs() {
+        let text = create_test_message(Utc::now()).to_message();
+        let altered = text.replace("Nonce: abc123", "Nonce: abc124");
+        let signer = recover_signer(&altered, &sign(&text)).unwrap();
+        assert_ne!(signer, TEST_SIGNER.parse::<Address>().unwrap());
+    }
+
+    #[test]
+    fn test_recover_signer_rejects_malformed_signature() {
+        assert!(recover_signer("hello", "0x1234").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use dotenv::dotenv;
+use reqwest::Client;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::time::Duration;
+use stillwater_alerts::{
+    BOT_HELP, BotCommand, BotMessage, TelegramBot, format_health, format_portfolio, format_position,
+};
+use stillwater_db::{get_telegram_chat, redeem_telegram_link_code};
+use stillwater_models::Address;
+use tracing::{error, info, warn};
+
+/// API the bot answers from unless `STILLWATER_API_URL` is set
+const DEFAULT_API_URL: &str = "http://127.0.0.1:3000";
+
+/// How long one `getUpdates` call waits for messages
+const POLL_TIMEOUT_SECONDS: u64 = 30;
+
+/// Pause after a failed poll before trying again
+const POLL_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interactive Telegram bot answering commands from the API
+///
+/// Usage: `cargo run --bin bot`. Polls `TELEGRAM_BOT_TOKEN`'s messages and
+/// answers `/portfolio`, `/position <nft_id>` and `/health` for the owner a
+/// chat follows (`/link <code>`, with a code from `POST
+/// /alerts/{owner}/telegram-link`), and `/mute`/`/unmute <rule_id>` for alert
+/// rules delivering to the chat, by calling the API at `STILLWATER_API_URL`
+/// (with `ADMIN_API_KEY` for the alert rule routes).
+/// `TELEGRAM_ALLOWED_CHATS` (comma-separated chat IDs) limits who it answers.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).init();
+
+    let bot_token = std::env::var("TELEGRAM_BOT_TOKEN").context("TELEGRAM_BOT_TOKEN is not set")?;
+    let api = Api {
+        client: Client::new(),
+        base: std::env::var("STILLWATER_API_URL")
+            .unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+            .trim_end_matches('/')
+            .to_string(),
+        admin_key: std::env::var("ADMIN_API_KEY").ok().filter(|key| !key.trim().is_empty()),
+    };
+    if api.admin_key.is_none() {
+        warn!("ADMIN_API_KEY is not set, /mute and /unmute will be refused by the API");
+    }
+    let allowed: Option<HashSet<String>> = std::env::var("TELEGRAM_ALLOWED_CHATS")
+        .ok()
+        .map(|chats| chats.split(',').map(|c| c.trim().to_string()).collect());
+    let alert_chat = std::env::var("TELEGRAM_CHAT_ID").ok();
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    let mut bot = TelegramBot::new(&bot_token);
+    info!("Telegram bot answering from {}", api.base);
+    loop {
+        let messages = match bot.poll(POLL_TIMEOUT_SECONDS).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                warn!("{}", e);
+                tokio::time::sleep(POLL_RETRY_INTERVAL).await;
+                continue;
+            }
+        };
+
+        for message in messages {
+            if allowed.as_ref().is_some_and(|allowed| !allowed.contains(&message.chat_id)) {
+                warn!(
+                    "Ignoring message from chat {}, not in TELEGRAM_ALLOWED_CHATS",
+                    message.chat_id
+                );
+                continue;
+            }
+            let Some(command) = BotCommand::parse(&message.text) else {
+                continue;
+            };
+            let reply = match command {
+                Ok(command) => {
+                    answer(&db_pool, &api, alert_chat.as_deref(), &message, command).await
+                }
+                Err(usage) => usage,
+            };
+            if let Err(e) = bot.reply(&message.chat_id, &reply).await {
+                error!("Failed to reply to chat {}: {}", message.chat_id, e);
+            }
+        }
+    }
+}
+
+/// The API, answering with its JSON or the error to show in chat
+struct Api {
+    client: Client,
+    base: String,
+    /// Sent to the `/admin` routes only
+    admin_key: Option<String>,
+}
+
+impl Api {
+    async fn get(&self, path: &str) -> Result<Value, String> {
+        self.send(self.client.get(format!("{}{}", self.base, path))).await
+    }
+
+    async fn admin_get(&self, path: &str) -> Result<Value, String> {
+        self.send(self.admin(self.client.get(format!("{}{}", self.base, path)))).await
+    }
+
+    async fn admin_put(&self, path: &str, body: &Value) -> Result<Value, String> {
+        self.send(self.admin(self.client.put(format!("{}{}", self.base, path)).json(body))).await
+    }
+
+    fn admin(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.admin_key {
+            Some(key) => request.bearer_auth(key.trim()),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value, String> {
+        let response = request.send().await.map_err(|e| {
+            error!("API request failed: {}", e);
+            "Stillwater is unavailable, try again later".to_string()
+        })?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if status.is_success() {
+            Ok(body)
+        } else {
+            Err(body["error"]
+                .as_str()
+                .map_or_else(|| format!("Request failed ({})", status), String::from))
+        }
+    }
+}
+
+/// Reply to a command from a chat
+async fn answer(
+    db_pool: &PgPool,
+    api: &Api,
+    alert_chat: Option<&str>,
+    message: &BotMessage,
+    command: BotCommand,
+) -> String {
+    let owner = match &command {
+        BotCommand::Portfolio | BotCommand::Position(_) | BotCommand::Health => {
+            match get_telegram_chat(db_pool, &message.chat_id).await {
+                Ok(Some(chat)) => chat.owner,
+                Ok(None) => return "Link an owner first: /link <code>".to_string(),
+                Err(e) => {
+                    error!("Failed to get Telegram chat {}: {}", message.chat_id, e);
+                    return "Something went wrong, try again later".to_string();
+                }
+            }
+        }
+        _ => Address::ZERO,
+    };
+
+    let reply = match command {
+        BotCommand::Help => Ok(BOT_HELP.to_string()),
+        BotCommand::Link(code) => link(db_pool, &message.chat_id, &code).await,
+        BotCommand::Portfolio => {
+            api.get(&format!("/portfolio/{}/totals", owner)).await.map(|t| format_portfolio(&t))
+        }
+        BotCommand::Position(nft_id) => {
+            api.get(&format!("/positions/{}/{}", owner, nft_id)).await.map(|p| format_position(&p))
+        }
+        BotCommand::Health => health(api, &owner).await,
+        BotCommand::Mute(id) => {
+            set_rule_enabled(api, alert_chat, &message.chat_id, id, false).await
+        }
+        BotCommand::Unmute(id) => {
+            set_rule_enabled(api, alert_chat, &message.chat_id, id, true).await
+        }
+    };
+    reply.unwrap_or_else(|e| e)
+}
+
+/// Follow the owner a link code was issued for
+///
+/// Codes come from `POST /alerts/{owner}/telegram-link` with the owner's API
+/// key, so only someone in control of the owner can link a chat to it.
+async fn link(db_pool: &PgPool, chat_id: &str, code: &str) -> Result<String, String> {
+    match redeem_telegram_link_code(db_pool, code, chat_id).await {
+        Ok(Some(chat)) => {
+            info!("Telegram chat {} now follows {}", chat_id, chat.owner);
+            Ok(format!("This chat now follows {}", chat.owner))
+        }
+        Ok(None) => Err("That link code is unknown, used or expired".to_string()),
+        Err(e) => {
+            error!("Failed to link Telegram chat {}: {}", chat_id, e);
+            Err("Something went wrong, try again later".to_string())
+        }
+    }
+}
+
+/// Health of the owner's open positions, one line each
+async fn health(api: &Api, owner: &Address) -> Result<String, String> {
+    let health = api.get(&format!("/portfolio/{}/health", owner)).await?;
+    let positions = health["positions"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if positions.is_empty() {
+        return Ok(format!("{} has no open positions", owner));
+    }
+
+    let mut lines = vec![format!("Health of {}'s open positions", owner)];
+    lines.extend(positions.iter().map(format_health));
+    Ok(lines.join("\n"))
+}
+
+/// Disable or enable an alert rule, if it notifies this chat
+///
+/// A rule notifies the chat when one of its sinks is the chat, or when it has
+/// none and the chat is `TELEGRAM_CHAT_ID`.
+async fn set_rule_enabled(
+    api: &Api,
+    alert_chat: Option<&str>,
+    chat_id: &str,
+    id: i64,
+    enabled: bool,
+) -> Result<String, String> {
+    let path = format!("/admin/alert-rules/{}", id);
+    let mut rule = api.admin_get(&path).await?;
+    let sinks: Vec<&str> =
+        rule["sinks"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+    let notifies_chat = if sinks.is_empty() {
+        alert_chat == Some(chat_id)
+    } else {
+        sinks.contains(&format!("telegram:{}", chat_id).as_str())
+    };
+    if !notifies_chat {
+        return Err(format!("Alert rule {} doesn't notify this chat", id));
+    }
+
+    rule["enabled"] = Value::Bool(enabled);
+    api.admin_put(&path, &rule).await?;
+    let action = if enabled { "unmuted" } else { "muted" };
+    info!("Chat {} {} alert rule {}", chat_id, action, id);
+    let condition = rule["condition"].as_str().unwrap_or_default();
+    Ok(format!("Alert rule {} ({}) {}", id, condition, action))
+}
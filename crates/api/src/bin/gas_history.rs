@@ -0,0 +1,44 @@
+use anyhow::{Context, Result, bail};
+use dotenv::dotenv;
+use stillwater_indexer::{GAS_HISTORY_STEP_BLOCKS, backfill_gas_prices};
+use stillwater_models::BlockchainService;
+use tracing::info;
+
+/// Backfill the chain's gas price history, for backtests replaying past windows
+///
+/// Usage: `cargo run --bin gas-history -- <from_block> [step_blocks]`. Every
+/// `step_blocks`-th block (default 300) up to the current one is sampled;
+/// `watch` keeps the history going from there.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).init();
+
+    let usage = "Usage: gas-history <from_block> [step_blocks]";
+    let mut args = std::env::args().skip(1);
+    let from_block: u64 =
+        args.next().context(usage)?.trim().parse().context("from_block must be a block number")?;
+    let step = match args.next() {
+        Some(step) => step.trim().parse().context("step_blocks must be a number")?,
+        None => GAS_HISTORY_STEP_BLOCKS,
+    };
+    if step == 0 {
+        bail!("step_blocks must be positive");
+    }
+
+    let rpc_url = std::env::var("ETHEREUM_RPC_URL").context("ETHEREUM_RPC_URL must be set")?;
+    let blockchain = BlockchainService::new(&rpc_url)?;
+    let chain_id = blockchain.get_chain_id().await.context("Failed to read the RPC's chain ID")?;
+    let to_block = blockchain.get_block_number().await?;
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    info!("Sampling gas prices of chain {} from block {} to {}", chain_id, from_block, to_block);
+    let stored =
+        backfill_gas_prices(&db_pool, &blockchain, chain_id, from_block, to_block, step).await?;
+    info!("Stored {} gas price samples", stored);
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use dotenv::dotenv;
+use std::fs::File;
+use stillwater_indexer::{parse_positions_csv, store_positions};
+use tracing::{info, warn};
+
+/// Import legacy positions from a CSV file as `manual` positions
+///
+/// Usage: `cargo run --bin import -- positions.csv`
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).init();
+
+    let path = std::env::args().nth(1).context("Usage: import <positions.csv>")?;
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path))?;
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    let parsed = parse_positions_csv(file);
+    info!("Parsed {} valid rows from {}", parsed.positions.len(), path);
+
+    let report = store_positions(&db_pool, parsed).await?;
+    for err in &report.errors {
+        warn!("Line {}: {}", err.line, err.message);
+    }
+
+    info!(
+        "Imported {} positions ({} already present, {} rejected)",
+        report.imported,
+        report.duplicates,
+        report.errors.len()
+    );
+
+    Ok(())
+}
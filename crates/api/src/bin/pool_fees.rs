@@ -0,0 +1,48 @@
+use anyhow::{Context, Result, anyhow, bail};
+use dotenv::dotenv;
+use stillwater_analytics::MAX_LP_FEE;
+use stillwater_db::{delete_pool_fee_override, get_pool_fee_overrides, set_pool_fee_override};
+
+const USAGE: &str = "Usage: pool-fees list | set <pool_id> <fee> [note] | clear <pool_id>";
+
+/// Manage pool fee overrides, like the `/admin/pool-fees` endpoints
+///
+/// Usage: `cargo run --bin pool-fees -- <command>`. `fee` is in hundredths of
+/// a bip (3000 = 0.3%); analytics use it wherever the pool's fee can't be
+/// resolved from its swaps.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    match args.as_slice() {
+        ["list"] => {
+            let overrides = get_pool_fee_overrides(&db_pool).await?;
+            println!("{}", serde_json::to_string_pretty(&overrides)?);
+        }
+        ["set", pool_id, fee] | ["set", pool_id, fee, _] => {
+            let fee: i32 = fee.parse().context("Fee must be a whole number")?;
+            if !(0..=MAX_LP_FEE).contains(&fee) {
+                bail!("Fee must be between 0 and {} (hundredths of a bip)", MAX_LP_FEE);
+            }
+            let fee_override = set_pool_fee_override(&db_pool, pool_id, fee, args.get(3).copied())
+                .await?
+                .ok_or_else(|| anyhow!("No pool {}", pool_id))?;
+            println!("{}", serde_json::to_string_pretty(&fee_override)?);
+        }
+        ["clear", pool_id] => {
+            if !delete_pool_fee_override(&db_pool, pool_id).await? {
+                bail!("No fee override for pool {}", pool_id);
+            }
+            println!("Cleared fee override for pool {}", pool_id);
+        }
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anyhow::{Context, Result, bail};
+use dotenv::dotenv;
+use std::io::Read;
+use stillwater_db::ReadOnlyDb;
+
+/// Run an ad hoc read-only query with the same guardrails as `POST /query`
+///
+/// Usage: `cargo run --bin query -- [--csv] "SELECT ..."` (reads the query
+/// from stdin when none is given). Prints JSON, or CSV with `--csv`.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    let mut csv = false;
+    let mut sql = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--csv" => csv = true,
+            _ if sql.is_none() => sql = Some(arg),
+            _ => bail!("Usage: query [--csv] [\"SELECT ...\"]"),
+        }
+    }
+    let sql = match sql {
+        Some(sql) => sql,
+        None => {
+            let mut sql = String::new();
+            std::io::stdin().read_to_string(&mut sql).context("Failed to read query from stdin")?;
+            sql
+        }
+    };
+
+    let query_db = ReadOnlyDb::from_env().await?.context(
+        "QUERY_DATABASE_URL must be set to a login in the role from docker/query_role.sql",
+    )?;
+    let result = query_db.run(&sql).await?;
+
+    if csv {
+        print!("{}", result.to_csv()?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    }
+    if result.truncated {
+        eprintln!("Results truncated to {} rows", query_db.max_rows);
+    }
+
+    Ok(())
+}
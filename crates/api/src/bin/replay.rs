@@ -0,0 +1,88 @@
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use stillwater_analytics::{FeeModelRegistry, PnlHistory, replay_position};
+use stillwater_db::{
+    get_gas_expenses_for_position, get_pool_by_id, get_position_by_nft, get_snapshots_for_position,
+    get_swaps_for_pool_between,
+};
+
+const USAGE: &str = "Usage: replay <nft_id> [--from <rfc3339>] [--to <rfc3339>] [--out <file>]";
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .with_context(|| format!("Invalid timestamp {} (expected RFC 3339)", value))
+}
+
+/// Replay a position's analytics over a recorded window and dump every step as JSON
+///
+/// Usage: `cargo run --bin replay -- <nft_id> [--from T] [--to T] [--out file]`.
+/// `from` defaults to the position's creation and `to` to its close, or now
+/// while it's open. The trace lists per-swap fee shares, snapshot ticks and IL,
+/// and gas, with the P&L at both ends; it's printed unless `--out` is given.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((nft_id, mut flags)) = args.split_first() else {
+        bail!(USAGE);
+    };
+    let (mut from, mut to, mut out) = (None, None, None);
+    while let Some((flag, rest)) = flags.split_first() {
+        let Some((value, rest)) = rest.split_first() else {
+            bail!(USAGE);
+        };
+        match flag.as_str() {
+            "--from" => from = Some(parse_time(value)?),
+            "--to" => to = Some(parse_time(value)?),
+            "--out" => out = Some(value.clone()),
+            _ => bail!(USAGE),
+        }
+        flags = rest;
+    }
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    let position = get_position_by_nft(&db_pool, nft_id)
+        .await?
+        .ok_or_else(|| anyhow!("No position {}", nft_id))?;
+    let pool = get_pool_by_id(&db_pool, &position.pool_id)
+        .await?
+        .ok_or_else(|| anyhow!("No pool {}", position.pool_id))?;
+
+    let from = from.unwrap_or(position.created_at);
+    let to = to.or(position.closed_at).unwrap_or_else(Utc::now);
+    if from > to {
+        bail!("--from must not be after --to");
+    }
+
+    let swaps =
+        get_swaps_for_pool_between(&db_pool, &position.pool_id, position.created_at, to).await?;
+    let snapshots =
+        get_snapshots_for_position(&db_pool, position.id, position.created_at, to).await?;
+    let gas = get_gas_expenses_for_position(&db_pool, position.id, to).await?;
+
+    let fee_models = FeeModelRegistry::from_env();
+    let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &gas };
+    let trace = replay_position(&position, &pool, fee_models.model_for(&pool), &history, from, to)?;
+    let json = serde_json::to_string_pretty(&trace)?;
+
+    match out {
+        Some(path) => {
+            std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path))?;
+            println!(
+                "Wrote {} swaps, {} prices and {} gas steps to {}",
+                trace.swaps.len(),
+                trace.prices.len(),
+                trace.gas.len(),
+                path
+            );
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result, anyhow, bail};
+use dotenv::dotenv;
+use sqlx::PgPool;
+use std::io::Read;
+use stillwater_alerts::{AlertDispatcher, validate_rule};
+use stillwater_db::{
+    create_alert_rule, delete_alert_rule, get_alert_rule, get_alert_rules, update_alert_rule,
+};
+use stillwater_models::{AlertRule, AlertRuleSpec};
+
+const USAGE: &str = "Usage: rules list [owner] | add [json] | update <id> [json] | \
+                     delete <id> | enable <id> | disable <id> | test <id> [--dry-run]";
+
+/// Manage alert rules, like the `/admin/alert-rules` endpoints
+///
+/// Usage: `cargo run --bin rules -- <command>`. `add` and `update` take a
+/// rule as JSON (`{"condition": "compound", "owner": "0x...", ...}`), read
+/// from stdin when not given. Running jobs pick up changes on their next reload.
+/// `test` sends a sample alert for the rule's condition to its sinks (or, with
+/// `--dry-run`, prints it and the sinks without sending).
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    match args.as_slice() {
+        ["list"] | ["list", _] => {
+            let rules = get_alert_rules(&db_pool, args.get(1).copied()).await?;
+            println!("{}", serde_json::to_string_pretty(&rules)?);
+        }
+        ["add"] | ["add", _] => {
+            let spec = read_spec(args.get(1).copied())?;
+            print_rule(&create_alert_rule(&db_pool, &spec).await?)?;
+        }
+        ["update", id] | ["update", id, _] => {
+            let spec = read_spec(args.get(2).copied())?;
+            let rule = update_alert_rule(&db_pool, parse_id(id)?, &spec).await?;
+            print_rule(&rule.ok_or_else(|| anyhow!("No alert rule {}", id))?)?;
+        }
+        ["delete", id] => {
+            if !delete_alert_rule(&db_pool, parse_id(id)?).await? {
+                bail!("No alert rule {}", id);
+            }
+            println!("Deleted alert rule {}", id);
+        }
+        ["enable", id] => set_enabled(&db_pool, parse_id(id)?, true).await?,
+        ["disable", id] => set_enabled(&db_pool, parse_id(id)?, false).await?,
+        ["test", id] => test_rule(&db_pool, parse_id(id)?, false).await?,
+        ["test", id, "--dry-run"] => test_rule(&db_pool, parse_id(id)?, true).await?,
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
+
+fn parse_id(id: &str) -> Result<i64> {
+    id.parse().context("Rule id must be a number")
+}
+
+/// Parse and validate a rule from the argument, or stdin without one
+fn read_spec(json: Option<&str>) -> Result<AlertRuleSpec> {
+    let json = match json {
+        Some(json) => json.to_string(),
+        None => {
+            let mut json = String::new();
+            std::io::stdin().read_to_string(&mut json).context("Failed to read rule from stdin")?;
+            json
+        }
+    };
+    let spec: AlertRuleSpec = serde_json::from_str(&json).context("Invalid alert rule JSON")?;
+    validate_rule(&spec).map_err(|e| anyhow!("Invalid alert rule: {}", e))?;
+    Ok(spec)
+}
+
+async fn set_enabled(db_pool: &PgPool, id: i64, enabled: bool) -> Result<()> {
+    let mut rule =
+        get_alert_rule(db_pool, id).await?.ok_or_else(|| anyhow!("No alert rule {}", id))?;
+    rule.spec.enabled = enabled;
+    let rule = update_alert_rule(db_pool, id, &rule.spec).await?;
+    print_rule(&rule.ok_or_else(|| anyhow!("No alert rule {}", id))?)
+}
+
+async fn test_rule(db_pool: &PgPool, id: i64, dry_run: bool) -> Result<()> {
+    let rule = get_alert_rule(db_pool, id).await?.ok_or_else(|| anyhow!("No alert rule {}", id))?;
+    let test = AlertDispatcher::from_env().test_rule(db_pool, &rule, dry_run).await?;
+
+    println!("{}\n\n{}\n", test.alert.title, test.alert.message);
+    if test.deliveries.is_empty() {
+        bail!("Alert rule {} has no sinks and none are configured", id);
+    }
+    for delivery in &test.deliveries {
+        match &delivery.error {
+            Some(e) => println!("{}: failed: {}", delivery.sink, e),
+            None if dry_run => println!("{}: not sent (dry run)", delivery.sink),
+            None => println!("{}: delivered", delivery.sink),
+        }
+    }
+    if test.deliveries.iter().any(|d| d.error.is_some()) {
+        bail!("Test alert failed for some sinks");
+    }
+    Ok(())
+}
+
+fn print_rule(rule: &AlertRule) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(rule)?);
+    Ok(())
+}
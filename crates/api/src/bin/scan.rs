@@ -0,0 +1,51 @@
+use alloy::primitives::Address;
+use anyhow::{Context, Result};
+use dotenv::dotenv;
+use stillwater_indexer::scan_wallet;
+use stillwater_models::BlockchainService;
+use tracing::{info, warn};
+
+/// Seed a wallet's open positions from the v4 PositionManager, without the subgraph
+///
+/// Usage: `cargo run --bin scan -- <wallet> [from_block]`. The start block
+/// defaults to `POSITION_MANAGER_DEPLOY_BLOCK` (or 0).
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).init();
+
+    let usage = "Usage: scan <wallet> [from_block]";
+    let mut args = std::env::args().skip(1);
+    let wallet: Address = args.next().context(usage)?.parse().context("Invalid wallet address")?;
+    let from_block =
+        match args.next().or_else(|| std::env::var("POSITION_MANAGER_DEPLOY_BLOCK").ok()) {
+            Some(block) => block.trim().parse().context("from_block must be a block number")?,
+            None => 0,
+        };
+
+    let rpc_url = std::env::var("ETHEREUM_RPC_URL").context("ETHEREUM_RPC_URL must be set")?;
+    let position_manager: Address = std::env::var("POSITION_MANAGER_ADDRESS")
+        .context("POSITION_MANAGER_ADDRESS must be set")?
+        .parse()
+        .context("Invalid POSITION_MANAGER_ADDRESS")?;
+    let blockchain = BlockchainService::new(&rpc_url)?;
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+
+    let report = scan_wallet(&db_pool, &blockchain, position_manager, wallet, from_block).await?;
+    for skip in &report.skipped {
+        warn!("Token {}: {}", skip.token_id, skip.reason);
+    }
+
+    info!(
+        "Imported {} positions ({} already present, {} skipped, {} pools added)",
+        report.imported,
+        report.already_known,
+        report.skipped.len(),
+        report.pools_added
+    );
+
+    Ok(())
+}
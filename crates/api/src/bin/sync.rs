@@ -1,8 +1,56 @@
+use alloy::primitives::{Address, B256};
 use anyhow::Result;
+use chrono::{Datelike, Duration, Months, NaiveTime, Utc};
+use std::collections::HashMap;
 use dotenv::dotenv;
+use rust_decimal::Decimal;
 use sqlx::PgPool;
-use stillwater_indexer::GraphIndexer;
-use tracing::{error, info};
+use stillwater_alerts::{
+    apply_alert_template, format_pool_param, AlertDispatcher, AlertRules, AlertVariables,
+};
+use stillwater_analytics::{
+    advance_accumulator, check_accumulator, check_swap_quality, fee_velocity_trend,
+    recommend_compound, unclaimed_fees, CompoundConfig, FeeModelRegistry, FeeVelocityConfig,
+    encode_tick_series, is_minute_aggregate, plan_downsampling, tick_points_from_swaps,
+    GasAccounting, QualityConfig, RetentionPolicy,
+};
+use stillwater_db::{
+    archive_stale_positions, archive_swaps_before, find_positions, get_fee_accumulators,
+    get_fee_accumulators_to_verify, get_alerting_open_positions, get_gas_expenses_for_position, get_pool_by_id, get_pool_ids,
+    get_days_to_downsample, get_hot_swaps_for_pool_between, get_months_to_compress,
+    get_position_by_id, get_retention_horizon, get_snapshots_for_position,
+    get_swaps_for_pool, get_swaps_for_pool_after_id, get_swaps_for_pool_between,
+    get_swap_minute_counts, get_swaps_for_pool_by_insertion, get_sync_schedule,
+    insert_pool_fee_growth, insert_quality_issues, insert_sync_run,
+    price_liquidity_events, purge_auth_nonces, purge_deleted_positions, purge_snapshots_before,
+    purge_swaps_before, purge_telegram_link_codes,
+    replace_swaps_with_aggregates, save_tick_chunk,
+    update_pool_fees, upsert_fee_accumulator, PositionFilter,
+};
+use stillwater_indexer::{reconcile_checkpoints, record_checkpoint, GraphIndexer};
+use stillwater_models::{
+    Alert, AlertKind, AlertSeverity, BlockchainService, FeeAccumulator, PoolParam,
+    PoolParamChange, Position, RetainedData, Swap, SyncRun,
+};
+use tracing::{error, info, warn};
+
+/// How far back the data quality job re-checks swaps
+const DATA_QUALITY_LOOKBACK_DAYS: i64 = 7;
+
+/// Swaps older than this many days are moved to the archive unless `SWAP_ARCHIVE_AFTER_DAYS` is set
+const DEFAULT_SWAP_ARCHIVE_AFTER_DAYS: i64 = 90;
+
+/// Positions closed this many days ago are archived unless `POSITION_ARCHIVE_AFTER_DAYS` is set
+const DEFAULT_POSITION_ARCHIVE_AFTER_DAYS: i64 = 90;
+
+/// Redis key the API's cache warmer watches (see `cache::SYNC_COMPLETED_KEY`)
+const SYNC_COMPLETED_KEY: &str = "stillwater:sync:completed_at";
+
+/// How far back each sync re-reads NFT transfers (already recorded ones are skipped)
+const TRANSFER_LOOKBACK_HOURS: i64 = 24;
+
+/// Fee accumulators checked against a full recomputation per sync
+const ACCUMULATOR_VERIFY_BATCH: i64 = 20;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -15,23 +63,85 @@ async fn main() -> Result<()> {
 
     info!("Starting position sync from The Graph...");
 
-    // Get database URL
-    let database_url =
-        std::env::var("DATABASE_URL").expect("DATABASE_URL must be set in environment");
-
-    // Connect to database
-    let db_pool = PgPool::connect(&database_url).await.expect("Failed to connect to database");
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.expect("Failed to connect to database");
 
     info!("Connected to database");
 
+    // Operators pause scheduled syncs through `PUT /admin/sync/schedule`; `--force` runs anyway
+    let force = std::env::args().skip(1).any(|arg| arg == "--force");
+    match get_sync_schedule(&db_pool).await {
+        Ok(schedule) if schedule.paused && !force => {
+            info!(
+                "Scheduled syncs paused since {} ({}), skipping",
+                schedule.updated_at,
+                schedule.reason.as_deref().unwrap_or("no reason given")
+            );
+            return Ok(());
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to check the sync schedule: {}", e),
+    }
+
     // Create indexer
-    let indexer = GraphIndexer::from_env()
+    let mut indexer = GraphIndexer::from_env()
         .expect("Failed to create GraphIndexer. Ensure GRAPH_API_URL is set");
 
+    // `--fresh` skips GRAPH_CACHE, e.g. after redeploying a subgraph that's still at the same block
+    if std::env::args().skip(1).any(|arg| arg == "--fresh") {
+        info!("Bypassing the subgraph response cache");
+        indexer = indexer.without_cache();
+    }
+
     info!("Indexer initialized with Graph API URL");
 
-    // Sync positions
-    match indexer.sync_positions(&db_pool).await {
+    // Price the gas of synced liquidity events from their receipts, L1 data fees included
+    if let Ok(rpc_url) = std::env::var("ETHEREUM_RPC_URL") {
+        let accounting = GasAccounting::from_env()?;
+        info!("Recording gas of liquidity events with {} accounting", accounting.as_str());
+        indexer = indexer.with_gas_tracking(BlockchainService::new(&rpc_url)?, accounting);
+    }
+
+    // Skip endpoints that are down or trailing the others
+    for health in indexer.check_endpoint_lag().await {
+        match (health.indexed_block, health.lagging) {
+            (Some(block), false) => info!("Endpoint {} at block {}", health.url, block),
+            (Some(block), true) => warn!("Endpoint {} lagging at block {}", health.url, block),
+            (None, _) => warn!("Endpoint {} unavailable: {:?}", health.url, health.last_error),
+        }
+    }
+
+    // Roll back past any reorged block before resuming
+    let resume_from = match reconcile_checkpoints(&indexer, &db_pool).await {
+        Ok(reconciliation) => reconciliation.resume_from,
+        Err(e) => {
+            warn!("Failed to verify sync checkpoints: {}", e);
+            None
+        }
+    };
+
+    // The head block this sync runs up to, recorded as a checkpoint once it succeeds
+    let head = match indexer.fetch_head_block().await {
+        Ok(head) => Some(head),
+        Err(e) => {
+            warn!("Failed to fetch the subgraph head block: {}", e);
+            None
+        }
+    };
+
+    // Sync positions, timing each stage
+    let mut run = SyncRun::start();
+    let result = indexer.sync_positions(&db_pool, &mut run).await;
+    run.finish(result.as_ref().err().map(|e| e.to_string()));
+    info!(
+        "Sync stages: fetch {}ms, parse {}ms, dedupe {}ms, insert {}ms (total {}ms)",
+        run.fetch_ms, run.parse_ms, run.dedupe_ms, run.insert_ms, run.total_ms()
+    );
+    if let Err(e) = insert_sync_run(&db_pool, &run).await {
+        warn!("Failed to record sync run: {}", e);
+    }
+
+    match result {
         Ok(count) => {
             info!("✓ Successfully synced {} positions", count);
         }
@@ -41,8 +151,713 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Move transferred position NFTs to their new owners
+    let lookback = Utc::now() - Duration::hours(TRANSFER_LOOKBACK_HOURS);
+    let transfers_since = lookback.min(resume_from.unwrap_or(Utc::now()));
+    match indexer.sync_transfers(&db_pool, transfers_since).await {
+        Ok(count) => info!("Synced {} position transfers", count),
+        Err(e) => error!("Failed to sync position transfers: {}", e),
+    }
+
+    // Sync recent swaps for every known pool, several pools per subgraph request,
+    // going back to the rollback point after a reorg
+    let since = (Utc::now() - Duration::hours(1)).min(resume_from.unwrap_or(Utc::now()));
+    match get_pool_ids(&db_pool).await {
+        Ok(pool_ids) => match indexer.sync_swaps_for_pools_since(&db_pool, &pool_ids, since).await {
+            Ok(count) => {
+                info!("Synced {} swaps across {} pools", count, pool_ids.len());
+                if let Some(head) = &head {
+                    match record_checkpoint(&db_pool, head).await {
+                        Ok(true) => info!("Checkpointed block {}", head.number),
+                        Ok(false) => info!("Subgraph reports no block hash, not checkpointing"),
+                        Err(e) => warn!("Failed to record sync checkpoint: {}", e),
+                    }
+                }
+            }
+            Err(e) => error!("Failed to sync swaps: {}", e),
+        },
+        Err(e) => error!("Failed to load pools for swap sync: {}", e),
+    }
+
+    // Price new deposits from the swaps around them, for weighted entry prices
+    match price_liquidity_events(&db_pool).await {
+        Ok(count) => info!("Priced {} liquidity additions", count),
+        Err(e) => error!("Failed to price liquidity additions: {}", e),
+    }
+
+    // Fold the new swaps into running fee totals, then spot-check a few from scratch
+    let fee_models = FeeModelRegistry::from_env();
+    let accumulators_current = match update_fee_accumulators(&db_pool, &fee_models).await {
+        Ok(count) => {
+            info!("Updated fee accumulators of {} positions", count);
+            true
+        }
+        Err(e) => {
+            error!("Failed to update fee accumulators: {}", e);
+            false
+        }
+    };
+    match verify_fee_accumulators(&db_pool, &fee_models).await {
+        Ok((checked, 0)) => info!("Verified {} fee accumulators", checked),
+        Ok((checked, drifted)) => {
+            warn!("Corrected {} of {} verified fee accumulators", drifted, checked)
+        }
+        Err(e) => error!("Failed to verify fee accumulators: {}", e),
+    }
+
+    // The subgraph doesn't expose current LP and protocol fees; read them from pool state
+    let param_changes = match refresh_pool_fees(&db_pool).await {
+        Ok(Some((count, changes))) => {
+            info!("Refreshed fees of {} pools, {} changed", count, changes.len());
+            changes
+        }
+        Ok(None) => {
+            info!("STATE_VIEW_ADDRESS not set, skipping pool fee refresh");
+            vec![]
+        }
+        Err(e) => {
+            error!("Failed to refresh pool fees: {}", e);
+            vec![]
+        }
+    };
+
+    // Sample fee growth globals so attributed fees can be reconciled against them
+    match record_fee_growth(&db_pool).await {
+        Ok(Some(count)) => info!("Sampled fee growth of {} pools", count),
+        Ok(None) => info!("STATE_VIEW_ADDRESS not set, skipping fee growth sampling"),
+        Err(e) => error!("Failed to sample fee growth: {}", e),
+    }
+
+    // Retry alerts whose earlier delivery failed
+    let dispatcher = AlertDispatcher::from_env();
+    match dispatcher.retry_pending(&db_pool).await {
+        Ok(count) => info!("Retried pending alerts, {} delivered", count),
+        Err(e) => error!("Failed to retry pending alerts: {}", e),
+    }
+
+    // Tell owners when their pools' fees changed under them
+    if !param_changes.is_empty() {
+        match alert_pool_param_changes(&db_pool, &dispatcher, &param_changes).await {
+            Ok(count) => info!("Alerted {} positions to pool fee changes", count),
+            Err(e) => error!("Failed to alert pool fee changes: {}", e),
+        }
+    }
+
+    // Tell owners when unclaimed fees are worth compounding
+    match check_compound_opportunities(&db_pool, &dispatcher).await {
+        Ok(Some(count)) => info!("Recommended compounding {} positions", count),
+        Ok(None) => info!("COMPOUND_GAS_COST not set, skipping compound recommendations"),
+        Err(e) => error!("Failed to check compound opportunities: {}", e),
+    }
+
+    // Warn owners whose positions stopped earning at their usual rate
+    match check_fee_velocity_drops(&db_pool, &dispatcher).await {
+        Ok(count) => info!("Flagged {} positions with falling fee velocity", count),
+        Err(e) => error!("Failed to check fee velocity: {}", e),
+    }
+
+    // Scan freshly synced swaps for anomalies
+    match check_data_quality(&db_pool).await {
+        Ok(count) => info!("Data quality check recorded {} new issues", count),
+        Err(e) => error!("Data quality check failed: {}", e),
+    }
+
+    // Fold finished days of high-volume pools into minute aggregates, once accumulators
+    // have counted their swaps
+    if accumulators_current {
+        match downsample_swaps(&db_pool).await {
+            Ok(0) => {}
+            Ok(count) => info!("Downsampled {} pool-days of swaps", count),
+            Err(e) => error!("Failed to downsample swaps: {}", e),
+        }
+    } else {
+        warn!("Skipping swap downsampling until fee accumulators are up to date");
+    }
+
+    // Compress finished months of swap ticks before archiving and retention touch them
+    match compress_tick_history(&db_pool).await {
+        Ok(count) => info!("Compressed {} months of tick history", count),
+        Err(e) => error!("Failed to compress tick history: {}", e),
+    }
+
+    // Move old swaps to the archive tier so the hot table stays small
+    match archive_old_swaps(&db_pool).await {
+        Ok(Some(count)) => info!("Archived {} swaps", count),
+        Ok(None) => info!("Swap archiving disabled"),
+        Err(e) => error!("Failed to archive swaps: {}", e),
+    }
+
+    // Take long-closed positions out of default listings
+    match archive_closed_positions(&db_pool).await {
+        Ok(Some(count)) => info!("Archived {} closed positions", count),
+        Ok(None) => info!("Position archiving disabled"),
+        Err(e) => error!("Failed to archive closed positions: {}", e),
+    }
+
+    // Delete raw data past its retention horizon
+    if let Err(e) = apply_retention(&db_pool).await {
+        error!("Failed to apply retention policy: {}", e);
+    }
+
+    // Let the API warm its cache from the fresh data
+    if let Err(e) = mark_sync_completed().await {
+        warn!("Failed to record sync completion for cache warming: {}", e);
+    }
+
     info!("Sync completed successfully!");
     println!("=== Euphoria Sync Complete ===");
 
     Ok(())
 }
+
+/// Fold swaps recorded since each position's accumulator last advanced into its totals
+///
+/// Each pool's new swaps are read once, from the lowest `last_swap_id` among
+/// its positions; positions without an accumulator start from their full
+/// history. Returns how many accumulators changed.
+async fn update_fee_accumulators(
+    db_pool: &PgPool,
+    fee_models: &FeeModelRegistry,
+) -> Result<usize> {
+    let positions = find_positions(db_pool, &PositionFilter::default()).await?;
+    let ids: Vec<i64> = positions.iter().map(|p| p.id).collect();
+    let mut accumulators: HashMap<i64, FeeAccumulator> = get_fee_accumulators(db_pool, &ids)
+        .await?
+        .into_iter()
+        .map(|a| (a.position_id, a))
+        .collect();
+
+    let mut by_pool: HashMap<&str, Vec<&Position>> = HashMap::new();
+    for position in &positions {
+        by_pool.entry(position.pool_id.as_str()).or_default().push(position);
+    }
+
+    let now = Utc::now();
+    let mut updated = 0;
+    for (pool_id, positions) in by_pool {
+        let Some(pool) = get_pool_by_id(db_pool, pool_id).await? else {
+            continue;
+        };
+        let after_id = positions
+            .iter()
+            .map(|p| accumulators.get(&p.id).map_or(0, |a| a.last_swap_id))
+            .min()
+            .unwrap_or_default();
+        let since = positions.iter().map(|p| p.created_at).min().unwrap_or(now);
+        let swaps = get_swaps_for_pool_after_id(db_pool, pool_id, after_id, since).await?;
+        if swaps.is_empty() {
+            continue;
+        }
+
+        let model = fee_models.model_for(&pool);
+        for position in positions {
+            let accumulator = accumulators
+                .entry(position.id)
+                .or_insert_with(|| FeeAccumulator::empty(position.id));
+            let last_swap_id = accumulator.last_swap_id;
+            advance_accumulator(accumulator, position, &pool, &swaps, model, now);
+            if accumulator.last_swap_id != last_swap_id {
+                upsert_fee_accumulator(db_pool, accumulator).await?;
+                updated += 1;
+            }
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Recompute the least recently verified fee accumulators from full swap history
+///
+/// Accumulators that drifted are logged and replaced by the recomputed totals.
+/// Positions older than the swap retention horizon are skipped, since their
+/// history is gone. Returns how many were checked and how many had drifted.
+async fn verify_fee_accumulators(
+    db_pool: &PgPool,
+    fee_models: &FeeModelRegistry,
+) -> Result<(usize, usize)> {
+    let horizon = get_retention_horizon(db_pool, RetainedData::Swaps).await?;
+    let now = Utc::now();
+    let mut checked = 0;
+    let mut drifted = 0;
+
+    for mut accumulator in
+        get_fee_accumulators_to_verify(db_pool, horizon, ACCUMULATOR_VERIFY_BATCH).await?
+    {
+        let Some(position) = get_position_by_id(db_pool, accumulator.position_id).await? else {
+            continue;
+        };
+        let Some(pool) = get_pool_by_id(db_pool, &position.pool_id).await? else {
+            continue;
+        };
+        let swaps = get_swaps_for_pool(db_pool, &position.pool_id, position.created_at).await?;
+        let model = fee_models.model_for(&pool);
+
+        if let Some(drift) = check_accumulator(&accumulator, &position, &pool, &swaps, model) {
+            warn!(
+                "Fee accumulator of position {} drifted: {} fees over {} swaps, expected {} over {}",
+                position.id,
+                drift.fees_earned,
+                drift.swap_count,
+                drift.expected.fees_earned,
+                drift.expected.swap_count
+            );
+            accumulator = drift.expected;
+            drifted += 1;
+        }
+        accumulator.verified_at = Some(now);
+        upsert_fee_accumulator(db_pool, &accumulator).await?;
+        checked += 1;
+    }
+
+    Ok((checked, drifted))
+}
+
+/// Run the swap data quality checks for every pool and record new findings
+///
+/// Minute aggregates of downsampled pools aren't swaps and are skipped.
+async fn check_data_quality(db_pool: &PgPool) -> Result<u64> {
+    let since = Utc::now() - Duration::days(DATA_QUALITY_LOOKBACK_DAYS);
+    let config = QualityConfig::default();
+    let mut recorded = 0;
+
+    for pool_id in get_pool_ids(db_pool).await? {
+        let mut swaps = get_swaps_for_pool_by_insertion(db_pool, &pool_id, since).await?;
+        swaps.retain(|s| !is_minute_aggregate(s));
+        let issues = check_swap_quality(&pool_id, &swaps, &config);
+        if !issues.is_empty() {
+            warn!("Pool {}: {} data quality issues", pool_id, issues.len());
+        }
+        recorded += insert_quality_issues(db_pool, &issues).await?;
+    }
+
+    Ok(recorded)
+}
+
+/// Alert on open positions covered by a compound rule whose unclaimed fees cover
+/// the rule's threshold (`COMPOUND_GAS_MULTIPLE` without one) times the gas of
+/// compounding them, as often as the rule's cooldown allows
+///
+/// Returns `None` when `COMPOUND_GAS_COST` isn't configured.
+async fn check_compound_opportunities(
+    db_pool: &PgPool,
+    dispatcher: &AlertDispatcher,
+) -> Result<Option<usize>> {
+    let config = CompoundConfig::from_env()?;
+    let Some(gas_cost) = config.gas_cost_per_tx else {
+        return Ok(None);
+    };
+    let rules = AlertRules::load(db_pool).await?;
+    if !rules.has(AlertKind::Compound) {
+        return Ok(Some(0));
+    }
+
+    let fee_models = FeeModelRegistry::from_env();
+    let now = Utc::now();
+    let mut recommended = 0;
+    for position in get_alerting_open_positions(db_pool).await? {
+        let mut matching = rules.matching(AlertKind::Compound, &position).peekable();
+        if matching.peek().is_none() {
+            continue;
+        }
+        let snapshots =
+            get_snapshots_for_position(db_pool, position.id, position.created_at, now).await?;
+        let gas = get_gas_expenses_for_position(db_pool, position.id, now).await?;
+        let unclaimed = unclaimed_fees(&snapshots, &gas);
+
+        let mut fired = false;
+        for rule in matching {
+            let multiple = rule.spec.threshold.unwrap_or(config.gas_multiple);
+            let recommendation = recommend_compound(unclaimed, gas_cost, multiple);
+            if !recommendation.compound_now {
+                continue;
+            }
+
+            // Keyed by run, so rules firing together deliver once per sink
+            let mut alert = Alert {
+                key: format!("compound:{}:{}", position.id, now.timestamp()),
+                severity: AlertSeverity::Info,
+                title: "Compound now".to_string(),
+                message: format!(
+                    "Position {} in pool {} has {} in unclaimed fees, {}x the {} gas of \
+                     compounding",
+                    position.nft_id,
+                    position.pool_id,
+                    recommendation.unclaimed_fees,
+                    recommendation.fees_to_gas.map(|r| r.round_dp(1)).unwrap_or_default(),
+                    recommendation.compound_gas
+                ),
+                position_id: Some(position.id),
+                owner: Some(position.owner.canonical()),
+                pool_id: Some(position.pool_id.clone()),
+                created_at: now,
+            };
+            let variables = AlertVariables::Compound(&recommendation);
+            apply_alert_template(
+                db_pool,
+                &fee_models,
+                &mut alert,
+                AlertKind::Compound,
+                &position,
+                variables,
+            )
+            .await?;
+            fired |= dispatcher.dispatch_for_rule(db_pool, rule, position.id, &alert).await?;
+        }
+        if fired {
+            recommended += 1;
+        }
+    }
+
+    Ok(Some(recommended))
+}
+
+/// Alert on open positions covered by a `fee_velocity_drop` rule whose fee rate
+/// over the last `FEE_VELOCITY_RECENT_HOURS` fell by the rule's threshold
+/// (`FEE_VELOCITY_DROP` without one) against the `FEE_VELOCITY_BASELINE_DAYS` before
+async fn check_fee_velocity_drops(
+    db_pool: &PgPool,
+    dispatcher: &AlertDispatcher,
+) -> Result<usize> {
+    let config = FeeVelocityConfig::from_env()?;
+    let rules = AlertRules::load(db_pool).await?;
+    if !rules.has(AlertKind::FeeVelocityDrop) {
+        return Ok(0);
+    }
+
+    let fee_models = FeeModelRegistry::from_env();
+    let now = Utc::now();
+    let since = now - Duration::hours(config.recent_hours) - Duration::days(config.baseline_days);
+    let mut flagged = 0;
+    for position in get_alerting_open_positions(db_pool).await? {
+        let mut matching = rules.matching(AlertKind::FeeVelocityDrop, &position).peekable();
+        if matching.peek().is_none() {
+            continue;
+        }
+        let snapshots = get_snapshots_for_position(db_pool, position.id, since, now).await?;
+
+        let mut fired = false;
+        for rule in matching {
+            let config = rule.spec.threshold.map_or(config.clone(), |drop| config.with_drop(drop));
+            let Some(trend) = fee_velocity_trend(&snapshots, now, &config) else {
+                break;
+            };
+            if !trend.dropping {
+                continue;
+            }
+
+            let mut alert = Alert {
+                key: format!("fee_velocity:{}:{}", position.id, now.timestamp()),
+                severity: AlertSeverity::Warning,
+                title: "Fee velocity dropped".to_string(),
+                message: format!(
+                    "Position {} in pool {} earned {} fees a day over the last {}h, down {}% \
+                     from {} over the {} days before; volume may have moved to another pool",
+                    position.nft_id,
+                    position.pool_id,
+                    trend.recent.fees_per_day.round_dp(6),
+                    config.recent_hours,
+                    trend
+                        .change
+                        .map(|c| (-c * Decimal::ONE_HUNDRED).round_dp(0))
+                        .unwrap_or_default(),
+                    trend.baseline.map(|b| b.fees_per_day.round_dp(6)).unwrap_or_default(),
+                    config.baseline_days
+                ),
+                position_id: Some(position.id),
+                owner: Some(position.owner.canonical()),
+                pool_id: Some(position.pool_id.clone()),
+                created_at: now,
+            };
+            let variables = AlertVariables::FeeVelocity(&trend);
+            apply_alert_template(
+                db_pool,
+                &fee_models,
+                &mut alert,
+                AlertKind::FeeVelocityDrop,
+                &position,
+                variables,
+            )
+            .await?;
+            fired |= dispatcher.dispatch_for_rule(db_pool, rule, position.id, &alert).await?;
+        }
+        if fired {
+            flagged += 1;
+        }
+    }
+
+    Ok(flagged)
+}
+
+/// Alert owners of open positions in pools whose fees changed
+///
+/// A `pool_param_change` rule fires when the change is at least its
+/// threshold (a fraction of the old fee) or has none; fees raised from zero
+/// always fire. Returns how many positions were alerted.
+async fn alert_pool_param_changes(
+    db_pool: &PgPool,
+    dispatcher: &AlertDispatcher,
+    changes: &[PoolParamChange],
+) -> Result<usize> {
+    let rules = AlertRules::load(db_pool).await?;
+    if !rules.has(AlertKind::PoolParamChange) {
+        return Ok(0);
+    }
+
+    let fee_models = FeeModelRegistry::from_env();
+    let positions = get_alerting_open_positions(db_pool).await?;
+    let mut alerted = 0;
+    for change in changes {
+        let relative = change.relative_change();
+        for position in positions.iter().filter(|p| p.pool_id == change.pool_id) {
+            let matching: Vec<_> = rules
+                .matching(AlertKind::PoolParamChange, position)
+                .filter(|rule| match (rule.spec.threshold, relative) {
+                    (Some(threshold), Some(relative)) => relative >= threshold,
+                    _ => true,
+                })
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+
+            let param = match change.param {
+                PoolParam::LpFee => "LP fee",
+                PoolParam::ProtocolFee => "protocol fee",
+            };
+            let mut alert = Alert {
+                key: format!("pool_param:{}:{}", change.id, position.id),
+                severity: AlertSeverity::Warning,
+                title: "Pool fee changed".to_string(),
+                message: format!(
+                    "The {} of pool {}, where position {} is open, changed from {} to {}",
+                    param,
+                    change.pool_id,
+                    position.nft_id,
+                    format_pool_param(change.param, change.old_value),
+                    format_pool_param(change.param, change.new_value)
+                ),
+                position_id: Some(position.id),
+                owner: Some(position.owner.canonical()),
+                pool_id: Some(position.pool_id.clone()),
+                created_at: change.detected_at,
+            };
+            apply_alert_template(
+                db_pool,
+                &fee_models,
+                &mut alert,
+                AlertKind::PoolParamChange,
+                position,
+                AlertVariables::PoolParamChange(change),
+            )
+            .await?;
+
+            let mut fired = false;
+            for rule in matching {
+                fired |= dispatcher.dispatch_for_rule(db_pool, rule, position.id, &alert).await?;
+            }
+            if fired {
+                alerted += 1;
+            }
+        }
+    }
+
+    Ok(alerted)
+}
+
+/// Downsample every finished day of opted-in pools not yet done
+///
+/// Days are planned from the hot table only and replaced one at a time, so
+/// an interrupted run picks up where it stopped. Returns how many pool-days
+/// were processed.
+async fn downsample_swaps(db_pool: &PgPool) -> Result<usize> {
+    let today = Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc();
+
+    let mut processed = 0;
+    for (pool_id, keep_largest, day) in get_days_to_downsample(db_pool, today).await? {
+        let next_day = day + Duration::days(1);
+        let swaps = get_hot_swaps_for_pool_between(db_pool, &pool_id, day, next_day).await?;
+        let counts = get_swap_minute_counts(db_pool, &pool_id, day, next_day).await?;
+        let plan = plan_downsampling(&swaps, &counts, keep_largest.max(0) as usize);
+        let aggregates: Vec<(&Swap, i32)> =
+            plan.aggregates.iter().map(|a| (&a.swap, a.swap_count)).collect();
+        replace_swaps_with_aggregates(db_pool, &pool_id, &plan.removed, &aggregates, next_day)
+            .await?;
+        if !plan.is_empty() {
+            info!(
+                "Pool {}: folded {} rows of {} into {} minute aggregates",
+                pool_id,
+                plan.removed.len(),
+                day.date_naive(),
+                aggregates.len()
+            );
+        }
+        processed += 1;
+    }
+    Ok(processed)
+}
+
+/// Encode each pool's swap ticks for every finished month not yet compressed
+///
+/// Runs before retention, so the compressed series outlives purged raw swaps.
+/// Returns how many monthly chunks were written.
+async fn compress_tick_history(db_pool: &PgPool) -> Result<usize> {
+    let now = Utc::now();
+    let current_month = now.date_naive().with_day(1).unwrap_or(now.date_naive());
+    let current_month = current_month.and_time(NaiveTime::MIN).and_utc();
+
+    let mut written = 0;
+    for (pool_id, month_start) in get_months_to_compress(db_pool, current_month).await? {
+        let next_month = month_start.checked_add_months(Months::new(1)).unwrap_or(current_month);
+        let end = next_month - Duration::microseconds(1);
+        let swaps = get_swaps_for_pool_between(db_pool, &pool_id, month_start, end).await?;
+        let points = tick_points_from_swaps(&swaps);
+        let span = points.first().zip(points.last()).map(|(f, l)| (f.timestamp, l.timestamp));
+        let data = encode_tick_series(&points);
+        save_tick_chunk(db_pool, &pool_id, month_start, points.len() as i32, span, &data).await?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Archive swaps older than `SWAP_ARCHIVE_AFTER_DAYS` (`0` disables archiving)
+///
+/// Runs after the data quality check, which only reads the hot table.
+async fn archive_old_swaps(db_pool: &PgPool) -> Result<Option<u64>> {
+    let days = match std::env::var("SWAP_ARCHIVE_AFTER_DAYS") {
+        Ok(days) => days.trim().parse::<i64>()?,
+        Err(_) => DEFAULT_SWAP_ARCHIVE_AFTER_DAYS,
+    };
+    if days <= 0 {
+        return Ok(None);
+    }
+    let archived = archive_swaps_before(db_pool, Utc::now() - Duration::days(days)).await?;
+    Ok(Some(archived))
+}
+
+/// Archive positions closed over `POSITION_ARCHIVE_AFTER_DAYS` ago (`0` disables archiving)
+async fn archive_closed_positions(db_pool: &PgPool) -> Result<Option<u64>> {
+    let days = match std::env::var("POSITION_ARCHIVE_AFTER_DAYS") {
+        Ok(days) => days.trim().parse::<i64>()?,
+        Err(_) => DEFAULT_POSITION_ARCHIVE_AFTER_DAYS,
+    };
+    if days <= 0 {
+        return Ok(None);
+    }
+    let archived = archive_stale_positions(db_pool, Utc::now() - Duration::days(days)).await?;
+    Ok(Some(archived))
+}
+
+/// Delete swaps, snapshots and soft-deleted positions past their retention horizon
+///
+/// Swaps are rolled up into daily pool volume before they go, and every purge
+/// advances its data set's horizon so analytics can flag windows reaching past it.
+/// Used and expired sign-in nonces are deleted too.
+async fn apply_retention(db_pool: &PgPool) -> Result<()> {
+    let policy = RetentionPolicy::from_env()?;
+    let now = Utc::now();
+
+    if let Some(cutoff) = policy.cutoff(RetainedData::Swaps, now) {
+        let purged = purge_swaps_before(db_pool, cutoff).await?;
+        info!("Purged {} swaps older than {} days", purged, policy.swap_days.unwrap_or_default());
+    }
+    if let Some(cutoff) = policy.cutoff(RetainedData::PositionSnapshots, now) {
+        let purged = purge_snapshots_before(db_pool, cutoff).await?;
+        info!(
+            "Purged {} snapshots older than {} days",
+            purged,
+            policy.snapshot_days.unwrap_or_default()
+        );
+    }
+
+    let deleted_before = now - Duration::days(policy.deleted_position_days);
+    let purged = purge_deleted_positions(db_pool, deleted_before).await?;
+    if purged > 0 {
+        info!("Purged {} positions deleted over {} days ago", purged, policy.deleted_position_days);
+    }
+
+    let purged = purge_auth_nonces(db_pool).await?;
+    if purged > 0 {
+        info!("Purged {} used or expired sign-in nonces", purged);
+    }
+    let purged = purge_telegram_link_codes(db_pool).await?;
+    if purged > 0 {
+        info!("Purged {} expired Telegram link codes", purged);
+    }
+    Ok(())
+}
+
+/// Read every pool's LP and protocol fees from the StateView contract
+///
+/// Returns how many pools were read and the fee changes seen, or `None` when
+/// `ETHEREUM_RPC_URL` or `STATE_VIEW_ADDRESS` isn't configured.
+async fn refresh_pool_fees(db_pool: &PgPool) -> Result<Option<(usize, Vec<PoolParamChange>)>> {
+    let (Ok(rpc_url), Ok(state_view)) =
+        (std::env::var("ETHEREUM_RPC_URL"), std::env::var("STATE_VIEW_ADDRESS"))
+    else {
+        return Ok(None);
+    };
+    let state_view: Address = state_view.parse()?;
+    let blockchain = BlockchainService::new(&rpc_url)?;
+
+    let mut refreshed = 0;
+    let mut changes = Vec::new();
+    for pool_id in get_pool_ids(db_pool).await? {
+        let Ok(id) = pool_id.parse::<B256>() else {
+            continue;
+        };
+        match blockchain.get_pool_fees(state_view, id).await {
+            Ok((lp_fee, protocol_fee)) => {
+                changes.extend(update_pool_fees(db_pool, &pool_id, lp_fee, protocol_fee).await?);
+                refreshed += 1;
+            }
+            Err(e) => warn!("Failed to read fees of pool {}: {}", pool_id, e),
+        }
+    }
+
+    Ok(Some((refreshed, changes)))
+}
+
+/// Record every pool's fee growth globals from the StateView contract
+///
+/// Returns `None` when `ETHEREUM_RPC_URL` or `STATE_VIEW_ADDRESS` isn't configured.
+async fn record_fee_growth(db_pool: &PgPool) -> Result<Option<usize>> {
+    let (Ok(rpc_url), Ok(state_view)) =
+        (std::env::var("ETHEREUM_RPC_URL"), std::env::var("STATE_VIEW_ADDRESS"))
+    else {
+        return Ok(None);
+    };
+    let state_view: Address = state_view.parse()?;
+    let blockchain = BlockchainService::new(&rpc_url)?;
+
+    let mut sampled = 0;
+    for pool_id in get_pool_ids(db_pool).await? {
+        let Ok(id) = pool_id.parse::<B256>() else {
+            continue;
+        };
+        match blockchain.get_pool_fee_growth(state_view, id).await {
+            Ok(mut growth) => {
+                growth.pool_id = pool_id;
+                insert_pool_fee_growth(db_pool, &growth).await?;
+                sampled += 1;
+            }
+            Err(e) => warn!("Failed to read fee growth of pool {}: {}", pool_id, e),
+        }
+    }
+
+    Ok(Some(sampled))
+}
+
+/// Stamp the sync completion time in Redis, if `REDIS_URL` is configured
+async fn mark_sync_completed() -> Result<()> {
+    let Ok(redis_url) = std::env::var("REDIS_URL") else {
+        return Ok(());
+    };
+    let client = redis::Client::open(redis_url)?;
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    redis::cmd("SET")
+        .arg(SYNC_COMPLETED_KEY)
+        .arg(Utc::now().to_rfc3339())
+        .query_async::<()>(&mut conn)
+        .await?;
+    Ok(())
+}
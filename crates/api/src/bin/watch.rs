@@ -0,0 +1,619 @@
+use alloy::primitives::{Address, B256};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use dotenv::dotenv;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use stillwater_alerts::{
+    AlertDispatcher, AlertRules, AlertVariables, apply_alert_template, notify_new_pool,
+};
+use stillwater_analytics::{
+    DEFAULT_QUOTE_CURRENCY, ExchangeRates, FeeModelRegistry, LargeSwapConfig, QuoteCurrencies,
+    RangeCrossing, SwapImpact, TickRange, swap_impacts, tick_to_price,
+};
+use stillwater_db::{
+    EventListener, get_alerting_open_positions, get_pool_by_id, get_pools_with_tokens, notify_event,
+};
+use stillwater_indexer::{PoolCreationScanner, record_gas_price};
+use stillwater_models::{
+    Alert, AlertKind, AlertSeverity, BlockchainService, DbEvent, LOG_SCAN_CHUNK_BLOCKS, Pool,
+    Position,
+};
+use tracing::{error, info, warn};
+
+/// How often to check for a new block (Unichain produces one per second)
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often to reload watched positions and alert rules, picking up changes since
+///
+/// New positions also trigger a reload as soon as their insert is announced.
+const POSITION_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long to wait before retrying a startup warm-up that failed
+const WARM_UP_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the head block's gas price is recorded for backtests
+const GAS_PRICE_SAMPLE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Watches open positions of watched owners block by block and alerts the
+/// moment one leaves or re-enters its range
+///
+/// Which crossings alert, and where, follows the `range_exited` and
+/// `range_entered` alert rules.
+///
+/// Only each pool's current tick is read per block; no P&L is computed, so
+/// alerts go out within seconds instead of at the next sync.
+///
+/// With `POOL_MANAGER_ADDRESS` set, new blocks are also scanned for pool
+/// creations; new pools are stored right away and owners watching one of
+/// their tokens, or their pair on this chain, are told. Swaps in watched
+/// pools are read from the same contract's logs for the `large_swap` rules.
+///
+/// The head block's base fee and median tip are recorded every few minutes,
+/// building the gas price history backtests price gas from.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).init();
+
+    let db_pool = stillwater_db::get_pool().await.expect("Failed to connect to database");
+
+    let rpc_url = std::env::var("ETHEREUM_RPC_URL").expect("ETHEREUM_RPC_URL must be set in .env");
+    let blockchain = BlockchainService::new(&rpc_url).expect("Failed to create blockchain service");
+    let state_view: Address = std::env::var("STATE_VIEW_ADDRESS")
+        .expect("STATE_VIEW_ADDRESS must be set to the v4 StateView contract")
+        .parse()
+        .context("STATE_VIEW_ADDRESS must be an address")?;
+
+    let dispatcher = AlertDispatcher::from_env();
+    let fee_models = FeeModelRegistry::from_env();
+    let large_swaps = LargeSwapConfig::from_env()?;
+    let currencies = QuoteCurrencies::from_env()?;
+    let usd_anchors = currencies.anchors_for(DEFAULT_QUOTE_CURRENCY).unwrap_or_default().to_vec();
+
+    let pool_manager = match std::env::var("POOL_MANAGER_ADDRESS") {
+        Ok(address) => {
+            Some(address.parse::<Address>().context("POOL_MANAGER_ADDRESS must be an address")?)
+        }
+        Err(_) => {
+            info!("POOL_MANAGER_ADDRESS not set, not watching for new pools or large swaps");
+            None
+        }
+    };
+    // Gas prices are recorded per chain, and new pool alerts name the chain,
+    // which pair watches may be limited to
+    let chain_id = blockchain.get_chain_id().await.context("Failed to read the RPC's chain ID")?;
+    let mut pool_creations = match pool_manager {
+        Some(pool_manager) => {
+            Some((pool_creation_scanner(&db_pool, &blockchain, pool_manager).await?, chain_id))
+        }
+        None => None,
+    };
+
+    let mut events = match EventListener::connect(&db_pool).await {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            warn!("Not listening for new positions, relying on periodic reloads: {}", e);
+            None
+        }
+    };
+
+    // Nothing is checked until positions, pool ticks and rates are in memory
+    let mut watcher = RangeWatcher::new(usd_anchors);
+    let mut last_block = loop {
+        let price_swaps = pool_manager.is_some();
+        match watcher.warm_up(&db_pool, &blockchain, state_view, price_swaps).await {
+            Ok(block) => break block,
+            Err(e) => {
+                error!("Failed to warm up, retrying: {}", e);
+                tokio::time::sleep(WARM_UP_RETRY_INTERVAL).await;
+            }
+        }
+    };
+    let mut last_refresh = Some(Instant::now());
+    let mut last_gas_sample: Option<Instant> = None;
+    let mut interval = tokio::time::interval(BLOCK_POLL_INTERVAL);
+
+    info!("Watching positions for range crossings");
+    loop {
+        match events.as_mut() {
+            Some(listener) => tokio::select! {
+                _ = interval.tick() => {}
+                event = listener.recv() => {
+                    match event {
+                        Ok(DbEvent::PositionInserted { .. }) => last_refresh = None,
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!("Event listener failed, relying on periodic reloads: {}", e);
+                            events = None;
+                        }
+                    }
+                    continue;
+                }
+            },
+            None => {
+                interval.tick().await;
+            }
+        }
+
+        if last_refresh.is_none_or(|t| t.elapsed() >= POSITION_REFRESH_INTERVAL) {
+            match watcher.refresh(&db_pool).await {
+                Ok(()) => last_refresh = Some(Instant::now()),
+                Err(e) => error!("Failed to load watched positions: {}", e),
+            }
+            if pool_manager.is_some()
+                && watcher.rules.has(AlertKind::LargeSwap)
+                && let Err(e) = watcher.refresh_rates(&db_pool, &blockchain, state_view).await
+            {
+                warn!("Failed to price tokens for large swap alerts: {}", e);
+            }
+        }
+
+        let block = match blockchain.get_block_number().await {
+            Ok(block) => block,
+            Err(e) => {
+                warn!("Failed to get block number: {}", e);
+                continue;
+            }
+        };
+        if block <= last_block {
+            continue;
+        }
+        last_block = block;
+
+        if last_gas_sample.is_none_or(|t| t.elapsed() >= GAS_PRICE_SAMPLE_INTERVAL) {
+            match record_gas_price(&db_pool, &blockchain, chain_id, block).await {
+                Ok(_) => last_gas_sample = Some(Instant::now()),
+                Err(e) => warn!("Failed to record gas price: {}", e),
+            }
+        }
+
+        if let Some((scanner, chain_id)) = pool_creations.as_mut() {
+            match scanner.scan(&db_pool, &blockchain, block).await {
+                Ok(created) => {
+                    for new in created {
+                        let notified = notify_new_pool(
+                            &db_pool,
+                            &dispatcher,
+                            &new.pool,
+                            &new.initialization,
+                            *chain_id,
+                        );
+                        if let Err(e) = notified.await {
+                            error!("Failed to notify watchers of pool {}: {}", new.pool.pool_id, e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to scan for pool creations: {}", e),
+            }
+        }
+
+        // Swaps are measured from the ticks of the last block, so read them first
+        if let Some(pool_manager) = pool_manager {
+            match watcher.check_swaps(&blockchain, pool_manager, block).await {
+                Ok(impacts) => {
+                    for impact in impacts {
+                        alert_large_swap(
+                            &db_pool,
+                            &dispatcher,
+                            &fee_models,
+                            &watcher,
+                            &large_swaps,
+                            &impact,
+                        )
+                        .await;
+                    }
+                }
+                Err(e) => warn!("Failed to read swaps: {}", e),
+            }
+        }
+
+        for crossed in watcher.check_block(&blockchain, state_view, block).await {
+            let Crossed { position, crossing, tick, mut alert, event } = crossed;
+            if let Err(e) = notify_event(&db_pool, &event).await {
+                warn!("Failed to announce range crossing: {}", e);
+            }
+
+            // Owners' templates replace the default text
+            let kind = match crossing {
+                RangeCrossing::Exited => AlertKind::RangeExited,
+                RangeCrossing::Entered => AlertKind::RangeEntered,
+            };
+            let variables = AlertVariables::Range { tick, block };
+            if let Err(e) =
+                apply_alert_template(&db_pool, &fee_models, &mut alert, kind, &position, variables)
+                    .await
+            {
+                warn!("Failed to apply alert template for {}: {}", alert.key, e);
+            }
+
+            info!("{}: {}", alert.title, alert.message);
+            for rule in watcher.rules.matching(kind, &position) {
+                let dispatched = dispatcher.dispatch_for_rule(&db_pool, rule, position.id, &alert);
+                if let Err(e) = dispatched.await {
+                    error!("Failed to dispatch alert {} for rule {}: {}", alert.key, rule.id, e);
+                }
+            }
+        }
+    }
+}
+
+/// Scanner for the pools `pool_manager` creates
+///
+/// The first run starts at `POOL_MANAGER_DEPLOY_BLOCK`, or the current block
+/// without it; later runs resume where the last one stopped.
+async fn pool_creation_scanner(
+    db_pool: &PgPool,
+    blockchain: &BlockchainService,
+    pool_manager: Address,
+) -> Result<PoolCreationScanner> {
+    let start_block = match std::env::var("POOL_MANAGER_DEPLOY_BLOCK") {
+        Ok(block) => {
+            block.trim().parse().context("POOL_MANAGER_DEPLOY_BLOCK must be a block number")?
+        }
+        Err(_) => blockchain.get_block_number().await?,
+    };
+
+    info!("Watching PoolManager {:#x} for new pools", pool_manager);
+    PoolCreationScanner::resume(db_pool, pool_manager, start_block).await
+}
+
+/// Alert owners of positions in a swap's pool whose `large_swap` rules it meets
+///
+/// Each position gets one alert, rendered with its owner's template, sent
+/// through every matching rule whose thresholds the swap reaches.
+async fn alert_large_swap(
+    db_pool: &PgPool,
+    dispatcher: &AlertDispatcher,
+    fee_models: &FeeModelRegistry,
+    watcher: &RangeWatcher,
+    config: &LargeSwapConfig,
+    impact: &SwapImpact,
+) {
+    let in_pool =
+        watcher.positions.iter().filter(|(p, _)| p.pool_id.eq_ignore_ascii_case(&impact.pool_id));
+    for (position, _) in in_pool {
+        let rules: Vec<_> = watcher
+            .rules
+            .matching(AlertKind::LargeSwap, position)
+            .filter(|rule| {
+                config.with_rule(rule.spec.threshold, rule.spec.tick_threshold).is_large(impact)
+            })
+            .collect();
+        if rules.is_empty() {
+            continue;
+        }
+
+        let mut alert = large_swap_alert(position, impact);
+        let variables = AlertVariables::LargeSwap(impact);
+        let kind = AlertKind::LargeSwap;
+        if let Err(e) =
+            apply_alert_template(db_pool, fee_models, &mut alert, kind, position, variables).await
+        {
+            warn!("Failed to apply alert template for {}: {}", alert.key, e);
+        }
+
+        info!("{}: {}", alert.title, alert.message);
+        for rule in rules {
+            let dispatched = dispatcher.dispatch_for_rule(db_pool, rule, position.id, &alert);
+            if let Err(e) = dispatched.await {
+                error!("Failed to dispatch alert {} for rule {}: {}", alert.key, rule.id, e);
+            }
+        }
+    }
+}
+
+/// A watched position that crossed a range edge
+struct Crossed {
+    position: Position,
+    crossing: RangeCrossing,
+    tick: i32,
+    alert: Alert,
+    event: DbEvent,
+}
+
+/// Watched positions, the alert rules and the last tick seen for each pool
+///
+/// Also keeps the watched pools and USD rates of their tokens, to value swaps.
+struct RangeWatcher {
+    positions: Vec<(Position, TickRange)>,
+    rules: AlertRules,
+    last_ticks: HashMap<String, i32>,
+    pools: HashMap<String, Pool>,
+    usd_anchors: Vec<String>,
+    rates: ExchangeRates,
+    /// First block whose swaps haven't been read (0: none read yet)
+    next_swap_block: u64,
+}
+
+impl RangeWatcher {
+    fn new(usd_anchors: Vec<String>) -> Self {
+        let rates = ExchangeRates::new(DEFAULT_QUOTE_CURRENCY, &usd_anchors, Utc::now());
+        Self {
+            positions: Vec::new(),
+            rules: AlertRules::default(),
+            last_ticks: HashMap::new(),
+            pools: HashMap::new(),
+            usd_anchors,
+            rates,
+            next_swap_block: 0,
+        }
+    }
+
+    async fn refresh(&mut self, db_pool: &PgPool) -> Result<()> {
+        self.rules = AlertRules::load(db_pool).await?;
+        let positions = get_alerting_open_positions(db_pool).await?;
+        self.positions = positions
+            .into_iter()
+            .filter_map(|p| TickRange::of(&p).ok().map(|range| (p, range)))
+            .collect();
+
+        let mut pools = HashMap::new();
+        for (position, _) in &self.positions {
+            if pools.contains_key(&position.pool_id) {
+                continue;
+            }
+            if let Some(pool) = get_pool_by_id(db_pool, &position.pool_id).await? {
+                pools.insert(position.pool_id.clone(), pool);
+            }
+        }
+        self.pools = pools;
+        Ok(())
+    }
+
+    /// Load positions, rules and pools, seed each watched pool's tick and price
+    /// tokens before the first block is checked; returns the block warmed up at
+    ///
+    /// Crossings and swaps in the first blocks after a restart are then measured
+    /// from the ticks at startup instead of only seeding state, and large swaps
+    /// are valued with rates in place. Pools whose tick can't be read yet are
+    /// seeded at the first block they can be. Fails when positions can't be
+    /// loaded or the head block is unknown.
+    async fn warm_up(
+        &mut self,
+        db_pool: &PgPool,
+        blockchain: &BlockchainService,
+        state_view: Address,
+        price_swaps: bool,
+    ) -> Result<u64> {
+        self.refresh(db_pool).await?;
+        let block = blockchain.get_block_number().await?;
+        let ticks = self.read_ticks(blockchain, state_view).await;
+        self.last_ticks.extend(ticks);
+        self.next_swap_block = block + 1;
+
+        if price_swaps
+            && self.rules.has(AlertKind::LargeSwap)
+            && let Err(e) = self.refresh_rates(db_pool, blockchain, state_view).await
+        {
+            warn!("Failed to price tokens for large swap alerts: {}", e);
+        }
+
+        info!(
+            "Warmed up at block {}: {} positions in {} pools, {} ticks, {} token rates",
+            block,
+            self.positions.len(),
+            self.pools.len(),
+            self.last_ticks.len(),
+            self.rates.rates.len()
+        );
+        Ok(block)
+    }
+
+    /// Current tick of each pool with a watched position, skipping pools that can't be read
+    async fn read_ticks(
+        &self,
+        blockchain: &BlockchainService,
+        state_view: Address,
+    ) -> HashMap<String, i32> {
+        let mut pool_ids: Vec<&str> =
+            self.positions.iter().map(|(p, _)| p.pool_id.as_str()).collect();
+        pool_ids.dedup();
+
+        let mut ticks = HashMap::new();
+        for pool_id in pool_ids {
+            let Ok(id) = pool_id.parse::<B256>() else {
+                continue;
+            };
+            match blockchain.get_pool_tick(state_view, id).await {
+                Ok(tick) => {
+                    ticks.insert(pool_id.to_string(), tick);
+                }
+                Err(e) => warn!("Failed to read tick of pool {}: {}", pool_id, e),
+            }
+        }
+        ticks
+    }
+
+    /// Price the watched pools' tokens in USD from current pool ticks
+    ///
+    /// Tokens not paired with a USD anchor in a watched pool are priced
+    /// through a stored pool pairing them with one.
+    async fn refresh_rates(
+        &mut self,
+        db_pool: &PgPool,
+        blockchain: &BlockchainService,
+        state_view: Address,
+    ) -> Result<()> {
+        let is_anchor =
+            |token: &str| self.usd_anchors.iter().any(|a| a.eq_ignore_ascii_case(token));
+        let mut tokens: Vec<String> = self
+            .pools
+            .values()
+            .flat_map(|p| [p.token0.to_lowercase(), p.token1.to_lowercase()])
+            .filter(|token| !is_anchor(token))
+            .collect();
+        tokens.sort();
+        tokens.dedup();
+
+        let mut candidates: Vec<Pool> = self.pools.values().cloned().collect();
+        for pool in get_pools_with_tokens(db_pool, &tokens).await? {
+            if (is_anchor(&pool.token0) || is_anchor(&pool.token1))
+                && !self.pools.contains_key(&pool.pool_id)
+            {
+                candidates.push(pool);
+            }
+        }
+
+        let mut priced = Vec::new();
+        for pool in candidates {
+            let tick = match self.last_ticks.get(&pool.pool_id) {
+                Some(tick) => *tick,
+                None => {
+                    let Ok(id) = pool.pool_id.parse::<B256>() else {
+                        continue;
+                    };
+                    match blockchain.get_pool_tick(state_view, id).await {
+                        Ok(tick) => tick,
+                        Err(e) => {
+                            warn!("Failed to read tick of pool {}: {}", pool.pool_id, e);
+                            continue;
+                        }
+                    }
+                }
+            };
+            priced.push((pool, tick_to_price(tick)));
+        }
+
+        let mut rates = ExchangeRates::new(DEFAULT_QUOTE_CURRENCY, &self.usd_anchors, Utc::now());
+        rates.link(&priced);
+        self.rates = rates;
+        Ok(())
+    }
+
+    /// Read the watched pools' swaps since the last check, up to `head`
+    ///
+    /// Each pool's first swap is measured from its tick at the last block read,
+    /// so after a gap longer than `LOG_SCAN_CHUNK_BLOCKS` the skipped blocks'
+    /// swaps are never seen and that first move can be off. Nothing is read
+    /// while no `large_swap` rule exists.
+    async fn check_swaps(
+        &mut self,
+        blockchain: &BlockchainService,
+        pool_manager: Address,
+        head: u64,
+    ) -> Result<Vec<SwapImpact>> {
+        if !self.rules.has(AlertKind::LargeSwap) || self.pools.is_empty() {
+            self.next_swap_block = head + 1;
+            return Ok(Vec::new());
+        }
+        let from = match self.next_swap_block {
+            0 => head,
+            next => next.max(head.saturating_sub(LOG_SCAN_CHUNK_BLOCKS - 1)),
+        };
+        if head < from {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<B256> = self.pools.keys().filter_map(|id| id.parse().ok()).collect();
+        let swaps = blockchain.get_pool_swaps(pool_manager, &ids, from, head).await?;
+        self.next_swap_block = head + 1;
+
+        let mut impacts = Vec::new();
+        for pool in self.pools.values() {
+            let tick_before = self.last_ticks.get(&pool.pool_id).copied();
+            impacts.extend(swap_impacts(pool, &swaps, tick_before, &self.rates));
+        }
+        Ok(impacts)
+    }
+
+    /// Read every watched pool's tick and collect alerts and events for positions
+    /// that crossed an edge
+    ///
+    /// A pool's first tick (read at warm-up for pools known then) only seeds its
+    /// state, so restarting doesn't re-alert positions that were already out of range.
+    async fn check_block(
+        &mut self,
+        blockchain: &BlockchainService,
+        state_view: Address,
+        block: u64,
+    ) -> Vec<Crossed> {
+        let ticks = self.read_ticks(blockchain, state_view).await;
+
+        let mut alerts = Vec::new();
+        for (position, range) in &self.positions {
+            let (Some(previous), Some(current)) =
+                (self.last_ticks.get(&position.pool_id), ticks.get(&position.pool_id))
+            else {
+                continue;
+            };
+            if let Some(crossing) = range.crossing(*previous, *current) {
+                let event = DbEvent::RangeCrossed {
+                    position_id: position.id,
+                    owner: position.owner.canonical(),
+                    pool_id: position.pool_id.clone(),
+                    in_range: crossing == RangeCrossing::Entered,
+                    tick: *current,
+                    block,
+                };
+                alerts.push(Crossed {
+                    position: position.clone(),
+                    crossing,
+                    tick: *current,
+                    alert: crossing_alert(position, crossing, *current, block),
+                    event,
+                });
+            }
+        }
+
+        self.last_ticks.extend(ticks);
+        alerts
+    }
+}
+
+fn crossing_alert(position: &Position, crossing: RangeCrossing, tick: i32, block: u64) -> Alert {
+    let (severity, event, title) = match crossing {
+        RangeCrossing::Exited => (AlertSeverity::Critical, "out", "Position out of range"),
+        RangeCrossing::Entered => (AlertSeverity::Info, "in", "Position back in range"),
+    };
+    Alert {
+        key: format!("range:{}:{}:{}", position.id, event, block),
+        severity,
+        title: title.to_string(),
+        message: format!(
+            "Position {} in pool {} (range {} to {}) is at tick {} as of block {}",
+            position.nft_id,
+            position.pool_id,
+            position.tick_lower,
+            position.tick_upper,
+            tick,
+            block
+        ),
+        position_id: Some(position.id),
+        owner: Some(position.owner.canonical()),
+        pool_id: Some(position.pool_id.clone()),
+        created_at: Utc::now(),
+    }
+}
+
+fn large_swap_alert(position: &Position, impact: &SwapImpact) -> Alert {
+    let value = match impact.value {
+        Some(value) => format!(" worth {} USD", value.round_dp(0)),
+        None => String::new(),
+    };
+    let moved = match impact.tick_move {
+        Some(ticks) => format!("moved the price {} ticks to tick {}", ticks, impact.tick_after),
+        None => format!("left the price at tick {}", impact.tick_after),
+    };
+    Alert {
+        key: format!("swap:{}:{}:{}", position.id, impact.block_number, impact.log_index),
+        severity: AlertSeverity::Warning,
+        title: "Large swap".to_string(),
+        message: format!(
+            "A swap{} in pool {} {} in block {}; position {} spans {} to {}",
+            value,
+            position.pool_id,
+            moved,
+            impact.block_number,
+            position.nft_id,
+            position.tick_lower,
+            position.tick_upper
+        ),
+        position_id: Some(position.id),
+        owner: Some(position.owner.canonical()),
+        pool_id: Some(position.pool_id.clone()),
+        created_at: Utc::now(),
+    }
+}
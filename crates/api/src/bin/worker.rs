@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use dotenv::dotenv;
+use sqlx::PgPool;
+use std::time::Duration;
+use stillwater_db::{claim_next_job, complete_job, fail_job, release_job, requeue_stale_jobs};
+use stillwater_indexer::{GraphIndexer, run_job};
+use tracing::{error, info, warn};
+
+/// How often to look for jobs when the queue is empty, by default
+const DEFAULT_JOB_POLL_SECS: u64 = 5;
+
+/// How long a job may run before it's assumed its worker died, by default
+const DEFAULT_JOB_TIMEOUT_SECS: i64 = 3600;
+
+/// Times a job is claimed before a worker dying on it fails it for good
+const MAX_JOB_ATTEMPTS: i32 = 3;
+
+/// Runs queued backfills and reports from the `jobs` table
+///
+/// Any number of workers can run against one database: jobs are claimed with
+/// `FOR UPDATE SKIP LOCKED`, so each goes to exactly one of them. Jobs without
+/// progress for `JOB_TIMEOUT_SECS` (their worker died) are requeued. On Ctrl-C
+/// the running job goes straight back to the queue; backfills resume from
+/// their cursor either way.
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+
+    tracing_subscriber::fmt().with_max_level(tracing::Level::INFO).init();
+
+    let poll = Duration::from_secs(env_number("JOB_POLL_SECS", DEFAULT_JOB_POLL_SECS)?);
+    let timeout =
+        chrono::Duration::seconds(env_number("JOB_TIMEOUT_SECS", DEFAULT_JOB_TIMEOUT_SECS)?);
+
+    // Connect to database (honours DB_SCHEMA)
+    let db_pool = stillwater_db::get_pool().await.context("Failed to connect to database")?;
+    let indexer = GraphIndexer::from_env()
+        .context("Failed to create GraphIndexer. Ensure GRAPH_API_URL is set")?;
+
+    info!("Worker polling for jobs every {}s", poll.as_secs());
+    loop {
+        match requeue_stale_jobs(&db_pool, Utc::now() - timeout, MAX_JOB_ATTEMPTS).await {
+            Ok(0) => {}
+            Ok(requeued) => warn!("Requeued {} jobs whose worker stopped", requeued),
+            Err(e) => error!("Failed to requeue stale jobs: {}", e),
+        }
+
+        // Drain the queue before sleeping again
+        loop {
+            match run_next_job(&db_pool, &indexer).await {
+                Ok(Step::Ran) => {}
+                Ok(Step::Idle) => break,
+                Ok(Step::Stopped) => return Ok(()),
+                Err(e) => {
+                    error!("Failed to claim job: {}", e);
+                    break;
+                }
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(poll) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// What a turn of the worker loop did
+enum Step {
+    Ran,
+    /// The queue was empty
+    Idle,
+    /// Asked to stop while running a job, which went back to the queue
+    Stopped,
+}
+
+/// Claim and run one job
+async fn run_next_job(db_pool: &PgPool, indexer: &GraphIndexer) -> Result<Step> {
+    let Some(job) = claim_next_job(db_pool).await? else {
+        return Ok(Step::Idle);
+    };
+    info!(
+        "Running {} job {} for {} (attempt {})",
+        job.kind.as_str(),
+        job.id,
+        job.owner,
+        job.attempts
+    );
+
+    let outcome = tokio::select! {
+        outcome = run_job(db_pool, indexer, &job) => outcome,
+        _ = tokio::signal::ctrl_c() => {
+            release_job(db_pool, job.id).await?;
+            info!("Stopping: job {} is back in the queue", job.id);
+            return Ok(Step::Stopped);
+        }
+    };
+    let recorded = match outcome {
+        Ok(result) => {
+            info!("Job {} succeeded", job.id);
+            complete_job(db_pool, job.id, job.attempts, &result).await?
+        }
+        Err(e) => {
+            warn!("Job {} failed: {:#}", job.id, e);
+            fail_job(db_pool, job.id, job.attempts, &format!("{:#}", e)).await?
+        }
+    };
+    if !recorded {
+        warn!(
+            "Job {} was requeued as stale while running; its outcome was dropped for the new claim",
+            job.id
+        );
+    }
+    Ok(Step::Ran)
+}
+
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> Result<T> {
+    match std::env::var(name) {
+        Ok(value) => {
+            value.trim().parse().ok().with_context(|| format!("{} must be a number", name))
+        }
+        Err(_) => Ok(default),
+    }
+}
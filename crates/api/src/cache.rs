@@ -0,0 +1,285 @@
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use redis::{AsyncCommands, Client as RedisClient};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use stillwater_db::EventListener;
+use stillwater_models::{Address, DbEvent};
+use tracing::{error, info, warn};
+
+use crate::address::OwnerScope;
+use crate::handlers::{pools::build_pool_stats, portfolio::build_portfolio};
+use crate::state::AppState;
+
+/// Redis key the `sync` binary stamps with its completion time after every cycle
+pub const SYNC_COMPLETED_KEY: &str = "stillwater:sync:completed_at";
+
+/// How long cached responses live; a new sync replaces them well before this
+const CACHE_TTL_SECS: u64 = 6 * 3600;
+
+/// How often the warmer checks whether a sync has completed
+const WARM_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long data change events are collected before affected responses are recomputed,
+/// so a sync inserting hundreds of swaps rebuilds each pool's stats once
+const EVENT_DEBOUNCE: Duration = Duration::from_secs(5);
+
+/// Wait before reconnecting a failed event listener
+const EVENT_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Redis-backed cache of expensive responses, keyed by sync cycle
+///
+/// Entries are namespaced by the completion stamp of the sync they were
+/// computed after, so a new sync implicitly invalidates everything cached
+/// before it without scanning for keys.
+#[derive(Clone)]
+pub struct ResponseCache {
+    client: RedisClient,
+    generation: Arc<RwLock<String>>,
+    /// When responses last started reflecting changed data (see `mark_modified`)
+    modified_at: Arc<RwLock<DateTime<Utc>>>,
+}
+
+impl ResponseCache {
+    pub fn new(client: RedisClient) -> Self {
+        Self {
+            client,
+            generation: Arc::new(RwLock::new("initial".to_string())),
+            modified_at: Arc::new(RwLock::new(next_second(Utc::now()))),
+        }
+    }
+
+    /// `Last-Modified` of data-backed responses: the last sync, refresh or API write
+    /// this process saw, in whole seconds
+    pub fn last_modified(&self) -> DateTime<Utc> {
+        *self.modified_at.read().unwrap()
+    }
+
+    /// Record that responses now reflect changed data
+    ///
+    /// HTTP dates only have whole seconds, so the stamp is rounded up and
+    /// always advances by at least a second; a client that saw the previous
+    /// stamp doesn't mistake a change in the same second for no change.
+    pub fn mark_modified(&self) {
+        let mut modified_at = self.modified_at.write().unwrap();
+        *modified_at = next_second(Utc::now()).max(*modified_at + TimeDelta::seconds(1));
+    }
+
+    fn key(&self, kind: &str, id: &str) -> String {
+        cache_key(&self.generation.read().unwrap(), kind, id)
+    }
+
+    /// Cached response for `kind`/`id` from the current sync cycle
+    ///
+    /// Redis errors are logged and treated as a miss so the API keeps serving.
+    pub async fn get(&self, kind: &str, id: &str) -> Option<serde_json::Value> {
+        let key = self.key(kind, id);
+        let result: redis::RedisResult<Option<String>> = async {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            conn.get(&key).await
+        }
+        .await;
+
+        match result {
+            Ok(cached) => cached.and_then(|json| serde_json::from_str(&json).ok()),
+            Err(e) => {
+                warn!("Cache read failed for {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// Store a response for `kind`/`id` in the current sync cycle
+    pub async fn put(&self, kind: &str, id: &str, value: &serde_json::Value) {
+        self.put_in(&self.key(kind, id), value).await;
+    }
+
+    async fn put_in(&self, key: &str, value: &serde_json::Value) {
+        let result: redis::RedisResult<()> = async {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            conn.set_ex(key, value.to_string(), CACHE_TTL_SECS).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("Cache write failed for {}: {}", key, e);
+        }
+    }
+
+    /// Completion stamp of the latest sync, if one has been recorded
+    async fn latest_sync(&self) -> redis::RedisResult<Option<String>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        conn.get(SYNC_COMPLETED_KEY).await
+    }
+}
+
+/// `at` rounded up to a whole second
+fn next_second(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.duration_trunc(TimeDelta::seconds(1)).map_or(at, |second| {
+        if second < at { second + TimeDelta::seconds(1) } else { second }
+    })
+}
+
+fn cache_key(generation: &str, kind: &str, id: &str) -> String {
+    format!("stillwater:cache:{}:{}:{}", generation, kind, id.to_lowercase())
+}
+
+/// Spawn the background task that warms the cache after each sync
+///
+/// Polls for a new sync stamp; when one appears, portfolio summaries for every
+/// watched owner and stats for every pool are computed into the new
+/// generation before handlers switch to it, so the first dashboard load after
+/// a sync is served from cache.
+pub fn spawn_cache_warmer(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WARM_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let stamp = match state.cache.latest_sync().await {
+                Ok(Some(stamp)) => stamp,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to read sync stamp: {}", e);
+                    continue;
+                }
+            };
+            if *state.cache.generation.read().unwrap() == stamp {
+                continue;
+            }
+
+            let warmed = warm_generation(&state, &stamp).await;
+            *state.cache.generation.write().unwrap() = stamp.clone();
+            state.cache.mark_modified();
+            info!("Cache warmed for sync {} ({} responses)", stamp, warmed);
+        }
+    });
+}
+
+/// Precompute the heaviest responses into `generation`, returning how many were stored
+async fn warm_generation(state: &AppState, generation: &str) -> usize {
+    let mut warmed = 0;
+
+    match stillwater_db::get_watchlist(&state.db_pool).await {
+        Ok(watched) => {
+            for owner in watched {
+                match build_portfolio(state, &OwnerScope::from(owner.address), false).await {
+                    Ok(portfolio) => {
+                        let value = serde_json::to_value(portfolio).unwrap();
+                        let key = cache_key(generation, "portfolio", &owner.address.canonical());
+                        state.cache.put_in(&key, &value).await;
+                        warmed += 1;
+                    }
+                    Err(e) => error!("Failed to warm portfolio for {}: {}", owner.address, e),
+                }
+            }
+        }
+        Err(e) => error!("Failed to load watchlist for cache warming: {}", e),
+    }
+
+    match stillwater_db::get_pool_ids(&state.db_pool).await {
+        Ok(pool_ids) => {
+            for pool_id in pool_ids {
+                match build_pool_stats(&state.db_pool, &pool_id).await {
+                    Ok(Some(stats)) => {
+                        let value = serde_json::to_value(stats).unwrap();
+                        state
+                            .cache
+                            .put_in(&cache_key(generation, "pool_stats", &pool_id), &value)
+                            .await;
+                        warmed += 1;
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("Failed to warm stats for pool {}: {}", pool_id, e),
+                }
+            }
+        }
+        Err(e) => error!("Failed to load pools for cache warming: {}", e),
+    }
+
+    warmed
+}
+
+/// Spawn the background task that refreshes cached responses as data changes
+///
+/// Listens for database change events: new positions and range crossings
+/// recompute the owner's portfolio, new swaps recompute the pool's stats.
+/// Events are batched for `EVENT_DEBOUNCE` before recomputing.
+pub fn spawn_event_listener(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            let mut listener = match EventListener::connect(&state.db_pool).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Failed to listen for data changes: {}", e);
+                    tokio::time::sleep(EVENT_RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+            info!("Listening for data change events");
+
+            let mut owners = HashSet::new();
+            let mut pools = HashSet::new();
+            let mut interval = tokio::time::interval(EVENT_DEBOUNCE);
+            loop {
+                tokio::select! {
+                    event = listener.recv() => match event {
+                        Ok(DbEvent::PositionInserted { owner, .. })
+                        | Ok(DbEvent::RangeCrossed { owner, .. }) => {
+                            owners.insert(owner.to_lowercase());
+                        }
+                        Ok(DbEvent::SwapInserted { pool_id, .. })
+                        | Ok(DbEvent::PoolCreated { pool_id, .. }) => {
+                            pools.insert(pool_id);
+                        }
+                        Err(e) => {
+                            warn!("Data change listener failed: {}", e);
+                            break;
+                        }
+                    },
+                    _ = interval.tick() => {
+                        refresh_changed(&state, owners.drain(), pools.drain()).await;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Recompute cached responses affected by data changes into the current generation
+async fn refresh_changed(
+    state: &AppState,
+    owners: impl Iterator<Item = String>,
+    pools: impl Iterator<Item = String>,
+) {
+    let mut changed = false;
+    for owner in owners {
+        changed = true;
+        let Ok(address) = Address::parse(&owner) else {
+            continue;
+        };
+        match build_portfolio(state, &OwnerScope::from(address), false).await {
+            Ok(portfolio) => {
+                let value = serde_json::to_value(portfolio).unwrap();
+                state.cache.put("portfolio", &owner, &value).await;
+            }
+            Err(e) => error!("Failed to refresh portfolio for {}: {}", owner, e),
+        }
+    }
+
+    for pool_id in pools {
+        changed = true;
+        match build_pool_stats(&state.db_pool, &pool_id).await {
+            Ok(Some(stats)) => {
+                let value = serde_json::to_value(stats).unwrap();
+                state.cache.put("pool_stats", &pool_id, &value).await;
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to refresh stats for pool {}: {}", pool_id, e),
+        }
+    }
+
+    if changed {
+        state.cache.mark_modified();
+    }
+}
@@ -0,0 +1,321 @@
+use alloy::primitives::keccak256;
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+use crate::cache::ResponseCache;
+
+/// Answer conditional GETs with `304 Not Modified` when nothing changed
+///
+/// `If-Modified-Since` is checked against the cache's last modification
+/// (the last sync, change refresh or API write) before the handler runs, so
+/// an unchanged poll costs no database work. Otherwise the JSON body's hash
+/// is sent as `ETag` and compared with `If-None-Match`, which takes
+/// precedence when both are sent. Streamed and error responses pass through.
+pub async fn conditional_get(
+    State(cache): State<ResponseCache>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Read before the handler runs, so a change landing meanwhile isn't
+    // stamped onto a response computed without it
+    let last_modified = cache.last_modified();
+    let if_none_match = header_str(request.headers(), header::IF_NONE_MATCH).map(str::to_string);
+    let if_modified_since = header_str(request.headers(), header::IF_MODIFIED_SINCE)
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+
+    if if_none_match.is_none() && if_modified_since.is_some_and(|since| since >= last_modified) {
+        return not_modified(None, last_modified);
+    }
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if response.status() != StatusCode::OK || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read response body for its ETag: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let etag = content_etag(&bytes);
+    if if_none_match.as_deref().is_some_and(|tags| etag_matches(tags, &etag)) {
+        return not_modified(Some(&etag), last_modified);
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&http_date(last_modified)) {
+        parts.headers.insert(header::LAST_MODIFIED, value);
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Count successful writes through the API as data changes
+///
+/// Deleting, restoring or importing positions changes what reads return
+/// without a sync, so later conditional GETs must not be answered with 304.
+pub async fn track_writes(
+    State(cache): State<ResponseCache>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_write = !matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let response = next.run(request).await;
+    if is_write && response.status().is_success() {
+        cache.mark_modified();
+    }
+    response
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn not_modified(etag: Option<&str>, last_modified: DateTime<Utc>) -> Response {
+    let mut response = StatusCode::NOT_MODIFIED.into_response();
+    if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&http_date(last_modified)) {
+        response.headers_mut().insert(header::LAST_MODIFIED, value);
+    }
+    response
+}
+
+/// Strong ETag from a hash of the response body
+fn content_etag(body: &[u8]) -> String {
+    let hash = keccak256(body);
+    format!("\"{}\"", alloy::hex::encode(&hash[..16]))
+}
+
+/// Whether an `If-None-Match` list names `etag` (weak comparison, as for GETs)
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// A time as an HTTP date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+fn http_date(at: DateTime<Utc>) -> String {
+    at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        routing::{get, post},
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serve JSON, text, error and write routes behind both middlewares,
+    /// returning the base URL and how often the JSON handler ran
+    async fn serve(cache: ResponseCache) -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let app = Router::new()
+            .route(
+                "/json",
+                get(move || {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    async { Json(json!({ "pool": "0xabc", "liquidity": "1000" })) }
+                }),
+            )
+            .route("/text", get(|| async { "plain" }))
+            .route(
+                "/missing",
+                get(|| async { (StatusCode::NOT_FOUND, Json(json!({ "error": "missing" }))) }),
+            )
+            .route("/write", post(|| async { StatusCode::OK }))
+            .route("/reject", post(|| async { StatusCode::BAD_REQUEST }))
+            .layer(axum::middleware::from_fn_with_state(cache.clone(), conditional_get))
+            .layer(axum::middleware::from_fn_with_state(cache, track_writes));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, calls)
+    }
+
+    fn test_cache() -> ResponseCache {
+        ResponseCache::new(redis::Client::open("redis://127.0.0.1/").unwrap())
+    }
+
+    async fn get_with(url: &str, headers: &[(header::HeaderName, &str)]) -> reqwest::Response {
+        let mut request = reqwest::Client::new().get(url);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), *value);
+        }
+        request.send().await.unwrap()
+    }
+
+    fn etag_of(response: &reqwest::Response) -> String {
+        response.headers()[header::ETAG.as_str()].to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_json_responses_carry_validators() {
+        let cache = test_cache();
+        let (url, _) = serve(cache.clone()).await;
+
+        let response = get_with(&format!("{url}/json"), &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = etag_of(&response);
+        assert!(etag.starts_with('"') && etag.ends_with('"'), "strong ETag, got {etag}");
+        assert_eq!(
+            response.headers()[header::LAST_MODIFIED.as_str()],
+            http_date(cache.last_modified()).as_str()
+        );
+        assert_eq!(response.json::<serde_json::Value>().await.unwrap()["pool"], "0xabc");
+    }
+
+    #[tokio::test]
+    async fn test_matching_etag_is_not_modified() {
+        let (url, _) = serve(test_cache()).await;
+        let etag = etag_of(&get_with(&format!("{url}/json"), &[]).await);
+
+        let response = get_with(&format!("{url}/json"), &[(header::IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(etag_of(&response), etag);
+        assert!(response.headers().contains_key(header::LAST_MODIFIED.as_str()));
+        assert!(response.bytes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_weak_tags_match_the_strong_etag() {
+        let (url, _) = serve(test_cache()).await;
+        let etag = etag_of(&get_with(&format!("{url}/json"), &[]).await);
+
+        for tags in [format!("W/{etag}"), format!("\"other\", W/{etag}"), "*".to_string()] {
+            let response =
+                get_with(&format!("{url}/json"), &[(header::IF_NONE_MATCH, &tags)]).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "If-None-Match: {tags}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_other_etags_get_the_full_response() {
+        let (url, _) = serve(test_cache()).await;
+        let etag = etag_of(&get_with(&format!("{url}/json"), &[]).await);
+
+        for tags in ["\"other\"", "W/\"other\""] {
+            let response = get_with(&format!("{url}/json"), &[(header::IF_NONE_MATCH, tags)]).await;
+            assert_eq!(response.status(), StatusCode::OK, "If-None-Match: {tags}");
+            assert_eq!(etag_of(&response), etag);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_since_skips_the_handler() {
+        let cache = test_cache();
+        let (url, calls) = serve(cache.clone()).await;
+
+        let since = http_date(cache.last_modified());
+        let response =
+            get_with(&format!("{url}/json"), &[(header::IF_MODIFIED_SINCE, &since)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::LAST_MODIFIED.as_str()], since.as_str());
+        assert!(!response.headers().contains_key(header::ETAG.as_str()));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_modified_since_gets_the_full_response() {
+        let cache = test_cache();
+        let (url, calls) = serve(cache.clone()).await;
+        let since = http_date(cache.last_modified());
+        cache.mark_modified();
+
+        let response =
+            get_with(&format!("{url}/json"), &[(header::IF_MODIFIED_SINCE, &since)]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match_takes_precedence() {
+        let cache = test_cache();
+        let (url, calls) = serve(cache.clone()).await;
+        let etag = etag_of(&get_with(&format!("{url}/json"), &[]).await);
+        let fresh = http_date(cache.last_modified());
+        let stale = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+        // A changed body is sent even though nothing was modified since
+        let response = get_with(
+            &format!("{url}/json"),
+            &[(header::IF_NONE_MATCH, "\"other\""), (header::IF_MODIFIED_SINCE, &fresh)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // An unchanged body is not, however old the date
+        let response = get_with(
+            &format!("{url}/json"),
+            &[(header::IF_NONE_MATCH, &etag), (header::IF_MODIFIED_SINCE, stale)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_text_and_errors_pass_through() {
+        let (url, _) = serve(test_cache()).await;
+
+        for path in ["text", "missing"] {
+            let response =
+                get_with(&format!("{url}/{path}"), &[(header::IF_NONE_MATCH, "*")]).await;
+            assert_ne!(response.status(), StatusCode::NOT_MODIFIED, "/{path}");
+            assert!(!response.headers().contains_key(header::ETAG.as_str()), "/{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_successful_writes_mark_modified() {
+        let cache = test_cache();
+        let (url, _) = serve(cache.clone()).await;
+        let client = reqwest::Client::new();
+        let initial = cache.last_modified();
+
+        get_with(&format!("{url}/json"), &[]).await;
+        client.post(format!("{url}/reject")).send().await.unwrap();
+        assert_eq!(cache.last_modified(), initial);
+
+        client.post(format!("{url}/write")).send().await.unwrap();
+        assert!(cache.last_modified() > initial);
+    }
+
+    #[test]
+    fn test_content_etag_is_stable_and_strong() {
+        let etag = content_etag(b"{\"pool\":\"0xabc\"}");
+        assert_eq!(etag, content_etag(b"{\"pool\":\"0xabc\"}"));
+        assert_ne!(etag, content_etag(b"{\"pool\":\"0xdef\"}"));
+        assert!(!etag.starts_with("W/"));
+        assert_eq!(etag.len(), 34);
+    }
+
+    #[test]
+    fn test_http_date_format() {
+        let at = DateTime::parse_from_rfc3339("1994-11-06T08:49:37Z").unwrap().to_utc();
+        assert_eq!(http_date(at), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+}
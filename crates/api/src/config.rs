@@ -1,19 +1,55 @@
 use redis::Client as RedisClient;
-use sqlx::{PgPool, postgres::PgPoolOptions};
-use tracing_subscriber::EnvFilter;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use stillwater_analytics::{
+    CompoundConfig, DisplayOptions, FeeModelRegistry, FeeVelocityConfig, HealthRules,
+    HookRegistry, JitConfig, QuoteCurrencies, RiskCategory, RiskHealthRules, TwapConfig,
+    parse_duration,
+};
+use tracing_subscriber::{
+    EnvFilter, Layer, filter::Targets, layer::SubscriberExt, util::SubscriberInitExt,
+};
+use stillwater_db::ReadOnlyDb;
 use stillwater_models::BlockchainService;
 
+use crate::admin::AdminKey;
+use crate::auth::SiweConfig;
+use crate::demo::{DEFAULT_DEMO_RATE_LIMIT, DemoMode};
+use crate::pseudonym::PseudonymKey;
+use crate::slowlog::{DEFAULT_SLOW_REQUEST_MS, SlowRequests, StatementCapture};
+use crate::volatility::{DeribitDvol, ImpliedVolatilitySources, StaticVolatility};
+
 /// Initializes tracing (logging)
-pub fn init_tracing() {
-    tracing_subscriber::fmt().with_env_filter(EnvFilter::new("info")).init();
+///
+/// With slow request logging on, the statements sqlx logs at debug level are
+/// recorded per request too, without being printed.
+pub fn init_tracing(slow_requests: &SlowRequests) {
+    let capture = slow_requests.threshold.map(|_| {
+        StatementCapture.with_filter(
+            Targets::new().with_target(StatementCapture::TARGET, StatementCapture::LEVEL),
+        )
+    });
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::new("info")))
+        .with(capture)
+        .init();
+}
+
+/// Reads the slow request threshold from `SLOW_REQUEST_MS` (`0` turns slow request logging off)
+pub fn init_slow_requests() -> SlowRequests {
+    let ms = std::env::var("SLOW_REQUEST_MS")
+        .ok()
+        .map(|ms| ms.trim().parse::<u64>().expect("SLOW_REQUEST_MS must be a number"))
+        .unwrap_or(DEFAULT_SLOW_REQUEST_MS);
+    SlowRequests { threshold: (ms > 0).then(|| Duration::from_millis(ms)) }
 }
 
-/// Initializes PostgreSQL connection pool
+/// Initializes PostgreSQL connection pool, scoped to `DB_SCHEMA` if set
 pub async fn init_database() -> PgPool {
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env");
-    PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
+    let schema = stillwater_db::schema_from_env().expect("Invalid DB_SCHEMA");
+    stillwater_db::connect(&database_url, 5, schema.as_deref())
         .await
         .expect("Failed to create Postgres pool")
 }
@@ -29,3 +65,152 @@ pub fn init_blockchain() -> BlockchainService {
     let rpc_url = std::env::var("ETHEREUM_RPC_URL").expect("ETHEREUM_RPC_URL must be set in .env");
     BlockchainService::new(&rpc_url).expect("Failed to create blockchain service")
 }
+
+/// Initializes Sign-In with Ethereum settings
+pub fn init_siwe() -> SiweConfig {
+    SiweConfig {
+        domain: std::env::var("SIWE_DOMAIN").unwrap_or_else(|_| "127.0.0.1:3000".to_string()),
+        uri: std::env::var("SIWE_URI").unwrap_or_else(|_| "http://127.0.0.1:3000".to_string()),
+        chain_id: std::env::var("CHAIN_ID")
+            .ok()
+            .map(|id| id.parse().expect("CHAIN_ID must be a number"))
+            .unwrap_or(1301), // Unichain Sepolia
+    }
+}
+
+/// Initializes the fee model registry (see `FeeModelRegistry::from_env`)
+///
+/// Hooks listed in `DYNAMIC_FEE_HOOKS` (comma-separated) are treated as
+/// dynamic-fee even if their pools don't carry the dynamic fee flag.
+pub fn init_fee_models() -> FeeModelRegistry {
+    FeeModelRegistry::from_env()
+}
+
+/// Loads the known hook registry (see `HookRegistry::from_env`)
+///
+/// `KNOWN_HOOKS` lists `address=kind[:name]` entries, kind being
+/// `limit_order`, `twamm` or `dynamic_fee`.
+pub fn init_hook_registry() -> HookRegistry {
+    HookRegistry::from_env().unwrap_or_else(|e| panic!("KNOWN_HOOKS must be valid: {}", e))
+}
+
+/// Loads position health rules per risk bucket
+///
+/// `HEALTH_RULES_<BUCKET>` (e.g. `HEALTH_RULES_DEGEN`) sets one bucket's rules. Buckets without
+/// their own rules use `HEALTH_RULES` when set, otherwise the built-in per-bucket rules.
+/// `HEALTH_PNL_GRACE` (e.g. `12h`, or `0` for none) replaces every bucket's P&L grace period.
+pub fn init_health_rules() -> RiskHealthRules {
+    let load = |var: &str| match std::env::var(var) {
+        Ok(spec) if !spec.trim().is_empty() => Some(
+            HealthRules::parse(&spec)
+                .unwrap_or_else(|e| panic!("{} must be valid health rules: {}", var, e)),
+        ),
+        _ => None,
+    };
+
+    let mut rules = match load("HEALTH_RULES") {
+        Some(rules) => RiskHealthRules::uniform(rules),
+        None => RiskHealthRules::default(),
+    };
+    for category in RiskCategory::ALL {
+        let var = format!("HEALTH_RULES_{}", category.as_str().to_uppercase());
+        if let Some(bucket_rules) = load(&var) {
+            rules.by_category.insert(category, bucket_rules);
+        }
+    }
+    match std::env::var("HEALTH_PNL_GRACE") {
+        Ok(grace) if grace.trim() == "0" => rules.with_pnl_grace(chrono::Duration::zero()),
+        Ok(grace) if !grace.trim().is_empty() => rules.with_pnl_grace(
+            parse_duration(&grace)
+                .unwrap_or_else(|e| panic!("HEALTH_PNL_GRACE must be a duration: {}", e)),
+        ),
+        _ => rules,
+    }
+}
+
+/// Initializes display precision from `DISPLAY_*` variables (see `DisplayOptions::from_env`)
+pub fn init_display_options() -> DisplayOptions {
+    DisplayOptions::from_env().expect("Invalid display options")
+}
+
+/// Connects the ad hoc query pool if `QUERY_DATABASE_URL` is set (see `ReadOnlyDb::from_env`)
+pub async fn init_query_db() -> Option<ReadOnlyDb> {
+    ReadOnlyDb::from_env().await.expect("Failed to connect ad hoc query pool")
+}
+
+/// Initializes the TWAP window from `TWAP_WINDOW_MINUTES` (see `TwapConfig::from_env`)
+pub fn init_twap_config() -> TwapConfig {
+    TwapConfig::from_env().expect("Invalid TWAP window")
+}
+
+/// Initializes JIT liquidity thresholds from `JIT_*` variables (see `JitConfig::from_env`)
+pub fn init_jit_config() -> JitConfig {
+    JitConfig::from_env().expect("Invalid JIT config")
+}
+
+/// Initializes implied volatility sources for volatility comparisons
+///
+/// `IMPLIED_VOLATILITY` figures (see `StaticVolatility::from_env`) are asked
+/// first, then Deribit's DVOL when `DERIBIT_DVOL` is `true`. None of either
+/// leaves comparisons to request-supplied figures.
+pub fn init_implied_volatility() -> ImpliedVolatilitySources {
+    let mut sources = ImpliedVolatilitySources::new();
+    if let Some(figures) = StaticVolatility::from_env().expect("Invalid IMPLIED_VOLATILITY") {
+        sources.push(Arc::new(figures));
+    }
+    if std::env::var("DERIBIT_DVOL").is_ok_and(|v| v.eq_ignore_ascii_case("true")) {
+        sources.push(Arc::new(DeribitDvol::from_env()));
+    }
+    sources
+}
+
+/// Initializes quote currencies from `QUOTE_*` variables (see `QuoteCurrencies::from_env`)
+pub fn init_quote_currencies() -> QuoteCurrencies {
+    QuoteCurrencies::from_env().expect("Invalid quote currency config")
+}
+
+/// Initializes the compound threshold from `COMPOUND_*` variables (see `CompoundConfig::from_env`)
+pub fn init_compound_config() -> CompoundConfig {
+    CompoundConfig::from_env().expect("Invalid compound config")
+}
+
+/// Initializes fee velocity windows from `FEE_VELOCITY_*` (see `FeeVelocityConfig::from_env`)
+pub fn init_fee_velocity_config() -> FeeVelocityConfig {
+    FeeVelocityConfig::from_env().expect("Invalid fee velocity config")
+}
+
+/// Initializes public demo mode from `DEMO_ADDRESSES` (comma-separated) and `DEMO_RATE_LIMIT`
+///
+/// Demo mode stays off unless at least one showcase address is set.
+pub fn init_demo_mode() -> Option<DemoMode> {
+    let addresses: Vec<String> = std::env::var("DEMO_ADDRESSES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .collect();
+    if addresses.is_empty() {
+        return None;
+    }
+    let requests_per_minute = std::env::var("DEMO_RATE_LIMIT")
+        .ok()
+        .map(|limit| limit.trim().parse().expect("DEMO_RATE_LIMIT must be a number"))
+        .unwrap_or(DEFAULT_DEMO_RATE_LIMIT);
+    Some(DemoMode::new(addresses, requests_per_minute))
+}
+
+/// Loads the sync control routes' key from `ADMIN_API_KEY`; they stay disabled without one
+pub fn init_admin_key() -> Option<AdminKey> {
+    match std::env::var("ADMIN_API_KEY") {
+        Ok(key) if !key.trim().is_empty() => Some(AdminKey::new(&key)),
+        _ => None,
+    }
+}
+
+/// Loads the leaderboard pseudonym secret from `PSEUDONYM_SECRET`
+pub fn init_pseudonym_key() -> Option<PseudonymKey> {
+    match std::env::var("PSEUDONYM_SECRET") {
+        Ok(secret) if !secret.trim().is_empty() => Some(PseudonymKey::new(&secret)),
+        _ => None,
+    }
+}
@@ -0,0 +1,174 @@
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, MatchedPath, RawPathParams, Request, State},
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use stillwater_db::get_position_by_id;
+use stillwater_models::canonical_address;
+use tracing::error;
+
+use crate::auth::bearer_token;
+use crate::handlers::auth::authorized_addresses;
+use crate::state::AppState;
+
+/// Unauthenticated requests per client per minute in demo mode unless `DEMO_RATE_LIMIT` is set
+pub const DEFAULT_DEMO_RATE_LIMIT: u32 = 10;
+
+/// Length of a rate limit window
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Clients tracked before expired windows are swept
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Routes open to anyone in demo mode, so operators can still sign in
+const PUBLIC_PATHS: &[&str] = &["/", "/health", "/auth/nonce", "/auth/verify"];
+
+/// Routes whose `{id}` is a position id, readable when the position is a showcase address's
+const POSITION_ID_ROUTES: &[&str] = &["/positions/{id}/chart"];
+
+/// Public demo: anonymous, rate-limited reads of a few showcase addresses
+///
+/// Requests with a valid API key are unaffected.
+pub struct DemoMode {
+    /// Showcase owner addresses, lowercased
+    pub addresses: HashSet<String>,
+    pub requests_per_minute: u32,
+    /// Start and request count of each client's current window
+    windows: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl DemoMode {
+    pub fn new(addresses: impl IntoIterator<Item = String>, requests_per_minute: u32) -> Self {
+        Self {
+            addresses: addresses.into_iter().map(|a| canonical_address(&a)).collect(),
+            requests_per_minute,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_showcase(&self, owner: &str) -> bool {
+        self.addresses.contains(&canonical_address(owner))
+    }
+
+    /// Count a request from `client`; the wait until its window resets when over the limit
+    fn check_rate(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= MAX_TRACKED_CLIENTS {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_WINDOW);
+        }
+
+        let (start, count) = windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= RATE_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.requests_per_minute {
+            return Err(RATE_WINDOW.saturating_sub(now.duration_since(*start)));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Restrict anonymous requests to showcase addresses and rate-limit them
+///
+/// Only runs when `DEMO_ADDRESSES` is set. Without a valid API key or the
+/// admin key, a client gets `DEMO_RATE_LIMIT` requests a minute (429 with
+/// `Retry-After` beyond that), limited to GETs of routes whose `{owner}` is a
+/// showcase address (or a showcase position's chart) plus the public paths.
+/// Anything else is 403.
+pub async fn demo_gate(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(demo) = state.demo.clone() else {
+        return next.run(request).await;
+    };
+    if state.admin.as_ref().is_some_and(|admin| admin.accepts(request.headers())) {
+        return next.run(request).await;
+    }
+    if bearer_token(request.headers()).is_some()
+        && authorized_addresses(&state, request.headers()).await.is_ok()
+    {
+        return next.run(request).await;
+    }
+
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |ConnectInfo(addr)| addr.ip());
+    if let Err(retry_after) = demo.check_rate(client, Instant::now()) {
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({ "error": "Demo rate limit exceeded" })),
+        )
+            .into_response();
+        if let Ok(value) = HeaderValue::from_str(&retry_after.as_secs().max(1).to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        return response;
+    }
+
+    if PUBLIC_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+    let (mut parts, body) = request.into_parts();
+    let route = parts.extensions.get::<MatchedPath>().cloned();
+    let params = RawPathParams::from_request_parts(&mut parts, &state).await.ok();
+    let showcase = parts.method == Method::GET
+        && match (&route, &params) {
+            (Some(route), Some(params)) => {
+                showcase_route(&state, &demo, route.as_str(), params).await
+            }
+            _ => false,
+        };
+    if !showcase {
+        let mut addresses: Vec<&String> = demo.addresses.iter().collect();
+        addresses.sort();
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "Demo mode: only showcase addresses can be read without an API key",
+                "demo_addresses": addresses,
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
+
+/// Whether a route's `{owner}` is a showcase address, or its `{id}` a showcase position
+///
+/// `{id}` only counts on `POSITION_ID_ROUTES`; elsewhere it names something
+/// else (a backtest, a job, an alert rule) that demo mode never exposes.
+async fn showcase_route(
+    state: &AppState,
+    demo: &DemoMode,
+    route: &str,
+    params: &RawPathParams,
+) -> bool {
+    for (key, value) in params.iter() {
+        match key {
+            "owner" => return demo.is_showcase(value),
+            "id" if POSITION_ID_ROUTES.contains(&route) => {
+                let Ok(id) = value.parse::<i64>() else {
+                    return false;
+                };
+                return match get_position_by_id(&state.db_pool, id).await {
+                    Ok(position) => {
+                        position.is_some_and(|p| demo.is_showcase(&p.owner.canonical()))
+                    }
+                    Err(e) => {
+                        error!("Failed to fetch position for demo check: {}", e);
+                        false
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,113 @@
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Query, Request, State},
+    http::{HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::Deserialize;
+use stillwater_analytics::{DisplayOptions, RoundingMode};
+use tracing::warn;
+
+use crate::state::AppState;
+
+/// Header carrying the configured currency symbol, for clients formatting amounts
+pub const CURRENCY_SYMBOL_HEADER: &str = "x-currency-symbol";
+
+/// Per-request overrides of the configured display options
+#[derive(Debug, Default, Deserialize)]
+pub struct DisplayParams {
+    /// Significant figures to round to (`0` returns unrounded values)
+    pub sig_figs: Option<u32>,
+    /// `half_even`, `half_up` or `down`
+    pub rounding: Option<String>,
+}
+
+/// Round every fractional value in JSON responses to the display precision
+///
+/// Decimals serialize as strings, so strings holding a fractional number are
+/// rounded along with JSON numbers. Integers (liquidity, ids, ticks) and
+/// non-numeric strings (addresses, hashes) are left unchanged, as are non-JSON
+/// responses such as ledger exports.
+pub async fn apply_display_options(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let params = Query::<DisplayParams>::try_from_uri(request.uri())
+        .map(|Query(params)| params)
+        .unwrap_or_default();
+    let mut options = (*state.display).clone();
+    if let Some(figures) = params.sig_figs {
+        options.significant_figures = (figures > 0).then_some(figures);
+    }
+    if let Some(rounding) = params.rounding.as_deref().and_then(RoundingMode::parse) {
+        options.rounding = rounding;
+    }
+
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    if let Some(symbol) = &options.currency_symbol
+        && let Ok(value) = HeaderValue::from_str(symbol)
+    {
+        parts.headers.insert(CURRENCY_SYMBOL_HEADER, value);
+    }
+    if options.significant_figures.is_none() {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read response body for rounding: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    round_json(&mut value, &options);
+    let body = serde_json::to_vec(&value).unwrap_or_else(|_| bytes.to_vec());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Round fractional numbers and decimal strings throughout a JSON value
+fn round_json(value: &mut serde_json::Value, options: &DisplayOptions) {
+    match value {
+        serde_json::Value::String(s) => {
+            if s.contains('.')
+                && let Ok(decimal) = Decimal::from_str(s)
+            {
+                *s = options.round(decimal).to_string();
+            }
+        }
+        serde_json::Value::Number(n) => {
+            if n.is_f64()
+                && let Some(decimal) = n.as_f64().and_then(Decimal::from_f64)
+                && let Some(rounded) = options.round(decimal).to_f64()
+                && let Some(number) = serde_json::Number::from_f64(rounded)
+            {
+                *n = number;
+            }
+        }
+        serde_json::Value::Array(items) => {
+            items.iter_mut().for_each(|item| round_json(item, options));
+        }
+        serde_json::Value::Object(fields) => {
+            fields.values_mut().for_each(|field| round_json(field, options));
+        }
+        serde_json::Value::Bool(_) | serde_json::Value::Null => {}
+    }
+}
@@ -0,0 +1,211 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use serde::Deserialize;
+use stillwater_db::{
+    add_account_address, create_account, delete_account, get_account, remove_account_address,
+};
+use stillwater_models::{Account, Address};
+use tracing::{error, info};
+
+use crate::address::owner_param;
+use crate::handlers::auth::authorized_addresses;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAccountRequest {
+    /// 1-64 lowercase letters, digits, `-` or `_`
+    pub name: String,
+    pub addresses: Vec<String>,
+}
+
+fn internal_error(context: &str, e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+}
+
+fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Account not found" })))
+}
+
+/// Check the API key is linked to every one of `addresses`
+async fn authorize_addresses(
+    state: &AppState,
+    headers: &HeaderMap,
+    addresses: &[Address],
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let linked = authorized_addresses(state, headers).await?;
+    if let Some(address) = addresses.iter().find(|a| !linked.contains(a)) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": format!("{} is not linked to this API key", address)
+            })),
+        ));
+    }
+    Ok(())
+}
+
+/// The account, answering `404` when there's none
+async fn load_account(
+    state: &AppState,
+    name: &str,
+) -> Result<Account, (StatusCode, Json<serde_json::Value>)> {
+    match get_account(&state.db_pool, name).await {
+        Ok(Some(account)) => Ok(account),
+        Ok(None) => Err(not_found()),
+        Err(e) => Err(internal_error("Failed to fetch account", e)),
+    }
+}
+
+/// POST /accounts
+/// Group addresses into an account, readable as `account:<name>` wherever an `:owner` is taken
+/// (requires an API key linked to every address)
+pub async fn create_account_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CreateAccountRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = Account::validate_name(&req.name) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e })));
+    }
+    if req.addresses.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "addresses must not be empty" })),
+        );
+    }
+    let mut addresses = Vec::with_capacity(req.addresses.len());
+    for address in &req.addresses {
+        match owner_param(address) {
+            Ok(address) => addresses.push(address),
+            Err(response) => return response,
+        }
+    }
+    if let Err(response) = authorize_addresses(&state, &headers, &addresses).await {
+        return response;
+    }
+
+    match create_account(&state.db_pool, &req.name, &addresses).await {
+        Ok(Some(account)) => {
+            info!("Created account {} of {} addresses", account.name, account.addresses.len());
+            (StatusCode::CREATED, Json(serde_json::to_value(account).unwrap()))
+        }
+        Ok(None) => {
+            (StatusCode::CONFLICT, Json(serde_json::json!({ "error": "Account name is taken" })))
+        }
+        Err(e) => internal_error("Failed to create account", e),
+    }
+}
+
+/// GET /accounts/:name
+/// An account and its addresses
+pub async fn get_account_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match load_account(&state, &name).await {
+        Ok(account) => (StatusCode::OK, Json(serde_json::to_value(account).unwrap())),
+        Err(response) => response,
+    }
+}
+
+/// DELETE /accounts/:name
+/// Ungroup an account; its addresses and their data are untouched
+/// (requires an API key linked to every address of the account)
+pub async fn delete_account_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let account = match load_account(&state, &name).await {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_addresses(&state, &headers, &account.addresses).await {
+        return response;
+    }
+
+    match delete_account(&state.db_pool, &name).await {
+        Ok(true) => {
+            info!("Deleted account {}", name);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": name })))
+        }
+        Ok(false) => not_found(),
+        Err(e) => internal_error("Failed to delete account", e),
+    }
+}
+
+/// PUT /accounts/:name/addresses/:address
+/// Add an address to an account (requires an API key linked to it and the account's addresses)
+pub async fn add_account_address_handler(
+    State(state): State<AppState>,
+    Path((name, address)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let address = match owner_param(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let mut account = match load_account(&state, &name).await {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+    account.addresses.push(address);
+    if let Err(response) = authorize_addresses(&state, &headers, &account.addresses).await {
+        return response;
+    }
+
+    let added = async {
+        if !add_account_address(&state.db_pool, &name, &address).await? {
+            return Ok(None);
+        }
+        get_account(&state.db_pool, &name).await
+    };
+    match added.await {
+        Ok(Some(account)) => {
+            info!("Added {} to account {}", address, name);
+            (StatusCode::OK, Json(serde_json::to_value(account).unwrap()))
+        }
+        Ok(None) => not_found(),
+        Err(e) => internal_error("Failed to add account address", e),
+    }
+}
+
+/// DELETE /accounts/:name/addresses/:address
+/// Take an address out of an account (requires an API key linked to every address of the
+/// account)
+pub async fn remove_account_address_handler(
+    State(state): State<AppState>,
+    Path((name, address)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let address = match owner_param(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let account = match load_account(&state, &name).await {
+        Ok(account) => account,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_addresses(&state, &headers, &account.addresses).await {
+        return response;
+    }
+
+    match remove_account_address(&state.db_pool, &name, &address).await {
+        Ok(true) => {
+            info!("Removed {} from account {}", address, name);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": address })))
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Address is not in this account" })),
+        ),
+        Err(e) => internal_error("Failed to remove account address", e),
+    }
+}
@@ -0,0 +1,505 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use stillwater_analytics::{
+    DEFAULT_FEE_GROWTH_TOLERANCE, DEFAULT_KEEP_LARGEST, MAX_LP_FEE, reconcile_fee_growth,
+};
+use stillwater_db::{
+    PositionFilter, delete_pool_fee_override, delete_swap_downsampling, delete_vault, enqueue_job,
+    find_positions, get_pool_by_id, get_pool_fee_growth, get_pool_fee_overrides,
+    get_recent_sync_runs, get_swap_downsampling, get_swaps_for_pool_between, get_sync_checkpoints,
+    get_sync_schedule, get_vaults, set_pool_fee_override, set_swap_downsampling, set_sync_paused,
+    upsert_vault,
+};
+use stillwater_models::JobKind;
+use tracing::{error, info, warn};
+
+use crate::address::owner_param;
+use crate::state::AppState;
+
+/// Maximum number of sync runs returned
+const MAX_SYNC_RUNS: i64 = 500;
+
+/// Maximum number of checkpoints `GET /admin/sync` returns
+const MAX_LISTED_CHECKPOINTS: i64 = 100;
+
+/// Longest window of fee growth samples a reconciliation covers
+const MAX_RECONCILIATION_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize)]
+pub struct SyncRunsParams {
+    /// Number of recent runs to list (default 50)
+    pub limit: Option<i64>,
+}
+
+/// GET /admin/sync-runs?limit=N
+/// Recent position syncs with per-stage timings (fetch, parse, dedupe, insert) and row counts
+pub async fn get_sync_runs_handler(
+    State(state): State<AppState>,
+    Query(params): Query<SyncRunsParams>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(50).clamp(1, MAX_SYNC_RUNS);
+
+    match get_recent_sync_runs(&state.db_pool, limit).await {
+        Ok(runs) => {
+            let runs: Vec<serde_json::Value> = runs
+                .into_iter()
+                .map(|run| {
+                    let total_ms = run.total_ms();
+                    let mut value = serde_json::to_value(run).unwrap();
+                    value["total_ms"] = total_ms.into();
+                    value
+                })
+                .collect();
+            (StatusCode::OK, Json(serde_json::json!({ "runs": runs })))
+        }
+        Err(e) => {
+            error!("Failed to get sync runs: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PoolFeeOverrideRequest {
+    /// LP fee in hundredths of a bip (3000 = 0.3%)
+    pub fee: i32,
+    /// Where the figure came from
+    pub note: Option<String>,
+}
+
+/// GET /admin/pool-fees
+/// Pools with an operator-set LP fee
+pub async fn get_pool_fee_overrides_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match get_pool_fee_overrides(&state.db_pool).await {
+        Ok(overrides) => (StatusCode::OK, Json(serde_json::json!({ "overrides": overrides }))),
+        Err(e) => {
+            error!("Failed to get pool fee overrides: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /admin/pool-fees/:pool_id
+/// Set the LP fee analytics assume for a pool whose fee can't be resolved
+pub async fn set_pool_fee_override_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Json(request): Json<PoolFeeOverrideRequest>,
+) -> impl IntoResponse {
+    if !(0..=MAX_LP_FEE).contains(&request.fee) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("fee must be between 0 and {} (hundredths of a bip)", MAX_LP_FEE)
+            })),
+        );
+    }
+
+    match set_pool_fee_override(&state.db_pool, &pool_id, request.fee, request.note.as_deref())
+        .await
+    {
+        Ok(Some(fee_override)) => {
+            info!("Set fee override of {} for pool {}", fee_override.fee, pool_id);
+            (StatusCode::OK, Json(serde_json::to_value(fee_override).unwrap()))
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Pool not found" })),
+        ),
+        Err(e) => {
+            error!("Failed to set pool fee override: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /admin/pool-fees/:pool_id
+/// Clear a pool's fee override, returning it to its fee tier or the default
+pub async fn delete_pool_fee_override_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+) -> impl IntoResponse {
+    match delete_pool_fee_override(&state.db_pool, &pool_id).await {
+        Ok(true) => {
+            info!("Cleared fee override for pool {}", pool_id);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": pool_id })))
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "No fee override for this pool" })),
+        ),
+        Err(e) => {
+            error!("Failed to delete pool fee override: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VaultRequest {
+    /// ERC20 token depositors hold shares in (defaults to the vault's own address)
+    pub share_token: Option<String>,
+    pub name: Option<String>,
+}
+
+/// GET /admin/vaults
+/// Registered vaults whose positions are split between share holders
+pub async fn get_vaults_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match get_vaults(&state.db_pool).await {
+        Ok(vaults) => (StatusCode::OK, Json(serde_json::json!({ "vaults": vaults }))),
+        Err(e) => {
+            error!("Failed to get vaults: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /admin/vaults/:address
+/// Register a liquidity manager contract as a vault, or change its share token or name
+pub async fn set_vault_handler(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Json(request): Json<VaultRequest>,
+) -> impl IntoResponse {
+    let address = match owner_param(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let share_token = match request.share_token.as_deref().map(owner_param) {
+        Some(Ok(token)) => token,
+        Some(Err(response)) => return response,
+        None => address,
+    };
+
+    match upsert_vault(&state.db_pool, &address, &share_token, request.name.as_deref()).await {
+        Ok(vault) => {
+            info!("Registered vault {} with share token {}", vault.address, vault.share_token);
+            (StatusCode::OK, Json(serde_json::to_value(vault).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to register vault: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /admin/vaults/:address
+/// Unregister a vault; its positions stay, listed under the vault's address
+pub async fn delete_vault_handler(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> impl IntoResponse {
+    let address = match owner_param(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    match delete_vault(&state.db_pool, &address).await {
+        Ok(true) => {
+            info!("Unregistered vault {}", address);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": address })))
+        }
+        Ok(false) => {
+            (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Vault not found" })))
+        }
+        Err(e) => {
+            error!("Failed to delete vault: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwapDownsamplingRequest {
+    /// Largest swaps kept per day (default 100); the rest are stored per minute
+    pub keep_largest: Option<i32>,
+}
+
+/// GET /admin/swap-downsampling
+/// Pools whose finished days of swaps are stored as minute aggregates
+pub async fn get_swap_downsampling_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match get_swap_downsampling(&state.db_pool).await {
+        Ok(pools) => (StatusCode::OK, Json(serde_json::json!({ "pools": pools }))),
+        Err(e) => {
+            error!("Failed to get swap downsampling: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /admin/swap-downsampling/:pool_id
+/// Opt a high-volume pool into downsampled swap storage from the next sync on
+pub async fn set_swap_downsampling_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Json(request): Json<SwapDownsamplingRequest>,
+) -> impl IntoResponse {
+    let keep_largest = request.keep_largest.unwrap_or(DEFAULT_KEEP_LARGEST);
+    if keep_largest < 0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "keep_largest must not be negative" })),
+        );
+    }
+
+    match set_swap_downsampling(&state.db_pool, &pool_id, keep_largest).await {
+        Ok(Some(downsampling)) => {
+            info!("Downsampling swaps of pool {}, keeping {} a day", pool_id, keep_largest);
+            (StatusCode::OK, Json(serde_json::to_value(downsampling).unwrap()))
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Pool not found" })),
+        ),
+        Err(e) => {
+            error!("Failed to set swap downsampling: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /admin/swap-downsampling/:pool_id
+/// Keep every swap of a pool again; minutes already aggregated stay so
+pub async fn delete_swap_downsampling_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+) -> impl IntoResponse {
+    match delete_swap_downsampling(&state.db_pool, &pool_id).await {
+        Ok(true) => {
+            info!("Stopped downsampling swaps of pool {}", pool_id);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": pool_id })))
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Pool is not downsampled" })),
+        ),
+        Err(e) => {
+            error!("Failed to delete swap downsampling: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeeReconciliationParams {
+    /// Days of fee growth samples to compare over (default 7)
+    pub days: Option<i64>,
+    /// Relative gap beyond the fee growth bounds that's flagged (default 0.1)
+    pub tolerance: Option<Decimal>,
+}
+
+/// GET /admin/fee-reconciliation/:pool_id?days=7&tolerance=0.1
+/// Positions' attributed fees against the pool's sampled fee growth, diverging ones first
+pub async fn get_fee_reconciliation_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<FeeReconciliationParams>,
+) -> impl IntoResponse {
+    let days = params.days.unwrap_or(7);
+    let tolerance = params.tolerance.unwrap_or(DEFAULT_FEE_GROWTH_TOLERANCE);
+    if !(1..=MAX_RECONCILIATION_DAYS).contains(&days) || tolerance < Decimal::ZERO {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "days must be between 1 and {} and tolerance not negative",
+                    MAX_RECONCILIATION_DAYS
+                )
+            })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let now = Utc::now();
+    let since = now - Duration::days(days);
+    let filter = PositionFilter { pool_id: Some(pool_id.clone()), ..Default::default() };
+    let history = tokio::try_join!(
+        get_pool_fee_growth(&state.db_pool, &pool_id, since),
+        get_swaps_for_pool_between(&state.db_pool, &pool_id, since, now),
+        find_positions(&state.db_pool, &filter),
+    );
+    let (samples, swaps, positions) = match history {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to fetch fee reconciliation data: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let model = state.fee_models.model_for(&pool);
+    let mut checks: Vec<_> = positions
+        .iter()
+        .filter_map(|p| reconcile_fee_growth(p, &pool, &samples, &swaps, model, tolerance))
+        .collect();
+    checks.sort_by_key(|c| {
+        (!c.diverged, std::cmp::Reverse(c.divergence.map(|d| d.abs()).unwrap_or(Decimal::MAX)))
+    });
+    let diverged = checks.iter().filter(|c| c.diverged).count();
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "pool_id": pool_id,
+            "samples": samples.len(),
+            "tolerance": tolerance,
+            "checked": checks.len(),
+            "diverged": diverged,
+            "positions": checks,
+        })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncStateParams {
+    /// Number of latest checkpoints to list (default 10)
+    pub limit: Option<i64>,
+}
+
+/// GET /admin/sync?limit=N
+/// Whether scheduled syncs are paused, and the latest checkpoints (highest block first)
+pub async fn get_sync_state_handler(
+    State(state): State<AppState>,
+    Query(params): Query<SyncStateParams>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(10).clamp(1, MAX_LISTED_CHECKPOINTS);
+
+    let schedule = get_sync_schedule(&state.db_pool).await;
+    let checkpoints = get_sync_checkpoints(&state.db_pool, limit).await;
+    match (schedule, checkpoints) {
+        (Ok(schedule), Ok(checkpoints)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "schedule": schedule, "checkpoints": checkpoints })),
+        ),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to get sync state: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TriggerSyncRequest {
+    pub owner: Option<String>,
+    pub pool_id: Option<String>,
+}
+
+/// POST /admin/sync
+/// Queue an immediate sync of an owner or a pool for a worker
+pub async fn trigger_sync_handler(
+    State(state): State<AppState>,
+    Json(request): Json<TriggerSyncRequest>,
+) -> impl IntoResponse {
+    let owner = request.owner.as_deref().map(str::trim).filter(|o| !o.is_empty());
+    let pool_id = request.pool_id.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    let (owner, params) = match (owner, pool_id) {
+        (Some(owner), None) => (owner, serde_json::json!({})),
+        (None, Some(pool_id)) => ("", serde_json::json!({ "pool_id": pool_id })),
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Give either owner or pool_id" })),
+            );
+        }
+    };
+
+    match enqueue_job(&state.db_pool, JobKind::Sync, owner, &params).await {
+        Ok(job) => {
+            info!("Queued sync job {} for {}", job.id, pool_id.unwrap_or(owner));
+            (StatusCode::ACCEPTED, Json(serde_json::to_value(job).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to enqueue sync job: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncScheduleRequest {
+    pub paused: bool,
+    /// Why syncs are paused, shown to other operators
+    pub reason: Option<String>,
+}
+
+/// PUT /admin/sync/schedule
+/// Pause or resume scheduled syncs
+pub async fn set_sync_schedule_handler(
+    State(state): State<AppState>,
+    Json(request): Json<SyncScheduleRequest>,
+) -> impl IntoResponse {
+    match set_sync_paused(&state.db_pool, request.paused, request.reason.as_deref()).await {
+        Ok(schedule) => {
+            if schedule.paused {
+                warn!("Scheduled syncs paused: {}", schedule.reason.as_deref().unwrap_or("-"));
+            } else {
+                info!("Scheduled syncs resumed");
+            }
+            (StatusCode::OK, Json(serde_json::to_value(schedule).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to set sync schedule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
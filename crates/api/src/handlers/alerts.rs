@@ -0,0 +1,649 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use stillwater_alerts::{AlertDispatcher, validate_rule, validate_sink_name, validate_template};
+use stillwater_db::{
+    create_alert_rule, delete_alert_rule, delete_alert_template, get_alert_rule, get_alert_rules,
+    get_alert_templates, get_watched_pairs, get_watched_tokens, insert_telegram_link_code,
+    set_alert_template, unwatch_pair, unwatch_token, update_alert_rule, watch_pair, watch_token,
+};
+use stillwater_models::{Address, AlertKind, AlertRuleSpec};
+use tracing::{error, info};
+
+use crate::address::owner_param;
+use crate::auth::generate_nonce;
+use crate::handlers::auth::authorized_addresses;
+use crate::state::AppState;
+
+/// How long a Telegram link code can be redeemed
+const TELEGRAM_LINK_CODE_TTL_MINUTES: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct AlertTemplateRequest {
+    /// Title template (the default title is kept when omitted)
+    pub title: Option<String>,
+    /// Message template
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchTokenRequest {
+    /// Sinks new pool alerts go to (the configured sinks when empty)
+    #[serde(default)]
+    pub sinks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchPairRequest {
+    /// Sinks new pool alerts go to (the configured sinks when empty)
+    #[serde(default)]
+    pub sinks: Vec<String>,
+    /// Chains to watch the pair on (every chain when empty)
+    #[serde(default)]
+    pub chain_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestRuleParams {
+    /// Render the alert and resolve the sinks without sending
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertRulesParams {
+    /// Only rules naming this owner
+    pub owner: Option<String>,
+}
+
+/// Check the API key is linked to `owner`
+async fn authorize_owner(
+    state: &AppState,
+    headers: &HeaderMap,
+    owner: &Address,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let addresses = authorized_addresses(state, headers).await?;
+    if !addresses.contains(owner) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Owner is not linked to this API key" })),
+        ));
+    }
+    Ok(())
+}
+
+/// Parse an alert kind path segment
+fn parse_kind(kind: &str) -> Result<AlertKind, (StatusCode, Json<serde_json::Value>)> {
+    AlertKind::parse(kind).ok_or_else(|| {
+        let kinds: Vec<&str> = AlertKind::ALL.iter().map(|k| k.as_str()).collect();
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Unknown alert kind, expected one of: {}", kinds.join(", "))
+            })),
+        )
+    })
+}
+
+/// GET /alerts/:owner/templates
+/// List an owner's alert templates (requires an API key for the owner)
+pub async fn get_alert_templates_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+
+    match get_alert_templates(&state.db_pool, &owner.canonical()).await {
+        Ok(templates) => (StatusCode::OK, Json(serde_json::to_value(templates).unwrap())),
+        Err(e) => {
+            error!("Failed to fetch alert templates: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /alerts/:owner/templates/:kind
+/// Set an owner's template for a kind of alert (requires an API key for the owner)
+///
+/// The template is rendered against sample values first; templates that fail
+/// (bad syntax, variables the kind doesn't have) are rejected. The response
+/// includes the rendered sample as a preview.
+pub async fn set_alert_template_handler(
+    State(state): State<AppState>,
+    Path((owner, kind)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(req): Json<AlertTemplateRequest>,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+    let kind = match parse_kind(&kind) {
+        Ok(kind) => kind,
+        Err(response) => return response,
+    };
+
+    let preview = match validate_template(kind, req.title.as_deref(), &req.body) {
+        Ok(preview) => preview,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("Invalid template: {}", e) })),
+            );
+        }
+    };
+
+    let title = req.title.as_deref();
+    match set_alert_template(&state.db_pool, &owner.canonical(), kind, title, &req.body).await {
+        Ok(template) => {
+            info!("{} set a {} alert template", owner, kind.as_str());
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "template": template,
+                    "preview": { "title": preview.title, "message": preview.message },
+                })),
+            )
+        }
+        Err(e) => {
+            error!("Failed to set alert template: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /alerts/:owner/templates/:kind
+/// Go back to the default text for a kind of alert (requires an API key for the owner)
+pub async fn delete_alert_template_handler(
+    State(state): State<AppState>,
+    Path((owner, kind)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+    let kind = match parse_kind(&kind) {
+        Ok(kind) => kind,
+        Err(response) => return response,
+    };
+
+    match delete_alert_template(&state.db_pool, &owner.canonical(), kind).await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({ "deleted": kind.as_str() }))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "No template set for this alert kind" })),
+        ),
+        Err(e) => {
+            error!("Failed to delete alert template: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// GET /alerts/:owner/watchlist
+/// List the tokens an owner is told about new pools for (requires an API key for the owner)
+pub async fn get_watchlist_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+
+    match get_watched_tokens(&state.db_pool, &owner.canonical()).await {
+        Ok(tokens) => (StatusCode::OK, Json(serde_json::json!({ "tokens": tokens }))),
+        Err(e) => {
+            error!("Failed to fetch watched tokens: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /alerts/:owner/watchlist/:token
+/// Alert an owner when a pool with this token is created (requires an API key for the owner)
+///
+/// The body is optional; `sinks` replaces the sinks of an existing watch.
+pub async fn watch_token_handler(
+    State(state): State<AppState>,
+    Path((owner, token)): Path<(String, String)>,
+    headers: HeaderMap,
+    req: Option<Json<WatchTokenRequest>>,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+    if Address::parse(&token).is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "token must be an address" })),
+        );
+    }
+    let sinks = req.map(|Json(req)| req.sinks).unwrap_or_default();
+    if let Err(e) = sinks.iter().try_for_each(|sink| validate_sink_name(sink)) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid sinks: {}", e) })),
+        );
+    }
+
+    match watch_token(&state.db_pool, &owner.canonical(), &token, &sinks).await {
+        Ok(watched) => {
+            info!("{} is watching {} for new pools", owner, watched.token);
+            (StatusCode::OK, Json(serde_json::to_value(watched).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to watch token: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /alerts/:owner/watchlist/:token
+/// Stop alerting an owner about new pools with this token (requires an API key for the owner)
+pub async fn unwatch_token_handler(
+    State(state): State<AppState>,
+    Path((owner, token)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+
+    match unwatch_token(&state.db_pool, &owner.canonical(), &token).await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({ "deleted": token }))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Token is not on the watchlist" })),
+        ),
+        Err(e) => {
+            error!("Failed to unwatch token: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// The two tokens of a pair, rejecting non-addresses and a token paired with itself
+fn pair_params(token_a: &str, token_b: &str) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let (Ok(a), Ok(b)) = (Address::parse(token_a), Address::parse(token_b)) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Both tokens must be addresses" })),
+        ));
+    };
+    if a == b {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "A pair needs two different tokens" })),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /alerts/:owner/pairs
+/// List the token pairs an owner is told about new pools for (requires an API key for the owner)
+pub async fn get_watched_pairs_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+
+    match get_watched_pairs(&state.db_pool, &owner.canonical()).await {
+        Ok(pairs) => (StatusCode::OK, Json(serde_json::json!({ "pairs": pairs }))),
+        Err(e) => {
+            error!("Failed to fetch watched pairs: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /alerts/:owner/pairs/:token_a/:token_b
+/// Alert an owner when a pool for this pair is created on any fee tier or hooks (requires an
+/// API key for the owner)
+///
+/// The tokens may come in either order. The body is optional; `chain_ids` and
+/// `sinks` replace those of an existing watch.
+pub async fn watch_pair_handler(
+    State(state): State<AppState>,
+    Path((owner, token_a, token_b)): Path<(String, String, String)>,
+    headers: HeaderMap,
+    req: Option<Json<WatchPairRequest>>,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+    if let Err(response) = pair_params(&token_a, &token_b) {
+        return response;
+    }
+    let (sinks, chain_ids) = req.map(|Json(req)| (req.sinks, req.chain_ids)).unwrap_or_default();
+    if let Err(e) = sinks.iter().try_for_each(|sink| validate_sink_name(sink)) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid sinks: {}", e) })),
+        );
+    }
+    if chain_ids.iter().any(|id| *id <= 0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "chain_ids must be positive" })),
+        );
+    }
+
+    let owner = owner.canonical();
+    match watch_pair(&state.db_pool, &owner, &token_a, &token_b, &chain_ids, &sinks).await {
+        Ok(watched) => {
+            info!("{} is watching {}/{} for new pools", owner, watched.token0, watched.token1);
+            (StatusCode::OK, Json(serde_json::to_value(watched).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to watch pair: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /alerts/:owner/pairs/:token_a/:token_b
+/// Stop alerting an owner about new pools for this pair (requires an API key for the owner)
+pub async fn unwatch_pair_handler(
+    State(state): State<AppState>,
+    Path((owner, token_a, token_b)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+
+    match unwatch_pair(&state.db_pool, &owner.canonical(), &token_a, &token_b).await {
+        Ok(true) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "deleted": { "token_a": token_a, "token_b": token_b } })),
+        ),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Pair is not on the watchlist" })),
+        ),
+        Err(e) => {
+            error!("Failed to unwatch pair: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// POST /alerts/:owner/telegram-link
+/// Issue a one-time code linking a Telegram chat to the owner (requires an API key for the owner)
+///
+/// Sending `/link <code>` to the bot within `TELEGRAM_LINK_CODE_TTL_MINUTES`
+/// makes that chat follow the owner; holding the owner's key is the proof of
+/// control the bot can't ask for itself.
+pub async fn create_telegram_link_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    if let Err(response) = authorize_owner(&state, &headers, &owner).await {
+        return response;
+    }
+
+    let code = generate_nonce();
+    let expires_at = Utc::now() + Duration::minutes(TELEGRAM_LINK_CODE_TTL_MINUTES);
+    match insert_telegram_link_code(&state.db_pool, &code, &owner, expires_at).await {
+        Ok(()) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "owner": owner,
+                "command": format!("/link {}", code),
+                "code": code,
+                "expires_at": expires_at,
+            })),
+        ),
+        Err(e) => {
+            error!("Failed to store Telegram link code: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// Reject a rule with invalid fields or sink names
+fn check_rule(spec: &AlertRuleSpec) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    validate_rule(spec).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid alert rule: {}", e) })),
+        )
+    })
+}
+
+fn rule_not_found() -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Alert rule not found" })))
+}
+
+/// GET /admin/alert-rules?owner=0x...
+/// List alert rules, enabled or not, optionally only those naming an owner
+pub async fn get_alert_rules_handler(
+    State(state): State<AppState>,
+    Query(params): Query<AlertRulesParams>,
+) -> impl IntoResponse {
+    match get_alert_rules(&state.db_pool, params.owner.as_deref()).await {
+        Ok(rules) => (StatusCode::OK, Json(serde_json::json!({ "rules": rules }))),
+        Err(e) => {
+            error!("Failed to fetch alert rules: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// POST /admin/alert-rules
+/// Create an alert rule; the watcher and sync jobs pick it up on their next reload
+pub async fn create_alert_rule_handler(
+    State(state): State<AppState>,
+    Json(spec): Json<AlertRuleSpec>,
+) -> impl IntoResponse {
+    if let Err(response) = check_rule(&spec) {
+        return response;
+    }
+
+    match create_alert_rule(&state.db_pool, &spec).await {
+        Ok(rule) => {
+            info!("Created {} alert rule {}", rule.spec.condition.as_str(), rule.id);
+            (StatusCode::CREATED, Json(serde_json::to_value(rule).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to create alert rule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// GET /admin/alert-rules/:id
+pub async fn get_alert_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match get_alert_rule(&state.db_pool, id).await {
+        Ok(Some(rule)) => (StatusCode::OK, Json(serde_json::to_value(rule).unwrap())),
+        Ok(None) => rule_not_found(),
+        Err(e) => {
+            error!("Failed to fetch alert rule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /admin/alert-rules/:id
+/// Replace an alert rule's fields (its cooldowns carry over)
+pub async fn update_alert_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(spec): Json<AlertRuleSpec>,
+) -> impl IntoResponse {
+    if let Err(response) = check_rule(&spec) {
+        return response;
+    }
+
+    match update_alert_rule(&state.db_pool, id, &spec).await {
+        Ok(Some(rule)) => {
+            info!("Updated alert rule {}", rule.id);
+            (StatusCode::OK, Json(serde_json::to_value(rule).unwrap()))
+        }
+        Ok(None) => rule_not_found(),
+        Err(e) => {
+            error!("Failed to update alert rule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /admin/alert-rules/:id
+pub async fn delete_alert_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match delete_alert_rule(&state.db_pool, id).await {
+        Ok(true) => {
+            info!("Deleted alert rule {}", id);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": id })))
+        }
+        Ok(false) => rule_not_found(),
+        Err(e) => {
+            error!("Failed to delete alert rule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// POST /admin/alert-rules/:id/test?dry_run=true
+/// Send a sample alert for the rule's condition to its sinks right away, reporting each
+/// sink's outcome; a dry run only renders the alert and lists the sinks
+pub async fn test_alert_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<TestRuleParams>,
+) -> impl IntoResponse {
+    let rule = match get_alert_rule(&state.db_pool, id).await {
+        Ok(Some(rule)) => rule,
+        Ok(None) => return rule_not_found(),
+        Err(e) => {
+            error!("Failed to fetch alert rule: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let dispatcher = AlertDispatcher::from_env();
+    match dispatcher.test_rule(&state.db_pool, &rule, params.dry_run).await {
+        Ok(test) => {
+            let failed = test.deliveries.iter().filter(|d| d.error.is_some()).count();
+            info!(
+                "Test-fired alert rule {} (dry run: {}), {} of {} sinks failed",
+                id,
+                test.dry_run,
+                failed,
+                test.deliveries.len()
+            );
+            (StatusCode::OK, Json(serde_json::to_value(test).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to test alert rule: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use stillwater_db::{
+    add_to_watchlist, consume_auth_nonce, create_api_key, get_api_key_addresses, insert_auth_nonce,
+    link_api_key_address, touch_api_key,
+};
+use stillwater_models::Address;
+use tracing::{error, info, warn};
+
+use crate::auth::{
+    SiweMessage, bearer_token, generate_api_key, generate_nonce, hash_api_key, recover_signer,
+};
+use crate::state::AppState;
+
+/// How long an issued sign-in message stays valid
+const NONCE_TTL_MINUTES: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct NonceRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NonceResponse {
+    pub nonce: String,
+    /// EIP-4361 message for the wallet to sign verbatim
+    pub message: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyRequest {
+    pub message: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyResponse {
+    pub address: Address,
+    /// Newly issued API key; only returned when the request had none
+    pub api_key: Option<String>,
+    /// All addresses verified for this API key
+    pub addresses: Vec<Address>,
+}
+
+/// POST /auth/nonce
+/// Issue a Sign-In with Ethereum message for an address to sign
+pub async fn create_nonce_handler(
+    State(state): State<AppState>,
+    Json(request): Json<NonceRequest>,
+) -> impl IntoResponse {
+    let address = match request.address.parse() {
+        Ok(a) => a,
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid address" })),
+            );
+        }
+    };
+
+    let now = Utc::now();
+    let message = SiweMessage {
+        domain: state.siwe.domain.clone(),
+        address,
+        statement: Some(
+            "Sign in to Stillwater to register this address for read-only tracking.".to_string(),
+        ),
+        uri: state.siwe.uri.clone(),
+        version: "1".to_string(),
+        chain_id: state.siwe.chain_id,
+        nonce: generate_nonce(),
+        issued_at: now,
+        expiration_time: Some(now + Duration::minutes(NONCE_TTL_MINUTES)),
+    };
+    let expires_at = now + Duration::minutes(NONCE_TTL_MINUTES);
+
+    let owner = Address::from(message.address);
+    if let Err(e) = insert_auth_nonce(&state.db_pool, &message.nonce, &owner, expires_at).await {
+        error!("Failed to store nonce: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Internal server error" })),
+        );
+    }
+
+    let response = NonceResponse {
+        nonce: message.nonce.clone(),
+        message: message.to_message(),
+        expires_at: expires_at.to_rfc3339(),
+    };
+
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+}
+
+/// POST /auth/verify
+/// Verify a signed sign-in message, watch the address and link it to an API key
+///
+/// Send `Authorization: Bearer <api key>` to add the address to an existing
+/// key; otherwise a new key is issued and returned once.
+pub async fn verify_signature_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<VerifyRequest>,
+) -> impl IntoResponse {
+    let message = match SiweMessage::parse(&request.message) {
+        Ok(m) => m,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("Invalid SIWE message: {}", e) })),
+            );
+        }
+    };
+
+    if let Err(e) = message.validate(&state.siwe, Utc::now()) {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": e.to_string() })));
+    }
+
+    match recover_signer(&request.message, &request.signature) {
+        Ok(signer) if signer == message.address => {}
+        Ok(_) | Err(_) => {
+            warn!("Rejected sign-in signature for {}", message.address);
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Signature does not match address" })),
+            );
+        }
+    }
+
+    let address = Address::from(message.address);
+
+    match consume_auth_nonce(&state.db_pool, &message.nonce, &address).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Nonce is invalid, expired or already used" })),
+            );
+        }
+        Err(e) => {
+            error!("Failed to consume nonce: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    }
+
+    // Resolve the caller's API key, or mint a new one
+    let (api_key, new_key) = match bearer_token(&headers) {
+        Some(token) => match touch_api_key(&state.db_pool, &hash_api_key(token)).await {
+            Ok(Some(key)) => (key, None),
+            Ok(None) => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(serde_json::json!({ "error": "Unknown API key" })),
+                );
+            }
+            Err(e) => {
+                error!("Failed to look up API key: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        },
+        None => {
+            let token = generate_api_key();
+            match create_api_key(&state.db_pool, &hash_api_key(&token), Some("siwe")).await {
+                Ok(key) => (key, Some(token)),
+                Err(e) => {
+                    error!("Failed to create API key: {}", e);
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(serde_json::json!({ "error": "Internal server error" })),
+                    );
+                }
+            }
+        }
+    };
+
+    let registered = async {
+        add_to_watchlist(&state.db_pool, &address, "siwe").await?;
+        link_api_key_address(&state.db_pool, api_key.id, &address).await?;
+        get_api_key_addresses(&state.db_pool, api_key.id).await
+    }
+    .await;
+
+    let addresses = match registered {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            error!("Failed to register address {}: {}", address, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    info!("Verified ownership of {} for API key {}", address, api_key.id);
+
+    let response = VerifyResponse { address, api_key: new_key, addresses };
+
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+}
+
+/// Addresses proven by the request's bearer API key
+///
+/// Returns the error response to send when the key is missing or unknown.
+pub(crate) async fn authorized_addresses(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Vec<Address>, (StatusCode, Json<serde_json::Value>)> {
+    let Some(token) = bearer_token(headers) else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Missing API key" })),
+        ));
+    };
+
+    let addresses = async {
+        let Some(key) = touch_api_key(&state.db_pool, &hash_api_key(token)).await? else {
+            return Ok(None);
+        };
+        get_api_key_addresses(&state.db_pool, key.id).await.map(Some)
+    }
+    .await;
+
+    match addresses {
+        Ok(Some(addresses)) => Ok(addresses),
+        Ok(None) => Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Unknown API key" })),
+        )),
+        Err(e) => {
+            error!("Failed to look up API key: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            ))
+        }
+    }
+}
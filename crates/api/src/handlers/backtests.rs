@@ -0,0 +1,361 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::Duration;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use stillwater_analytics::{
+    BacktestConfig, BacktestMetrics, BacktestParams, CompoundingRule, TickRange, TimeRangeLimits,
+    diff_backtests, liquidity_for_value, market_points_from_swaps, run_backtest, tick_to_price,
+};
+use stillwater_db::{
+    NewBacktest, get_backtest, get_backtest_equity_curve, get_backtests, get_pool_by_id,
+    get_position_by_id, get_swaps_for_pool_between, save_backtest,
+};
+use stillwater_models::{BacktestRun, RetainedData};
+use tracing::{error, info};
+
+use crate::handlers::pools::{apply_historical_gas, average_lp_fee_rate, no_gas_history_response};
+use crate::handlers::positions::invalid_range_response;
+use crate::retention::{retention_warning, with_retention_warnings};
+use crate::state::AppState;
+use crate::timerange::{interval_param, time_range_param};
+
+/// Default and longest swap history a backtest replays, in days
+const BACKTEST_DEFAULT_DAYS: i64 = 30;
+const MAX_BACKTEST_DAYS: i64 = 365;
+
+/// Upper bound on market points per backtest, which is also the equity curve's length
+const MAX_BACKTEST_POINTS: i64 = 5000;
+
+/// Default and most runs `GET /backtests` lists
+const DEFAULT_LISTED_BACKTESTS: i64 = 50;
+const MAX_LISTED_BACKTESTS: i64 = 500;
+
+type ErrorResponse = (StatusCode, Json<serde_json::Value>);
+
+#[derive(Debug, Deserialize)]
+pub struct BacktestRequest {
+    /// Free-form note stored with the run
+    pub label: Option<String>,
+    /// Position whose range is backtested
+    pub position_id: Option<i64>,
+    /// Explicit range, used when no position is given
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+    /// Capital to deploy, in raw token1 units
+    pub capital: Decimal,
+    /// Gas cost of one transaction, in raw token1 units
+    pub gas_cost: Decimal,
+    /// Scale `gas_cost`, taken as today's cost, by the recorded gas prices
+    /// over the window (default false)
+    pub historical_gas: Option<bool>,
+    /// Share of in-range swap fees the position earns at the start (default 0.01)
+    pub pool_share: Option<Decimal>,
+    /// Compound collected fees this often, in days (no compounding without it)
+    pub compound_interval_days: Option<i64>,
+    /// Start of the history replayed (defaults to 30 days before `to`)
+    pub from: Option<String>,
+    /// End of the history replayed (defaults to now)
+    pub to: Option<String>,
+    /// Market point spacing like `15m` or `1h` (default `1h`)
+    pub interval: Option<String>,
+    /// Market point spacing in minutes, used when `interval` isn't given
+    pub interval_minutes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BacktestListParams {
+    /// Only runs in this pool
+    pub pool_id: Option<String>,
+    /// Most runs returned (default 50, at most 500)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BacktestDiffParams {
+    /// Run compared against
+    pub a: i64,
+    /// Run compared
+    pub b: i64,
+}
+
+fn internal_error(context: &str, e: anyhow::Error) -> ErrorResponse {
+    error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+}
+
+fn backtest_not_found(id: i64) -> ErrorResponse {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": format!("Backtest {} not found", id) })),
+    )
+}
+
+/// A stored run by ID
+async fn stored_backtest(state: &AppState, id: i64) -> Result<BacktestRun, ErrorResponse> {
+    match get_backtest(&state.db_pool, id).await {
+        Ok(Some(run)) => Ok(run),
+        Ok(None) => Err(backtest_not_found(id)),
+        Err(e) => Err(internal_error("Failed to fetch backtest", e)),
+    }
+}
+
+/// A stored run's settings and figures, as analytics compares them
+fn run_figures(run: &BacktestRun) -> Result<(BacktestParams, BacktestMetrics), ErrorResponse> {
+    let params = serde_json::from_value(run.params.clone());
+    let metrics = serde_json::from_value(run.metrics.clone());
+    match (params, metrics) {
+        (Ok(params), Ok(metrics)) => Ok((params, metrics)),
+        (Err(e), _) | (_, Err(e)) => {
+            Err(internal_error(&format!("Invalid stored backtest {}", run.id), e.into()))
+        }
+    }
+}
+
+/// POST /pools/:pool_id/backtests
+/// Replay the pool's swap history against a range and store the run
+pub async fn create_backtest_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Json(request): Json<BacktestRequest>,
+) -> impl IntoResponse {
+    info!("Backtesting a range in pool {}", pool_id);
+
+    let limits = TimeRangeLimits::days(BACKTEST_DEFAULT_DAYS, MAX_BACKTEST_DAYS);
+    let (from, to) = match time_range_param(request.from.as_deref(), request.to.as_deref(), limits)
+    {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+    let interval = match interval_param(
+        request.interval.as_deref(),
+        request.interval_minutes,
+        Duration::hours(1),
+        limits.max_span,
+    ) {
+        Ok(interval) => interval,
+        Err(response) => return response,
+    };
+    let pool_share = request.pool_share.unwrap_or(Decimal::new(1, 2));
+    if (to - from).num_seconds() / interval.num_seconds() > MAX_BACKTEST_POINTS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "At most {} intervals may fit between from and to",
+                    MAX_BACKTEST_POINTS
+                )
+            })),
+        );
+    }
+    if request.capital <= Decimal::ZERO
+        || request.gas_cost < Decimal::ZERO
+        || pool_share <= Decimal::ZERO
+        || pool_share >= Decimal::ONE
+        || request.compound_interval_days.is_some_and(|days| days <= 0)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "capital must be positive, gas_cost non-negative, pool_share between 0 and 1 \
+                          and compound_interval_days positive"
+            })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => return internal_error("Failed to fetch pool", e),
+    };
+
+    let (tick_lower, tick_upper) =
+        match (request.position_id, request.tick_lower, request.tick_upper) {
+            (Some(id), _, _) => match get_position_by_id(&state.db_pool, id).await {
+                Ok(Some(position)) if position.pool_id == pool_id => {
+                    (position.tick_lower, position.tick_upper)
+                }
+                Ok(_) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(serde_json::json!({ "error": "Position not found in this pool" })),
+                    );
+                }
+                Err(e) => return internal_error("Failed to fetch position", e),
+            },
+            (None, Some(lower), Some(upper)) => (lower, upper),
+            _ => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "position_id or both tick_lower and tick_upper are required"
+                    })),
+                );
+            }
+        };
+    if let Err(e) = TickRange::new(tick_lower, tick_upper) {
+        return invalid_range_response(e);
+    }
+
+    let swaps = match get_swaps_for_pool_between(&state.db_pool, &pool_id, from, to).await {
+        Ok(swaps) => swaps,
+        Err(e) => return internal_error("Failed to fetch swaps", e),
+    };
+
+    // Swaps don't record pool liquidity: size other LPs' liquidity so the
+    // position earns `pool_share` of in-range fees at the start
+    let mut points = market_points_from_swaps(&swaps, from, to, interval, Decimal::ZERO);
+    let Some(first) = points.first() else {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "No swaps in the window to replay" })),
+        );
+    };
+    let own_liquidity = liquidity_for_value(
+        request.capital,
+        first.price,
+        tick_to_price(tick_lower),
+        tick_to_price(tick_upper),
+    );
+    let active_liquidity = own_liquidity * (Decimal::ONE - pool_share) / pool_share;
+    for point in &mut points {
+        point.active_liquidity = active_liquidity;
+    }
+    let historical_gas = request.historical_gas.unwrap_or(false);
+    if historical_gas {
+        match apply_historical_gas(&state, &mut points, request.gas_cost).await {
+            Ok(0) => return no_gas_history_response(&state),
+            Ok(_) => {}
+            Err(e) => return internal_error("Failed to fetch gas prices", e),
+        }
+    }
+
+    let params = BacktestParams {
+        config: BacktestConfig {
+            tick_lower,
+            tick_upper,
+            initial_capital: request.capital,
+            fee_rate: average_lp_fee_rate(&state, &pool, &swaps),
+            gas_cost_per_tx: request.gas_cost,
+            compounding: request
+                .compound_interval_days
+                .map(|interval_days| CompoundingRule { interval_days }),
+        },
+        pool_share,
+        interval_secs: interval.num_seconds(),
+        historical_gas,
+    };
+    let result = match run_backtest(&params.config, &points) {
+        Ok(result) => result,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() })));
+        }
+    };
+
+    let backtest = NewBacktest {
+        pool_id: &pool_id,
+        label: request.label.as_deref(),
+        params: &serde_json::to_value(&params).unwrap(),
+        period_start: result.start,
+        period_end: result.end,
+        metrics: &serde_json::to_value(result.metrics()).unwrap(),
+        equity_curve: &serde_json::to_value(&result.equity_curve).unwrap(),
+    };
+    let run = match save_backtest(&state.db_pool, &backtest).await {
+        Ok(run) => run,
+        Err(e) => return internal_error("Failed to save backtest", e),
+    };
+    info!("Stored backtest {} for pool {}", run.id, pool_id);
+
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    let body = serde_json::to_value(run).unwrap();
+    (StatusCode::CREATED, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+}
+
+/// GET /backtests?pool_id=X&limit=50
+/// Stored runs, newest first, without their equity curves
+pub async fn get_backtests_handler(
+    State(state): State<AppState>,
+    Query(params): Query<BacktestListParams>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(DEFAULT_LISTED_BACKTESTS);
+    if !(1..=MAX_LISTED_BACKTESTS).contains(&limit) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("limit must be between 1 and {}", MAX_LISTED_BACKTESTS)
+            })),
+        );
+    }
+
+    match get_backtests(&state.db_pool, params.pool_id.as_deref(), limit).await {
+        Ok(runs) => (StatusCode::OK, Json(serde_json::to_value(runs).unwrap())),
+        Err(e) => internal_error("Failed to fetch backtests", e),
+    }
+}
+
+/// GET /backtests/:id
+/// A stored run's settings and figures
+pub async fn get_backtest_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match stored_backtest(&state, id).await {
+        Ok(run) => (StatusCode::OK, Json(serde_json::to_value(run).unwrap())),
+        Err(response) => response,
+    }
+}
+
+/// GET /backtests/:id/equity-curve
+/// A stored run's equity at each market point
+pub async fn get_backtest_equity_curve_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match get_backtest_equity_curve(&state.db_pool, id).await {
+        Ok(Some(curve)) => {
+            (StatusCode::OK, Json(serde_json::json!({ "id": id, "equity_curve": curve })))
+        }
+        Ok(None) => backtest_not_found(id),
+        Err(e) => internal_error("Failed to fetch backtest equity curve", e),
+    }
+}
+
+/// GET /backtests/diff?a=1&b=2
+/// Settings that differ between two runs and each figure's change from `a` to `b`
+pub async fn diff_backtests_handler(
+    State(state): State<AppState>,
+    Query(params): Query<BacktestDiffParams>,
+) -> impl IntoResponse {
+    let (run_a, run_b) = match tokio::try_join!(
+        stored_backtest(&state, params.a),
+        stored_backtest(&state, params.b)
+    ) {
+        Ok(runs) => runs,
+        Err(response) => return response,
+    };
+    let (figures_a, figures_b) = match (run_figures(&run_a), run_figures(&run_b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+
+    let diff = diff_backtests((&figures_a.0, &figures_a.1), (&figures_b.0, &figures_b.1));
+    let body = serde_json::json!({
+        "a": run_a,
+        "b": run_b,
+        "same_pool": run_a.pool_id == run_b.pool_id,
+        "same_period": run_a.period_start == run_b.period_start
+            && run_a.period_end == run_b.period_end,
+        "params": diff.params,
+        "metrics": diff.metrics,
+    });
+    (StatusCode::OK, Json(body))
+}
@@ -0,0 +1,139 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use stillwater_analytics::{
+    PriceDisplay, RangeBandPoint, TimeRangeLimits, build_range_band, tick_to_price,
+    time_in_range,
+};
+use stillwater_db::{get_pool_by_id, get_position_by_id, get_snapshots_for_position};
+use tracing::{error, info};
+
+use crate::handlers::preferences::resolve_price_display;
+use crate::state::AppState;
+use crate::timerange::time_range_param;
+
+/// Default and longest chart windows, in days
+const CHART_DEFAULT_DAYS: i64 = 7;
+const MAX_CHART_DAYS: i64 = 365;
+
+#[derive(Debug, Serialize)]
+pub struct PositionChartResponse {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub price_lower: Decimal,
+    pub price_upper: Decimal,
+    pub time_in_range: Decimal,
+    pub points: Vec<RangeBandPoint>,
+    /// Set when prices are shown in the owner's preferred (or requested) quote token
+    pub price_display: Option<PriceDisplay>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChartQueryParams {
+    /// Start of the window, e.g. `30d`, unix seconds or RFC3339 (defaults to 7 days before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
+    /// Quote token for prices (address, `token0` or `token1`); defaults to the owner's preference
+    pub quote: Option<String>,
+}
+
+/// GET /positions/:id/chart?from=X&to=Y&quote=Z
+/// Get pool price with the position's range bounds as a chart-ready series
+pub async fn get_position_chart_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<ChartQueryParams>,
+) -> impl IntoResponse {
+    info!("Fetching chart data for position {}", id);
+
+    let limits = TimeRangeLimits::days(CHART_DEFAULT_DAYS, MAX_CHART_DAYS);
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+
+    let position = match get_position_by_id(&state.db_pool, id).await {
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Position not found" })),
+            );
+        }
+        Err(e) => {
+            error!("Failed to fetch position: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let snapshots = match get_snapshots_for_position(&state.db_pool, position.id, from, to).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to fetch snapshots: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch snapshots" })),
+            );
+        }
+    };
+
+    let display = match get_pool_by_id(&state.db_pool, &position.pool_id).await {
+        Ok(Some(pool)) => {
+            match resolve_price_display(&state, &position.owner, &pool, params.quote.as_deref())
+                .await
+            {
+                Ok(display) => display,
+                Err(response) => return response,
+            }
+        }
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch pool" })),
+            );
+        }
+    };
+
+    let mut points = build_range_band(&position, &snapshots);
+    let (price_lower, price_upper) = match &display {
+        Some(display) => {
+            // Inversion swaps which bound is lower; in_range is orientation-independent
+            let (lower, upper) = display.range(position.tick_lower, position.tick_upper);
+            for point in &mut points {
+                point.price = display.to_display(point.price);
+                point.lower = lower;
+                point.upper = upper;
+            }
+            (lower, upper)
+        }
+        None => (tick_to_price(position.tick_lower), tick_to_price(position.tick_upper)),
+    };
+
+    let response = PositionChartResponse {
+        position_id: position.id,
+        nft_id: position.nft_id,
+        pool_id: position.pool_id,
+        tick_lower: position.tick_lower,
+        tick_upper: position.tick_upper,
+        price_lower,
+        price_upper,
+        time_in_range: time_in_range(&points),
+        points,
+        price_display: display,
+    };
+
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+}
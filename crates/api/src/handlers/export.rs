@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use stillwater_indexer::{LedgerExportParams, build_owner_ledger, ledger_filename};
+use tracing::{error, info};
+
+use crate::address::owner_scope_param;
+use crate::state::AppState;
+
+fn internal_error(context: &str, e: anyhow::Error) -> Response {
+    error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+        .into_response()
+}
+
+/// GET /export/:owner/ledger?format=beancount&symbols=0x...:USDC,0x...:WETH&native=ETH
+/// Double-entry ledger of an owner's position deposits/withdrawals, fee income and gas
+pub async fn export_ledger_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<LedgerExportParams>,
+) -> Response {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response.into_response(),
+    };
+    let (format, config) = match params.parse() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e })))
+                .into_response();
+        }
+    };
+
+    info!("Exporting {} ledger for owner: {}", format.as_str(), owner);
+
+    let body = match build_owner_ledger(&state.db_pool, &owner.addresses, format, &config).await {
+        Ok(body) => body,
+        Err(e) => return internal_error("Failed to build ledger", e),
+    };
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", ledger_filename(&owner.label, format)),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,230 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use stillwater_analytics::{cumulative_fee_series, series_step};
+use stillwater_db::{get_snapshots_for_owners_between, get_sync_runs_between};
+use stillwater_models::{HealthStatus, SyncRun, SyncRunStatus};
+use tracing::{error, info};
+
+use crate::address::owner_scope_param;
+use crate::handlers::portfolio::batch_health;
+use crate::state::AppState;
+use crate::timerange::ErrorResponse;
+
+/// Points per series when the query doesn't set `maxDataPoints`
+const DEFAULT_MAX_DATA_POINTS: usize = 1000;
+
+/// Most sync runs plotted per target (the latest in the range)
+const MAX_SYNC_RUNS: i64 = 10_000;
+
+/// Metrics `POST /grafana/query` serves: name, what it plots, and whether it takes an owner
+const METRICS: [(&str, &str, bool); 6] = [
+    ("pnl_fees", "Fees earned by an owner's positions", true),
+    ("health", "Open positions per health status now (all watched owners without one)", true),
+    ("sync_duration_ms", "Wall-clock duration of each sync run", false),
+    ("sync_rows_fetched", "Rows fetched from the subgraph by each sync run", false),
+    ("sync_rows_inserted", "Rows inserted by each sync run", false),
+    ("sync_failed", "1 for each failed sync run, 0 for each successful one", false),
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaQuery {
+    pub range: GrafanaRange,
+    /// Grafana's suggested step between points
+    pub interval_ms: Option<i64>,
+    pub max_data_points: Option<usize>,
+    pub targets: Vec<GrafanaTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrafanaRange {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaTarget {
+    /// Metric name, one of `METRICS`
+    #[serde(default)]
+    pub target: String,
+    /// Per-target options (`data` in older datasource versions)
+    #[serde(alias = "data")]
+    pub payload: Option<TargetPayload>,
+    #[serde(default)]
+    pub hide: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TargetPayload {
+    pub owner: Option<String>,
+}
+
+/// One series of a query response: `[value, unix milliseconds]` pairs
+#[derive(Debug, Serialize)]
+pub struct TimeSeries {
+    pub target: String,
+    pub datapoints: Vec<(f64, i64)>,
+}
+
+impl TimeSeries {
+    fn new(target: &str, points: impl Iterator<Item = (Decimal, DateTime<Utc>)>) -> Self {
+        let datapoints = points
+            .filter_map(|(value, at)| Some((value.to_f64()?, at.timestamp_millis())))
+            .collect();
+        Self { target: target.to_string(), datapoints }
+    }
+}
+
+fn bad_request(message: String) -> ErrorResponse {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message })))
+}
+
+fn internal_error(context: &str, e: anyhow::Error) -> ErrorResponse {
+    error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+}
+
+/// GET /grafana
+/// Connection test of Grafana's JSON datasource
+pub async fn grafana_test_handler() -> &'static str {
+    "OK"
+}
+
+/// POST /grafana/search
+/// Metric names, for the datasource's query editor
+pub async fn grafana_search_handler() -> impl IntoResponse {
+    Json(METRICS.map(|(name, _, _)| name))
+}
+
+/// POST /grafana/metrics
+/// Metrics with their descriptions and the owner payload of those taking one
+pub async fn grafana_metrics_handler() -> impl IntoResponse {
+    let metrics: Vec<serde_json::Value> = METRICS
+        .iter()
+        .map(|(name, description, takes_owner)| {
+            let payloads = if *takes_owner {
+                serde_json::json!([{
+                    "label": "Owner",
+                    "name": "owner",
+                    "type": "input",
+                    "placeholder": "0x... or account:<name>",
+                }])
+            } else {
+                serde_json::json!([])
+            };
+            serde_json::json!({
+                "label": name,
+                "value": name,
+                "description": description,
+                "payloads": payloads,
+            })
+        })
+        .collect();
+    Json(metrics)
+}
+
+/// POST /grafana/query
+/// Time series for Grafana panels, in the JSON datasource's format
+///
+/// Points are spaced at Grafana's `intervalMs`, widened to fit
+/// `maxDataPoints`. Sync metrics plot one point per run; `health` is a single
+/// point per status at the current time.
+pub async fn grafana_query_handler(
+    State(state): State<AppState>,
+    Json(query): Json<GrafanaQuery>,
+) -> Response {
+    let range = &query.range;
+    if range.to <= range.from {
+        return bad_request("range.to must be after range.from".to_string()).into_response();
+    }
+    let interval = Duration::milliseconds(query.interval_ms.unwrap_or(0));
+    let max_points = query.max_data_points.unwrap_or(DEFAULT_MAX_DATA_POINTS);
+    let step = series_step(range.from, range.to, interval, max_points);
+
+    let mut series = Vec::new();
+    for target in query.targets.iter().filter(|t| !t.hide) {
+        info!("Grafana query for {} from {} to {}", target.target, range.from, range.to);
+        match target_series(&state, target, range, step).await {
+            Ok(target_series) => series.extend(target_series),
+            Err(response) => return response.into_response(),
+        }
+    }
+    (StatusCode::OK, Json(series)).into_response()
+}
+
+async fn target_series(
+    state: &AppState,
+    target: &GrafanaTarget,
+    range: &GrafanaRange,
+    step: Duration,
+) -> Result<Vec<TimeSeries>, ErrorResponse> {
+    let owner = match target.payload.as_ref().and_then(|p| p.owner.as_deref()) {
+        Some(owner) if !owner.is_empty() => Some(owner_scope_param(&state.db_pool, owner).await?),
+        _ => None,
+    };
+
+    match target.target.as_str() {
+        "pnl_fees" => {
+            let Some(owner) = owner else {
+                return Err(bad_request("pnl_fees needs an owner in its payload".to_string()));
+            };
+            let snapshots = get_snapshots_for_owners_between(
+                &state.db_pool,
+                &owner.addresses,
+                range.from,
+                range.to,
+            )
+            .await
+            .map_err(|e| internal_error("Failed to fetch snapshots", e))?;
+            let points = cumulative_fee_series(&snapshots, range.from, range.to, step);
+            Ok(vec![TimeSeries::new("pnl_fees", points.iter().map(|p| (p.value, p.timestamp)))])
+        }
+        "health" => {
+            let positions = batch_health(state, owner.as_ref().map(|o| o.addresses.as_slice()))
+                .await
+                .map_err(|e| internal_error("Failed to evaluate health", e))?;
+            let now = Utc::now();
+            let statuses = [
+                ("healthy", Some(HealthStatus::Healthy)),
+                ("warning", Some(HealthStatus::Warning)),
+                ("critical", Some(HealthStatus::Critical)),
+                ("unevaluated", None),
+            ];
+            Ok(statuses
+                .into_iter()
+                .map(|(name, status)| {
+                    let count = positions.iter().filter(|p| p.status == status).count();
+                    TimeSeries::new(name, std::iter::once((Decimal::from(count), now)))
+                })
+                .collect())
+        }
+        name
+        @ ("sync_duration_ms" | "sync_rows_fetched" | "sync_rows_inserted" | "sync_failed") => {
+            let runs = get_sync_runs_between(&state.db_pool, range.from, range.to, MAX_SYNC_RUNS)
+                .await
+                .map_err(|e| internal_error("Failed to fetch sync runs", e))?;
+            let value = |run: &SyncRun| match name {
+                "sync_duration_ms" => Decimal::from(run.total_ms()),
+                "sync_rows_fetched" => Decimal::from(run.rows_fetched),
+                "sync_rows_inserted" => Decimal::from(run.rows_inserted),
+                _ => Decimal::from(u8::from(run.status == SyncRunStatus::Failed)),
+            };
+            Ok(vec![TimeSeries::new(name, runs.iter().map(|run| (value(run), run.started_at)))])
+        }
+        other => Err(bad_request(format!(
+            "Unknown target {}; POST /grafana/search lists the metrics",
+            other
+        ))),
+    }
+}
@@ -0,0 +1,58 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use stillwater_indexer::{parse_positions_csv, store_positions, ImportRowError};
+use tracing::{error, info};
+
+use crate::handlers::auth::authorized_addresses;
+use crate::state::AppState;
+
+/// POST /positions/import
+///
+/// Body is a CSV with header `owner,pool_id,entry_date,tick_lower,tick_upper,liquidity`.
+/// Requires `Authorization: Bearer <api key>`; rows may only name owners the
+/// key has proven via Sign-In with Ethereum.
+pub async fn import_positions_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let owners = match authorized_addresses(&state, &headers).await {
+        Ok(owners) => owners,
+        Err(response) => return response,
+    };
+
+    let mut parsed = parse_positions_csv(body.as_bytes());
+
+    // Only accept positions for addresses this key owns
+    let (allowed, foreign): (Vec<_>, Vec<_>) = parsed
+        .positions
+        .into_iter()
+        .partition(|p| owners.contains(&p.position.owner));
+    parsed.positions = allowed;
+    parsed.errors.extend(foreign.into_iter().map(|p| ImportRowError {
+        line: p.line,
+        message: format!("owner {} is not linked to this API key", p.position.owner),
+    }));
+
+    match store_positions(&state.db_pool, parsed).await {
+        Ok(report) => {
+            info!(
+                "Imported {} manual positions ({} duplicates, {} rejected)",
+                report.imported,
+                report.duplicates,
+                report.errors.len()
+            );
+            (StatusCode::OK, Json(serde_json::to_value(report).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to import positions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use stillwater_db::{enqueue_job, get_job, get_jobs_for_owners};
+use stillwater_indexer::validate_job_params;
+use stillwater_models::{Address, Job, JobKind, JobStatus};
+use tracing::{error, info};
+
+use crate::handlers::auth::authorized_addresses;
+use crate::state::AppState;
+
+/// Most jobs `GET /jobs` lists
+const MAX_LISTED_JOBS: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct JobRequest {
+    /// `backfill`, `ledger_export` or `sync`
+    pub kind: String,
+    pub owner: Address,
+    /// Kind-specific options
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+fn internal_error(context: &str, e: anyhow::Error) -> Response {
+    error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+        .into_response()
+}
+
+fn job_not_found() -> Response {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Job not found" }))).into_response()
+}
+
+/// A job, if it belongs to one of the API key's owners
+async fn authorized_job(state: &AppState, headers: &HeaderMap, id: i64) -> Result<Job, Response> {
+    let addresses =
+        authorized_addresses(state, headers).await.map_err(IntoResponse::into_response)?;
+    match get_job(&state.db_pool, id).await {
+        Ok(Some(job)) if addresses.iter().any(|a| *a == job.owner) => Ok(job),
+        // Other owners' jobs are indistinguishable from missing ones
+        Ok(_) => Err(job_not_found()),
+        Err(e) => Err(internal_error("Failed to fetch job", e)),
+    }
+}
+
+/// POST /jobs
+/// Queue a backfill or report for a worker (requires an API key for the owner)
+pub async fn create_job_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<JobRequest>,
+) -> Response {
+    let addresses = match authorized_addresses(&state, &headers).await {
+        Ok(addresses) => addresses,
+        Err(response) => return response.into_response(),
+    };
+    if !addresses.contains(&request.owner) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Owner is not linked to this API key" })),
+        )
+            .into_response();
+    }
+
+    let Some(kind) = JobKind::parse(&request.kind) else {
+        let kinds: Vec<&str> = JobKind::ALL.iter().map(|k| k.as_str()).collect();
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Unknown job kind, expected one of: {}", kinds.join(", "))
+            })),
+        )
+            .into_response();
+    };
+    let params = match request.params {
+        serde_json::Value::Null => serde_json::json!({}),
+        params => params,
+    };
+    if let Err(e) = validate_job_params(kind, &params) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid params: {}", e) })),
+        )
+            .into_response();
+    }
+
+    match enqueue_job(&state.db_pool, kind, &request.owner.canonical(), &params).await {
+        Ok(job) => {
+            info!("Queued {} job {} for owner: {}", kind.as_str(), job.id, job.owner);
+            (StatusCode::ACCEPTED, Json(serde_json::to_value(job).unwrap())).into_response()
+        }
+        Err(e) => internal_error("Failed to enqueue job", e),
+    }
+}
+
+/// GET /jobs
+/// Recent jobs of the API key's owners, newest first
+pub async fn get_jobs_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let addresses = match authorized_addresses(&state, &headers).await {
+        Ok(addresses) => addresses,
+        Err(response) => return response.into_response(),
+    };
+
+    let owners: Vec<String> = addresses.iter().map(Address::canonical).collect();
+    match get_jobs_for_owners(&state.db_pool, &owners, MAX_LISTED_JOBS).await {
+        Ok(jobs) => (StatusCode::OK, Json(serde_json::to_value(jobs).unwrap())).into_response(),
+        Err(e) => internal_error("Failed to fetch jobs", e),
+    }
+}
+
+/// GET /jobs/:id
+/// A job's status, and its result once it succeeded
+pub async fn get_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Response {
+    match authorized_job(&state, &headers, id).await {
+        Ok(job) => (StatusCode::OK, Json(serde_json::to_value(job).unwrap())).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// GET /jobs/:id/download
+/// A finished ledger export as a file, like `/export/:owner/ledger` returns it
+pub async fn download_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Response {
+    let job = match authorized_job(&state, &headers, id).await {
+        Ok(job) => job,
+        Err(response) => return response,
+    };
+    if job.kind != JobKind::LedgerExport {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Only ledger exports can be downloaded" })),
+        )
+            .into_response();
+    }
+    if job.status != JobStatus::Succeeded {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": format!("Job is {}", job.status.as_str()),
+                "status": job.status,
+            })),
+        )
+            .into_response();
+    }
+
+    let result = job.result.unwrap_or_default();
+    let field = |name: &str| result.get(name).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", field("filename")),
+            ),
+        ],
+        field("ledger"),
+    )
+        .into_response()
+}
@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use stillwater_analytics::{
+    PositionPerformance, RankBy, aggregate_by_owner, format_duration, rank_owners,
+    rank_positions, summarize_performance,
+};
+use stillwater_db::get_snapshot_windows;
+use tracing::{error, info, warn};
+
+use crate::state::AppState;
+use crate::timerange::duration_param;
+
+/// Maximum number of leaderboard entries returned
+const MAX_LEADERBOARD_SIZE: usize = 100;
+
+/// Longest window positions are ranked over
+const MAX_LEADERBOARD_WINDOW_DAYS: i64 = 365;
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardParams {
+    /// Window like `24h`, `7d`, `30d` (default `7d`, at most a year)
+    pub window: Option<String>,
+    /// `position` (default) or `owner`
+    pub by: Option<String>,
+    /// `pnl` (default) or `apr`
+    pub metric: Option<String>,
+    /// `gainers` (default) or `losers`
+    pub order: Option<String>,
+    pub limit: Option<usize>,
+    /// Replace owner addresses with stable pseudonyms and drop position
+    /// identifiers (default `true`)
+    pub anonymize: Option<bool>,
+}
+
+/// GET /leaderboard?window=7d&by=position&metric=pnl&order=gainers&limit=N&anonymize=true
+/// Rank tracked positions or owners by net P&L or APR over a window
+pub async fn get_leaderboard_handler(
+    State(state): State<AppState>,
+    Query(params): Query<LeaderboardParams>,
+) -> impl IntoResponse {
+    let window = match duration_param(
+        "window",
+        params.window.as_deref(),
+        Duration::days(7),
+        Duration::days(MAX_LEADERBOARD_WINDOW_DAYS),
+    ) {
+        Ok(window) => window,
+        Err(response) => return response,
+    };
+
+    let by_owner = match params.by.as_deref().unwrap_or("position") {
+        "position" => false,
+        "owner" => true,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid by parameter" })),
+            );
+        }
+    };
+
+    let metric = match params.metric.as_deref().unwrap_or("pnl") {
+        "pnl" => RankBy::NetPnl,
+        "apr" => RankBy::Apr,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid metric parameter" })),
+            );
+        }
+    };
+
+    let descending = match params.order.as_deref().unwrap_or("gainers") {
+        "gainers" => true,
+        "losers" => false,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid order parameter" })),
+            );
+        }
+    };
+
+    let limit = params.limit.unwrap_or(20).clamp(1, MAX_LEADERBOARD_SIZE);
+    let anonymize = params.anonymize.unwrap_or(true);
+
+    info!("Building leaderboard over {} (by owner: {})", format_duration(window), by_owner);
+
+    let end = Utc::now();
+    let windows = match get_snapshot_windows(&state.db_pool, end - window, end).await {
+        Ok(w) => w,
+        Err(e) => {
+            error!("Failed to fetch snapshot windows: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    // Positions with invalid ranges are quarantined at sync; skip any that predate that
+    let performances: Vec<PositionPerformance> = windows
+        .iter()
+        .filter_map(|(position, w)| match summarize_performance(position, w) {
+            Ok(perf) => Some(perf),
+            Err(e) => {
+                warn!("Skipping position {} on leaderboard: {}", position.id, e);
+                None
+            }
+        })
+        .collect();
+
+    let mut entries = if by_owner {
+        let mut owners = rank_owners(aggregate_by_owner(&performances, window), metric, descending);
+        owners.truncate(limit);
+        serde_json::to_value(owners).unwrap()
+    } else {
+        let mut positions = rank_positions(performances, metric, descending);
+        positions.truncate(limit);
+        serde_json::to_value(positions).unwrap()
+    };
+    // Rankings can then be shared without doxxing owners. NFT and position ids
+    // are dropped too: `ownerOf` on the position manager would give the owner away.
+    if anonymize {
+        for entry in entries.as_array_mut().into_iter().flatten() {
+            if let Some(owner) = entry["owner"].as_str().map(|a| state.pseudonyms.pseudonym(a)) {
+                entry["owner"] = owner.into();
+            }
+            if let Some(entry) = entry.as_object_mut() {
+                entry.remove("nft_id");
+                entry.remove("position_id");
+            }
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "window_start": (end - window).to_rfc3339(),
+            "window_end": end.to_rfc3339(),
+            "entries": entries,
+        })),
+    )
+}
@@ -1 +1,18 @@
+pub mod accounts;
+pub mod admin;
+pub mod alerts;
+pub mod auth;
+pub mod backtests;
+pub mod chart;
+pub mod export;
+pub mod grafana;
+pub mod import;
+pub mod jobs;
+pub mod leaderboard;
+pub mod planner;
+pub mod pools;
+pub mod portfolio;
 pub mod positions;
+pub mod preferences;
+pub mod quality;
+pub mod query;
@@ -0,0 +1,243 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use stillwater_analytics::{
+    PLANNER_LOOKBACK_DAYS, TickRange, daily_tick_volatility, expected_tick_move,
+    fee_per_liquidity_per_day, frontier_widths, in_range_volume_share, range_frontier,
+    size_for_target_income,
+};
+use stillwater_db::{get_pool_by_id, get_snapshot_windows, get_swaps_for_pool};
+use stillwater_models::RetainedData;
+use tracing::{error, info};
+
+use crate::handlers::pools::{pool_twaps, volatility_comparison};
+use crate::handlers::positions::invalid_range_response;
+use crate::retention::{retention_warning, with_retention_warnings};
+use crate::state::AppState;
+use crate::volatility::implied_volatility_param;
+
+/// Upper bound on the fee history a plan is based on
+const MAX_PLANNER_LOOKBACK_DAYS: i64 = 90;
+/// Days ahead a frontier projects IL over by default, and at most
+const FRONTIER_HORIZON_DAYS: u32 = 7;
+const MAX_FRONTIER_HORIZON_DAYS: u32 = 365;
+/// Most candidate widths one frontier request may ask for
+const MAX_FRONTIER_CANDIDATES: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct SizeRequest {
+    pub pool_id: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Fee income wanted per month, in the units fees are tracked in (raw token1)
+    pub target_monthly_fees: Decimal,
+    /// Days of fee history and volume to measure rates over (default 7)
+    pub lookback_days: Option<i64>,
+    /// Annualized implied volatility to compare with realized, e.g. `"0.65"` or `"65%"`
+    /// (defaults to the configured sources)
+    pub implied_volatility: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FrontierRequest {
+    pub pool_id: String,
+    /// Candidate range widths in ticks (default multiples of the expected move)
+    pub widths: Option<Vec<i32>>,
+    /// Days of price movement IL is projected over (default 7)
+    pub horizon_days: Option<u32>,
+    /// Days of fee history, volume and volatility to measure over (default 7)
+    pub lookback_days: Option<i64>,
+}
+
+/// Internal error response
+fn internal_error(context: &str, e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    error!("{}: {}", context, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Internal server error" })),
+    )
+}
+
+/// POST /planner/size
+/// Liquidity and token amounts a range needs to earn a target monthly fee income
+///
+/// The in-range fee rate per unit of liquidity comes from tracked positions'
+/// snapshots over the lookback, scaled by the share of the pool's recent volume
+/// that executed inside the range. Amounts are at the pool's TWAP. With an
+/// implied volatility for the pair, `volatility` compares it with realized.
+pub async fn size_position_handler(
+    State(state): State<AppState>,
+    Json(req): Json<SizeRequest>,
+) -> impl IntoResponse {
+    info!("Sizing a position in pool {} for {} a month", req.pool_id, req.target_monthly_fees);
+
+    let lookback_days = req.lookback_days.unwrap_or(PLANNER_LOOKBACK_DAYS);
+    if !(1..=MAX_PLANNER_LOOKBACK_DAYS).contains(&lookback_days) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("lookback_days must be 1-{}", MAX_PLANNER_LOOKBACK_DAYS)
+            })),
+        );
+    }
+    let range = match TickRange::new(req.tick_lower, req.tick_upper) {
+        Ok(range) => range,
+        Err(e) => return invalid_range_response(e),
+    };
+
+    let implied_volatility = match implied_volatility_param(req.implied_volatility.as_deref()) {
+        Ok(volatility) => volatility,
+        Err(response) => return response,
+    };
+
+    let pool = match get_pool_by_id(&state.db_pool, &req.pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => return internal_error("Failed to fetch pool", e),
+    };
+
+    let now = Utc::now();
+    let from = now - Duration::days(lookback_days);
+    let price = match pool_twaps(&state.db_pool, &req.pool_id, &[state.twap.window()], now).await {
+        Ok(mut twaps) => twaps.pop().flatten().map(|twap| twap.price),
+        Err(e) => return internal_error("Failed to compute TWAP", e),
+    };
+    let swaps = match get_swaps_for_pool(&state.db_pool, &req.pool_id, from).await {
+        Ok(swaps) => swaps,
+        Err(e) => return internal_error("Failed to fetch swaps", e),
+    };
+    let windows = match get_snapshot_windows(&state.db_pool, from, now).await {
+        Ok(windows) => windows,
+        Err(e) => return internal_error("Failed to fetch snapshot windows", e),
+    };
+    let pool_windows: Vec<_> =
+        windows.into_iter().filter(|(position, _)| position.pool_id == req.pool_id).collect();
+
+    let (Some(price), Some(in_range_share)) = (price, in_range_volume_share(&swaps, range)) else {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "No recent swaps to price the pool" })),
+        );
+    };
+    let fee_rate = fee_per_liquidity_per_day(&pool_windows).unwrap_or(Decimal::ZERO);
+
+    match size_for_target_income(req.target_monthly_fees, range, price, fee_rate, in_range_share) {
+        Ok(size) => {
+            let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+            let volatility = volatility_comparison(&state, &pool, implied_volatility).await;
+            let body = serde_json::json!({
+                "pool_id": req.pool_id,
+                "tick_lower": range.lower,
+                "tick_upper": range.upper,
+                "lookback_days": lookback_days,
+                "size": size,
+                "volatility": volatility,
+            });
+            (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+        }
+        Err(e) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e.to_string() })))
+        }
+    }
+}
+
+/// POST /planner/frontier
+/// Candidate ranges around the pool price, narrow to wide, with projected APR and IL
+///
+/// APR uses the same in-range fee rate per unit of liquidity and in-range
+/// volume share as sizing, over the capital each range takes per unit. IL is
+/// the worse of a move down or up by the pool's realized daily tick volatility
+/// scaled to the horizon. Without `widths`, candidates are multiples of that
+/// move. `projected_apr` is null when no tracked position measured a fee rate.
+pub async fn range_frontier_handler(
+    State(state): State<AppState>,
+    Json(req): Json<FrontierRequest>,
+) -> impl IntoResponse {
+    info!("Building a range frontier for pool {}", req.pool_id);
+
+    let lookback_days = req.lookback_days.unwrap_or(PLANNER_LOOKBACK_DAYS);
+    let horizon_days = req.horizon_days.unwrap_or(FRONTIER_HORIZON_DAYS);
+    let widths = req.widths.unwrap_or_default();
+    if !(1..=MAX_PLANNER_LOOKBACK_DAYS).contains(&lookback_days)
+        || !(1..=MAX_FRONTIER_HORIZON_DAYS).contains(&horizon_days)
+        || widths.len() > MAX_FRONTIER_CANDIDATES
+        || widths.iter().any(|width| *width <= 0)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "lookback_days must be 1-{}, horizon_days 1-{}, and at most {} positive widths",
+                    MAX_PLANNER_LOOKBACK_DAYS, MAX_FRONTIER_HORIZON_DAYS, MAX_FRONTIER_CANDIDATES
+                )
+            })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &req.pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => return internal_error("Failed to fetch pool", e),
+    };
+
+    let now = Utc::now();
+    let from = now - Duration::days(lookback_days);
+    let price = match pool_twaps(&state.db_pool, &req.pool_id, &[state.twap.window()], now).await {
+        Ok(mut twaps) => twaps.pop().flatten().map(|twap| twap.price),
+        Err(e) => return internal_error("Failed to compute TWAP", e),
+    };
+    let swaps = match get_swaps_for_pool(&state.db_pool, &req.pool_id, from).await {
+        Ok(swaps) => swaps,
+        Err(e) => return internal_error("Failed to fetch swaps", e),
+    };
+    let windows = match get_snapshot_windows(&state.db_pool, from, now).await {
+        Ok(windows) => windows,
+        Err(e) => return internal_error("Failed to fetch snapshot windows", e),
+    };
+    let pool_windows: Vec<_> =
+        windows.into_iter().filter(|(position, _)| position.pool_id == req.pool_id).collect();
+
+    let (Some(price), Some(volatility)) = (price, daily_tick_volatility(&swaps)) else {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({
+                "error": "Not enough recent swaps to price the pool and measure its volatility"
+            })),
+        );
+    };
+    let fee_rate = fee_per_liquidity_per_day(&pool_windows);
+    let expected_move = expected_tick_move(volatility, horizon_days);
+    let widths =
+        if widths.is_empty() { frontier_widths(expected_move, pool.tick_spacing) } else { widths };
+    let frontier = range_frontier(
+        &swaps,
+        price,
+        pool.tick_spacing,
+        &widths,
+        fee_rate,
+        volatility,
+        horizon_days,
+    );
+
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    let body = serde_json::json!({
+        "pool_id": req.pool_id,
+        "price": price,
+        "lookback_days": lookback_days,
+        "horizon_days": horizon_days,
+        "daily_volatility": volatility,
+        "expected_move_ticks": expected_move,
+        "fee_per_liquidity_per_day": fee_rate,
+        "frontier": frontier,
+    });
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+}
@@ -0,0 +1,1278 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use futures::{StreamExt, TryStreamExt};
+use chrono::{DateTime, Duration, Months, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use stillwater_alerts::format_pool_param;
+use stillwater_analytics::{
+    FORECAST_LOOKBACK_DAYS, HeatmapConfig, MarketPoint, PriceDisplay, RebalanceConfig, TickPoint,
+    TickRange, TimeRangeLimits, Twap, VOLATILITY_LOOKBACK_DAYS, VolatilityComparison,
+    annualize_tick_volatility, apply_gas_price_history, build_cohorts, build_liquidity_heatmap,
+    calculate_twap, compare_volatility, daily_tick_volatility, daily_volumes, decode_tick_series,
+    default_ewma_alpha, detect_jit, fee_to_rate, forecast_volume, format_duration,
+    in_range_volume_share, liquidity_for_value, lp_fee_rate, market_points_from_swaps,
+    optimize_rebalance_trigger, price_to_tick, projected_fee_apr, rolling_realized_volatility,
+    summarize_performance, tick_points_from_swaps, tick_to_price,
+};
+use stillwater_db::{
+    get_gas_prices_between, get_last_swap_before, get_latest_gas_price,
+    get_liquidity_events_for_pool_between, get_pool_by_id, get_pool_initialization,
+    get_pool_lifetime_windows, get_pool_param_changes, get_pool_stats, get_position_by_id,
+    get_range_liquidity_before, get_swaps_for_pool, get_swaps_for_pool_between, get_tick_chunks,
+    stream_swaps_for_pool,
+};
+use stillwater_models::{Pool, PoolStats, RetainedData, Swap};
+use tracing::{error, info, warn};
+
+use crate::handlers::positions::invalid_range_response;
+use crate::ndjson::{ndjson_response, wants_ndjson};
+use crate::retention::{retention_warning, with_retention_warnings};
+use crate::state::AppState;
+use crate::timerange::{duration_param, interval_param, time_param, time_range_param};
+use crate::volatility::{ImpliedVolatility, implied_volatility_param};
+
+/// Window the pool stats' swap figures cover
+const POOL_STATS_WINDOW_HOURS: i64 = 24;
+
+/// Upper bounds on heatmap size, to keep responses renderable
+const MAX_HEATMAP_ROWS: i64 = 2000;
+const MAX_HEATMAP_BUCKETS: usize = 200;
+
+/// Default and longest heatmap windows, in days
+const HEATMAP_DEFAULT_DAYS: i64 = 7;
+const MAX_HEATMAP_DAYS: i64 = 90;
+
+/// Default and longest swap history replayed by the rebalance optimizer, in days
+const REBALANCE_DEFAULT_DAYS: i64 = 30;
+const MAX_REBALANCE_DAYS: i64 = 365;
+
+/// Upper bound on market points replayed per rebalance candidate
+const MAX_REBALANCE_POINTS: i64 = 5000;
+
+/// Upper bounds on forecast horizon and history
+const MAX_FORECAST_DAYS: usize = 90;
+const MAX_FORECAST_LOOKBACK_DAYS: i64 = 365;
+
+/// Default and longest swap listing windows in JSON, in days (streamed ones may span any length)
+const SWAPS_DEFAULT_DAYS: i64 = 1;
+const MAX_SWAPS_DAYS: i64 = 30;
+
+/// Most swaps a JSON listing returns
+const MAX_SWAP_ROWS: usize = 5000;
+
+/// Default and longest JIT detection windows, in days
+const JIT_DEFAULT_DAYS: i64 = 7;
+const MAX_JIT_DAYS: i64 = 90;
+
+/// Default and longest tick series windows, in days
+const TICKS_DEFAULT_DAYS: i64 = 30;
+const MAX_TICKS_DAYS: i64 = 5 * 365;
+
+/// Most tick points a response returns
+const MAX_TICK_POINTS: usize = 100_000;
+
+/// Default and longest rolling volatility series, in days
+const VOLATILITY_DEFAULT_DAYS: i64 = 30;
+const MAX_VOLATILITY_DAYS: i64 = 365;
+
+/// Longest trailing window one volatility point measures, in days
+const MAX_VOLATILITY_WINDOW_DAYS: i64 = 90;
+
+/// Most points a rolling volatility series returns
+const MAX_VOLATILITY_POINTS: i64 = 2000;
+
+/// Upper bounds on TWAP windows per request and their length (one week)
+/// Pool parameter changes listed unless `limit` is given, and the most allowed
+const DEFAULT_PARAM_CHANGES: i64 = 50;
+const MAX_PARAM_CHANGES: i64 = 500;
+
+const MAX_TWAP_WINDOWS: usize = 10;
+const MAX_TWAP_WINDOW_MINUTES: i64 = 7 * 24 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQueryParams {
+    /// Position whose range the grid is centered on
+    pub position_id: Option<i64>,
+    /// Explicit range, used when no position is given
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+    /// Start of the window, e.g. `3d`, unix seconds or RFC3339 (defaults to 7 days before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
+    /// Row height like `15m` or `1h` (default `1h`)
+    pub interval: Option<String>,
+    /// Row height in minutes, used when `interval` isn't given
+    pub interval_minutes: Option<i64>,
+    /// Number of tick columns (default 20)
+    pub buckets: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwapListParams {
+    /// Start of the window (defaults to 1 day before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JitQueryParams {
+    /// Start of the window (defaults to 7 days before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TickSeriesParams {
+    /// Start of the window (defaults to 30 days before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwapQueryParams {
+    /// Comma-separated window lengths in minutes (defaults to `TWAP_WINDOW_MINUTES`)
+    pub windows: Option<String>,
+    /// End of the windows, e.g. `1d`, unix seconds or RFC3339 (defaults to now)
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForecastQueryParams {
+    /// Days to project (default 7)
+    pub days: Option<usize>,
+    /// Days of history fitted (default 28)
+    pub lookback_days: Option<i64>,
+    /// EWMA weight of the newest day, between 0 and 1 (default 0.3)
+    pub alpha: Option<Decimal>,
+    /// Capital for the projected APR, in raw token1 units
+    pub capital: Option<Decimal>,
+    /// Share of in-range liquidity the capital would hold (default 0.01)
+    pub pool_share: Option<Decimal>,
+    /// Range the APR is projected for: a position's, or `tick_lower`/`tick_upper`.
+    /// Without one, all volume is assumed in range.
+    pub position_id: Option<i64>,
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebalanceQueryParams {
+    /// Position whose range width is optimized
+    pub position_id: Option<i64>,
+    /// Range width in ticks, used when no position is given
+    pub width: Option<i32>,
+    /// Capital to deploy, in raw token1 units
+    pub capital: Decimal,
+    /// Gas cost of one transaction, in raw token1 units
+    pub gas_cost: Decimal,
+    /// Scale `gas_cost`, taken as today's cost, by the recorded gas prices
+    /// over the window (default false)
+    pub historical_gas: Option<bool>,
+    /// Share of in-range swap fees the position earns (default 0.01, as in P&L estimates)
+    pub pool_share: Option<Decimal>,
+    /// Start of the history replayed (defaults to 30 days before `to`)
+    pub from: Option<String>,
+    /// End of the history replayed (defaults to now)
+    pub to: Option<String>,
+    /// Market point spacing like `15m` or `1h` (default `1h`)
+    pub interval: Option<String>,
+    /// Market point spacing in minutes, used when `interval` isn't given
+    pub interval_minutes: Option<i64>,
+    /// Annualized implied volatility to compare with realized, e.g. `0.65` or `65%`
+    /// (defaults to the configured sources)
+    pub implied_volatility: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParamChangeQueryParams {
+    /// Most changes returned (default 50, at most 500)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VolatilityQueryParams {
+    /// First point of the series (defaults to 30 days before `to`)
+    pub from: Option<String>,
+    /// Last point of the series (defaults to now)
+    pub to: Option<String>,
+    /// Point spacing like `1h` or `1d` (default `1d`)
+    pub interval: Option<String>,
+    /// Trailing window each point measures (default 7 days)
+    pub window: Option<String>,
+    /// Annualized implied volatility to compare with, e.g. `0.65` or `65%`
+    /// (defaults to the configured sources)
+    pub implied_volatility: Option<String>,
+}
+
+/// Compute a pool's stats (also used to warm the cache after sync)
+pub(crate) async fn build_pool_stats(
+    db_pool: &PgPool,
+    pool_id: &str,
+) -> anyhow::Result<Option<PoolStats>> {
+    let since = Utc::now() - Duration::hours(POOL_STATS_WINDOW_HOURS);
+    get_pool_stats(db_pool, pool_id, since).await
+}
+
+/// Daily tick volatility of a pool over the volatility lookback, if it has enough swaps
+pub(crate) async fn pool_volatility(
+    db_pool: &PgPool,
+    pool_id: &str,
+) -> anyhow::Result<Option<Decimal>> {
+    let since = Utc::now() - Duration::days(VOLATILITY_LOOKBACK_DAYS);
+    let swaps = get_swaps_for_pool(db_pool, pool_id, since).await?;
+    Ok(daily_tick_volatility(&swaps))
+}
+
+/// Implied volatility for a pool: the figure a request gave, else the configured sources'
+pub(crate) async fn pool_implied_volatility(
+    state: &AppState,
+    pool: &Pool,
+    requested: Option<Decimal>,
+) -> Option<ImpliedVolatility> {
+    match requested {
+        Some(volatility) => Some(ImpliedVolatility { volatility, source: "request".to_string() }),
+        None => state.implied_volatility.for_pool(pool).await,
+    }
+}
+
+/// A pool's realized volatility over the volatility lookback next to implied
+///
+/// None without an implied figure or enough recent swaps to measure realized
+/// volatility. Errors are logged rather than returned: the comparison only
+/// annotates a suggestion.
+pub(crate) async fn volatility_comparison(
+    state: &AppState,
+    pool: &Pool,
+    requested: Option<Decimal>,
+) -> Option<VolatilityComparison> {
+    let implied = pool_implied_volatility(state, pool, requested).await?;
+    let realized = match pool_volatility(&state.db_pool, &pool.pool_id).await {
+        Ok(volatility) => volatility.and_then(annualize_tick_volatility)?,
+        Err(e) => {
+            error!("Failed to estimate pool volatility: {}", e);
+            return None;
+        }
+    };
+    compare_volatility(realized, implied.volatility, &implied.source)
+}
+
+/// TWAPs of a pool over windows ending at `to`, in the order given
+///
+/// Reads the swaps of the longest window plus the last swap before it, which
+/// sets the price each window starts at. None for a window without any price.
+pub(crate) async fn pool_twaps(
+    db_pool: &PgPool,
+    pool_id: &str,
+    windows: &[Duration],
+    to: DateTime<Utc>,
+) -> anyhow::Result<Vec<Option<Twap>>> {
+    let Some(longest) = windows.iter().max() else {
+        return Ok(Vec::new());
+    };
+    let start = to - *longest;
+    let mut swaps = get_swaps_for_pool_between(db_pool, pool_id, start, to).await?;
+    swaps.extend(get_last_swap_before(db_pool, pool_id, start).await?);
+
+    Ok(windows.iter().map(|window| calculate_twap(&swaps, to - *window, to)).collect())
+}
+
+/// Average LP fee rate over swaps, per the pool's fee model and net of protocol fees
+///
+/// Falls back to the pool's fee tier without swaps.
+pub(crate) fn average_lp_fee_rate(state: &AppState, pool: &Pool, swaps: &[Swap]) -> Decimal {
+    let model = state.fee_models.model_for(pool);
+    if swaps.is_empty() {
+        fee_to_rate(pool.fee_tier)
+    } else {
+        swaps.iter().map(|swap| lp_fee_rate(model, pool, swap)).sum::<Decimal>()
+            / Decimal::from(swaps.len())
+    }
+}
+
+/// Price each market point's gas from the chain's recorded gas prices
+///
+/// `gas_cost` is what a transaction costs today; each point pays it scaled by
+/// how the gas price then compared with the latest sample of `CHAIN_ID`.
+/// Returns how many points were priced, zero without history in the window.
+pub(crate) async fn apply_historical_gas(
+    state: &AppState,
+    points: &mut [MarketPoint],
+    gas_cost: Decimal,
+) -> anyhow::Result<usize> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Ok(0);
+    };
+    let chain_id = state.siwe.chain_id as i64;
+    let Some(current) = get_latest_gas_price(&state.db_pool, chain_id).await? else {
+        return Ok(0);
+    };
+    let samples =
+        get_gas_prices_between(&state.db_pool, chain_id, first.timestamp, last.timestamp).await?;
+    let current_price = current.gas_price().unwrap_or_default();
+    Ok(apply_gas_price_history(points, gas_cost, &samples, current_price))
+}
+
+/// Error response when historical gas was asked for but none is recorded
+pub(crate) fn no_gas_history_response(state: &AppState) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(serde_json::json!({
+            "error": format!(
+                "No gas prices recorded for chain {} in the window (see the gas-history binary)",
+                state.siwe.chain_id
+            )
+        })),
+    )
+}
+
+/// GET /pools/:pool_id/stats
+/// Swap count and volume over the last 24h plus open/total position counts
+pub async fn get_pool_stats_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+) -> impl IntoResponse {
+    if let Some(cached) = state.cache.get("pool_stats", &pool_id).await {
+        return (StatusCode::OK, Json(cached));
+    }
+
+    match build_pool_stats(&state.db_pool, &pool_id).await {
+        Ok(Some(stats)) => {
+            let value = serde_json::to_value(stats).unwrap();
+            state.cache.put("pool_stats", &pool_id, &value).await;
+            (StatusCode::OK, Json(value))
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" }))),
+        Err(e) => {
+            error!("Failed to fetch pool stats: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// GET /pools/:pool_id/swaps?from=A&to=B
+/// A pool's swaps in a window, oldest first: at most 5000 as JSON, or all of them over any
+/// window streamed as NDJSON when `Accept` asks for it
+pub async fn get_pool_swaps_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    headers: HeaderMap,
+    Query(params): Query<SwapListParams>,
+) -> Response {
+    let streaming = wants_ndjson(&headers);
+    let mut limits = TimeRangeLimits::days(SWAPS_DEFAULT_DAYS, MAX_SWAPS_DAYS);
+    if streaming {
+        limits.max_span = Duration::MAX;
+    }
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response.into_response(),
+    };
+
+    info!("Listing swaps of pool {}", pool_id);
+
+    let rows = stream_swaps_for_pool(&state.db_pool, &pool_id, from, to);
+    if streaming {
+        return ndjson_response(rows, |swap| swap);
+    }
+
+    // One past the cap tells whether the listing was cut short
+    let mut swaps: Vec<Swap> = match rows.take(MAX_SWAP_ROWS + 1).try_collect().await {
+        Ok(swaps) => swaps,
+        Err(e) => {
+            error!("Failed to fetch swaps: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+                .into_response();
+        }
+    };
+    let truncated = swaps.len() > MAX_SWAP_ROWS;
+    swaps.truncate(MAX_SWAP_ROWS);
+
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    let body = serde_json::json!({
+        "pool_id": pool_id,
+        "from": from,
+        "to": to,
+        "swaps": swaps,
+        "truncated": truncated,
+    });
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+        .into_response()
+}
+
+/// GET /pools/:pool_id/creation
+/// When and at what price a pool was created, if its `Initialize` event was indexed
+pub async fn get_pool_creation_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+) -> impl IntoResponse {
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    match get_pool_initialization(&state.db_pool, &pool_id).await {
+        Ok(Some(initialization)) => {
+            // Token1 per token0, in whole tokens
+            let initial_price = PriceDisplay::for_pool(&pool, "token1")
+                .map(|display| display.to_display(tick_to_price(initialization.tick)));
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "pool_id": pool_id,
+                    "block_number": initialization.block_number,
+                    "tx_hash": initialization.tx_hash,
+                    "created_at": initialization.created_at,
+                    "sqrt_price_x96": initialization.sqrt_price_x96.to_string(),
+                    "tick": initialization.tick,
+                    "initial_price": initial_price,
+                })),
+            )
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Pool creation has not been indexed" })),
+        ),
+        Err(e) => {
+            error!("Failed to fetch pool creation: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// GET /pools/:pool_id/ticks?from=A&to=B
+/// The pool's execution tick at every swap, read from compressed monthly chunks
+/// where `sync` has built them and from raw swaps after the last one
+pub async fn get_pool_ticks_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<TickSeriesParams>,
+) -> impl IntoResponse {
+    info!("Fetching tick series of pool {}", pool_id);
+
+    let limits = TimeRangeLimits::days(TICKS_DEFAULT_DAYS, MAX_TICKS_DAYS);
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+
+    let chunks = match get_tick_chunks(&state.db_pool, &pool_id, from, to).await {
+        Ok(chunks) => chunks,
+        Err(e) => {
+            error!("Failed to fetch tick chunks: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let mut points: Vec<TickPoint> = Vec::new();
+    for chunk in &chunks {
+        let decoded = decode_tick_series(&chunk.data)
+            .and_then(|decoder| decoder.collect::<Result<Vec<_>, _>>());
+        match decoded {
+            Ok(decoded) => points.extend(
+                decoded.into_iter().filter(|p| p.timestamp >= from && p.timestamp <= to),
+            ),
+            Err(e) => {
+                error!("Corrupt tick chunk for {} at {}: {}", pool_id, chunk.month_start, e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        }
+    }
+    let compressed_points = points.len();
+
+    // Months after the last chunk haven't been compressed yet
+    let raw_from = chunks
+        .last()
+        .and_then(|c| c.month_start.checked_add_months(Months::new(1)))
+        .map_or(from, |end| end.max(from));
+    if raw_from <= to {
+        match get_swaps_for_pool_between(&state.db_pool, &pool_id, raw_from, to).await {
+            Ok(swaps) => points.extend(tick_points_from_swaps(&swaps)),
+            Err(e) => {
+                error!("Failed to fetch swaps: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        }
+    }
+    let raw_points = points.len() - compressed_points;
+    let truncated = points.len() > MAX_TICK_POINTS;
+    points.truncate(MAX_TICK_POINTS);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "pool_id": pool_id,
+            "from": from,
+            "to": to,
+            "compressed_points": compressed_points,
+            "raw_points": raw_points,
+            "truncated": truncated,
+            "points": points,
+        })),
+    )
+}
+
+/// GET /pools/:pool_id/heatmap?position_id=X&from=A&to=B&interval=1h&buckets=20
+/// Swap activity as a time × tick-bucket grid around a position's range (or `tick_lower`/`tick_upper`)
+pub async fn get_pool_heatmap_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<HeatmapQueryParams>,
+) -> impl IntoResponse {
+    info!("Fetching liquidity heatmap for pool {}", pool_id);
+
+    let limits = TimeRangeLimits::days(HEATMAP_DEFAULT_DAYS, MAX_HEATMAP_DAYS);
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+    let interval = match interval_param(
+        params.interval.as_deref(),
+        params.interval_minutes,
+        Duration::hours(1),
+        limits.max_span,
+    ) {
+        Ok(interval) => interval,
+        Err(response) => return response,
+    };
+
+    let config = HeatmapConfig { interval, tick_buckets: params.buckets.unwrap_or(20) };
+    let rows = (to - from).num_seconds() / config.interval.num_seconds().max(1);
+    if config.tick_buckets == 0
+        || config.tick_buckets > MAX_HEATMAP_BUCKETS
+        || rows > MAX_HEATMAP_ROWS
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "Grid too large or empty: at most {} rows and {} buckets",
+                    MAX_HEATMAP_ROWS, MAX_HEATMAP_BUCKETS
+                )
+            })),
+        );
+    }
+
+    let (tick_lower, tick_upper) = match (params.position_id, params.tick_lower, params.tick_upper)
+    {
+        (Some(id), _, _) => match get_position_by_id(&state.db_pool, id).await {
+            Ok(Some(position)) if position.pool_id == pool_id => {
+                (position.tick_lower, position.tick_upper)
+            }
+            Ok(_) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": "Position not found in this pool" })),
+                );
+            }
+            Err(e) => {
+                error!("Failed to fetch position: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        },
+        (None, Some(lower), Some(upper)) => (lower, upper),
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "position_id or both tick_lower and tick_upper are required"
+                })),
+            );
+        }
+    };
+    let range = match TickRange::new(tick_lower, tick_upper) {
+        Ok(range) => range,
+        Err(e) => return invalid_range_response(e),
+    };
+
+    let swaps = match get_swaps_for_pool_between(&state.db_pool, &pool_id, from, to).await {
+        Ok(swaps) => swaps,
+        Err(e) => {
+            error!("Failed to fetch swaps: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    let heatmap = build_liquidity_heatmap(range, &swaps, from, to, &config);
+    let body = serde_json::to_value(heatmap).unwrap();
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+}
+
+/// GET /pools/:pool_id/rebalance-policy?width=200&capital=X&gas_cost=Y&from=A&to=B&interval=1h
+/// Rebalance trigger distance that would have earned the most over the pool's swap history
+pub async fn get_rebalance_policy_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<RebalanceQueryParams>,
+) -> impl IntoResponse {
+    info!("Optimizing rebalance policy for pool {}", pool_id);
+
+    let limits = TimeRangeLimits::days(REBALANCE_DEFAULT_DAYS, MAX_REBALANCE_DAYS);
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+    let interval = match interval_param(
+        params.interval.as_deref(),
+        params.interval_minutes,
+        Duration::hours(1),
+        limits.max_span,
+    ) {
+        Ok(interval) => interval,
+        Err(response) => return response,
+    };
+    let pool_share = params.pool_share.unwrap_or(Decimal::new(1, 2));
+    if (to - from).num_seconds() / interval.num_seconds() > MAX_REBALANCE_POINTS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "At most {} intervals may fit between from and to",
+                    MAX_REBALANCE_POINTS
+                )
+            })),
+        );
+    }
+    if params.capital <= Decimal::ZERO
+        || params.gas_cost < Decimal::ZERO
+        || pool_share <= Decimal::ZERO
+        || pool_share >= Decimal::ONE
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "capital must be positive, gas_cost non-negative and pool_share between 0 and 1"
+            })),
+        );
+    }
+    let implied_volatility = match implied_volatility_param(params.implied_volatility.as_deref()) {
+        Ok(volatility) => volatility,
+        Err(response) => return response,
+    };
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let range_width = match (params.position_id, params.width) {
+        (Some(id), _) => match get_position_by_id(&state.db_pool, id).await {
+            Ok(Some(position)) if position.pool_id == pool_id => match TickRange::of(&position) {
+                Ok(range) => range.width(),
+                Err(e) => return invalid_range_response(e),
+            },
+            Ok(_) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": "Position not found in this pool" })),
+                );
+            }
+            Err(e) => {
+                error!("Failed to fetch position: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        },
+        (None, Some(width)) if width > 0 => width,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "position_id or a positive width is required" })),
+            );
+        }
+    };
+
+    let swaps = match get_swaps_for_pool_between(&state.db_pool, &pool_id, from, to).await {
+        Ok(swaps) => swaps,
+        Err(e) => {
+            error!("Failed to fetch swaps: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let fee_rate = average_lp_fee_rate(&state, &pool, &swaps);
+
+    // Swaps don't record pool liquidity: size other LPs' liquidity so the
+    // initial position earns `pool_share` of fees
+    let mut points = market_points_from_swaps(&swaps, from, to, interval, Decimal::ZERO);
+    let Some(first) = points.first() else {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "No swaps in the window to replay" })),
+        );
+    };
+    let tick = price_to_tick(first.price);
+    let own_liquidity = liquidity_for_value(
+        params.capital,
+        first.price,
+        tick_to_price(tick - range_width / 2),
+        tick_to_price(tick + range_width / 2),
+    );
+    let active_liquidity = own_liquidity * (Decimal::ONE - pool_share) / pool_share;
+    for point in &mut points {
+        point.active_liquidity = active_liquidity;
+    }
+    let mut gas_points_priced = None;
+    if params.historical_gas.unwrap_or(false) {
+        match apply_historical_gas(&state, &mut points, params.gas_cost).await {
+            Ok(0) => return no_gas_history_response(&state),
+            Ok(priced) => gas_points_priced = Some(priced),
+            Err(e) => {
+                error!("Failed to fetch gas prices: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        }
+    }
+
+    let config = RebalanceConfig {
+        range_width,
+        tick_spacing: pool.tick_spacing,
+        initial_capital: params.capital,
+        fee_rate,
+        gas_cost_per_tx: params.gas_cost,
+        triggers: vec![],
+    };
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    match optimize_rebalance_trigger(&config, &points) {
+        Ok(optimization) => {
+            let mut body = serde_json::to_value(optimization).unwrap();
+            body["volatility"] =
+                serde_json::json!(volatility_comparison(&state, &pool, implied_volatility).await);
+            if let Some(priced) = gas_points_priced {
+                body["historical_gas"] =
+                    serde_json::json!({ "points_priced": priced, "points": points.len() });
+            }
+            (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// GET /pools/:pool_id/volatility?from=A&to=B&interval=1d&window=7d&implied_volatility=X
+/// Rolling realized volatility of a pool, compared with implied volatility for its pair
+///
+/// Each point is the annualized volatility of the trailing `window`; the last
+/// point's is compared with implied volatility from the request or the
+/// configured sources.
+pub async fn get_pool_volatility_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<VolatilityQueryParams>,
+) -> impl IntoResponse {
+    info!("Comparing realized and implied volatility of pool {}", pool_id);
+
+    let limits = TimeRangeLimits::days(VOLATILITY_DEFAULT_DAYS, MAX_VOLATILITY_DAYS);
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+    let interval = match duration_param(
+        "interval",
+        params.interval.as_deref(),
+        Duration::days(1),
+        limits.max_span,
+    ) {
+        Ok(interval) => interval,
+        Err(response) => return response,
+    };
+    let window = match duration_param(
+        "window",
+        params.window.as_deref(),
+        Duration::days(VOLATILITY_LOOKBACK_DAYS),
+        Duration::days(MAX_VOLATILITY_WINDOW_DAYS),
+    ) {
+        Ok(window) => window,
+        Err(response) => return response,
+    };
+    if interval <= Duration::zero() || window <= Duration::zero() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "interval and window must be positive" })),
+        );
+    }
+    if (to - from).num_seconds() / interval.num_seconds() >= MAX_VOLATILITY_POINTS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "At most {} points may fit between from and to",
+                    MAX_VOLATILITY_POINTS
+                )
+            })),
+        );
+    }
+    let requested = match implied_volatility_param(params.implied_volatility.as_deref()) {
+        Ok(volatility) => volatility,
+        Err(response) => return response,
+    };
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+    let swaps = match get_swaps_for_pool_between(&state.db_pool, &pool_id, from - window, to).await
+    {
+        Ok(swaps) => swaps,
+        Err(e) => {
+            error!("Failed to fetch swaps: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    // Step back from `to` so the last point measures the window ending there
+    let steps = (to - from).num_seconds() / interval.num_seconds();
+    let first = to - interval * steps as i32;
+    let series = rolling_realized_volatility(&swaps, first, to, interval, window);
+    let realized = series.last().and_then(|point| point.volatility);
+    let implied = pool_implied_volatility(&state, &pool, requested).await;
+    let comparison = realized.zip(implied.as_ref()).and_then(|(realized, implied)| {
+        compare_volatility(realized, implied.volatility, &implied.source)
+    });
+
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from - window).await;
+    let body = serde_json::json!({
+        "pool_id": pool_id,
+        "window": format_duration(window),
+        "realized": realized,
+        "implied": implied.as_ref().map(|implied| implied.volatility),
+        "implied_source": implied.map(|implied| implied.source),
+        "comparison": comparison,
+        "series": series,
+    });
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+}
+
+/// GET /pools/:pool_id/param-changes?limit=50
+/// LP and protocol fee changes `sync` has seen in the pool, newest first
+pub async fn get_pool_param_changes_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<ParamChangeQueryParams>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(DEFAULT_PARAM_CHANGES);
+    if !(1..=MAX_PARAM_CHANGES).contains(&limit) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("limit must be between 1 and {}", MAX_PARAM_CHANGES)
+            })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    match get_pool_param_changes(&state.db_pool, &pool_id, limit).await {
+        Ok(changes) => {
+            let changes: Vec<_> = changes
+                .into_iter()
+                .map(|change| {
+                    serde_json::json!({
+                        "relative_change": change.relative_change(),
+                        "old": format_pool_param(change.param, change.old_value),
+                        "new": format_pool_param(change.param, change.new_value),
+                        "change": change,
+                    })
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "pool_id": pool.pool_id,
+                    "changes": changes,
+                })),
+            )
+        }
+        Err(e) => {
+            error!("Failed to fetch pool parameter changes: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// GET /pools/:pool_id/jit?from=A&to=B
+/// Just-in-time liquidity around the pool's swaps and the fees it took from passive LPs
+pub async fn get_pool_jit_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<JitQueryParams>,
+) -> impl IntoResponse {
+    info!("Detecting JIT liquidity in pool {}", pool_id);
+
+    let limits = TimeRangeLimits::days(JIT_DEFAULT_DAYS, MAX_JIT_DAYS);
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response,
+    };
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let history = tokio::try_join!(
+        get_swaps_for_pool_between(&state.db_pool, &pool_id, from, to),
+        get_range_liquidity_before(&state.db_pool, &pool_id, from),
+        get_liquidity_events_for_pool_between(&state.db_pool, &pool_id, from, to),
+    );
+    let (swaps, ranges_before, events) = match history {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to fetch pool history: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let model = state.fee_models.model_for(&pool);
+    let report = detect_jit(&pool, model, &ranges_before, &events, &swaps, &state.jit);
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    let body = serde_json::json!({
+        "pool_id": pool_id,
+        "from": from,
+        "to": to,
+        "config": *state.jit,
+        "episodes": report.episodes,
+        "total_fees": report.total_fees,
+        "jit_fees": report.jit_fees,
+        "jit_fee_share": report.jit_fee_share,
+    });
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+}
+
+/// GET /pools/:pool_id/cohorts
+/// Positions grouped by entry month and range width, with median APR and IL per cohort
+pub async fn get_pool_cohorts_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+) -> impl IntoResponse {
+    info!("Fetching position cohorts for pool {}", pool_id);
+
+    let windows = match get_pool_lifetime_windows(&state.db_pool, &pool_id).await {
+        Ok(windows) => windows,
+        Err(e) => {
+            error!("Failed to fetch snapshot windows: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let performances: Vec<_> = windows
+        .iter()
+        .filter_map(|(position, window)| match summarize_performance(position, window) {
+            Ok(performance) => {
+                Some((position.created_at, position.tick_lower, position.tick_upper, performance))
+            }
+            Err(e) => {
+                warn!("Skipping position {} in cohorts: {}", position.id, e);
+                None
+            }
+        })
+        .collect();
+
+    let cohorts = build_cohorts(&performances);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "pool_id": pool_id,
+            "positions": performances.len(),
+            "cohorts": cohorts,
+        })),
+    )
+}
+
+/// GET /pools/:pool_id/twap?windows=5,30,1440&to=T
+/// Time-weighted average price over each window, geometric mean of swap execution prices
+pub async fn get_pool_twap_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<TwapQueryParams>,
+) -> impl IntoResponse {
+    let minutes: Option<Vec<i64>> = match &params.windows {
+        Some(windows) => windows.split(',').map(|w| w.trim().parse().ok()).collect(),
+        None => Some(vec![state.twap.window_minutes]),
+    };
+    let windows = match minutes {
+        Some(minutes)
+            if !minutes.is_empty()
+                && minutes.len() <= MAX_TWAP_WINDOWS
+                && minutes.iter().all(|m| (1..=MAX_TWAP_WINDOW_MINUTES).contains(m)) =>
+        {
+            minutes
+        }
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!(
+                        "windows must be up to {} comma-separated minute counts, each 1-{}",
+                        MAX_TWAP_WINDOWS, MAX_TWAP_WINDOW_MINUTES
+                    )
+                })),
+            );
+        }
+    };
+
+    match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    }
+
+    let to = match time_param("to", params.to.as_deref()) {
+        Ok(to) => to.unwrap_or_else(Utc::now),
+        Err(response) => return response,
+    };
+    let durations: Vec<Duration> = windows.iter().map(|m| Duration::minutes(*m)).collect();
+    match pool_twaps(&state.db_pool, &pool_id, &durations, to).await {
+        Ok(twaps) => {
+            let twaps: Vec<_> = windows
+                .iter()
+                .zip(twaps)
+                .map(|(minutes, twap)| {
+                    serde_json::json!({ "window_minutes": minutes, "twap": twap })
+                })
+                .collect();
+            (StatusCode::OK, Json(serde_json::json!({ "pool_id": pool_id, "twaps": twaps })))
+        }
+        Err(e) => {
+            error!("Failed to compute TWAP: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch swaps" })),
+            )
+        }
+    }
+}
+
+/// GET /pools/:pool_id/forecast?days=7&lookback_days=28&capital=X&pool_share=0.01&position_id=Y
+/// Projected daily swap volume and, with `capital`, the fee APR a range would earn at that volume
+pub async fn get_volume_forecast_handler(
+    State(state): State<AppState>,
+    Path(pool_id): Path<String>,
+    Query(params): Query<ForecastQueryParams>,
+) -> impl IntoResponse {
+    info!("Forecasting volume for pool {}", pool_id);
+
+    let days = params.days.unwrap_or(7);
+    let lookback_days = params.lookback_days.unwrap_or(FORECAST_LOOKBACK_DAYS);
+    let alpha = params.alpha.unwrap_or_else(default_ewma_alpha);
+    let pool_share = params.pool_share.unwrap_or(Decimal::new(1, 2));
+    if days == 0
+        || days > MAX_FORECAST_DAYS
+        || lookback_days <= 0
+        || lookback_days > MAX_FORECAST_LOOKBACK_DAYS
+        || alpha <= Decimal::ZERO
+        || alpha > Decimal::ONE
+        || pool_share <= Decimal::ZERO
+        || pool_share > Decimal::ONE
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "days must be 1-{}, lookback_days 1-{}, alpha and pool_share in (0, 1]",
+                    MAX_FORECAST_DAYS, MAX_FORECAST_LOOKBACK_DAYS
+                )
+            })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Pool not found" })));
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let range = match (params.position_id, params.tick_lower, params.tick_upper) {
+        (Some(id), _, _) => match get_position_by_id(&state.db_pool, id).await {
+            Ok(Some(position)) if position.pool_id == pool_id => match TickRange::of(&position) {
+                Ok(range) => Some(range),
+                Err(e) => return invalid_range_response(e),
+            },
+            Ok(_) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": "Position not found in this pool" })),
+                );
+            }
+            Err(e) => {
+                error!("Failed to fetch position: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        },
+        (None, Some(lower), Some(upper)) => match TickRange::new(lower, upper) {
+            Ok(range) => Some(range),
+            Err(e) => return invalid_range_response(e),
+        },
+        _ => None,
+    };
+
+    // Fit whole UTC days only, so today's partial volume doesn't drag the level down
+    let to = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let from = to - Duration::days(lookback_days);
+    let swaps = match get_swaps_for_pool_between(&state.db_pool, &pool_id, from, to).await {
+        Ok(swaps) => swaps,
+        Err(e) => {
+            error!("Failed to fetch swaps: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let Some(forecast) = forecast_volume(&daily_volumes(&swaps, from, to), days, alpha) else {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "No volume history to forecast from" })),
+        );
+    };
+
+    let fee_rate = average_lp_fee_rate(&state, &pool, &swaps);
+    let in_range_share = match range {
+        Some(range) => in_range_volume_share(&swaps, range).unwrap_or(Decimal::ZERO),
+        None => Decimal::ONE,
+    };
+    let projected_apr = params.capital.and_then(|capital| {
+        projected_fee_apr(
+            forecast.projected_daily_volume,
+            fee_rate,
+            pool_share,
+            in_range_share,
+            capital,
+        )
+    });
+    // The same fees over the capital while it's in range
+    let projected_in_range_apr =
+        projected_apr.and_then(|apr| apr.checked_div(in_range_share).filter(|_| range.is_some()));
+
+    let warnings = retention_warning(&state.db_pool, RetainedData::Swaps, from).await;
+    let body = serde_json::json!({
+        "pool_id": pool_id,
+        "forecast": forecast,
+        "fee_rate": fee_rate,
+        "pool_share": pool_share,
+        "in_range_share": in_range_share,
+        "projected_apr": projected_apr,
+        "projected_in_range_apr": projected_in_range_apr,
+    });
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+}
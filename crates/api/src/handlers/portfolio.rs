@@ -0,0 +1,1117 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use alloy::primitives::U256;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
+use stillwater_analytics::{
+    DEFAULT_CHAIN_WINDOW_MINUTES, DecompositionTotals, ExchangeRates, FeeVelocityTrend,
+    HEALTH_TTL_LOOKBACK, HoldingSummary, MAX_RATE_HOPS, PositionHealth, evaluate_health_batch,
+    NormalizedPosition, NormalizedTotals, PnlDecomposition, PoolExposure, PositionStress,
+    RebalanceChain, TimeRange,
+    DEFAULT_PNL_SWING, RiskAdjustedReturns, RiskDistribution, StressTotals, TickRange,
+    TimeRangeLimits, TimelineEvent, TokenRate, build_timeline, chain_link, classify_risk, decompose_pnl, combine_daily_returns, daily_returns, detect_rebalance_chains,
+    fee_velocity_trend, format_duration, normalize_position, parse_price_move, position_greeks,
+    risk_adjusted_returns, stress_position, sum_decompositions, sum_normalized, sum_stress,
+    summarize_exposure,
+    summarize_holding, summarize_risk, vault_exposure, VaultExposure, VaultStake,
+};
+use stillwater_db::{
+    PositionFilter, PositionStatus, find_positions, get_fee_accumulators,
+    get_gas_expenses_for_position, get_liquidity_events_for_owners, get_pool_by_id,
+    get_pools_with_tokens, get_position_health_rows, get_snapshots_for_owners,
+    get_snapshots_for_position, get_transfers_for_owners, get_vaults,
+};
+use stillwater_models::{Address, Pool, Position, PositionSnapshot, Vault};
+use tracing::{error, info, warn};
+
+use crate::address::{OwnerScope, owner_scope_param};
+use crate::handlers::pools::{pool_twaps, pool_volatility};
+use crate::state::AppState;
+use crate::timerange::{ErrorResponse, duration_param, time_range_param};
+
+#[derive(Debug, Serialize)]
+pub struct PortfolioResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub holding: HoldingSummary,
+    /// Open positions per risk bucket (range width relative to pair volatility)
+    pub risk: RiskDistribution,
+    /// Delta/gamma of open positions per pool at the pool's TWAP (pools without swaps are left out)
+    pub exposure: Vec<PoolExposure>,
+    /// Recent fee accrual rate of open positions against their baseline, falling ones first
+    pub fee_velocity: Vec<PositionFeeVelocity>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PositionFeeVelocity {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    #[serde(flatten)]
+    pub trend: FeeVelocityTrend,
+}
+
+/// Compute an owner's portfolio analytics (also used to warm the cache after sync)
+///
+/// Archived positions are left out unless `include_archived` is set; only
+/// the default view is cached.
+pub(crate) async fn build_portfolio(
+    state: &AppState,
+    owner: &OwnerScope,
+    include_archived: bool,
+) -> anyhow::Result<PortfolioResponse> {
+    let db_pool = &state.db_pool;
+    let filter = PositionFilter {
+        owners: Some(owner.addresses.clone()),
+        include_archived,
+        ..Default::default()
+    };
+    let positions = find_positions(db_pool, &filter).await?;
+    let snapshots = get_snapshots_for_owners(db_pool, &owner.addresses).await?;
+
+    let now = Utc::now();
+    let mut volatility = HashMap::new();
+    let mut prices = HashMap::new();
+    let mut categories = Vec::new();
+    let mut greeks = Vec::new();
+    let mut fee_velocity = Vec::new();
+    for position in positions.iter().filter(|p| !p.liquidity.is_zero()) {
+        if !volatility.contains_key(&position.pool_id) {
+            let v = pool_volatility(db_pool, &position.pool_id).await?;
+            volatility.insert(position.pool_id.clone(), v);
+            let window = state.twap.window();
+            let mut twaps = pool_twaps(db_pool, &position.pool_id, &[window], now).await?;
+            prices.insert(position.pool_id.clone(), twaps.pop().flatten().map(|t| t.price));
+        }
+        let pool_volatility = volatility[&position.pool_id];
+        let range = TickRange::of(position).ok();
+        categories.push(range.zip(pool_volatility).map(|(range, v)| classify_risk(range, v)));
+
+        let liquidity = Decimal::from_str(&position.liquidity.to_string()).ok();
+        if let (Some(range), Some(price), Some(liquidity)) =
+            (range, prices[&position.pool_id], liquidity)
+        {
+            let position_greeks = position_greeks(liquidity, range, price);
+            greeks.push((position.pool_id.clone(), price, position_greeks));
+        }
+
+        let position_snapshots: Vec<PositionSnapshot> =
+            snapshots.iter().filter(|s| s.position_id == position.id).cloned().collect();
+        if let Some(trend) = fee_velocity_trend(&position_snapshots, now, &state.fee_velocity) {
+            fee_velocity.push(PositionFeeVelocity {
+                position_id: position.id,
+                nft_id: position.nft_id.clone(),
+                pool_id: position.pool_id.clone(),
+                trend,
+            });
+        }
+    }
+    fee_velocity.sort_by_key(|v| (!v.trend.dropping, v.trend.change));
+
+    Ok(PortfolioResponse {
+        owner: owner.label.clone(),
+        holding: summarize_holding(&positions, &snapshots, now),
+        risk: summarize_risk(categories),
+        exposure: summarize_exposure(greeks),
+        fee_velocity,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PortfolioParams {
+    /// Also count archived positions (left out by default)
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// GET /portfolio/:owner?include_archived=true
+/// Portfolio analytics for an owner: position ages, hold times, fee income by week of life,
+/// how open positions spread across risk buckets and their delta/gamma per pool
+pub async fn get_portfolio_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<PortfolioParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    info!("Fetching portfolio analytics for owner: {}", owner);
+
+    // Only single addresses are cached; the cache is refreshed per address after sync
+    let cache_key = owner.address().filter(|_| !params.include_archived).map(|a| a.canonical());
+    if let Some(key) = &cache_key
+        && let Some(cached) = state.cache.get("portfolio", key).await
+    {
+        return (StatusCode::OK, Json(cached));
+    }
+
+    match build_portfolio(&state, &owner, params.include_archived).await {
+        Ok(portfolio) => {
+            let value = serde_json::to_value(portfolio).unwrap();
+            if let Some(key) = &cache_key {
+                state.cache.put("portfolio", key, &value).await;
+            }
+            (StatusCode::OK, Json(value))
+        }
+        Err(e) => {
+            error!("Failed to build portfolio analytics: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotalsParams {
+    /// Quote currency, e.g. `usd` or `eth` (defaults to `QUOTE_CURRENCY`)
+    pub currency: Option<String>,
+    /// Also count archived positions (left out by default)
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PortfolioTotalsResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub totals: NormalizedTotals,
+    pub positions: Vec<NormalizedPosition>,
+    /// Prices of the tokens involved and the pools each was derived through
+    pub rates: BTreeMap<String, TokenRate>,
+}
+
+/// A pool's TWAP (raw) over the configured window ending at `as_of`, None without swaps
+async fn pool_price(
+    state: &AppState,
+    pool_id: &str,
+    as_of: DateTime<Utc>,
+) -> anyhow::Result<Option<Decimal>> {
+    let mut twaps = pool_twaps(&state.db_pool, pool_id, &[state.twap.window()], as_of).await?;
+    Ok(twaps.pop().flatten().map(|t| t.price))
+}
+
+/// Token prices in `currency` for the tokens of `pools`, all from pool prices at `as_of`
+///
+/// Pools sharing a token with a still unpriced one are looked up for up to
+/// `MAX_RATE_HOPS` rounds; only those with a priced side are TWAPed. Returns
+/// the rates with the raw prices of every pool priced along the way.
+async fn resolve_rates(
+    state: &AppState,
+    currency: &str,
+    anchors: &[String],
+    pools: &[Pool],
+    as_of: DateTime<Utc>,
+) -> anyhow::Result<(ExchangeRates, HashMap<String, Decimal>)> {
+    let mut rates = ExchangeRates::new(currency, anchors, as_of);
+    let mut prices = HashMap::new();
+    let mut priced = Vec::new();
+    for pool in pools {
+        if let Some(price) = pool_price(state, &pool.pool_id, as_of).await? {
+            prices.insert(pool.pool_id.clone(), price);
+            priced.push((pool.clone(), price));
+        }
+    }
+    rates.link(&priced);
+
+    let mut seen: HashSet<String> = pools.iter().map(|p| p.pool_id.clone()).collect();
+    let mut wanted: HashSet<String> =
+        pools.iter().flat_map(|p| [p.token0.to_lowercase(), p.token1.to_lowercase()]).collect();
+    let mut pending: Vec<Pool> = Vec::new();
+    for _ in 0..MAX_RATE_HOPS {
+        let unpriced: Vec<String> =
+            wanted.iter().filter(|t| rates.rate(t).is_none()).cloned().collect();
+        if unpriced.is_empty() {
+            break;
+        }
+        for pool in get_pools_with_tokens(&state.db_pool, &unpriced).await? {
+            if seen.insert(pool.pool_id.clone()) {
+                wanted.extend([pool.token0.to_lowercase(), pool.token1.to_lowercase()]);
+                pending.push(pool);
+            }
+        }
+
+        // A pool can only carry a rate once one of its tokens has one
+        loop {
+            let (ready, waiting): (Vec<Pool>, Vec<Pool>) = pending
+                .into_iter()
+                .partition(|p| rates.rate(&p.token0).is_some() || rates.rate(&p.token1).is_some());
+            pending = waiting;
+            if ready.is_empty() {
+                break;
+            }
+            for pool in ready {
+                if let Some(price) = pool_price(state, &pool.pool_id, as_of).await? {
+                    priced.push((pool, price));
+                }
+            }
+            if rates.link(&priced) == 0 {
+                break;
+            }
+        }
+    }
+    Ok((rates, prices))
+}
+
+/// Positions of some owners normalized into one quote currency, with the rates and pools used
+struct NormalizedHoldings {
+    positions: Vec<NormalizedPosition>,
+    rates: ExchangeRates,
+    pools: Vec<Pool>,
+}
+
+/// Value, fees and IL of the positions `owners` hold, converted into one quote currency
+async fn normalize_holdings(
+    state: &AppState,
+    owners: &[Address],
+    currency: &str,
+    anchors: &[String],
+    include_archived: bool,
+) -> anyhow::Result<NormalizedHoldings> {
+    let db_pool = &state.db_pool;
+    let filter =
+        PositionFilter { owners: Some(owners.to_vec()), include_archived, ..Default::default() };
+    let positions = find_positions(db_pool, &filter).await?;
+
+    let mut pools: HashMap<String, Pool> = HashMap::new();
+    for position in &positions {
+        if pools.contains_key(&position.pool_id) {
+            continue;
+        }
+        if let Some(pool) = get_pool_by_id(db_pool, &position.pool_id).await? {
+            pools.insert(pool.pool_id.clone(), pool);
+        }
+    }
+    let held: Vec<Pool> = pools.values().cloned().collect();
+
+    // Every price is a TWAP ending at the same instant
+    let as_of = Utc::now();
+    let (rates, prices) = resolve_rates(state, currency, anchors, &held, as_of).await?;
+
+    let ids: Vec<i64> = positions.iter().map(|p| p.id).collect();
+    let fees: HashMap<i64, Decimal> = get_fee_accumulators(db_pool, &ids)
+        .await?
+        .into_iter()
+        .map(|a| (a.position_id, a.fees_earned))
+        .collect();
+    let mut entry_prices: HashMap<i64, (DateTime<Utc>, Decimal)> = HashMap::new();
+    for snapshot in get_snapshots_for_owners(db_pool, owners).await? {
+        let entry = entry_prices
+            .entry(snapshot.position_id)
+            .or_insert((snapshot.timestamp, snapshot.price));
+        if snapshot.timestamp < entry.0 {
+            *entry = (snapshot.timestamp, snapshot.price);
+        }
+    }
+
+    let normalized: Vec<NormalizedPosition> = positions
+        .iter()
+        .filter_map(|position| {
+            let pool = pools.get(&position.pool_id)?;
+            Some(normalize_position(
+                position,
+                pool,
+                prices.get(&pool.pool_id).copied(),
+                entry_prices.get(&position.id).map(|(_, price)| *price),
+                fees.get(&position.id).copied().unwrap_or_default(),
+                &rates,
+            ))
+        })
+        .collect();
+
+    Ok(NormalizedHoldings { positions: normalized, rates, pools: held })
+}
+
+/// Value, fees and IL of an owner's positions converted into one quote currency
+async fn build_portfolio_totals(
+    state: &AppState,
+    owner: &OwnerScope,
+    currency: &str,
+    anchors: &[String],
+    include_archived: bool,
+) -> anyhow::Result<PortfolioTotalsResponse> {
+    let NormalizedHoldings { positions, rates, pools } =
+        normalize_holdings(state, &owner.addresses, currency, anchors, include_archived).await?;
+
+    // Rates of the held pools' tokens; intermediate tokens show up in their routes
+    let mut used: HashSet<String> = HashSet::new();
+    for pool in &pools {
+        used.extend([pool.token0.to_lowercase(), pool.token1.to_lowercase()]);
+    }
+    let reported = rates.rates.iter().filter(|(token, _)| used.contains(*token));
+
+    Ok(PortfolioTotalsResponse {
+        owner: owner.label.clone(),
+        totals: sum_normalized(&positions, &rates),
+        positions,
+        rates: reported.map(|(token, rate)| (token.clone(), rate.clone())).collect(),
+    })
+}
+
+/// GET /portfolio/:owner/totals?currency=usd&include_archived=true
+/// Value, fees and impermanent loss of an owner's positions in one quote currency, priced
+/// from pool TWAPs at one instant; positions whose tokens have no price are left out of the
+/// totals and listed
+pub async fn get_portfolio_totals_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<TotalsParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let currencies = &state.quote_currencies;
+    let currency = params.currency.unwrap_or_else(|| currencies.default_currency.clone());
+    let Some(anchors) = currencies.anchors_for(&currency) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("currency must be one of: {}", currencies.names().join(", "))
+            })),
+        );
+    };
+
+    info!("Normalizing portfolio totals for owner {} into {}", owner, currency);
+
+    let totals =
+        build_portfolio_totals(&state, &owner, &currency, anchors, params.include_archived).await;
+    match totals {
+        Ok(totals) => (StatusCode::OK, Json(serde_json::to_value(totals).unwrap())),
+        Err(e) => {
+            error!("Failed to normalize portfolio totals: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VaultsParams {
+    /// Quote currency, e.g. `usd` or `eth` (defaults to `QUOTE_CURRENCY`)
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PortfolioVaultsResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    /// Vaults the owner holds shares of, with their part of each vault position
+    pub vaults: Vec<VaultExposure>,
+    /// Registered vaults whose share balances couldn't be read
+    pub unreadable: Vec<Address>,
+}
+
+/// An owner's stake in a vault, from its share token's live balances (None without shares)
+async fn read_vault_stake(
+    state: &AppState,
+    vault: &Vault,
+    owner: &OwnerScope,
+) -> anyhow::Result<Option<VaultStake>> {
+    let token = vault.share_token.into();
+    let mut shares = U256::ZERO;
+    for address in &owner.addresses {
+        shares += state.blockchain.get_token_balance(token, (*address).into()).await?;
+    }
+    if shares.is_zero() {
+        return Ok(None);
+    }
+    let supply = state.blockchain.get_token_supply(token).await?;
+    Ok(VaultStake::new(vault, shares, supply))
+}
+
+/// An owner's effective exposure through the vaults they hold shares of
+async fn build_vault_exposure(
+    state: &AppState,
+    owner: &OwnerScope,
+    currency: &str,
+    anchors: &[String],
+) -> anyhow::Result<PortfolioVaultsResponse> {
+    let mut vaults = Vec::new();
+    let mut unreadable = Vec::new();
+    for vault in get_vaults(&state.db_pool).await? {
+        let stake = match read_vault_stake(state, &vault, owner).await {
+            Ok(Some(stake)) => stake,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Failed to read shares of vault {}: {}", vault.address, e);
+                unreadable.push(vault.address);
+                continue;
+            }
+        };
+        let holdings = normalize_holdings(state, &[vault.address], currency, anchors, false).await?;
+        vaults.push(vault_exposure(stake, &holdings.positions, &holdings.rates));
+    }
+
+    Ok(PortfolioVaultsResponse { owner: owner.label.clone(), vaults, unreadable })
+}
+
+/// GET /portfolio/:owner/vaults?currency=usd
+/// The owner's part of the open positions of every registered vault they hold shares of, in
+/// one quote currency; shares are read live from each vault's share token
+pub async fn get_portfolio_vaults_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<VaultsParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let currencies = &state.quote_currencies;
+    let currency = params.currency.unwrap_or_else(|| currencies.default_currency.clone());
+    let Some(anchors) = currencies.anchors_for(&currency) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("currency must be one of: {}", currencies.names().join(", "))
+            })),
+        );
+    };
+
+    info!("Resolving vault exposure for owner {} in {}", owner, currency);
+
+    match build_vault_exposure(&state, &owner, &currency, anchors).await {
+        Ok(exposure) => (StatusCode::OK, Json(serde_json::to_value(exposure).unwrap())),
+        Err(e) => {
+            error!("Failed to resolve vault exposure: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PortfolioHealthResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    /// Open positions, ordered by pool
+    pub positions: Vec<PositionHealth>,
+}
+
+/// Health of many open positions at once: `owners`', or the alerting ones without owners
+///
+/// Positions and their latest pool ticks, entries, fees and snapshots come
+/// from one query (`get_position_health_rows`); volatility is read once per
+/// pool for the risk buckets. Errors reading a pool's volatility leave its
+/// positions on the default rules.
+pub(crate) async fn batch_health(
+    state: &AppState,
+    owners: Option<&[Address]>,
+) -> anyhow::Result<Vec<PositionHealth>> {
+    let now = Utc::now();
+    let rows = get_position_health_rows(&state.db_pool, owners, now - HEALTH_TTL_LOOKBACK).await?;
+
+    let pool_ids: BTreeSet<&str> = rows.iter().map(|r| r.position.pool_id.as_str()).collect();
+    let mut volatility = HashMap::new();
+    for pool_id in pool_ids {
+        match pool_volatility(&state.db_pool, pool_id).await {
+            Ok(Some(v)) => {
+                volatility.insert(pool_id.to_string(), v);
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to estimate volatility of pool {}: {}", pool_id, e),
+        }
+    }
+
+    Ok(evaluate_health_batch(&state.health_rules, &rows, &volatility, now))
+}
+
+/// GET /portfolio/:owner/health
+/// Health of all the owner's open positions, evaluated together from stored data: the
+/// pool's latest tick rather than its TWAP, and accumulated lifetime fees
+pub async fn get_portfolio_health_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    info!("Evaluating health of open positions of owner {}", owner);
+
+    match batch_health(&state, Some(&owner.addresses)).await {
+        Ok(positions) => {
+            let response = PortfolioHealthResponse { owner: owner.label, positions };
+            (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to evaluate portfolio health: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebalanceChainParams {
+    /// Max minutes between closing a position and opening its successor (default 60)
+    pub window_minutes: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RebalanceChainsResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub window_minutes: i64,
+    pub chains: Vec<RebalanceChain>,
+}
+
+/// Link an owner's positions into rebalance chains with strategy-level P&L
+async fn build_rebalance_chains(
+    db_pool: &PgPool,
+    owner: &OwnerScope,
+    window_minutes: i64,
+) -> anyhow::Result<RebalanceChainsResponse> {
+    let filter = PositionFilter { owners: Some(owner.addresses.clone()), ..Default::default() };
+    let positions = find_positions(db_pool, &filter).await?;
+
+    let mut snapshots: HashMap<i64, Vec<PositionSnapshot>> = HashMap::new();
+    for snapshot in get_snapshots_for_owners(db_pool, &owner.addresses).await? {
+        snapshots.entry(snapshot.position_id).or_default().push(snapshot);
+    }
+
+    let now = Utc::now();
+    let mut links = Vec::with_capacity(positions.len());
+    for position in &positions {
+        let mut series = snapshots.remove(&position.id).unwrap_or_default();
+        series.sort_by_key(|s| s.timestamp);
+        let gas = get_gas_expenses_for_position(db_pool, position.id, now).await?;
+        links.push((position, chain_link(position, &series, &gas, now)));
+    }
+
+    Ok(RebalanceChainsResponse {
+        owner: owner.label.clone(),
+        window_minutes,
+        chains: detect_rebalance_chains(&links, Duration::minutes(window_minutes)),
+    })
+}
+
+/// GET /portfolio/:owner/rebalance-chains?window_minutes=60
+/// Positions closed and re-opened in the same pool within the window, linked into chains
+/// whose P&L carries the first position's cost basis across rebalances
+pub async fn get_rebalance_chains_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<RebalanceChainParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let window_minutes = params.window_minutes.unwrap_or(DEFAULT_CHAIN_WINDOW_MINUTES);
+    if window_minutes <= 0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "window_minutes must be positive" })),
+        );
+    }
+
+    info!("Detecting rebalance chains for owner: {}", owner);
+
+    match build_rebalance_chains(&state.db_pool, &owner, window_minutes).await {
+        Ok(chains) => (StatusCode::OK, Json(serde_json::to_value(chains).unwrap())),
+        Err(e) => {
+            error!("Failed to detect rebalance chains: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// Longest window risk-adjusted returns are computed over
+const MAX_RETURNS_WINDOW_DAYS: i64 = 365;
+
+/// Default and longest activity timeline windows, in days
+const TIMELINE_DEFAULT_DAYS: i64 = 90;
+const MAX_TIMELINE_DAYS: i64 = 730;
+
+/// Default and largest number of timeline entries returned
+const DEFAULT_TIMELINE_LIMIT: usize = 100;
+const MAX_TIMELINE_LIMIT: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct RiskAdjustedParams {
+    /// Window like `30d` or `12w` (default `30d`)
+    pub window: Option<String>,
+    /// Annual risk-free rate as a fraction, e.g. `0.04` (default 0)
+    pub risk_free_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PositionRiskAdjusted {
+    pub position_id: i64,
+    pub nft_id: String,
+    pub pool_id: String,
+    pub metrics: RiskAdjustedReturns,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskAdjustedResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub window: String,
+    pub risk_free_rate: Decimal,
+    /// All positions combined, weighting each day's returns by capital
+    pub portfolio: RiskAdjustedReturns,
+    pub positions: Vec<PositionRiskAdjusted>,
+}
+
+/// Sharpe/Sortino ratios of an owner's positions from daily snapshot returns over a window
+async fn build_risk_adjusted(
+    db_pool: &PgPool,
+    owner: &OwnerScope,
+    window: Duration,
+    risk_free_rate: Decimal,
+) -> anyhow::Result<(RiskAdjustedReturns, Vec<PositionRiskAdjusted>)> {
+    let filter = PositionFilter { owners: Some(owner.addresses.clone()), ..Default::default() };
+    let positions = find_positions(db_pool, &filter).await?;
+
+    // The day before the window closes its first day's capital
+    let start = Utc::now() - window - Duration::days(1);
+    let mut snapshots: HashMap<i64, Vec<PositionSnapshot>> = HashMap::new();
+    for snapshot in get_snapshots_for_owners(db_pool, &owner.addresses).await? {
+        if snapshot.timestamp >= start {
+            snapshots.entry(snapshot.position_id).or_default().push(snapshot);
+        }
+    }
+
+    let mut all_returns = Vec::new();
+    let mut results = Vec::new();
+    for position in &positions {
+        let Some(mut series) = snapshots.remove(&position.id) else {
+            continue;
+        };
+        series.sort_by_key(|s| s.timestamp);
+        let returns = daily_returns(position, &series);
+        if returns.is_empty() {
+            continue;
+        }
+        results.push(PositionRiskAdjusted {
+            position_id: position.id,
+            nft_id: position.nft_id.clone(),
+            pool_id: position.pool_id.clone(),
+            metrics: risk_adjusted_returns(&returns, risk_free_rate),
+        });
+        all_returns.extend(returns);
+    }
+
+    let portfolio = risk_adjusted_returns(&combine_daily_returns(all_returns), risk_free_rate);
+    Ok((portfolio, results))
+}
+
+/// GET /portfolio/:owner/risk-adjusted?window=30d&risk_free_rate=0.04
+/// Sharpe and Sortino ratios per position and for the whole portfolio, from daily snapshot
+/// returns (fees plus value change) over the window
+pub async fn get_risk_adjusted_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<RiskAdjustedParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let window = match duration_param(
+        "window",
+        params.window.as_deref(),
+        Duration::days(30),
+        Duration::days(MAX_RETURNS_WINDOW_DAYS),
+    ) {
+        Ok(window) => window,
+        Err(response) => return response,
+    };
+    let window_param = format_duration(window);
+    let risk_free_rate = params.risk_free_rate.unwrap_or(Decimal::ZERO);
+
+    info!("Computing risk-adjusted returns for owner {} over {}", owner, window_param);
+
+    match build_risk_adjusted(&state.db_pool, &owner, window, risk_free_rate).await {
+        Ok((portfolio, positions)) => {
+            let response = RiskAdjustedResponse {
+                owner: owner.label,
+                window: window_param,
+                risk_free_rate,
+                portfolio,
+                positions,
+            };
+            (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to compute risk-adjusted returns: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StressParams {
+    /// Move of every pool's price (token0 in token1) in percent, e.g. `-15%`
+    #[serde(rename = "move")]
+    pub price_move: String,
+    /// Quote currency for the totals (defaults to `QUOTE_CURRENCY`)
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StressResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub totals: StressTotals,
+    /// Open positions, those leaving their range first
+    pub positions: Vec<PositionStress>,
+    /// Pools without swaps to take a price from; their positions are left out
+    pub unpriced_pools: Vec<String>,
+}
+
+/// Revalue an owner's open positions with every pool's price moved by `price_move`
+async fn build_stress(
+    state: &AppState,
+    owner: &OwnerScope,
+    price_move: Decimal,
+    currency: &str,
+    anchors: &[String],
+) -> anyhow::Result<StressResponse> {
+    let db_pool = &state.db_pool;
+    let filter = PositionFilter {
+        owners: Some(owner.addresses.clone()),
+        status: Some(PositionStatus::Open),
+        ..Default::default()
+    };
+    let positions = find_positions(db_pool, &filter).await?;
+
+    let mut pools: HashMap<String, Pool> = HashMap::new();
+    for position in &positions {
+        if pools.contains_key(&position.pool_id) {
+            continue;
+        }
+        if let Some(pool) = get_pool_by_id(db_pool, &position.pool_id).await? {
+            pools.insert(pool.pool_id.clone(), pool);
+        }
+    }
+    let held: Vec<Pool> = pools.values().cloned().collect();
+    let (rates, prices) = resolve_rates(state, currency, anchors, &held, Utc::now()).await?;
+
+    let mut stressed = Vec::new();
+    let mut unpriced_pools = BTreeSet::new();
+    for position in &positions {
+        let pool_id = &position.pool_id;
+        let (Some(pool), Some(price)) = (pools.get(pool_id), prices.get(pool_id)) else {
+            unpriced_pools.insert(pool_id.clone());
+            continue;
+        };
+        // Positions with unusable ranges never make it past quarantine
+        let Ok(mut stress) = stress_position(position, *price, price_move) else {
+            continue;
+        };
+        stress.price_in(pool, &rates);
+        stressed.push(stress);
+    }
+    stressed.sort_by_key(|s| (!s.exits_range, s.position_id));
+
+    Ok(StressResponse {
+        owner: owner.label.clone(),
+        totals: sum_stress(&stressed, currency, price_move),
+        positions: stressed,
+        unpriced_pools: unpriced_pools.into_iter().collect(),
+    })
+}
+
+/// GET /owners/:owner/stress?move=-15%&currency=usd
+/// Which open positions leave their range if every pool's price moves by `move`, and what the
+/// move does to their value, per position (raw token1) and in total (quote currency)
+pub async fn get_stress_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<StressParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let price_move = match parse_price_move(&params.price_move) {
+        Ok(price_move) => price_move,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e })));
+        }
+    };
+    let currencies = &state.quote_currencies;
+    let currency = params.currency.unwrap_or_else(|| currencies.default_currency.clone());
+    let Some(anchors) = currencies.anchors_for(&currency) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("currency must be one of: {}", currencies.names().join(", "))
+            })),
+        );
+    };
+
+    info!("Stress testing positions of {} for a {} price move", owner, params.price_move);
+
+    match build_stress(&state, &owner, price_move, &currency, anchors).await {
+        Ok(stress) => (StatusCode::OK, Json(serde_json::to_value(stress).unwrap())),
+        Err(e) => {
+            error!("Failed to stress test positions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineParams {
+    /// Start of the feed (defaults to 90 days before `to`)
+    pub from: Option<String>,
+    /// End of the feed (defaults to now)
+    pub to: Option<String>,
+    /// Daily return, as a fraction of capital, listed as a P&L swing either way (default 0.1)
+    pub swing: Option<Decimal>,
+    /// Most entries returned, newest first (default 100, at most 1000)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub events: Vec<TimelineEvent>,
+    /// Whether older entries in the window were cut by `limit`
+    pub truncated: bool,
+}
+
+/// GET /owners/:owner/timeline?from=A&to=B&swing=0.1&limit=100
+/// What happened to an owner's positions, newest first: mints, liquidity increases and
+/// decreases, transfers in and out, and days with big P&L swings
+pub async fn get_timeline_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<TimelineParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let limits = TimeRangeLimits::days(TIMELINE_DEFAULT_DAYS, MAX_TIMELINE_DAYS);
+    let range = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => range,
+        Err(response) => return response,
+    };
+    let swing = params.swing.unwrap_or(DEFAULT_PNL_SWING);
+    let limit = params.limit.unwrap_or(DEFAULT_TIMELINE_LIMIT);
+    if swing <= Decimal::ZERO || limit == 0 || limit > MAX_TIMELINE_LIMIT {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "swing must be positive and limit between 1 and {}",
+                    MAX_TIMELINE_LIMIT
+                )
+            })),
+        );
+    }
+
+    info!("Building activity timeline of {}", owner);
+
+    let filter = PositionFilter {
+        owners: Some(owner.addresses.clone()),
+        include_archived: true,
+        ..Default::default()
+    };
+    let history = tokio::try_join!(
+        get_liquidity_events_for_owners(&state.db_pool, &owner.addresses, range.from, range.to),
+        get_transfers_for_owners(&state.db_pool, &owner.addresses, range.from, range.to),
+        find_positions(&state.db_pool, &filter),
+        get_snapshots_for_owners(&state.db_pool, &owner.addresses),
+    );
+    let (events, transfers, positions, snapshots) = match history {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to fetch owner activity: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let mut events =
+        build_timeline(&owner.addresses, &events, &transfers, &positions, &snapshots, swing, range);
+    let truncated = events.len() > limit;
+    events.truncate(limit);
+
+    let response = TimelineResponse {
+        owner: owner.label,
+        from: range.from,
+        to: range.to,
+        events,
+        truncated,
+    };
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+}
+
+/// Default and longest P&L attribution windows, in days
+const ATTRIBUTION_DEFAULT_DAYS: i64 = 30;
+const MAX_ATTRIBUTION_DAYS: i64 = 365;
+
+#[derive(Debug, Deserialize)]
+pub struct AttributionParams {
+    /// Start of the window (defaults to 30 days before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
+    /// Quote currency the components are restated in (defaults to `QUOTE_CURRENCY`)
+    pub currency: Option<String>,
+}
+
+impl AttributionParams {
+    /// The window, and the currency with its anchor tokens, answering `400` when invalid
+    pub(crate) fn resolve<'a>(
+        &self,
+        state: &'a AppState,
+    ) -> Result<(TimeRange, String, &'a [String]), ErrorResponse> {
+        let limits = TimeRangeLimits::days(ATTRIBUTION_DEFAULT_DAYS, MAX_ATTRIBUTION_DAYS);
+        let range = time_range_param(self.from.as_deref(), self.to.as_deref(), limits)?;
+        let currencies = &state.quote_currencies;
+        let currency = self.currency.clone().unwrap_or_else(|| currencies.default_currency.clone());
+        let Some(anchors) = currencies.anchors_for(&currency) else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("currency must be one of: {}", currencies.names().join(", "))
+                })),
+            ));
+        };
+        Ok((range, currency, anchors))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PnlAttributionResponse {
+    /// The owner's address, or `account:<name>`
+    pub owner: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// All positions' components in the quote currency
+    pub totals: DecompositionTotals,
+    /// Per position, in raw token1 and the quote currency
+    pub positions: Vec<PnlDecomposition>,
+    /// Positions without two snapshots in the window to measure between
+    pub unmeasured_positions: Vec<String>,
+}
+
+/// Split positions' P&L change over `range` into components, priced at the end of the range
+///
+/// Returns the decompositions and the NFT ids of positions that couldn't be measured.
+pub(crate) async fn decompose_positions(
+    state: &AppState,
+    positions: &[Position],
+    range: TimeRange,
+    currency: &str,
+    anchors: &[String],
+) -> anyhow::Result<(Vec<PnlDecomposition>, Vec<String>)> {
+    let db_pool = &state.db_pool;
+    let mut pools: HashMap<String, Pool> = HashMap::new();
+    let mut decompositions = Vec::new();
+    let mut unmeasured = Vec::new();
+    for position in positions {
+        let (snapshots, gas) = tokio::try_join!(
+            get_snapshots_for_position(db_pool, position.id, position.created_at, range.to),
+            get_gas_expenses_for_position(db_pool, position.id, range.to),
+        )?;
+        match decompose_pnl(position, &snapshots, &gas, range) {
+            Some(decomposition) => decompositions.push(decomposition),
+            None => {
+                unmeasured.push(position.nft_id.clone());
+                continue;
+            }
+        }
+        if !pools.contains_key(&position.pool_id)
+            && let Some(pool) = get_pool_by_id(db_pool, &position.pool_id).await?
+        {
+            pools.insert(pool.pool_id.clone(), pool);
+        }
+    }
+
+    let held: Vec<Pool> = pools.values().cloned().collect();
+    let (rates, _) = resolve_rates(state, currency, anchors, &held, range.to).await?;
+    for decomposition in &mut decompositions {
+        if let Some(pool) = pools.get(&decomposition.pool_id) {
+            decomposition.price_in(pool, &rates);
+        }
+    }
+    Ok((decompositions, unmeasured))
+}
+
+/// GET /portfolio/:owner/pnl-attribution?from=A&to=B&currency=usd
+/// Why an owner's P&L moved between two times: fee income, price appreciation of the tokens
+/// held, IL and gas, per position (raw token1) and in total (quote currency)
+pub async fn get_pnl_attribution_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    Query(params): Query<AttributionParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let (range, currency, anchors) = match params.resolve(&state) {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+
+    info!("Attributing P&L of {} from {} to {}", owner, range.from, range.to);
+
+    let filter = PositionFilter {
+        owners: Some(owner.addresses.clone()),
+        include_archived: true,
+        ..Default::default()
+    };
+    let attributed = async {
+        let positions = find_positions(&state.db_pool, &filter).await?;
+        decompose_positions(&state, &positions, range, &currency, anchors).await
+    };
+    match attributed.await {
+        Ok((positions, unmeasured_positions)) => {
+            let response = PnlAttributionResponse {
+                owner: owner.label,
+                from: range.from,
+                to: range.to,
+                totals: sum_decompositions(&positions, &currency),
+                positions,
+                unmeasured_positions,
+            };
+            (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to attribute P&L: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
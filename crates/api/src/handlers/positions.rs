@@ -1,43 +1,114 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
 use rust_decimal::Decimal;
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 use stillwater_analytics::{
-    calculate_position_pnl, get_health_details, get_position_health, is_in_range,
+    annualized_return, attribute_pnl, calculate_position_pnl, calculate_position_pnl_at,
+    calculate_position_pnl_with_model, classify_risk, estimate_ttl_to_edge, fee_velocity_trend,
+    fees_to_break_even, is_in_range, position_greeks, price_to_tick, recommend_compound,
+    tick_to_price, unclaimed_fees, value_per_liquidity, weighted_entry, EntryMetrics,
+    FeeVelocityTrend, HealthInputs, HookAnnotation, OwnerPnl, PnlHistory, PositionGreeks,
+    PriceDisplay, RangeError, RetentionWarning, RiskCategory, TickRange, TimeRangeLimits, Twap,
 };
-use stillwater_db::{get_position_by_nft, get_positions_by_owner, get_swaps_for_pool};
-use stillwater_models::PositionPnL;
-use tracing::{error, info};
+use stillwater_db::{
+    find_positions, get_fee_accumulator, get_gas_expenses_for_position, get_pool_by_id,
+    get_position_by_nft, get_position_range_events, get_snapshots_for_position, get_swaps_for_pool,
+    get_swaps_for_pool_between, get_transfers_for_position, restore_position,
+    soft_delete_position, stream_positions, stream_snapshots_for_position, PositionFilter,
+    PositionSort, PositionStatus,
+};
+use stillwater_models::{
+    Address, FeeAccumulator, Pool, Position, PositionLifecycle, PositionPnL, PositionSnapshot,
+    PositionTransfer, RetainedData,
+};
+use tracing::{error, info, warn};
 
+use crate::address::{OwnerScope, owner_scope_param};
+use crate::handlers::auth::authorized_addresses;
+use crate::handlers::pools::{pool_twaps, pool_volatility};
+use crate::handlers::portfolio::{AttributionParams, build_portfolio, decompose_positions};
+use crate::handlers::preferences::resolve_price_display;
+use crate::ndjson::{ndjson_response, wants_ndjson};
+use crate::retention::{retention_warning, with_retention_warnings};
 use crate::state::AppState;
+use crate::timerange::{time_param, time_range_param};
 
 #[derive(Debug, Serialize)]
 pub struct PositionResponse {
     pub nft_id: String,
-    pub owner: String,
+    pub owner: Address,
     pub pool_id: String,
     pub tick_lower: i32,
     pub tick_upper: i32,
     pub liquidity: String,
     pub created_at: String,
+    pub manual: bool,
+    pub lifecycle: PositionLifecycle,
+    pub closed_at: Option<String>,
+    pub archived_at: Option<String>,
+}
+
+impl From<Position> for PositionResponse {
+    fn from(p: Position) -> Self {
+        Self {
+            lifecycle: p.lifecycle(),
+            closed_at: p.closed_at.map(|t| t.to_rfc3339()),
+            archived_at: p.archived_at.map(|t| t.to_rfc3339()),
+            nft_id: p.nft_id,
+            owner: p.owner,
+            pool_id: p.pool_id,
+            tick_lower: p.tick_lower,
+            tick_upper: p.tick_upper,
+            liquidity: p.liquidity.to_string(),
+            created_at: p.created_at.to_rfc3339(),
+            manual: p.manual,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 pub struct PositionWithPnlResponse {
     pub nft_id: String,
-    pub owner: String,
+    pub owner: Address,
     pub pool_id: String,
     pub tick_lower: i32,
     pub tick_upper: i32,
     pub liquidity: String,
     pub created_at: String,
+    pub manual: bool,
+    pub lifecycle: PositionLifecycle,
     pub pnl: PositionPnL,
+    /// Further fees needed to cover IL and gas at the current price
+    pub break_even_fees: Decimal,
+    /// Liquidity-weighted entry over the deposits on the position's range, price in the
+    /// display orientation (None until a deposit is priced)
+    pub entry: Option<EntryMetrics>,
     pub in_range: bool,
     pub current_tick: i32,
+    /// Pool TWAP the current price/tick came from, when not given explicitly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twap: Option<Twap>,
+    /// Range bounds as prices, in the display orientation when one is set
+    pub price_lower: Decimal,
+    pub price_upper: Decimal,
+    pub price_display: Option<PriceDisplay>,
+    /// Set when `as_of` P&L needs history retention has deleted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub retention_warnings: Vec<RetentionWarning>,
+    /// How the pool's hook affects these figures, for pools with hooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook: Option<HookAnnotation>,
+    /// Swap count, volume and fees since creation, kept up to date by the sync
+    /// (None until its first run after the position appeared)
+    pub lifetime_fees: Option<FeeAccumulator>,
+    /// Recent fee accrual rate against the baseline before it (None without snapshots)
+    pub fee_velocity: Option<FeeVelocityTrend>,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,80 +116,326 @@ pub struct PositionHealthResponse {
     pub nft_id: String,
     pub status: String,
     pub details: String,
+    /// The configured rule that decided the status (None when no rule matched)
+    pub rule: Option<String>,
+    /// Risk bucket whose rules were applied (None when the pair has too little swap history)
+    pub risk: Option<RiskCategory>,
+    /// Delta/gamma at the current price, in raw pool orientation
+    pub greeks: PositionGreeks,
+    /// Pool TWAP the current price/tick came from, when not given explicitly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twap: Option<Twap>,
+    /// How the pool's hook affects these figures, for pools with hooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook: Option<HookAnnotation>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PnlQueryParams {
-    #[serde(default = "default_initial_price")]
-    pub initial_price: String,
-    #[serde(default = "default_current_price")]
-    pub current_price: String,
-    #[serde(default = "default_current_tick")]
-    pub current_tick: i32,
+    /// Defaults to the liquidity-weighted entry of the position's deposits (see `entry_price`)
+    pub initial_price: Option<String>,
+    /// Defaults to the pool's TWAP (see `current_market`)
+    pub current_price: Option<String>,
+    /// Defaults to the pool's TWAP tick
+    pub current_tick: Option<i32>,
     #[serde(default = "default_gas_spent")]
     pub gas_spent: String,
+    /// Reconstruct P&L as of this time from recorded data (ignores price/gas params),
+    /// e.g. `7d`, unix seconds or RFC3339
+    pub as_of: Option<String>,
+    /// Quote token for prices (address, `token0` or `token1`); defaults to the owner's preference
+    pub quote: Option<String>,
 }
 
-fn default_initial_price() -> String {
-    "1.0".to_string()
+#[derive(Debug, Deserialize)]
+pub struct CompoundQueryParams {
+    /// Gas cost of one transaction, in raw token1 units (defaults to `COMPOUND_GAS_COST`)
+    pub gas_cost: Option<Decimal>,
+    /// Required ratio of unclaimed fees to compound gas (defaults to `COMPOUND_GAS_MULTIPLE`)
+    pub multiple: Option<Decimal>,
 }
 
-fn default_current_price() -> String {
-    "1.0".to_string()
+#[derive(Debug, Deserialize)]
+pub struct OwnersQueryParams {
+    /// Attribute P&L up to this time, e.g. `30d`, unix seconds or RFC3339 (defaults to now)
+    pub as_of: Option<String>,
 }
 
-fn default_current_tick() -> i32 {
-    0
+#[derive(Debug, Deserialize)]
+pub struct HistoryQueryParams {
+    /// Start of the window (defaults to 30 days before `to`)
+    pub from: Option<String>,
+    /// End of the window (defaults to now)
+    pub to: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PositionOwnersResponse {
+    pub nft_id: String,
+    pub current_owner: Address,
+    pub as_of: DateTime<Utc>,
+    /// Each owner's holding period and the P&L earned in it, oldest first
+    pub owners: Vec<OwnerPnl>,
+    pub transfers: Vec<PositionTransfer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PositionListParams {
+    pub pool_id: Option<String>,
+    /// `open`, `closed` or `archived`
+    pub status: Option<String>,
+    /// Also list archived positions (left out by default)
+    #[serde(default)]
+    pub include_archived: bool,
+    /// `created_at_desc` (default), `created_at_asc`, `liquidity_desc`, `liquidity_asc`
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Maximum page size for position listings
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// Default and longest P&L history windows in JSON, in days (streamed ones may span any length)
+const HISTORY_DEFAULT_DAYS: i64 = 30;
+const MAX_HISTORY_DAYS: i64 = 365;
+
 fn default_gas_spent() -> String {
     "0".to_string()
 }
 
-/// GET /positions/:owner
-/// Get all positions for an address
+/// Reconstruct a position's P&L at `as_of` from stored swaps, snapshots and gas
+async fn historical_pnl(
+    state: &AppState,
+    position: &Position,
+    pool: &Pool,
+    as_of: DateTime<Utc>,
+) -> anyhow::Result<Option<PositionPnL>> {
+    let swaps =
+        get_swaps_for_pool_between(&state.db_pool, &position.pool_id, position.created_at, as_of)
+            .await?;
+    let snapshots =
+        get_snapshots_for_position(&state.db_pool, position.id, position.created_at, as_of).await?;
+    let gas = get_gas_expenses_for_position(&state.db_pool, position.id, as_of).await?;
+
+    let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &gas };
+    Ok(calculate_position_pnl_at(
+        position,
+        pool,
+        state.fee_models.model_for(pool),
+        &history,
+        as_of,
+    )?)
+}
+
+/// Current price (raw) and tick a position is evaluated at
+///
+/// Explicit `current_price`/`current_tick` params win. Otherwise both come from
+/// the pool's TWAP over `TWAP_WINDOW_MINUTES`, so a single large swap doesn't
+/// flip a position's health; a pool without swaps falls back to price 1 and
+/// tick 0. The TWAP used, if any, is returned alongside.
+async fn current_market(
+    state: &AppState,
+    position: &Position,
+    params: &PnlQueryParams,
+    display: Option<&PriceDisplay>,
+) -> Result<(Decimal, i32, Option<Twap>), (StatusCode, Json<serde_json::Value>)> {
+    let twap = if params.current_price.is_none() || params.current_tick.is_none() {
+        let windows = [state.twap.window()];
+        match pool_twaps(&state.db_pool, &position.pool_id, &windows, Utc::now()).await {
+            Ok(mut twaps) => twaps.pop().flatten(),
+            Err(e) => {
+                error!("Failed to compute TWAP: {}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Failed to fetch swaps" })),
+                ));
+            }
+        }
+    } else {
+        None
+    };
+
+    let current_price = match &params.current_price {
+        Some(price) => parse_price(price, "current_price", display)?,
+        None => twap.as_ref().map_or(Decimal::ONE, |t| t.price),
+    };
+    let current_tick = params.current_tick.or(twap.as_ref().map(|t| t.tick)).unwrap_or(0);
+    Ok((current_price, current_tick, twap))
+}
+
+/// Entry price (raw) IL and capital are measured from, and the position's weighted entry
+///
+/// An explicit `initial_price` wins. Otherwise the liquidity-weighted entry of
+/// the deposits on the position's range is used, so positions built up over
+/// several increases aren't measured from their first deposit alone; without
+/// a priced deposit the entry price falls back to 1.0.
+async fn entry_price(
+    state: &AppState,
+    position: &Position,
+    params: &PnlQueryParams,
+    display: Option<&PriceDisplay>,
+) -> Result<(Decimal, Option<EntryMetrics>), (StatusCode, Json<serde_json::Value>)> {
+    let entry = match get_position_range_events(&state.db_pool, position).await {
+        Ok(events) => weighted_entry(&events),
+        Err(e) => {
+            warn!("Failed to load deposits for weighted entry: {}", e);
+            None
+        }
+    };
+    let price = match &params.initial_price {
+        Some(price) => parse_price(price, "initial_price", display)?,
+        None => entry.map_or(Decimal::ONE, |e| e.price),
+    };
+    Ok((price, entry))
+}
+
+/// Parse a price query param given in the display orientation into a raw pool price
+fn parse_price(
+    value: &str,
+    name: &str,
+    display: Option<&PriceDisplay>,
+) -> Result<Decimal, (StatusCode, Json<serde_json::Value>)> {
+    match value.parse::<Decimal>() {
+        Ok(price) => Ok(display.map_or(price, |d| d.to_raw(price))),
+        Err(_) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid {} parameter", name) })),
+        )),
+    }
+}
+
+/// Fetch the position's pool and resolve its price display for `owner`
+async fn load_pool_display(
+    state: &AppState,
+    owner: &Address,
+    position: &Position,
+    quote: Option<&str>,
+) -> Result<(Option<Pool>, Option<PriceDisplay>), (StatusCode, Json<serde_json::Value>)> {
+    let pool = match get_pool_by_id(&state.db_pool, &position.pool_id).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch pool" })),
+            ));
+        }
+    };
+
+    let display = match &pool {
+        Some(pool) => resolve_price_display(state, owner, pool, quote).await?,
+        None if quote.is_some() => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Pool not found" })),
+            ));
+        }
+        None => None,
+    };
+
+    Ok((pool, display))
+}
+
+/// 422 response for positions whose tick range can't be analyzed
+pub(crate) fn invalid_range_response(e: RangeError) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(serde_json::json!({ "error": format!("Invalid position range: {}", e) })),
+    )
+}
+
+/// GET /positions/:owner?pool_id=X&status=open&include_archived=true&sort=created_at_desc&limit=N&offset=M
+/// Get all positions for an address, streamed unpaged as NDJSON when `Accept` asks for it
 pub async fn get_positions_handler(
     State(state): State<AppState>,
     Path(owner): Path<String>,
-) -> impl IntoResponse {
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<PositionListParams>,
+) -> Response {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response.into_response(),
+    };
     info!("Fetching positions for owner: {}", owner);
 
-    match get_positions_by_owner(&state.db_pool, &owner).await {
-        Ok(positions) => {
-            let response: Vec<PositionResponse> = positions
-                .into_iter()
-                .map(|p| PositionResponse {
-                    nft_id: p.nft_id,
-                    owner: p.owner,
-                    pool_id: p.pool_id,
-                    tick_lower: p.tick_lower,
-                    tick_upper: p.tick_upper,
-                    liquidity: p.liquidity.to_string(),
-                    created_at: p.created_at.to_rfc3339(),
-                })
-                .collect();
+    let status = match params.status.as_deref().map(PositionStatus::parse) {
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid status parameter" })),
+            )
+                .into_response();
+        }
+        Some(status) => status,
+        None => None,
+    };
+
+    let sort = match params.sort.as_deref().map(PositionSort::parse) {
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Invalid sort parameter" })),
+            )
+                .into_response();
+        }
+        Some(Some(sort)) => sort,
+        None => PositionSort::default(),
+    };
+
+    let mut filter = PositionFilter {
+        owners: Some(owner.addresses),
+        pool_id: params.pool_id,
+        status,
+        include_archived: params.include_archived,
+        sort,
+        limit: Some(params.limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)),
+        offset: params.offset.map(|o| o.max(0)),
+        ..Default::default()
+    };
+
+    // Streamed listings aren't paged unless asked to be
+    if wants_ndjson(&headers) {
+        filter.limit = params.limit.map(|l| l.max(1));
+        let rows = stream_positions(&state.db_pool, filter);
+        return ndjson_response(rows, PositionResponse::from);
+    }
 
-            (StatusCode::OK, Json(response))
+    match find_positions(&state.db_pool, &filter).await {
+        Ok(positions) => {
+            let response: Vec<PositionResponse> =
+                positions.into_iter().map(PositionResponse::from).collect();
+            (StatusCode::OK, Json(serde_json::to_value(response).unwrap())).into_response()
         }
         Err(e) => {
             error!("Failed to fetch positions: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(vec![]),
+                Json(serde_json::json!({ "error": "Internal server error" })),
             )
+                .into_response()
         }
     }
 }
 
-/// GET /positions/:owner/:nft_id?initial_price=X&current_price=Y&current_tick=Z&gas_spent=W
+/// GET /positions/:owner/:nft_id?initial_price=X&current_price=Y&current_tick=Z&gas_spent=W&as_of=T
 /// Get specific position with P&L
 pub async fn get_position_with_pnl_handler(
     State(state): State<AppState>,
     Path((owner, nft_id)): Path<(String, String)>,
     axum::extract::Query(params): axum::extract::Query<PnlQueryParams>,
 ) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
     info!("Fetching position {} for owner {} with P&L", nft_id, owner);
 
+    let as_of = match time_param("as_of", params.as_of.as_deref()) {
+        Ok(as_of) => as_of,
+        Err(response) => return response,
+    };
+
     // Get position from database
     let position = match get_position_by_nft(&state.db_pool, &nft_id).await {
         Ok(Some(p)) => p,
@@ -138,68 +455,144 @@ pub async fn get_position_with_pnl_handler(
     };
 
     // Verify owner matches
-    if position.owner.to_lowercase() != owner.to_lowercase() {
+    if !owner.contains(&position.owner) {
         return (
             StatusCode::FORBIDDEN,
             Json(serde_json::json!({ "error": "Position does not belong to this owner" })),
         );
     }
 
-    // Get swaps for the pool from the past 24 hours
-    let since = Utc::now() - chrono::Duration::hours(24);
-    let swaps = match get_swaps_for_pool(&state.db_pool, &position.pool_id, since).await {
-        Ok(s) => s,
-        Err(e) => {
-            error!("Failed to fetch swaps: {}", e);
+    // Inverted or zero-width ranges would produce nonsense figures
+    if let Err(e) = TickRange::of(&position) {
+        return invalid_range_response(e);
+    }
+
+    let (pool, display) =
+        match load_pool_display(&state, &position.owner, &position, params.quote.as_deref()).await {
+            Ok(loaded) => loaded,
+            Err(response) => return response,
+        };
+    let (current_price, current_tick, twap) =
+        match current_market(&state, &position, &params, display.as_ref()).await {
+            Ok(market) => market,
+            Err(response) => return response,
+        };
+
+    let mut retention_warnings = Vec::new();
+    let mut entry = None;
+    let pnl = if let Some(as_of) = as_of {
+        // Reconstruct P&L from recorded history only
+        let Some(pool) = &pool else {
             return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "Failed to fetch swaps" })),
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Pool not found" })),
             );
+        };
+        for data in [RetainedData::Swaps, RetainedData::PositionSnapshots] {
+            retention_warnings
+                .extend(retention_warning(&state.db_pool, data, position.created_at).await);
         }
-    };
+        match historical_pnl(&state, &position, pool, as_of).await {
+            Ok(Some(pnl)) => pnl,
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({
+                        "error": "No price data recorded for position by as_of"
+                    })),
+                );
+            }
+            Err(e) => {
+                error!("Failed to reconstruct historical P&L: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Internal server error" })),
+                );
+            }
+        }
+    } else {
+        // Get swaps for the pool from the past 24 hours
+        let since = Utc::now() - chrono::Duration::hours(24);
+        let swaps = match get_swaps_for_pool(&state.db_pool, &position.pool_id, since).await {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to fetch swaps: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Failed to fetch swaps" })),
+                );
+            }
+        };
 
-    // Parse price parameters
-    let initial_price = match params.initial_price.parse::<Decimal>() {
-        Ok(p) => p,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": "Invalid initial_price parameter" })),
-            );
+        // Entry price given in the display orientation, or averaged over the deposits
+        let initial_price = match entry_price(&state, &position, &params, display.as_ref()).await {
+            Ok((price, metrics)) => {
+                entry = metrics;
+                price
+            }
+            Err(response) => return response,
+        };
+
+        let gas_spent = match params.gas_spent.parse::<Decimal>() {
+            Ok(g) => g,
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({ "error": "Invalid gas_spent parameter" })),
+                );
+            }
+        };
+
+        // Calculate P&L with the pool's fee model (falls back to the default tier if unknown)
+        let result = match &pool {
+            Some(pool) => calculate_position_pnl_with_model(
+                &position,
+                pool,
+                &swaps,
+                state.fee_models.model_for(pool),
+                initial_price,
+                current_price,
+                gas_spent,
+            ),
+            None => {
+                calculate_position_pnl(&position, &swaps, initial_price, current_price, gas_spent)
+            }
+        };
+        match result {
+            Ok(pnl) => pnl,
+            Err(e) => return invalid_range_response(e),
         }
     };
 
-    let current_price = match params.current_price.parse::<Decimal>() {
-        Ok(p) => p,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": "Invalid current_price parameter" })),
-            );
-        }
+    let in_range = is_in_range(current_tick, position.tick_lower, position.tick_upper);
+    let (price_lower, price_upper) = match &display {
+        Some(display) => display.range(position.tick_lower, position.tick_upper),
+        None => (tick_to_price(position.tick_lower), tick_to_price(position.tick_upper)),
     };
 
-    let gas_spent = match params.gas_spent.parse::<Decimal>() {
-        Ok(g) => g,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": "Invalid gas_spent parameter" })),
-            );
+    let lifetime_fees = match get_fee_accumulator(&state.db_pool, position.id).await {
+        Ok(accumulator) => accumulator,
+        Err(e) => {
+            warn!("Failed to load fee accumulator: {}", e);
+            None
         }
     };
 
-    // Calculate P&L
-    let pnl = calculate_position_pnl(
-        &position,
-        &swaps,
-        initial_price,
-        current_price,
-        gas_spent,
-    );
-
-    let in_range = is_in_range(params.current_tick, position.tick_lower, position.tick_upper);
+    let now = Utc::now();
+    let config = &state.fee_velocity;
+    let since = now
+        - chrono::Duration::hours(config.recent_hours)
+        - chrono::Duration::days(config.baseline_days);
+    let fee_velocity = match get_snapshots_for_position(&state.db_pool, position.id, since, now).await
+    {
+        Ok(snapshots) => fee_velocity_trend(&snapshots, now, config),
+        Err(e) => {
+            warn!("Failed to load snapshots for fee velocity: {}", e);
+            None
+        }
+    };
 
+    let lifecycle = position.lifecycle();
     let response = PositionWithPnlResponse {
         nft_id: position.nft_id,
         owner: position.owner,
@@ -208,9 +601,24 @@ pub async fn get_position_with_pnl_handler(
         tick_upper: position.tick_upper,
         liquidity: position.liquidity.to_string(),
         created_at: position.created_at.to_rfc3339(),
+        manual: position.manual,
+        lifecycle,
+        break_even_fees: fees_to_break_even(&pnl),
+        entry: entry.map(|e| EntryMetrics {
+            price: display.as_ref().map_or(e.price, |d| d.to_display(e.price)),
+            ..e
+        }),
         pnl,
         in_range,
-        current_tick: params.current_tick,
+        current_tick,
+        twap,
+        price_lower,
+        price_upper,
+        price_display: display,
+        retention_warnings,
+        hook: pool.as_ref().and_then(|pool| state.hooks.annotate(pool, &state.fee_models)),
+        lifetime_fees,
+        fee_velocity,
     };
 
     (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
@@ -223,6 +631,10 @@ pub async fn get_position_health_handler(
     Path((owner, nft_id)): Path<(String, String)>,
     axum::extract::Query(params): axum::extract::Query<PnlQueryParams>,
 ) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
     info!("Fetching health for position {} owner {}", nft_id, owner);
 
     // Get position from database
@@ -244,13 +656,29 @@ pub async fn get_position_health_handler(
     };
 
     // Verify owner matches
-    if position.owner.to_lowercase() != owner.to_lowercase() {
+    if !owner.contains(&position.owner) {
         return (
             StatusCode::FORBIDDEN,
             Json(serde_json::json!({ "error": "Position does not belong to this owner" })),
         );
     }
 
+    // Inverted or zero-width ranges would produce nonsense figures
+    if let Err(e) = TickRange::of(&position) {
+        return invalid_range_response(e);
+    }
+
+    let (pool, display) =
+        match load_pool_display(&state, &position.owner, &position, params.quote.as_deref()).await {
+            Ok(loaded) => loaded,
+            Err(response) => return response,
+        };
+    let (current_price, current_tick, twap) =
+        match current_market(&state, &position, &params, display.as_ref()).await {
+            Ok(market) => market,
+            Err(response) => return response,
+        };
+
     // Get swaps for the pool from the past 24 hours
     let since = Utc::now() - chrono::Duration::hours(24);
     let swaps = match get_swaps_for_pool(&state.db_pool, &position.pool_id, since).await {
@@ -264,55 +692,523 @@ pub async fn get_position_health_handler(
         }
     };
 
-    // Parse price parameters
-    let initial_price = match params.initial_price.parse::<Decimal>() {
-        Ok(p) => p,
+    // Entry price given in the display orientation, or averaged over the deposits
+    let initial_price = match entry_price(&state, &position, &params, display.as_ref()).await {
+        Ok((price, _)) => price,
+        Err(response) => return response,
+    };
+
+    let gas_spent = match params.gas_spent.parse::<Decimal>() {
+        Ok(g) => g,
         Err(_) => {
             return (
                 StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": "Invalid initial_price parameter" })),
+                Json(serde_json::json!({ "error": "Invalid gas_spent parameter" })),
             );
         }
     };
 
-    let current_price = match params.current_price.parse::<Decimal>() {
-        Ok(p) => p,
-        Err(_) => {
+    // Calculate P&L with the pool's fee model (falls back to the default tier if unknown)
+    let result = match &pool {
+        Some(pool) => calculate_position_pnl_with_model(
+            &position,
+            pool,
+            &swaps,
+            state.fee_models.model_for(pool),
+            initial_price,
+            current_price,
+            gas_spent,
+        ),
+        None => calculate_position_pnl(&position, &swaps, initial_price, current_price, gas_spent),
+    };
+
+    let mut inputs = match result
+        .and_then(|pnl| HealthInputs::new(&position, current_tick, &pnl).map(|i| (i, pnl)))
+    {
+        Ok((inputs, pnl)) => {
+            // Annualize P&L and gas over the capital deployed at entry
+            let now = Utc::now();
+            let capital = Decimal::from_str(&position.liquidity.to_string())
+                .ok()
+                .and_then(|l| {
+                    l.checked_mul(value_per_liquidity(
+                        initial_price,
+                        tick_to_price(position.tick_lower),
+                        tick_to_price(position.tick_upper),
+                    ))
+                })
+                .unwrap_or(Decimal::ZERO);
+            let age = now - position.created_at;
+            HealthInputs {
+                capital: Some(capital),
+                apr: annualized_return(pnl.net_pnl, capital, age),
+                gas_runrate: annualized_return(pnl.gas_spent, capital, age),
+                age: Some(age),
+                ..inputs
+            }
+        }
+        Err(e) => return invalid_range_response(e),
+    };
+
+    // Extrapolate recent price movement to estimate time until the range edge
+    let now = Utc::now();
+    match get_snapshots_for_position(&state.db_pool, position.id, since, now).await {
+        Ok(snapshots) => {
+            if let Some(earliest) = snapshots.first() {
+                inputs.ttl_to_edge = estimate_ttl_to_edge(
+                    inputs.range,
+                    current_tick,
+                    price_to_tick(earliest.price),
+                    now - earliest.timestamp,
+                );
+            }
+        }
+        Err(e) => {
+            error!("Failed to fetch snapshots: {}", e);
             return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": "Invalid current_price parameter" })),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch snapshots" })),
             );
         }
-    };
+    }
 
-    let gas_spent = match params.gas_spent.parse::<Decimal>() {
-        Ok(g) => g,
-        Err(_) => {
+    // Tighter ranges relative to the pair's volatility get earlier warnings
+    let risk = match pool_volatility(&state.db_pool, &position.pool_id).await {
+        Ok(volatility) => volatility.map(|v| classify_risk(inputs.range, v)),
+        Err(e) => {
+            error!("Failed to estimate pool volatility: {}", e);
             return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": "Invalid gas_spent parameter" })),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to fetch swaps" })),
             );
         }
     };
 
-    // Calculate P&L
-    let pnl = calculate_position_pnl(
-        &position,
-        &swaps,
-        initial_price,
-        current_price,
-        gas_spent,
-    );
-
-    // Get health status
-    let status = get_position_health(&position, params.current_tick, &pnl);
-    let details = get_health_details(&position, params.current_tick, &pnl);
+    let (status, rule) = state.health_rules.for_category(risk).evaluate(&inputs);
+    let greeks = Decimal::from_str(&position.liquidity.to_string())
+        .map(|liquidity| position_greeks(liquidity, inputs.range, current_price))
+        .unwrap_or_default();
 
     let response = PositionHealthResponse {
         nft_id: position.nft_id,
         status: format!("{:?}", status),
-        details,
+        details: inputs.summary(status),
+        rule: rule.map(|r| r.source.clone()),
+        risk,
+        greeks,
+        twap,
+        hook: pool.as_ref().and_then(|pool| state.hooks.annotate(pool, &state.fee_models)),
+    };
+
+    (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+}
+
+/// GET /positions/:owner/:nft_id/compound?gas_cost=X&multiple=3
+/// Whether the position's unclaimed fees cover the gas of collecting and re-depositing them
+pub async fn get_compound_recommendation_handler(
+    State(state): State<AppState>,
+    Path((owner, nft_id)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<CompoundQueryParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    info!("Checking compound opportunity for position {} owner {}", nft_id, owner);
+
+    let Some(gas_cost) = params.gas_cost.or(state.compound.gas_cost_per_tx) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "gas_cost is required (COMPOUND_GAS_COST is not set)"
+            })),
+        );
+    };
+    let multiple = params.multiple.unwrap_or(state.compound.gas_multiple);
+    if gas_cost < Decimal::ZERO || multiple <= Decimal::ZERO {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "gas_cost must be non-negative and multiple positive"
+            })),
+        );
+    }
+
+    let position = match get_position_by_nft(&state.db_pool, &nft_id).await {
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Position not found" })),
+            )
+        }
+        Err(e) => {
+            error!("Failed to fetch position: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    if !owner.contains(&position.owner) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Position does not belong to this owner" })),
+        );
+    }
+
+    let now = Utc::now();
+    let history = tokio::try_join!(
+        get_snapshots_for_position(&state.db_pool, position.id, position.created_at, now),
+        get_gas_expenses_for_position(&state.db_pool, position.id, now),
+    );
+    let (snapshots, gas) = match history {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to fetch position history: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let recommendation = recommend_compound(unclaimed_fees(&snapshots, &gas), gas_cost, multiple);
+    (StatusCode::OK, Json(serde_json::to_value(recommendation).unwrap()))
+}
+
+/// GET /positions/:owner/:nft_id/pnl-attribution?from=A&to=B&currency=usd
+/// Why the position's P&L moved between two times: fee income, price appreciation of the tokens
+/// held, IL and gas, in raw token1 and the quote currency
+pub async fn get_position_pnl_attribution_handler(
+    State(state): State<AppState>,
+    Path((owner, nft_id)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<AttributionParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let (range, currency, anchors) = match params.resolve(&state) {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+
+    let position = match get_position_by_nft(&state.db_pool, &nft_id).await {
+        Ok(Some(p)) if owner.contains(&p.owner) => p,
+        Ok(Some(_)) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Position does not belong to this owner" })),
+            );
+        }
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Position not found" })),
+            );
+        }
+        Err(e) => {
+            error!("Failed to fetch position: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    info!("Attributing P&L of position {} from {} to {}", nft_id, range.from, range.to);
+
+    match decompose_positions(&state, &[position], range, &currency, anchors).await {
+        Ok((mut decompositions, _)) => match decompositions.pop() {
+            Some(decomposition) => {
+                (StatusCode::OK, Json(serde_json::to_value(decomposition).unwrap()))
+            }
+            None => (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "No two snapshots of the position in the window"
+                })),
+            ),
+        },
+        Err(e) => {
+            error!("Failed to attribute P&L: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// GET /positions/:owner/:nft_id/history?from=A&to=B
+/// The position's recorded P&L history (snapshots of fees earned, liquidity and price), oldest
+/// first; streamed as NDJSON over any window when `Accept` asks for it
+pub async fn get_position_history_handler(
+    State(state): State<AppState>,
+    Path((owner, nft_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<HistoryQueryParams>,
+) -> Response {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response.into_response(),
+    };
+    let streaming = wants_ndjson(&headers);
+    let mut limits = TimeRangeLimits::days(HISTORY_DEFAULT_DAYS, MAX_HISTORY_DAYS);
+    if streaming {
+        limits.max_span = chrono::Duration::MAX;
+    }
+    let (from, to) = match time_range_param(params.from.as_deref(), params.to.as_deref(), limits) {
+        Ok(range) => (range.from, range.to),
+        Err(response) => return response.into_response(),
+    };
+
+    let position = match get_position_by_nft(&state.db_pool, &nft_id).await {
+        Ok(Some(p)) if owner.contains(&p.owner) => p,
+        Ok(Some(_)) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Position does not belong to this owner" })),
+            )
+                .into_response();
+        }
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Position not found" })),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("Failed to fetch position: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+                .into_response();
+        }
     };
 
+    info!("Fetching P&L history of position {} for owner {}", nft_id, owner);
+
+    let rows = stream_snapshots_for_position(&state.db_pool, position.id, from, to);
+    if streaming {
+        return ndjson_response(rows, |snapshot| snapshot);
+    }
+
+    let snapshots: Vec<PositionSnapshot> = match rows.try_collect().await {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            error!("Failed to fetch snapshots: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+                .into_response();
+        }
+    };
+    let warnings = retention_warning(&state.db_pool, RetainedData::PositionSnapshots, from).await;
+    let body = serde_json::json!({
+        "position_id": position.id,
+        "nft_id": position.nft_id,
+        "from": from,
+        "to": to,
+        "snapshots": snapshots,
+    });
+    (StatusCode::OK, Json(with_retention_warnings(body, warnings.into_iter().collect())))
+        .into_response()
+}
+
+/// GET /positions/:owner/:nft_id/owners?as_of=T
+/// Split a transferred position's P&L between everyone who held it, at the transfer times.
+/// Any current or past owner may ask.
+pub async fn get_position_owners_handler(
+    State(state): State<AppState>,
+    Path((owner, nft_id)): Path<(String, String)>,
+    axum::extract::Query(params): axum::extract::Query<OwnersQueryParams>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    info!("Attributing P&L of position {} for owner {}", nft_id, owner);
+
+    let as_of = match time_param("as_of", params.as_of.as_deref()) {
+        Ok(as_of) => as_of.unwrap_or_else(Utc::now),
+        Err(response) => return response,
+    };
+
+    let position = match get_position_by_nft(&state.db_pool, &nft_id).await {
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Position not found" })),
+            )
+        }
+        Err(e) => {
+            error!("Failed to fetch position: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let transfers = match get_transfers_for_position(&state.db_pool, position.id).await {
+        Ok(transfers) => transfers,
+        Err(e) => {
+            error!("Failed to fetch position transfers: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let held =
+        owner.contains(&position.owner) || transfers.iter().any(|t| owner.contains(&t.from_owner));
+    if !held {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Position never belonged to this owner" })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &position.pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Pool not found" })),
+            );
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let history = tokio::try_join!(
+        get_swaps_for_pool_between(&state.db_pool, &position.pool_id, position.created_at, as_of),
+        get_snapshots_for_position(&state.db_pool, position.id, position.created_at, as_of),
+        get_gas_expenses_for_position(&state.db_pool, position.id, as_of),
+    );
+    let (swaps, snapshots, gas) = match history {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Failed to fetch position history: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    let history = PnlHistory { swaps: &swaps, snapshots: &snapshots, gas: &gas };
+    let model = state.fee_models.model_for(&pool);
+    let owners = match attribute_pnl(&position, &pool, model, &history, &transfers, as_of) {
+        Ok(owners) => owners,
+        Err(e) => return invalid_range_response(e),
+    };
+
+    let response = PositionOwnersResponse {
+        nft_id: position.nft_id,
+        current_owner: position.owner,
+        as_of,
+        owners,
+        transfers,
+    };
     (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
 }
+
+/// DELETE /positions/:owner/:nft_id
+/// Soft-delete a position: it disappears from every read, but is kept (with its history)
+/// for `DELETED_POSITION_RETENTION_DAYS` and can be restored until then.
+/// Requires `Authorization: Bearer <api key>` linked to `owner` (every address of an account).
+pub async fn delete_position_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((owner, nft_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    set_position_deleted(&state, &headers, &owner, &nft_id, true).await
+}
+
+/// POST /positions/:owner/:nft_id/restore
+/// Undo a soft delete that retention hasn't purged yet (same authorization as DELETE)
+pub async fn restore_position_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((owner, nft_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let owner = match owner_scope_param(&state.db_pool, &owner).await {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    set_position_deleted(&state, &headers, &owner, &nft_id, false).await
+}
+
+async fn set_position_deleted(
+    state: &AppState,
+    headers: &HeaderMap,
+    owner: &OwnerScope,
+    nft_id: &str,
+    deleted: bool,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let owners = match authorized_addresses(state, headers).await {
+        Ok(owners) => owners,
+        Err(response) => return response,
+    };
+    if !owner.addresses.iter().all(|address| owners.contains(address)) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Owner is not linked to this API key" })),
+        );
+    }
+
+    let action = if deleted { "Deleting" } else { "Restoring" };
+    info!("{} position {} for owner {}", action, nft_id, owner);
+    let changed = if deleted {
+        soft_delete_position(&state.db_pool, &owner.addresses, nft_id).await
+    } else {
+        restore_position(&state.db_pool, &owner.addresses, nft_id).await
+    };
+    match changed {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Position not found" })),
+            );
+        }
+        Err(e) => {
+            error!("Failed to update position: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    }
+
+    // The cached portfolio still counts (or omits) the position
+    for address in &owner.addresses {
+        match build_portfolio(state, &OwnerScope::from(*address), false).await {
+            Ok(portfolio) => {
+                let value = serde_json::to_value(portfolio).unwrap();
+                state.cache.put("portfolio", &address.canonical(), &value).await;
+            }
+            Err(e) => warn!("Failed to refresh portfolio for {}: {}", address, e),
+        }
+    }
+
+    (StatusCode::OK, Json(serde_json::json!({ "nft_id": nft_id, "deleted": deleted })))
+}
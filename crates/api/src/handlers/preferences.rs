@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use serde::Deserialize;
+use stillwater_analytics::PriceDisplay;
+use stillwater_db::{
+    get_pool_by_id, get_quote_preference, get_quote_preferences, set_quote_preference,
+};
+use stillwater_models::{Address, Pool};
+use tracing::{error, info};
+
+use crate::address::owner_param;
+use crate::handlers::auth::authorized_addresses;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct QuotePreferenceRequest {
+    pub pool_id: String,
+    /// Token address to quote prices in (must be one of the pool's tokens)
+    pub quote_token: String,
+}
+
+/// Resolve how to display a pool's prices for an owner
+///
+/// An explicit `quote` (token address, `token0` or `token1`) wins over the
+/// owner's stored preference. Returns `Ok(None)` when neither is set, in which
+/// case raw pool prices are used unchanged.
+pub(crate) async fn resolve_price_display(
+    state: &AppState,
+    owner: &Address,
+    pool: &Pool,
+    quote: Option<&str>,
+) -> Result<Option<PriceDisplay>, (StatusCode, Json<serde_json::Value>)> {
+    let quote_token = match quote {
+        Some(quote) => quote.to_string(),
+        None => {
+            match get_quote_preference(&state.db_pool, &owner.canonical(), &pool.pool_id).await {
+                Ok(Some(preference)) => preference.quote_token,
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    error!("Failed to fetch quote preference: {}", e);
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(serde_json::json!({ "error": "Internal server error" })),
+                    ));
+                }
+            }
+        }
+    };
+
+    match PriceDisplay::for_pool(pool, &quote_token) {
+        Some(display) => Ok(Some(display)),
+        None => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "quote must be one of the pool's tokens" })),
+        )),
+    }
+}
+
+/// GET /preferences/:owner
+/// List an owner's quote token preferences
+pub async fn get_preferences_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    match get_quote_preferences(&state.db_pool, &owner.canonical()).await {
+        Ok(preferences) => (StatusCode::OK, Json(serde_json::to_value(preferences).unwrap())),
+        Err(e) => {
+            error!("Failed to fetch quote preferences: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
+
+/// PUT /preferences/:owner/quote
+/// Set which token an owner's prices in a pool are quoted in (requires an API key for the owner)
+pub async fn set_quote_preference_handler(
+    State(state): State<AppState>,
+    Path(owner): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<QuotePreferenceRequest>,
+) -> impl IntoResponse {
+    let owner = match owner_param(&owner) {
+        Ok(owner) => owner,
+        Err(response) => return response,
+    };
+    let addresses = match authorized_addresses(&state, &headers).await {
+        Ok(addresses) => addresses,
+        Err(response) => return response,
+    };
+    if !addresses.contains(&owner) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Owner is not linked to this API key" })),
+        );
+    }
+
+    let pool = match get_pool_by_id(&state.db_pool, &req.pool_id).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Pool not found" })),
+            );
+        }
+        Err(e) => {
+            error!("Failed to fetch pool: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            );
+        }
+    };
+
+    // Store the address so "token0"/"token1" shorthands resolve to the actual token
+    let Some(display) = PriceDisplay::for_pool(&pool, &req.quote_token) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "quote_token must be one of the pool's tokens" })),
+        );
+    };
+
+    let (owner_key, quote) = (owner.canonical(), &display.quote_token);
+    match set_quote_preference(&state.db_pool, &owner_key, &pool.pool_id, quote).await {
+        Ok(preference) => {
+            info!("{} now quotes pool {} in {}", owner, pool.pool_id, preference.quote_token);
+            (StatusCode::OK, Json(serde_json::to_value(preference).unwrap()))
+        }
+        Err(e) => {
+            error!("Failed to set quote preference: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
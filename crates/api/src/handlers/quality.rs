@@ -0,0 +1,58 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use serde::Deserialize;
+use stillwater_db::{get_quality_issues, get_quality_summary};
+use tracing::error;
+
+use crate::state::AppState;
+
+/// Maximum number of recent issues returned
+const MAX_RECENT_ISSUES: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DataQualityParams {
+    /// Only list recent issues for this pool
+    pub pool_id: Option<String>,
+    /// Number of recent issues to list (default 50)
+    pub limit: Option<i64>,
+}
+
+/// GET /data-quality?pool_id=X&limit=N
+/// Issue counts per pool and kind, plus the most recently detected issues
+pub async fn get_data_quality_handler(
+    State(state): State<AppState>,
+    Query(params): Query<DataQualityParams>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(50).clamp(1, MAX_RECENT_ISSUES);
+
+    let result = async {
+        let summary = get_quality_summary(&state.db_pool).await?;
+        let recent = get_quality_issues(&state.db_pool, params.pool_id.as_deref(), limit).await?;
+        anyhow::Ok((summary, recent))
+    }
+    .await;
+
+    match result {
+        Ok((summary, recent)) => {
+            let total: i64 = summary.iter().map(|s| s.count).sum();
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "total_issues": total,
+                    "summary": summary,
+                    "recent": recent,
+                })),
+            )
+        }
+        Err(e) => {
+            error!("Failed to get data quality summary: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+        }
+    }
+}
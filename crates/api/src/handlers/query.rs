@@ -0,0 +1,86 @@
+use axum::{
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use stillwater_db::QueryError;
+use tracing::{error, info};
+
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct QueryParams {
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+}
+
+/// POST /query?format=json
+///
+/// Body is a single SELECT statement, run read-only as the restricted role in
+/// `QUERY_DATABASE_URL` under the statement timeout and row limit. Behind the
+/// admin key, since the role can read every owner's data.
+pub async fn run_query_handler(
+    State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
+    body: String,
+) -> Response {
+    let csv = match params.format.as_deref().unwrap_or("json") {
+        "json" => false,
+        "csv" => true,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "format must be json or csv" })),
+            )
+                .into_response();
+        }
+    };
+    let Some(query_db) = &state.query_db else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "Ad hoc queries are not enabled" })),
+        )
+            .into_response();
+    };
+
+    let result = match query_db.run(&body).await {
+        Ok(result) => result,
+        Err(QueryError::Rejected(reason) | QueryError::Failed(reason)) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": reason })))
+                .into_response();
+        }
+        Err(QueryError::Internal(e)) => {
+            error!("Failed to run query: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+                .into_response();
+        }
+    };
+    info!("Ran ad hoc query: {} rows in {}ms", result.rows.len(), result.elapsed_ms);
+
+    if !csv {
+        return (StatusCode::OK, Json(serde_json::to_value(result).unwrap())).into_response();
+    }
+    match result.to_csv() {
+        Ok(body) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"query.csv\""),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to render query result as CSV: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Internal server error" })),
+            )
+                .into_response()
+        }
+    }
+}
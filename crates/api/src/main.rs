@@ -1,24 +1,94 @@
+mod address;
+mod admin;
+mod auth;
+mod cache;
+mod conditional;
 mod config;
+mod demo;
+mod display;
 mod handlers;
+mod ndjson;
+mod pseudonym;
+mod retention;
+mod slowlog;
 mod state;
+mod timerange;
+mod volatility;
 
-use axum::{Router, extract::State, routing::get};
+use axum::{Router, extract::State, handler::Handler, middleware, routing::{get, post, put}};
 use dotenv::dotenv;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tracing::info;
 use state::AppState;
+use pseudonym::PseudonymKey;
+use stillwater_analytics::RiskCategory;
 
+use handlers::accounts::{
+    add_account_address_handler, create_account_handler, delete_account_handler,
+    get_account_handler, remove_account_address_handler,
+};
+use handlers::admin::{
+    delete_pool_fee_override_handler, get_pool_fee_overrides_handler, get_sync_runs_handler,
+    get_fee_reconciliation_handler, get_sync_state_handler, set_pool_fee_override_handler,
+    set_sync_schedule_handler, trigger_sync_handler, delete_swap_downsampling_handler,
+    get_swap_downsampling_handler, set_swap_downsampling_handler, delete_vault_handler,
+    get_vaults_handler, set_vault_handler,
+};
+use handlers::alerts::{
+    create_alert_rule_handler, create_telegram_link_handler, delete_alert_rule_handler,
+    delete_alert_template_handler, get_alert_rule_handler, get_alert_rules_handler,
+    get_alert_templates_handler, get_watched_pairs_handler, get_watchlist_handler,
+    set_alert_template_handler, test_alert_rule_handler, unwatch_pair_handler,
+    unwatch_token_handler, update_alert_rule_handler, watch_pair_handler, watch_token_handler,
+};
+use handlers::auth::{create_nonce_handler, verify_signature_handler};
+use handlers::backtests::{
+    create_backtest_handler, diff_backtests_handler, get_backtest_equity_curve_handler,
+    get_backtest_handler, get_backtests_handler,
+};
+use handlers::chart::get_position_chart_handler;
+use handlers::export::export_ledger_handler;
+use handlers::grafana::{
+    grafana_metrics_handler, grafana_query_handler, grafana_search_handler, grafana_test_handler,
+};
+use handlers::import::import_positions_handler;
+use handlers::jobs::{
+    create_job_handler, download_job_handler, get_job_handler, get_jobs_handler,
+};
+use handlers::leaderboard::get_leaderboard_handler;
+use handlers::planner::{range_frontier_handler, size_position_handler};
+use handlers::pools::{
+    get_pool_cohorts_handler, get_pool_creation_handler, get_pool_heatmap_handler,
+    get_pool_jit_handler, get_pool_param_changes_handler, get_pool_stats_handler,
+    get_pool_swaps_handler, get_pool_ticks_handler, get_pool_twap_handler,
+    get_pool_volatility_handler, get_rebalance_policy_handler, get_volume_forecast_handler,
+};
+use handlers::portfolio::{
+    get_pnl_attribution_handler, get_portfolio_handler, get_portfolio_health_handler,
+    get_portfolio_totals_handler, get_rebalance_chains_handler, get_risk_adjusted_handler,
+    get_portfolio_vaults_handler, get_stress_handler, get_timeline_handler,
+};
+use handlers::preferences::{get_preferences_handler, set_quote_preference_handler};
+use handlers::quality::get_data_quality_handler;
+use handlers::query::run_query_handler;
 use handlers::positions::{
     get_positions_handler,
     get_position_with_pnl_handler,
     get_position_health_handler,
+    get_compound_recommendation_handler,
+    get_position_owners_handler,
+    get_position_history_handler,
+    get_position_pnl_attribution_handler,
+    delete_position_handler,
+    restore_position_handler,
 };
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    config::init_tracing();
+    let slow_requests = config::init_slow_requests();
+    config::init_tracing(&slow_requests);
 
     let db_pool = config::init_database().await;
     sqlx::migrate!("../../migrations").run(&db_pool).await.expect("Failed to run migrations");
@@ -29,21 +99,219 @@ async fn main() {
     let blockchain = config::init_blockchain();
     info!("Blockchain service initialized");
 
-    let app_state = AppState::new(db_pool, redis_client, blockchain);
+    let siwe = config::init_siwe();
+    let fee_models = config::init_fee_models();
+    let hooks = config::init_hook_registry();
+    let health_rules = config::init_health_rules();
+    let display_options = config::init_display_options();
+    let compound = config::init_compound_config();
+    let fee_velocity = config::init_fee_velocity_config();
+    let twap = config::init_twap_config();
+    let jit = config::init_jit_config();
+    let quote_currencies = config::init_quote_currencies();
+    let implied_volatility = config::init_implied_volatility();
+    let query_db = config::init_query_db().await;
+    let demo = config::init_demo_mode();
+    let admin = config::init_admin_key();
+    if admin.is_none() {
+        info!("ADMIN_API_KEY not set, sync control routes disabled");
+    }
+    let pseudonyms = config::init_pseudonym_key().unwrap_or_else(|| {
+        info!("PSEUDONYM_SECRET not set, leaderboard pseudonyms change on restart");
+        PseudonymKey::random()
+    });
+    if let Some(demo) = &demo {
+        info!(
+            "Demo mode: {} showcase addresses, {} anonymous requests a minute",
+            demo.addresses.len(),
+            demo.requests_per_minute
+        );
+    }
+    match &query_db {
+        Some(db) => {
+            info!("Ad hoc queries enabled ({}ms timeout, {} rows)", db.timeout_ms, db.max_rows)
+        }
+        None => info!("QUERY_DATABASE_URL not set, ad hoc queries disabled"),
+    }
+    if implied_volatility.is_empty() {
+        info!("No implied volatility source, comparisons need a request-supplied figure");
+    } else {
+        info!("{} implied volatility sources", implied_volatility.len());
+    }
+    match slow_requests.threshold {
+        Some(threshold) => info!("Logging requests slower than {}ms", threshold.as_millis()),
+        None => info!("SLOW_REQUEST_MS is 0, slow request logging disabled"),
+    }
+    info!(
+        "Rounding responses to {:?} significant figures ({})",
+        display_options.significant_figures,
+        display_options.rounding.as_str()
+    );
+    for category in RiskCategory::ALL {
+        let rules = health_rules.for_category(Some(category));
+        info!("Loaded {} {} position health rules", rules.rules.len(), category.as_str());
+    }
+
+    let app_state = AppState::new(
+        db_pool,
+        redis_client,
+        blockchain,
+        siwe,
+        fee_models,
+        hooks,
+        health_rules,
+        display_options,
+        compound,
+        fee_velocity,
+        twap,
+        jit,
+        quote_currencies,
+        implied_volatility,
+        query_db,
+        demo,
+        admin,
+        pseudonyms,
+    );
+    cache::spawn_cache_warmer(app_state.clone());
+    cache::spawn_event_listener(app_state.clone());
+
+    // Portfolio and position reads answer unchanged polls with 304
+    let conditional =
+        middleware::from_fn_with_state(app_state.cache.clone(), conditional::conditional_get);
+
+    // The whole /admin tree and ad hoc SQL are for operators only
+    let admin_routes = Router::new()
+        .route("/admin/sync", get(get_sync_state_handler).post(trigger_sync_handler))
+        .route("/admin/sync/schedule", put(set_sync_schedule_handler))
+        .route("/admin/sync-runs", get(get_sync_runs_handler))
+        .route("/admin/pool-fees", get(get_pool_fee_overrides_handler))
+        .route(
+            "/admin/pool-fees/{pool_id}",
+            put(set_pool_fee_override_handler).delete(delete_pool_fee_override_handler),
+        )
+        .route("/admin/vaults", get(get_vaults_handler))
+        .route("/admin/vaults/{address}", put(set_vault_handler).delete(delete_vault_handler))
+        .route("/admin/swap-downsampling", get(get_swap_downsampling_handler))
+        .route(
+            "/admin/swap-downsampling/{pool_id}",
+            put(set_swap_downsampling_handler).delete(delete_swap_downsampling_handler),
+        )
+        .route("/admin/fee-reconciliation/{pool_id}", get(get_fee_reconciliation_handler))
+        .route(
+            "/admin/alert-rules",
+            get(get_alert_rules_handler).post(create_alert_rule_handler),
+        )
+        .route(
+            "/admin/alert-rules/{id}",
+            get(get_alert_rule_handler)
+                .put(update_alert_rule_handler)
+                .delete(delete_alert_rule_handler),
+        )
+        .route("/admin/alert-rules/{id}/test", post(test_alert_rule_handler))
+        .route("/query", post(run_query_handler))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), admin::require_admin));
 
     let app = Router::new()
         .route("/", get(root_handler))
         .route("/health", get(health_handler))
-        .route("/positions/{owner}", get(get_positions_handler))
-        .route("/positions/{owner}/{nft_id}", get(get_position_with_pnl_handler))
+        .route("/positions/{owner}", get(get_positions_handler.layer(conditional.clone())))
+        .route("/positions/import", post(import_positions_handler))
+        .route(
+            "/positions/{owner}/{nft_id}",
+            get(get_position_with_pnl_handler.layer(conditional.clone()))
+                .delete(delete_position_handler),
+        )
+        .route("/positions/{owner}/{nft_id}/restore", post(restore_position_handler))
         .route("/positions/{owner}/{nft_id}/health", get(get_position_health_handler))
+        .route("/positions/{owner}/{nft_id}/compound", get(get_compound_recommendation_handler))
+        .route("/positions/{owner}/{nft_id}/owners", get(get_position_owners_handler))
+        .route("/positions/{owner}/{nft_id}/history", get(get_position_history_handler))
+        .route(
+            "/positions/{owner}/{nft_id}/pnl-attribution",
+            get(get_position_pnl_attribution_handler),
+        )
+        .route("/positions/{id}/chart", get(get_position_chart_handler))
+        .route("/portfolio/{owner}", get(get_portfolio_handler.layer(conditional.clone())))
+        .route("/portfolio/{owner}/rebalance-chains", get(get_rebalance_chains_handler))
+        .route("/portfolio/{owner}/risk-adjusted", get(get_risk_adjusted_handler))
+        .route("/portfolio/{owner}/pnl-attribution", get(get_pnl_attribution_handler))
+        .route("/portfolio/{owner}/health", get(get_portfolio_health_handler))
+        .route(
+            "/portfolio/{owner}/totals",
+            get(get_portfolio_totals_handler.layer(conditional.clone())),
+        )
+        .route("/portfolio/{owner}/vaults", get(get_portfolio_vaults_handler))
+        .route("/owners/{owner}/stress", get(get_stress_handler))
+        .route("/owners/{owner}/timeline", get(get_timeline_handler))
+        .route("/export/{owner}/ledger", get(export_ledger_handler))
+        .route("/pools/{pool_id}/stats", get(get_pool_stats_handler))
+        .route("/pools/{pool_id}/swaps", get(get_pool_swaps_handler))
+        .route("/pools/{pool_id}/creation", get(get_pool_creation_handler))
+        .route("/pools/{pool_id}/ticks", get(get_pool_ticks_handler))
+        .route("/pools/{pool_id}/heatmap", get(get_pool_heatmap_handler))
+        .route("/pools/{pool_id}/cohorts", get(get_pool_cohorts_handler))
+        .route("/pools/{pool_id}/forecast", get(get_volume_forecast_handler))
+        .route("/pools/{pool_id}/twap", get(get_pool_twap_handler))
+        .route("/pools/{pool_id}/rebalance-policy", get(get_rebalance_policy_handler))
+        .route("/pools/{pool_id}/volatility", get(get_pool_volatility_handler))
+        .route("/pools/{pool_id}/jit", get(get_pool_jit_handler))
+        .route("/pools/{pool_id}/param-changes", get(get_pool_param_changes_handler))
+        .route("/pools/{pool_id}/backtests", post(create_backtest_handler))
+        .route("/backtests", get(get_backtests_handler))
+        .route("/backtests/diff", get(diff_backtests_handler))
+        .route("/backtests/{id}", get(get_backtest_handler))
+        .route("/backtests/{id}/equity-curve", get(get_backtest_equity_curve_handler))
+        .route("/leaderboard", get(get_leaderboard_handler))
+        .route("/planner/size", post(size_position_handler))
+        .route("/planner/frontier", post(range_frontier_handler))
+        .route("/data-quality", get(get_data_quality_handler))
+        .route("/grafana", get(grafana_test_handler))
+        .route("/grafana/search", post(grafana_search_handler))
+        .route("/grafana/metrics", post(grafana_metrics_handler))
+        .route("/grafana/query", post(grafana_query_handler))
+        .route("/jobs", get(get_jobs_handler).post(create_job_handler))
+        .route("/jobs/{id}", get(get_job_handler))
+        .route("/jobs/{id}/download", get(download_job_handler))
+        .route("/preferences/{owner}", get(get_preferences_handler))
+        .route("/preferences/{owner}/quote", put(set_quote_preference_handler))
+        .route("/alerts/{owner}/templates", get(get_alert_templates_handler))
+        .route(
+            "/alerts/{owner}/templates/{kind}",
+            put(set_alert_template_handler).delete(delete_alert_template_handler),
+        )
+        .route("/alerts/{owner}/watchlist", get(get_watchlist_handler))
+        .route(
+            "/alerts/{owner}/watchlist/{token}",
+            put(watch_token_handler).delete(unwatch_token_handler),
+        )
+        .route("/alerts/{owner}/pairs", get(get_watched_pairs_handler))
+        .route(
+            "/alerts/{owner}/pairs/{token_a}/{token_b}",
+            put(watch_pair_handler).delete(unwatch_pair_handler),
+        )
+        .route("/alerts/{owner}/telegram-link", post(create_telegram_link_handler))
+        .route("/accounts", post(create_account_handler))
+        .route("/accounts/{name}", get(get_account_handler).delete(delete_account_handler))
+        .route(
+            "/accounts/{name}/addresses/{address}",
+            put(add_account_address_handler).delete(remove_account_address_handler),
+        )
+        .route("/auth/nonce", post(create_nonce_handler))
+        .route("/auth/verify", post(verify_signature_handler))
+        .merge(admin_routes)
+        .layer(middleware::from_fn_with_state(app_state.clone(), display::apply_display_options))
+        .layer(middleware::from_fn_with_state(app_state.cache.clone(), conditional::track_writes))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), demo::demo_gate))
+        .layer(middleware::from_fn_with_state(slow_requests, slowlog::log_slow_requests))
         .with_state(app_state);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     info!("Server running on http://{}", addr);
 
     let listener = TcpListener::bind(addr).await.expect("Failed to bind TCP listener");
-    axum::serve(listener, app.into_make_service()).await.expect("Failed to start server");
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .expect("Failed to start server");
 }
 
 async fn root_handler(State(_state): State<AppState>) -> &'static str {
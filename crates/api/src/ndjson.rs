@@ -0,0 +1,49 @@
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use serde::Serialize;
+use std::convert::Infallible;
+use stillwater_db::RowStream;
+use tracing::error;
+
+/// Media type of newline-delimited JSON
+pub const NDJSON: &str = "application/x-ndjson";
+
+/// Whether the client asked for NDJSON through `Accept`
+pub fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media| media.split(';').next().is_some_and(|m| m.trim() == NDJSON))
+}
+
+/// Stream rows as NDJSON, one JSON object per line, without buffering the result set
+///
+/// The status is sent before the first row, so a query failing partway ends
+/// the body with an `{"error": ...}` line instead (the row stream stops after
+/// an error).
+pub fn ndjson_response<T, R, F>(rows: RowStream<T>, to_line: F) -> Response
+where
+    T: Send + 'static,
+    R: Serialize,
+    F: Fn(T) -> R + Send + 'static,
+{
+    let lines = rows.map(move |row| {
+        let value = match row {
+            Ok(row) => serde_json::to_value(to_line(row)).unwrap_or_default(),
+            Err(e) => {
+                error!("Failed to stream rows: {:#}", e);
+                serde_json::json!({ "error": "Internal server error" })
+            }
+        };
+        Ok::<_, Infallible>(format!("{}\n", value))
+    });
+
+    ([(header::CONTENT_TYPE, HeaderValue::from_static(NDJSON))], Body::from_stream(lines))
+        .into_response()
+}
@@ -0,0 +1,56 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Secret keying the pseudonyms that stand in for owner addresses on shared rankings
+///
+/// Pseudonyms are an HMAC of the address, so without the secret they can't be
+/// reversed by hashing every known address.
+pub struct PseudonymKey {
+    secret: Vec<u8>,
+}
+
+impl PseudonymKey {
+    pub fn new(secret: &str) -> Self {
+        Self { secret: secret.trim().as_bytes().to_vec() }
+    }
+
+    /// A random key, for deployments without a configured secret
+    ///
+    /// Pseudonyms then change whenever the API restarts.
+    pub fn random() -> Self {
+        Self::new(&format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()))
+    }
+
+    /// Stable pseudonym for an address
+    pub fn pseudonym(&self, address: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("any key length works");
+        mac.update(address.to_lowercase().as_bytes());
+        let hash = mac.finalize().into_bytes();
+        format!("lp-{}", alloy::hex::encode(&hash[..6]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    #[test]
+    fn test_pseudonym_is_stable_per_key() {
+        let key = PseudonymKey::new("secret");
+        assert_eq!(key.pseudonym(ADDRESS), key.pseudonym(&ADDRESS.to_lowercase()));
+        assert_eq!(key.pseudonym(ADDRESS), PseudonymKey::new("secret").pseudonym(ADDRESS));
+        assert!(key.pseudonym(ADDRESS).starts_with("lp-"));
+        assert!(!key.pseudonym(ADDRESS).contains(&ADDRESS.to_lowercase()[2..10]));
+    }
+
+    #[test]
+    fn test_pseudonym_depends_on_secret() {
+        let a = PseudonymKey::new("secret");
+        let b = PseudonymKey::new("other secret");
+        assert_ne!(a.pseudonym(ADDRESS), b.pseudonym(ADDRESS));
+        assert_ne!(PseudonymKey::random().pseudonym(ADDRESS), a.pseudonym(ADDRESS));
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_analytics::{RetentionWarning, check_retention};
+use stillwater_db::get_retention_horizon;
+use stillwater_models::RetainedData;
+use tracing::warn;
+
+/// Warning for a window starting at `from` that needs data retention has deleted
+///
+/// A horizon that can't be read is logged and treated as unknown; the request
+/// itself doesn't depend on it.
+pub async fn retention_warning(
+    db_pool: &PgPool,
+    data: RetainedData,
+    from: DateTime<Utc>,
+) -> Option<RetentionWarning> {
+    match get_retention_horizon(db_pool, data).await {
+        Ok(horizon) => check_retention(data, from, horizon),
+        Err(e) => {
+            warn!("Failed to read {} retention horizon: {}", data.as_str(), e);
+            None
+        }
+    }
+}
+
+/// Add `retention_warnings` to a JSON object response when there are any
+pub fn with_retention_warnings(
+    mut body: serde_json::Value,
+    warnings: Vec<RetentionWarning>,
+) -> serde_json::Value {
+    if let (false, Some(object)) = (warnings.is_empty(), body.as_object_mut()) {
+        object.insert(
+            "retention_warnings".to_string(),
+            serde_json::to_value(warnings).unwrap_or_default(),
+        );
+    }
+    body
+}
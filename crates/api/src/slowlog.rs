@@ -0,0 +1,157 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Instrument, Subscriber, info_span, warn};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Requests taking longer are logged unless `SLOW_REQUEST_MS` is set
+pub const DEFAULT_SLOW_REQUEST_MS: u64 = 1000;
+
+/// Most statements a slow request's log lists, slowest first
+pub const MAX_LOGGED_STATEMENTS: usize = 20;
+
+/// Target sqlx logs the statements it runs under
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+/// When a request counts as slow
+#[derive(Debug, Clone, Copy)]
+pub struct SlowRequests {
+    /// None: slow request logging is off
+    pub threshold: Option<Duration>,
+}
+
+/// A statement a request ran
+#[derive(Debug, Clone)]
+struct Statement {
+    sql: String,
+    elapsed: Duration,
+}
+
+tokio::task_local! {
+    /// Statements run by the request the current task is serving
+    static STATEMENTS: RefCell<Vec<Statement>>;
+}
+
+/// Tracing layer recording the statements sqlx logs for the request that ran them
+///
+/// sqlx logs every statement at debug level under `sqlx::query`, so this layer
+/// needs those events enabled. Statements run outside a request, or from a
+/// task the handler spawned, aren't recorded.
+pub struct StatementCapture;
+
+impl StatementCapture {
+    /// Level the layer needs `sqlx::query` events at
+    pub const LEVEL: tracing::Level = tracing::Level::DEBUG;
+
+    pub const TARGET: &str = SQLX_QUERY_TARGET;
+}
+
+impl<S: Subscriber> Layer<S> for StatementCapture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != SQLX_QUERY_TARGET {
+            return;
+        }
+        let _ = STATEMENTS.try_with(|statements| {
+            let mut visitor = StatementVisitor::default();
+            event.record(&mut visitor);
+            let sql = if visitor.statement.trim().is_empty() {
+                visitor.summary
+            } else {
+                visitor.statement
+            };
+            statements.borrow_mut().push(Statement {
+                sql: sql.split_whitespace().collect::<Vec<_>>().join(" "),
+                elapsed: Duration::try_from_secs_f64(visitor.elapsed_secs).unwrap_or_default(),
+            });
+        });
+    }
+}
+
+/// Reads the statement and its duration off a sqlx event
+#[derive(Default)]
+struct StatementVisitor {
+    summary: String,
+    statement: String,
+    elapsed_secs: f64,
+}
+
+impl Visit for StatementVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.statement = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed_secs = value;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// Trace each request under its route and log the slow ones with their SQL
+///
+/// Every log line a request emits carries its method and route (the path
+/// pattern, e.g. `/positions/{owner}`). A request taking longer than the
+/// threshold is logged as a warning with its status, how many statements it
+/// ran and how long they took, followed by the slowest of them. Time to the
+/// response headers is measured, not to the end of a streamed body.
+pub async fn log_slow_requests(
+    State(config): State<SlowRequests>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path().to_string(), |path| path.as_str().to_string());
+    let span = info_span!("request", %method, route = %route);
+
+    let Some(threshold) = config.threshold else {
+        return next.run(request).instrument(span).await;
+    };
+
+    let started = Instant::now();
+    let (response, mut statements) = STATEMENTS
+        .scope(RefCell::new(Vec::new()), async move {
+            let response = next.run(request).await;
+            (response, STATEMENTS.with(RefCell::take))
+        })
+        .instrument(span.clone())
+        .await;
+    let elapsed = started.elapsed();
+    if elapsed < threshold {
+        return response;
+    }
+
+    let sql_time: Duration = statements.iter().map(|s| s.elapsed).sum();
+    statements.sort_by_key(|s| std::cmp::Reverse(s.elapsed));
+    let mut message = format!(
+        "Slow request: {} {} returned {} in {}ms ({} statements, {}ms in SQL)",
+        method,
+        route,
+        response.status().as_u16(),
+        elapsed.as_millis(),
+        statements.len(),
+        sql_time.as_millis()
+    );
+    for statement in statements.iter().take(MAX_LOGGED_STATEMENTS) {
+        let _ = write!(message, "\n  {:>6}ms  {}", statement.elapsed.as_millis(), statement.sql);
+    }
+    if statements.len() > MAX_LOGGED_STATEMENTS {
+        let _ = write!(message, "\n  … {} more", statements.len() - MAX_LOGGED_STATEMENTS);
+    }
+    span.in_scope(|| warn!("{}", message));
+    response
+}
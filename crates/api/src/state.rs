@@ -1,21 +1,98 @@
 use redis::Client as RedisClient;
 use sqlx::PgPool;
+use std::sync::Arc;
+use stillwater_analytics::{
+    CompoundConfig, DisplayOptions, FeeModelRegistry, FeeVelocityConfig, HookRegistry, JitConfig,
+    QuoteCurrencies, RiskHealthRules, TwapConfig,
+};
+use stillwater_db::ReadOnlyDb;
 use stillwater_models::BlockchainService;
 
+use crate::admin::AdminKey;
+use crate::auth::SiweConfig;
+use crate::cache::ResponseCache;
+use crate::demo::DemoMode;
+use crate::pseudonym::PseudonymKey;
+use crate::volatility::ImpliedVolatilitySources;
+
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: PgPool,
-    pub redis_client: RedisClient,
+    /// Redis-backed cache of expensive responses, warmed after each sync
+    pub cache: ResponseCache,
     pub blockchain: BlockchainService,
+    pub siwe: SiweConfig,
+    pub fee_models: Arc<FeeModelRegistry>,
+    /// Known hook contracts, for caveats on positions in hooked pools
+    pub hooks: Arc<HookRegistry>,
+    /// Position health rules per risk bucket
+    pub health_rules: Arc<RiskHealthRules>,
+    /// Precision JSON responses are rounded to
+    pub display: Arc<DisplayOptions>,
+    /// When unclaimed fees are worth compounding
+    pub compound: Arc<CompoundConfig>,
+    /// Windows fee velocity trends compare, and the drop flagged
+    pub fee_velocity: Arc<FeeVelocityConfig>,
+    /// Window current prices are averaged over
+    pub twap: TwapConfig,
+    /// What counts as just-in-time liquidity
+    pub jit: Arc<JitConfig>,
+    /// Currencies portfolio totals can be normalized into
+    pub quote_currencies: Arc<QuoteCurrencies>,
+    /// Where implied volatility for pools' pairs comes from
+    pub implied_volatility: Arc<ImpliedVolatilitySources>,
+    /// Pool for ad hoc SQL (None unless `QUERY_DATABASE_URL` is set)
+    pub query_db: Option<ReadOnlyDb>,
+    /// Public demo restrictions (None unless `DEMO_ADDRESSES` is set)
+    pub demo: Option<Arc<DemoMode>>,
+    /// Key for the sync control routes (None unless `ADMIN_API_KEY` is set)
+    pub admin: Option<Arc<AdminKey>>,
+    /// Secret for the pseudonyms replacing owners on anonymized leaderboards
+    pub pseudonyms: Arc<PseudonymKey>,
 }
 
 impl AppState {
-    pub fn new(db_pool: PgPool, redis_client: RedisClient, blockchain: BlockchainService) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db_pool: PgPool,
+        redis_client: RedisClient,
+        blockchain: BlockchainService,
+        siwe: SiweConfig,
+        fee_models: FeeModelRegistry,
+        hooks: HookRegistry,
+        health_rules: RiskHealthRules,
+        display: DisplayOptions,
+        compound: CompoundConfig,
+        fee_velocity: FeeVelocityConfig,
+        twap: TwapConfig,
+        jit: JitConfig,
+        quote_currencies: QuoteCurrencies,
+        implied_volatility: ImpliedVolatilitySources,
+        query_db: Option<ReadOnlyDb>,
+        demo: Option<DemoMode>,
+        admin: Option<AdminKey>,
+        pseudonyms: PseudonymKey,
+    ) -> Self {
         Self {
             db_pool,
-            redis_client,
+            cache: ResponseCache::new(redis_client),
             blockchain,
+            siwe,
+            fee_models: Arc::new(fee_models),
+            hooks: Arc::new(hooks),
+            health_rules: Arc::new(health_rules),
+            display: Arc::new(display),
+            compound: Arc::new(compound),
+            fee_velocity: Arc::new(fee_velocity),
+            twap,
+            jit: Arc::new(jit),
+            quote_currencies: Arc::new(quote_currencies),
+            implied_volatility: Arc::new(implied_volatility),
+            query_db,
+            demo: demo.map(Arc::new),
+            admin: admin.map(Arc::new),
+            pseudonyms: Arc::new(pseudonyms),
         }
     }
 }
@@ -0,0 +1,74 @@
+use axum::{http::StatusCode, response::Json};
+use chrono::{DateTime, Duration, Utc};
+use stillwater_analytics::{
+    TimeRange, TimeRangeError, TimeRangeLimits, format_duration, parse_duration, parse_time,
+};
+
+pub(crate) type ErrorResponse = (StatusCode, Json<serde_json::Value>);
+
+fn bad_request(param: &str, e: TimeRangeError) -> ErrorResponse {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": format!("{}: {}", param, e) })))
+}
+
+/// Parse `from`/`to` query parameters, answering `400` when they're invalid or too far apart
+pub fn time_range_param(
+    from: Option<&str>,
+    to: Option<&str>,
+    limits: TimeRangeLimits,
+) -> Result<TimeRange, ErrorResponse> {
+    TimeRange::parse(from, to, limits, Utc::now()).map_err(|e| {
+        let param = match e {
+            TimeRangeError::InvalidTime { ref value } if from == Some(value.as_str()) => "from",
+            TimeRangeError::InvalidTime { .. } => "to",
+            _ => "from/to",
+        };
+        bad_request(param, e)
+    })
+}
+
+/// Parse a single point-in-time query parameter such as `as_of`
+pub fn time_param(name: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, ErrorResponse> {
+    value.map(|value| parse_time(value, Utc::now())).transpose().map_err(|e| bad_request(name, e))
+}
+
+/// Parse a duration query parameter (`window`, `interval`), at most `max`
+pub fn duration_param(
+    name: &str,
+    value: Option<&str>,
+    default: Duration,
+    max: Duration,
+) -> Result<Duration, ErrorResponse> {
+    let duration = value.map(parse_duration).transpose().map_err(|e| bad_request(name, e))?;
+    let duration = duration.unwrap_or(default);
+    if duration > max {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("{} must be at most {}", name, format_duration(max))
+            })),
+        ));
+    }
+    Ok(duration)
+}
+
+/// Parse `interval`, falling back to the older `interval_minutes`
+pub fn interval_param(
+    interval: Option<&str>,
+    interval_minutes: Option<i64>,
+    default: Duration,
+    max: Duration,
+) -> Result<Duration, ErrorResponse> {
+    if let (None, Some(minutes)) = (interval, interval_minutes) {
+        let interval =
+            Duration::try_minutes(minutes).filter(|i| *i > Duration::zero() && *i <= max);
+        return interval.ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("interval_minutes must be 1-{}", max.num_minutes())
+                })),
+            )
+        });
+    }
+    duration_param("interval", interval, default, max)
+}
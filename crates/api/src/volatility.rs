@@ -0,0 +1,256 @@
+use anyhow::{Context, Result, bail};
+use axum::{http::StatusCode, response::Json};
+use chrono::{Duration, Utc};
+use futures::future::BoxFuture;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use stillwater_analytics::parse_volatility;
+use stillwater_models::{Pool, canonical_address};
+use tracing::warn;
+
+use crate::timerange::ErrorResponse;
+
+/// Deribit's public API
+const DERIBIT_API_URL: &str = "https://www.deribit.com/api/v2";
+
+/// How long a fetched DVOL is reused before asking Deribit again
+const DVOL_CACHE_SECS: u64 = 300;
+
+/// Tokens priced by Deribit's ETH DVOL by default: native ETH and mainnet WETH
+const DEFAULT_DVOL_ETH_TOKENS: [&str; 2] =
+    ["0x0000000000000000000000000000000000000000", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"];
+
+/// Tokens priced by Deribit's BTC DVOL by default: mainnet WBTC
+const DEFAULT_DVOL_BTC_TOKENS: [&str; 1] = ["0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"];
+
+/// Annualized implied volatility for a pool's pair, as a fraction
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpliedVolatility {
+    pub volatility: Decimal,
+    /// Where the figure came from, e.g. `deribit:ETH`
+    pub source: String,
+}
+
+/// Somewhere implied volatility can be looked up
+///
+/// A source answers None for pools it has no figure for, so the next source
+/// is tried.
+pub trait ImpliedVolatilitySource: Send + Sync {
+    fn implied_volatility<'a>(
+        &'a self,
+        pool: &'a Pool,
+    ) -> BoxFuture<'a, Result<Option<ImpliedVolatility>>>;
+}
+
+/// The one entry of `figures` keyed by the pool, or else by exactly one of its tokens
+///
+/// A pool whose two tokens both have a figure gets none: neither token's
+/// volatility against the dollar measures the pair's.
+fn figure_for_pool<T: Clone>(figures: &HashMap<String, T>, pool: &Pool) -> Option<T> {
+    if let Some(figure) = figures.get(&pool.pool_id.to_lowercase()) {
+        return Some(figure.clone());
+    }
+    match (
+        figures.get(&canonical_address(&pool.token0)),
+        figures.get(&canonical_address(&pool.token1)),
+    ) {
+        (Some(figure), None) | (None, Some(figure)) => Some(figure.clone()),
+        _ => None,
+    }
+}
+
+/// Operator-supplied implied volatility per pool or token
+#[derive(Debug, Clone, Default)]
+pub struct StaticVolatility {
+    /// Lowercase pool ID or token address to annualized volatility
+    pub figures: HashMap<String, Decimal>,
+}
+
+impl StaticVolatility {
+    /// Figures from `IMPLIED_VOLATILITY`: comma-separated `pool_or_token=volatility`
+    ///
+    /// Volatility is a fraction or percent, e.g. `0xc02a...=65%`. None when unset.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(spec) = std::env::var("IMPLIED_VOLATILITY") else {
+            return Ok(None);
+        };
+        let mut figures = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((key, volatility)) = entry.split_once('=') else {
+                bail!("IMPLIED_VOLATILITY entries must be pool_or_token=volatility: {}", entry);
+            };
+            let volatility = parse_volatility(volatility)
+                .with_context(|| format!("IMPLIED_VOLATILITY entry {}", entry))?;
+            figures.insert(key.trim().to_lowercase(), volatility);
+        }
+        Ok((!figures.is_empty()).then_some(Self { figures }))
+    }
+}
+
+impl ImpliedVolatilitySource for StaticVolatility {
+    fn implied_volatility<'a>(
+        &'a self,
+        pool: &'a Pool,
+    ) -> BoxFuture<'a, Result<Option<ImpliedVolatility>>> {
+        let figure = figure_for_pool(&self.figures, pool).map(|volatility| ImpliedVolatility {
+            volatility,
+            source: "configured".to_string(),
+        });
+        Box::pin(async move { Ok(figure) })
+    }
+}
+
+/// Deribit's DVOL index, the 30-day implied volatility of BTC or ETH options
+///
+/// Pools pairing an indexed token with one that isn't (typically a
+/// stablecoin) get the index of that token.
+pub struct DeribitDvol {
+    client: Client,
+    /// Lowercase token address to the Deribit currency indexing it
+    currencies: HashMap<String, String>,
+    /// Latest DVOL per currency and when it was fetched
+    cache: Mutex<HashMap<String, (Instant, Decimal)>>,
+}
+
+impl DeribitDvol {
+    pub fn new(currencies: HashMap<String, String>) -> Self {
+        Self { client: Client::new(), currencies, cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// Tokens from `DVOL_ETH_TOKENS` and `DVOL_BTC_TOKENS`, defaulting to mainnet's
+    pub fn from_env() -> Self {
+        let mut currencies = HashMap::new();
+        for (currency, var, defaults) in [
+            ("ETH", "DVOL_ETH_TOKENS", &DEFAULT_DVOL_ETH_TOKENS[..]),
+            ("BTC", "DVOL_BTC_TOKENS", &DEFAULT_DVOL_BTC_TOKENS[..]),
+        ] {
+            let tokens: Vec<String> = match std::env::var(var) {
+                Ok(tokens) => tokens
+                    .split(',')
+                    .map(canonical_address)
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                Err(_) => defaults.iter().map(|t| t.to_string()).collect(),
+            };
+            for token in tokens {
+                currencies.insert(token, currency.to_string());
+            }
+        }
+        Self::new(currencies)
+    }
+
+    /// Latest hourly DVOL close for a currency, as a fraction
+    async fn dvol(&self, currency: &str) -> Result<Option<Decimal>> {
+        if let Some((fetched, dvol)) = self.cache.lock().unwrap().get(currency)
+            && fetched.elapsed().as_secs() < DVOL_CACHE_SECS
+        {
+            return Ok(Some(*dvol));
+        }
+
+        let now = Utc::now();
+        let start = now - Duration::hours(3);
+        let response: Value = self
+            .client
+            .get(format!("{}/public/get_volatility_index_data", DERIBIT_API_URL))
+            .query(&[
+                ("currency", currency.to_string()),
+                ("start_timestamp", start.timestamp_millis().to_string()),
+                ("end_timestamp", now.timestamp_millis().to_string()),
+                ("resolution", "3600".to_string()),
+            ])
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .context("Failed to reach Deribit")?
+            .json()
+            .await
+            .context("Invalid Deribit response")?;
+        if let Some(error) = response.get("error") {
+            bail!("Deribit returned an error: {}", error);
+        }
+
+        // Candles are [timestamp, open, high, low, close], DVOL in percent
+        let close = response["result"]["data"]
+            .as_array()
+            .and_then(|candles| candles.iter().max_by_key(|c| c[0].as_i64()))
+            .and_then(|candle| candle[4].as_f64())
+            .and_then(Decimal::from_f64_retain)
+            .map(|percent| (percent / Decimal::ONE_HUNDRED).round_dp(4));
+        if let Some(dvol) = close {
+            self.cache.lock().unwrap().insert(currency.to_string(), (Instant::now(), dvol));
+        }
+        Ok(close)
+    }
+}
+
+impl ImpliedVolatilitySource for DeribitDvol {
+    fn implied_volatility<'a>(
+        &'a self,
+        pool: &'a Pool,
+    ) -> BoxFuture<'a, Result<Option<ImpliedVolatility>>> {
+        Box::pin(async move {
+            let Some(currency) = figure_for_pool(&self.currencies, pool) else {
+                return Ok(None);
+            };
+            let dvol = self.dvol(&currency).await?;
+            Ok(dvol.map(|volatility| ImpliedVolatility {
+                volatility,
+                source: format!("deribit:{}", currency),
+            }))
+        })
+    }
+}
+
+/// Implied volatility sources, asked in order until one has a figure
+#[derive(Clone, Default)]
+pub struct ImpliedVolatilitySources {
+    sources: Vec<Arc<dyn ImpliedVolatilitySource>>,
+}
+
+impl ImpliedVolatilitySources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask `source` after the sources already added
+    pub fn push(&mut self, source: Arc<dyn ImpliedVolatilitySource>) {
+        self.sources.push(source);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// The first figure a source has for the pool
+    ///
+    /// A failing source is logged and skipped: a missing comparison shouldn't
+    /// fail the suggestion it annotates.
+    pub async fn for_pool(&self, pool: &Pool) -> Option<ImpliedVolatility> {
+        for source in &self.sources {
+            match source.implied_volatility(pool).await {
+                Ok(Some(implied)) => return Some(implied),
+                Ok(None) => {}
+                Err(e) => warn!("Implied volatility lookup for pool {} failed: {}", pool.pool_id, e),
+            }
+        }
+        None
+    }
+}
+
+/// Parse an `implied_volatility` parameter, answering `400` when it isn't a volatility
+pub fn implied_volatility_param(value: Option<&str>) -> Result<Option<Decimal>, ErrorResponse> {
+    value.map(parse_volatility).transpose().map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid implied_volatility: {}", e) })),
+        )
+    })
+}
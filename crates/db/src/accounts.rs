@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_models::{Account, Address};
+
+// ============================================================================
+// Account Operations
+// ============================================================================
+
+/// Create an account of addresses, returning None if the name is taken
+pub async fn create_account(
+    pool: &PgPool,
+    name: &str,
+    addresses: &[Address],
+) -> Result<Option<Account>> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+
+    let id: Option<i64> = sqlx::query_scalar(
+        r#"
+        INSERT INTO accounts (name)
+        VALUES ($1)
+        ON CONFLICT (name) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to create account")?;
+    let Some(id) = id else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO account_addresses (account_id, address)
+        SELECT $1, UNNEST($2::text[])
+        ON CONFLICT (account_id, address) DO NOTHING
+        "#,
+    )
+    .bind(id)
+    .bind(addresses)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to add account addresses")?;
+
+    tx.commit().await.context("Failed to commit account")?;
+    get_account(pool, name).await
+}
+
+/// Get an account with its addresses
+pub async fn get_account(pool: &PgPool, name: &str) -> Result<Option<Account>> {
+    let row: Option<(i64, String, DateTime<Utc>, Vec<Address>)> = sqlx::query_as(
+        r#"
+        SELECT a.id, a.name, a.created_at,
+               COALESCE(ARRAY_AGG(aa.address ORDER BY aa.address)
+                        FILTER (WHERE aa.address IS NOT NULL), '{}')
+        FROM accounts a
+        LEFT JOIN account_addresses aa ON aa.account_id = a.id
+        WHERE a.name = $1
+        GROUP BY a.id
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get account")?;
+
+    Ok(row.map(|(id, name, created_at, addresses)| Account { id, name, addresses, created_at }))
+}
+
+/// Delete an account, returning false if there was none (its addresses' data stays)
+pub async fn delete_account(pool: &PgPool, name: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM accounts WHERE name = $1")
+        .bind(name)
+        .execute(pool)
+        .await
+        .context("Failed to delete account")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Add an address to an account (no-op if already in it), returning false if there's no account
+pub async fn add_account_address(pool: &PgPool, name: &str, address: &Address) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO account_addresses (account_id, address)
+        SELECT id, $2 FROM accounts WHERE name = $1
+        ON CONFLICT (account_id, address) DO NOTHING
+        "#,
+    )
+    .bind(name)
+    .bind(address)
+    .execute(pool)
+    .await
+    .context("Failed to add account address")?;
+
+    if result.rows_affected() > 0 {
+        return Ok(true);
+    }
+    Ok(get_account(pool, name).await?.is_some())
+}
+
+/// Remove an address from an account, returning false if it wasn't in it
+pub async fn remove_account_address(pool: &PgPool, name: &str, address: &Address) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM account_addresses aa
+        USING accounts a
+        WHERE aa.account_id = a.id AND a.name = $1 AND aa.address = $2
+        "#,
+    )
+    .bind(name)
+    .bind(address)
+    .execute(pool)
+    .await
+    .context("Failed to remove account address")?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,169 @@
+use alloy::primitives::I256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use stillwater_models::{FeeAccumulator, Swap};
+
+// ============================================================================
+// Fee Accumulator Operations
+// ============================================================================
+
+fn accumulator_from_row(row: &PgRow) -> FeeAccumulator {
+    FeeAccumulator {
+        position_id: row.get(0),
+        last_swap_id: row.get(1),
+        swap_count: row.get(2),
+        volume: row.get(3),
+        fees_earned: row.get(4),
+        updated_at: row.get(5),
+        verified_at: row.get(6),
+    }
+}
+
+/// Get a position's fee accumulator, if one has been started
+pub async fn get_fee_accumulator(
+    pool: &PgPool,
+    position_id: i64,
+) -> Result<Option<FeeAccumulator>> {
+    let row = sqlx::query(
+        r#"
+        SELECT position_id, last_swap_id, swap_count, volume, fees_earned, updated_at, verified_at
+        FROM position_fee_accumulators
+        WHERE position_id = $1
+        "#,
+    )
+    .bind(position_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get fee accumulator")?;
+
+    Ok(row.as_ref().map(accumulator_from_row))
+}
+
+/// Get the fee accumulators of a set of positions
+pub async fn get_fee_accumulators(
+    pool: &PgPool,
+    position_ids: &[i64],
+) -> Result<Vec<FeeAccumulator>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT position_id, last_swap_id, swap_count, volume, fees_earned, updated_at, verified_at
+        FROM position_fee_accumulators
+        WHERE position_id = ANY($1)
+        "#,
+    )
+    .bind(position_ids)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get fee accumulators")?;
+
+    Ok(rows.iter().map(accumulator_from_row).collect())
+}
+
+/// Insert or replace a position's fee accumulator
+pub async fn upsert_fee_accumulator(pool: &PgPool, accumulator: &FeeAccumulator) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO position_fee_accumulators
+            (position_id, last_swap_id, swap_count, volume, fees_earned, updated_at, verified_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (position_id) DO UPDATE
+        SET last_swap_id = EXCLUDED.last_swap_id,
+            swap_count = EXCLUDED.swap_count,
+            volume = EXCLUDED.volume,
+            fees_earned = EXCLUDED.fees_earned,
+            updated_at = EXCLUDED.updated_at,
+            verified_at = EXCLUDED.verified_at
+        "#,
+    )
+    .bind(accumulator.position_id)
+    .bind(accumulator.last_swap_id)
+    .bind(accumulator.swap_count)
+    .bind(accumulator.volume)
+    .bind(accumulator.fees_earned)
+    .bind(accumulator.updated_at)
+    .bind(accumulator.verified_at)
+    .execute(pool)
+    .await
+    .context("Failed to upsert fee accumulator")?;
+
+    Ok(())
+}
+
+/// Accumulators least recently verified first (never-verified ones before any)
+///
+/// With `created_since`, only positions created since then: older ones'
+/// swaps may have been purged, so they can't be recomputed.
+pub async fn get_fee_accumulators_to_verify(
+    pool: &PgPool,
+    created_since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<FeeAccumulator>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT a.position_id, a.last_swap_id, a.swap_count, a.volume, a.fees_earned,
+               a.updated_at, a.verified_at
+        FROM position_fee_accumulators a
+        JOIN positions p ON p.id = a.position_id
+        WHERE p.deleted_at IS NULL AND ($1::timestamptz IS NULL OR p.created_at >= $1)
+        ORDER BY a.verified_at ASC NULLS FIRST, a.position_id ASC
+        LIMIT $2
+        "#,
+    )
+    .bind(created_since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get fee accumulators to verify")?;
+
+    Ok(rows.iter().map(accumulator_from_row).collect())
+}
+
+/// Get a pool's swaps (hot and archived) with IDs above `after_id`, in ID order
+///
+/// IDs follow insertion order, so this returns every swap recorded since an
+/// accumulator's `last_swap_id`, including late arrivals with old timestamps.
+/// Swaps before `since` are left out.
+pub async fn get_swaps_for_pool_after_id(
+    pool: &PgPool,
+    pool_id: &str,
+    after_id: i64,
+    since: DateTime<Utc>,
+) -> Result<Vec<Swap>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps
+        WHERE pool_id = $1 AND id > $2 AND timestamp >= $3
+        UNION ALL
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps_archive
+        WHERE pool_id = $1 AND id > $2 AND timestamp >= $3
+        ORDER BY id ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(after_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get swaps for pool")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let amount0_str: String = r.get(3);
+            let amount1_str: String = r.get(4);
+            Swap {
+                id: r.get(0),
+                tx_hash: r.get(1),
+                pool_id: r.get(2),
+                amount0: amount0_str.parse::<I256>().unwrap_or_default(),
+                amount1: amount1_str.parse::<I256>().unwrap_or_default(),
+                fee: r.get(5),
+                timestamp: r.get(6),
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,420 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use stillwater_models::{
+    Alert, AlertKind, AlertRule, AlertRuleSpec, AlertTemplate, DeliveryStatus, PendingAlert,
+};
+
+// ============================================================================
+// Pending Alert Operations
+// ============================================================================
+
+const PENDING_ALERT_COLUMNS: &str = "id, idempotency_key, sink, payload, status, attempts, \
+     next_attempt_at, last_error, delivered_at, created_at, target";
+
+fn row_to_pending_alert(r: &PgRow) -> Result<PendingAlert> {
+    let payload: serde_json::Value = r.get(3);
+    let status: String = r.get(4);
+
+    Ok(PendingAlert {
+        id: r.get(0),
+        idempotency_key: r.get(1),
+        sink: r.get(2),
+        alert: serde_json::from_value::<Alert>(payload).context("Invalid alert payload")?,
+        status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Pending),
+        attempts: r.get(5),
+        next_attempt_at: r.get(6),
+        last_error: r.get(7),
+        delivered_at: r.get(8),
+        created_at: r.get(9),
+        target: r.get(10),
+    })
+}
+
+/// Queue an alert for delivery to a sink, claimed until `claimed_until`
+///
+/// The row is claimed so the caller can attempt delivery at once without the
+/// retry worker picking it up too. Returns `None` if an alert with the same
+/// idempotency key was already queued, in which case it must not be delivered again.
+pub async fn enqueue_alert(
+    pool: &PgPool,
+    idempotency_key: &str,
+    sink: &str,
+    target: &str,
+    alert: &Alert,
+    claimed_until: DateTime<Utc>,
+) -> Result<Option<PendingAlert>> {
+    let payload = serde_json::to_value(alert).context("Failed to serialize alert")?;
+
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO pending_alerts (idempotency_key, sink, target, payload, claimed_until)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (idempotency_key) DO NOTHING
+        RETURNING {}
+        "#,
+        PENDING_ALERT_COLUMNS
+    ))
+    .bind(idempotency_key)
+    .bind(sink)
+    .bind(target)
+    .bind(payload)
+    .bind(claimed_until)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to enqueue alert")?;
+
+    row.as_ref().map(row_to_pending_alert).transpose()
+}
+
+/// Claim pending alerts whose next attempt is due, until `claimed_until`
+///
+/// Rows another worker holds (locked, or claimed with an unexpired lease) are
+/// skipped, so concurrent workers never deliver the same alert at once.
+pub async fn claim_due_alerts(
+    pool: &PgPool,
+    now: DateTime<Utc>,
+    claimed_until: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<PendingAlert>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        UPDATE pending_alerts
+        SET claimed_until = $2
+        WHERE id IN (
+            SELECT id
+            FROM pending_alerts
+            WHERE status = 'pending'
+              AND next_attempt_at <= $1
+              AND (claimed_until IS NULL OR claimed_until < $1)
+            ORDER BY next_attempt_at ASC
+            LIMIT $3
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING {}
+        "#,
+        PENDING_ALERT_COLUMNS
+    ))
+    .bind(now)
+    .bind(claimed_until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to claim due alerts")?;
+
+    let mut alerts = rows.iter().map(row_to_pending_alert).collect::<Result<Vec<_>>>()?;
+    alerts.sort_by_key(|a| a.next_attempt_at);
+    Ok(alerts)
+}
+
+/// Mark a queued alert as delivered
+pub async fn mark_alert_delivered(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE pending_alerts
+        SET status = 'delivered', attempts = attempts + 1, delivered_at = NOW(), last_error = NULL,
+            claimed_until = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .context("Failed to mark alert delivered")?;
+
+    Ok(())
+}
+
+/// Record a failed delivery attempt and schedule the next retry
+pub async fn mark_alert_retry(
+    pool: &PgPool,
+    id: i64,
+    next_attempt_at: DateTime<Utc>,
+    error: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE pending_alerts
+        SET attempts = attempts + 1, next_attempt_at = $2, last_error = $3, claimed_until = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(next_attempt_at)
+    .bind(error)
+    .execute(pool)
+    .await
+    .context("Failed to schedule alert retry")?;
+
+    Ok(())
+}
+
+/// Give up on a queued alert after a permanent error or too many attempts
+pub async fn mark_alert_failed(pool: &PgPool, id: i64, error: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE pending_alerts
+        SET status = 'failed', attempts = attempts + 1, last_error = $2, claimed_until = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .execute(pool)
+    .await
+    .context("Failed to mark alert failed")?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Alert Template Operations
+// ============================================================================
+
+fn row_to_alert_template(r: &PgRow) -> Result<AlertTemplate> {
+    let kind: String = r.get(1);
+
+    Ok(AlertTemplate {
+        owner: r.get(0),
+        kind: AlertKind::parse(&kind).context("Invalid alert kind")?,
+        title: r.get(2),
+        body: r.get(3),
+        updated_at: r.get(4),
+    })
+}
+
+/// Set an owner's template for a kind of alert, replacing any previous one
+pub async fn set_alert_template(
+    pool: &PgPool,
+    owner: &str,
+    kind: AlertKind,
+    title: Option<&str>,
+    body: &str,
+) -> Result<AlertTemplate> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO alert_templates (owner, kind, title, body)
+        VALUES (LOWER($1), $2, $3, $4)
+        ON CONFLICT (owner, kind) DO UPDATE
+        SET title = EXCLUDED.title, body = EXCLUDED.body, updated_at = NOW()
+        RETURNING owner, kind, title, body, updated_at
+        "#,
+    )
+    .bind(owner)
+    .bind(kind.as_str())
+    .bind(title)
+    .bind(body)
+    .fetch_one(pool)
+    .await
+    .context("Failed to set alert template")?;
+
+    row_to_alert_template(&row)
+}
+
+/// Get an owner's template for a kind of alert, if set
+pub async fn get_alert_template(
+    pool: &PgPool,
+    owner: &str,
+    kind: AlertKind,
+) -> Result<Option<AlertTemplate>> {
+    let row = sqlx::query(
+        r#"
+        SELECT owner, kind, title, body, updated_at
+        FROM alert_templates
+        WHERE owner = LOWER($1) AND kind = $2
+        "#,
+    )
+    .bind(owner)
+    .bind(kind.as_str())
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get alert template")?;
+
+    row.as_ref().map(row_to_alert_template).transpose()
+}
+
+/// Get all of an owner's alert templates
+pub async fn get_alert_templates(pool: &PgPool, owner: &str) -> Result<Vec<AlertTemplate>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT owner, kind, title, body, updated_at
+        FROM alert_templates
+        WHERE owner = LOWER($1)
+        ORDER BY kind
+        "#,
+    )
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get alert templates")?;
+
+    rows.iter().map(row_to_alert_template).collect()
+}
+
+/// Remove an owner's template for a kind of alert, returning whether one existed
+pub async fn delete_alert_template(pool: &PgPool, owner: &str, kind: AlertKind) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM alert_templates WHERE owner = LOWER($1) AND kind = $2")
+        .bind(owner)
+        .bind(kind.as_str())
+        .execute(pool)
+        .await
+        .context("Failed to delete alert template")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// ============================================================================
+// Alert Rule Operations
+// ============================================================================
+
+const ALERT_RULE_COLUMNS: &str = "id, owner, pool_id, condition, threshold, sinks, \
+     cooldown_seconds, enabled, created_at, updated_at, tick_threshold";
+
+fn row_to_alert_rule(r: &PgRow) -> Result<AlertRule> {
+    let condition: String = r.get(3);
+
+    Ok(AlertRule {
+        id: r.get(0),
+        spec: AlertRuleSpec {
+            owner: r.get(1),
+            pool_id: r.get(2),
+            condition: AlertKind::parse(&condition).context("Invalid alert rule condition")?,
+            threshold: r.get(4),
+            tick_threshold: r.get(10),
+            sinks: r.get(5),
+            cooldown_seconds: r.get(6),
+            enabled: r.get(7),
+        },
+        created_at: r.get(8),
+        updated_at: r.get(9),
+    })
+}
+
+/// Create an alert rule (owners are stored lowercase)
+pub async fn create_alert_rule(pool: &PgPool, spec: &AlertRuleSpec) -> Result<AlertRule> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO alert_rules
+            (owner, pool_id, condition, threshold, sinks, cooldown_seconds, enabled, tick_threshold)
+        VALUES (LOWER($1), $2, $3, $4, $5, $6, $7, $8)
+        RETURNING {}
+        "#,
+        ALERT_RULE_COLUMNS
+    ))
+    .bind(&spec.owner)
+    .bind(&spec.pool_id)
+    .bind(spec.condition.as_str())
+    .bind(spec.threshold)
+    .bind(&spec.sinks)
+    .bind(spec.cooldown_seconds)
+    .bind(spec.enabled)
+    .bind(spec.tick_threshold)
+    .fetch_one(pool)
+    .await
+    .context("Failed to create alert rule")?;
+
+    row_to_alert_rule(&row)
+}
+
+/// Replace an alert rule's definition, returning None if it doesn't exist
+pub async fn update_alert_rule(
+    pool: &PgPool,
+    id: i64,
+    spec: &AlertRuleSpec,
+) -> Result<Option<AlertRule>> {
+    let row = sqlx::query(&format!(
+        r#"
+        UPDATE alert_rules
+        SET owner = LOWER($2), pool_id = $3, condition = $4, threshold = $5, sinks = $6,
+            cooldown_seconds = $7, enabled = $8, tick_threshold = $9, updated_at = NOW()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        ALERT_RULE_COLUMNS
+    ))
+    .bind(id)
+    .bind(&spec.owner)
+    .bind(&spec.pool_id)
+    .bind(spec.condition.as_str())
+    .bind(spec.threshold)
+    .bind(&spec.sinks)
+    .bind(spec.cooldown_seconds)
+    .bind(spec.enabled)
+    .bind(spec.tick_threshold)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to update alert rule")?;
+
+    row.as_ref().map(row_to_alert_rule).transpose()
+}
+
+/// Get an alert rule by ID
+pub async fn get_alert_rule(pool: &PgPool, id: i64) -> Result<Option<AlertRule>> {
+    let row = sqlx::query(&format!("SELECT {} FROM alert_rules WHERE id = $1", ALERT_RULE_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to get alert rule")?;
+
+    row.as_ref().map(row_to_alert_rule).transpose()
+}
+
+/// Get alert rules, optionally only those for one owner (global rules excluded)
+pub async fn get_alert_rules(pool: &PgPool, owner: Option<&str>) -> Result<Vec<AlertRule>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM alert_rules
+        WHERE $1::text IS NULL OR owner = LOWER($1)
+        ORDER BY id
+        "#,
+        ALERT_RULE_COLUMNS
+    ))
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get alert rules")?;
+
+    rows.iter().map(row_to_alert_rule).collect()
+}
+
+/// Delete an alert rule, returning whether it existed
+pub async fn delete_alert_rule(pool: &PgPool, id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .context("Failed to delete alert rule")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record a rule firing for a position unless it fired within its cooldown
+///
+/// Returns false (recording nothing) while the cooldown runs, so concurrent
+/// jobs can't both fire the same rule for the same position.
+pub async fn claim_alert_rule_firing(
+    pool: &PgPool,
+    rule: &AlertRule,
+    position_id: i64,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO alert_rule_firings (rule_id, position_id, fired_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (rule_id, position_id) DO UPDATE
+        SET fired_at = EXCLUDED.fired_at
+        WHERE alert_rule_firings.fired_at <= EXCLUDED.fired_at - make_interval(secs => $4)
+        "#,
+    )
+    .bind(rule.id)
+    .bind(position_id)
+    .bind(now)
+    .bind(rule.spec.cooldown_seconds as f64)
+    .execute(pool)
+    .await
+    .context("Failed to record alert rule firing")?;
+
+    Ok(result.rows_affected() > 0)
+}
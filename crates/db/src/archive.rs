@@ -0,0 +1,190 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use sqlx::PgPool;
+
+// ============================================================================
+// Swap Archive Operations
+// ============================================================================
+
+/// Name of the archive partition holding swaps from `month` (`swaps_archive_y2024m03`)
+pub(crate) fn archive_partition_name(month: NaiveDate) -> String {
+    format!("swaps_archive_y{:04}m{:02}", month.year(), month.month())
+}
+
+/// First day of the month after `month`
+pub(crate) fn next_month(month: NaiveDate) -> NaiveDate {
+    if month.month() == 12 {
+        NaiveDate::from_ymd_opt(month.year() + 1, 1, 1).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(month.year(), month.month() + 1, 1).unwrap()
+    }
+}
+
+/// Move swaps older than `cutoff` from `swaps` into the monthly `swaps_archive` partitions
+///
+/// Partitions for the affected months are created first; the move itself is a
+/// single `DELETE ... RETURNING` feeding the insert, in one transaction. A
+/// swap that's already in the archive (e.g. re-synced after archiving) fails
+/// the whole move rather than being deleted without a copy. Returns the
+/// number of swaps archived.
+pub async fn archive_swaps_before(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<u64> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+
+    let months: Vec<DateTime<Utc>> = sqlx::query_scalar(
+        "SELECT DISTINCT date_trunc('month', timestamp, 'UTC') FROM swaps WHERE timestamp < $1",
+    )
+    .bind(cutoff)
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to find months to archive")?;
+
+    for month in months {
+        let month = month.date_naive();
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF swaps_archive \
+             FOR VALUES FROM ('{}') TO ('{}')",
+            archive_partition_name(month),
+            month,
+            next_month(month)
+        ))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to create swap archive partition")?;
+    }
+
+    let (deleted, archived): (i64, i64) = sqlx::query_as(
+        r#"
+        WITH moved AS (
+            DELETE FROM swaps WHERE timestamp < $1
+            RETURNING id, tx_hash, pool_id, amount0, amount1, fee, timestamp
+        ),
+        archived AS (
+            INSERT INTO swaps_archive (id, tx_hash, pool_id, amount0, amount1, fee, timestamp)
+            SELECT id, tx_hash, pool_id, amount0, amount1, fee, timestamp FROM moved
+            ON CONFLICT DO NOTHING
+            RETURNING id
+        )
+        SELECT (SELECT COUNT(*) FROM moved), (SELECT COUNT(*) FROM archived)
+        "#,
+    )
+    .bind(cutoff)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to archive swaps")?;
+
+    // Dropping the transaction rolls the delete back
+    anyhow::ensure!(
+        deleted == archived,
+        "{} of {} swaps before {} are already archived; not archiving any",
+        deleted - archived,
+        deleted,
+        cutoff
+    );
+
+    tx.commit().await.context("Failed to commit swap archive")?;
+    Ok(archived as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{create_test_pool, create_test_swap, test_database};
+    use crate::{get_swaps_for_pool, get_swaps_for_pool_after_id, get_swaps_for_pool_between};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        date(year, month, day).and_hms_opt(12, 0, 0).unwrap().and_utc()
+    }
+
+    #[test]
+    fn test_next_month_rolls_over_the_year() {
+        assert_eq!(next_month(date(2024, 1, 1)), date(2024, 2, 1));
+        assert_eq!(next_month(date(2024, 2, 1)), date(2024, 3, 1));
+        assert_eq!(next_month(date(2024, 11, 1)), date(2024, 12, 1));
+        assert_eq!(next_month(date(2024, 12, 1)), date(2025, 1, 1));
+    }
+
+    #[test]
+    fn test_partition_names_pad_year_and_month() {
+        assert_eq!(archive_partition_name(date(2024, 3, 1)), "swaps_archive_y2024m03");
+        assert_eq!(archive_partition_name(date(2024, 12, 1)), "swaps_archive_y2024m12");
+        assert_eq!(archive_partition_name(date(999, 1, 1)), "swaps_archive_y0999m01");
+    }
+
+    #[test]
+    fn test_archived_swaps_are_still_read() {
+        let Some((runtime, db_pool)) = test_database("archive_reads") else {
+            return;
+        };
+
+        runtime.block_on(async {
+            sqlx::query("TRUNCATE pools, swaps, swaps_archive RESTART IDENTITY CASCADE")
+                .execute(&db_pool)
+                .await
+                .unwrap();
+            create_test_pool(&db_pool, "0xarchive").await;
+            for (i, timestamp) in
+                [at(2023, 12, 20), at(2024, 1, 10), at(2024, 3, 5)].iter().enumerate()
+            {
+                create_test_swap(&db_pool, "0xarchive", &format!("0xtx{}", i), *timestamp).await;
+            }
+
+            let archived = archive_swaps_before(&db_pool, at(2024, 2, 1)).await.unwrap();
+            assert_eq!(archived, 2);
+            let hot: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM swaps").fetch_one(&db_pool).await.unwrap();
+            assert_eq!(hot, 1);
+
+            let all = get_swaps_for_pool(&db_pool, "0xarchive", at(2023, 1, 1)).await.unwrap();
+            let hashes: Vec<&str> = all.iter().map(|s| s.tx_hash.as_str()).collect();
+            assert_eq!(hashes, vec!["0xtx0", "0xtx1", "0xtx2"]);
+
+            let january =
+                get_swaps_for_pool_between(&db_pool, "0xarchive", at(2024, 1, 1), at(2024, 1, 31))
+                    .await
+                    .unwrap();
+            assert_eq!(january.len(), 1);
+            assert_eq!(january[0].tx_hash, "0xtx1");
+
+            let after_first =
+                get_swaps_for_pool_after_id(&db_pool, "0xarchive", all[0].id, at(2023, 1, 1))
+                    .await
+                    .unwrap();
+            assert_eq!(after_first.len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_already_archived_swaps_fail_the_move() {
+        let Some((runtime, db_pool)) = test_database("archive_conflicts") else {
+            return;
+        };
+
+        runtime.block_on(async {
+            sqlx::query("TRUNCATE pools, swaps, swaps_archive RESTART IDENTITY CASCADE")
+                .execute(&db_pool)
+                .await
+                .unwrap();
+            create_test_pool(&db_pool, "0xarchive").await;
+            create_test_swap(&db_pool, "0xarchive", "0xtx0", at(2024, 1, 10)).await;
+            archive_swaps_before(&db_pool, at(2024, 2, 1)).await.unwrap();
+
+            // The same swap (same id) comes back into the hot table
+            sqlx::query(
+                "INSERT INTO swaps (id, tx_hash, pool_id, amount0, amount1, fee, timestamp) \
+                 SELECT id, tx_hash, pool_id, amount0, amount1, fee, timestamp FROM swaps_archive",
+            )
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+            assert!(archive_swaps_before(&db_pool, at(2024, 2, 1)).await.is_err());
+            let hot: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM swaps").fetch_one(&db_pool).await.unwrap();
+            assert_eq!(hot, 1, "the failed move must not delete the swap");
+        });
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_models::{Address, ApiKey, WatchedAddress};
+
+// ============================================================================
+// Watchlist Operations
+// ============================================================================
+
+/// Add an address to the watchlist (no-op if already watched)
+pub async fn add_to_watchlist(pool: &PgPool, address: &Address, source: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO watchlist (address, source)
+        VALUES ($1, $2)
+        ON CONFLICT (address) DO NOTHING
+        "#,
+    )
+    .bind(address)
+    .bind(source)
+    .execute(pool)
+    .await
+    .context("Failed to add address to watchlist")?;
+
+    Ok(())
+}
+
+/// Get all watched addresses
+pub async fn get_watchlist(pool: &PgPool) -> Result<Vec<WatchedAddress>> {
+    let rows = sqlx::query_as::<_, WatchedAddress>(
+        r#"
+        SELECT address, source, added_at
+        FROM watchlist
+        ORDER BY added_at ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to get watchlist")?;
+
+    Ok(rows)
+}
+
+// ============================================================================
+// API Key Operations
+// ============================================================================
+
+/// Create an API key record from the key's hash
+pub async fn create_api_key(pool: &PgPool, key_hash: &str, label: Option<&str>) -> Result<ApiKey> {
+    let key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        INSERT INTO api_keys (key_hash, label)
+        VALUES ($1, $2)
+        RETURNING id, key_hash, label, created_at, last_used_at
+        "#,
+    )
+    .bind(key_hash)
+    .bind(label)
+    .fetch_one(pool)
+    .await
+    .context("Failed to create API key")?;
+
+    Ok(key)
+}
+
+/// Look up an API key by hash, recording its use
+pub async fn touch_api_key(pool: &PgPool, key_hash: &str) -> Result<Option<ApiKey>> {
+    let key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        UPDATE api_keys
+        SET last_used_at = NOW()
+        WHERE key_hash = $1
+        RETURNING id, key_hash, label, created_at, last_used_at
+        "#,
+    )
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up API key")?;
+
+    Ok(key)
+}
+
+/// Associate a verified address with an API key
+pub async fn link_api_key_address(pool: &PgPool, api_key_id: i64, address: &Address) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO api_key_addresses (api_key_id, address)
+        VALUES ($1, $2)
+        ON CONFLICT (api_key_id, address) DO UPDATE SET verified_at = NOW()
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(address)
+    .execute(pool)
+    .await
+    .context("Failed to link address to API key")?;
+
+    Ok(())
+}
+
+/// Get addresses verified for an API key
+pub async fn get_api_key_addresses(pool: &PgPool, api_key_id: i64) -> Result<Vec<Address>> {
+    let addresses = sqlx::query_scalar::<_, Address>(
+        r#"
+        SELECT address
+        FROM api_key_addresses
+        WHERE api_key_id = $1
+        ORDER BY verified_at ASC
+        "#,
+    )
+    .bind(api_key_id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get API key addresses")?;
+
+    Ok(addresses)
+}
+
+// ============================================================================
+// Auth Nonce Operations
+// ============================================================================
+
+/// Store a sign-in nonce issued for an address
+pub async fn insert_auth_nonce(
+    pool: &PgPool,
+    nonce: &str,
+    address: &Address,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO auth_nonces (nonce, address, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(nonce)
+    .bind(address)
+    .bind(expires_at)
+    .execute(pool)
+    .await
+    .context("Failed to insert auth nonce")?;
+
+    Ok(())
+}
+
+/// Atomically consume an unexpired nonce issued for `address`
+///
+/// Returns false if the nonce is unknown, expired, already used, or was
+/// issued for a different address.
+pub async fn consume_auth_nonce(pool: &PgPool, nonce: &str, address: &Address) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE auth_nonces
+        SET used_at = NOW()
+        WHERE nonce = $1 AND address = $2 AND used_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(nonce)
+    .bind(address)
+    .execute(pool)
+    .await
+    .context("Failed to consume auth nonce")?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Delete nonces that were used or have expired, returning how many went
+///
+/// Neither can be consumed again, so they only grow the table.
+pub async fn purge_auth_nonces(pool: &PgPool) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM auth_nonces
+        WHERE used_at IS NOT NULL OR expires_at <= NOW()
+        "#,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to purge auth nonces")?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_models::BacktestRun;
+
+// ============================================================================
+// Backtest Run Operations
+// ============================================================================
+
+const BACKTEST_COLUMNS: &str =
+    "id, pool_id, label, params, period_start, period_end, metrics, created_at";
+
+/// What a backtest run stores
+pub struct NewBacktest<'a> {
+    pub pool_id: &'a str,
+    pub label: Option<&'a str>,
+    pub params: &'a serde_json::Value,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub metrics: &'a serde_json::Value,
+    pub equity_curve: &'a serde_json::Value,
+}
+
+/// Store a backtest run
+pub async fn save_backtest(pool: &PgPool, backtest: &NewBacktest<'_>) -> Result<BacktestRun> {
+    let run = sqlx::query_as::<_, BacktestRun>(&format!(
+        r#"
+        INSERT INTO backtests
+            (pool_id, label, params, period_start, period_end, metrics, equity_curve)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING {}
+        "#,
+        BACKTEST_COLUMNS
+    ))
+    .bind(backtest.pool_id)
+    .bind(backtest.label)
+    .bind(backtest.params)
+    .bind(backtest.period_start)
+    .bind(backtest.period_end)
+    .bind(backtest.metrics)
+    .bind(backtest.equity_curve)
+    .fetch_one(pool)
+    .await
+    .context("Failed to save backtest")?;
+
+    Ok(run)
+}
+
+/// Get the most recent backtest runs, of one pool or all, newest first
+pub async fn get_backtests(
+    pool: &PgPool,
+    pool_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<BacktestRun>> {
+    let runs = sqlx::query_as::<_, BacktestRun>(&format!(
+        r#"
+        SELECT {}
+        FROM backtests
+        WHERE $1::VARCHAR IS NULL OR pool_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+        BACKTEST_COLUMNS
+    ))
+    .bind(pool_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get backtests")?;
+
+    Ok(runs)
+}
+
+/// Get a backtest run by ID
+pub async fn get_backtest(pool: &PgPool, id: i64) -> Result<Option<BacktestRun>> {
+    let run = sqlx::query_as::<_, BacktestRun>(&format!(
+        "SELECT {} FROM backtests WHERE id = $1",
+        BACKTEST_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get backtest")?;
+
+    Ok(run)
+}
+
+/// Get a backtest run's equity curve (None if there's no such run)
+pub async fn get_backtest_equity_curve(
+    pool: &PgPool,
+    id: i64,
+) -> Result<Option<serde_json::Value>> {
+    let curve = sqlx::query_scalar("SELECT equity_curve FROM backtests WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to get backtest equity curve")?;
+
+    Ok(curve)
+}
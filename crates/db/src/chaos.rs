@@ -0,0 +1,91 @@
+use anyhow::{Context, Result, anyhow};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use stillwater_models::{Fault, FaultPlan};
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Port assumed when the database URL doesn't name one
+const DEFAULT_POSTGRES_PORT: u16 = 5432;
+
+/// Start a local TCP proxy to `upstream` (host:port) that injects `plan`'s faults
+///
+/// Each new connection takes the plan's next step: `drop` and status codes
+/// close it straight away, `timeout` holds it open without forwarding before
+/// closing, `latency` delays connecting upstream, and `partial:<bytes>` cuts
+/// the connection after that many bytes from the server. Pools keep their
+/// connections, so faults hit connection setup and reconnects, not every query.
+pub async fn spawn_chaos_proxy(upstream: String, plan: Arc<FaultPlan>) -> Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.context("Failed to bind chaos proxy")?;
+    let local_addr = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((client, _)) = listener.accept().await else {
+                continue;
+            };
+            tokio::spawn(proxy_connection(client, upstream.clone(), plan.next_fault()));
+        }
+    });
+
+    Ok(local_addr)
+}
+
+async fn proxy_connection(mut client: TcpStream, upstream: String, fault: Option<Fault>) {
+    match fault {
+        Some(Fault::ConnectionDrop | Fault::Status(_)) => return,
+        Some(Fault::Timeout(wait)) => {
+            tokio::time::sleep(wait).await;
+            return;
+        }
+        Some(Fault::Latency(wait)) => tokio::time::sleep(wait).await,
+        Some(Fault::PartialPage(_)) | None => {}
+    }
+
+    let Ok(mut server) = TcpStream::connect(&upstream).await else {
+        return;
+    };
+
+    if let Some(Fault::PartialPage(limit)) = fault {
+        let (mut client_read, mut client_write) = client.split();
+        let (server_read, mut server_write) = server.split();
+        let mut server_read = server_read.take(limit as u64);
+        tokio::select! {
+            _ = tokio::io::copy(&mut client_read, &mut server_write) => {}
+            _ = tokio::io::copy(&mut server_read, &mut client_write) => {}
+        }
+    } else {
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+    }
+}
+
+/// Route a Postgres URL through a chaos proxy, returning the rewritten URL
+pub async fn chaos_database_url(database_url: &str, plan: Arc<FaultPlan>) -> Result<String> {
+    let (scheme, rest) =
+        database_url.split_once("://").ok_or_else(|| anyhow!("DATABASE_URL has no scheme"))?;
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((userinfo, host_port)) => (Some(userinfo), host_port),
+        None => (None, authority),
+    };
+    if host_port.is_empty() || host_port.contains(',') {
+        return Err(anyhow!("Chaos proxy needs a single TCP database host"));
+    }
+
+    let has_port = match host_port.rfind(':') {
+        Some(colon) => !host_port[colon..].contains(']'),
+        None => false,
+    };
+    let upstream = if has_port {
+        host_port.to_string()
+    } else {
+        format!("{}:{}", host_port, DEFAULT_POSTGRES_PORT)
+    };
+
+    let proxy = spawn_chaos_proxy(upstream, plan).await?;
+    Ok(match userinfo {
+        Some(userinfo) => format!("{}://{}@{}{}", scheme, userinfo, proxy, path),
+        None => format!("{}://{}{}", scheme, proxy, path),
+    })
+}
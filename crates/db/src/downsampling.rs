@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use stillwater_models::{Swap, SwapDownsampling};
+
+use crate::row_to_swap;
+
+// ============================================================================
+// Swap Downsampling Operations
+// ============================================================================
+
+/// Opt a pool into downsampled swap storage, or change how many swaps a day it keeps
+///
+/// None if the pool is unknown. Days already downsampled stay as they are.
+pub async fn set_swap_downsampling(
+    pool: &PgPool,
+    pool_id: &str,
+    keep_largest: i32,
+) -> Result<Option<SwapDownsampling>> {
+    let downsampling = sqlx::query_as::<_, SwapDownsampling>(
+        r#"
+        INSERT INTO pool_swap_downsampling (pool_id, keep_largest)
+        SELECT pool_id, $2 FROM pools WHERE pool_id = $1
+        ON CONFLICT (pool_id) DO UPDATE
+        SET keep_largest = EXCLUDED.keep_largest, updated_at = NOW()
+        RETURNING pool_id, keep_largest, downsampled_through, updated_at
+        "#,
+    )
+    .bind(pool_id)
+    .bind(keep_largest)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to set swap downsampling")?;
+
+    Ok(downsampling)
+}
+
+/// Get every pool opted into downsampled swap storage
+pub async fn get_swap_downsampling(pool: &PgPool) -> Result<Vec<SwapDownsampling>> {
+    let downsampling = sqlx::query_as::<_, SwapDownsampling>(
+        r#"
+        SELECT pool_id, keep_largest, downsampled_through, updated_at
+        FROM pool_swap_downsampling
+        ORDER BY pool_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to get swap downsampling")?;
+
+    Ok(downsampling)
+}
+
+/// Stop downsampling a pool's swaps; false if it wasn't
+///
+/// Minutes already aggregated stay aggregated.
+pub async fn delete_swap_downsampling(pool: &PgPool, pool_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM pool_swap_downsampling WHERE pool_id = $1")
+        .bind(pool_id)
+        .execute(pool)
+        .await
+        .context("Failed to delete swap downsampling")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Opted-in pools, their `keep_largest` and the days before `before` not yet downsampled
+///
+/// Only days with swaps still in the hot table count, oldest first.
+pub async fn get_days_to_downsample(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+) -> Result<Vec<(String, i32, DateTime<Utc>)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT d.pool_id, d.keep_largest, date_trunc('day', s.timestamp, 'UTC') AS day
+        FROM pool_swap_downsampling d
+        JOIN swaps s ON s.pool_id = d.pool_id
+        WHERE s.timestamp < $1
+          AND (d.downsampled_through IS NULL OR s.timestamp >= d.downsampled_through)
+        GROUP BY 1, 2, 3
+        ORDER BY 3, 1
+        "#,
+    )
+    .bind(before)
+    .fetch_all(pool)
+    .await
+    .context("Failed to find days to downsample")?;
+
+    Ok(rows.iter().map(|r| (r.get(0), r.get(1), r.get(2))).collect())
+}
+
+/// Get a pool's swaps in [start, end) from the hot table only, oldest first
+///
+/// Archived swaps can't be replaced in place, so downsampling leaves them be.
+pub async fn get_hot_swaps_for_pool_between(
+    pool: &PgPool,
+    pool_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Swap>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps
+        WHERE pool_id = $1 AND timestamp >= $2 AND timestamp < $3
+        ORDER BY timestamp ASC, id ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get swaps for pool")?;
+
+    Ok(rows.iter().map(row_to_swap).collect())
+}
+
+/// How many swaps each of a pool's minute aggregates in [start, end) stands for
+pub async fn get_swap_minute_counts(
+    pool: &PgPool,
+    pool_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<HashMap<DateTime<Utc>, i32>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT minute, swap_count
+        FROM swap_minute_aggregates
+        WHERE pool_id = $1 AND minute >= $2 AND minute < $3
+        "#,
+    )
+    .bind(pool_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get swap minute counts")?;
+
+    Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+}
+
+/// Replace swaps with minute aggregates and mark the pool downsampled through `through`
+///
+/// `aggregates` pairs each aggregate row with the number of swaps it stands
+/// for. The rows in `removed` are deleted before the aggregates are inserted,
+/// so an aggregate may reuse the ID of a row it replaces. Everything runs in
+/// one transaction.
+pub async fn replace_swaps_with_aggregates(
+    pool: &PgPool,
+    pool_id: &str,
+    removed: &[i64],
+    aggregates: &[(&Swap, i32)],
+    through: DateTime<Utc>,
+) -> Result<()> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+
+    sqlx::query("DELETE FROM swaps WHERE pool_id = $1 AND id = ANY($2)")
+        .bind(pool_id)
+        .bind(removed)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete downsampled swaps")?;
+
+    for (swap, swap_count) in aggregates {
+        sqlx::query(
+            r#"
+            INSERT INTO swaps (id, tx_hash, pool_id, amount0, amount1, fee, timestamp)
+            VALUES ($1, $2, $3, $4::NUMERIC, $5::NUMERIC, $6, $7)
+            "#,
+        )
+        .bind(swap.id)
+        .bind(&swap.tx_hash)
+        .bind(pool_id)
+        .bind(swap.amount0.to_string())
+        .bind(swap.amount1.to_string())
+        .bind(swap.fee)
+        .bind(swap.timestamp)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to insert minute aggregate")?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO swap_minute_aggregates (pool_id, minute, swap_count)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (pool_id, minute) DO UPDATE SET swap_count = EXCLUDED.swap_count
+            "#,
+        )
+        .bind(pool_id)
+        .bind(swap.timestamp)
+        .bind(swap_count)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to record minute aggregate count")?;
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE pool_swap_downsampling
+        SET downsampled_through = GREATEST(downsampled_through, $2)
+        WHERE pool_id = $1
+        "#,
+    )
+    .bind(pool_id)
+    .bind(through)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to advance downsampling")?;
+
+    tx.commit().await.context("Failed to commit downsampling")?;
+    Ok(())
+}
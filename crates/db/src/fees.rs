@@ -0,0 +1,224 @@
+use alloy::primitives::U256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use stillwater_models::{PoolFeeGrowth, PoolFeeOverride, PoolParam, PoolParamChange};
+
+// ============================================================================
+// Pool Fee Override Operations
+// ============================================================================
+
+/// Set the LP fee analytics assume for a pool (None if the pool is unknown)
+pub async fn set_pool_fee_override(
+    pool: &PgPool,
+    pool_id: &str,
+    fee: i32,
+    note: Option<&str>,
+) -> Result<Option<PoolFeeOverride>> {
+    let fee_override = sqlx::query_as::<_, PoolFeeOverride>(
+        r#"
+        INSERT INTO pool_fee_overrides (pool_id, fee, note)
+        SELECT pool_id, $2, $3 FROM pools WHERE pool_id = $1
+        ON CONFLICT (pool_id) DO UPDATE
+        SET fee = EXCLUDED.fee, note = EXCLUDED.note, updated_at = NOW()
+        RETURNING pool_id, fee, note, updated_at
+        "#,
+    )
+    .bind(pool_id)
+    .bind(fee)
+    .bind(note)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to set pool fee override")?;
+
+    Ok(fee_override)
+}
+
+/// Get every pool fee override
+pub async fn get_pool_fee_overrides(pool: &PgPool) -> Result<Vec<PoolFeeOverride>> {
+    let overrides = sqlx::query_as::<_, PoolFeeOverride>(
+        r#"
+        SELECT pool_id, fee, note, updated_at
+        FROM pool_fee_overrides
+        ORDER BY pool_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to get pool fee overrides")?;
+
+    Ok(overrides)
+}
+
+/// Remove a pool's fee override; false if it had none
+pub async fn delete_pool_fee_override(pool: &PgPool, pool_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM pool_fee_overrides WHERE pool_id = $1")
+        .bind(pool_id)
+        .execute(pool)
+        .await
+        .context("Failed to delete pool fee override")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// ============================================================================
+// Pool Fee Growth Operations
+// ============================================================================
+
+/// Record a sample of a pool's fee growth globals
+pub async fn insert_pool_fee_growth(pool: &PgPool, growth: &PoolFeeGrowth) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO pool_fee_growth
+            (pool_id, sampled_at, fee_growth_global0, fee_growth_global1, tick)
+        VALUES ($1, $2, $3::NUMERIC, $4::NUMERIC, $5)
+        ON CONFLICT (pool_id, sampled_at) DO NOTHING
+        "#,
+    )
+    .bind(&growth.pool_id)
+    .bind(growth.sampled_at)
+    .bind(growth.fee_growth_global0.to_string())
+    .bind(growth.fee_growth_global1.to_string())
+    .bind(growth.tick)
+    .execute(pool)
+    .await
+    .context("Failed to insert pool fee growth")?;
+
+    Ok(())
+}
+
+/// Get a pool's fee growth samples since a time, oldest first
+pub async fn get_pool_fee_growth(
+    pool: &PgPool,
+    pool_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<PoolFeeGrowth>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT pool_id, sampled_at, fee_growth_global0::text, fee_growth_global1::text, tick
+        FROM pool_fee_growth
+        WHERE pool_id = $1 AND sampled_at >= $2
+        ORDER BY sampled_at ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get pool fee growth")?;
+
+    Ok(rows
+        .iter()
+        .map(|r| {
+            let growth0: String = r.get(2);
+            let growth1: String = r.get(3);
+            PoolFeeGrowth {
+                pool_id: r.get(0),
+                sampled_at: r.get(1),
+                fee_growth_global0: U256::from_str_radix(&growth0, 10).unwrap_or_default(),
+                fee_growth_global1: U256::from_str_radix(&growth1, 10).unwrap_or_default(),
+                tick: r.get(4),
+            }
+        })
+        .collect())
+}
+
+// ============================================================================
+// Pool Parameter Change Operations
+// ============================================================================
+
+fn row_to_param_change(r: &PgRow) -> Result<PoolParamChange> {
+    let param: String = r.get("param");
+    Ok(PoolParamChange {
+        id: r.get("id"),
+        pool_id: r.get("pool_id"),
+        param: PoolParam::parse(&param).context("Invalid pool parameter")?,
+        old_value: r.get("old_value"),
+        new_value: r.get("new_value"),
+        detected_at: r.get("detected_at"),
+    })
+}
+
+/// Record a pool's LP and protocol fees as read from pool state, returning what changed
+///
+/// The first read of a pool only sets its baseline. Each change is kept in
+/// `pool_param_changes`. Nothing is recorded for an unknown pool.
+pub async fn update_pool_fees(
+    pool: &PgPool,
+    pool_id: &str,
+    lp_fee: i32,
+    protocol_fee: i32,
+) -> Result<Vec<PoolParamChange>> {
+    let mut tx = pool.begin().await.context("Failed to begin pool fee update")?;
+    let current: Option<(Option<i32>, i32)> =
+        sqlx::query_as("SELECT lp_fee, protocol_fee FROM pools WHERE pool_id = $1 FOR UPDATE")
+            .bind(pool_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to get pool fees")?;
+    let Some((old_lp_fee, old_protocol_fee)) = current else {
+        return Ok(vec![]);
+    };
+
+    sqlx::query("UPDATE pools SET lp_fee = $2, protocol_fee = $3 WHERE pool_id = $1")
+        .bind(pool_id)
+        .bind(lp_fee)
+        .bind(protocol_fee)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to update pool fees")?;
+
+    let mut changes = Vec::new();
+    if let Some(old_lp_fee) = old_lp_fee {
+        for (param, old_value, new_value) in [
+            (PoolParam::LpFee, old_lp_fee, lp_fee),
+            (PoolParam::ProtocolFee, old_protocol_fee, protocol_fee),
+        ] {
+            if old_value == new_value {
+                continue;
+            }
+            let row = sqlx::query(
+                r#"
+                INSERT INTO pool_param_changes (pool_id, param, old_value, new_value)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, pool_id, param, old_value, new_value, detected_at
+                "#,
+            )
+            .bind(pool_id)
+            .bind(param.as_str())
+            .bind(old_value)
+            .bind(new_value)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to record pool parameter change")?;
+            changes.push(row_to_param_change(&row)?);
+        }
+    }
+
+    tx.commit().await.context("Failed to commit pool fee update")?;
+    Ok(changes)
+}
+
+/// Get a pool's parameter changes, newest first
+pub async fn get_pool_param_changes(
+    pool: &PgPool,
+    pool_id: &str,
+    limit: i64,
+) -> Result<Vec<PoolParamChange>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, pool_id, param, old_value, new_value, detected_at
+        FROM pool_param_changes
+        WHERE pool_id = $1
+        ORDER BY detected_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(pool_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get pool parameter changes")?;
+
+    rows.iter().map(row_to_param_change).collect()
+}
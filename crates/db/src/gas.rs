@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_models::GasPriceSample;
+
+// ============================================================================
+// Gas Price Operations
+// ============================================================================
+
+/// Record a block's gas price; a block already sampled is left as is
+pub async fn insert_gas_price(pool: &PgPool, sample: &GasPriceSample) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO gas_prices
+            (chain_id, block_number, timestamp, base_fee_per_gas, priority_fee_per_gas)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (chain_id, block_number) DO NOTHING
+        "#,
+    )
+    .bind(sample.chain_id)
+    .bind(sample.block_number)
+    .bind(sample.timestamp)
+    .bind(sample.base_fee_per_gas)
+    .bind(sample.priority_fee_per_gas)
+    .execute(pool)
+    .await
+    .context("Failed to insert gas price")?;
+
+    Ok(())
+}
+
+/// A chain's gas prices sampled between two times, oldest first
+///
+/// Also returns the last sample before `from`, which still priced gas at the
+/// window's start.
+pub async fn get_gas_prices_between(
+    pool: &PgPool,
+    chain_id: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<GasPriceSample>> {
+    let samples = sqlx::query_as::<_, GasPriceSample>(
+        r#"
+        (
+            SELECT chain_id, block_number, timestamp, base_fee_per_gas, priority_fee_per_gas
+            FROM gas_prices
+            WHERE chain_id = $1 AND timestamp < $2
+            ORDER BY timestamp DESC
+            LIMIT 1
+        )
+        UNION ALL
+        (
+            SELECT chain_id, block_number, timestamp, base_fee_per_gas, priority_fee_per_gas
+            FROM gas_prices
+            WHERE chain_id = $1 AND timestamp >= $2 AND timestamp <= $3
+        )
+        ORDER BY timestamp ASC
+        "#,
+    )
+    .bind(chain_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get gas prices")?;
+
+    Ok(samples)
+}
+
+/// A chain's most recent gas price sample
+pub async fn get_latest_gas_price(pool: &PgPool, chain_id: i64) -> Result<Option<GasPriceSample>> {
+    let sample = sqlx::query_as::<_, GasPriceSample>(
+        r#"
+        SELECT chain_id, block_number, timestamp, base_fee_per_gas, priority_fee_per_gas
+        FROM gas_prices
+        WHERE chain_id = $1
+        ORDER BY timestamp DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(chain_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get latest gas price")?;
+
+    Ok(sample)
+}
@@ -0,0 +1,293 @@
+use alloy::primitives::U256;
+use anyhow::{Context, Result};
+use sqlx::{PgPool, Row};
+use stillwater_models::{
+    DbEvent, EVENTS_CHANNEL_PREFIX, PoolInitialization, WatchedPair, WatchedToken,
+};
+
+// ============================================================================
+// Pool Initialization Operations
+// ============================================================================
+
+/// Record a pool's creation (its pool row must exist), returning false if already recorded
+///
+/// A newly recorded creation is announced with a `PoolCreated` event.
+pub async fn record_pool_initialization(pool: &PgPool, init: &PoolInitialization) -> Result<bool> {
+    let event = DbEvent::PoolCreated {
+        pool_id: init.pool_id.clone(),
+        token0: init.token0.clone(),
+        token1: init.token1.clone(),
+        block: init.block_number,
+    };
+
+    let result = sqlx::query(
+        r#"
+        WITH inserted AS (
+            INSERT INTO pool_initializations
+                (pool_id, sqrt_price_x96, tick, block_number, tx_hash, created_at)
+            VALUES ($1, $2::numeric, $3, $4, $5, $6)
+            ON CONFLICT (pool_id) DO NOTHING
+            RETURNING pool_id
+        )
+        SELECT pg_notify($7 || current_schema(), $8) FROM inserted
+        "#,
+    )
+    .bind(&init.pool_id)
+    .bind(init.sqrt_price_x96.to_string())
+    .bind(init.tick)
+    .bind(init.block_number)
+    .bind(&init.tx_hash)
+    .bind(init.created_at)
+    .bind(EVENTS_CHANNEL_PREFIX)
+    .bind(event.to_payload())
+    .execute(pool)
+    .await
+    .context("Failed to record pool initialization")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get a pool's recorded creation, with its pool key from the pool row
+pub async fn get_pool_initialization(
+    pool: &PgPool,
+    pool_id: &str,
+) -> Result<Option<PoolInitialization>> {
+    let row = sqlx::query(
+        r#"
+        SELECT i.pool_id, p.token0, p.token1, p.fee_tier, p.tick_spacing, p.hooks,
+               i.sqrt_price_x96::text, i.tick, i.block_number, i.tx_hash, i.created_at
+        FROM pool_initializations i
+        JOIN pools p ON p.pool_id = i.pool_id
+        WHERE i.pool_id = $1
+        "#,
+    )
+    .bind(pool_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get pool initialization")?;
+
+    Ok(row.map(|r| {
+        let sqrt_price: String = r.get(6);
+        PoolInitialization {
+            pool_id: r.get(0),
+            token0: r.get(1),
+            token1: r.get(2),
+            fee_tier: r.get(3),
+            tick_spacing: r.get(4),
+            hooks: r.get(5),
+            sqrt_price_x96: U256::from_str_radix(&sqrt_price, 10).unwrap_or_default(),
+            tick: r.get(7),
+            block_number: r.get(8),
+            tx_hash: r.get(9),
+            created_at: r.get(10),
+        }
+    }))
+}
+
+// ============================================================================
+// Log Scan Cursor Operations
+// ============================================================================
+
+/// Last block a named log scan read up to, if it ever ran
+pub async fn get_log_scan_cursor(pool: &PgPool, name: &str) -> Result<Option<i64>> {
+    sqlx::query_scalar::<_, i64>("SELECT block_number FROM log_scan_cursors WHERE name = $1")
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to get log scan cursor")
+}
+
+/// Record the last block a named log scan read up to
+pub async fn set_log_scan_cursor(pool: &PgPool, name: &str, block_number: i64) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO log_scan_cursors (name, block_number)
+        VALUES ($1, $2)
+        ON CONFLICT (name) DO UPDATE
+        SET block_number = EXCLUDED.block_number, updated_at = NOW()
+        "#,
+    )
+    .bind(name)
+    .bind(block_number)
+    .execute(pool)
+    .await
+    .context("Failed to set log scan cursor")?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Token Watchlist Operations
+// ============================================================================
+
+/// Watch a token for new pools, replacing the sinks if it's already watched
+pub async fn watch_token(
+    pool: &PgPool,
+    owner: &str,
+    token: &str,
+    sinks: &[String],
+) -> Result<WatchedToken> {
+    sqlx::query_as::<_, WatchedToken>(
+        r#"
+        INSERT INTO token_watchlist (owner, token, sinks)
+        VALUES (LOWER($1), LOWER($2), $3)
+        ON CONFLICT (owner, token) DO UPDATE SET sinks = EXCLUDED.sinks
+        RETURNING owner, token, sinks, added_at
+        "#,
+    )
+    .bind(owner)
+    .bind(token)
+    .bind(sinks)
+    .fetch_one(pool)
+    .await
+    .context("Failed to watch token")
+}
+
+/// Stop watching a token, returning whether it was watched
+pub async fn unwatch_token(pool: &PgPool, owner: &str, token: &str) -> Result<bool> {
+    let result =
+        sqlx::query("DELETE FROM token_watchlist WHERE owner = LOWER($1) AND token = LOWER($2)")
+            .bind(owner)
+            .bind(token)
+            .execute(pool)
+            .await
+            .context("Failed to unwatch token")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the tokens an owner watches
+pub async fn get_watched_tokens(pool: &PgPool, owner: &str) -> Result<Vec<WatchedToken>> {
+    sqlx::query_as::<_, WatchedToken>(
+        r#"
+        SELECT owner, token, sinks, added_at
+        FROM token_watchlist
+        WHERE owner = LOWER($1)
+        ORDER BY added_at ASC
+        "#,
+    )
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get watched tokens")
+}
+
+/// Get every owner's watch on any of these tokens
+pub async fn get_token_watchers(pool: &PgPool, tokens: &[String]) -> Result<Vec<WatchedToken>> {
+    let tokens: Vec<String> = tokens.iter().map(|t| t.to_lowercase()).collect();
+    sqlx::query_as::<_, WatchedToken>(
+        r#"
+        SELECT owner, token, sinks, added_at
+        FROM token_watchlist
+        WHERE token = ANY($1)
+        ORDER BY owner, token
+        "#,
+    )
+    .bind(&tokens)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get token watchers")
+}
+
+// ============================================================================
+// Pair Watchlist Operations
+// ============================================================================
+
+// The pair watchlist is shared by every instance on the database (see
+// migration 043), so these queries name its schema.
+
+/// Watch a token pair for new pools, replacing its chains and sinks if it's already watched
+///
+/// The tokens may come in either order; they're stored in pool order.
+pub async fn watch_pair(
+    pool: &PgPool,
+    owner: &str,
+    token_a: &str,
+    token_b: &str,
+    chain_ids: &[i64],
+    sinks: &[String],
+) -> Result<WatchedPair> {
+    sqlx::query_as::<_, WatchedPair>(
+        r#"
+        INSERT INTO public.pair_watchlist (owner, token0, token1, chain_ids, sinks)
+        VALUES (LOWER($1), LEAST(LOWER($2), LOWER($3)), GREATEST(LOWER($2), LOWER($3)), $4, $5)
+        ON CONFLICT (owner, token0, token1) DO UPDATE
+        SET chain_ids = EXCLUDED.chain_ids, sinks = EXCLUDED.sinks
+        RETURNING owner, token0, token1, chain_ids, sinks, added_at
+        "#,
+    )
+    .bind(owner)
+    .bind(token_a)
+    .bind(token_b)
+    .bind(chain_ids)
+    .bind(sinks)
+    .fetch_one(pool)
+    .await
+    .context("Failed to watch pair")
+}
+
+/// Stop watching a token pair (in either order), returning whether it was watched
+pub async fn unwatch_pair(
+    pool: &PgPool,
+    owner: &str,
+    token_a: &str,
+    token_b: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM public.pair_watchlist
+        WHERE owner = LOWER($1)
+          AND token0 = LEAST(LOWER($2), LOWER($3))
+          AND token1 = GREATEST(LOWER($2), LOWER($3))
+        "#,
+    )
+    .bind(owner)
+    .bind(token_a)
+    .bind(token_b)
+    .execute(pool)
+    .await
+    .context("Failed to unwatch pair")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the token pairs an owner watches
+pub async fn get_watched_pairs(pool: &PgPool, owner: &str) -> Result<Vec<WatchedPair>> {
+    sqlx::query_as::<_, WatchedPair>(
+        r#"
+        SELECT owner, token0, token1, chain_ids, sinks, added_at
+        FROM public.pair_watchlist
+        WHERE owner = LOWER($1)
+        ORDER BY added_at ASC
+        "#,
+    )
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get watched pairs")
+}
+
+/// Get every owner's watch on a token pair that covers `chain_id`
+pub async fn get_pair_watchers(
+    pool: &PgPool,
+    token_a: &str,
+    token_b: &str,
+    chain_id: i64,
+) -> Result<Vec<WatchedPair>> {
+    sqlx::query_as::<_, WatchedPair>(
+        r#"
+        SELECT owner, token0, token1, chain_ids, sinks, added_at
+        FROM public.pair_watchlist
+        WHERE token0 = LEAST(LOWER($1), LOWER($2))
+          AND token1 = GREATEST(LOWER($1), LOWER($2))
+          AND (chain_ids = '{}' OR $3 = ANY(chain_ids))
+        ORDER BY owner
+        "#,
+    )
+    .bind(token_a)
+    .bind(token_b)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get pair watchers")
+}
@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use stillwater_models::{Job, JobKind, JobStatus};
+
+// ============================================================================
+// Job Queue Operations
+// ============================================================================
+
+const JOB_COLUMNS: &str = "id, kind, owner, params, status, attempts, result, error, \
+     progress, cursor, created_at, started_at, heartbeat_at, finished_at";
+
+fn row_to_job(r: &PgRow) -> Result<Job> {
+    let kind: String = r.get(1);
+    let status: String = r.get(4);
+
+    Ok(Job {
+        id: r.get(0),
+        kind: JobKind::parse(&kind).context("Invalid job kind")?,
+        owner: r.get(2),
+        params: r.get(3),
+        status: JobStatus::parse(&status).context("Invalid job status")?,
+        attempts: r.get(5),
+        result: r.get(6),
+        error: r.get(7),
+        progress: r.get(8),
+        cursor: r.get(9),
+        created_at: r.get(10),
+        started_at: r.get(11),
+        heartbeat_at: r.get(12),
+        finished_at: r.get(13),
+    })
+}
+
+/// Queue a job for a worker (owners are stored lowercase)
+pub async fn enqueue_job(
+    pool: &PgPool,
+    kind: JobKind,
+    owner: &str,
+    params: &serde_json::Value,
+) -> Result<Job> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO jobs (kind, owner, params)
+        VALUES ($1, LOWER($2), $3)
+        RETURNING {}
+        "#,
+        JOB_COLUMNS
+    ))
+    .bind(kind.as_str())
+    .bind(owner)
+    .bind(params)
+    .fetch_one(pool)
+    .await
+    .context("Failed to enqueue job")?;
+
+    row_to_job(&row)
+}
+
+/// Claim the oldest pending job, marking it running
+///
+/// Rows locked by another worker's claim are skipped rather than waited on,
+/// so concurrent workers never claim the same job. Returns None when the
+/// queue is empty.
+pub async fn claim_next_job(pool: &PgPool) -> Result<Option<Job>> {
+    let row = sqlx::query(&format!(
+        r#"
+        UPDATE jobs
+        SET status = 'running', attempts = attempts + 1, started_at = NOW(),
+            heartbeat_at = NOW(), error = NULL
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE status = 'pending'
+            ORDER BY created_at ASC, id ASC
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING {}
+        "#,
+        JOB_COLUMNS
+    ))
+    .fetch_optional(pool)
+    .await
+    .context("Failed to claim job")?;
+
+    row.as_ref().map(row_to_job).transpose()
+}
+
+/// Save how far a running job got and where to resume it from
+pub async fn update_job_progress(
+    pool: &PgPool,
+    id: i64,
+    progress: Decimal,
+    cursor: &serde_json::Value,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET progress = $2, cursor = $3, heartbeat_at = NOW()
+        WHERE id = $1 AND status = 'running'
+        "#,
+    )
+    .bind(id)
+    .bind(progress)
+    .bind(cursor)
+    .execute(pool)
+    .await
+    .context("Failed to update job progress")?;
+
+    Ok(())
+}
+
+/// Hand a running job back to the queue, e.g. when its worker shuts down
+///
+/// The claim doesn't count as an attempt, and its cursor is kept.
+pub async fn release_job(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'pending', attempts = GREATEST(attempts - 1, 0)
+        WHERE id = $1 AND status = 'running'
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .context("Failed to release job")?;
+
+    Ok(())
+}
+
+/// Record a running job's output
+///
+/// `attempt` is the job's `attempts` as claimed. Returns false, leaving the
+/// job alone, if that claim was lost, e.g. the job was requeued as stale and
+/// another worker claimed it again.
+pub async fn complete_job(
+    pool: &PgPool,
+    id: i64,
+    attempt: i32,
+    result: &serde_json::Value,
+) -> Result<bool> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'succeeded', result = $3, error = NULL, finished_at = NOW(),
+            progress = CASE WHEN progress IS NOT NULL THEN 100 END
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        "#,
+    )
+    .bind(id)
+    .bind(attempt)
+    .bind(result)
+    .execute(pool)
+    .await
+    .context("Failed to complete job")?;
+
+    Ok(updated.rows_affected() > 0)
+}
+
+/// Record why a running job failed
+///
+/// Like `complete_job`, returns false if the claim at `attempt` was lost.
+pub async fn fail_job(pool: &PgPool, id: i64, attempt: i32, error: &str) -> Result<bool> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'failed', error = $3, finished_at = NOW()
+        WHERE id = $1 AND status = 'running' AND attempts = $2
+        "#,
+    )
+    .bind(id)
+    .bind(attempt)
+    .bind(error)
+    .execute(pool)
+    .await
+    .context("Failed to mark job failed")?;
+
+    Ok(updated.rows_affected() > 0)
+}
+
+/// Put jobs whose worker stopped before finishing them back in the queue
+///
+/// Jobs without a claim or progress update since `stale_before` are requeued,
+/// or failed once they've been claimed `max_attempts` times. A requeued job
+/// keeps its cursor. Returns how many were touched.
+pub async fn requeue_stale_jobs(
+    pool: &PgPool,
+    stale_before: DateTime<Utc>,
+    max_attempts: i32,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = CASE WHEN attempts >= $2 THEN 'failed' ELSE 'pending' END,
+            error = 'Worker stopped before finishing the job',
+            finished_at = CASE WHEN attempts >= $2 THEN NOW() END
+        WHERE status = 'running' AND COALESCE(heartbeat_at, started_at) < $1
+        "#,
+    )
+    .bind(stale_before)
+    .bind(max_attempts)
+    .execute(pool)
+    .await
+    .context("Failed to requeue stale jobs")?;
+
+    Ok(result.rows_affected())
+}
+
+/// Get a job by ID
+pub async fn get_job(pool: &PgPool, id: i64) -> Result<Option<Job>> {
+    let row = sqlx::query(&format!("SELECT {} FROM jobs WHERE id = $1", JOB_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to get job")?;
+
+    row.as_ref().map(row_to_job).transpose()
+}
+
+/// Get the most recent jobs of some owners, newest first
+pub async fn get_jobs_for_owners(pool: &PgPool, owners: &[String], limit: i64) -> Result<Vec<Job>> {
+    let owners: Vec<String> = owners.iter().map(|o| o.to_lowercase()).collect();
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM jobs
+        WHERE owner = ANY($1)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+        JOB_COLUMNS
+    ))
+    .bind(&owners)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get jobs")?;
+
+    rows.iter().map(row_to_job).collect()
+}
@@ -1,45 +1,228 @@
+mod accounts;
+mod accumulators;
+mod alerts;
+mod archive;
+mod backtests;
+mod auth;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod downsampling;
+mod fees;
+mod gas;
+mod initializations;
+mod jobs;
+mod liquidity;
+mod notify;
+mod preferences;
+mod quality;
+mod query;
+mod reorg;
+mod retention;
+mod stream;
+mod sync;
+mod telegram;
+#[cfg(test)]
+mod testing;
+mod ticks;
+mod transfers;
+mod vaults;
+
 use alloy::primitives::{I256, U256};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use sqlx::{postgres::PgPoolOptions, PgPool, Row};
-use stillwater_models::{Pool, Position, PositionSnapshot, Swap};
+use log::LevelFilter;
+use rust_decimal::Decimal;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions, PgRow},
+    ConnectOptions, Executor, PgExecutor, PgPool, Postgres, QueryBuilder, Row,
+};
+use std::str::FromStr;
+use std::time::Duration;
+use stillwater_models::{
+    Address, DbEvent, GasExpense, Pool, PoolStats, Position, PositionHealthRow,
+    PositionSnapshot, SnapshotWindow, Swap, EVENTS_CHANNEL_PREFIX,
+};
+
+pub use accounts::*;
+pub use accumulators::*;
+pub use alerts::*;
+pub use archive::*;
+pub use backtests::*;
+pub use auth::*;
+#[cfg(feature = "chaos")]
+pub use chaos::*;
+pub use downsampling::*;
+pub use fees::*;
+pub use gas::*;
+pub use initializations::*;
+pub use jobs::*;
+pub use liquidity::*;
+pub use notify::*;
+pub use preferences::*;
+pub use quality::*;
+pub use query::*;
+pub use reorg::*;
+pub use retention::*;
+pub use stream::*;
+pub use sync::*;
+pub use telegram::*;
+pub use ticks::*;
+pub use transfers::*;
+pub use vaults::*;
 
 pub type DbPool = PgPool;
 
+/// Statements taking longer are logged as warnings unless `SLOW_QUERY_MS` is set
+pub const DEFAULT_SLOW_QUERY_MS: u64 = 1000;
+
 /// Create a PostgreSQL connection pool
+///
+/// Reads `DATABASE_URL` and the optional `DB_SCHEMA` from the environment.
+/// Built with the `chaos` feature, `CHAOS_DB_FAULTS` routes connections
+/// through a local fault-injecting proxy (see `spawn_chaos_proxy`) for
+/// resilience testing; release builds never read it.
 pub async fn get_pool() -> Result<PgPool> {
     let database_url = std::env::var("DATABASE_URL")
         .context("DATABASE_URL must be set in environment")?;
+    #[cfg(feature = "chaos")]
+    let database_url = match stillwater_models::FaultPlan::from_env("CHAOS_DB_FAULTS")? {
+        Some(plan) => chaos_database_url(&database_url, std::sync::Arc::new(plan)).await?,
+        None => database_url,
+    };
+
+    connect(&database_url, 10, schema_from_env()?.as_deref()).await
+}
+
+/// Read the optional `DB_SCHEMA` environment variable
+pub fn schema_from_env() -> Result<Option<String>> {
+    match std::env::var("DB_SCHEMA") {
+        Ok(schema) if !schema.trim().is_empty() => {
+            let schema = schema.trim().to_string();
+            validate_schema_name(&schema)?;
+            Ok(Some(schema))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Read the optional `SLOW_QUERY_MS`: how long a statement may take before it's logged
+///
+/// `0` turns slow statement warnings off.
+pub fn slow_query_threshold_from_env() -> Result<Option<Duration>> {
+    let ms = match std::env::var("SLOW_QUERY_MS") {
+        Ok(ms) => ms.trim().parse::<u64>().context("SLOW_QUERY_MS must be a number")?,
+        Err(_) => DEFAULT_SLOW_QUERY_MS,
+    };
+    Ok((ms > 0).then(|| Duration::from_millis(ms)))
+}
+
+/// Create a connection pool, optionally scoped to a Postgres schema
+///
+/// With a schema, every connection's `search_path` is set to that schema (then
+/// `public` for extensions), so all queries and migrations in this crate
+/// operate on the instance's own tables. This lets several stillwater
+/// instances (per chain or environment) share one database.
+///
+/// Statements slower than `SLOW_QUERY_MS` are logged as warnings (target
+/// `sqlx::query`); every statement is also emitted at debug level.
+pub async fn connect(
+    database_url: &str,
+    max_connections: u32,
+    schema: Option<&str>,
+) -> Result<PgPool> {
+    let mut options = PgPoolOptions::new().max_connections(max_connections);
+    let connect_options =
+        PgConnectOptions::from_str(database_url).context("Invalid database URL")?;
+    let connect_options = match slow_query_threshold_from_env()? {
+        Some(threshold) => connect_options.log_slow_statements(LevelFilter::Warn, threshold),
+        None => connect_options.log_slow_statements(LevelFilter::Off, Duration::MAX),
+    };
+
+    if let Some(schema) = schema {
+        validate_schema_name(schema)?;
+        let set_search_path = format!("SET search_path TO \"{}\", public", schema);
+        options = options.after_connect(move |conn, _meta| {
+            let set_search_path = set_search_path.clone();
+            Box::pin(async move {
+                conn.execute(set_search_path.as_str()).await?;
+                Ok(())
+            })
+        });
+    }
 
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&database_url)
+    let pool = options
+        .connect_with(connect_options)
         .await
         .context("Failed to create database connection pool")?;
 
+    if let Some(schema) = schema {
+        prepare_schema(&pool, schema).await?;
+    }
+
     Ok(pool)
 }
 
+/// Create the instance schema
+///
+/// TimescaleDB must already be installed in `public` by whoever set up the
+/// database: extensions are database-wide, and the initial migration would
+/// otherwise install it into the first instance's schema, hiding it from
+/// every other instance.
+async fn prepare_schema(pool: &PgPool, schema: &str) -> Result<()> {
+    sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS \"{}\"", schema))
+        .execute(pool)
+        .await
+        .context("Failed to create database schema")?;
+
+    Ok(())
+}
+
+/// Longest schema name whose events channel (`stillwater_events_<schema>`)
+/// still fits Postgres's 63-byte identifier limit
+const MAX_SCHEMA_NAME_LEN: usize = 63 - EVENTS_CHANNEL_PREFIX.len();
+
+/// Schema names are interpolated into SQL, so only allow plain identifiers
+fn validate_schema_name(schema: &str) -> Result<()> {
+    let valid = !schema.is_empty()
+        && schema.len() <= MAX_SCHEMA_NAME_LEN
+        && schema.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !schema.starts_with(|c: char| c.is_ascii_digit());
+
+    if !valid {
+        anyhow::bail!("Invalid database schema name: {}", schema);
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Pool Operations
 // ============================================================================
 
 /// Insert a new pool
-pub async fn insert_pool(pool: &PgPool, p: &Pool) -> Result<()> {
+pub async fn insert_pool(executor: impl PgExecutor<'_>, p: &Pool) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO pools (pool_id, token0, token1, fee_tier, tick_spacing, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        ON CONFLICT (pool_id) DO NOTHING
+        INSERT INTO pools (pool_id, token0, token1, token0_decimals, token1_decimals, fee_tier, tick_spacing, hooks, protocol_fee, created_at, created_at_block)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (pool_id) DO UPDATE
+        SET token0_decimals = EXCLUDED.token0_decimals,
+            token1_decimals = EXCLUDED.token1_decimals,
+            created_at = COALESCE(EXCLUDED.created_at, pools.created_at),
+            created_at_block = COALESCE(EXCLUDED.created_at_block, pools.created_at_block)
         "#,
     )
     .bind(&p.pool_id)
     .bind(&p.token0)
     .bind(&p.token1)
+    .bind(p.token0_decimals)
+    .bind(p.token1_decimals)
     .bind(p.fee_tier)
     .bind(p.tick_spacing)
+    .bind(&p.hooks)
+    .bind(p.protocol_fee)
     .bind(p.created_at)
-    .execute(pool)
+    .bind(p.created_at_block)
+    .execute(executor)
     .await
     .context("Failed to insert pool")?;
 
@@ -50,8 +233,10 @@ pub async fn insert_pool(pool: &PgPool, p: &Pool) -> Result<()> {
 pub async fn get_pool_by_id(pool: &PgPool, pool_id: &str) -> Result<Option<Pool>> {
     let result = sqlx::query_as::<_, Pool>(
         r#"
-        SELECT pool_id, token0, token1, fee_tier, tick_spacing, created_at
+        SELECT pool_id, token0, token1, token0_decimals, token1_decimals, fee_tier, tick_spacing,
+               hooks, protocol_fee, created_at, created_at_block, o.fee AS fee_override
         FROM pools
+        LEFT JOIN pool_fee_overrides o USING (pool_id)
         WHERE pool_id = $1
         "#,
     )
@@ -63,188 +248,663 @@ pub async fn get_pool_by_id(pool: &PgPool, pool_id: &str) -> Result<Option<Pool>
     Ok(result)
 }
 
+/// Get pools with either token among `tokens` (matched case-insensitively)
+pub async fn get_pools_with_tokens(pool: &PgPool, tokens: &[String]) -> Result<Vec<Pool>> {
+    let tokens: Vec<String> = tokens.iter().map(|t| t.to_lowercase()).collect();
+    let pools = sqlx::query_as::<_, Pool>(
+        r#"
+        SELECT pool_id, token0, token1, token0_decimals, token1_decimals, fee_tier, tick_spacing,
+               hooks, protocol_fee, created_at, created_at_block, o.fee AS fee_override
+        FROM pools
+        LEFT JOIN pool_fee_overrides o USING (pool_id)
+        WHERE LOWER(token0) = ANY($1) OR LOWER(token1) = ANY($1)
+        ORDER BY pool_id
+        "#,
+    )
+    .bind(&tokens)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get pools by token")?;
+
+    Ok(pools)
+}
+
+/// Swap activity since `since` and position counts for a pool (None if the pool is unknown)
+///
+/// Minute aggregates of downsampled pools count as the swaps they stand for.
+pub async fn get_pool_stats(
+    pool: &PgPool,
+    pool_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Option<PoolStats>> {
+    let row = sqlx::query(
+        r#"
+        SELECT p.pool_id,
+               (SELECT COUNT(*) FROM swaps s WHERE s.pool_id = p.pool_id AND s.timestamp >= $2)
+                 + (SELECT COALESCE(SUM(m.swap_count - 1), 0) FROM swap_minute_aggregates m
+                    WHERE m.pool_id = p.pool_id AND m.minute >= $2),
+               (SELECT COALESCE(SUM(ABS(s.amount0)), 0)::text FROM swaps s
+                WHERE s.pool_id = p.pool_id AND s.timestamp >= $2),
+               (SELECT COALESCE(SUM(ABS(s.amount1)), 0)::text FROM swaps s
+                WHERE s.pool_id = p.pool_id AND s.timestamp >= $2),
+               (SELECT MAX(s.timestamp) FROM swaps s WHERE s.pool_id = p.pool_id),
+               (SELECT COUNT(*) FROM positions pos
+                WHERE pos.pool_id = p.pool_id AND pos.deleted_at IS NULL),
+               (SELECT COUNT(*) FROM positions pos
+                WHERE pos.pool_id = p.pool_id AND pos.deleted_at IS NULL AND pos.liquidity > 0)
+        FROM pools p
+        WHERE p.pool_id = $1
+        "#,
+    )
+    .bind(pool_id)
+    .bind(since)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get pool stats")?;
+
+    Ok(row.map(|r| PoolStats {
+        pool_id: r.get(0),
+        since,
+        swap_count: r.get(1),
+        volume0: r.get(2),
+        volume1: r.get(3),
+        last_swap_at: r.get(4),
+        positions: r.get(5),
+        open_positions: r.get(6),
+    }))
+}
+
 // ============================================================================
 // Position Operations
 // ============================================================================
 
-/// Insert a new position
-pub async fn insert_position(pool: &PgPool, pos: &Position) -> Result<()> {
+/// Columns selected for every position query, in `row_to_position` order
+const POSITION_COLUMNS: &str =
+    "id, nft_id, owner, pool_id, tick_lower, tick_upper, liquidity::text, created_at, manual, \
+     closed_at, archived_at";
+
+/// Number of `POSITION_COLUMNS`; columns selected after them start at this index
+const POSITION_COLUMN_COUNT: usize = 11;
+
+/// `POSITION_COLUMNS` qualified with a table alias, for queries joining positions
+fn position_columns(alias: &str) -> String {
+    POSITION_COLUMNS
+        .split(", ")
+        .map(|column| format!("{}.{}", alias, column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn row_to_position(r: &PgRow) -> Position {
+    let liquidity_str: String = r.get(6);
+    Position {
+        id: r.get(0),
+        nft_id: r.get(1),
+        owner: r.get(2),
+        pool_id: r.get(3),
+        tick_lower: r.get(4),
+        tick_upper: r.get(5),
+        liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
+        created_at: r.get(7),
+        manual: r.get(8),
+        closed_at: r.get(9),
+        archived_at: r.get(10),
+    }
+}
+
+/// Open/closed state of a position, derived from its liquidity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStatus {
+    /// Liquidity > 0
+    Open,
+    /// Liquidity fully removed
+    Closed,
+    /// Moved out of default listings by the archival job
+    Archived,
+}
+
+/// Sort order for position queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionSort {
+    #[default]
+    CreatedAtDesc,
+    CreatedAtAsc,
+    LiquidityDesc,
+    LiquidityAsc,
+}
+
+impl PositionStatus {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(PositionStatus::Open),
+            "closed" => Some(PositionStatus::Closed),
+            "archived" => Some(PositionStatus::Archived),
+            _ => None,
+        }
+    }
+}
+
+impl PositionSort {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "created_at_desc" => Some(PositionSort::CreatedAtDesc),
+            "created_at_asc" => Some(PositionSort::CreatedAtAsc),
+            "liquidity_desc" => Some(PositionSort::LiquidityDesc),
+            "liquidity_asc" => Some(PositionSort::LiquidityAsc),
+            _ => None,
+        }
+    }
+
+    fn order_by(&self) -> &'static str {
+        match self {
+            PositionSort::CreatedAtDesc => "created_at DESC, id DESC",
+            PositionSort::CreatedAtAsc => "created_at ASC, id ASC",
+            PositionSort::LiquidityDesc => "liquidity DESC, id DESC",
+            PositionSort::LiquidityAsc => "liquidity ASC, id ASC",
+        }
+    }
+}
+
+/// Filter for `find_positions`; unset fields don't constrain the query
+#[derive(Debug, Clone, Default)]
+pub struct PositionFilter {
+    /// Only positions held by one of these owners (none for an empty list)
+    pub owners: Option<Vec<Address>>,
+    pub pool_id: Option<String>,
+    /// Only positions whose range overlaps [lower, upper)
+    pub tick_range: Option<(i32, i32)>,
+    pub min_liquidity: Option<U256>,
+    pub max_liquidity: Option<U256>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub status: Option<PositionStatus>,
+    /// Also match archived positions (implied by `status: Archived`)
+    pub include_archived: bool,
+    pub sort: PositionSort,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Find positions matching a filter
+pub async fn find_positions(pool: &PgPool, filter: &PositionFilter) -> Result<Vec<Position>> {
+    let rows = position_query(filter)
+        .build()
+        .fetch_all(pool)
+        .await
+        .context("Failed to find positions")?;
+
+    Ok(rows.iter().map(row_to_position).collect())
+}
+
+/// Query selecting the positions matching a filter, in the filter's order
+fn position_query(filter: &PositionFilter) -> QueryBuilder<'_, Postgres> {
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+        "SELECT {} FROM positions WHERE deleted_at IS NULL",
+        POSITION_COLUMNS
+    ));
+
+    if let Some(owners) = &filter.owners {
+        qb.push(" AND owner = ANY(").push_bind(owners.as_slice()).push(")");
+    }
+    if let Some(pool_id) = &filter.pool_id {
+        qb.push(" AND pool_id = ").push_bind(pool_id);
+    }
+    if let Some((lower, upper)) = filter.tick_range {
+        qb.push(" AND tick_lower < ").push_bind(upper);
+        qb.push(" AND tick_upper > ").push_bind(lower);
+    }
+    if let Some(min) = filter.min_liquidity {
+        qb.push(" AND liquidity >= ").push_bind(min.to_string()).push("::numeric");
+    }
+    if let Some(max) = filter.max_liquidity {
+        qb.push(" AND liquidity <= ").push_bind(max.to_string()).push("::numeric");
+    }
+    if let Some(after) = filter.created_after {
+        qb.push(" AND created_at >= ").push_bind(after);
+    }
+    if let Some(before) = filter.created_before {
+        qb.push(" AND created_at < ").push_bind(before);
+    }
+    match filter.status {
+        Some(PositionStatus::Open) => {
+            qb.push(" AND liquidity > 0");
+        }
+        Some(PositionStatus::Closed) => {
+            qb.push(" AND liquidity = 0");
+        }
+        Some(PositionStatus::Archived) => {
+            qb.push(" AND archived_at IS NOT NULL");
+        }
+        None => {}
+    }
+    if !filter.include_archived && filter.status != Some(PositionStatus::Archived) {
+        qb.push(" AND archived_at IS NULL");
+    }
+
+    qb.push(" ORDER BY ").push(filter.sort.order_by());
+
+    if let Some(limit) = filter.limit {
+        qb.push(" LIMIT ").push_bind(limit);
+    }
+    if let Some(offset) = filter.offset {
+        qb.push(" OFFSET ").push_bind(offset);
+    }
+    qb
+}
+
+/// Insert a new position, returning false if its nft_id already exists
+///
+/// A new position is announced with a `PositionInserted` event. One that
+/// arrives already withdrawn counts as closed from now.
+pub async fn insert_position(executor: impl PgExecutor<'_>, pos: &Position) -> Result<bool> {
     let liquidity_str = pos.liquidity.to_string();
+    let event = DbEvent::PositionInserted {
+        nft_id: pos.nft_id.clone(),
+        owner: pos.owner.canonical(),
+        pool_id: pos.pool_id.clone(),
+    };
 
-    sqlx::query(
+    let result = sqlx::query(
         r#"
-        INSERT INTO positions (nft_id, owner, pool_id, tick_lower, tick_upper, liquidity, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6::numeric, $7)
-        ON CONFLICT (nft_id) DO NOTHING
+        WITH inserted AS (
+            INSERT INTO positions
+                (nft_id, owner, pool_id, tick_lower, tick_upper, liquidity, created_at, manual, closed_at)
+            VALUES ($1, $2, $3, $4, $5, $6::numeric, $7, $8, CASE WHEN $6::numeric = 0 THEN NOW() END)
+            ON CONFLICT (nft_id) DO NOTHING
+            RETURNING id
+        )
+        SELECT pg_notify($9 || current_schema(), $10) FROM inserted
         "#,
     )
     .bind(&pos.nft_id)
-    .bind(&pos.owner)
+    .bind(pos.owner)
     .bind(&pos.pool_id)
     .bind(pos.tick_lower)
     .bind(pos.tick_upper)
     .bind(&liquidity_str)
     .bind(pos.created_at)
-    .execute(pool)
+    .bind(pos.manual)
+    .bind(EVENTS_CHANNEL_PREFIX)
+    .bind(event.to_payload())
+    .execute(executor)
     .await
     .context("Failed to insert position")?;
 
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set aside a position whose tick range can't be analyzed
+pub async fn quarantine_position(
+    executor: impl PgExecutor<'_>,
+    pos: &Position,
+    reason: &str,
+) -> Result<()> {
+    let liquidity_str = pos.liquidity.to_string();
+
+    sqlx::query(
+        r#"
+        INSERT INTO quarantined_positions (nft_id, owner, pool_id, tick_lower, tick_upper, liquidity, created_at, reason)
+        VALUES ($1, $2, $3, $4, $5, $6::numeric, $7, $8)
+        ON CONFLICT (nft_id) DO UPDATE
+        SET tick_lower = EXCLUDED.tick_lower,
+            tick_upper = EXCLUDED.tick_upper,
+            liquidity = EXCLUDED.liquidity,
+            reason = EXCLUDED.reason,
+            quarantined_at = NOW()
+        "#,
+    )
+    .bind(&pos.nft_id)
+    .bind(pos.owner)
+    .bind(&pos.pool_id)
+    .bind(pos.tick_lower)
+    .bind(pos.tick_upper)
+    .bind(&liquidity_str)
+    .bind(pos.created_at)
+    .bind(reason)
+    .execute(executor)
+    .await
+    .context("Failed to quarantine position")?;
+
     Ok(())
 }
 
 /// Get a position by database ID
 pub async fn get_position_by_id(pool: &PgPool, id: i64) -> Result<Option<Position>> {
-    let row = sqlx::query(
+    let row = sqlx::query(&format!(
         r#"
-        SELECT id, nft_id, owner, pool_id, tick_lower, tick_upper, liquidity::text, created_at
+        SELECT {}
         FROM positions
-        WHERE id = $1
+        WHERE id = $1 AND deleted_at IS NULL
         "#,
-    )
+        POSITION_COLUMNS
+    ))
     .bind(id)
     .fetch_optional(pool)
     .await
     .context("Failed to get position by ID")?;
 
-    Ok(row.map(|r| {
-        let liquidity_str: String = r.get(6);
-        Position {
-            id: r.get(0),
-            nft_id: r.get(1),
-            owner: r.get(2),
-            pool_id: r.get(3),
-            tick_lower: r.get(4),
-            tick_upper: r.get(5),
-            liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
-            created_at: r.get(7),
-        }
-    }))
+    Ok(row.as_ref().map(row_to_position))
 }
 
 /// Get a position by NFT ID
-pub async fn get_position_by_nft(pool: &PgPool, nft_id: &str) -> Result<Option<Position>> {
-    let row = sqlx::query(
+pub async fn get_position_by_nft(
+    executor: impl PgExecutor<'_>,
+    nft_id: &str,
+) -> Result<Option<Position>> {
+    let row = sqlx::query(&format!(
         r#"
-        SELECT id, nft_id, owner, pool_id, tick_lower, tick_upper, liquidity::text, created_at
+        SELECT {}
         FROM positions
-        WHERE nft_id = $1
+        WHERE nft_id = $1 AND deleted_at IS NULL
         "#,
-    )
+        POSITION_COLUMNS
+    ))
     .bind(nft_id)
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await
     .context("Failed to get position by NFT ID")?;
 
-    Ok(row.map(|r| {
-        let liquidity_str: String = r.get(6);
-        Position {
-            id: r.get(0),
-            nft_id: r.get(1),
-            owner: r.get(2),
-            pool_id: r.get(3),
-            tick_lower: r.get(4),
-            tick_upper: r.get(5),
-            liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
-            created_at: r.get(7),
-        }
-    }))
+    Ok(row.as_ref().map(row_to_position))
 }
 
 /// Get all positions for an owner
-pub async fn get_positions_by_owner(pool: &PgPool, owner: &str) -> Result<Vec<Position>> {
-    let rows = sqlx::query(
+pub async fn get_positions_by_owner(pool: &PgPool, owner: &Address) -> Result<Vec<Position>> {
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT id, nft_id, owner, pool_id, tick_lower, tick_upper, liquidity::text, created_at
+        SELECT {}
         FROM positions
-        WHERE owner = $1
+        WHERE owner = $1 AND deleted_at IS NULL AND archived_at IS NULL
         ORDER BY created_at DESC
         "#,
-    )
+        POSITION_COLUMNS
+    ))
     .bind(owner)
     .fetch_all(pool)
     .await
     .context("Failed to get positions by owner")?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| {
-            let liquidity_str: String = r.get(6);
-            Position {
-                id: r.get(0),
-                nft_id: r.get(1),
-                owner: r.get(2),
-                pool_id: r.get(3),
-                tick_lower: r.get(4),
-                tick_upper: r.get(5),
-                liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
-                created_at: r.get(7),
-            }
-        })
-        .collect())
+    Ok(rows.iter().map(row_to_position).collect())
 }
 
 /// Get all positions in a pool
 pub async fn get_positions_by_pool(pool: &PgPool, pool_id: &str) -> Result<Vec<Position>> {
-    let rows = sqlx::query(
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT id, nft_id, owner, pool_id, tick_lower, tick_upper, liquidity::text, created_at
+        SELECT {}
         FROM positions
-        WHERE pool_id = $1
+        WHERE pool_id = $1 AND deleted_at IS NULL AND archived_at IS NULL
         ORDER BY created_at DESC
         "#,
-    )
+        POSITION_COLUMNS
+    ))
     .bind(pool_id)
     .fetch_all(pool)
     .await
     .context("Failed to get positions by pool")?;
 
+    Ok(rows.iter().map(row_to_position).collect())
+}
+
+/// Get the open positions alerts are checked for, ordered by pool
+///
+/// Those of watched owners, plus those of owners named by an enabled alert rule.
+pub async fn get_alerting_open_positions(pool: &PgPool) -> Result<Vec<Position>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM positions p
+        WHERE p.liquidity > 0 AND p.deleted_at IS NULL
+          AND (
+              EXISTS (SELECT 1 FROM watchlist w WHERE w.address = p.owner)
+              OR EXISTS (SELECT 1 FROM alert_rules r WHERE r.enabled AND r.owner = p.owner)
+          )
+        ORDER BY p.pool_id, p.id
+        "#,
+        position_columns("p")
+    ))
+    .fetch_all(pool)
+    .await
+    .context("Failed to get alerting open positions")?;
+
+    Ok(rows.iter().map(row_to_position).collect())
+}
+
+/// Read what health is evaluated from for many open positions in one query
+///
+/// Covers `owners`' open positions, or the alerting ones (see
+/// `get_alerting_open_positions`) without an owner. Each row carries its pool's
+/// latest tick, its weighted entry tick (as `weighted_entry` takes it from the
+/// range's deposits), accumulated fees and the earliest snapshot since
+/// `snapshots_since`. Ordered by pool.
+pub async fn get_position_health_rows(
+    pool: &PgPool,
+    owners: Option<&[Address]>,
+    snapshots_since: DateTime<Utc>,
+) -> Result<Vec<PositionHealthRow>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        WITH targets AS (
+            SELECT p.id, p.nft_id, p.owner, p.pool_id, p.tick_lower, p.tick_upper, p.liquidity,
+                   p.created_at, p.manual, p.closed_at, p.archived_at
+            FROM positions p
+            WHERE p.liquidity > 0 AND p.deleted_at IS NULL
+              AND CASE
+                  WHEN $1::text[] IS NOT NULL THEN p.owner = ANY($1)
+                  ELSE EXISTS (SELECT 1 FROM watchlist w WHERE w.address = p.owner)
+                      OR EXISTS (
+                          SELECT 1 FROM alert_rules r WHERE r.enabled AND r.owner = p.owner
+                      )
+              END
+        ),
+        pool_ticks AS (
+            SELECT t.pool_id,
+                   CASE
+                       WHEN g.sampled_at IS NOT NULL
+                            AND (s.timestamp IS NULL OR g.sampled_at >= s.timestamp) THEN g.tick
+                       WHEN s.timestamp IS NOT NULL
+                           THEN ROUND(LN(ABS(s.amount1 / s.amount0)) / LN(1.0001))::int
+                   END AS tick
+            FROM (SELECT DISTINCT pool_id FROM targets) t
+            LEFT JOIN LATERAL (
+                SELECT tick, sampled_at FROM pool_fee_growth
+                WHERE pool_id = t.pool_id
+                ORDER BY sampled_at DESC LIMIT 1
+            ) g ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT amount0, amount1, timestamp FROM swaps
+                WHERE pool_id = t.pool_id AND amount0 <> 0 AND amount1 <> 0
+                ORDER BY timestamp DESC LIMIT 1
+            ) s ON TRUE
+        ),
+        range_events AS (
+            SELECT t.id AS position_id, e.liquidity_delta, e.price,
+                   ROW_NUMBER() OVER w AS seq,
+                   SUM(e.liquidity_delta) OVER w AS held
+            FROM targets t
+            JOIN liquidity_events e
+              ON e.pool_id = t.pool_id AND e.tick_lower = t.tick_lower
+             AND e.tick_upper = t.tick_upper AND (e.owner = t.owner OR e.position_id = t.id)
+             AND e.timestamp <= NOW()
+            WINDOW w AS (PARTITION BY t.id ORDER BY e.timestamp, e.event_id)
+        ),
+        emptied AS (
+            SELECT position_id, MAX(seq) AS seq FROM range_events WHERE held <= 0
+            GROUP BY position_id
+        ),
+        entries AS (
+            SELECT r.position_id,
+                   ROUND(SUM(r.liquidity_delta * LN(r.price)) / SUM(r.liquidity_delta)
+                         / LN(1.0001))::int AS entry_tick
+            FROM range_events r
+            LEFT JOIN emptied x ON x.position_id = r.position_id
+            WHERE r.liquidity_delta > 0 AND r.price > 0 AND r.seq > COALESCE(x.seq, 0)
+            GROUP BY r.position_id
+        )
+        SELECT {},
+               pt.tick, en.entry_tick, a.fees_earned::text, snap.price::text, snap.timestamp
+        FROM targets t
+        LEFT JOIN pool_ticks pt ON pt.pool_id = t.pool_id
+        LEFT JOIN entries en ON en.position_id = t.id
+        LEFT JOIN position_fee_accumulators a ON a.position_id = t.id
+        LEFT JOIN LATERAL (
+            SELECT price, timestamp FROM position_snapshots
+            WHERE position_id = t.id AND timestamp >= $2
+            ORDER BY timestamp LIMIT 1
+        ) snap ON TRUE
+        ORDER BY t.pool_id, t.id
+        "#,
+        position_columns("t")
+    ))
+    .bind(owners)
+    .bind(snapshots_since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get position health rows")?;
+
     Ok(rows
-        .into_iter()
+        .iter()
         .map(|r| {
-            let liquidity_str: String = r.get(6);
-            Position {
-                id: r.get(0),
-                nft_id: r.get(1),
-                owner: r.get(2),
-                pool_id: r.get(3),
-                tick_lower: r.get(4),
-                tick_upper: r.get(5),
-                liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
-                created_at: r.get(7),
+            let fees: Option<String> = r.get(POSITION_COLUMN_COUNT + 2);
+            let price: Option<String> = r.get(POSITION_COLUMN_COUNT + 3);
+            let taken_at: Option<DateTime<Utc>> = r.get(POSITION_COLUMN_COUNT + 4);
+            PositionHealthRow {
+                position: row_to_position(r),
+                current_tick: r.get(POSITION_COLUMN_COUNT),
+                entry_tick: r.get(POSITION_COLUMN_COUNT + 1),
+                fees_earned: fees.and_then(|f| Decimal::from_str(&f).ok()).unwrap_or_default(),
+                earlier_price: price.and_then(|p| Decimal::from_str(&p).ok()).zip(taken_at),
             }
         })
         .collect())
 }
 
+/// Hide a position held by one of `owners` from every read, returning false if there was none
+///
+/// The row and its history stay until the retention job purges it, so the
+/// position can be restored until then.
+pub async fn soft_delete_position(
+    pool: &PgPool,
+    owners: &[Address],
+    nft_id: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE positions SET deleted_at = NOW()
+        WHERE nft_id = $1 AND owner = ANY($2) AND deleted_at IS NULL
+        "#,
+    )
+    .bind(nft_id)
+    .bind(owners)
+    .execute(pool)
+    .await
+    .context("Failed to soft-delete position")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Undo `soft_delete_position`, returning false if the position isn't deleted (or was purged)
+pub async fn restore_position(pool: &PgPool, owners: &[Address], nft_id: &str) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE positions SET deleted_at = NULL
+        WHERE nft_id = $1 AND owner = ANY($2) AND deleted_at IS NOT NULL
+        "#,
+    )
+    .bind(nft_id)
+    .bind(owners)
+    .execute(pool)
+    .await
+    .context("Failed to restore position")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Archive positions closed before `closed_before`, returning how many were archived
+///
+/// Archived positions keep their history and stay reachable by ID, but
+/// listings skip them unless asked to include them.
+pub async fn archive_stale_positions(pool: &PgPool, closed_before: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE positions SET archived_at = NOW()
+        WHERE liquidity = 0 AND closed_at < $1 AND archived_at IS NULL AND deleted_at IS NULL
+        "#,
+    )
+    .bind(closed_before)
+    .execute(pool)
+    .await
+    .context("Failed to archive stale positions")?;
+
+    Ok(result.rows_affected())
+}
+
 // ============================================================================
 // Swap Operations
 // ============================================================================
 
-/// Insert a new swap
-pub async fn insert_swap(pool: &PgPool, swap: &Swap) -> Result<()> {
+/// Insert a new swap, announcing it with a `SwapInserted` event
+///
+/// A swap without a fee takes its pool's LP fee as last read from pool state
+/// (`pools.lp_fee`), so dynamic-fee models price it at the fee actually in
+/// force rather than a fallback; it stays unknown until that state is read.
+pub async fn insert_swap(executor: impl PgExecutor<'_>, swap: &Swap) -> Result<()> {
     let amount0_str = swap.amount0.to_string();
     let amount1_str = swap.amount1.to_string();
+    let event = DbEvent::SwapInserted {
+        pool_id: swap.pool_id.clone(),
+        tx_hash: swap.tx_hash.clone(),
+        timestamp: swap.timestamp,
+    };
 
     sqlx::query(
         r#"
-        INSERT INTO swaps (tx_hash, pool_id, amount0, amount1, timestamp)
-        VALUES ($1, $2, $3::numeric, $4::numeric, $5)
-        ON CONFLICT (tx_hash, pool_id) DO NOTHING
+        WITH inserted AS (
+            INSERT INTO swaps (tx_hash, pool_id, amount0, amount1, fee, timestamp)
+            VALUES (
+                $1, $2, $3::numeric, $4::numeric,
+                COALESCE($5, (SELECT lp_fee FROM pools WHERE pool_id = $2)),
+                $6
+            )
+            ON CONFLICT (tx_hash, pool_id) DO NOTHING
+            RETURNING id
+        )
+        SELECT pg_notify($7 || current_schema(), $8) FROM inserted
         "#,
     )
     .bind(&swap.tx_hash)
     .bind(&swap.pool_id)
     .bind(&amount0_str)
     .bind(&amount1_str)
+    .bind(swap.fee)
     .bind(swap.timestamp)
-    .execute(pool)
+    .bind(EVENTS_CHANNEL_PREFIX)
+    .bind(event.to_payload())
+    .execute(executor)
     .await
     .context("Failed to insert swap")?;
 
     Ok(())
 }
 
+/// Map a row of `id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp`
+pub(crate) fn row_to_swap(r: &PgRow) -> Swap {
+    let amount0_str: String = r.get(3);
+    let amount1_str: String = r.get(4);
+    Swap {
+        id: r.get(0),
+        tx_hash: r.get(1),
+        pool_id: r.get(2),
+        amount0: amount0_str.parse::<I256>().unwrap_or_default(),
+        amount1: amount1_str.parse::<I256>().unwrap_or_default(),
+        fee: r.get(5),
+        timestamp: r.get(6),
+    }
+}
+
 /// Get swaps for a pool since a specific timestamp
+///
+/// Reads both the hot table and the archive; archive partitions outside the
+/// window are pruned, so recent windows don't touch cold data.
 pub async fn get_swaps_for_pool(
     pool: &PgPool,
     pool_id: &str,
@@ -252,9 +912,13 @@ pub async fn get_swaps_for_pool(
 ) -> Result<Vec<Swap>> {
     let rows = sqlx::query(
         r#"
-        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, timestamp
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
         FROM swaps
         WHERE pool_id = $1 AND timestamp >= $2
+        UNION ALL
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps_archive
+        WHERE pool_id = $1 AND timestamp >= $2
         ORDER BY timestamp ASC
         "#,
     )
@@ -264,21 +928,130 @@ pub async fn get_swaps_for_pool(
     .await
     .context("Failed to get swaps for pool")?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| {
-            let amount0_str: String = r.get(3);
-            let amount1_str: String = r.get(4);
-            Swap {
-                id: r.get(0),
-                tx_hash: r.get(1),
-                pool_id: r.get(2),
-                amount0: amount0_str.parse::<I256>().unwrap_or_default(),
-                amount1: amount1_str.parse::<I256>().unwrap_or_default(),
-                timestamp: r.get(5),
-            }
-        })
-        .collect())
+    Ok(rows.iter().map(row_to_swap).collect())
+}
+
+/// Get swaps for a pool in [start, end], across the hot table and the archive
+pub async fn get_swaps_for_pool_between(
+    pool: &PgPool,
+    pool_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Swap>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps
+        WHERE pool_id = $1 AND timestamp >= $2 AND timestamp <= $3
+        UNION ALL
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps_archive
+        WHERE pool_id = $1 AND timestamp >= $2 AND timestamp <= $3
+        ORDER BY timestamp ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get swaps for pool")?;
+
+    Ok(rows.iter().map(row_to_swap).collect())
+}
+
+/// Get a pool's last swap before `before`, across the hot table and the archive
+pub async fn get_last_swap_before(
+    pool: &PgPool,
+    pool_id: &str,
+    before: DateTime<Utc>,
+) -> Result<Option<Swap>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM (
+            (SELECT id, tx_hash, pool_id, amount0, amount1, fee, timestamp
+             FROM swaps
+             WHERE pool_id = $1 AND timestamp < $2
+             ORDER BY timestamp DESC
+             LIMIT 1)
+            UNION ALL
+            (SELECT id, tx_hash, pool_id, amount0, amount1, fee, timestamp
+             FROM swaps_archive
+             WHERE pool_id = $1 AND timestamp < $2
+             ORDER BY timestamp DESC
+             LIMIT 1)
+        ) latest
+        ORDER BY timestamp DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(pool_id)
+    .bind(before)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get last swap")?;
+
+    Ok(row.map(|r| {
+        let amount0_str: String = r.get(3);
+        let amount1_str: String = r.get(4);
+        Swap {
+            id: r.get(0),
+            tx_hash: r.get(1),
+            pool_id: r.get(2),
+            amount0: amount0_str.parse::<I256>().unwrap_or_default(),
+            amount1: amount1_str.parse::<I256>().unwrap_or_default(),
+            fee: r.get(5),
+            timestamp: r.get(6),
+        }
+    }))
+}
+
+// ============================================================================
+// Gas Expense Operations
+// ============================================================================
+
+/// Record gas paid for a position transaction
+pub async fn insert_gas_expense(executor: impl PgExecutor<'_>, expense: &GasExpense) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO gas_expenses (position_id, tx_hash, gas_cost, timestamp)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (position_id, tx_hash) DO NOTHING
+        "#,
+    )
+    .bind(expense.position_id)
+    .bind(&expense.tx_hash)
+    .bind(expense.gas_cost)
+    .bind(expense.timestamp)
+    .execute(executor)
+    .await
+    .context("Failed to insert gas expense")?;
+
+    Ok(())
+}
+
+/// Get gas expenses for a position up to a timestamp
+pub async fn get_gas_expenses_for_position(
+    pool: &PgPool,
+    position_id: i64,
+    until: DateTime<Utc>,
+) -> Result<Vec<GasExpense>> {
+    let rows = sqlx::query_as::<_, GasExpense>(
+        r#"
+        SELECT id, position_id, tx_hash, gas_cost, timestamp
+        FROM gas_expenses
+        WHERE position_id = $1 AND timestamp <= $2
+        ORDER BY timestamp ASC
+        "#,
+    )
+    .bind(position_id)
+    .bind(until)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get gas expenses for position")?;
+
+    Ok(rows)
 }
 
 // ============================================================================
@@ -307,6 +1080,19 @@ pub async fn insert_snapshot(pool: &PgPool, snapshot: &PositionSnapshot) -> Resu
     Ok(())
 }
 
+/// Map a row of `id, position_id, timestamp, fees_earned, liquidity::text, price`
+fn row_to_snapshot(r: &PgRow) -> PositionSnapshot {
+    let liquidity_str: String = r.get(4);
+    PositionSnapshot {
+        id: r.get(0),
+        position_id: r.get(1),
+        timestamp: r.get(2),
+        fees_earned: r.get(3),
+        liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
+        price: r.get(5),
+    }
+}
+
 /// Get snapshots for a position in a time range
 pub async fn get_snapshots_for_position(
     pool: &PgPool,
@@ -329,18 +1115,155 @@ pub async fn get_snapshots_for_position(
     .await
     .context("Failed to get snapshots for position")?;
 
+    Ok(rows.iter().map(row_to_snapshot).collect())
+}
+
+/// Get every snapshot of the positions of any of `owners`, oldest first
+pub async fn get_snapshots_for_owners(
+    pool: &PgPool,
+    owners: &[Address],
+) -> Result<Vec<PositionSnapshot>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.id, s.position_id, s.timestamp, s.fees_earned, s.liquidity::text, s.price
+        FROM position_snapshots s
+        JOIN positions p ON p.id = s.position_id
+        WHERE p.owner = ANY($1) AND p.deleted_at IS NULL
+        ORDER BY s.timestamp ASC
+        "#,
+    )
+    .bind(owners)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get snapshots for owners")?;
+
+    Ok(rows.iter().map(row_to_snapshot).collect())
+}
+
+/// Get owners' snapshots in a time range, plus each position's last one before it, oldest first
+///
+/// The earlier snapshot carries the fees a position had already earned into
+/// the window.
+pub async fn get_snapshots_for_owners_between(
+    pool: &PgPool,
+    owners: &[Address],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<PositionSnapshot>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, position_id, timestamp, fees_earned, liquidity, price
+        FROM (
+            SELECT DISTINCT ON (s.position_id)
+                   s.id, s.position_id, s.timestamp, s.fees_earned, s.liquidity::text AS liquidity,
+                   s.price
+            FROM position_snapshots s
+            JOIN positions p ON p.id = s.position_id
+            WHERE p.owner = ANY($1) AND p.deleted_at IS NULL AND s.timestamp < $2
+            ORDER BY s.position_id, s.timestamp DESC
+        ) earlier
+        UNION ALL
+        SELECT s.id, s.position_id, s.timestamp, s.fees_earned, s.liquidity::text, s.price
+        FROM position_snapshots s
+        JOIN positions p ON p.id = s.position_id
+        WHERE p.owner = ANY($1) AND p.deleted_at IS NULL AND s.timestamp >= $2
+          AND s.timestamp <= $3
+        ORDER BY timestamp ASC
+        "#,
+    )
+    .bind(owners)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get snapshots for owners")?;
+
+    Ok(rows.iter().map(row_to_snapshot).collect())
+}
+
+/// Aggregates of a position's snapshots (`s`) selected after its columns
+const SNAPSHOT_WINDOW_COLUMNS: &str = r#"
+               MIN(s.timestamp), MAX(s.timestamp),
+               first(s.fees_earned, s.timestamp), last(s.fees_earned, s.timestamp),
+               first(s.price, s.timestamp), last(s.price, s.timestamp),
+               AVG(CASE WHEN s.price > 0
+                         AND ln(s.price::FLOAT8) / ln(1.0001) >= p.tick_lower
+                         AND ln(s.price::FLOAT8) / ln(1.0001) < p.tick_upper
+                        THEN 1 ELSE 0 END)"#;
+
+fn row_to_snapshot_window(r: &PgRow) -> (Position, SnapshotWindow) {
+    let position = row_to_position(r);
+    let window = SnapshotWindow {
+        position_id: position.id,
+        start_time: r.get(POSITION_COLUMN_COUNT),
+        end_time: r.get(POSITION_COLUMN_COUNT + 1),
+        start_fees: r.get(POSITION_COLUMN_COUNT + 2),
+        end_fees: r.get(POSITION_COLUMN_COUNT + 3),
+        start_price: r.get(POSITION_COLUMN_COUNT + 4),
+        end_price: r.get(POSITION_COLUMN_COUNT + 5),
+        in_range_share: r.get(POSITION_COLUMN_COUNT + 6),
+    };
+    (position, window)
+}
+
+/// Get first/last snapshot values in a window for every position with snapshots in it
+pub async fn get_snapshot_windows(
+    pool: &PgPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(Position, SnapshotWindow)>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}, {}
+        FROM position_snapshots s
+        JOIN positions p ON p.id = s.position_id
+        WHERE s.timestamp >= $1 AND s.timestamp <= $2 AND p.deleted_at IS NULL
+        GROUP BY p.id
+        "#,
+        position_columns("p"),
+        SNAPSHOT_WINDOW_COLUMNS
+    ))
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get snapshot windows")?;
+
     Ok(rows
-        .into_iter()
+        .iter()
         .map(|r| {
-            let liquidity_str: String = r.get(4);
-            PositionSnapshot {
-                id: r.get(0),
-                position_id: r.get(1),
-                timestamp: r.get(2),
-                fees_earned: r.get(3),
-                liquidity: U256::from_str_radix(&liquidity_str, 10).unwrap_or_default(),
-                price: r.get(5),
-            }
+            let (mut position, window) = row_to_snapshot_window(r);
+            let entry_liquidity: String = r.get(POSITION_COLUMN_COUNT + 7);
+            position.liquidity = U256::from_str_radix(&entry_liquidity, 10).unwrap_or_default();
+            (position, window)
         })
         .collect())
 }
+
+/// Get lifetime snapshot windows of every position in a pool
+///
+/// Each position's liquidity is replaced by its liquidity at its first
+/// snapshot with any, so positions closed since still report entry capital.
+pub async fn get_pool_lifetime_windows(
+    pool: &PgPool,
+    pool_id: &str,
+) -> Result<Vec<(Position, SnapshotWindow)>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}, {},
+               COALESCE(first(s.liquidity, s.timestamp) FILTER (WHERE s.liquidity > 0), 0)::text
+        FROM position_snapshots s
+        JOIN positions p ON p.id = s.position_id
+        WHERE p.pool_id = $1 AND p.deleted_at IS NULL
+        GROUP BY p.id
+        "#,
+        position_columns("p"),
+        SNAPSHOT_WINDOW_COLUMNS
+    ))
+    .bind(pool_id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get pool lifetime windows")?;
+
+    Ok(rows.iter().map(row_to_snapshot_window).collect())
+}
@@ -0,0 +1,369 @@
+use alloy::primitives::{I256, U256};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{Acquire, PgPool, Postgres, Row, Transaction};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use stillwater_models::{
+    Address, LiquidityChange, LiquidityEvent, Position, PositionLiquidityEvent,
+    PricedLiquidityEvent, RangeLiquidity,
+};
+
+// ============================================================================
+// Liquidity Event Operations
+// ============================================================================
+
+/// Result of applying a liquidity removal to an owner's positions
+#[derive(Debug, Clone, Default)]
+pub struct RemovalOutcome {
+    /// Positions whose liquidity was reduced
+    pub reduced: usize,
+    /// Of those, positions now fully withdrawn
+    pub closed: usize,
+    /// Liquidity removed beyond what the matching positions held (e.g. positions
+    /// opened before the sync window)
+    pub unmatched: U256,
+    /// The oldest matching position, which the event is recorded against
+    pub position_id: Option<i64>,
+}
+
+/// Record an event, returning false if it was already recorded
+async fn insert_liquidity_event(
+    tx: &mut Transaction<'_, Postgres>,
+    event: &LiquidityEvent,
+    position_id: Option<i64>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO liquidity_events
+            (event_id, owner, pool_id, tick_lower, tick_upper, liquidity_delta, kind, position_id, timestamp)
+        VALUES ($1, $2, $3, $4, $5, $6::numeric, $7, $8, $9)
+        ON CONFLICT (event_id) DO NOTHING
+        "#,
+    )
+    .bind(&event.event_id)
+    .bind(event.owner)
+    .bind(&event.pool_id)
+    .bind(event.tick_lower)
+    .bind(event.tick_upper)
+    .bind(event.liquidity_delta.to_string())
+    .bind(event.change().as_str())
+    .bind(position_id)
+    .bind(event.timestamp)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to insert liquidity event")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record a liquidity addition and the position it created
+///
+/// Takes a pool or a connection; inside a transaction it runs under a savepoint.
+pub async fn record_liquidity_addition(
+    conn: impl Acquire<'_, Database = Postgres>,
+    event: &LiquidityEvent,
+    position_id: Option<i64>,
+) -> Result<bool> {
+    let mut tx = conn.begin().await.context("Failed to begin transaction")?;
+    let recorded = insert_liquidity_event(&mut tx, event, position_id).await?;
+    tx.commit().await.context("Failed to commit liquidity event")?;
+    Ok(recorded)
+}
+
+/// Apply a liquidity removal to the owner's open positions on the same range, oldest first
+///
+/// Returns `None` if the event was applied before, so re-syncing a window
+/// never subtracts the same removal twice. Inside a transaction it runs under
+/// a savepoint.
+pub async fn apply_liquidity_removal(
+    conn: impl Acquire<'_, Database = Postgres>,
+    event: &LiquidityEvent,
+) -> Result<Option<RemovalOutcome>> {
+    anyhow::ensure!(
+        event.change() == LiquidityChange::Remove,
+        "event {} is not a removal",
+        event.event_id
+    );
+
+    let mut tx = conn.begin().await.context("Failed to begin transaction")?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, liquidity::text
+        FROM positions
+        WHERE owner = $1 AND pool_id = $2 AND tick_lower = $3 AND tick_upper = $4
+          AND liquidity > 0 AND created_at <= $5
+        ORDER BY created_at ASC, id ASC
+        FOR UPDATE
+        "#,
+    )
+    .bind(event.owner)
+    .bind(&event.pool_id)
+    .bind(event.tick_lower)
+    .bind(event.tick_upper)
+    .bind(event.timestamp)
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to find positions for liquidity removal")?;
+
+    let first_position: Option<i64> = rows.first().map(|r| r.get(0));
+    if !insert_liquidity_event(&mut tx, event, first_position).await? {
+        return Ok(None);
+    }
+
+    let mut remaining = event.liquidity_delta.unsigned_abs();
+    let mut outcome = RemovalOutcome { position_id: first_position, ..Default::default() };
+
+    for row in rows {
+        if remaining.is_zero() {
+            break;
+        }
+        let id: i64 = row.get(0);
+        let liquidity_str: String = row.get(1);
+        let liquidity = U256::from_str_radix(&liquidity_str, 10).unwrap_or_default();
+
+        let removed = remaining.min(liquidity);
+        let left = liquidity - removed;
+        remaining -= removed;
+
+        sqlx::query(
+            r#"
+            UPDATE positions
+            SET liquidity = $1::numeric,
+                closed_at = CASE WHEN $1::numeric = 0 THEN $3 END
+            WHERE id = $2
+            "#,
+        )
+            .bind(left.to_string())
+            .bind(id)
+            .bind(event.timestamp)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to reduce position liquidity")?;
+
+        outcome.reduced += 1;
+        if left.is_zero() {
+            outcome.closed += 1;
+        }
+    }
+
+    outcome.unmatched = remaining;
+    tx.commit().await.context("Failed to commit liquidity removal")?;
+    Ok(Some(outcome))
+}
+
+/// Get a pool's liquidity events in [from, to], oldest first
+pub async fn get_liquidity_events_for_pool_between(
+    pool: &PgPool,
+    pool_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<LiquidityEvent>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT event_id, owner, pool_id, tick_lower, tick_upper, liquidity_delta::text, timestamp
+        FROM liquidity_events
+        WHERE pool_id = $1 AND timestamp >= $2 AND timestamp <= $3
+        ORDER BY timestamp ASC, event_id ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get liquidity events")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let delta: String = r.get(5);
+            LiquidityEvent {
+                event_id: r.get(0),
+                owner: r.get(1),
+                pool_id: r.get(2),
+                tick_lower: r.get(3),
+                tick_upper: r.get(4),
+                liquidity_delta: delta.parse::<I256>().unwrap_or_default(),
+                timestamp: r.get(6),
+            }
+        })
+        .collect())
+}
+
+/// Get liquidity events in [from, to] made by owners or on positions they now hold, oldest first
+pub async fn get_liquidity_events_for_owners(
+    pool: &PgPool,
+    owners: &[Address],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<PositionLiquidityEvent>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT event_id, owner, pool_id, tick_lower, tick_upper, liquidity_delta::text, timestamp,
+               position_id, first_for_position
+        FROM (
+            SELECT e.*,
+                   e.position_id IS NOT NULL AND ROW_NUMBER() OVER (
+                       PARTITION BY e.position_id ORDER BY e.timestamp, e.event_id
+                   ) = 1 AS first_for_position
+            FROM liquidity_events e
+            WHERE e.owner = ANY($1)
+               OR e.position_id IN (SELECT id FROM positions WHERE owner = ANY($1))
+        ) e
+        WHERE timestamp >= $2 AND timestamp <= $3
+        ORDER BY timestamp ASC, event_id ASC
+        "#,
+    )
+    .bind(owners)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get liquidity events for owners")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let delta: String = r.get(5);
+            PositionLiquidityEvent {
+                event: LiquidityEvent {
+                    event_id: r.get(0),
+                    owner: r.get(1),
+                    pool_id: r.get(2),
+                    tick_lower: r.get(3),
+                    tick_upper: r.get(4),
+                    liquidity_delta: delta.parse::<I256>().unwrap_or_default(),
+                    timestamp: r.get(6),
+                },
+                position_id: r.get(7),
+                first_for_position: r.get(8),
+            }
+        })
+        .collect())
+}
+
+/// Price liquidity additions from the last swap at or before each, returning how many were priced
+///
+/// Additions the pool has no recorded swap before stay unpriced until one is
+/// synced; archived swaps count, so backfilled events can still be priced.
+pub async fn price_liquidity_events(pool: &PgPool) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        WITH priced AS (
+            SELECT e.event_id,
+                   (SELECT ABS(s.amount1 / s.amount0)
+                    FROM (
+                        SELECT amount0, amount1, timestamp FROM swaps
+                        WHERE pool_id = e.pool_id AND timestamp <= e.timestamp
+                          AND amount0 <> 0 AND amount1 <> 0
+                        UNION ALL
+                        SELECT amount0, amount1, timestamp FROM swaps_archive
+                        WHERE pool_id = e.pool_id AND timestamp <= e.timestamp
+                          AND amount0 <> 0 AND amount1 <> 0
+                    ) s
+                    ORDER BY s.timestamp DESC
+                    LIMIT 1) AS price
+            FROM liquidity_events e
+            WHERE e.kind = 'add' AND e.price IS NULL
+        )
+        UPDATE liquidity_events e
+        SET price = p.price
+        FROM priced p
+        WHERE e.event_id = p.event_id AND p.price IS NOT NULL
+        "#,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to price liquidity events")?;
+
+    Ok(result.rows_affected())
+}
+
+/// Liquidity events on a position's range, oldest first, with the price of each addition
+///
+/// Covers the events recorded against the position and its owner's other
+/// events on the same pool and range, which together make up the liquidity
+/// the owner built up there (removals apply to them oldest first).
+pub async fn get_position_range_events(
+    pool: &PgPool,
+    position: &Position,
+) -> Result<Vec<PricedLiquidityEvent>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT event_id, owner, pool_id, tick_lower, tick_upper, liquidity_delta::text, timestamp,
+               price::text
+        FROM liquidity_events
+        WHERE pool_id = $1 AND tick_lower = $2 AND tick_upper = $3
+          AND (owner = $4 OR position_id = $5)
+          AND timestamp <= NOW()
+        ORDER BY timestamp ASC, event_id ASC
+        "#,
+    )
+    .bind(&position.pool_id)
+    .bind(position.tick_lower)
+    .bind(position.tick_upper)
+    .bind(position.owner)
+    .bind(position.id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get position range events")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let delta: String = r.get(5);
+            let price: Option<String> = r.get(7);
+            PricedLiquidityEvent {
+                event: LiquidityEvent {
+                    event_id: r.get(0),
+                    owner: r.get(1),
+                    pool_id: r.get(2),
+                    tick_lower: r.get(3),
+                    tick_upper: r.get(4),
+                    liquidity_delta: delta.parse::<I256>().unwrap_or_default(),
+                    timestamp: r.get(6),
+                },
+                price: price.and_then(|p| Decimal::from_str(&p).ok()),
+            }
+        })
+        .collect())
+}
+
+/// Net recorded liquidity per tick range of a pool just before a time
+///
+/// Only ranges with liquidity left are returned. Covers recorded events only,
+/// so liquidity added before the first sync is missing.
+pub async fn get_range_liquidity_before(
+    pool: &PgPool,
+    pool_id: &str,
+    before: DateTime<Utc>,
+) -> Result<Vec<RangeLiquidity>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT tick_lower, tick_upper, SUM(liquidity_delta)::text
+        FROM liquidity_events
+        WHERE pool_id = $1 AND timestamp < $2
+        GROUP BY tick_lower, tick_upper
+        HAVING SUM(liquidity_delta) > 0
+        "#,
+    )
+    .bind(pool_id)
+    .bind(before)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get range liquidity")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let liquidity: String = r.get(2);
+            RangeLiquidity {
+                tick_lower: r.get(0),
+                tick_upper: r.get(1),
+                liquidity: liquidity.parse::<I256>().unwrap_or_default(),
+            }
+        })
+        .collect())
+}
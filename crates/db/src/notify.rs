@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use stillwater_models::{DbEvent, EVENTS_CHANNEL_PREFIX, events_channel};
+
+// ============================================================================
+// Change Notification Operations
+// ============================================================================
+
+/// Send an event to everyone listening on the current schema's events channel
+///
+/// Inserts that emit events (`insert_position`, `insert_swap`) notify in the
+/// same statement; this is for events without a row of their own.
+pub async fn notify_event(pool: &PgPool, event: &DbEvent) -> Result<()> {
+    sqlx::query("SELECT pg_notify($1 || current_schema(), $2)")
+        .bind(EVENTS_CHANNEL_PREFIX)
+        .bind(event.to_payload())
+        .execute(pool)
+        .await
+        .context("Failed to send event notification")?;
+
+    Ok(())
+}
+
+/// Listener for data change events
+///
+/// Holds a dedicated connection; sqlx reconnects and re-subscribes if it
+/// drops, though events sent while disconnected are lost.
+pub struct EventListener {
+    listener: PgListener,
+}
+
+impl EventListener {
+    /// Subscribe to the events channel of `pool`'s schema, using a connection
+    /// from `pool`'s settings
+    pub async fn connect(pool: &PgPool) -> Result<Self> {
+        // The listener's own connection doesn't get the pool's search_path
+        let schema: String = sqlx::query_scalar("SELECT current_schema()")
+            .fetch_one(pool)
+            .await
+            .context("Failed to get current schema")?;
+        let mut listener =
+            PgListener::connect_with(pool).await.context("Failed to connect event listener")?;
+        listener.listen(&events_channel(&schema)).await.context("Failed to listen for events")?;
+        Ok(Self { listener })
+    }
+
+    /// Wait for the next event, skipping payloads that aren't events
+    pub async fn recv(&mut self) -> Result<DbEvent> {
+        loop {
+            let notification =
+                self.listener.recv().await.context("Failed to receive event notification")?;
+            if let Some(event) = DbEvent::from_payload(notification.payload()) {
+                return Ok(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use stillwater_models::QuotePreference;
+
+// ============================================================================
+// Display Preference Operations
+// ============================================================================
+
+/// Set the token an owner wants a pool's prices quoted in
+pub async fn set_quote_preference(
+    pool: &PgPool,
+    owner: &str,
+    pool_id: &str,
+    quote_token: &str,
+) -> Result<QuotePreference> {
+    let preference = sqlx::query_as::<_, QuotePreference>(
+        r#"
+        INSERT INTO quote_preferences (owner, pool_id, quote_token)
+        VALUES (LOWER($1), $2, LOWER($3))
+        ON CONFLICT (owner, pool_id) DO UPDATE
+        SET quote_token = EXCLUDED.quote_token, updated_at = NOW()
+        RETURNING owner, pool_id, quote_token, updated_at
+        "#,
+    )
+    .bind(owner)
+    .bind(pool_id)
+    .bind(quote_token)
+    .fetch_one(pool)
+    .await
+    .context("Failed to set quote preference")?;
+
+    Ok(preference)
+}
+
+/// Get an owner's quote token for a pool, if set
+pub async fn get_quote_preference(
+    pool: &PgPool,
+    owner: &str,
+    pool_id: &str,
+) -> Result<Option<QuotePreference>> {
+    let preference = sqlx::query_as::<_, QuotePreference>(
+        r#"
+        SELECT owner, pool_id, quote_token, updated_at
+        FROM quote_preferences
+        WHERE owner = LOWER($1) AND pool_id = $2
+        "#,
+    )
+    .bind(owner)
+    .bind(pool_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get quote preference")?;
+
+    Ok(preference)
+}
+
+/// Get all of an owner's quote preferences
+pub async fn get_quote_preferences(pool: &PgPool, owner: &str) -> Result<Vec<QuotePreference>> {
+    let preferences = sqlx::query_as::<_, QuotePreference>(
+        r#"
+        SELECT owner, pool_id, quote_token, updated_at
+        FROM quote_preferences
+        WHERE owner = LOWER($1)
+        ORDER BY pool_id
+        "#,
+    )
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get quote preferences")?;
+
+    Ok(preferences)
+}
@@ -0,0 +1,152 @@
+use alloy::primitives::I256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use stillwater_models::{DataQualityIssue, DataQualitySummary, IssueKind, Swap};
+
+// ============================================================================
+// Data Quality Operations
+// ============================================================================
+
+/// Get the IDs of all synced pools
+pub async fn get_pool_ids(pool: &PgPool) -> Result<Vec<String>> {
+    let ids = sqlx::query_scalar::<_, String>("SELECT pool_id FROM pools ORDER BY pool_id")
+        .fetch_all(pool)
+        .await
+        .context("Failed to get pool IDs")?;
+
+    Ok(ids)
+}
+
+/// Get swaps for a pool since a timestamp, in insertion order
+pub async fn get_swaps_for_pool_by_insertion(
+    pool: &PgPool,
+    pool_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<Swap>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+        FROM swaps
+        WHERE pool_id = $1 AND timestamp >= $2
+        ORDER BY id ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get swaps for pool")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let amount0_str: String = r.get(3);
+            let amount1_str: String = r.get(4);
+            Swap {
+                id: r.get(0),
+                tx_hash: r.get(1),
+                pool_id: r.get(2),
+                amount0: amount0_str.parse::<I256>().unwrap_or_default(),
+                amount1: amount1_str.parse::<I256>().unwrap_or_default(),
+                fee: r.get(5),
+                timestamp: r.get(6),
+            }
+        })
+        .collect())
+}
+
+/// Record data quality issues, skipping ones already reported
+///
+/// Returns the number of newly recorded issues.
+pub async fn insert_quality_issues(pool: &PgPool, issues: &[DataQualityIssue]) -> Result<u64> {
+    let mut inserted = 0;
+
+    for issue in issues {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO data_quality_issues (pool_id, kind, swap_id, tx_hash, detail, detected_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (pool_id, kind, swap_id) DO NOTHING
+            "#,
+        )
+        .bind(&issue.pool_id)
+        .bind(issue.kind.as_str())
+        .bind(issue.swap_id)
+        .bind(&issue.tx_hash)
+        .bind(&issue.detail)
+        .bind(issue.detected_at)
+        .execute(pool)
+        .await
+        .context("Failed to insert data quality issue")?;
+
+        inserted += result.rows_affected();
+    }
+
+    Ok(inserted)
+}
+
+/// Count recorded issues per pool and kind
+pub async fn get_quality_summary(pool: &PgPool) -> Result<Vec<DataQualitySummary>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT pool_id, kind, COUNT(*), MAX(detected_at)
+        FROM data_quality_issues
+        GROUP BY pool_id, kind
+        ORDER BY pool_id, kind
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to get data quality summary")?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let kind: String = r.get(1);
+            Some(DataQualitySummary {
+                pool_id: r.get(0),
+                kind: IssueKind::parse(&kind)?,
+                count: r.get(2),
+                last_detected_at: r.get(3),
+            })
+        })
+        .collect())
+}
+
+/// Get the most recently detected issues, optionally for a single pool
+pub async fn get_quality_issues(
+    pool: &PgPool,
+    pool_id: Option<&str>,
+    limit: i64,
+) -> Result<Vec<DataQualityIssue>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT pool_id, kind, swap_id, tx_hash, detail, detected_at
+        FROM data_quality_issues
+        WHERE $1::text IS NULL OR pool_id = $1
+        ORDER BY detected_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(pool_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get data quality issues")?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let kind: String = r.get(1);
+            Some(DataQualityIssue {
+                pool_id: r.get(0),
+                kind: IssueKind::parse(&kind)?,
+                swap_id: r.get(2),
+                tx_hash: r.get(3),
+                detail: r.get(4),
+                detected_at: r.get(5),
+            })
+        })
+        .collect())
+}
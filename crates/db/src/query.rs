@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use sqlx::{Column, Executor, PgPool, postgres::PgPoolOptions};
+use std::fmt;
+use std::time::Instant;
+use stillwater_models::QueryResult;
+
+use crate::{schema_from_env, validate_schema_name};
+
+// ============================================================================
+// Ad Hoc Query Operations
+// ============================================================================
+
+/// Queries are cancelled after this long unless `QUERY_TIMEOUT_MS` is set
+pub const DEFAULT_QUERY_TIMEOUT_MS: u64 = 5_000;
+
+/// At most this many rows are returned unless `QUERY_MAX_ROWS` is set
+pub const DEFAULT_QUERY_MAX_ROWS: usize = 1_000;
+
+/// Why an ad hoc query produced no result
+#[derive(Debug)]
+pub enum QueryError {
+    /// The SQL was refused before reaching the database
+    Rejected(String),
+    /// The database refused or cancelled the query (syntax, permissions, timeout)
+    Failed(String),
+    /// Connection or other internal failure
+    Internal(anyhow::Error),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Rejected(reason) => write!(f, "query rejected: {}", reason),
+            QueryError::Failed(message) => write!(f, "query failed: {}", message),
+            QueryError::Internal(e) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl From<sqlx::Error> for QueryError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(db) => QueryError::Failed(db.message().to_string()),
+            other => QueryError::Internal(other.into()),
+        }
+    }
+}
+
+/// Connection pool for ad hoc SQL, logged in as a restricted read-only role
+///
+/// The role's grants decide what can be read (see `docker/query_role.sql`); every
+/// query additionally runs in a read-only transaction under a statement timeout.
+#[derive(Clone)]
+pub struct ReadOnlyDb {
+    pool: PgPool,
+    pub timeout_ms: u64,
+    pub max_rows: usize,
+}
+
+impl ReadOnlyDb {
+    /// Connect to `QUERY_DATABASE_URL` (None when unset)
+    ///
+    /// `QUERY_TIMEOUT_MS` and `QUERY_MAX_ROWS` override the limits, and
+    /// `DB_SCHEMA` scopes queries to the instance's tables as for the main pool.
+    pub async fn from_env() -> Result<Option<Self>> {
+        let Ok(url) = std::env::var("QUERY_DATABASE_URL") else {
+            return Ok(None);
+        };
+        let timeout_ms = match std::env::var("QUERY_TIMEOUT_MS") {
+            Ok(ms) => ms.trim().parse().context("QUERY_TIMEOUT_MS must be a positive integer")?,
+            Err(_) => DEFAULT_QUERY_TIMEOUT_MS,
+        };
+        let max_rows = match std::env::var("QUERY_MAX_ROWS") {
+            Ok(rows) => rows.trim().parse().context("QUERY_MAX_ROWS must be a positive integer")?,
+            Err(_) => DEFAULT_QUERY_MAX_ROWS,
+        };
+        let schema = schema_from_env()?;
+        Self::connect(&url, schema.as_deref(), timeout_ms, max_rows).await.map(Some)
+    }
+
+    /// Connect as the role in `database_url`
+    ///
+    /// Unlike `connect`, this never creates the schema: the role can't.
+    pub async fn connect(
+        database_url: &str,
+        schema: Option<&str>,
+        timeout_ms: u64,
+        max_rows: usize,
+    ) -> Result<Self> {
+        let mut session = vec!["SET default_transaction_read_only = on".to_string()];
+        if let Some(schema) = schema {
+            validate_schema_name(schema)?;
+            session.push(format!("SET search_path TO \"{}\", public", schema));
+        }
+
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .after_connect(move |conn, _meta| {
+                let session = session.clone();
+                Box::pin(async move {
+                    for statement in &session {
+                        conn.execute(statement.as_str()).await?;
+                    }
+                    Ok(())
+                })
+            })
+            .connect(database_url)
+            .await
+            .context("Failed to create read-only query pool")?;
+
+        Ok(Self { pool, timeout_ms: timeout_ms.max(1), max_rows: max_rows.max(1) })
+    }
+
+    /// Run a single SELECT (or WITH/VALUES) statement and collect its rows as JSON
+    ///
+    /// The statement is wrapped as a subquery, which Postgres only accepts for
+    /// row-returning, non-modifying queries. Semicolons are refused outright, so
+    /// a literal one must be written as `chr(59)`.
+    pub async fn run(&self, sql: &str) -> Result<QueryResult, QueryError> {
+        let sql = sql.trim().trim_end_matches(';').trim_end();
+        if sql.is_empty() {
+            return Err(QueryError::Rejected("query is empty".to_string()));
+        }
+        if sql.contains(';') {
+            return Err(QueryError::Rejected("only a single statement is allowed".to_string()));
+        }
+
+        let started = Instant::now();
+        let mut tx = self.pool.begin().await?;
+        tx.execute("SET TRANSACTION READ ONLY").await?;
+        tx.execute(format!("SET LOCAL statement_timeout = {}", self.timeout_ms).as_str()).await?;
+
+        let described = (&mut *tx).describe(&format!("SELECT * FROM ({}\n) AS q", sql)).await?;
+        let columns = described.columns().iter().map(|c| c.name().to_string()).collect();
+
+        // json_each keeps the columns' order (and duplicate names) of the row
+        let values = sqlx::query_scalar::<_, Option<String>>(&format!(
+            "SELECT (SELECT json_agg(value) FROM json_each(row_to_json(q)))::text \
+             FROM ({}\n) AS q LIMIT {}",
+            sql,
+            self.max_rows + 1
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.rollback().await?;
+
+        let truncated = values.len() > self.max_rows;
+        let rows = values
+            .into_iter()
+            .take(self.max_rows)
+            .map(|row| match row {
+                Some(json) => serde_json::from_str(&json),
+                None => Ok(Vec::new()),
+            })
+            .collect::<Result<Vec<Vec<serde_json::Value>>, _>>()
+            .map_err(|e| QueryError::Internal(e.into()))?;
+
+        Ok(QueryResult {
+            columns,
+            rows,
+            truncated,
+            elapsed_ms: started.elapsed().as_millis() as i64,
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+// ============================================================================
+// Reorg Rollback Operations
+// ============================================================================
+
+/// Rows a reorg rollback deleted or restored, per table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainRollback {
+    pub checkpoints: u64,
+    /// Hot and archived swaps
+    pub swaps: u64,
+    /// Per-minute counts of downsampled swaps
+    pub swap_minute_aggregates: u64,
+    pub liquidity_events: u64,
+    /// Positions given back the liquidity a deleted removal took from them
+    pub positions_restored: u64,
+    pub position_transfers: u64,
+    /// Positions handed back to their owner before a deleted transfer
+    pub owners_restored: u64,
+    pub gas_expenses: u64,
+    pub pool_initializations: u64,
+    pub fee_accumulators: u64,
+}
+
+/// Roll block-derived data back to a reorg's last common ancestor, in one transaction
+///
+/// Deletes checkpoints above `ancestor_block` (all of them with None) and,
+/// with `after`, every swap, swap minute aggregate, liquidity event, position
+/// transfer, gas expense and pool initialization timed after it. Deleted
+/// removals give their liquidity back to the position they were recorded
+/// against (reopening it), and positions go back to their owner before the
+/// earliest deleted transfer. Fee accumulators are cleared when swaps or
+/// liquidity events go, to be rebuilt from what remains. Either everything
+/// is rolled back or nothing is.
+pub async fn roll_back_chain_data(
+    pool: &PgPool,
+    ancestor_block: Option<i64>,
+    after: Option<DateTime<Utc>>,
+) -> Result<ChainRollback> {
+    let mut tx = pool.begin().await.context("Failed to begin reorg rollback")?;
+    let checkpoints =
+        sqlx::query("DELETE FROM sync_checkpoints WHERE $1::BIGINT IS NULL OR block_number > $1")
+            .bind(ancestor_block)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete sync checkpoints")?
+            .rows_affected();
+    let mut rollback = ChainRollback { checkpoints, ..Default::default() };
+
+    let Some(after) = after else {
+        tx.commit().await.context("Failed to commit reorg rollback")?;
+        return Ok(rollback);
+    };
+
+    rollback.swaps = delete_after(&mut tx, "swaps", "timestamp", after).await?
+        + delete_after(&mut tx, "swaps_archive", "timestamp", after).await?;
+    rollback.swap_minute_aggregates =
+        delete_after(&mut tx, "swap_minute_aggregates", "minute", after).await?;
+    rollback.gas_expenses = delete_after(&mut tx, "gas_expenses", "timestamp", after).await?;
+    rollback.pool_initializations =
+        delete_after(&mut tx, "pool_initializations", "created_at", after).await?;
+
+    let (events, restored): (i64, i64) = sqlx::query_as(
+        r#"
+        WITH removed AS (
+            DELETE FROM liquidity_events WHERE timestamp > $1
+            RETURNING position_id, kind, liquidity_delta
+        ),
+        given_back AS (
+            SELECT position_id, -SUM(liquidity_delta) AS liquidity
+            FROM removed
+            WHERE kind = 'remove' AND position_id IS NOT NULL
+            GROUP BY position_id
+        ),
+        restored AS (
+            UPDATE positions p
+            SET liquidity = p.liquidity + g.liquidity, closed_at = NULL
+            FROM given_back g
+            WHERE p.id = g.position_id
+            RETURNING p.id
+        )
+        SELECT (SELECT COUNT(*) FROM removed), (SELECT COUNT(*) FROM restored)
+        "#,
+    )
+    .bind(after)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to roll back liquidity events")?;
+    rollback.liquidity_events = events as u64;
+    rollback.positions_restored = restored as u64;
+
+    let (transfers, owners): (i64, i64) = sqlx::query_as(
+        r#"
+        WITH removed AS (
+            DELETE FROM position_transfers WHERE effective_from > $1
+            RETURNING position_id, from_owner, effective_from
+        ),
+        earliest AS (
+            SELECT DISTINCT ON (position_id) position_id, from_owner
+            FROM removed
+            ORDER BY position_id, effective_from ASC
+        ),
+        restored AS (
+            UPDATE positions p
+            SET owner = e.from_owner
+            FROM earliest e
+            WHERE p.id = e.position_id
+            RETURNING p.id
+        )
+        SELECT (SELECT COUNT(*) FROM removed), (SELECT COUNT(*) FROM restored)
+        "#,
+    )
+    .bind(after)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to roll back position transfers")?;
+    rollback.position_transfers = transfers as u64;
+    rollback.owners_restored = owners as u64;
+
+    // Accumulated fees include the deleted swaps and liquidity; rebuild them from what remains
+    if rollback.swaps > 0 || rollback.liquidity_events > 0 {
+        rollback.fee_accumulators = sqlx::query("DELETE FROM position_fee_accumulators")
+            .execute(&mut *tx)
+            .await
+            .context("Failed to reset fee accumulators")?
+            .rows_affected();
+    }
+
+    tx.commit().await.context("Failed to commit reorg rollback")?;
+    Ok(rollback)
+}
+
+/// Delete a table's rows whose `column` is after `after`, returning how many
+async fn delete_after(
+    tx: &mut Transaction<'_, Postgres>,
+    table: &str,
+    column: &str,
+    after: DateTime<Utc>,
+) -> Result<u64> {
+    let result = sqlx::query(&format!("DELETE FROM {} WHERE {} > $1", table, column))
+        .bind(after)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to roll back {}", table))?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use stillwater_models::RetainedData;
+
+use crate::archive::{archive_partition_name, next_month};
+
+// ============================================================================
+// Retention Operations
+// ============================================================================
+
+/// Oldest time still retained for a data set, None while retention hasn't removed any
+pub async fn get_retention_horizon(
+    pool: &PgPool,
+    data: RetainedData,
+) -> Result<Option<DateTime<Utc>>> {
+    sqlx::query_scalar("SELECT retained_from FROM retention_horizons WHERE dataset = $1")
+        .bind(data.as_str())
+        .fetch_optional(pool)
+        .await
+        .context("Failed to get retention horizon")
+}
+
+/// Move a data set's horizon up to `cutoff` (never back)
+async fn advance_horizon(
+    tx: &mut Transaction<'_, Postgres>,
+    data: RetainedData,
+    cutoff: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO retention_horizons (dataset, retained_from)
+        VALUES ($1, $2)
+        ON CONFLICT (dataset) DO UPDATE
+        SET retained_from = GREATEST(retention_horizons.retained_from, EXCLUDED.retained_from),
+            updated_at = NOW()
+        "#,
+    )
+    .bind(data.as_str())
+    .bind(cutoff)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to record retention horizon")?;
+    Ok(())
+}
+
+/// Delete swaps older than `cutoff` from `swaps` and `swaps_archive`
+///
+/// The swaps are first added to their pool's daily totals in
+/// `pool_daily_volume`, which is never purged; minute aggregates count as the
+/// swaps they stand for. Archive partitions left empty are dropped.
+/// Everything runs in one transaction. Returns the number of swaps deleted.
+pub async fn purge_swaps_before(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<u64> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO pool_daily_volume (pool_id, day, swap_count, volume0, volume1)
+        SELECT expired.pool_id, (expired.timestamp AT TIME ZONE 'UTC')::date,
+               SUM(CASE WHEN expired.tx_hash LIKE 'minute:%'
+                        THEN COALESCE(m.swap_count, 1) ELSE 1 END),
+               SUM(ABS(expired.amount0)), SUM(ABS(expired.amount1))
+        FROM (
+            SELECT pool_id, tx_hash, amount0, amount1, timestamp FROM swaps WHERE timestamp < $1
+            UNION ALL
+            SELECT pool_id, tx_hash, amount0, amount1, timestamp FROM swaps_archive
+            WHERE timestamp < $1
+        ) expired
+        LEFT JOIN swap_minute_aggregates m
+            ON m.pool_id = expired.pool_id AND m.minute = expired.timestamp
+        GROUP BY 1, 2
+        ON CONFLICT (pool_id, day) DO UPDATE
+        SET swap_count = pool_daily_volume.swap_count + EXCLUDED.swap_count,
+            volume0 = pool_daily_volume.volume0 + EXCLUDED.volume0,
+            volume1 = pool_daily_volume.volume1 + EXCLUDED.volume1
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to roll up expiring swaps")?;
+
+    let months: Vec<DateTime<Utc>> = sqlx::query_scalar(
+        "SELECT DISTINCT date_trunc('month', timestamp, 'UTC') FROM swaps_archive \
+         WHERE timestamp < $1",
+    )
+    .bind(cutoff)
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to find expiring archive months")?;
+
+    let mut deleted = 0;
+    for table in ["swaps_archive", "swaps"] {
+        deleted += sqlx::query(&format!("DELETE FROM {} WHERE timestamp < $1", table))
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete expired swaps")?
+            .rows_affected();
+    }
+    sqlx::query("DELETE FROM swap_minute_aggregates WHERE minute < $1")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete expired minute aggregates")?;
+
+    for month in months {
+        let month = month.date_naive();
+        if next_month(month) <= cutoff.date_naive() {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", archive_partition_name(month)))
+                .execute(&mut *tx)
+                .await
+                .context("Failed to drop expired archive partition")?;
+        }
+    }
+
+    advance_horizon(&mut tx, RetainedData::Swaps, cutoff).await?;
+    tx.commit().await.context("Failed to commit swap purge")?;
+    Ok(deleted)
+}
+
+/// Delete position snapshots older than `cutoff`, returning how many were deleted
+pub async fn purge_snapshots_before(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<u64> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+
+    let result = sqlx::query("DELETE FROM position_snapshots WHERE timestamp < $1")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete expired snapshots")?;
+
+    advance_horizon(&mut tx, RetainedData::PositionSnapshots, cutoff).await?;
+    tx.commit().await.context("Failed to commit snapshot purge")?;
+    Ok(result.rows_affected())
+}
+
+/// Permanently delete positions soft-deleted before `deleted_before`, with their history
+pub async fn purge_deleted_positions(pool: &PgPool, deleted_before: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM positions WHERE deleted_at < $1")
+        .bind(deleted_before)
+        .execute(pool)
+        .await
+        .context("Failed to purge deleted positions")?;
+
+    Ok(result.rows_affected())
+}
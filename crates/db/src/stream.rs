@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use sqlx::PgPool;
+use std::future::Future;
+use stillwater_models::{Position, PositionSnapshot, Swap};
+use tokio::sync::mpsc;
+
+use crate::{position_query, row_to_position, row_to_snapshot, row_to_swap, PositionFilter};
+
+// ============================================================================
+// Streaming Queries
+// ============================================================================
+
+/// Rows a streaming query reads ahead of its consumer
+pub const STREAM_BUFFER_ROWS: usize = 256;
+
+/// Rows read from a database cursor as the consumer pulls them
+///
+/// A query error ends the stream with that error.
+pub type RowStream<T> = BoxStream<'static, Result<T>>;
+
+/// Where a streaming query sends its rows
+pub(crate) struct RowSender<T>(mpsc::Sender<Result<T>>);
+
+impl<T> RowSender<T> {
+    /// Hand a row to the consumer, waiting while it's `STREAM_BUFFER_ROWS` behind;
+    /// false once the consumer is gone
+    pub(crate) async fn send(&self, row: T) -> bool {
+        self.0.send(Ok(row)).await.is_ok()
+    }
+}
+
+/// Run a query on its own task, streaming the rows it sends
+///
+/// The bounded channel keeps at most `STREAM_BUFFER_ROWS` rows in memory, and
+/// dropping the stream (e.g. a client disconnecting) stops the query at its
+/// next row.
+pub(crate) fn spawn_row_stream<T, F, Fut>(query: F) -> RowStream<T>
+where
+    T: Send + 'static,
+    F: FnOnce(RowSender<T>) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(STREAM_BUFFER_ROWS);
+    let errors = tx.clone();
+    let task = query(RowSender(tx));
+    tokio::spawn(async move {
+        if let Err(e) = task.await {
+            let _ = errors.send(Err(e)).await;
+        }
+    });
+    stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|row| (row, rx)) }).boxed()
+}
+
+/// Stream the positions matching a filter, in the filter's order
+pub fn stream_positions(pool: &PgPool, filter: PositionFilter) -> RowStream<Position> {
+    let pool = pool.clone();
+    spawn_row_stream(move |tx| async move {
+        let mut query = position_query(&filter);
+        let mut rows = query.build().fetch(&pool);
+        while let Some(row) = rows.try_next().await.context("Failed to stream positions")? {
+            if !tx.send(row_to_position(&row)).await {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Stream a pool's swaps in [start, end], oldest first, across the hot table and the archive
+pub fn stream_swaps_for_pool(
+    pool: &PgPool,
+    pool_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> RowStream<Swap> {
+    let (pool, pool_id) = (pool.clone(), pool_id.to_string());
+    spawn_row_stream(move |tx| async move {
+        let mut rows = sqlx::query(
+            r#"
+            SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+            FROM swaps
+            WHERE pool_id = $1 AND timestamp >= $2 AND timestamp <= $3
+            UNION ALL
+            SELECT id, tx_hash, pool_id, amount0::text, amount1::text, fee, timestamp
+            FROM swaps_archive
+            WHERE pool_id = $1 AND timestamp >= $2 AND timestamp <= $3
+            ORDER BY timestamp ASC, id ASC
+            "#,
+        )
+        .bind(&pool_id)
+        .bind(start)
+        .bind(end)
+        .fetch(&pool);
+        while let Some(row) = rows.try_next().await.context("Failed to stream swaps")? {
+            if !tx.send(row_to_swap(&row)).await {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Stream a position's snapshots in [start, end], oldest first
+pub fn stream_snapshots_for_position(
+    pool: &PgPool,
+    position_id: i64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> RowStream<PositionSnapshot> {
+    let pool = pool.clone();
+    spawn_row_stream(move |tx| async move {
+        let mut rows = sqlx::query(
+            r#"
+            SELECT id, position_id, timestamp, fees_earned, liquidity::text, price
+            FROM position_snapshots
+            WHERE position_id = $1 AND timestamp >= $2 AND timestamp <= $3
+            ORDER BY timestamp ASC
+            "#,
+        )
+        .bind(position_id)
+        .bind(start)
+        .bind(end)
+        .fetch(&pool);
+        while let Some(row) = rows.try_next().await.context("Failed to stream snapshots")? {
+            if !tx.send(row_to_snapshot(&row)).await {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
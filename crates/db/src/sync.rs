@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use stillwater_models::{SyncCheckpoint, SyncRun, SyncRunStatus, SyncSchedule};
+
+// ============================================================================
+// Sync Run Operations
+// ============================================================================
+
+/// Record a finished sync run, returning its ID
+pub async fn insert_sync_run(pool: &PgPool, run: &SyncRun) -> Result<i64> {
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO sync_runs (
+            started_at, finished_at, status, error,
+            fetch_ms, parse_ms, dedupe_ms, insert_ms,
+            rows_fetched, rows_parsed, rows_kept, rows_inserted, rows_removed, fields
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING id
+        "#,
+    )
+    .bind(run.started_at)
+    .bind(run.finished_at)
+    .bind(run.status.as_str())
+    .bind(&run.error)
+    .bind(run.fetch_ms)
+    .bind(run.parse_ms)
+    .bind(run.dedupe_ms)
+    .bind(run.insert_ms)
+    .bind(run.rows_fetched)
+    .bind(run.rows_parsed)
+    .bind(run.rows_kept)
+    .bind(run.rows_inserted)
+    .bind(run.rows_removed)
+    .bind(serde_json::to_value(&run.fields)?)
+    .fetch_one(pool)
+    .await
+    .context("Failed to insert sync run")?;
+
+    Ok(id)
+}
+
+/// Map a row of `id, started_at, finished_at, status, error, fetch_ms, parse_ms, dedupe_ms,
+/// insert_ms, rows_fetched, rows_parsed, rows_kept, rows_inserted, rows_removed, fields`
+/// (None for an unknown status)
+fn row_to_sync_run(r: &PgRow) -> Option<SyncRun> {
+    let status: String = r.get(3);
+    Some(SyncRun {
+        id: r.get(0),
+        started_at: r.get(1),
+        finished_at: r.get(2),
+        status: SyncRunStatus::parse(&status)?,
+        error: r.get(4),
+        fetch_ms: r.get(5),
+        parse_ms: r.get(6),
+        dedupe_ms: r.get(7),
+        insert_ms: r.get(8),
+        rows_fetched: r.get(9),
+        rows_parsed: r.get(10),
+        rows_kept: r.get(11),
+        rows_inserted: r.get(12),
+        rows_removed: r.get(13),
+        // Runs recorded before field reports existed have none
+        fields: serde_json::from_value(r.get(14)).unwrap_or_default(),
+    })
+}
+
+/// Get the most recent sync runs, newest first
+pub async fn get_recent_sync_runs(pool: &PgPool, limit: i64) -> Result<Vec<SyncRun>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, started_at, finished_at, status, error,
+               fetch_ms, parse_ms, dedupe_ms, insert_ms,
+               rows_fetched, rows_parsed, rows_kept, rows_inserted, rows_removed, fields
+        FROM sync_runs
+        ORDER BY started_at DESC, id DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get sync runs")?;
+
+    Ok(rows.iter().filter_map(row_to_sync_run).collect())
+}
+
+/// Get the latest `limit` sync runs started in a time range, oldest first
+pub async fn get_sync_runs_between(
+    pool: &PgPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<SyncRun>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT * FROM (
+            SELECT id, started_at, finished_at, status, error,
+                   fetch_ms, parse_ms, dedupe_ms, insert_ms,
+                   rows_fetched, rows_parsed, rows_kept, rows_inserted, rows_removed, fields
+            FROM sync_runs
+            WHERE started_at >= $1 AND started_at <= $2
+            ORDER BY started_at DESC, id DESC
+            LIMIT $3
+        ) latest
+        ORDER BY started_at ASC, id ASC
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get sync runs")?;
+
+    Ok(rows.iter().filter_map(row_to_sync_run).collect())
+}
+
+// ============================================================================
+// Sync Checkpoint Operations
+// ============================================================================
+
+/// Record the head block a sync ran up to (replacing any earlier hash for that number)
+pub async fn insert_sync_checkpoint(pool: &PgPool, checkpoint: &SyncCheckpoint) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO sync_checkpoints (block_number, block_hash, block_timestamp)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (block_number) DO UPDATE
+        SET block_hash = EXCLUDED.block_hash,
+            block_timestamp = EXCLUDED.block_timestamp,
+            created_at = NOW()
+        "#,
+    )
+    .bind(checkpoint.block_number)
+    .bind(&checkpoint.block_hash)
+    .bind(checkpoint.block_timestamp)
+    .execute(pool)
+    .await
+    .context("Failed to insert sync checkpoint")?;
+
+    Ok(())
+}
+
+/// Get the most recent sync checkpoints, highest block first
+pub async fn get_sync_checkpoints(pool: &PgPool, limit: i64) -> Result<Vec<SyncCheckpoint>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT block_number, block_hash, block_timestamp, created_at
+        FROM sync_checkpoints
+        ORDER BY block_number DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get sync checkpoints")?;
+
+    Ok(rows
+        .iter()
+        .map(|r| SyncCheckpoint {
+            block_number: r.get(0),
+            block_hash: r.get(1),
+            block_timestamp: r.get(2),
+            created_at: r.get(3),
+        })
+        .collect())
+}
+
+/// Keep only the `keep` most recent checkpoints, returning how many were deleted
+pub async fn prune_sync_checkpoints(pool: &PgPool, keep: i64) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM sync_checkpoints
+        WHERE block_number NOT IN (
+            SELECT block_number FROM sync_checkpoints ORDER BY block_number DESC LIMIT $1
+        )
+        "#,
+    )
+    .bind(keep)
+    .execute(pool)
+    .await
+    .context("Failed to prune sync checkpoints")?;
+
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// Sync Schedule Operations
+// ============================================================================
+
+/// Whether scheduled syncs are paused
+pub async fn get_sync_schedule(pool: &PgPool) -> Result<SyncSchedule> {
+    let row = sqlx::query("SELECT paused, reason, updated_at FROM sync_schedule")
+        .fetch_one(pool)
+        .await
+        .context("Failed to get sync schedule")?;
+
+    Ok(SyncSchedule { paused: row.get(0), reason: row.get(1), updated_at: row.get(2) })
+}
+
+/// Pause or resume scheduled syncs, returning the new state (resuming clears the reason)
+pub async fn set_sync_paused(
+    pool: &PgPool,
+    paused: bool,
+    reason: Option<&str>,
+) -> Result<SyncSchedule> {
+    let row = sqlx::query(
+        r#"
+        UPDATE sync_schedule
+        SET paused = $1, reason = CASE WHEN $1 THEN $2 END, updated_at = NOW()
+        RETURNING paused, reason, updated_at
+        "#,
+    )
+    .bind(paused)
+    .bind(reason)
+    .fetch_one(pool)
+    .await
+    .context("Failed to set sync schedule")?;
+
+    Ok(SyncSchedule { paused: row.get(0), reason: row.get(1), updated_at: row.get(2) })
+}
@@ -0,0 +1,116 @@
+use alloy::primitives::keccak256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use stillwater_models::{Address, TelegramChat};
+
+// ============================================================================
+// Telegram Chat Operations
+// ============================================================================
+
+/// Make a Telegram chat follow an owner, replacing the one it followed
+pub async fn link_telegram_chat(
+    executor: impl PgExecutor<'_>,
+    chat_id: &str,
+    owner: &Address,
+) -> Result<TelegramChat> {
+    let chat = sqlx::query_as::<_, TelegramChat>(
+        r#"
+        INSERT INTO telegram_chats (chat_id, owner)
+        VALUES ($1, $2)
+        ON CONFLICT (chat_id) DO UPDATE
+        SET owner = EXCLUDED.owner, linked_at = NOW()
+        RETURNING chat_id, owner, linked_at
+        "#,
+    )
+    .bind(chat_id)
+    .bind(owner)
+    .fetch_one(executor)
+    .await
+    .context("Failed to link Telegram chat")?;
+
+    Ok(chat)
+}
+
+/// Get the owner a Telegram chat follows, if any
+pub async fn get_telegram_chat(pool: &PgPool, chat_id: &str) -> Result<Option<TelegramChat>> {
+    let chat = sqlx::query_as::<_, TelegramChat>(
+        "SELECT chat_id, owner, linked_at FROM telegram_chats WHERE chat_id = $1",
+    )
+    .bind(chat_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to get Telegram chat")?;
+
+    Ok(chat)
+}
+
+// ============================================================================
+// Telegram Link Code Operations
+// ============================================================================
+
+/// Hash a link code for storage and lookup; codes themselves aren't stored
+fn hash_link_code(code: &str) -> String {
+    keccak256(code.trim().as_bytes()).to_string()
+}
+
+/// Store a one-time code letting a chat follow `owner` until `expires_at`
+pub async fn insert_telegram_link_code(
+    pool: &PgPool,
+    code: &str,
+    owner: &Address,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO telegram_link_codes (code_hash, owner, expires_at) VALUES ($1, $2, $3)",
+    )
+    .bind(hash_link_code(code))
+    .bind(owner)
+    .bind(expires_at)
+    .execute(pool)
+    .await
+    .context("Failed to insert Telegram link code")?;
+
+    Ok(())
+}
+
+/// Use up an unexpired link code, making the chat follow the owner it was issued for
+///
+/// Returns None if the code is unknown, expired or already used. The code is
+/// consumed and the chat linked in one transaction.
+pub async fn redeem_telegram_link_code(
+    pool: &PgPool,
+    code: &str,
+    chat_id: &str,
+) -> Result<Option<TelegramChat>> {
+    let mut tx = pool.begin().await.context("Failed to begin transaction")?;
+
+    let owner: Option<Address> = sqlx::query_scalar(
+        r#"
+        DELETE FROM telegram_link_codes
+        WHERE code_hash = $1 AND expires_at > NOW()
+        RETURNING owner
+        "#,
+    )
+    .bind(hash_link_code(code))
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to redeem Telegram link code")?;
+    let Some(owner) = owner else {
+        return Ok(None);
+    };
+
+    let chat = link_telegram_chat(&mut *tx, chat_id, &owner).await?;
+    tx.commit().await.context("Failed to commit Telegram link")?;
+    Ok(Some(chat))
+}
+
+/// Delete expired link codes, returning how many went
+pub async fn purge_telegram_link_codes(pool: &PgPool) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM telegram_link_codes WHERE expires_at <= NOW()")
+        .execute(pool)
+        .await
+        .context("Failed to purge Telegram link codes")?;
+
+    Ok(result.rows_affected())
+}
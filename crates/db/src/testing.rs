@@ -0,0 +1,66 @@
+//! Fixtures shared by the crate's database tests
+//!
+//! They need a disposable Postgres with TimescaleDB in `TEST_DATABASE_URL`
+//! and skip without one. Each test migrates its own schema, so it never
+//! touches an instance's tables.
+
+use alloy::primitives::I256;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_models::{Pool, Swap};
+use tokio::runtime::Runtime;
+
+use crate::{insert_pool, insert_swap};
+
+/// A runtime and a pool on a freshly migrated schema, or None without a test database
+pub(crate) fn test_database(schema: &str) -> Option<(Runtime, PgPool)> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping database test");
+        return None;
+    };
+    let runtime = Runtime::new().unwrap();
+    let db_pool = runtime.block_on(async {
+        let db_pool = crate::connect(&url, 2, Some(schema)).await.unwrap();
+        sqlx::migrate!("../../migrations").run(&db_pool).await.unwrap();
+        db_pool
+    });
+    Some((runtime, db_pool))
+}
+
+/// Store a plain 0.3% pool
+pub(crate) async fn create_test_pool(db_pool: &PgPool, pool_id: &str) {
+    let pool = Pool {
+        pool_id: pool_id.to_string(),
+        token0: format!("0x{}", "11".repeat(20)),
+        token1: format!("0x{}", "22".repeat(20)),
+        token0_decimals: 18,
+        token1_decimals: 6,
+        fee_tier: 3000,
+        tick_spacing: 60,
+        hooks: format!("0x{}", "00".repeat(20)),
+        protocol_fee: 0,
+        created_at: None,
+        created_at_block: None,
+        fee_override: None,
+    };
+    insert_pool(db_pool, &pool).await.unwrap();
+}
+
+/// Store a swap of 1000 token0 for 1000 token1 in a pool
+pub(crate) async fn create_test_swap(
+    db_pool: &PgPool,
+    pool_id: &str,
+    tx_hash: &str,
+    timestamp: DateTime<Utc>,
+) {
+    let swap = Swap {
+        id: 0, // Will be auto-generated
+        tx_hash: tx_hash.to_string(),
+        pool_id: pool_id.to_string(),
+        amount0: I256::try_from(-1000).unwrap(),
+        amount1: I256::try_from(1000).unwrap(),
+        fee: None,
+        timestamp,
+    };
+    insert_swap(db_pool, &swap).await.unwrap();
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use stillwater_models::TickSeriesChunk;
+
+// ============================================================================
+// Tick Series Operations
+// ============================================================================
+
+/// Pools and months with swaps before `before` and no compressed chunk yet, oldest first
+///
+/// Only months after a pool's latest chunk count, so swaps backfilled into an
+/// already compressed month aren't picked up again.
+pub async fn get_months_to_compress(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+) -> Result<Vec<(String, DateTime<Utc>)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.pool_id, date_trunc('month', s.timestamp, 'UTC') AS month_start
+        FROM (
+            SELECT pool_id, timestamp FROM swaps WHERE timestamp < $1
+            UNION ALL
+            SELECT pool_id, timestamp FROM swaps_archive WHERE timestamp < $1
+        ) s
+        LEFT JOIN (
+            SELECT pool_id, MAX(month_start) AS last_month
+            FROM tick_series_chunks
+            GROUP BY pool_id
+        ) c ON c.pool_id = s.pool_id
+        WHERE c.last_month IS NULL OR s.timestamp >= c.last_month + INTERVAL '1 month'
+        GROUP BY 1, 2
+        ORDER BY 2, 1
+        "#,
+    )
+    .bind(before)
+    .fetch_all(pool)
+    .await
+    .context("Failed to find months to compress")?;
+
+    Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+}
+
+/// Store a month's compressed ticks, replacing an earlier chunk for the same month
+pub async fn save_tick_chunk(
+    pool: &PgPool,
+    pool_id: &str,
+    month_start: DateTime<Utc>,
+    point_count: i32,
+    span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    data: &[u8],
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO tick_series_chunks (pool_id, month_start, point_count, first_at, last_at, data)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (pool_id, month_start) DO UPDATE
+        SET point_count = EXCLUDED.point_count,
+            first_at = EXCLUDED.first_at,
+            last_at = EXCLUDED.last_at,
+            data = EXCLUDED.data,
+            created_at = NOW()
+        "#,
+    )
+    .bind(pool_id)
+    .bind(month_start)
+    .bind(point_count)
+    .bind(span.map(|(first, _)| first))
+    .bind(span.map(|(_, last)| last))
+    .bind(data)
+    .execute(pool)
+    .await
+    .context("Failed to save tick series chunk")?;
+
+    Ok(())
+}
+
+/// Get a pool's chunks for the months overlapping [from, to], oldest first
+pub async fn get_tick_chunks(
+    pool: &PgPool,
+    pool_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<TickSeriesChunk>> {
+    let chunks = sqlx::query_as::<_, TickSeriesChunk>(
+        r#"
+        SELECT pool_id, month_start, point_count, first_at, last_at, data, created_at
+        FROM tick_series_chunks
+        WHERE pool_id = $1 AND month_start <= $3 AND month_start + INTERVAL '1 month' > $2
+        ORDER BY month_start ASC
+        "#,
+    )
+    .bind(pool_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get tick series chunks")?;
+
+    Ok(chunks)
+}
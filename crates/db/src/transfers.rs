@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{Acquire, PgPool, Postgres, Row};
+use stillwater_models::{Address, PositionTransfer};
+
+// ============================================================================
+// Position Transfer Operations
+// ============================================================================
+
+/// Record a position transfer, moving the position to the new owner
+///
+/// The owner only changes when no later transfer is recorded, so transfers
+/// applied out of order still leave the latest recipient as owner. Returns
+/// false if the transfer was already recorded. Inside a transaction it runs
+/// under a savepoint.
+pub async fn record_position_transfer(
+    conn: impl Acquire<'_, Database = Postgres>,
+    transfer: &PositionTransfer,
+) -> Result<bool> {
+    let mut tx = conn.begin().await.context("Failed to begin transaction")?;
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO position_transfers
+            (transfer_id, position_id, from_owner, to_owner, tx_hash, effective_from)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (transfer_id) DO NOTHING
+        "#,
+    )
+    .bind(&transfer.transfer_id)
+    .bind(transfer.position_id)
+    .bind(transfer.from_owner)
+    .bind(transfer.to_owner)
+    .bind(&transfer.tx_hash)
+    .bind(transfer.effective_from)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to insert position transfer")?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE positions SET owner = $2
+        WHERE id = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM position_transfers
+              WHERE position_id = $1 AND effective_from > $3
+          )
+        "#,
+    )
+    .bind(transfer.position_id)
+    .bind(transfer.to_owner)
+    .bind(transfer.effective_from)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to update position owner")?;
+
+    tx.commit().await.context("Failed to commit position transfer")?;
+    Ok(true)
+}
+
+/// Get a position's transfers, oldest first
+pub async fn get_transfers_for_position(
+    pool: &PgPool,
+    position_id: i64,
+) -> Result<Vec<PositionTransfer>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT transfer_id, position_id, from_owner, to_owner, tx_hash, effective_from
+        FROM position_transfers
+        WHERE position_id = $1
+        ORDER BY effective_from ASC, transfer_id ASC
+        "#,
+    )
+    .bind(position_id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get position transfers")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| PositionTransfer {
+            transfer_id: r.get(0),
+            position_id: r.get(1),
+            from_owner: r.get(2),
+            to_owner: r.get(3),
+            tx_hash: r.get(4),
+            effective_from: r.get(5),
+        })
+        .collect())
+}
+
+/// Get transfers in [from, to] sending positions to or from any of `owners`, oldest first
+pub async fn get_transfers_for_owners(
+    pool: &PgPool,
+    owners: &[Address],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<PositionTransfer>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT transfer_id, position_id, from_owner, to_owner, tx_hash, effective_from
+        FROM position_transfers
+        WHERE (from_owner = ANY($1) OR to_owner = ANY($1))
+          AND effective_from >= $2 AND effective_from <= $3
+        ORDER BY effective_from ASC, transfer_id ASC
+        "#,
+    )
+    .bind(owners)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context("Failed to get transfers for owners")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| PositionTransfer {
+            transfer_id: r.get(0),
+            position_id: r.get(1),
+            from_owner: r.get(2),
+            to_owner: r.get(3),
+            tx_hash: r.get(4),
+            effective_from: r.get(5),
+        })
+        .collect())
+}
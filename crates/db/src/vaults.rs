@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use stillwater_models::{Address, Vault};
+
+// ============================================================================
+// Vault Operations
+// ============================================================================
+
+/// Register a vault or update its share token and name
+pub async fn upsert_vault(
+    pool: &PgPool,
+    address: &Address,
+    share_token: &Address,
+    name: Option<&str>,
+) -> Result<Vault> {
+    let vault = sqlx::query_as::<_, Vault>(
+        r#"
+        INSERT INTO vaults (address, share_token, name)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (address) DO UPDATE
+        SET share_token = EXCLUDED.share_token, name = EXCLUDED.name
+        RETURNING address, share_token, name, created_at
+        "#,
+    )
+    .bind(address)
+    .bind(share_token)
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .context("Failed to upsert vault")?;
+
+    Ok(vault)
+}
+
+/// Get all registered vaults, by address
+pub async fn get_vaults(pool: &PgPool) -> Result<Vec<Vault>> {
+    let vaults = sqlx::query_as::<_, Vault>(
+        r#"
+        SELECT address, share_token, name, created_at
+        FROM vaults
+        ORDER BY address
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to get vaults")?;
+
+    Ok(vaults)
+}
+
+/// Unregister a vault, returning false if there was none (its positions stay)
+pub async fn delete_vault(pool: &PgPool, address: &Address) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM vaults WHERE address = $1")
+        .bind(address)
+        .execute(pool)
+        .await
+        .context("Failed to delete vault")?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Times a sync batch is run before its error is returned
+pub const SYNC_BATCH_ATTEMPTS: u32 = 3;
+
+/// Pause before the first retry of a failed batch, doubled before each later one
+const SYNC_BATCH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Run a sync batch, running it again from the start if it fails
+///
+/// A batch writes in a single transaction, so a failed attempt (a dropped
+/// connection, a failed commit) leaves nothing behind and is safe to repeat.
+pub(crate) async fn retry_batch<T, F, Fut>(batch: &str, mut run: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = SYNC_BATCH_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match run().await {
+            Err(e) if attempt < SYNC_BATCH_ATTEMPTS => {
+                warn!(
+                    "{} batch failed (attempt {} of {}), retrying: {:#}",
+                    batch, attempt, SYNC_BATCH_ATTEMPTS, e
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Batches run through the database chaos proxy, which only forwards TCP,
+    //! so `TEST_DATABASE_URL` must name a TCP host for these.
+
+    use super::*;
+    use crate::testing::test_database;
+    use sqlx::{Connection, PgConnection};
+    use std::sync::Arc;
+    use stillwater_db::chaos_database_url;
+    use stillwater_models::FaultPlan;
+
+    /// Run a batch that opens its own connection through a proxy injecting `plan`
+    ///
+    /// Each attempt connects anew, so it takes the plan's next step. A batch
+    /// that gets through records a checkpoint in `schema`; returns the outcome
+    /// and how many attempts were made.
+    async fn run_batch_through_faults(url: &str, schema: &str, plan: &str) -> (Result<()>, u32) {
+        let url = chaos_database_url(url, Arc::new(FaultPlan::parse(plan).unwrap())).await.unwrap();
+        let mut attempts = 0;
+        let result = retry_batch("Test", || {
+            attempts += 1;
+            let url = url.clone();
+            let set_search_path = format!("SET LOCAL search_path TO {}", schema);
+            async move {
+                let mut conn = PgConnection::connect(&url).await?;
+                let mut tx = conn.begin().await?;
+                sqlx::query(&set_search_path).execute(&mut *tx).await?;
+                sqlx::query(
+                    "INSERT INTO sync_checkpoints (block_number, block_hash) VALUES (1, '0x01')",
+                )
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                Ok(())
+            }
+        })
+        .await;
+        (result, attempts)
+    }
+
+    async fn checkpoints(db_pool: &sqlx::PgPool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM sync_checkpoints")
+            .fetch_one(db_pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_batch_retries_past_dropped_connections() {
+        let Some((runtime, db_pool)) = test_database("batch_faults") else {
+            return;
+        };
+        let url = std::env::var("TEST_DATABASE_URL").unwrap();
+
+        runtime.block_on(async {
+            sqlx::query("TRUNCATE sync_checkpoints").execute(&db_pool).await.unwrap();
+
+            let (result, attempts) =
+                run_batch_through_faults(&url, "batch_faults", "drop,drop,ok").await;
+
+            result.unwrap();
+            assert_eq!(attempts, SYNC_BATCH_ATTEMPTS);
+            assert_eq!(checkpoints(&db_pool).await, 1);
+        });
+    }
+
+    #[test]
+    fn test_batch_gives_up_after_its_attempts() {
+        let Some((runtime, db_pool)) = test_database("batch_faults_exhausted") else {
+            return;
+        };
+        let url = std::env::var("TEST_DATABASE_URL").unwrap();
+
+        runtime.block_on(async {
+            sqlx::query("TRUNCATE sync_checkpoints").execute(&db_pool).await.unwrap();
+
+            let (result, attempts) =
+                run_batch_through_faults(&url, "batch_faults_exhausted", "drop").await;
+
+            assert!(result.is_err());
+            assert_eq!(attempts, SYNC_BATCH_ATTEMPTS);
+            assert_eq!(checkpoints(&db_pool).await, 0);
+        });
+    }
+}
@@ -0,0 +1,172 @@
+use alloy::primitives::keccak256;
+use anyhow::{anyhow, Context, Result};
+use chrono::Duration;
+use redis::{AsyncCommands, Client as RedisClient};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::SystemTime;
+use stillwater_analytics::parse_duration;
+use tracing::warn;
+
+/// How long cached responses are reused unless `GRAPH_CACHE_TTL` is set
+pub const DEFAULT_CACHE_TTL_HOURS: i64 = 24;
+
+/// Where cached subgraph responses are kept
+#[derive(Debug, Clone)]
+pub enum CacheStore {
+    /// One JSON file per response in a directory
+    Disk(PathBuf),
+    Redis(RedisClient),
+}
+
+/// Read-through cache of subgraph responses, addressed by content
+///
+/// Entries are keyed by a hash of the query as sent, its variables and the
+/// block the endpoint had indexed, so a response is only reused for the same
+/// request against the same data. Without a known block (the endpoint's lag
+/// wasn't checked) entries are reused until `ttl` runs out. Only responses
+/// without GraphQL errors are stored; store errors are logged and treated as
+/// misses so a broken cache never fails a sync.
+#[derive(Debug, Clone)]
+pub struct SubgraphCache {
+    store: CacheStore,
+    ttl: Duration,
+}
+
+impl SubgraphCache {
+    pub fn new(store: CacheStore) -> Self {
+        Self { store, ttl: Duration::hours(DEFAULT_CACHE_TTL_HOURS) }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Cache from `GRAPH_CACHE`: a `redis://` URL or a directory; None when unset
+    ///
+    /// `GRAPH_CACHE_TTL` (e.g. `6h`, `7d`) sets how long entries live.
+    pub fn from_env() -> Result<Option<Self>> {
+        let target = match std::env::var("GRAPH_CACHE") {
+            Ok(target) if !target.trim().is_empty() => target.trim().to_string(),
+            _ => return Ok(None),
+        };
+        let store = if target.starts_with("redis://") || target.starts_with("rediss://") {
+            CacheStore::Redis(RedisClient::open(target).context("Invalid GRAPH_CACHE Redis URL")?)
+        } else {
+            CacheStore::Disk(PathBuf::from(target))
+        };
+
+        let mut cache = Self::new(store);
+        if let Ok(ttl) = std::env::var("GRAPH_CACHE_TTL") {
+            let ttl = parse_duration(&ttl).map_err(|e| anyhow!("Invalid GRAPH_CACHE_TTL: {}", e))?;
+            cache = cache.with_ttl(ttl);
+        }
+        Ok(Some(cache))
+    }
+
+    /// Content hash of a request: query as sent, variables and indexed block
+    pub fn key(query: &str, variables: &Value, block: Option<u64>) -> String {
+        let request = json!([query, variables, block]);
+        keccak256(request.to_string().as_bytes()).to_string()
+    }
+
+    /// A stored response, if there's one younger than the TTL
+    pub async fn get(&self, key: &str) -> Option<Value> {
+        let result = match &self.store {
+            CacheStore::Disk(dir) => read_file(&dir.join(format!("{}.json", key)), self.ttl).await,
+            CacheStore::Redis(client) => async {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let cached: Option<String> = conn.get(redis_key(key)).await?;
+                Ok(cached)
+            }
+            .await,
+        };
+
+        match result {
+            Ok(cached) => cached.and_then(|json| serde_json::from_str(&json).ok()),
+            Err(e) => {
+                warn!("Subgraph cache read failed for {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// Store a response under `key`
+    pub async fn put(&self, key: &str, response: &Value) {
+        let json = response.to_string();
+        let result = match &self.store {
+            CacheStore::Disk(dir) => write_file(dir, key, &json).await,
+            CacheStore::Redis(client) => async {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let ttl = self.ttl.num_seconds().max(1) as u64;
+                let () = conn.set_ex(redis_key(key), json, ttl).await?;
+                Ok(())
+            }
+            .await,
+        };
+
+        if let Err(e) = result {
+            warn!("Subgraph cache write failed for {}: {}", key, e);
+        }
+    }
+}
+
+fn redis_key(key: &str) -> String {
+    format!("stillwater:subgraph:{}", key)
+}
+
+async fn read_file(path: &PathBuf, ttl: Duration) -> Result<Option<String>> {
+    let metadata = match tokio::fs::metadata(path).await {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let age = SystemTime::now().duration_since(metadata.modified()?).unwrap_or_default();
+    if Duration::from_std(age).map_or(true, |age| age >= ttl) {
+        return Ok(None);
+    }
+    Ok(Some(tokio::fs::read_to_string(path).await?))
+}
+
+/// Write through a temporary file so a concurrent reader never sees half a response
+async fn write_file(dir: &PathBuf, key: &str, json: &str) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let temp = dir.join(format!("{}.json.tmp", key));
+    tokio::fs::write(&temp, json).await?;
+    tokio::fs::rename(&temp, dir.join(format!("{}.json", key))).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_covers_query_variables_and_block() {
+        let variables = json!({ "poolId": "0xpool", "timestamp": "1700000000" });
+        let key = SubgraphCache::key("query { swaps }", &variables, Some(100));
+        assert_eq!(key, SubgraphCache::key("query { swaps }", &variables, Some(100)));
+        assert_ne!(key, SubgraphCache::key("query { swaps }", &variables, Some(101)));
+        assert_ne!(key, SubgraphCache::key("query { swaps }", &variables, None));
+        assert_ne!(key, SubgraphCache::key("query { positions }", &variables, Some(100)));
+        let other = json!({ "poolId": "0xpool", "timestamp": "1700000001" });
+        assert_ne!(key, SubgraphCache::key("query { swaps }", &other, Some(100)));
+    }
+
+    #[tokio::test]
+    async fn test_disk_store_round_trip_and_expiry() {
+        let dir = std::env::temp_dir().join(format!("stillwater-cache-{}", std::process::id()));
+        let cache = SubgraphCache::new(CacheStore::Disk(dir.clone()));
+        let key = SubgraphCache::key("query { swaps }", &json!({}), Some(1));
+        let response = json!({ "data": { "swaps": [] } });
+
+        assert_eq!(cache.get(&key).await, None);
+        cache.put(&key, &response).await;
+        assert_eq!(cache.get(&key).await, Some(response));
+
+        let expired = cache.clone().with_ttl(Duration::zero());
+        assert_eq!(expired.get(&key).await, None);
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Result};
+use stillwater_models::Fault;
+use tracing::warn;
+
+/// Act out an injected fault before a subgraph request is sent
+///
+/// Returns the page size to cut the response's lists to for `PartialPage`,
+/// an error for the faults that fail the request, and nothing once a
+/// `Latency` delay has passed.
+pub(crate) async fn apply_fault(fault: Fault) -> Result<Option<usize>> {
+    warn!("Injecting subgraph fault: {:?}", fault);
+    match fault {
+        Fault::Timeout(wait) => {
+            tokio::time::sleep(wait).await;
+            Err(anyhow!("GraphQL request timed out after {:?} (injected)", wait))
+        }
+        Fault::Status(status) => Err(anyhow!(
+            "GraphQL request failed with status {}: injected fault",
+            status
+        )),
+        Fault::ConnectionDrop => Err(anyhow!("Connection reset by peer (injected)")),
+        Fault::Latency(wait) => {
+            tokio::time::sleep(wait).await;
+            Ok(None)
+        }
+        Fault::PartialPage(limit) => Ok(Some(limit)),
+    }
+}
+
+/// Truncate every list in a GraphQL `data` object to `limit` items, recursively
+pub(crate) fn truncate_lists(value: &mut serde_json::Value, limit: usize) {
+    match value {
+        serde_json::Value::Array(items) => {
+            items.truncate(limit);
+            for item in items {
+                truncate_lists(item, limit);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                truncate_lists(field, limit);
+            }
+        }
+        _ => {}
+    }
+}
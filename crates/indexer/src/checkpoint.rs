@@ -0,0 +1,321 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use stillwater_db::{
+    ChainRollback, get_sync_checkpoints, insert_sync_checkpoint, prune_sync_checkpoints,
+    roll_back_chain_data,
+};
+use stillwater_models::SyncCheckpoint;
+use tracing::{info, warn};
+
+use crate::{GraphIndexer, MetaBlockResponse};
+
+/// Checkpoints kept, and searched for a common ancestor after a reorg
+pub const CHECKPOINT_HISTORY: i64 = 100;
+
+/// Outcome of re-checking stored checkpoints against the subgraph's chain
+#[derive(Debug, Default)]
+pub struct CheckpointReconciliation {
+    /// Latest checkpoint whose hash is still canonical
+    pub ancestor: Option<SyncCheckpoint>,
+    /// Checkpoints whose block was reorged out, highest first (now deleted)
+    pub rolled_back: Vec<SyncCheckpoint>,
+    /// Time data was rolled back to; the next sync should re-fetch from here
+    pub resume_from: Option<DateTime<Utc>>,
+    /// Rows after `resume_from` deleted or restored so the re-fetch replaces them
+    pub rolled_back_rows: ChainRollback,
+}
+
+/// Verify the stored checkpoints' hashes and roll back past any reorged block
+///
+/// Checkpoints are checked highest block first against the hash the subgraph
+/// now has at that number; the first match is the last common ancestor and
+/// every checkpoint above it is deleted. Block-derived data after the
+/// ancestor's block time (or the oldest rolled-back checkpoint's, when none
+/// matches) is rolled back with them in one transaction (see
+/// `roll_back_chain_data`), so the sync re-fetches it from the canonical
+/// chain. Stops without rolling back when the subgraph doesn't report hashes.
+pub async fn reconcile_checkpoints(
+    indexer: &GraphIndexer,
+    db_pool: &PgPool,
+) -> Result<CheckpointReconciliation> {
+    let checkpoints = get_sync_checkpoints(db_pool, CHECKPOINT_HISTORY).await?;
+    let mut reconciliation = CheckpointReconciliation::default();
+
+    for checkpoint in checkpoints {
+        let block = indexer.fetch_block(checkpoint.block_number as u64).await?;
+        let Some(hash) = block.hash else {
+            warn!("Subgraph doesn't report block hashes, skipping reorg check");
+            return Ok(CheckpointReconciliation::default());
+        };
+        if hash.eq_ignore_ascii_case(&checkpoint.block_hash) {
+            reconciliation.ancestor = Some(checkpoint);
+            break;
+        }
+        warn!(
+            "Checkpoint block {} was reorged out ({} is now {})",
+            checkpoint.block_number, checkpoint.block_hash, hash
+        );
+        reconciliation.rolled_back.push(checkpoint);
+    }
+
+    if reconciliation.rolled_back.is_empty() {
+        return Ok(reconciliation);
+    }
+
+    let ancestor_block = reconciliation.ancestor.as_ref().map(|a| a.block_number);
+    reconciliation.resume_from = match &reconciliation.ancestor {
+        Some(ancestor) => ancestor.block_timestamp,
+        None => reconciliation.rolled_back.last().and_then(|c| c.block_timestamp),
+    };
+    let rows = roll_back_chain_data(db_pool, ancestor_block, reconciliation.resume_from).await?;
+    info!(
+        "Rolled back {} checkpoints to block {:?}: {} swaps, {} liquidity events \
+         ({} positions restored), {} transfers, {} gas expenses, {} pool initializations",
+        reconciliation.rolled_back.len(),
+        ancestor_block,
+        rows.swaps,
+        rows.liquidity_events,
+        rows.positions_restored,
+        rows.position_transfers,
+        rows.gas_expenses,
+        rows.pool_initializations
+    );
+    reconciliation.rolled_back_rows = rows;
+
+    Ok(reconciliation)
+}
+
+/// Store the head block a successful sync ran up to, keeping `CHECKPOINT_HISTORY` of them
+///
+/// Returns false without storing anything when the block has no hash.
+pub async fn record_checkpoint(db_pool: &PgPool, head: &MetaBlockResponse) -> Result<bool> {
+    let Some(hash) = &head.hash else {
+        return Ok(false);
+    };
+    let checkpoint = SyncCheckpoint {
+        block_number: head.number as i64,
+        block_hash: hash.clone(),
+        block_timestamp: head.block_time(),
+        created_at: Utc::now(),
+    };
+    insert_sync_checkpoint(db_pool, &checkpoint).await?;
+    prune_sync_checkpoints(db_pool, CHECKPOINT_HISTORY).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, test_database};
+    use serde_json::json;
+    use sqlx::Row;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use stillwater_models::FaultPlan;
+
+    const POOL: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+    const OLD_OWNER: &str = "0x1111111111111111111111111111111111111111";
+    const NEW_OWNER: &str = "0x2222222222222222222222222222222222222222";
+    /// ID the position gets on freshly truncated tables
+    const POSITION_ID: i64 = 1;
+
+    /// Block time of the last common ancestor
+    const ANCESTOR_TIME: i64 = 1_700_000_000;
+
+    /// Start a subgraph stub answering `_meta` block lookups with `hashes`, returning its URL
+    async fn fake_blocks(hashes: HashMap<u64, &'static str>) -> String {
+        let (url, _) = testing::fake_subgraph(move |request| {
+            let number = request["variables"]["number"].as_u64().unwrap();
+            let block = json!({ "number": number, "hash": hashes.get(&number) });
+            (200, json!({ "data": { "_meta": { "block": block } } }))
+        })
+        .await;
+        url
+    }
+
+    fn at(offset: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(ANCESTOR_TIME + offset, 0).unwrap()
+    }
+
+    fn create_test_checkpoint(block_number: i64, hash: &str, offset: i64) -> SyncCheckpoint {
+        SyncCheckpoint {
+            block_number,
+            block_hash: hash.to_string(),
+            block_timestamp: Some(at(offset)),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Store a pool and a closed position on it, with activity on both sides of the ancestor
+    ///
+    /// Before the ancestor: the position's mint, a swap and a gas expense.
+    /// After it: a transfer to `NEW_OWNER`, a swap, a removal of all 1000
+    /// liquidity, its gas, a minute aggregate and a new pool's initialization.
+    async fn create_test_chain_data(db_pool: &PgPool) {
+        let statements = [
+            "TRUNCATE pools, sync_checkpoints RESTART IDENTITY CASCADE".to_string(),
+            format!(
+                "INSERT INTO pools (pool_id, token0, token1, fee_tier, tick_spacing) \
+                 VALUES ('{POOL}', '{OLD_OWNER}', '{NEW_OWNER}', 3000, 60)"
+            ),
+            format!(
+                "INSERT INTO positions \
+                 (nft_id, owner, pool_id, tick_lower, tick_upper, liquidity, \
+                  created_at, closed_at) \
+                 VALUES ('1', '{NEW_OWNER}', '{POOL}', -60, 60, 0, '{}', '{}')",
+                at(-1000),
+                at(60)
+            ),
+            format!(
+                "INSERT INTO liquidity_events \
+                 (event_id, owner, pool_id, tick_lower, tick_upper, liquidity_delta, kind, \
+                  position_id, timestamp) \
+                 VALUES ('mint', '{OLD_OWNER}', '{POOL}', -60, 60, 1000, 'add', 1, '{}'), \
+                        ('burn', '{NEW_OWNER}', '{POOL}', -60, 60, -1000, 'remove', 1, '{}')",
+                at(-1000),
+                at(60)
+            ),
+            format!(
+                "INSERT INTO position_transfers \
+                 (transfer_id, position_id, from_owner, to_owner, effective_from) \
+                 VALUES ('transfer', 1, '{OLD_OWNER}', '{NEW_OWNER}', '{}')",
+                at(30)
+            ),
+            format!(
+                "INSERT INTO swaps (tx_hash, pool_id, amount0, amount1, timestamp) \
+                 VALUES ('0x01', '{POOL}', 1, -1, '{}'), ('0x02', '{POOL}', -1, 1, '{}')",
+                at(-10),
+                at(30)
+            ),
+            format!(
+                "INSERT INTO gas_expenses (position_id, tx_hash, gas_cost, timestamp) \
+                 VALUES (1, '0x03', 1, '{}'), (1, '0x04', 1, '{}')",
+                at(-1000),
+                at(60)
+            ),
+            format!(
+                "INSERT INTO swap_minute_aggregates (pool_id, minute, swap_count) \
+                 VALUES ('{POOL}', '{}', 5)",
+                at(60)
+            ),
+            format!(
+                "INSERT INTO pool_initializations \
+                 (pool_id, sqrt_price_x96, tick, block_number, created_at) \
+                 VALUES ('{POOL}', 79228162514264337593543950336, 0, 105, '{}')",
+                at(90)
+            ),
+            "INSERT INTO position_fee_accumulators (position_id) VALUES (1)".to_string(),
+        ];
+        for statement in statements {
+            sqlx::query(&statement).execute(db_pool).await.unwrap();
+        }
+        insert_sync_checkpoint(db_pool, &create_test_checkpoint(100, "0xaa", 0)).await.unwrap();
+        insert_sync_checkpoint(db_pool, &create_test_checkpoint(110, "0xbb", 120)).await.unwrap();
+    }
+
+    async fn count(db_pool: &PgPool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(db_pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_reorg_rolls_back_every_table_past_the_ancestor() {
+        let Some((runtime, db_pool)) = test_database("reorg_rollback") else {
+            return;
+        };
+
+        runtime.block_on(async {
+            create_test_chain_data(&db_pool).await;
+            // Block 110 was replaced; block 100 is still canonical
+            let url = fake_blocks(HashMap::from([(100, "0xAA"), (110, "0xcc")])).await;
+            let indexer = GraphIndexer::new(url);
+
+            let reconciliation = reconcile_checkpoints(&indexer, &db_pool).await.unwrap();
+
+            assert_eq!(reconciliation.ancestor.unwrap().block_number, 100);
+            assert_eq!(reconciliation.rolled_back.len(), 1);
+            assert_eq!(reconciliation.resume_from, Some(at(0)));
+            let rows = reconciliation.rolled_back_rows;
+            assert_eq!(rows.checkpoints, 1);
+            assert_eq!(rows.swaps, 1);
+            assert_eq!(rows.swap_minute_aggregates, 1);
+            assert_eq!(rows.liquidity_events, 1);
+            assert_eq!(rows.positions_restored, 1);
+            assert_eq!(rows.position_transfers, 1);
+            assert_eq!(rows.owners_restored, 1);
+            assert_eq!(rows.gas_expenses, 1);
+            assert_eq!(rows.pool_initializations, 1);
+            assert_eq!(rows.fee_accumulators, 1);
+
+            // Only what predates the ancestor is left
+            assert_eq!(count(&db_pool, "sync_checkpoints").await, 1);
+            assert_eq!(count(&db_pool, "swaps").await, 1);
+            assert_eq!(count(&db_pool, "liquidity_events").await, 1);
+            assert_eq!(count(&db_pool, "gas_expenses").await, 1);
+            assert_eq!(count(&db_pool, "position_transfers").await, 0);
+            assert_eq!(count(&db_pool, "swap_minute_aggregates").await, 0);
+            assert_eq!(count(&db_pool, "pool_initializations").await, 0);
+
+            // The position is open again, with its liquidity and owner from before the reorg
+            let row = sqlx::query(
+                "SELECT liquidity::text, owner, closed_at FROM positions WHERE id = $1",
+            )
+            .bind(POSITION_ID)
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+            assert_eq!(row.get::<String, _>(0), "1000");
+            assert_eq!(row.get::<String, _>(1), OLD_OWNER);
+            assert_eq!(row.get::<Option<DateTime<Utc>>, _>(2), None);
+        });
+    }
+
+    #[test]
+    fn test_canonical_checkpoints_roll_back_nothing() {
+        let Some((runtime, db_pool)) = test_database("reorg_canonical") else {
+            return;
+        };
+
+        runtime.block_on(async {
+            create_test_chain_data(&db_pool).await;
+            let url = fake_blocks(HashMap::from([(100, "0xaa"), (110, "0xbb")])).await;
+            let indexer = GraphIndexer::new(url);
+
+            let reconciliation = reconcile_checkpoints(&indexer, &db_pool).await.unwrap();
+
+            assert_eq!(reconciliation.ancestor.unwrap().block_number, 110);
+            assert!(reconciliation.rolled_back.is_empty());
+            assert_eq!(reconciliation.rolled_back_rows, ChainRollback::default());
+            assert_eq!(count(&db_pool, "swaps").await, 2);
+            assert_eq!(count(&db_pool, "liquidity_events").await, 2);
+        });
+    }
+
+    #[test]
+    fn test_failed_block_lookup_rolls_back_nothing() {
+        let Some((runtime, db_pool)) = test_database("reorg_faults") else {
+            return;
+        };
+
+        runtime.block_on(async {
+            create_test_chain_data(&db_pool).await;
+            let url = fake_blocks(HashMap::from([(100, "0xaa"), (110, "0xcc")])).await;
+            // Block 110's lookup gets through, block 100's fails; the next run is healthy
+            let plan = FaultPlan::parse("ok,500,ok").unwrap();
+            let indexer = GraphIndexer::new(url).with_faults(Arc::new(plan));
+
+            assert!(reconcile_checkpoints(&indexer, &db_pool).await.is_err());
+            assert_eq!(count(&db_pool, "sync_checkpoints").await, 2);
+            assert_eq!(count(&db_pool, "swaps").await, 2);
+            assert_eq!(count(&db_pool, "position_transfers").await, 1);
+
+            let reconciliation = reconcile_checkpoints(&indexer, &db_pool).await.unwrap();
+            assert_eq!(reconciliation.ancestor.unwrap().block_number, 100);
+            assert_eq!(reconciliation.rolled_back_rows.swaps, 1);
+            assert_eq!(count(&db_pool, "sync_checkpoints").await, 1);
+        });
+    }
+}
@@ -0,0 +1,105 @@
+use alloy::primitives::Address;
+use anyhow::Result;
+use sqlx::PgPool;
+use stillwater_db::{
+    get_log_scan_cursor, insert_pool, record_pool_initialization, set_log_scan_cursor,
+};
+use stillwater_models::{BlockchainService, Pool, PoolInitialization, LOG_SCAN_CHUNK_BLOCKS};
+use tracing::info;
+
+use crate::scan::token_decimals;
+
+/// Name of the pool creation scan's entry in `log_scan_cursors`
+pub const POOL_CREATION_CURSOR: &str = "pool_initialize";
+
+/// A pool whose creation was just recorded
+#[derive(Debug, Clone)]
+pub struct CreatedPool {
+    pub pool: Pool,
+    pub initialization: PoolInitialization,
+}
+
+/// Store the pools the PoolManager created between two blocks (inclusive)
+///
+/// Each pool is stored with its creation block and time, overriding whatever a
+/// sync recorded, and its starting price is kept. Returns the pools whose
+/// creation wasn't recorded before.
+pub async fn ingest_pool_creations(
+    db_pool: &PgPool,
+    blockchain: &BlockchainService,
+    pool_manager: Address,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<CreatedPool>> {
+    let initializations =
+        blockchain.get_pool_initializations(pool_manager, from_block, to_block).await?;
+
+    let mut created = Vec::new();
+    for init in initializations {
+        let token0 = init.token0.parse::<Address>()?;
+        let token1 = init.token1.parse::<Address>()?;
+        let pool = Pool {
+            pool_id: init.pool_id.clone(),
+            token0: init.token0.clone(),
+            token1: init.token1.clone(),
+            token0_decimals: token_decimals(blockchain, token0).await,
+            token1_decimals: token_decimals(blockchain, token1).await,
+            fee_tier: init.fee_tier,
+            tick_spacing: init.tick_spacing,
+            hooks: init.hooks.clone(),
+            protocol_fee: 0, // Read from pool state by the sync binary
+            created_at: Some(init.created_at),
+            created_at_block: Some(init.block_number),
+            fee_override: None,
+        };
+        insert_pool(db_pool, &pool).await?;
+        if record_pool_initialization(db_pool, &init).await? {
+            info!("Pool {} created at block {}", pool.pool_id, init.block_number);
+            created.push(CreatedPool { pool, initialization: init });
+        }
+    }
+    Ok(created)
+}
+
+/// Follows pool creations block by block, resuming where it last stopped
+///
+/// Progress is kept in `log_scan_cursors`, so a restart picks up the pools
+/// created while it was down. Each `scan` reads at most
+/// `LOG_SCAN_CHUNK_BLOCKS` blocks, so catching up never stalls the caller.
+#[derive(Debug, Clone)]
+pub struct PoolCreationScanner {
+    pool_manager: Address,
+    /// First block not scanned yet
+    next_block: u64,
+}
+
+impl PoolCreationScanner {
+    /// Resume from the stored cursor, or start at `start_block` on the first run
+    pub async fn resume(db_pool: &PgPool, pool_manager: Address, start_block: u64) -> Result<Self> {
+        let next_block = match get_log_scan_cursor(db_pool, POOL_CREATION_CURSOR).await? {
+            Some(block) => block as u64 + 1,
+            None => start_block,
+        };
+        Ok(Self { pool_manager, next_block })
+    }
+
+    /// Store pools created since the last scan, up to `head` at most
+    pub async fn scan(
+        &mut self,
+        db_pool: &PgPool,
+        blockchain: &BlockchainService,
+        head: u64,
+    ) -> Result<Vec<CreatedPool>> {
+        if head < self.next_block {
+            return Ok(Vec::new());
+        }
+        let to_block = head.min(self.next_block + LOG_SCAN_CHUNK_BLOCKS - 1);
+        let (from_block, pool_manager) = (self.next_block, self.pool_manager);
+        let created =
+            ingest_pool_creations(db_pool, blockchain, pool_manager, from_block, to_block).await?;
+
+        set_log_scan_cursor(db_pool, POOL_CREATION_CURSOR, to_block as i64).await?;
+        self.next_block = to_block + 1;
+        Ok(created)
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+use crate::types::{PositionResponse, PositionsData, SwapResponse, SwapsData};
+
+/// Maps a custom query's `data` object to standard responses
+pub type ResponseMapper<T> = Arc<dyn Fn(serde_json::Value) -> Result<Vec<T>> + Send + Sync>;
+
+/// Position queries that can be overridden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositionQuery {
+    /// `fetch_positions_by_owner`; receives `$owner`
+    ByOwner,
+    /// `fetch_positions_by_pool`; receives `$poolId`
+    ByPool,
+    /// `fetch_recent_positions` (sync); receives `$timestamp`
+    Recent,
+}
+
+/// A replacement GraphQL query for a custom subgraph
+///
+/// The query receives the same variables as the one it replaces, plus any
+/// added with `with_variables` (e.g. extra filters), which win on conflict.
+/// Its `data` is turned into standard responses by the mapper, so results go
+/// through the usual conversion, filtering and database pipeline.
+#[derive(Clone)]
+pub struct CustomQuery<T> {
+    pub query: String,
+    pub variables: serde_json::Map<String, serde_json::Value>,
+    mapper: ResponseMapper<T>,
+}
+
+impl<T> CustomQuery<T> {
+    /// Use `mapper` instead of the standard response shape
+    pub fn with_mapper(
+        mut self,
+        mapper: impl Fn(serde_json::Value) -> Result<Vec<T>> + Send + Sync + 'static,
+    ) -> Self {
+        self.mapper = Arc::new(mapper);
+        self
+    }
+
+    /// Add variables sent with every request of this query
+    pub fn with_variables(mut self, variables: serde_json::Value) -> Self {
+        if let serde_json::Value::Object(map) = variables {
+            self.variables.extend(map);
+        }
+        self
+    }
+
+    /// Standard variables merged with this query's own
+    pub(crate) fn variables_with(&self, standard: serde_json::Value) -> serde_json::Value {
+        let mut merged = match standard {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        merged.extend(self.variables.clone());
+        serde_json::Value::Object(merged)
+    }
+
+    pub(crate) fn map(&self, data: serde_json::Value) -> Result<Vec<T>> {
+        (self.mapper)(data)
+    }
+}
+
+fn standard_mapper<D, T>(extract: fn(D) -> Vec<T>) -> ResponseMapper<T>
+where
+    D: DeserializeOwned + 'static,
+    T: 'static,
+{
+    Arc::new(move |data| {
+        let data: D = serde_json::from_value(data).context("Unexpected custom query response")?;
+        Ok(extract(data))
+    })
+}
+
+impl CustomQuery<PositionResponse> {
+    /// A position query returning `modifyLiquidities` like the standard ones
+    pub fn positions(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            variables: serde_json::Map::new(),
+            mapper: standard_mapper(|data: PositionsData| data.positions.items),
+        }
+    }
+}
+
+impl CustomQuery<SwapResponse> {
+    /// A swaps query returning `swaps` like the standard one
+    pub fn swaps(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            variables: serde_json::Map::new(),
+            mapper: standard_mapper(|data: SwapsData| data.swaps.items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use serde_json::json;
+
+    fn swap_json(id: &str) -> serde_json::Value {
+        let mut swap = testing::swap_json();
+        swap["id"] = json!(id);
+        swap
+    }
+
+    #[test]
+    fn test_standard_variables_pass_through() {
+        let query = CustomQuery::swaps("query { swaps { id } }");
+        let merged = query.variables_with(json!({ "timestamp": "1700000000" }));
+        assert_eq!(merged, json!({ "timestamp": "1700000000" }));
+    }
+
+    #[test]
+    fn test_custom_variables_are_added_and_win_on_conflict() {
+        let query = CustomQuery::positions("query { modifyLiquidities { id } }")
+            .with_variables(json!({ "owner": "0xcustom", "minAmount": "1" }));
+        let merged = query.variables_with(json!({ "owner": "0xstandard", "poolId": "0xpool" }));
+        assert_eq!(merged, json!({ "owner": "0xcustom", "minAmount": "1", "poolId": "0xpool" }));
+    }
+
+    #[test]
+    fn test_with_variables_accumulates_and_ignores_non_objects() {
+        let query = CustomQuery::swaps("query { swaps { id } }")
+            .with_variables(json!({ "first": 100, "skip": 0 }))
+            .with_variables(json!(["ignored"]))
+            .with_variables(json!({ "first": 500 }));
+        assert_eq!(query.variables_with(json!({})), json!({ "first": 500, "skip": 0 }));
+    }
+
+    #[test]
+    fn test_non_object_standard_variables_are_replaced() {
+        let query = CustomQuery::swaps("query { swaps { id } }").with_variables(json!({ "a": 1 }));
+        assert_eq!(query.variables_with(serde_json::Value::Null), json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn test_standard_mapper_reads_the_usual_shape() {
+        let query = CustomQuery::swaps("query { swaps { id } }");
+        let swaps = query.map(json!({ "swaps": [swap_json("0xswap-0"), swap_json("0xswap-1")] }));
+        let ids: Vec<_> = swaps.unwrap().into_iter().map(|swap| swap.id).collect();
+        assert_eq!(ids, ["0xswap-0", "0xswap-1"]);
+
+        let error = query.map(json!({ "trades": [] })).unwrap_err();
+        assert!(error.to_string().contains("Unexpected custom query response"), "{error}");
+    }
+
+    #[test]
+    fn test_custom_mapper_replaces_the_standard_one() {
+        let query = CustomQuery::swaps("query { trades { id } }")
+            .with_mapper(|data| Ok(serde_json::from_value(data["trades"]["nodes"].clone())?));
+        let swaps = query.map(json!({ "trades": { "nodes": [swap_json("0xtrade")] } })).unwrap();
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].id, "0xtrade");
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::queries;
+
+/// Relation fields the standard queries filter on; Subsquid matches them by nested `id_eq`
+const RELATION_FIELDS: [&str; 1] = ["pool"];
+
+/// GraphQL dialect of a subgraph-compatible endpoint
+///
+/// The standard queries are written for graph-node (The Graph, Goldsky) and
+/// rewritten for the others before they're sent, so an endpoint of another
+/// dialect answers them instead of erroring or returning nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointDialect {
+    /// graph-node behind The Graph's gateway or self-hosted
+    #[default]
+    TheGraph,
+    /// Goldsky-hosted subgraphs: graph-node syntax, without The Graph's gateway key
+    Goldsky,
+    /// Subsquid (SQD) OpenReader APIs serving the same entities
+    Subsquid,
+}
+
+impl EndpointDialect {
+    /// Parse a `GRAPH_API_DIALECT` value
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "thegraph" | "graph" | "graph-node" => Some(EndpointDialect::TheGraph),
+            "goldsky" => Some(EndpointDialect::Goldsky),
+            "subsquid" | "sqd" => Some(EndpointDialect::Subsquid),
+            _ => None,
+        }
+    }
+
+    /// Dialect of a known hosted service, from the endpoint's host
+    pub fn detect(url: &str) -> Option<Self> {
+        let host = url.split("://").nth(1).unwrap_or(url).split(['/', ':']).next()?;
+        let host = host.to_lowercase();
+        let on = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+        if on("thegraph.com") {
+            Some(EndpointDialect::TheGraph)
+        } else if on("goldsky.com") {
+            Some(EndpointDialect::Goldsky)
+        } else if on("subsquid.io") || on("sqd.dev") {
+            Some(EndpointDialect::Subsquid)
+        } else {
+            None
+        }
+    }
+
+    /// Largest page (`first`) a query may ask for; None when uncapped
+    pub fn max_page_size(&self) -> Option<u32> {
+        match self {
+            EndpointDialect::TheGraph | EndpointDialect::Goldsky => Some(1000),
+            EndpointDialect::Subsquid => None,
+        }
+    }
+
+    /// Whether `GRAPH_API_KEY` may be sent (it's only valid on The Graph's gateway)
+    pub fn accepts_graph_api_key(&self) -> bool {
+        *self == EndpointDialect::TheGraph
+    }
+
+    /// Rewrite a graph-node query for this dialect
+    ///
+    /// Fails for queries the dialect can't express, such as reading a past
+    /// block's metadata from Subsquid, so the caller can try another endpoint.
+    pub fn translate(&self, query: &str) -> Result<String> {
+        let limited = match self.max_page_size() {
+            Some(max) => clamp_page_size(query, max),
+            None => query.to_string(),
+        };
+        match self {
+            EndpointDialect::TheGraph | EndpointDialect::Goldsky => Ok(limited),
+            EndpointDialect::Subsquid => to_subsquid(&limited),
+        }
+    }
+
+    /// Reshape response data into the graph-node shape the response types expect
+    pub fn normalize_data(&self, data: &mut Value) {
+        if *self != EndpointDialect::Subsquid {
+            return;
+        }
+        if let Some(height) = data.get("squidStatus").and_then(|s| s.get("height")).cloned() {
+            *data = json!({ "_meta": { "block": { "number": height } } });
+        }
+    }
+}
+
+/// Lower any `first: N` above the dialect's cap, which graph-node rejects
+fn clamp_page_size(query: &str, max: u32) -> String {
+    query
+        .split('\n')
+        .map(|line| match line.trim().strip_prefix("first: ").map(str::parse::<u32>) {
+            Some(Ok(size)) if size > max => line.replace(&size.to_string(), &max.to_string()),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rewrite graph-node syntax into Subsquid's OpenReader syntax, line by line
+///
+/// `first` becomes `limit`, `orderBy`/`orderDirection` pairs become a single
+/// `orderBy: field_DIRECTION`, equality filters gain `_eq` (relations
+/// `{ id_eq: ... }`, inequalities `_not_eq`), and the indexed block comes from
+/// `squidStatus`.
+fn to_subsquid(query: &str) -> Result<String> {
+    if query.contains("_meta(") {
+        return Err(anyhow!("Subsquid endpoints can't query past blocks"));
+    }
+    if query.contains("_meta {") {
+        return Ok(queries::SQUID_STATUS.to_string());
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut order_by: Option<usize> = None;
+    for line in query.split('\n') {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        if let Some(size) = trimmed.strip_prefix("first: ") {
+            lines.push(format!("{}limit: {}", indent, size));
+        } else if trimmed.starts_with("orderBy: ") {
+            order_by = Some(lines.len());
+            lines.push(line.to_string());
+        } else if let Some(direction) = trimmed.strip_prefix("orderDirection: ") {
+            let index = order_by.take().ok_or_else(|| anyhow!("orderDirection without orderBy"))?;
+            lines[index] = format!("{}_{}", lines[index], direction.trim().to_uppercase());
+        } else if let Some(filters) =
+            trimmed.strip_prefix("where: {").and_then(|f| f.trim_end().strip_suffix('}'))
+        {
+            lines.push(format!("{}where: {{ {} }}", indent, subsquid_filters(filters)));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+fn subsquid_filters(filters: &str) -> String {
+    filters
+        .split(',')
+        .map(|filter| {
+            let (field, value) = filter.split_once(':').unwrap_or((filter, ""));
+            let (field, value) = (field.trim(), value.trim());
+            if RELATION_FIELDS.contains(&field) {
+                format!("{}: {{ id_eq: {} }}", field, value)
+            } else if field.ends_with("_not") {
+                format!("{}_eq: {}", field, value)
+            } else if field.contains('_') {
+                format!("{}: {}", field, value)
+            } else {
+                format!("{}_eq: {}", field, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialect_is_detected_from_the_host() {
+        let detect = EndpointDialect::detect;
+        assert_eq!(
+            detect("https://gateway.thegraph.com/api/key/subgraphs/id/abc"),
+            Some(EndpointDialect::TheGraph)
+        );
+        assert_eq!(
+            detect("https://api.goldsky.com/api/public/project_x/subgraphs/v4/1.0/gn"),
+            Some(EndpointDialect::Goldsky)
+        );
+        assert_eq!(
+            detect("https://v4.squids.live.sqd.dev/graphql"),
+            Some(EndpointDialect::Subsquid)
+        );
+        assert_eq!(detect("http://localhost:8000/subgraphs/name/v4"), None);
+        assert_eq!(detect("https://goldsky.com.evil.example/graphql"), None);
+    }
+
+    #[test]
+    fn test_subsquid_rewrites_filters_ordering_and_paging() {
+        let query = EndpointDialect::Subsquid.translate(queries::POSITIONS_BY_POOL).unwrap();
+        assert!(query.contains("where: { pool: { id_eq: $poolId }, amount_not_eq: \"0\" }"));
+        assert!(query.contains("orderBy: timestamp_DESC"));
+        assert!(query.contains("limit: 100"));
+        assert!(!query.contains("orderDirection") && !query.contains("first:"));
+
+        let query = EndpointDialect::Subsquid.translate(queries::POSITIONS_BY_OWNER).unwrap();
+        assert!(query.contains("where: { origin_eq: $owner, amount_not_eq: \"0\" }"));
+
+        let query = EndpointDialect::Subsquid.translate(&queries::batched_swaps_query(2)).unwrap();
+        assert!(query.contains("where: { pool: { id_eq: $p1 }, timestamp_gte: $timestamp }"));
+        assert!(query.contains("orderBy: timestamp_ASC"));
+    }
+
+    #[test]
+    fn test_subsquid_block_metadata() {
+        let query = EndpointDialect::Subsquid.translate(queries::INDEXED_BLOCK).unwrap();
+        assert!(query.contains("squidStatus") && !query.contains("_meta"));
+        assert!(EndpointDialect::Subsquid.translate(queries::BLOCK_AT).is_err());
+
+        let mut data = json!({ "squidStatus": { "height": 1234 } });
+        EndpointDialect::Subsquid.normalize_data(&mut data);
+        assert_eq!(data["_meta"]["block"]["number"], 1234);
+    }
+
+    #[test]
+    fn test_graph_node_pages_are_capped() {
+        let query = "  transfers(\n    first: 5000\n  ) {\n    id\n  }";
+        let translated = EndpointDialect::Goldsky.translate(query).unwrap();
+        assert!(translated.contains("first: 1000"));
+        let unchanged = EndpointDialect::TheGraph.translate(queries::RECENT_TRANSFERS).unwrap();
+        assert_eq!(unchanged, queries::RECENT_TRANSFERS);
+    }
+}
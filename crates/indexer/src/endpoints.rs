@@ -0,0 +1,377 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::dialect::EndpointDialect;
+
+/// Chain the indexer syncs when `CHAIN_ID` isn't set (Unichain Sepolia)
+const DEFAULT_CHAIN_ID: u64 = 1301;
+
+/// Cooldown after an endpoint's first consecutive failure; doubles per failure
+const BASE_COOLDOWN_SECS: i64 = 30;
+
+/// Longest an endpoint is benched after repeated failures
+const MAX_COOLDOWN_SECS: i64 = 600;
+
+/// Blocks an endpoint may trail the most up-to-date one before it counts as lagging
+pub const MAX_LAG_BLOCKS: u64 = 50;
+
+/// A subgraph endpoint, optionally authenticated against The Graph's gateway
+#[derive(Debug, Clone)]
+pub struct SubgraphEndpoint {
+    pub url: String,
+    /// Gateway API key, sent as `Authorization: Bearer <key>`
+    pub api_key: Option<String>,
+    /// Query dialect, detected from the host for known services
+    pub dialect: EndpointDialect,
+}
+
+impl SubgraphEndpoint {
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        let dialect = EndpointDialect::detect(&url).unwrap_or_default();
+        Self { url, api_key: None, dialect }
+    }
+
+    /// Use a dialect for endpoints whose host isn't a known service
+    pub fn with_default_dialect(mut self, dialect: EndpointDialect) -> Self {
+        if EndpointDialect::detect(&self.url).is_none() {
+            self.dialect = dialect;
+        }
+        self
+    }
+
+    /// Attach a gateway key; dialects other than The Graph's never receive it
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key.filter(|k| !k.is_empty() && self.dialect.accepts_graph_api_key());
+        self
+    }
+
+    /// URL with any gateway key embedded in the path (`/api/<key>/`) masked for logs
+    pub fn display_url(&self) -> String {
+        match self.url.split_once("/api/") {
+            Some((host, rest)) => match rest.split_once('/') {
+                Some((_key, path)) => format!("{}/api/***/{}", host, path),
+                None => self.url.clone(),
+            },
+            None => self.url.clone(),
+        }
+    }
+}
+
+/// Health of one endpoint as observed by the indexer
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub dialect: EndpointDialect,
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Not used again until this time, unless every endpoint is benched
+    pub cooldown_until: Option<DateTime<Utc>>,
+    /// Latest block the endpoint reported having indexed
+    pub indexed_block: Option<u64>,
+    pub lagging: bool,
+}
+
+impl EndpointHealth {
+    fn is_available(&self, now: DateTime<Utc>) -> bool {
+        !self.lagging && self.cooldown_until.is_none_or(|until| until <= now)
+    }
+}
+
+/// Prioritized subgraph endpoints with per-endpoint health tracking
+///
+/// Requests go to the highest-priority endpoint that isn't cooling down after
+/// errors or lagging behind the others; if every endpoint is benched they're
+/// all tried in priority order rather than failing outright.
+#[derive(Debug)]
+pub struct EndpointSet {
+    endpoints: Vec<SubgraphEndpoint>,
+    health: Mutex<Vec<EndpointHealth>>,
+}
+
+impl EndpointSet {
+    pub fn new(endpoints: Vec<SubgraphEndpoint>) -> Self {
+        let health = endpoints
+            .iter()
+            .map(|e| EndpointHealth {
+                url: e.display_url(),
+                dialect: e.dialect,
+                ..Default::default()
+            })
+            .collect();
+        Self { endpoints, health: Mutex::new(health) }
+    }
+
+    /// Endpoints from the environment, in priority order
+    ///
+    /// Uses `GRAPH_API_URLS_<CHAIN_ID>` (comma-separated) when set for the
+    /// configured chain, otherwise `GRAPH_API_URL` followed by
+    /// `GRAPH_API_FALLBACK_URLS`. Endpoints on The Graph, Goldsky or Subsquid
+    /// hosts get their dialect from the host, others `GRAPH_API_DIALECT`
+    /// (default `thegraph`). `GRAPH_API_KEY` is attached to The Graph endpoints.
+    pub fn from_env() -> Result<Option<Self>> {
+        let chain_id = std::env::var("CHAIN_ID")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_CHAIN_ID);
+        let api_key = std::env::var("GRAPH_API_KEY").ok();
+        let dialect = match std::env::var("GRAPH_API_DIALECT") {
+            Ok(value) => EndpointDialect::parse(&value).ok_or_else(|| {
+                anyhow!("GRAPH_API_DIALECT must be thegraph, goldsky or subsquid, got {:?}", value)
+            })?,
+            Err(_) => EndpointDialect::default(),
+        };
+
+        let urls: Vec<String> = match std::env::var(format!("GRAPH_API_URLS_{}", chain_id)) {
+            Ok(list) => split_urls(&list),
+            Err(_) => {
+                let mut urls: Vec<String> =
+                    std::env::var("GRAPH_API_URL").ok().into_iter().collect();
+                if let Ok(list) = std::env::var("GRAPH_API_FALLBACK_URLS") {
+                    urls.extend(split_urls(&list));
+                }
+                urls
+            }
+        };
+
+        if urls.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(
+            urls.into_iter()
+                .map(|url| {
+                    SubgraphEndpoint::new(url)
+                        .with_default_dialect(dialect)
+                        .with_api_key(api_key.clone())
+                })
+                .collect(),
+        )))
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn endpoints(&self) -> &[SubgraphEndpoint] {
+        &self.endpoints
+    }
+
+    /// Indices of endpoints to try, in order: available ones first, then benched ones
+    pub fn attempt_order(&self) -> Vec<usize> {
+        let now = Utc::now();
+        let health = self.health.lock().unwrap();
+        let (mut available, benched): (Vec<usize>, Vec<usize>) =
+            (0..self.endpoints.len()).partition(|&i| health[i].is_available(now));
+        available.extend(benched);
+        available
+    }
+
+    pub fn record_success(&self, index: usize) {
+        let mut health = self.health.lock().unwrap();
+        let h = &mut health[index];
+        h.successes += 1;
+        h.consecutive_failures = 0;
+        h.cooldown_until = None;
+        h.last_success_at = Some(Utc::now());
+    }
+
+    pub fn record_failure(&self, index: usize, error: &str) {
+        let mut health = self.health.lock().unwrap();
+        let h = &mut health[index];
+        h.failures += 1;
+        h.consecutive_failures += 1;
+        h.last_error = Some(error.to_string());
+
+        let backoff = BASE_COOLDOWN_SECS
+            .saturating_mul(1i64 << (h.consecutive_failures - 1).min(10))
+            .min(MAX_COOLDOWN_SECS);
+        h.cooldown_until = Some(Utc::now() + Duration::seconds(backoff));
+    }
+
+    /// Record the block each endpoint has indexed and flag the ones trailing the best
+    pub fn record_indexed_blocks(&self, blocks: &[Option<u64>]) {
+        let mut health = self.health.lock().unwrap();
+        let best = blocks.iter().flatten().max().copied();
+
+        for (h, block) in health.iter_mut().zip(blocks) {
+            if block.is_some() {
+                h.indexed_block = *block;
+            }
+            h.lagging = match (best, h.indexed_block) {
+                (Some(best), Some(own)) => best.saturating_sub(own) > MAX_LAG_BLOCKS,
+                _ => false,
+            };
+        }
+    }
+
+    /// Latest block an endpoint reported having indexed, if it was checked
+    pub fn indexed_block(&self, index: usize) -> Option<u64> {
+        self.health.lock().unwrap()[index].indexed_block
+    }
+
+    /// Snapshot of every endpoint's health, in priority order
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.health.lock().unwrap().clone()
+    }
+}
+
+fn split_urls(list: &str) -> Vec<String> {
+    list.split(',').map(str::trim).filter(|u| !u.is_empty()).map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GraphIndexer;
+    use crate::testing::fake_subgraph;
+    use serde_json::json;
+
+    const GATEWAY_URL: &str = "https://gateway.thegraph.com/api/secret/subgraphs/id/abc";
+
+    fn create_test_set(count: usize) -> EndpointSet {
+        EndpointSet::new(
+            (0..count).map(|i| SubgraphEndpoint::new(format!("http://endpoint{}", i))).collect(),
+        )
+    }
+
+    fn head_block(number: u64) -> (u16, serde_json::Value) {
+        (200, json!({ "data": { "_meta": { "block": { "number": number } } } }))
+    }
+
+    #[test]
+    fn test_endpoints_are_tried_in_priority_order() {
+        assert_eq!(create_test_set(3).attempt_order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_failed_endpoint_goes_behind_healthy_ones_until_it_succeeds() {
+        let set = create_test_set(3);
+
+        set.record_failure(0, "timeout");
+        assert_eq!(set.attempt_order(), vec![1, 2, 0]);
+        set.record_failure(1, "timeout");
+        assert_eq!(set.attempt_order(), vec![2, 0, 1]);
+
+        set.record_success(0);
+        assert_eq!(set.attempt_order(), vec![0, 2, 1]);
+        let health = &set.health()[0];
+        assert_eq!((health.successes, health.failures, health.consecutive_failures), (1, 1, 0));
+        assert_eq!(health.cooldown_until, None);
+    }
+
+    #[test]
+    fn test_benched_endpoints_are_all_still_tried_in_priority_order() {
+        let set = create_test_set(3);
+        for index in [2, 0, 1] {
+            set.record_failure(index, "timeout");
+        }
+
+        assert_eq!(set.attempt_order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cooldown_doubles_per_failure_up_to_the_cap() {
+        let set = create_test_set(1);
+        let mut cooldowns = Vec::new();
+        for _ in 0..7 {
+            let before = Utc::now();
+            set.record_failure(0, "timeout");
+            let until = set.health()[0].cooldown_until.unwrap();
+            cooldowns.push((until - before).num_seconds());
+        }
+
+        assert_eq!(cooldowns, vec![30, 60, 120, 240, 480, 600, 600]);
+        assert_eq!(set.health()[0].last_error.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn test_lagging_endpoints_go_last() {
+        let set = create_test_set(3);
+        set.record_indexed_blocks(&[Some(1000 - MAX_LAG_BLOCKS - 1), Some(1000), None]);
+
+        assert_eq!(set.attempt_order(), vec![1, 2, 0]);
+        assert!(set.health()[0].lagging);
+
+        // Catching up puts it back in priority order
+        set.record_indexed_blocks(&[Some(1000 - MAX_LAG_BLOCKS), None, None]);
+        assert_eq!(set.attempt_order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_api_key_is_only_attached_to_the_graph() {
+        let key = Some("secret".to_string());
+        let gateway = SubgraphEndpoint::new(GATEWAY_URL).with_api_key(key.clone());
+        assert_eq!(gateway.api_key, key);
+
+        let goldsky = SubgraphEndpoint::new("https://api.goldsky.com/api/public/p/subgraphs/v4/gn");
+        assert_eq!(goldsky.with_api_key(key).api_key, None);
+
+        let empty = SubgraphEndpoint::new(GATEWAY_URL).with_api_key(Some(String::new()));
+        assert_eq!(empty.api_key, None);
+    }
+
+    #[test]
+    fn test_display_url_masks_a_key_in_the_path() {
+        let endpoint = SubgraphEndpoint::new(GATEWAY_URL);
+        assert_eq!(endpoint.display_url(), "https://gateway.thegraph.com/api/***/subgraphs/id/abc");
+        assert_eq!(
+            SubgraphEndpoint::new("http://localhost:8000").display_url(),
+            "http://localhost:8000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_fails_over_to_the_next_endpoint() {
+        let (primary, primary_requests) =
+            fake_subgraph(|_| (500, json!({ "error": "overloaded" }))).await;
+        let (fallback, fallback_requests) = fake_subgraph(|_| head_block(42)).await;
+        let set = EndpointSet::new(vec![
+            SubgraphEndpoint::new(primary).with_api_key(Some("primary-key".to_string())),
+            SubgraphEndpoint::new(fallback),
+        ]);
+        let indexer = GraphIndexer::with_endpoints(set);
+
+        assert_eq!(indexer.fetch_head_block().await.unwrap().number, 42);
+        let primary_requests = primary_requests.lock().unwrap().clone();
+        let fallback_requests = fallback_requests.lock().unwrap().clone();
+        assert_eq!(primary_requests.len(), 1);
+        assert_eq!(primary_requests[0].authorization.as_deref(), Some("Bearer primary-key"));
+        assert_eq!(fallback_requests.len(), 1);
+        assert_eq!(fallback_requests[0].authorization, None);
+        assert_eq!(fallback_requests[0].body, primary_requests[0].body);
+
+        // The failed primary is benched, so the next query starts at the fallback
+        let health = indexer.endpoint_health();
+        assert_eq!((health[0].failures, health[1].successes), (1, 1));
+        indexer.fetch_head_block().await.unwrap();
+        assert_eq!(indexer.endpoint_health()[0].failures, 1);
+        assert_eq!(indexer.endpoint_health()[1].successes, 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_fails_once_every_endpoint_has() {
+        let (first, first_requests) = fake_subgraph(|_| (502, json!({}))).await;
+        let (second, second_requests) = fake_subgraph(|_| (503, json!({}))).await;
+        let indexer = GraphIndexer::with_endpoints(EndpointSet::new(vec![
+            SubgraphEndpoint::new(first),
+            SubgraphEndpoint::new(second),
+        ]));
+
+        let error = indexer.fetch_head_block().await.unwrap_err();
+
+        assert!(format!("{:#}", error).starts_with("All 2 subgraph endpoints failed"));
+        assert!(format!("{:#}", error).contains("503"));
+        assert_eq!(first_requests.lock().unwrap().len(), 1);
+        assert_eq!(second_requests.lock().unwrap().len(), 1);
+    }
+}
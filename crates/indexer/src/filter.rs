@@ -0,0 +1,265 @@
+use alloy::primitives::U256;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::types::{PoolResponse, PositionResponse, TokenResponse};
+
+/// Substrings commonly found in spam token symbols (links, fake airdrops)
+const SUSPICIOUS_SYMBOL_PATTERNS: &[&str] =
+    &["http", "www", ".com", ".io", ".org", ".xyz", "t.me", "visit", "claim", "reward", "airdrop"];
+
+/// Longest symbol considered legitimate
+const MAX_SYMBOL_LEN: usize = 20;
+
+/// Why a pool or position was rejected by the token filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterReason {
+    Denylisted(String),
+    NotAllowlisted(String),
+    SuspiciousSymbol(String),
+    NoLiquidity,
+}
+
+impl fmt::Display for FilterReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterReason::Denylisted(token) => write!(f, "token {} is denylisted", token),
+            FilterReason::NotAllowlisted(token) => write!(f, "token {} is not allowlisted", token),
+            FilterReason::SuspiciousSymbol(symbol) => {
+                write!(f, "suspicious token symbol {:?}", symbol)
+            }
+            FilterReason::NoLiquidity => write!(f, "no liquidity"),
+        }
+    }
+}
+
+/// Token allow/deny lists plus spam heuristics applied during sync
+///
+/// Allowlisted tokens are trusted and skip the heuristics. With
+/// `allowlist_only` set, any token not on the allowlist is rejected.
+#[derive(Debug, Clone, Default)]
+pub struct TokenFilter {
+    allowlist: HashSet<String>,
+    denylist: HashSet<String>,
+    allowlist_only: bool,
+}
+
+impl TokenFilter {
+    /// Create a filter from token address lists
+    pub fn new<I, J>(allowlist: I, denylist: J) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        J: IntoIterator<Item = String>,
+    {
+        Self {
+            allowlist: allowlist.into_iter().map(|a| a.to_lowercase()).collect(),
+            denylist: denylist.into_iter().map(|a| a.to_lowercase()).collect(),
+            allowlist_only: false,
+        }
+    }
+
+    /// Create filter from environment variables
+    ///
+    /// - `TOKEN_ALLOWLIST`: comma-separated trusted token addresses
+    /// - `TOKEN_DENYLIST`: comma-separated blocked token addresses
+    /// - `TOKEN_ALLOWLIST_ONLY`: if `true`, reject tokens not on the allowlist
+    pub fn from_env() -> Self {
+        let parse_list = |var: &str| -> Vec<String> {
+            std::env::var(var)
+                .unwrap_or_default()
+                .split(',')
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .collect()
+        };
+
+        let mut filter = Self::new(parse_list("TOKEN_ALLOWLIST"), parse_list("TOKEN_DENYLIST"));
+        filter.allowlist_only = std::env::var("TOKEN_ALLOWLIST_ONLY")
+            .map(|v| v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        filter
+    }
+
+    /// Only accept allowlisted tokens
+    pub fn allowlist_only(mut self, enabled: bool) -> Self {
+        self.allowlist_only = enabled;
+        self
+    }
+
+    /// Check a single token against the lists and symbol heuristics
+    pub fn check_token(&self, token: &TokenResponse) -> Result<(), FilterReason> {
+        let address = token.id.to_lowercase();
+
+        if self.denylist.contains(&address) {
+            return Err(FilterReason::Denylisted(address));
+        }
+        if self.allowlist.contains(&address) {
+            return Ok(());
+        }
+        if self.allowlist_only {
+            return Err(FilterReason::NotAllowlisted(address));
+        }
+
+        match &token.symbol {
+            Some(symbol) if is_suspicious_symbol(symbol) => {
+                Err(FilterReason::SuspiciousSymbol(symbol.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check both tokens of a pool
+    pub fn check_pool(&self, pool: &PoolResponse) -> Result<(), FilterReason> {
+        self.check_token(&pool.token0)?;
+        self.check_token(&pool.token1)
+    }
+
+    /// Check a position's pool, and that there is some sign of real liquidity
+    ///
+    /// A pool with no in-range liquidity is only rejected when the event itself
+    /// moved none either: legitimate pools lose all in-range liquidity whenever
+    /// the price leaves every position's range, while spam deployments often
+    /// never hold any. Subgraphs that don't expose liquidity are given the
+    /// benefit of the doubt.
+    pub fn check_position(&self, position: &PositionResponse) -> Result<(), FilterReason> {
+        let pool = &position.pool;
+        self.check_pool(pool)?;
+
+        let trusted = self.allowlist.contains(&pool.token0.id.to_lowercase())
+            && self.allowlist.contains(&pool.token1.id.to_lowercase());
+        let no_pool_liquidity = pool
+            .liquidity
+            .as_deref()
+            .is_some_and(|l| U256::from_str_radix(l, 10).is_ok_and(|l| l.is_zero()));
+        let no_event_liquidity = position.liquidity_delta().is_some_and(|d| d.is_zero());
+        if !trusted && no_pool_liquidity && no_event_liquidity {
+            return Err(FilterReason::NoLiquidity);
+        }
+
+        Ok(())
+    }
+}
+
+/// Heuristic check for spam-looking token symbols
+///
+/// Non-ASCII letters, digits and currency signs are fine (e.g. USD₮0, 龙), but
+/// a symbol mixing ASCII and non-ASCII letters is likely impersonating another
+/// token with look-alike characters (e.g. a Cyrillic "С" in USDС).
+pub fn is_suspicious_symbol(symbol: &str) -> bool {
+    let trimmed = symbol.trim();
+    if trimmed.is_empty() || trimmed.chars().count() > MAX_SYMBOL_LEN {
+        return true;
+    }
+
+    let lower = trimmed.to_lowercase();
+    if SUSPICIOUS_SYMBOL_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return true;
+    }
+
+    // Legit symbols are short alphanumerics with the odd separator (e.g. USDC.e, WETH-2)
+    let allowed =
+        |c: char| c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | '+') || is_currency_sign(c);
+    if !trimmed.chars().all(allowed) {
+        return true;
+    }
+
+    let ascii_letters = trimmed.chars().any(|c| c.is_ascii_alphabetic());
+    let other_letters = trimmed.chars().any(|c| c.is_alphabetic() && !c.is_ascii());
+    ascii_letters && other_letters
+}
+
+/// Currency signs seen in stablecoin and wrapped-asset symbols
+fn is_currency_sign(c: char) -> bool {
+    // U+20A0..U+20CF is the Currency Symbols block (₮, €, ₿, ₹, ...)
+    matches!(c, '$' | '¢' | '£' | '¥' | '\u{20A0}'..='\u{20CF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TOKEN0, TOKEN1, create_test_position, create_test_token};
+
+    #[test]
+    fn test_plain_symbols_pass() {
+        for symbol in ["WETH", "USDC.e", "WETH-2", "cbBTC", "USD+", "$MEME", "stETH_2"] {
+            assert!(!is_suspicious_symbol(symbol), "{} flagged", symbol);
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_symbols_pass() {
+        for symbol in ["USD₮0", "€URC", "龙", "ПРИВЕТ", "₿TC"] {
+            assert!(!is_suspicious_symbol(symbol), "{} flagged", symbol);
+        }
+    }
+
+    #[test]
+    fn test_spam_symbols_flagged() {
+        for symbol in [
+            "",
+            "   ",
+            "visit uni-claim.com",
+            "t.me/freetokens",
+            "AIRDROP",
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            "USD C",
+            "WETH\u{200B}",
+            "🚀MOON",
+        ] {
+            assert!(is_suspicious_symbol(symbol), "{:?} not flagged", symbol);
+        }
+    }
+
+    #[test]
+    fn test_mixed_script_lookalikes_flagged() {
+        // Cyrillic "С" (U+0421) standing in for a Latin C
+        assert!(is_suspicious_symbol("USD\u{0421}"));
+        assert!(is_suspicious_symbol("WΕTH")); // Greek Epsilon
+    }
+
+    #[test]
+    fn test_denylist_beats_allowlist() {
+        let filter = TokenFilter::new(vec!["0xAAA".to_string()], vec!["0xaaa".to_string()]);
+        let token = create_test_token("0xaaa", "WETH");
+        assert_eq!(filter.check_token(&token), Err(FilterReason::Denylisted("0xaaa".to_string())));
+    }
+
+    #[test]
+    fn test_allowlist_skips_heuristics() {
+        let filter = TokenFilter::new(vec!["0xaaa".to_string()], Vec::new());
+        assert!(filter.check_token(&create_test_token("0xaaa", "claim.com")).is_ok());
+
+        let strict = filter.allowlist_only(true);
+        assert_eq!(
+            strict.check_token(&create_test_token("0xbbb", "USDC")),
+            Err(FilterReason::NotAllowlisted("0xbbb".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_pool_with_liquidity_event_passes() {
+        // Price left every range: nothing in range, but the event moved liquidity
+        let filter = TokenFilter::default();
+        assert!(filter.check_position(&create_test_position(Some("0"), "1000")).is_ok());
+        assert!(filter.check_position(&create_test_position(Some("0"), "-1000")).is_ok());
+    }
+
+    #[test]
+    fn test_pool_and_event_without_liquidity_rejected() {
+        let filter = TokenFilter::default();
+        assert_eq!(
+            filter.check_position(&create_test_position(Some("0"), "0")),
+            Err(FilterReason::NoLiquidity)
+        );
+    }
+
+    #[test]
+    fn test_unknown_or_trusted_liquidity_passes() {
+        let filter = TokenFilter::default();
+        assert!(filter.check_position(&create_test_position(None, "0")).is_ok());
+        assert!(filter.check_position(&create_test_position(Some("5"), "0")).is_ok());
+
+        let trusted = TokenFilter::new(vec![TOKEN0.to_string(), TOKEN1.to_string()], Vec::new());
+        assert!(trusted.check_position(&create_test_position(Some("0"), "0")).is_ok());
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use stillwater_db::insert_gas_price;
+use stillwater_models::{BlockchainService, GasPriceSample};
+use tracing::{info, warn};
+
+/// Blocks between samples `backfill_gas_prices` takes by default
+pub const GAS_HISTORY_STEP_BLOCKS: u64 = 300;
+
+/// Samples stored between backfill progress logs
+const BACKFILL_LOG_EVERY: usize = 100;
+
+/// Read a block's base fee and median tip and store them
+pub async fn record_gas_price(
+    db_pool: &PgPool,
+    blockchain: &BlockchainService,
+    chain_id: u64,
+    block: u64,
+) -> Result<GasPriceSample> {
+    let sample = blockchain.get_gas_price_sample(chain_id, block).await?;
+    insert_gas_price(db_pool, &sample).await?;
+    Ok(sample)
+}
+
+/// Store the gas price of every `step`-th block from `from_block` through `to_block`
+///
+/// Blocks already sampled are left as they are, so an interrupted backfill
+/// can be run again. Blocks that can't be read are logged and skipped;
+/// returns how many samples were stored.
+pub async fn backfill_gas_prices(
+    db_pool: &PgPool,
+    blockchain: &BlockchainService,
+    chain_id: u64,
+    from_block: u64,
+    to_block: u64,
+    step: u64,
+) -> Result<usize> {
+    let mut stored = 0;
+    for block in (from_block..=to_block).step_by(step.max(1) as usize) {
+        match record_gas_price(db_pool, blockchain, chain_id, block).await {
+            Ok(_) => {
+                stored += 1;
+                if stored % BACKFILL_LOG_EVERY == 0 {
+                    info!("Stored {} gas price samples, up to block {}", stored, block);
+                }
+            }
+            Err(e) => warn!("Failed to sample gas price at block {}: {}", block, e),
+        }
+    }
+    Ok(stored)
+}
@@ -0,0 +1,190 @@
+use alloy::primitives::{keccak256, Address, U256};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::Read;
+use stillwater_analytics::TickRange;
+use stillwater_db::{get_pool_by_id, insert_position};
+use stillwater_models::Position;
+
+/// One row of a position import CSV
+///
+/// Expected header: `owner,pool_id,entry_date,tick_lower,tick_upper,liquidity`.
+/// `entry_date` is RFC3339 or a plain `YYYY-MM-DD` (midnight UTC).
+#[derive(Debug, Deserialize)]
+struct PositionCsvRow {
+    owner: String,
+    pool_id: String,
+    entry_date: String,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: String,
+}
+
+/// A CSV row that could not be imported
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportRowError {
+    /// 1-based line number in the CSV (header is line 1)
+    pub line: u64,
+    pub message: String,
+}
+
+/// A validated row awaiting insertion
+#[derive(Debug, Clone)]
+pub struct ParsedPosition {
+    pub line: u64,
+    pub position: Position,
+}
+
+/// Result of parsing a CSV: valid positions plus per-row errors
+#[derive(Debug, Default)]
+pub struct ParsedImport {
+    pub positions: Vec<ParsedPosition>,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// Outcome of storing an import
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Rows written as new positions
+    pub imported: usize,
+    /// Rows matching a position imported earlier
+    pub duplicates: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// Parse legacy positions from CSV into `manual` positions
+///
+/// Invalid rows are collected as errors instead of aborting the import.
+pub fn parse_positions_csv<R: Read>(reader: R) -> ParsedImport {
+    let mut csv_reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let mut parsed = ParsedImport::default();
+
+    let headers = match csv_reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            parsed.errors.push(ImportRowError { line: 1, message: e.to_string() });
+            return parsed;
+        }
+    };
+
+    for record in csv_reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or(0);
+                parsed.errors.push(ImportRowError { line, message: e.to_string() });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+
+        let result = record
+            .deserialize::<PositionCsvRow>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(row_to_position);
+
+        match result {
+            Ok(position) => parsed.positions.push(ParsedPosition { line, position }),
+            Err(message) => parsed.errors.push(ImportRowError { line, message }),
+        }
+    }
+
+    parsed
+}
+
+/// Insert parsed positions, rejecting rows whose pool is not synced yet
+///
+/// Re-importing the same file is a no-op: manual nft_ids are derived from
+/// the row contents, so repeated rows are reported as duplicates.
+pub async fn store_positions(db_pool: &PgPool, parsed: ParsedImport) -> Result<ImportReport> {
+    let mut report = ImportReport { errors: parsed.errors, ..Default::default() };
+    let mut known_pools: HashMap<String, bool> = HashMap::new();
+
+    for ParsedPosition { line, position } in parsed.positions {
+        let exists = match known_pools.get(&position.pool_id) {
+            Some(exists) => *exists,
+            None => {
+                let exists = get_pool_by_id(db_pool, &position.pool_id).await?.is_some();
+                known_pools.insert(position.pool_id.clone(), exists);
+                exists
+            }
+        };
+        if !exists {
+            report.errors.push(ImportRowError {
+                line,
+                message: format!("unknown pool {}", position.pool_id),
+            });
+            continue;
+        }
+
+        if insert_position(db_pool, &position).await? {
+            report.imported += 1;
+        } else {
+            report.duplicates += 1;
+        }
+    }
+
+    report.errors.sort_by_key(|e| e.line);
+    Ok(report)
+}
+
+/// Deterministic nft_id for a manual position (fits the 78-char column)
+pub fn manual_nft_id(position: &Position) -> String {
+    let key = format!(
+        "{}|{}|{}|{}|{}|{}",
+        position.owner.canonical(),
+        position.pool_id,
+        position.tick_lower,
+        position.tick_upper,
+        position.liquidity,
+        position.created_at.timestamp()
+    );
+    format!("manual-{:x}", keccak256(key.as_bytes()))
+}
+
+fn row_to_position(row: PositionCsvRow) -> Result<Position, String> {
+    let owner = row
+        .owner
+        .parse::<Address>()
+        .map_err(|_| format!("invalid owner address {:?}", row.owner))?;
+    if row.pool_id.is_empty() {
+        return Err("missing pool_id".to_string());
+    }
+    TickRange::new(row.tick_lower, row.tick_upper).map_err(|e| e.to_string())?;
+    let liquidity = U256::from_str_radix(&row.liquidity, 10)
+        .map_err(|_| format!("invalid liquidity {:?}", row.liquidity))?;
+    let created_at = parse_entry_date(&row.entry_date)
+        .ok_or_else(|| format!("invalid entry_date {:?}", row.entry_date))?;
+    if created_at > Utc::now() {
+        return Err("entry_date is in the future".to_string());
+    }
+
+    let mut position = Position {
+        id: 0,
+        nft_id: String::new(),
+        owner: owner.into(),
+        pool_id: row.pool_id.to_lowercase(),
+        tick_lower: row.tick_lower,
+        tick_upper: row.tick_upper,
+        liquidity,
+        created_at,
+        manual: true,
+        closed_at: None,
+        archived_at: None,
+    };
+    position.nft_id = manual_nft_id(&position);
+    Ok(position)
+}
+
+fn parse_entry_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
@@ -0,0 +1,369 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use stillwater_analytics::{
+    build_ledger_entries, render_entries, LedgerConfig, LedgerFormat, PositionActivity,
+    TimeRange, TimeRangeLimits,
+};
+use stillwater_db::{
+    find_positions, get_gas_expenses_for_position, get_pool_by_id, get_snapshots_for_owners,
+    update_job_progress, PositionFilter,
+};
+use stillwater_models::{Address, Job, JobKind, PositionSnapshot};
+use tracing::info;
+
+use crate::GraphIndexer;
+
+/// Days of swaps a backfill re-fetches by default
+pub const DEFAULT_BACKFILL_DAYS: i64 = 30;
+
+/// Longest window a backfill may re-fetch
+pub const MAX_BACKFILL_DAYS: i64 = 365;
+
+/// Options of a `backfill` job
+#[derive(Debug, Default, Deserialize)]
+pub struct BackfillParams {
+    /// Days of swaps to re-fetch (default 30)
+    pub days: Option<i64>,
+    /// Re-fetch from this time instead, e.g. `90d`, unix seconds or RFC3339
+    pub from: Option<String>,
+}
+
+/// Outcome of a `backfill` job
+#[derive(Debug, Serialize)]
+pub struct BackfillReport {
+    pub since: DateTime<Utc>,
+    /// Pools of the owner's positions whose swaps were fetched
+    pub pools: usize,
+    pub swaps_inserted: usize,
+}
+
+/// Where a `backfill` job is, saved as its cursor after each pool
+///
+/// The window and pools are fixed when the job first runs, so a requeued job
+/// picks up at the next pool over the same window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillCursor {
+    pub since: DateTime<Utc>,
+    /// Pools of the owner's positions, in the order they're backfilled
+    pub pools: Vec<String>,
+    /// How many of `pools` are done
+    pub done: usize,
+    pub swaps_inserted: usize,
+}
+
+impl BackfillCursor {
+    /// Percent of pools done (100 without any)
+    pub fn progress(&self) -> Decimal {
+        if self.pools.is_empty() {
+            return Decimal::ONE_HUNDRED;
+        }
+        (Decimal::from(self.done * 100) / Decimal::from(self.pools.len())).round_dp(2)
+    }
+}
+
+/// Hours of swaps a `sync` job fetches, the window a scheduled sync covers
+pub const SYNC_JOB_SWAP_HOURS: i64 = 1;
+
+/// Options of a `sync` job
+#[derive(Debug, Default, Deserialize)]
+pub struct SyncParams {
+    /// Sync this pool instead of the job's owner
+    pub pool_id: Option<String>,
+}
+
+/// Outcome of a `sync` job
+#[derive(Debug, Serialize)]
+pub struct SyncReport {
+    pub positions_inserted: usize,
+    /// Pools whose swaps were fetched
+    pub pools: usize,
+    pub swaps_inserted: usize,
+}
+
+/// Options of a ledger export: `/export/:owner/ledger` query parameters or `ledger_export` params
+#[derive(Debug, Default, Deserialize)]
+pub struct LedgerExportParams {
+    /// `beancount` (default) or `ledger`
+    pub format: Option<String>,
+    /// Commodity names for tokens, as `address:SYMBOL` pairs separated by commas
+    pub symbols: Option<String>,
+    /// Commodity gas is paid in (default `ETH`)
+    pub native: Option<String>,
+}
+
+impl LedgerExportParams {
+    /// The export's format and ledger config, or why the options are invalid
+    pub fn parse(&self) -> Result<(LedgerFormat, LedgerConfig), String> {
+        let format = LedgerFormat::parse(self.format.as_deref().unwrap_or("beancount"))
+            .ok_or("format must be beancount or ledger")?;
+
+        let mut config = LedgerConfig::default();
+        if let Some(native) = &self.native {
+            config.native_commodity = native.clone();
+        }
+        let pairs = self.symbols.as_deref().unwrap_or("").split(',');
+        for pair in pairs.filter(|p| !p.trim().is_empty()) {
+            let (address, symbol) =
+                pair.split_once(':').ok_or("symbols must be address:SYMBOL pairs")?;
+            config.symbols.insert(address.trim().to_lowercase(), symbol.trim().to_string());
+        }
+        Ok((format, config))
+    }
+}
+
+/// Render owners' double-entry ledger of deposits/withdrawals, fee income and gas
+pub async fn build_owner_ledger(
+    db_pool: &PgPool,
+    owners: &[Address],
+    format: LedgerFormat,
+    config: &LedgerConfig,
+) -> Result<String> {
+    // The books cover every position, however long ago it closed
+    let filter = PositionFilter {
+        owners: Some(owners.to_vec()),
+        include_archived: true,
+        ..Default::default()
+    };
+    let positions = find_positions(db_pool, &filter).await.context("Failed to fetch positions")?;
+
+    let mut snapshots: HashMap<i64, Vec<PositionSnapshot>> = HashMap::new();
+    let owner_snapshots =
+        get_snapshots_for_owners(db_pool, owners).await.context("Failed to fetch snapshots")?;
+    for s in owner_snapshots {
+        snapshots.entry(s.position_id).or_default().push(s);
+    }
+
+    let mut pools = HashMap::new();
+    let mut gas = HashMap::new();
+    for position in &positions {
+        if !pools.contains_key(&position.pool_id) {
+            let pool =
+                get_pool_by_id(db_pool, &position.pool_id).await.context("Failed to fetch pool")?;
+            match pool {
+                Some(pool) => {
+                    pools.insert(position.pool_id.clone(), pool);
+                }
+                None => continue,
+            }
+        }
+        let expenses = get_gas_expenses_for_position(db_pool, position.id, Utc::now())
+            .await
+            .context("Failed to fetch gas expenses")?;
+        gas.insert(position.id, expenses);
+    }
+
+    let activity: Vec<PositionActivity> = positions
+        .iter()
+        .filter_map(|position| {
+            Some(PositionActivity {
+                position,
+                pool: pools.get(&position.pool_id)?,
+                snapshots: snapshots.get(&position.id).map(Vec::as_slice).unwrap_or(&[]),
+                gas_expenses: gas.get(&position.id).map(Vec::as_slice).unwrap_or(&[]),
+            })
+        })
+        .collect();
+
+    Ok(render_entries(&build_ledger_entries(&activity, config), format))
+}
+
+/// File name a ledger is downloaded as, after its owner's address or `account:<name>`
+pub fn ledger_filename(owner: &str, format: LedgerFormat) -> String {
+    let extension = match format {
+        LedgerFormat::Beancount => "beancount",
+        LedgerFormat::Ledger => "journal",
+    };
+    format!("{}.{}", owner.to_lowercase().replace(':', "-"), extension)
+}
+
+/// Check a job's params before queueing it, so bad requests fail up front
+pub fn validate_job_params(kind: JobKind, params: &serde_json::Value) -> Result<(), String> {
+    match kind {
+        JobKind::Backfill => {
+            let params: BackfillParams =
+                serde_json::from_value(params.clone()).map_err(|e| e.to_string())?;
+            backfill_since(&params, Utc::now()).map(|_| ())
+        }
+        JobKind::LedgerExport => {
+            let params: LedgerExportParams =
+                serde_json::from_value(params.clone()).map_err(|e| e.to_string())?;
+            params.parse().map(|_| ())
+        }
+        JobKind::Sync => {
+            let params: SyncParams =
+                serde_json::from_value(params.clone()).map_err(|e| e.to_string())?;
+            match params.pool_id {
+                Some(pool_id) if pool_id.trim().is_empty() => {
+                    Err("pool_id must not be empty".to_string())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// Start of the window a backfill re-fetches
+fn backfill_since(params: &BackfillParams, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    match (&params.from, params.days) {
+        (Some(_), Some(_)) => Err("give either days or from, not both".to_string()),
+        (Some(from), None) => {
+            let limits = TimeRangeLimits::days(DEFAULT_BACKFILL_DAYS, MAX_BACKFILL_DAYS);
+            let range = TimeRange::parse(Some(from), None, limits, now).map_err(|e| e.to_string())?;
+            Ok(range.from)
+        }
+        (None, days) => {
+            let days = days.unwrap_or(DEFAULT_BACKFILL_DAYS);
+            if !(1..=MAX_BACKFILL_DAYS).contains(&days) {
+                return Err(format!("days must be between 1 and {}", MAX_BACKFILL_DAYS));
+            }
+            Ok(now - Duration::days(days))
+        }
+    }
+}
+
+/// Run a claimed job, returning the result to store with it
+pub async fn run_job(
+    db_pool: &PgPool,
+    indexer: &GraphIndexer,
+    job: &Job,
+) -> Result<serde_json::Value> {
+    match job.kind {
+        JobKind::Backfill => {
+            let params: BackfillParams =
+                serde_json::from_value(job.params.clone()).context("Invalid backfill params")?;
+            let cursor = match &job.cursor {
+                Some(cursor) => {
+                    serde_json::from_value(cursor.clone()).context("Invalid backfill cursor")?
+                }
+                None => {
+                    let since = backfill_since(&params, Utc::now()).map_err(|e| anyhow!(e))?;
+                    let pools = owner_pool_ids(db_pool, &job_owner(job)?).await?;
+                    BackfillCursor { since, pools, done: 0, swaps_inserted: 0 }
+                }
+            };
+            let report = backfill_owner(db_pool, indexer, job, cursor).await?;
+            Ok(serde_json::to_value(report)?)
+        }
+        JobKind::LedgerExport => {
+            let params: LedgerExportParams = serde_json::from_value(job.params.clone())
+                .context("Invalid ledger export params")?;
+            let (format, config) = params.parse().map_err(|e| anyhow!(e))?;
+            let owner = job_owner(job)?;
+            let ledger = build_owner_ledger(db_pool, &[owner], format, &config).await?;
+            Ok(serde_json::json!({
+                "format": format.as_str(),
+                "filename": ledger_filename(&owner.canonical(), format),
+                "ledger": ledger,
+            }))
+        }
+        JobKind::Sync => {
+            let params: SyncParams =
+                serde_json::from_value(job.params.clone()).context("Invalid sync params")?;
+            let report = match &params.pool_id {
+                Some(pool_id) => sync_pool(db_pool, indexer, pool_id).await?,
+                None if job.owner.is_empty() => {
+                    return Err(anyhow!("Sync job has no owner or pool"));
+                }
+                None => sync_owner(db_pool, indexer, &job_owner(job)?).await?,
+            };
+            Ok(serde_json::to_value(report)?)
+        }
+    }
+}
+
+/// Sync an owner's positions, then the last hour of swaps in their pools
+async fn sync_owner(
+    db_pool: &PgPool,
+    indexer: &GraphIndexer,
+    owner: &Address,
+) -> Result<SyncReport> {
+    let positions_inserted = indexer.sync_owner_positions(db_pool, &owner.canonical()).await?;
+    let pool_ids = owner_pool_ids(db_pool, owner).await?;
+    let since = Utc::now() - Duration::hours(SYNC_JOB_SWAP_HOURS);
+    let swaps_inserted = if pool_ids.is_empty() {
+        0
+    } else {
+        indexer.sync_swaps_for_pools_since(db_pool, &pool_ids, since).await?
+    };
+
+    Ok(SyncReport { positions_inserted, pools: pool_ids.len(), swaps_inserted })
+}
+
+/// Sync a pool's latest positions and its last hour of swaps
+async fn sync_pool(db_pool: &PgPool, indexer: &GraphIndexer, pool_id: &str) -> Result<SyncReport> {
+    let positions_inserted = indexer.sync_pool_positions(db_pool, pool_id).await?;
+    let since = Utc::now() - Duration::hours(SYNC_JOB_SWAP_HOURS);
+    let swaps_inserted =
+        indexer.sync_swaps_for_pools_since(db_pool, &[pool_id.to_lowercase()], since).await?;
+
+    Ok(SyncReport { positions_inserted, pools: 1, swaps_inserted })
+}
+
+/// The address a job is for
+fn job_owner(job: &Job) -> Result<Address> {
+    Address::parse(&job.owner).with_context(|| format!("Invalid job owner {:?}", job.owner))
+}
+
+/// Distinct pools of an owner's positions
+async fn owner_pool_ids(db_pool: &PgPool, owner: &Address) -> Result<Vec<String>> {
+    let filter = PositionFilter { owners: Some(vec![*owner]), ..Default::default() };
+    let positions = find_positions(db_pool, &filter).await.context("Failed to fetch positions")?;
+    Ok(positions.into_iter().map(|p| p.pool_id).collect::<BTreeSet<_>>().into_iter().collect())
+}
+
+/// Re-fetch swaps in the pools of an owner's positions, one pool at a time from the cursor
+///
+/// Progress and the cursor are saved after each pool. Swaps already stored
+/// are skipped on insert, so redoing a pool a stopped worker was halfway
+/// through is harmless.
+async fn backfill_owner(
+    db_pool: &PgPool,
+    indexer: &GraphIndexer,
+    job: &Job,
+    mut cursor: BackfillCursor,
+) -> Result<BackfillReport> {
+    if cursor.done == 0 {
+        info!(
+            "Backfilling {} pools of {} since {}",
+            cursor.pools.len(),
+            job.owner,
+            cursor.since
+        );
+    } else {
+        info!(
+            "Resuming backfill of {} at pool {} of {}",
+            job.owner,
+            cursor.done + 1,
+            cursor.pools.len()
+        );
+    }
+    update_job_progress(db_pool, job.id, cursor.progress(), &serde_json::to_value(&cursor)?)
+        .await?;
+
+    while let Some(pool_id) = cursor.pools.get(cursor.done).cloned() {
+        cursor.swaps_inserted += indexer
+            .sync_swaps_for_pools_since(db_pool, std::slice::from_ref(&pool_id), cursor.since)
+            .await?;
+        cursor.done += 1;
+        update_job_progress(db_pool, job.id, cursor.progress(), &serde_json::to_value(&cursor)?)
+            .await?;
+        info!(
+            "Job {}: backfilled pool {} ({}/{} pools, {}%)",
+            job.id,
+            pool_id,
+            cursor.done,
+            cursor.pools.len(),
+            cursor.progress()
+        );
+    }
+
+    Ok(BackfillReport {
+        since: cursor.since,
+        pools: cursor.pools.len(),
+        swaps_inserted: cursor.swaps_inserted,
+    })
+}
@@ -1,71 +1,357 @@
+mod batch;
+mod cache;
+mod chaos;
+mod checkpoint;
+mod creation;
+mod custom;
+mod dialect;
+mod endpoints;
+mod filter;
+mod gas;
+mod import;
+mod jobs;
 mod queries;
+mod scan;
+#[cfg(test)]
+mod testing;
 mod types;
 
-use alloy::primitives::{I256, U256};
+use alloy::primitives::{B256, I256};
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde_json::json;
-use sqlx::PgPool;
-use stillwater_db::{insert_pool, insert_position, insert_swap};
-use stillwater_models::{Pool, Position, Swap};
+use sqlx::{Acquire, PgConnection, PgPool};
+use stillwater_analytics::{transaction_gas_cost, GasAccounting, TickRange};
+use stillwater_db::{
+    apply_liquidity_removal, get_position_by_nft, insert_gas_expense, insert_pool,
+    insert_position, insert_swap, quarantine_position, record_liquidity_addition,
+    record_position_transfer,
+};
+use stillwater_models::{
+    Address, BlockchainService, FaultPlan, FieldReport, GasExpense, LiquidityChange, LiquidityEvent,
+    Pool, Position, PositionTransfer, Swap, SyncRun, SyncStage, NO_HOOKS,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
 use tracing::{debug, info, warn};
 
+use crate::batch::retry_batch;
+
+pub use batch::SYNC_BATCH_ATTEMPTS;
+pub use cache::{CacheStore, SubgraphCache, DEFAULT_CACHE_TTL_HOURS};
+pub use checkpoint::{
+    reconcile_checkpoints, record_checkpoint, CheckpointReconciliation, CHECKPOINT_HISTORY,
+};
+pub use creation::{
+    ingest_pool_creations, CreatedPool, PoolCreationScanner, POOL_CREATION_CURSOR,
+};
+pub use custom::{CustomQuery, PositionQuery, ResponseMapper};
+pub use dialect::EndpointDialect;
+pub use endpoints::{EndpointHealth, EndpointSet, SubgraphEndpoint, MAX_LAG_BLOCKS};
+pub use filter::{is_suspicious_symbol, FilterReason, TokenFilter};
+pub use gas::{backfill_gas_prices, record_gas_price, GAS_HISTORY_STEP_BLOCKS};
+pub use import::{
+    manual_nft_id, parse_positions_csv, store_positions, ImportReport, ImportRowError,
+    ParsedImport, ParsedPosition,
+};
+pub use jobs::{
+    build_owner_ledger, ledger_filename, run_job, validate_job_params, BackfillParams,
+    BackfillReport, LedgerExportParams, SyncParams, SyncReport, DEFAULT_BACKFILL_DAYS,
+    MAX_BACKFILL_DAYS, SYNC_JOB_SWAP_HOURS,
+};
+pub use scan::{scan_wallet, ScanReport, ScanSkip};
 pub use types::*;
 
+/// Pools whose swaps are fetched per HTTP request when syncing several pools
+pub const SWAP_BATCH_SIZE: usize = 20;
+
 /// The Graph indexer client
 pub struct GraphIndexer {
     client: Client,
-    graph_url: String,
+    endpoints: EndpointSet,
+    token_filter: TokenFilter,
+    position_queries: HashMap<PositionQuery, CustomQuery<PositionResponse>>,
+    swaps_query: Option<CustomQuery<SwapResponse>>,
+    /// RPC used to price the gas of synced liquidity events
+    gas_tracking: Option<(BlockchainService, GasAccounting)>,
+    /// Failures injected into subgraph requests, for resilience testing
+    faults: Option<Arc<FaultPlan>>,
+    /// Responses reused across runs instead of downloaded again
+    cache: Option<SubgraphCache>,
 }
 
 impl GraphIndexer {
     /// Create a new Graph indexer client
     pub fn new(graph_url: String) -> Self {
+        Self::with_endpoints(EndpointSet::new(vec![SubgraphEndpoint::new(graph_url)]))
+    }
+
+    /// Create a client that fails over across prioritized endpoints
+    pub fn with_endpoints(endpoints: EndpointSet) -> Self {
         Self {
             client: Client::new(),
-            graph_url,
+            endpoints,
+            token_filter: TokenFilter::default(),
+            position_queries: HashMap::new(),
+            swaps_query: None,
+            gas_tracking: None,
+            faults: None,
+            cache: None,
         }
     }
 
-    /// Create indexer from environment variable
+    /// Use a custom token filter for sync and discovery
+    pub fn with_token_filter(mut self, token_filter: TokenFilter) -> Self {
+        self.token_filter = token_filter;
+        self
+    }
+
+    /// Replace one of the position queries, e.g. for a subgraph with extra filters
+    pub fn with_position_query(
+        mut self,
+        kind: PositionQuery,
+        query: CustomQuery<PositionResponse>,
+    ) -> Self {
+        self.position_queries.insert(kind, query);
+        self
+    }
+
+    /// Replace the recent swaps query
+    ///
+    /// Custom swap queries are sent per pool; multi-pool syncs no longer batch them.
+    pub fn with_swaps_query(mut self, query: CustomQuery<SwapResponse>) -> Self {
+        self.swaps_query = Some(query);
+        self
+    }
+
+    /// Record the gas of newly synced liquidity events, read from receipts over RPC
+    pub fn with_gas_tracking(
+        mut self,
+        blockchain: BlockchainService,
+        accounting: GasAccounting,
+    ) -> Self {
+        self.gas_tracking = Some((blockchain, accounting));
+        self
+    }
+
+    /// Inject failures into every subgraph request, one plan step per request
+    ///
+    /// Lets the retry, failover and checkpoint logic be exercised against a
+    /// real subgraph without waiting for it to misbehave.
+    pub fn with_faults(mut self, plan: Arc<FaultPlan>) -> Self {
+        self.faults = Some(plan);
+        self
+    }
+
+    /// Reuse responses from a cache instead of downloading identical pages again
+    ///
+    /// Block metadata is always fetched fresh, so head tracking and reorg
+    /// checks never see a cached block.
+    pub fn with_cache(mut self, cache: SubgraphCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Send every request to the subgraph, for paths that must see the latest data
+    pub fn without_cache(mut self) -> Self {
+        self.cache = None;
+        self
+    }
+
+    /// Create indexer from environment variables (see `EndpointSet::from_env`)
+    ///
+    /// `CHAOS_GRAPH_FAULTS` injects a fault plan (see `FaultPlan::parse`) and
+    /// `GRAPH_CACHE` a response cache (see `SubgraphCache::from_env`).
     pub fn from_env() -> Result<Self> {
-        let graph_url = std::env::var("GRAPH_API_URL")
+        let endpoints = EndpointSet::from_env()?
             .context("GRAPH_API_URL must be set in environment")?;
-        Ok(Self::new(graph_url))
+        let mut indexer =
+            Self::with_endpoints(endpoints).with_token_filter(TokenFilter::from_env());
+        if let Some(plan) = FaultPlan::from_env("CHAOS_GRAPH_FAULTS")? {
+            warn!("Injecting subgraph faults from CHAOS_GRAPH_FAULTS");
+            indexer = indexer.with_faults(Arc::new(plan));
+        }
+        if let Some(cache) = SubgraphCache::from_env()? {
+            info!("Caching subgraph responses (GRAPH_CACHE)");
+            indexer = indexer.with_cache(cache);
+        }
+        Ok(indexer)
+    }
+
+    /// Health of each configured endpoint, in priority order
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.health()
     }
 
-    /// Execute a GraphQL query
+    /// Execute a standard GraphQL query, failing over to the next endpoint on error
+    ///
+    /// The query is rewritten for each endpoint's dialect; endpoints that
+    /// can't express it are skipped.
     async fn query<T>(&self, query: &str, variables: serde_json::Value) -> Result<T>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
-        let body = json!({
-            "query": query,
-            "variables": variables
-        });
+        self.query_endpoints(query, variables, true, true).await
+    }
+
+    /// Execute a standard query against the subgraph itself, never the cache
+    async fn query_fresh<T>(&self, query: &str, variables: serde_json::Value) -> Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        self.query_endpoints(query, variables, true, false).await
+    }
+
+    /// Execute a user-supplied query as written, on every endpoint regardless of dialect
+    async fn query_as_written<T>(&self, query: &str, variables: serde_json::Value) -> Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        self.query_endpoints(query, variables, false, true).await
+    }
 
-        info!("Sending GraphQL query to {}", self.graph_url);
+    async fn query_endpoints<T>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+        translate: bool,
+        cached: bool,
+    ) -> Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
         debug!("Query variables: {:?}", variables);
 
-        let response = self
-            .client
-            .post(&self.graph_url)
-            .json(&body)
-            .send()
-            .await
-            .context("Failed to send GraphQL request")?;
+        let mut last_error = None;
+        for index in self.endpoints.attempt_order() {
+            let endpoint = &self.endpoints.endpoints()[index];
+            let query = if translate {
+                match endpoint.dialect.translate(query) {
+                    Ok(query) => query,
+                    Err(e) => {
+                        debug!("Skipping endpoint {}: {}", endpoint.display_url(), e);
+                        last_error = Some(e);
+                        continue;
+                    }
+                }
+            } else {
+                query.to_string()
+            };
+            let cache_key = match &self.cache {
+                Some(_) if cached => Some(SubgraphCache::key(
+                    &query,
+                    &variables,
+                    self.endpoints.indexed_block(index),
+                )),
+                _ => None,
+            };
+            let body = json!({
+                "query": query,
+                "variables": variables
+            });
+            info!("Sending GraphQL query to {}", endpoint.display_url());
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("GraphQL request failed with status {}: {}", status, text));
+            match self.send_query(endpoint, &body, cache_key.as_deref()).await {
+                Ok(data) => {
+                    self.endpoints.record_success(index);
+                    return Ok(data);
+                }
+                Err(e) => {
+                    warn!("Subgraph endpoint {} failed: {}", endpoint.display_url(), e);
+                    self.endpoints.record_failure(index, &e.to_string());
+                    last_error = Some(e);
+                }
+            }
         }
 
-        let result: GraphQLResponse<T> = response
-            .json()
-            .await
-            .context("Failed to parse GraphQL response")?;
+        let error = last_error.unwrap_or_else(|| anyhow!("No subgraph endpoints configured"));
+        Err(error.context(format!("All {} subgraph endpoints failed", self.endpoints.len())))
+    }
+
+    /// Query every endpoint's indexed block and mark the ones lagging behind
+    ///
+    /// Run before a sync so lagging endpoints are skipped in favour of fresher ones.
+    pub async fn check_endpoint_lag(&self) -> Vec<EndpointHealth> {
+        let mut blocks = Vec::with_capacity(self.endpoints.len());
+
+        for (index, endpoint) in self.endpoints.endpoints().iter().enumerate() {
+            // Every dialect can report its indexed block
+            let query = endpoint.dialect.translate(queries::INDEXED_BLOCK).unwrap_or_default();
+            let body = json!({ "query": query });
+            match self.send_query::<MetaData>(endpoint, &body, None).await {
+                Ok(meta) => blocks.push(Some(meta.meta.block.number)),
+                Err(e) => {
+                    warn!("Subgraph endpoint {} failed: {}", endpoint.display_url(), e);
+                    self.endpoints.record_failure(index, &e.to_string());
+                    blocks.push(None);
+                }
+            }
+        }
+
+        self.endpoints.record_indexed_blocks(&blocks);
+        self.endpoints.health()
+    }
+
+    /// Latest block the subgraph has indexed
+    pub async fn fetch_head_block(&self) -> Result<MetaBlockResponse> {
+        let data: MetaData = self.query_fresh(queries::INDEXED_BLOCK, json!({})).await?;
+        Ok(data.meta.block)
+    }
+
+    /// The block the subgraph has at `number`, with its canonical hash
+    pub async fn fetch_block(&self, number: u64) -> Result<MetaBlockResponse> {
+        let data: MetaData =
+            self.query_fresh(queries::BLOCK_AT, json!({ "number": number })).await?;
+        Ok(data.meta.block)
+    }
+
+    /// Send a GraphQL request to a single endpoint, or answer it from the cache under `cache_key`
+    async fn send_query<T>(
+        &self,
+        endpoint: &SubgraphEndpoint,
+        body: &serde_json::Value,
+        cache_key: Option<&str>,
+    ) -> Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let cache = self.cache.as_ref().zip(cache_key);
+        let cached = match cache {
+            Some((cache, key)) => cache.get(key).await,
+            None => None,
+        };
+
+        let (mut value, page_limit) = match cached {
+            Some(value) => {
+                debug!("Answered GraphQL query from cache");
+                (value, None)
+            }
+            None => {
+                let fault = self.faults.as_ref().and_then(|plan| plan.next_fault());
+                let page_limit = match fault {
+                    Some(fault) => chaos::apply_fault(fault).await?,
+                    None => None,
+                };
+                let value = self.fetch_response(endpoint, body).await?;
+                if let Some((cache, key)) = cache
+                    && value.get("errors").is_none_or(|e| e.is_null())
+                {
+                    cache.put(key, &value).await;
+                }
+                (value, page_limit)
+            }
+        };
+
+        if let Some(data) = value.get_mut("data") {
+            if let Some(limit) = page_limit {
+                chaos::truncate_lists(data, limit);
+            }
+            endpoint.dialect.normalize_data(data);
+        }
+        let result: GraphQLResponse<T> =
+            serde_json::from_value(value).context("Failed to parse GraphQL response")?;
 
         if let Some(errors) = result.errors {
             let error_messages: Vec<String> = errors.iter().map(|e| e.message.clone()).collect();
@@ -75,18 +361,69 @@ impl GraphIndexer {
         result.data.ok_or_else(|| anyhow!("No data in GraphQL response"))
     }
 
+    /// POST a GraphQL request and read the response as JSON
+    async fn fetch_response(
+        &self,
+        endpoint: &SubgraphEndpoint,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let mut request = self.client.post(&endpoint.url).json(body);
+        if let Some(api_key) = &endpoint.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request.send().await.context("Failed to send GraphQL request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("GraphQL request failed with status {}: {}", status, text));
+        }
+
+        response.json().await.context("Failed to parse GraphQL response")
+    }
+
+    /// Run a position query, or its registered override
+    ///
+    /// The standard queries skip events that don't parse; a custom query's
+    /// mapper decides for itself, so its report only counts missing fields.
+    async fn query_positions(
+        &self,
+        kind: PositionQuery,
+        standard_query: &str,
+        variables: serde_json::Value,
+    ) -> Result<Tolerant<PositionResponse>> {
+        let positions = match self.position_queries.get(&kind) {
+            Some(custom) => {
+                let data =
+                    self.query_as_written(&custom.query, custom.variables_with(variables)).await?;
+                Tolerant::from_items(custom.map(data)?)
+            }
+            None => {
+                let data: PositionsData = self.query(standard_query, variables).await?;
+                data.positions
+            }
+        };
+        log_field_report("Liquidity events", &positions.report);
+        Ok(positions)
+    }
+
     /// Fetch positions by owner address
     pub async fn fetch_positions_by_owner(&self, owner: &str) -> Result<Vec<PositionResponse>> {
         let variables = json!({ "owner": owner.to_lowercase() });
-        let data: PositionsData = self.query(queries::POSITIONS_BY_OWNER, variables).await?;
-        Ok(data.positions)
+        let positions =
+            self.query_positions(PositionQuery::ByOwner, queries::POSITIONS_BY_OWNER, variables)
+                .await?;
+        Ok(self.filter_positions(positions.items))
     }
 
     /// Fetch positions by pool ID
     pub async fn fetch_positions_by_pool(&self, pool_id: &str) -> Result<Vec<PositionResponse>> {
         let variables = json!({ "poolId": pool_id.to_lowercase() });
-        let data: PositionsData = self.query(queries::POSITIONS_BY_POOL, variables).await?;
-        Ok(data.positions)
+        let positions =
+            self.query_positions(PositionQuery::ByPool, queries::POSITIONS_BY_POOL, variables)
+                .await?;
+        Ok(self.filter_positions(positions.items))
     }
 
     /// Fetch recent swaps for a pool since a timestamp
@@ -100,81 +437,414 @@ impl GraphIndexer {
             "poolId": pool_id.to_lowercase(),
             "timestamp": timestamp.to_string()
         });
-        let data: SwapsData = self.query(queries::RECENT_SWAPS, variables).await?;
-        Ok(data.swaps)
+        let swaps = match &self.swaps_query {
+            Some(custom) => {
+                let data =
+                    self.query_as_written(&custom.query, custom.variables_with(variables)).await?;
+                Tolerant::from_items(custom.map(data)?)
+            }
+            None => {
+                let data: SwapsData = self.query(queries::RECENT_SWAPS, variables).await?;
+                data.swaps
+            }
+        };
+        log_field_report("Swaps", &swaps.report);
+        Ok(swaps.items)
+    }
+
+    /// Fetch recent swaps for several pools, `SWAP_BATCH_SIZE` pools per request
+    ///
+    /// Each batch is a single aliased query (`s0: swaps(...)`, `s1: ...`), so
+    /// syncing many pools costs one round-trip per batch instead of per pool.
+    /// A custom swaps query is sent once per pool instead. A batch (or pool)
+    /// that fails is logged and left out, so it doesn't hold up the others;
+    /// the error is only returned when nothing could be fetched.
+    pub async fn fetch_recent_swaps_batched(
+        &self,
+        pool_ids: &[String],
+        since: DateTime<Utc>,
+    ) -> Result<HashMap<String, Vec<SwapResponse>>> {
+        let mut swaps_by_pool = HashMap::with_capacity(pool_ids.len());
+        let mut failed = None;
+
+        if self.swaps_query.is_some() {
+            for pool_id in pool_ids {
+                match self.fetch_recent_swaps(pool_id, since).await {
+                    Ok(swaps) => {
+                        swaps_by_pool.insert(pool_id.clone(), swaps);
+                    }
+                    Err(e) => {
+                        warn!("Skipping swaps of pool {}: {:#}", pool_id, e);
+                        failed = Some(e);
+                    }
+                }
+            }
+        } else {
+            for batch in pool_ids.chunks(SWAP_BATCH_SIZE) {
+                let query = queries::batched_swaps_query(batch.len());
+                let variables = queries::batched_swaps_variables(batch, since);
+                match self.query::<BatchedSwapsData>(&query, variables).await {
+                    Ok(data) => {
+                        let (swaps, report) = queries::unbatch_swaps(batch, data);
+                        swaps_by_pool.extend(swaps);
+                        log_field_report("Swaps", &report);
+                    }
+                    Err(e) => {
+                        warn!("Skipping swaps of {} pools from {}: {:#}", batch.len(), batch[0], e);
+                        failed = Some(e);
+                    }
+                }
+            }
+        }
+
+        match failed {
+            Some(e) if swaps_by_pool.is_empty() => Err(e),
+            _ => Ok(swaps_by_pool),
+        }
     }
 
     /// Fetch recent positions since a timestamp
     pub async fn fetch_recent_positions(&self, since: DateTime<Utc>) -> Result<Vec<PositionResponse>> {
+        Ok(self.fetch_recent_position_batch(since).await?.items)
+    }
+
+    /// Recent positions along with the fields the subgraph left out of them
+    async fn fetch_recent_position_batch(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Tolerant<PositionResponse>> {
         let timestamp = since.timestamp();
         let variables = json!({ "timestamp": timestamp.to_string() });
-        let data: PositionsData = self.query(queries::RECENT_POSITIONS, variables).await?;
-        Ok(data.positions)
+        self.query_positions(PositionQuery::Recent, queries::RECENT_POSITIONS, variables).await
+    }
+
+    /// Fetch PositionManager NFT transfers since a timestamp, oldest first
+    pub async fn fetch_recent_transfers(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<TransferResponse>> {
+        let variables = json!({ "timestamp": since.timestamp().to_string() });
+        let data: TransfersData = self.query(queries::RECENT_TRANSFERS, variables).await?;
+        log_field_report("Transfers", &data.transfers.report);
+        Ok(data.transfers.items)
+    }
+
+    /// Drop positions in pools rejected by the token filter
+    fn filter_positions(&self, positions: Vec<PositionResponse>) -> Vec<PositionResponse> {
+        positions.into_iter().filter(|pos| self.allows_position(pos)).collect()
     }
 
-    /// Sync positions to database
-    pub async fn sync_positions(&self, db_pool: &PgPool) -> Result<usize> {
+    /// Whether the token filter accepts a position's pool
+    fn allows_position(&self, pos: &PositionResponse) -> bool {
+        match self.token_filter.check_position(pos) {
+            Ok(()) => true,
+            Err(reason) => {
+                debug!("Skipping position {} in pool {}: {}", pos.id, pos.pool.id, reason);
+                false
+            }
+        }
+    }
+
+    /// Sync positions to database, recording per-stage timings and row counts in `run`
+    pub async fn sync_positions(&self, db_pool: &PgPool, run: &mut SyncRun) -> Result<usize> {
         // Fetch positions from the last 30 days (increased from 1 hour for testing)
         let since = Utc::now() - chrono::Duration::days(30);
         info!("Fetching positions since {}", since);
 
-        let positions = self.fetch_recent_positions(since).await?;
+        let stage = Instant::now();
+        let batch = self.fetch_recent_position_batch(since).await?;
+        run.record(SyncStage::Fetch, stage.elapsed());
+        run.rows_fetched = batch.items.len() as i32;
+        run.fields = batch.report;
+        let positions = batch.items;
 
         info!("Fetched {} positions from The Graph", positions.len());
 
-        let mut inserted = 0;
+        let stage = Instant::now();
+        let mut events = Vec::with_capacity(positions.len());
         for pos_resp in positions {
-            // First, ensure the pool exists
-            match self.convert_and_insert_pool(db_pool, &pos_resp.pool).await {
-                Ok(_) => {}
+            match self.convert_liquidity_event(&pos_resp) {
+                Ok(event) => events.push((pos_resp, event)),
+                Err(e) => warn!("Failed to parse position {}: {}", pos_resp.id, e),
+            }
+        }
+        run.record(SyncStage::Parse, stage.elapsed());
+        run.rows_parsed = events.len() as i32;
+
+        let stage = Instant::now();
+        let parsed = events.len();
+        events.retain(|(pos_resp, _)| self.allows_position(pos_resp));
+        if events.len() < parsed {
+            info!("Filtered out {} positions in spam or denylisted pools", parsed - events.len());
+        }
+        // Endpoint failover can return the same event twice in one batch
+        let mut seen = HashSet::new();
+        events.retain(|(_, event)| seen.insert(event.event_id.clone()));
+        // Apply events oldest first so removals follow the additions they undo
+        events.sort_by_key(|(_, event)| event.timestamp);
+        run.record(SyncStage::Dedupe, stage.elapsed());
+        run.rows_kept = events.len() as i32;
+
+        let stage = Instant::now();
+        let (inserted, removals) = self.apply_events(db_pool, events).await?;
+        run.record(SyncStage::Insert, stage.elapsed());
+        run.rows_inserted = inserted as i32;
+        run.rows_removed = removals;
+
+        info!("Inserted {} new positions, applied {} liquidity removals", inserted, removals);
+        Ok(inserted)
+    }
+
+    /// Sync one owner's liquidity events, e.g. when an embedding application starts tracking them
+    ///
+    /// Runs the same parse, filter and insert steps as `sync_positions` over
+    /// the owner's full subgraph history. Returns how many new positions were inserted.
+    pub async fn sync_owner_positions(&self, db_pool: &PgPool, owner: &str) -> Result<usize> {
+        let positions = self.fetch_positions_by_owner(owner).await?;
+        info!("Fetched {} positions of {} from The Graph", positions.len(), owner);
+        let (inserted, removals) = self.apply_fetched(db_pool, positions).await?;
+        info!(
+            "Inserted {} new positions of {}, applied {} liquidity removals",
+            inserted, owner, removals
+        );
+        Ok(inserted)
+    }
+
+    /// Sync the latest liquidity events of one pool, e.g. when an operator asks for it
+    ///
+    /// Like `sync_owner_positions`, over the pool's most recent events.
+    /// Returns how many new positions were inserted.
+    pub async fn sync_pool_positions(&self, db_pool: &PgPool, pool_id: &str) -> Result<usize> {
+        let positions = self.fetch_positions_by_pool(pool_id).await?;
+        info!("Fetched {} positions in pool {} from The Graph", positions.len(), pool_id);
+        let (inserted, removals) = self.apply_fetched(db_pool, positions).await?;
+        info!(
+            "Inserted {} new positions in pool {}, applied {} liquidity removals",
+            inserted, pool_id, removals
+        );
+        Ok(inserted)
+    }
+
+    /// Parse and dedupe fetched (already filtered) events, then store them oldest first
+    async fn apply_fetched(
+        &self,
+        db_pool: &PgPool,
+        positions: Vec<PositionResponse>,
+    ) -> Result<(usize, i32)> {
+        let mut seen = HashSet::new();
+        let mut events: Vec<_> = positions
+            .into_iter()
+            .filter_map(|pos_resp| match self.convert_liquidity_event(&pos_resp) {
+                Ok(event) => Some((pos_resp, event)),
                 Err(e) => {
-                    warn!("Failed to insert pool {}: {}", pos_resp.pool.id, e);
-                    continue;
+                    warn!("Failed to parse position {}: {}", pos_resp.id, e);
+                    None
                 }
-            }
+            })
+            .filter(|(_, event)| seen.insert(event.event_id.clone()))
+            .collect();
+        events.sort_by_key(|(_, event)| event.timestamp);
 
-            // Then insert the position
-            match self.convert_and_insert_position(db_pool, &pos_resp).await {
-                Ok(_) => {
-                    inserted += 1;
-                    debug!("Inserted position {}", pos_resp.id);
+        self.apply_events(db_pool, events).await
+    }
+
+    /// Store parsed liquidity events oldest first, returning (positions inserted, removals applied)
+    ///
+    /// The batch is one transaction, so a crash part way through leaves no pool
+    /// without its positions and no event without the position it's attributed
+    /// to; the next sync applies the whole batch again. Each event runs under a
+    /// savepoint, so one that fails is rolled back and logged without aborting
+    /// the rest. A batch that fails as a whole is retried (`retry_batch`).
+    async fn apply_events(
+        &self,
+        db_pool: &PgPool,
+        events: Vec<(PositionResponse, LiquidityEvent)>,
+    ) -> Result<(usize, i32)> {
+        let events = &events;
+        retry_batch("Liquidity event", || self.apply_event_batch(db_pool, events)).await
+    }
+
+    /// One attempt at `apply_events`
+    async fn apply_event_batch(
+        &self,
+        db_pool: &PgPool,
+        events: &[(PositionResponse, LiquidityEvent)],
+    ) -> Result<(usize, i32)> {
+        let mut tx = db_pool.begin().await.context("Failed to begin sync batch")?;
+        let mut inserted = 0;
+        let mut removals = 0;
+        for (pos_resp, event) in events {
+            let mut savepoint = tx.begin().await.context("Failed to create savepoint")?;
+            match self.apply_event(&mut savepoint, pos_resp, event).await {
+                Ok(applied) => {
+                    savepoint.commit().await.context("Failed to release savepoint")?;
+                    match applied {
+                        Some(LiquidityChange::Add) => inserted += 1,
+                        Some(LiquidityChange::Remove) => removals += 1,
+                        None => {}
+                    }
                 }
                 Err(e) => {
-                    warn!("Failed to insert position {}: {}", pos_resp.id, e);
+                    warn!("Skipping liquidity event {}: {:#}", event.event_id, e);
+                    savepoint.rollback().await.context("Failed to roll back savepoint")?;
                 }
             }
         }
+        tx.commit().await.context("Failed to commit sync batch")?;
+        Ok((inserted, removals))
+    }
 
-        info!("Inserted {} new positions", inserted);
-        Ok(inserted)
+    /// Store one liquidity event with its pool and gas
+    ///
+    /// Returns the change applied, None for a removal applied by an earlier
+    /// sync or an event that moved no liquidity (a fee collect or poke).
+    async fn apply_event(
+        &self,
+        conn: &mut PgConnection,
+        pos_resp: &PositionResponse,
+        event: &LiquidityEvent,
+    ) -> Result<Option<LiquidityChange>> {
+        let Some(change) = LiquidityChange::of(event.liquidity_delta) else {
+            debug!("Skipping liquidity event {} with a zero delta", event.event_id);
+            return Ok(None);
+        };
+
+        // First, ensure the pool exists
+        self.convert_and_insert_pool(conn, &pos_resp.pool)
+            .await
+            .with_context(|| format!("Failed to insert pool {}", pos_resp.pool.id))?;
+
+        // Removals reduce existing positions; additions create new ones
+        if change == LiquidityChange::Remove {
+            let Some(position_id) = self
+                .apply_removal(conn, event)
+                .await
+                .context("Failed to apply liquidity removal")?
+            else {
+                return Ok(None);
+            };
+            debug!("Applied liquidity removal {}", event.event_id);
+            self.record_gas(conn, position_id, pos_resp, event).await;
+            return Ok(Some(LiquidityChange::Remove));
+        }
+
+        // Then insert the position
+        let position_id =
+            self.insert_added_position(conn, event).await.context("Failed to insert position")?;
+        debug!("Inserted position {}", event.event_id);
+        self.record_gas(conn, position_id, pos_resp, event).await;
+        Ok(Some(LiquidityChange::Add))
+    }
+
+    /// Move transferred position NFTs to their new owners
+    ///
+    /// Transfers are matched to positions stored under the token id as
+    /// `nft_id` (positions found by wallet scans); mints, burns and transfers
+    /// of untracked tokens are skipped. Written as one batch like
+    /// `apply_events`. Returns how many new transfers were recorded.
+    pub async fn sync_transfers(&self, db_pool: &PgPool, since: DateTime<Utc>) -> Result<usize> {
+        let transfers = self.fetch_recent_transfers(since).await?;
+        info!("Fetched {} NFT transfers from The Graph", transfers.len());
+
+        let transfers: Vec<&TransferResponse> =
+            transfers.iter().filter(|t| !t.is_mint_or_burn()).collect();
+        let transfers = &transfers;
+        let recorded =
+            retry_batch("Transfer", || self.record_transfer_batch(db_pool, transfers)).await?;
+
+        info!("Recorded {} position transfers", recorded);
+        Ok(recorded)
+    }
+
+    /// One attempt at recording a batch of transfers, each under a savepoint
+    async fn record_transfer_batch(
+        &self,
+        db_pool: &PgPool,
+        transfers: &[&TransferResponse],
+    ) -> Result<usize> {
+        let mut tx = db_pool.begin().await.context("Failed to begin sync batch")?;
+        let mut recorded = 0;
+        for transfer_resp in transfers {
+            let mut savepoint = tx.begin().await.context("Failed to create savepoint")?;
+            match self.convert_and_record_transfer(&mut savepoint, transfer_resp).await {
+                Ok(new) => {
+                    savepoint.commit().await.context("Failed to release savepoint")?;
+                    if new {
+                        recorded += 1;
+                        debug!("Recorded transfer {}", transfer_resp.id);
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to record transfer {}: {}", transfer_resp.id, e);
+                    savepoint.rollback().await.context("Failed to roll back savepoint")?;
+                }
+            }
+        }
+        tx.commit().await.context("Failed to commit sync batch")?;
+        Ok(recorded)
     }
 
     /// Sync swaps to database
     pub async fn sync_swaps(&self, db_pool: &PgPool, pool_id: &str) -> Result<usize> {
+        self.sync_swaps_for_pools(db_pool, &[pool_id.to_string()]).await
+    }
+
+    /// Sync the last hour of swaps for several pools, batching the subgraph queries
+    pub async fn sync_swaps_for_pools(&self, db_pool: &PgPool, pool_ids: &[String]) -> Result<usize> {
         let since = Utc::now() - chrono::Duration::hours(1);
-        let swaps = self.fetch_recent_swaps(pool_id, since).await?;
+        self.sync_swaps_for_pools_since(db_pool, pool_ids, since).await
+    }
 
-        info!("Fetched {} swaps from The Graph for pool {}", swaps.len(), pool_id);
+    /// Sync swaps since `since` for several pools, batching the subgraph queries
+    ///
+    /// Each pool's swaps are written as one batch like `apply_events`.
+    pub async fn sync_swaps_for_pools_since(
+        &self,
+        db_pool: &PgPool,
+        pool_ids: &[String],
+        since: DateTime<Utc>,
+    ) -> Result<usize> {
+        let swaps_by_pool = self.fetch_recent_swaps_batched(pool_ids, since).await?;
 
+        let mut inserted = 0;
+        for (pool_id, swaps) in swaps_by_pool {
+            info!("Fetched {} swaps from The Graph for pool {}", swaps.len(), pool_id);
+            let swaps = &swaps;
+            inserted += retry_batch("Swap", || self.insert_swap_batch(db_pool, swaps)).await?;
+        }
+
+        info!("Inserted {} new swaps", inserted);
+        Ok(inserted)
+    }
+
+    /// One attempt at inserting a batch of swaps, each under a savepoint
+    async fn insert_swap_batch(&self, db_pool: &PgPool, swaps: &[SwapResponse]) -> Result<usize> {
+        let mut tx = db_pool.begin().await.context("Failed to begin sync batch")?;
         let mut inserted = 0;
         for swap_resp in swaps {
-            match self.convert_and_insert_swap(db_pool, &swap_resp).await {
+            let mut savepoint = tx.begin().await.context("Failed to create savepoint")?;
+            match self.convert_and_insert_swap(&mut savepoint, swap_resp).await {
                 Ok(_) => {
+                    savepoint.commit().await.context("Failed to release savepoint")?;
                     inserted += 1;
                     debug!("Inserted swap {}", swap_resp.id);
                 }
                 Err(e) => {
                     warn!("Failed to insert swap {}: {}", swap_resp.id, e);
+                    savepoint.rollback().await.context("Failed to roll back savepoint")?;
                 }
             }
         }
-
-        info!("Inserted {} new swaps", inserted);
+        tx.commit().await.context("Failed to commit sync batch")?;
         Ok(inserted)
     }
 
     /// Convert and insert pool into database
-    async fn convert_and_insert_pool(&self, db_pool: &PgPool, pool_resp: &PoolResponse) -> Result<()> {
+    async fn convert_and_insert_pool(
+        &self,
+        conn: &mut PgConnection,
+        pool_resp: &PoolResponse,
+    ) -> Result<()> {
         let fee_tier = pool_resp.fee.parse::<i32>()
             .context("Failed to parse fee")?;
         let tick_spacing = pool_resp.tick_spacing.parse::<i32>()
@@ -184,57 +854,219 @@ impl GraphIndexer {
             pool_id: pool_resp.id.clone(),
             token0: pool_resp.token0.id.clone(),
             token1: pool_resp.token1.id.clone(),
+            token0_decimals: pool_resp.token0.decimals(),
+            token1_decimals: pool_resp.token1.decimals(),
             fee_tier,
             tick_spacing,
-            created_at: Utc::now(), // We don't have creation time from subgraph
+            hooks: pool_resp.hooks.clone().unwrap_or_else(|| NO_HOOKS.to_string()),
+            protocol_fee: 0, // Read from pool state by the sync binary
+            created_at: pool_resp.created_at(),
+            created_at_block: pool_resp.created_at_block(),
+            fee_override: None,
         };
 
-        insert_pool(db_pool, &pool).await?;
+        insert_pool(conn, &pool).await?;
         Ok(())
     }
 
-    /// Convert and insert position into database (v4: from ModifyLiquidity event)
-    async fn convert_and_insert_position(&self, db_pool: &PgPool, pos_resp: &PositionResponse) -> Result<()> {
+    /// Convert a ModifyLiquidity event, keeping the sign of its liquidity delta
+    fn convert_liquidity_event(&self, pos_resp: &PositionResponse) -> Result<LiquidityEvent> {
         let tick_lower = pos_resp.tick_lower.parse::<i32>()
             .context("Failed to parse tick_lower")?;
         let tick_upper = pos_resp.tick_upper.parse::<i32>()
             .context("Failed to parse tick_upper")?;
-        let liquidity = U256::from_str_radix(&pos_resp.liquidity, 10)
-            .context("Failed to parse liquidity")?;
+        let liquidity_delta = pos_resp.liquidity_delta()
+            .ok_or_else(|| anyhow!("Failed to parse liquidity delta {:?}", pos_resp.amount))?;
         // In v4, timestamp is a direct field
         let timestamp = pos_resp.timestamp.parse::<i64>()
             .context("Failed to parse timestamp")?;
-        let created_at = DateTime::from_timestamp(timestamp, 0)
+        let timestamp = DateTime::from_timestamp(timestamp, 0)
             .ok_or_else(|| anyhow!("Invalid timestamp"))?;
 
-        let position = Position {
-            id: 0, // Will be auto-generated
-            nft_id: pos_resp.id.clone(),
-            owner: pos_resp.owner.clone(),
+        Ok(LiquidityEvent {
+            event_id: pos_resp.id.clone(),
+            owner: Address::parse(&pos_resp.owner)
+                .with_context(|| format!("Invalid owner {:?}", pos_resp.owner))?,
             pool_id: pos_resp.pool.id.clone(),
             tick_lower,
             tick_upper,
-            liquidity,
-            created_at,
+            liquidity_delta,
+            timestamp,
+        })
+    }
+
+    /// Apply a liquidity removal to the owner's matching positions
+    ///
+    /// Returns None if the event was already applied by an earlier sync,
+    /// otherwise the position it was recorded against (if any matched).
+    async fn apply_removal(
+        &self,
+        conn: &mut PgConnection,
+        event: &LiquidityEvent,
+    ) -> Result<Option<Option<i64>>> {
+        let Some(outcome) = apply_liquidity_removal(conn, event).await? else {
+            return Ok(None);
         };
+        if !outcome.unmatched.is_zero() {
+            warn!(
+                "Removal {} exceeded tracked liquidity of {} in pool {} by {}",
+                event.event_id, event.owner, event.pool_id, outcome.unmatched
+            );
+        }
+        if outcome.closed > 0 {
+            info!("Removal {} closed {} positions", event.event_id, outcome.closed);
+        }
+        Ok(Some(outcome.position_id))
+    }
 
-        insert_position(db_pool, &position).await?;
-        Ok(())
+    /// Insert the position opened by a ModifyLiquidity event
+    ///
+    /// Returns the position's ID when the event is recorded for the first time.
+    async fn insert_added_position(
+        &self,
+        conn: &mut PgConnection,
+        event: &LiquidityEvent,
+    ) -> Result<Option<i64>> {
+        if LiquidityChange::of(event.liquidity_delta) != Some(LiquidityChange::Add) {
+            return Err(anyhow!("Event {} adds no liquidity", event.event_id));
+        }
+
+        let position = Position {
+            id: 0, // Will be auto-generated
+            nft_id: event.event_id.clone(),
+            owner: event.owner,
+            pool_id: event.pool_id.clone(),
+            tick_lower: event.tick_lower,
+            tick_upper: event.tick_upper,
+            liquidity: event.liquidity_delta.unsigned_abs(),
+            created_at: event.timestamp,
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        };
+
+        // Subgraph anomalies can yield inverted or zero-width ranges
+        if let Err(e) = TickRange::of(&position) {
+            warn!("Quarantining position {}: {}", position.nft_id, e);
+            quarantine_position(&mut *conn, &position, &e.to_string()).await?;
+            return Ok(None);
+        }
+
+        insert_position(&mut *conn, &position).await?;
+        let position_id = get_position_by_nft(&mut *conn, &position.nft_id).await?.map(|p| p.id);
+        let recorded = record_liquidity_addition(conn, event, position_id).await?;
+        Ok(position_id.filter(|_| recorded))
+    }
+
+    /// Record the gas of a liquidity event's transaction against a position
+    ///
+    /// Only with gas tracking configured; failures are logged, not returned,
+    /// so a flaky RPC doesn't fail the sync. The row is written under its own
+    /// savepoint so a failed insert doesn't take the event down with it.
+    async fn record_gas(
+        &self,
+        conn: &mut PgConnection,
+        position_id: Option<i64>,
+        pos_resp: &PositionResponse,
+        event: &LiquidityEvent,
+    ) {
+        let (Some((blockchain, accounting)), Some(position_id), Some(tx_id)) =
+            (&self.gas_tracking, position_id, pos_resp.tx_hash())
+        else {
+            return;
+        };
+        let Ok(tx_hash) = tx_id.parse::<B256>() else {
+            warn!("Event {} has an invalid transaction hash {}", event.event_id, tx_id);
+            return;
+        };
+
+        let cost = match blockchain.get_transaction_fees(tx_hash).await {
+            Ok(Some(fees)) => transaction_gas_cost(&fees, *accounting),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Failed to read receipt of {}: {}", tx_id, e);
+                return;
+            }
+        };
+        let Some(cost) = cost else {
+            warn!("No gas cost for transaction {}", tx_id);
+            return;
+        };
+
+        let expense = GasExpense {
+            id: 0, // Will be auto-generated
+            position_id,
+            tx_hash: tx_id.to_lowercase(),
+            gas_cost: cost.total,
+            timestamp: event.timestamp,
+        };
+        let recorded = async {
+            let mut savepoint = conn.begin().await?;
+            insert_gas_expense(&mut *savepoint, &expense).await?;
+            savepoint.commit().await?;
+            anyhow::Ok(())
+        };
+        match recorded.await {
+            Ok(()) => debug!(
+                "Recorded gas {} ({} L1 data) for {}",
+                cost.total, cost.l1_data, tx_id
+            ),
+            Err(e) => warn!("Failed to record gas of {}: {}", tx_id, e),
+        }
+    }
+
+    /// Record a transfer against the position holding its token id
+    ///
+    /// Returns false if no position has the token or the transfer was already recorded.
+    async fn convert_and_record_transfer(
+        &self,
+        conn: &mut PgConnection,
+        transfer_resp: &TransferResponse,
+    ) -> Result<bool> {
+        let Some(position) = get_position_by_nft(&mut *conn, &transfer_resp.token_id).await? else {
+            return Ok(false);
+        };
+        let timestamp = transfer_resp.timestamp.parse::<i64>()
+            .context("Failed to parse timestamp")?;
+        let effective_from = DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| anyhow!("Invalid timestamp"))?;
+
+        let transfer = PositionTransfer {
+            transfer_id: transfer_resp.id.clone(),
+            position_id: position.id,
+            from_owner: Address::parse(&transfer_resp.from)
+                .with_context(|| format!("Invalid sender {:?}", transfer_resp.from))?,
+            to_owner: Address::parse(&transfer_resp.to)
+                .with_context(|| format!("Invalid recipient {:?}", transfer_resp.to))?,
+            tx_hash: transfer_resp
+                .transaction
+                .as_ref()
+                .and_then(|t| t.id.as_deref())
+                .map(str::to_lowercase),
+            effective_from,
+        };
+        record_position_transfer(conn, &transfer).await
     }
 
     /// Convert and insert swap into database
-    async fn convert_and_insert_swap(&self, db_pool: &PgPool, swap_resp: &SwapResponse) -> Result<()> {
+    async fn convert_and_insert_swap(
+        &self,
+        conn: &mut PgConnection,
+        swap_resp: &SwapResponse,
+    ) -> Result<()> {
         let amount0 = swap_resp.amount0.parse::<I256>()
             .context("Failed to parse amount0")?;
         let amount1 = swap_resp.amount1.parse::<I256>()
             .context("Failed to parse amount1")?;
-        let timestamp = swap_resp.transaction.timestamp.parse::<i64>()
+        let timestamp = swap_resp.timestamp()
+            .ok_or_else(|| anyhow!("Swap has no timestamp"))?
+            .parse::<i64>()
             .context("Failed to parse timestamp")?;
         let swap_time = DateTime::from_timestamp(timestamp, 0)
             .ok_or_else(|| anyhow!("Invalid timestamp"))?;
 
-        let tx_hash = swap_resp.transaction.id.clone()
-            .unwrap_or_else(|| swap_resp.id.clone());
+        // Swap ids are `<tx hash>-<log index>`, so the id stands in for a missing hash
+        let tx_hash = swap_resp.tx_hash().unwrap_or(&swap_resp.id).to_string();
 
         let swap = Swap {
             id: 0, // Will be auto-generated
@@ -242,20 +1074,251 @@ impl GraphIndexer {
             pool_id: swap_resp.pool.id.clone(),
             amount0,
             amount1,
+            fee: None, // Not exposed by the subgraph; taken from the pool's LP fee on insert
             timestamp: swap_time,
         };
 
-        insert_swap(db_pool, &swap).await?;
+        insert_swap(conn, &swap).await?;
         Ok(())
     }
 }
 
+/// Log the fields a subgraph left out of a response, if any
+///
+/// Missing optional fields are expected on some subgraph versions; skipped
+/// items mean data was lost and are warned about.
+fn log_field_report(source: &str, report: &FieldReport) {
+    if !report.missing.is_empty() {
+        let fields: Vec<String> =
+            report.missing.iter().map(|(field, count)| format!("{} ({})", field, count)).collect();
+        info!("{} lacking optional fields: {}", source, fields.join(", "));
+    }
+    for (reason, count) in &report.dropped {
+        warn!("Skipped {} of {} {}: {}", count, report.items, source.to_lowercase(), reason);
+    }
+}
+
 /// Helper function to create indexer from environment and sync all data
 pub async fn sync_all(db_pool: &PgPool) -> Result<()> {
     let indexer = GraphIndexer::from_env()?;
 
-    let positions_count = indexer.sync_positions(db_pool).await?;
+    let positions_count = indexer.sync_positions(db_pool, &mut SyncRun::start()).await?;
     info!("Synced {} positions", positions_count);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    //! Round trips of subgraph responses through conversion, insertion and reads
+    //!
+    //! They skip without a test database (see `crate::testing`).
+
+    use super::*;
+    use crate::testing::{create_test_pool, hex, test_database};
+    use alloy::primitives::U256;
+    use proptest::prelude::*;
+    use proptest::test_runner::{Config, TestRunner};
+    use stillwater_analytics::{MAX_TICK, MIN_TICK};
+    use stillwater_db::{get_position_by_id, get_swaps_for_pool_between};
+
+    /// Latest timestamp generated (2100-01-01), well inside TIMESTAMPTZ's range
+    const MAX_TIMESTAMP: i64 = 4_102_444_800;
+
+    /// Any positive liquidity a NUMERIC(78, 0) column must hold, up to `I256::MAX`
+    fn liquidity() -> impl Strategy<Value = U256> {
+        prop_oneof![
+            any::<u128>().prop_map(U256::from),
+            any::<[u8; 32]>().prop_map(|bytes| U256::from_be_bytes(bytes) >> 1),
+        ]
+        .prop_filter("liquidity must be positive", |l| !l.is_zero())
+    }
+
+    /// Any int256 amount, including both extremes
+    fn amount() -> impl Strategy<Value = I256> {
+        prop_oneof![
+            any::<i128>().prop_map(|a| I256::try_from(a).unwrap()),
+            any::<[u8; 32]>().prop_map(I256::from_be_bytes),
+            Just(I256::MIN),
+            Just(I256::MAX),
+        ]
+    }
+
+    fn position_response() -> impl Strategy<Value = PositionResponse> {
+        (
+            any::<[u8; 32]>(),
+            any::<[u8; 20]>(),
+            MIN_TICK..MAX_TICK,
+            1..=(MAX_TICK - MIN_TICK),
+            liquidity(),
+            0..=MAX_TIMESTAMP,
+        )
+            .prop_map(|(id, owner, tick_lower, width, liquidity, timestamp)| PositionResponse {
+                id: format!("{}-{}", hex(&id), id[0]),
+                owner: hex(&owner),
+                pool: create_test_pool(&hex(&id)),
+                tick_lower: tick_lower.to_string(),
+                tick_upper: (tick_lower + width).min(MAX_TICK).to_string(),
+                amount: liquidity.to_string(),
+                timestamp: timestamp.to_string(),
+                transaction: None,
+            })
+            .prop_filter("range must not be empty", |p| p.tick_lower != p.tick_upper)
+    }
+
+    fn swap_response() -> impl Strategy<Value = SwapResponse> {
+        (any::<[u8; 32]>(), amount(), amount(), 0..=MAX_TIMESTAMP).prop_map(
+            |(id, amount0, amount1, timestamp)| SwapResponse {
+                id: format!("{}-0", hex(&id)),
+                transaction: Some(TransactionResponse { id: Some(hex(&id)), timestamp: None }),
+                timestamp: Some(timestamp.to_string()),
+                pool: PoolIdResponse { id: hex(&id) },
+                amount0: amount0.to_string(),
+                amount1: amount1.to_string(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_positions_round_trip_losslessly() {
+        let Some((runtime, db_pool)) = test_database("roundtrip_positions") else {
+            return;
+        };
+        let indexer = GraphIndexer::new("http://localhost".to_string());
+
+        let mut runner = TestRunner::new(Config::with_cases(64));
+        let result = runner.run(&position_response(), |response| {
+            let event = indexer.convert_liquidity_event(&response).unwrap();
+            let stored = runtime.block_on(async {
+                let mut conn = db_pool.acquire().await.unwrap();
+                indexer.convert_and_insert_pool(&mut conn, &response.pool).await.unwrap();
+                let id = indexer.insert_added_position(&mut conn, &event).await.unwrap();
+                get_position_by_id(&db_pool, id.unwrap()).await.unwrap().unwrap()
+            });
+
+            prop_assert_eq!(stored.tick_lower.to_string(), response.tick_lower);
+            prop_assert_eq!(stored.tick_upper.to_string(), response.tick_upper);
+            prop_assert_eq!(stored.liquidity.to_string(), response.amount);
+            prop_assert_eq!(stored.created_at.timestamp().to_string(), response.timestamp);
+            prop_assert_eq!(stored.pool_id, response.pool.id);
+            prop_assert!(stored.owner == response.owner);
+            Ok(())
+        });
+        result.unwrap();
+    }
+
+    #[test]
+    fn test_swaps_round_trip_losslessly() {
+        let Some((runtime, db_pool)) = test_database("roundtrip_swaps") else {
+            return;
+        };
+        let indexer = GraphIndexer::new("http://localhost".to_string());
+
+        let mut runner = TestRunner::new(Config::with_cases(64));
+        let result = runner.run(&swap_response(), |response| {
+            let mut pool = create_test_pool(&hex(&[0; 32]));
+            pool.id = response.pool.id.clone();
+            let timestamp = response.timestamp().unwrap().parse::<i64>().unwrap();
+            let at = DateTime::from_timestamp(timestamp, 0).unwrap();
+            let stored = runtime.block_on(async {
+                let mut conn = db_pool.acquire().await.unwrap();
+                indexer.convert_and_insert_pool(&mut conn, &pool).await.unwrap();
+                indexer.convert_and_insert_swap(&mut conn, &response).await.unwrap();
+                get_swaps_for_pool_between(&db_pool, &pool.id, at, at).await.unwrap()
+            });
+
+            prop_assert_eq!(stored.len(), 1);
+            prop_assert_eq!(stored[0].amount0.to_string(), response.amount0);
+            prop_assert_eq!(stored[0].amount1.to_string(), response.amount1);
+            prop_assert_eq!(stored[0].timestamp, at);
+            Ok(())
+        });
+        result.unwrap();
+    }
+
+    #[test]
+    fn test_failed_row_rolls_back_alone() {
+        let Some((runtime, db_pool)) = test_database("batch_savepoints") else {
+            return;
+        };
+        let indexer = GraphIndexer::new("http://localhost".to_string());
+        let pool = create_test_pool(&hex(&[0xba; 32]));
+        let swap = |byte: u8, pool_id: &str| SwapResponse {
+            id: format!("{}-0", hex(&[byte; 32])),
+            transaction: Some(TransactionResponse { id: Some(hex(&[byte; 32])), timestamp: None }),
+            timestamp: Some("1700000000".to_string()),
+            pool: PoolIdResponse { id: pool_id.to_string() },
+            amount0: "-1000".to_string(),
+            amount1: "1000".to_string(),
+        };
+        // The middle swap's pool was never stored, so its insert fails
+        let swaps = vec![swap(1, &pool.id), swap(2, "0xmissing"), swap(3, &pool.id)];
+
+        let (inserted, stored) = runtime.block_on(async {
+            let mut conn = db_pool.acquire().await.unwrap();
+            indexer.convert_and_insert_pool(&mut conn, &pool).await.unwrap();
+            let inserted = indexer.insert_swap_batch(&db_pool, &swaps).await.unwrap();
+            let at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+            (inserted, get_swaps_for_pool_between(&db_pool, &pool.id, at, at).await.unwrap())
+        });
+
+        assert_eq!(inserted, 2);
+        assert_eq!(stored.len(), 2);
+    }
+
+    #[test]
+    fn test_removals_reconcile_against_open_positions() {
+        let Some((runtime, db_pool)) = test_database("removal_reconciliation") else {
+            return;
+        };
+        let indexer = GraphIndexer::new("http://localhost".to_string());
+        let pool = create_test_pool(&hex(&[0xee; 32]));
+        let event = |byte: u8, amount: &str, timestamp: i64| {
+            let response = PositionResponse {
+                id: format!("{}-0", hex(&[byte; 32])),
+                owner: hex(&[0x0a; 20]),
+                pool: pool.clone(),
+                tick_lower: "-600".to_string(),
+                tick_upper: "600".to_string(),
+                amount: amount.to_string(),
+                timestamp: timestamp.to_string(),
+                transaction: None,
+            };
+            let event = indexer.convert_liquidity_event(&response).unwrap();
+            (response, event)
+        };
+        let events = vec![
+            event(1, "1000", 1_700_000_000),
+            event(2, "500", 1_700_000_100),
+            // A fee collect moves no liquidity and opens no position
+            event(3, "0", 1_700_000_200),
+            // Takes all of the oldest position and part of the next
+            event(4, "-1200", 1_700_000_300),
+        ];
+        let removal = vec![events[3].clone()];
+
+        let (applied, reapplied, positions) = runtime.block_on(async {
+            sqlx::query("TRUNCATE pools RESTART IDENTITY CASCADE").execute(&db_pool).await.unwrap();
+            let applied = indexer.apply_events(&db_pool, events.clone()).await.unwrap();
+            let reapplied = indexer.apply_events(&db_pool, removal).await.unwrap();
+            let mut positions = Vec::new();
+            for (_, event) in &events {
+                positions.push(get_position_by_nft(&db_pool, &event.event_id).await.unwrap());
+            }
+            (applied, reapplied, positions)
+        });
+
+        assert_eq!(applied, (2, 1));
+        // Re-syncing the window doesn't subtract the removal twice
+        assert_eq!(reapplied, (0, 0));
+
+        let oldest = positions[0].as_ref().unwrap();
+        assert!(oldest.liquidity.is_zero());
+        assert_eq!(oldest.closed_at, DateTime::from_timestamp(1_700_000_300, 0));
+        let next = positions[1].as_ref().unwrap();
+        assert_eq!(next.liquidity, U256::from(300));
+        assert_eq!(next.closed_at, None);
+        assert!(positions[2].is_none());
+        assert!(positions[3].is_none());
+    }
+}
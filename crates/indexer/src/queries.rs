@@ -1,24 +1,41 @@
-/// GraphQL query to fetch modify liquidity events by origin (owner)
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use stillwater_models::FieldReport;
+
+use crate::types::{BatchedSwapsData, SwapResponse};
+
+/// GraphQL query to fetch modify liquidity events (additions and removals) by origin (owner)
 pub const POSITIONS_BY_OWNER: &str = r#"
 query ModifyLiquidityByOrigin($owner: String!) {
   modifyLiquidities(
-    where: { origin: $owner, amount_gt: "0" }
+    where: { origin: $owner, amount_not: "0" }
     orderBy: timestamp
     orderDirection: desc
     first: 100
   ) {
     id
     timestamp
+    transaction {
+      id
+    }
     pool {
       id
       token0 {
         id
+        symbol
+        decimals
       }
       token1 {
         id
+        symbol
+        decimals
       }
       feeTier
       tickSpacing
+      hooks
+      liquidity
+      createdAtTimestamp
+      createdAtBlockNumber
     }
     tickLower
     tickUpper
@@ -28,27 +45,38 @@ query ModifyLiquidityByOrigin($owner: String!) {
 }
 "#;
 
-/// GraphQL query to fetch modify liquidity events by pool ID
+/// GraphQL query to fetch modify liquidity events (additions and removals) by pool ID
 pub const POSITIONS_BY_POOL: &str = r#"
 query ModifyLiquidityByPool($poolId: String!) {
   modifyLiquidities(
-    where: { pool: $poolId, amount_gt: "0" }
+    where: { pool: $poolId, amount_not: "0" }
     orderBy: timestamp
     orderDirection: desc
     first: 100
   ) {
     id
     timestamp
+    transaction {
+      id
+    }
     pool {
       id
       token0 {
         id
+        symbol
+        decimals
       }
       token1 {
         id
+        symbol
+        decimals
       }
       feeTier
       tickSpacing
+      hooks
+      liquidity
+      createdAtTimestamp
+      createdAtBlockNumber
     }
     tickLower
     tickUpper
@@ -65,8 +93,10 @@ query RecentSwaps($poolId: String!, $timestamp: BigInt!) {
     where: { pool: $poolId, timestamp_gte: $timestamp }
     orderBy: timestamp
     orderDirection: asc
+    first: 1000
   ) {
     id
+    timestamp
     transaction {
       id
       timestamp
@@ -80,27 +110,38 @@ query RecentSwaps($poolId: String!, $timestamp: BigInt!) {
 }
 "#;
 
-/// GraphQL query to fetch all recent modify liquidity events (for polling)
+/// GraphQL query to fetch all recent modify liquidity events, both signs (for polling)
 pub const RECENT_POSITIONS: &str = r#"
 query RecentModifyLiquidity($timestamp: BigInt!) {
   modifyLiquidities(
-    where: { timestamp_gte: $timestamp, amount_gt: "0" }
+    where: { timestamp_gte: $timestamp, amount_not: "0" }
     orderBy: timestamp
     orderDirection: desc
     first: 100
   ) {
     id
     timestamp
+    transaction {
+      id
+    }
     pool {
       id
       token0 {
         id
+        symbol
+        decimals
       }
       token1 {
         id
+        symbol
+        decimals
       }
       feeTier
       tickSpacing
+      hooks
+      liquidity
+      createdAtTimestamp
+      createdAtBlockNumber
     }
     tickLower
     tickUpper
@@ -109,3 +150,218 @@ query RecentModifyLiquidity($timestamp: BigInt!) {
   }
 }
 "#;
+
+/// GraphQL query to fetch recent PositionManager NFT transfers, oldest first
+pub const RECENT_TRANSFERS: &str = r#"
+query RecentTransfers($timestamp: BigInt!) {
+  transfers(
+    where: { timestamp_gte: $timestamp }
+    orderBy: timestamp
+    orderDirection: asc
+    first: 1000
+  ) {
+    id
+    tokenId
+    from
+    to
+    timestamp
+    transaction {
+      id
+    }
+  }
+}
+"#;
+
+/// GraphQL query for the latest block a subgraph endpoint has indexed
+pub const INDEXED_BLOCK: &str = r#"
+query IndexedBlock {
+  _meta {
+    block {
+      number
+      hash
+      timestamp
+    }
+  }
+}
+"#;
+
+/// Subsquid's counterpart of `INDEXED_BLOCK` (no hash or timestamp)
+pub const SQUID_STATUS: &str = r#"
+query IndexedBlock {
+  squidStatus {
+    height
+  }
+}
+"#;
+
+/// GraphQL query for the hash the subgraph has for a block number
+pub const BLOCK_AT: &str = r#"
+query BlockAt($number: Int!) {
+  _meta(block: { number: $number }) {
+    block {
+      number
+      hash
+      timestamp
+    }
+  }
+}
+"#;
+
+/// Recent swaps for several pools in one request, one aliased field per pool
+///
+/// Pool `i` is queried as `s{i}` with variable `$p{i}` (see
+/// `batched_swaps_variables`); all share `$timestamp`.
+pub fn batched_swaps_query(pool_count: usize) -> String {
+    let params: String = (0..pool_count).map(|i| format!(", $p{}: String!", i)).collect();
+    let fields: String = (0..pool_count)
+        .map(|i| {
+            format!(
+                r#"
+  s{i}: swaps(
+    where: {{ pool: $p{i}, timestamp_gte: $timestamp }}
+    orderBy: timestamp
+    orderDirection: asc
+    first: 1000
+  ) {{
+    id
+    timestamp
+    transaction {{
+      id
+      timestamp
+    }}
+    pool {{
+      id
+    }}
+    amount0
+    amount1
+  }}"#
+            )
+        })
+        .collect();
+    format!("query BatchedSwaps($timestamp: BigInt!{}) {{{}\n}}\n", params, fields)
+}
+
+/// Variables for `batched_swaps_query` over these pools
+pub fn batched_swaps_variables(pool_ids: &[String], since: DateTime<Utc>) -> Value {
+    let mut variables = json!({ "timestamp": since.timestamp().to_string() });
+    for (i, pool_id) in pool_ids.iter().enumerate() {
+        variables[format!("p{}", i)] = json!(pool_id.to_lowercase());
+    }
+    variables
+}
+
+/// Swaps of a `batched_swaps_query` response by pool, with the fields they lacked
+///
+/// A pool whose alias is missing from the response gets no swaps.
+pub fn unbatch_swaps(
+    pool_ids: &[String],
+    mut data: BatchedSwapsData,
+) -> (Vec<(String, Vec<SwapResponse>)>, FieldReport) {
+    let mut report = FieldReport::default();
+    let swaps = pool_ids
+        .iter()
+        .enumerate()
+        .map(|(i, pool_id)| {
+            let swaps = data.remove(&format!("s{}", i)).map_or_else(Vec::new, |swaps| {
+                report.merge(&swaps.report);
+                swaps.items
+            });
+            (pool_id.clone(), swaps)
+        })
+        .collect();
+    (swaps, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_ids(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("0xPOOL{}", i)).collect()
+    }
+
+    fn swap_json(id: &str, pool_id: &str) -> Value {
+        json!({
+            "id": id,
+            "timestamp": "1700000000",
+            "transaction": { "id": "0xtx", "timestamp": "1700000000" },
+            "pool": { "id": pool_id },
+            "amount0": "-1000",
+            "amount1": "1000",
+        })
+    }
+
+    #[test]
+    fn test_batched_swaps_query_aliases_each_pool() {
+        let query = batched_swaps_query(3);
+
+        assert!(query.starts_with(
+            "query BatchedSwaps($timestamp: BigInt!, $p0: String!, $p1: String!, $p2: String!) {"
+        ));
+        for i in 0..3 {
+            assert!(query.contains(&format!("s{}: swaps(", i)));
+            assert!(
+                query.contains(&format!("where: {{ pool: $p{}, timestamp_gte: $timestamp }}", i))
+            );
+        }
+        assert!(!query.contains("s3:") && !query.contains("$p3"));
+        assert_eq!(query.matches('{').count(), query.matches('}').count());
+    }
+
+    #[test]
+    fn test_batched_swaps_query_limits_every_alias() {
+        let query = batched_swaps_query(4);
+        assert_eq!(query.matches("first: 1000").count(), 4);
+        assert!(RECENT_SWAPS.contains("first: 1000"));
+    }
+
+    #[test]
+    fn test_batched_swaps_variables_match_the_aliases() {
+        let since = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let variables = batched_swaps_variables(&pool_ids(2), since);
+
+        assert_eq!(
+            variables,
+            json!({ "timestamp": "1700000000", "p0": "0xpool0", "p1": "0xpool1" })
+        );
+    }
+
+    #[test]
+    fn test_unbatch_swaps_maps_aliases_back_to_pools() {
+        let pools = pool_ids(3);
+        let data: BatchedSwapsData = serde_json::from_value(json!({
+            "s0": [swap_json("0xa-0", "0xpool0"), swap_json("0xa-1", "0xpool0")],
+            "s1": [],
+            "s2": [swap_json("0xc-0", "0xpool2")],
+        }))
+        .unwrap();
+
+        let (swaps, report) = unbatch_swaps(&pools, data);
+
+        let ids: Vec<(&str, Vec<&str>)> = swaps
+            .iter()
+            .map(|(pool, swaps)| (pool.as_str(), swaps.iter().map(|s| s.id.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("0xPOOL0", vec!["0xa-0", "0xa-1"]),
+                ("0xPOOL1", vec![]),
+                ("0xPOOL2", vec!["0xc-0"]),
+            ]
+        );
+        assert_eq!(report.items, 3);
+    }
+
+    #[test]
+    fn test_unbatch_swaps_gives_missing_aliases_no_swaps() {
+        let data: BatchedSwapsData =
+            serde_json::from_value(json!({ "s1": [swap_json("0xb-0", "0xpool1")] })).unwrap();
+
+        let (swaps, _) = unbatch_swaps(&pool_ids(2), data);
+
+        assert_eq!(swaps[0].0, "0xPOOL0");
+        assert!(swaps[0].1.is_empty());
+        assert_eq!(swaps[1].1.len(), 1);
+    }
+}
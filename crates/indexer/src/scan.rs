@@ -0,0 +1,135 @@
+use alloy::primitives::{Address, U256};
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use stillwater_analytics::TickRange;
+use stillwater_db::{get_pool_by_id, insert_pool, insert_position};
+use stillwater_models::{BlockchainService, OnchainPosition, Pool, Position};
+use tracing::{info, warn};
+
+/// A position NFT the scan found but didn't import
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanSkip {
+    pub token_id: String,
+    pub reason: String,
+}
+
+/// Outcome of scanning a wallet's position NFTs
+#[derive(Debug, Default, Serialize)]
+pub struct ScanReport {
+    /// Distinct position NFTs ever received by the wallet
+    pub tokens_found: usize,
+    pub imported: usize,
+    /// Positions already stored under the same NFT id
+    pub already_known: usize,
+    /// Pools created from on-chain pool keys because no sync had seen them
+    pub pools_added: usize,
+    pub skipped: Vec<ScanSkip>,
+}
+
+/// Seed the database with a wallet's open positions, read from the v4 PositionManager
+///
+/// The PositionManager isn't ERC721Enumerable, so tokens are found through
+/// Transfer logs to the wallet from `from_block` (ideally the PositionManager's
+/// deployment block). Tokens sent on since and positions without liquidity are
+/// skipped. Positions are stored under their token id as `nft_id` with the
+/// time the wallet received them as `created_at`; rescanning is a no-op.
+pub async fn scan_wallet(
+    db_pool: &PgPool,
+    blockchain: &BlockchainService,
+    position_manager: Address,
+    owner: Address,
+    from_block: u64,
+) -> Result<ScanReport> {
+    let received =
+        blockchain.get_received_position_ids(position_manager, owner, from_block).await?;
+
+    // A token may come back to the wallet; the first receipt is the entry
+    let mut first_received: HashMap<U256, u64> = HashMap::new();
+    for (token_id, block) in received {
+        first_received.entry(token_id).and_modify(|b| *b = (*b).min(block)).or_insert(block);
+    }
+    let mut tokens: Vec<(U256, u64)> = first_received.into_iter().collect();
+    tokens.sort();
+
+    let mut report = ScanReport { tokens_found: tokens.len(), ..Default::default() };
+    for (token_id, block) in tokens {
+        let skip = |reason: String| ScanSkip { token_id: token_id.to_string(), reason };
+
+        if blockchain.get_position_owner(position_manager, token_id).await? != owner {
+            report.skipped.push(skip("transferred to another wallet".to_string()));
+            continue;
+        }
+        let onchain = blockchain.get_position(position_manager, token_id).await?;
+        if onchain.liquidity.is_zero() {
+            report.skipped.push(skip("no liquidity".to_string()));
+            continue;
+        }
+        if let Err(e) = TickRange::new(onchain.tick_lower, onchain.tick_upper) {
+            report.skipped.push(skip(e.to_string()));
+            continue;
+        }
+
+        let pool_id = format!("{:#x}", onchain.pool_id);
+        if get_pool_by_id(db_pool, &pool_id).await?.is_none() {
+            insert_pool(db_pool, &pool_from_key(blockchain, &onchain).await?).await?;
+            report.pools_added += 1;
+        }
+
+        let position = Position {
+            id: 0,
+            nft_id: token_id.to_string(),
+            owner: owner.into(),
+            pool_id,
+            tick_lower: onchain.tick_lower,
+            tick_upper: onchain.tick_upper,
+            liquidity: onchain.liquidity,
+            created_at: blockchain.get_block_timestamp(block).await?,
+            manual: false,
+            closed_at: None,
+            archived_at: None,
+        };
+        if insert_position(db_pool, &position).await? {
+            report.imported += 1;
+        } else {
+            report.already_known += 1;
+        }
+    }
+
+    info!(
+        "Scanned {:#x}: {} tokens, {} imported, {} known, {} skipped",
+        owner,
+        report.tokens_found,
+        report.imported,
+        report.already_known,
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// ERC20 decimals of a token read on-chain, assuming 18 when they can't be read
+pub(crate) async fn token_decimals(blockchain: &BlockchainService, token: Address) -> i16 {
+    blockchain.get_token_decimals(token).await.map(i16::from).unwrap_or_else(|e| {
+        warn!("Failed to read decimals of {:#x}, assuming 18: {}", token, e);
+        18
+    })
+}
+
+/// Pool row from a position's pool key, with token decimals read on-chain
+async fn pool_from_key(blockchain: &BlockchainService, onchain: &OnchainPosition) -> Result<Pool> {
+    Ok(Pool {
+        pool_id: format!("{:#x}", onchain.pool_id),
+        token0: format!("{:#x}", onchain.currency0),
+        token1: format!("{:#x}", onchain.currency1),
+        token0_decimals: token_decimals(blockchain, onchain.currency0).await,
+        token1_decimals: token_decimals(blockchain, onchain.currency1).await,
+        fee_tier: onchain.fee as i32,
+        tick_spacing: onchain.tick_spacing,
+        hooks: format!("{:#x}", onchain.hooks),
+        protocol_fee: 0, // Read from pool state by the sync binary
+        created_at: None,
+        created_at_block: None,
+        fee_override: None,
+    })
+}
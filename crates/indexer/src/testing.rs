@@ -0,0 +1,166 @@
+//! Fixtures shared by the crate's tests
+//!
+//! Database tests need a disposable Postgres with TimescaleDB in
+//! `TEST_DATABASE_URL` (e.g. the docker compose database) and skip without
+//! one. Each test migrates its own schema, so it never touches an instance's
+//! tables.
+
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+
+use crate::types::{PoolResponse, PositionResponse, TokenResponse};
+
+/// Token0 of the test pool
+pub(crate) const TOKEN0: &str = "0x1111111111111111111111111111111111111111";
+/// Token1 of the test pool
+pub(crate) const TOKEN1: &str = "0x2222222222222222222222222222222222222222";
+
+/// A runtime and a pool on a freshly migrated schema, or None without a test database
+pub(crate) fn test_database(schema: &str) -> Option<(Runtime, PgPool)> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping database round trip test");
+        return None;
+    };
+    let runtime = Runtime::new().unwrap();
+    let db_pool = runtime.block_on(async {
+        let db_pool = stillwater_db::connect(&url, 2, Some(schema)).await.unwrap();
+        sqlx::migrate!("../../migrations").run(&db_pool).await.unwrap();
+        db_pool
+    });
+    Some((runtime, db_pool))
+}
+
+/// `bytes` as 0x-prefixed lowercase hex
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// An 18-decimal token
+pub(crate) fn create_test_token(id: &str, symbol: &str) -> TokenResponse {
+    TokenResponse {
+        id: id.to_string(),
+        symbol: Some(symbol.to_string()),
+        decimals: Some("18".to_string()),
+    }
+}
+
+/// A 0.3% pool (tick spacing 60) of `TOKEN0` and `TOKEN1`
+pub(crate) fn create_test_pool(id: &str) -> PoolResponse {
+    PoolResponse {
+        id: id.to_string(),
+        token0: create_test_token(TOKEN0, "WETH"),
+        token1: create_test_token(TOKEN1, "USDC"),
+        fee: "3000".to_string(),
+        tick_spacing: "60".to_string(),
+        hooks: None,
+        liquidity: None,
+        created_at_timestamp: None,
+        created_at_block_number: None,
+    }
+}
+
+/// A liquidity event of `amount` across ticks -600..600 of a test pool
+pub(crate) fn create_test_position(pool_liquidity: Option<&str>, amount: &str) -> PositionResponse {
+    PositionResponse {
+        id: "0xevent".to_string(),
+        owner: "0xowner".to_string(),
+        pool: PoolResponse {
+            liquidity: pool_liquidity.map(str::to_string),
+            ..create_test_pool("0xpool")
+        },
+        tick_lower: "-600".to_string(),
+        tick_upper: "600".to_string(),
+        amount: amount.to_string(),
+        timestamp: "1700000000".to_string(),
+        transaction: None,
+    }
+}
+
+/// A subgraph swap with every field set
+pub(crate) fn swap_json() -> Value {
+    json!({
+        "id": "0xswap-0",
+        "timestamp": "1700000100",
+        "transaction": { "id": "0xtx", "timestamp": "1700000000" },
+        "pool": { "id": "0xpool" },
+        "amount0": "-1000",
+        "amount1": 1000,
+    })
+}
+
+/// A GraphQL request a fake subgraph received
+#[derive(Debug, Clone)]
+pub(crate) struct SubgraphRequest {
+    /// The `Authorization` header, if any
+    pub authorization: Option<String>,
+    pub body: Value,
+}
+
+/// Requests a fake subgraph received, oldest first
+pub(crate) type SubgraphRequests = Arc<Mutex<Vec<SubgraphRequest>>>;
+
+/// Start a subgraph stub, returning its URL and the requests it receives
+///
+/// Each request body is answered with `respond`'s HTTP status and JSON.
+pub(crate) async fn fake_subgraph<F>(respond: F) -> (String, SubgraphRequests)
+where
+    F: Fn(&Value) -> (u16, Value) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = SubgraphRequests::default();
+    let respond = Arc::new(respond);
+    let received = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, respond.clone(), received.clone()));
+        }
+    });
+    (url, requests)
+}
+
+/// Answer each request on a keep-alive connection
+async fn serve<F>(stream: TcpStream, respond: Arc<F>, requests: SubgraphRequests)
+where
+    F: Fn(&Value) -> (u16, Value),
+{
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut content_length = 0;
+        let mut authorization = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            if line == "\r\n" {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                } else if name.eq_ignore_ascii_case("authorization") {
+                    authorization = Some(value.trim().to_string());
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await.unwrap();
+
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let (status, response) = respond(&body);
+        requests.lock().unwrap().push(SubgraphRequest { authorization, body });
+        let response = response.to_string();
+        let reply = format!(
+            "HTTP/1.1 {} Stub\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        );
+        reader.get_mut().write_all(reply.as_bytes()).await.unwrap();
+    }
+}
@@ -1,4 +1,9 @@
+use alloy::primitives::{Address, I256};
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeOwned, Deserializer};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use stillwater_models::{FieldReport, LiquidityChange};
 
 /// GraphQL response wrapper
 #[derive(Debug, Deserialize)]
@@ -13,19 +18,101 @@ pub struct GraphQLError {
     pub message: String,
 }
 
+/// Optional fields a subgraph response item can lack
+pub trait OptionalFields {
+    /// Paths (e.g. `transaction.id`) of the optional fields this item lacks
+    fn missing_fields(&self, missing: &mut Vec<&'static str>);
+}
+
+/// A list from a subgraph response, keeping the items that parse
+///
+/// An item another subgraph version shaped differently is skipped (and
+/// counted in `report`) instead of failing the whole batch.
+#[derive(Debug)]
+pub struct Tolerant<T> {
+    pub items: Vec<T>,
+    pub report: FieldReport,
+}
+
+impl<T: OptionalFields> Tolerant<T> {
+    /// Items that already parsed, e.g. through a custom query's mapper
+    pub fn from_items(items: Vec<T>) -> Self {
+        let mut report = FieldReport { items: items.len(), ..FieldReport::default() };
+        let mut missing = Vec::new();
+        for item in &items {
+            missing.clear();
+            item.missing_fields(&mut missing);
+            for field in &missing {
+                *report.missing.entry(field.to_string()).or_default() += 1;
+            }
+        }
+        Self { items, report }
+    }
+}
+
+impl<'de, T: DeserializeOwned + OptionalFields> Deserialize<'de> for Tolerant<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+        let count = values.len();
+        let mut items = Vec::with_capacity(count);
+        let mut dropped = Vec::new();
+        for value in values {
+            match serde_json::from_value(value) {
+                Ok(item) => items.push(item),
+                Err(e) => dropped.push(e.to_string()),
+            }
+        }
+
+        let mut tolerant = Self::from_items(items);
+        tolerant.report.items = count;
+        for reason in dropped {
+            *tolerant.report.dropped.entry(reason).or_default() += 1;
+        }
+        Ok(tolerant)
+    }
+}
+
+/// A BigInt or Int field some subgraph versions send as a string and others as a number
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(serde_json::Number),
+    }
+
+    Ok(match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) => s,
+        StringOrNumber::Number(n) => n.to_string(),
+    })
+}
+
+/// `string_or_number` for optional fields (use with `#[serde(default)]`)
+fn optional_string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    struct Field(#[serde(deserialize_with = "string_or_number")] String);
+
+    Ok(Option::<Field>::deserialize(deserializer)?.map(|field| field.0))
+}
+
 /// Response data for positions query (v4: modifyLiquidities)
 #[derive(Debug, Deserialize)]
 pub struct PositionsData {
     #[serde(rename = "modifyLiquidities")]
-    pub positions: Vec<PositionResponse>,
+    pub positions: Tolerant<PositionResponse>,
 }
 
 /// Response data for swaps query
 #[derive(Debug, Deserialize)]
 pub struct SwapsData {
-    pub swaps: Vec<SwapResponse>,
+    pub swaps: Tolerant<SwapResponse>,
 }
 
+/// Response data for a batched swaps query: swaps keyed by alias (`s0`, `s1`, ...)
+pub type BatchedSwapsData = HashMap<String, Tolerant<SwapResponse>>;
+
 /// Position from The Graph (v4: ModifyLiquidity event)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PositionResponse {
@@ -34,15 +121,47 @@ pub struct PositionResponse {
     #[serde(rename = "origin")]
     pub owner: String,
     pub pool: PoolResponse,
-    #[serde(rename = "tickLower")]
+    #[serde(rename = "tickLower", deserialize_with = "string_or_number")]
     pub tick_lower: String,
-    #[serde(rename = "tickUpper")]
+    #[serde(rename = "tickUpper", deserialize_with = "string_or_number")]
     pub tick_upper: String,
-    /// In v4, this is the amount field (liquidity delta)
-    #[serde(rename = "amount")]
-    pub liquidity: String,
+    /// In v4, this is the signed liquidity delta: positive adds, negative removes
+    #[serde(deserialize_with = "string_or_number")]
+    pub amount: String,
     /// In v4, timestamp is a direct field
+    #[serde(deserialize_with = "string_or_number")]
     pub timestamp: String,
+    /// Transaction that emitted the event (absent from custom queries that don't select it)
+    #[serde(default)]
+    pub transaction: Option<TransactionIdResponse>,
+}
+
+impl PositionResponse {
+    /// The signed liquidity delta (`amount`), or None if it isn't an integer
+    pub fn liquidity_delta(&self) -> Option<I256> {
+        self.amount.parse::<I256>().ok()
+    }
+
+    /// Whether the event added or removed liquidity, None if it moved none or isn't parseable
+    pub fn change(&self) -> Option<LiquidityChange> {
+        self.liquidity_delta().and_then(LiquidityChange::of)
+    }
+
+    /// Hash of the transaction that emitted the event, if the subgraph reported it
+    pub fn tx_hash(&self) -> Option<&str> {
+        self.transaction.as_ref()?.id.as_deref()
+    }
+}
+
+impl OptionalFields for PositionResponse {
+    fn missing_fields(&self, missing: &mut Vec<&'static str>) {
+        match &self.transaction {
+            None => missing.push("transaction"),
+            Some(transaction) if transaction.id.is_none() => missing.push("transaction.id"),
+            Some(_) => {}
+        }
+        self.pool.missing_fields(missing);
+    }
 }
 
 /// Pool information from The Graph
@@ -52,16 +171,74 @@ pub struct PoolResponse {
     pub token0: TokenResponse,
     pub token1: TokenResponse,
     /// In v4, this is feeTier instead of fee
-    #[serde(rename = "feeTier")]
+    #[serde(rename = "feeTier", alias = "fee", deserialize_with = "string_or_number")]
     pub fee: String,
-    #[serde(rename = "tickSpacing")]
+    #[serde(rename = "tickSpacing", deserialize_with = "string_or_number")]
     pub tick_spacing: String,
+    /// Hook contract address (zero address if none)
+    #[serde(default)]
+    pub hooks: Option<String>,
+    /// Current in-range liquidity (not exposed by every subgraph version)
+    #[serde(default)]
+    pub liquidity: Option<String>,
+    /// Unix timestamp of the block that created the pool
+    #[serde(
+        rename = "createdAtTimestamp",
+        default,
+        deserialize_with = "optional_string_or_number"
+    )]
+    pub created_at_timestamp: Option<String>,
+    #[serde(
+        rename = "createdAtBlockNumber",
+        default,
+        deserialize_with = "optional_string_or_number"
+    )]
+    pub created_at_block_number: Option<String>,
+}
+
+impl PoolResponse {
+    /// Creation time from the block timestamp, None if missing or malformed
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        let seconds = self.created_at_timestamp.as_deref()?.parse::<i64>().ok()?;
+        DateTime::from_timestamp(seconds, 0)
+    }
+
+    /// Creation block number, None if missing or malformed
+    pub fn created_at_block(&self) -> Option<i64> {
+        self.created_at_block_number.as_deref()?.parse().ok()
+    }
+}
+
+impl OptionalFields for PoolResponse {
+    fn missing_fields(&self, missing: &mut Vec<&'static str>) {
+        let fields = [
+            ("pool.hooks", self.hooks.is_none()),
+            ("pool.liquidity", self.liquidity.is_none()),
+            ("pool.createdAtTimestamp", self.created_at_timestamp.is_none()),
+            ("pool.createdAtBlockNumber", self.created_at_block_number.is_none()),
+            ("pool.token0.decimals", self.token0.decimals.is_none()),
+            ("pool.token1.decimals", self.token1.decimals.is_none()),
+        ];
+        missing.extend(fields.into_iter().filter(|(_, lacking)| *lacking).map(|(field, _)| field));
+    }
 }
 
 /// Token information from The Graph
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenResponse {
     pub id: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Token decimals as a BigInt string
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    pub decimals: Option<String>,
+}
+
+impl TokenResponse {
+    /// Token decimals, assuming 18 when the subgraph doesn't report them
+    pub fn decimals(&self) -> i16 {
+        self.decimals.as_deref().and_then(|d| d.parse().ok()).unwrap_or(18)
+    }
 }
 
 /// Transaction information from The Graph
@@ -69,21 +246,356 @@ pub struct TokenResponse {
 pub struct TransactionResponse {
     #[serde(default)]
     pub id: Option<String>,
-    pub timestamp: String,
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    pub timestamp: Option<String>,
+}
+
+/// Transaction ID from The Graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionIdResponse {
+    /// None on subgraph versions that don't expose it
+    #[serde(default)]
+    pub id: Option<String>,
 }
 
 /// Swap from The Graph
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SwapResponse {
     pub id: String,
-    pub transaction: TransactionResponse,
+    #[serde(default)]
+    pub transaction: Option<TransactionResponse>,
+    /// Block time of the swap (older subgraph versions only have the transaction's)
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    pub timestamp: Option<String>,
     pub pool: PoolIdResponse,
+    #[serde(deserialize_with = "string_or_number")]
     pub amount0: String,
+    #[serde(deserialize_with = "string_or_number")]
     pub amount1: String,
 }
 
+impl SwapResponse {
+    /// Unix timestamp of the swap, from the swap or else its transaction
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp
+            .as_deref()
+            .or_else(|| self.transaction.as_ref()?.timestamp.as_deref())
+    }
+
+    /// Hash of the swap's transaction, if the subgraph reported it
+    pub fn tx_hash(&self) -> Option<&str> {
+        self.transaction.as_ref()?.id.as_deref()
+    }
+}
+
+impl OptionalFields for SwapResponse {
+    fn missing_fields(&self, missing: &mut Vec<&'static str>) {
+        if self.tx_hash().is_none() {
+            missing.push("transaction.id");
+        }
+        if self.timestamp().is_none() {
+            missing.push("timestamp");
+        }
+    }
+}
+
+/// Response data for transfers query
+#[derive(Debug, Deserialize)]
+pub struct TransfersData {
+    pub transfers: Tolerant<TransferResponse>,
+}
+
+/// PositionManager NFT transfer from The Graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferResponse {
+    pub id: String,
+    /// Position NFT token id
+    #[serde(rename = "tokenId", deserialize_with = "string_or_number")]
+    pub token_id: String,
+    pub from: String,
+    pub to: String,
+    #[serde(deserialize_with = "string_or_number")]
+    pub timestamp: String,
+    #[serde(default)]
+    pub transaction: Option<TransactionIdResponse>,
+}
+
+impl TransferResponse {
+    /// Whether the transfer mints or burns the NFT rather than changing its owner
+    pub fn is_mint_or_burn(&self) -> bool {
+        let is_zero = |address: &str| address.parse::<Address>().is_ok_and(|a| a.is_zero());
+        is_zero(&self.from) || is_zero(&self.to)
+    }
+}
+
+impl OptionalFields for TransferResponse {
+    fn missing_fields(&self, missing: &mut Vec<&'static str>) {
+        match &self.transaction {
+            None => missing.push("transaction"),
+            Some(transaction) if transaction.id.is_none() => missing.push("transaction.id"),
+            Some(_) => {}
+        }
+    }
+}
+
 /// Simple pool ID response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolIdResponse {
     pub id: String,
 }
+
+/// Response data for the `_meta` query
+#[derive(Debug, Deserialize)]
+pub struct MetaData {
+    #[serde(rename = "_meta")]
+    pub meta: MetaResponse,
+}
+
+/// Subgraph indexing status
+#[derive(Debug, Deserialize)]
+pub struct MetaResponse {
+    pub block: MetaBlockResponse,
+}
+
+/// Latest indexed block
+#[derive(Debug, Deserialize)]
+pub struct MetaBlockResponse {
+    pub number: u64,
+    /// Block hash, None on subgraphs that don't report it
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// Unix timestamp of the block, None on subgraphs that don't report it
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+}
+
+impl MetaBlockResponse {
+    /// Block time, None if missing or malformed
+    pub fn block_time(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.timestamp?, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::swap_json;
+    use serde_json::{Value, json};
+
+    fn position_json(amount: Value) -> Value {
+        json!({
+            "id": "0xevent-1",
+            "origin": "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "pool": {
+                "id": "0xpool",
+                "token0": { "id": "0xaaa", "symbol": "WETH", "decimals": "18" },
+                "token1": { "id": "0xbbb", "symbol": "USDC", "decimals": "6" },
+                "feeTier": "3000",
+                "tickSpacing": "60",
+            },
+            "tickLower": "-600",
+            "tickUpper": "600",
+            "amount": amount,
+            "timestamp": "1700000000",
+        })
+    }
+
+    fn position(amount: Value) -> PositionResponse {
+        serde_json::from_value(position_json(amount)).unwrap()
+    }
+
+    #[test]
+    fn test_signed_amounts_parse_from_strings_and_numbers() {
+        let removal = position(json!("-123456789012345678901234567890"));
+        assert_eq!(
+            removal.liquidity_delta().unwrap().to_string(),
+            "-123456789012345678901234567890"
+        );
+        assert_eq!(removal.change(), Some(LiquidityChange::Remove));
+
+        let addition = position(json!(5000));
+        assert_eq!(addition.liquidity_delta(), Some(I256::try_from(5000).unwrap()));
+        assert_eq!(addition.change(), Some(LiquidityChange::Add));
+
+        assert_eq!(position(json!(-5000)).change(), Some(LiquidityChange::Remove));
+        assert_eq!(position(json!(I256::MIN.to_string())).liquidity_delta(), Some(I256::MIN));
+    }
+
+    #[test]
+    fn test_zero_amounts_move_no_liquidity() {
+        for amount in [json!("0"), json!(0), json!("-0")] {
+            let zero = position(amount);
+            assert_eq!(zero.liquidity_delta(), Some(I256::ZERO));
+            assert_eq!(zero.change(), None);
+        }
+    }
+
+    #[test]
+    fn test_malformed_amounts_have_no_delta() {
+        for amount in ["1.5", "twelve", "1e18"] {
+            let malformed = position(json!(amount));
+            assert_eq!(malformed.liquidity_delta(), None, "{:?} parsed", amount);
+            assert_eq!(malformed.change(), None);
+        }
+    }
+
+    fn pool_json() -> Value {
+        json!({
+            "id": "0xpool",
+            "token0": { "id": "0xaaa", "symbol": "WETH", "decimals": "18" },
+            "token1": { "id": "0xbbb", "symbol": "USDC", "decimals": 6 },
+            "feeTier": "3000",
+            "tickSpacing": 60,
+            "hooks": "0x0000000000000000000000000000000000000000",
+            "liquidity": "1000",
+            "createdAtTimestamp": "1700000000",
+            "createdAtBlockNumber": 123,
+        })
+    }
+
+    /// `value` with the field at `path` removed, or set to null
+    fn without(mut value: Value, path: &[&str], null: bool) -> Value {
+        let (field, parents) = path.split_last().unwrap();
+        let parent = parents.iter().fold(&mut value, |v, key| &mut v[*key]);
+        if null {
+            parent[*field] = Value::Null;
+        } else {
+            parent.as_object_mut().unwrap().remove(*field);
+        }
+        value
+    }
+
+    fn report_of<T: OptionalFields>(item: T) -> FieldReport {
+        Tolerant::from_items(vec![item]).report
+    }
+
+    #[test]
+    fn test_complete_items_report_nothing_missing() {
+        let pool: PoolResponse = serde_json::from_value(pool_json()).unwrap();
+        assert!(report_of(pool).is_complete());
+        let swap: SwapResponse = serde_json::from_value(swap_json()).unwrap();
+        assert!(report_of(swap).is_complete());
+    }
+
+    #[test]
+    fn test_missing_pool_fields_fall_back() {
+        for null in [false, true] {
+            for field in ["hooks", "liquidity", "createdAtTimestamp", "createdAtBlockNumber"] {
+                let json = without(pool_json(), &[field], null);
+                let pool: PoolResponse = serde_json::from_value(json).unwrap();
+                let report = report_of(pool.clone());
+                assert_eq!(report.missing.get(&format!("pool.{}", field)), Some(&1), "{}", field);
+
+                match field {
+                    "hooks" => assert_eq!(pool.hooks, None),
+                    "liquidity" => assert_eq!(pool.liquidity, None),
+                    "createdAtTimestamp" => assert_eq!(pool.created_at(), None),
+                    _ => assert_eq!(pool.created_at_block(), None),
+                }
+            }
+        }
+
+        let pool: PoolResponse = serde_json::from_value(pool_json()).unwrap();
+        assert_eq!(pool.created_at(), DateTime::from_timestamp(1_700_000_000, 0));
+        assert_eq!(pool.created_at_block(), Some(123));
+    }
+
+    #[test]
+    fn test_missing_token_decimals_assume_eighteen() {
+        for null in [false, true] {
+            let json = without(pool_json(), &["token1", "decimals"], null);
+            let pool: PoolResponse = serde_json::from_value(json).unwrap();
+            assert_eq!(pool.token1.decimals(), 18);
+            assert_eq!(pool.token0.decimals(), 18);
+            assert_eq!(report_of(pool).missing.get("pool.token1.decimals"), Some(&1));
+
+            let json = without(pool_json(), &["token0", "symbol"], null);
+            let pool: PoolResponse = serde_json::from_value(json).unwrap();
+            assert_eq!(pool.token0.symbol, None);
+        }
+
+        let pool: PoolResponse = serde_json::from_value(pool_json()).unwrap();
+        assert_eq!(pool.token1.decimals(), 6);
+    }
+
+    #[test]
+    fn test_old_fee_field_name_is_accepted() {
+        let mut json = without(pool_json(), &["feeTier"], false);
+        json["fee"] = json!(500);
+        let pool: PoolResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(pool.fee, "500");
+    }
+
+    #[test]
+    fn test_swap_timestamp_falls_back_to_its_transaction() {
+        for null in [false, true] {
+            let swap: SwapResponse =
+                serde_json::from_value(without(swap_json(), &["timestamp"], null)).unwrap();
+            assert_eq!(swap.timestamp(), Some("1700000000"));
+            assert!(report_of(swap).is_complete());
+
+            let json = without(without(swap_json(), &["timestamp"], null), &["transaction"], null);
+            let swap: SwapResponse = serde_json::from_value(json).unwrap();
+            assert_eq!((swap.timestamp(), swap.tx_hash()), (None, None));
+            let report = report_of(swap);
+            assert_eq!(report.missing.get("timestamp"), Some(&1));
+            assert_eq!(report.missing.get("transaction.id"), Some(&1));
+        }
+
+        let swap: SwapResponse = serde_json::from_value(swap_json()).unwrap();
+        assert_eq!(swap.timestamp(), Some("1700000100"));
+        assert_eq!(swap.amount1, "1000");
+    }
+
+    #[test]
+    fn test_missing_transactions_leave_no_tx_hash() {
+        for null in [false, true] {
+            let json = without(position_json(json!("5")), &["transaction"], null);
+            let position: PositionResponse = serde_json::from_value(json).unwrap();
+            assert_eq!(position.tx_hash(), None);
+            assert_eq!(report_of(position).missing.get("transaction"), Some(&1));
+
+            let mut json = position_json(json!("5"));
+            json["transaction"] = json!({ "id": null });
+            let json = without(json, &["transaction", "id"], null);
+            let position: PositionResponse = serde_json::from_value(json).unwrap();
+            assert_eq!(position.tx_hash(), None);
+            assert_eq!(report_of(position).missing.get("transaction.id"), Some(&1));
+        }
+
+        let transfer = json!({
+            "id": "0xtransfer-0",
+            "tokenId": 7,
+            "from": "0x1111111111111111111111111111111111111111",
+            "to": "0x2222222222222222222222222222222222222222",
+            "timestamp": 1700000000,
+        });
+        let transfer: TransferResponse = serde_json::from_value(transfer).unwrap();
+        assert_eq!(transfer.token_id, "7");
+        assert_eq!(report_of(transfer).missing.get("transaction"), Some(&1));
+    }
+
+    #[test]
+    fn test_missing_block_metadata_is_none() {
+        let block: MetaBlockResponse =
+            serde_json::from_value(json!({ "number": 5, "hash": null })).unwrap();
+        assert_eq!((block.number, block.block_time()), (5, None));
+        assert_eq!(block.hash, None);
+    }
+
+    #[test]
+    fn test_unparseable_items_are_dropped_and_counted() {
+        let items = json!([
+            swap_json(),
+            without(swap_json(), &["amount0"], false),
+            without(swap_json(), &["transaction"], true),
+        ]);
+        let swaps: Tolerant<SwapResponse> = serde_json::from_value(items).unwrap();
+
+        assert_eq!(swaps.items.len(), 2);
+        assert_eq!(swaps.report.items, 3);
+        assert_eq!(swaps.report.dropped_count(), 1);
+        assert!(swaps.report.dropped.keys().all(|reason| reason.contains("amount0")));
+        assert_eq!(swaps.report.missing.get("transaction.id"), Some(&1));
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::address::Address;
+
+/// Address tracked by stillwater
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WatchedAddress {
+    pub address: Address,
+    pub source: String,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Token an owner wants to hear about new pools for
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WatchedToken {
+    pub owner: String,
+    pub token: String,
+    /// Sink names new pool notifications go to; empty sends to the configured sinks
+    pub sinks: Vec<String>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Token pair an owner wants to hear about new pools for, on any fee tier or hooks
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WatchedPair {
+    pub owner: String,
+    /// The pair's tokens in pool order (`token0 < token1`)
+    pub token0: String,
+    pub token1: String,
+    /// Chains the pair is watched on; empty watches every chain
+    pub chain_ids: Vec<i64>,
+    /// Sink names new pool notifications go to; empty sends to the configured sinks
+    pub sinks: Vec<String>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// API key record (the key itself is never stored)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    pub key_hash: String,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Token an owner wants a pool's prices quoted in
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct QuotePreference {
+    pub owner: String,
+    pub pool_id: String,
+    pub quote_token: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Telegram chat talking to the bot and the owner it follows
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TelegramChat {
+    pub chat_id: String,
+    pub owner: Address,
+    pub linked_at: DateTime<Utc>,
+}
+
+/// Longest account name
+pub const MAX_ACCOUNT_NAME_LEN: usize = 64;
+
+/// Several of a user's wallets grouped under one name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+    /// In address order
+    pub addresses: Vec<Address>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Account {
+    /// Check a name is 1-64 lowercase letters, digits, `-` or `_`
+    pub fn validate_name(name: &str) -> Result<(), String> {
+        let valid = !name.is_empty()
+            && name.len() <= MAX_ACCOUNT_NAME_LEN
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "account names are 1-{} lowercase letters, digits, '-' or '_'",
+                MAX_ACCOUNT_NAME_LEN
+            ))
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Running fee totals of a position, advanced as new swaps arrive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeAccumulator {
+    pub position_id: i64,
+    /// Highest swap ID folded in; later swaps are still to be added
+    pub last_swap_id: i64,
+    pub swap_count: i64,
+    pub volume: Decimal,
+    pub fees_earned: Decimal,
+    pub updated_at: DateTime<Utc>,
+    /// Last time a full recomputation confirmed the totals
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+impl FeeAccumulator {
+    /// An accumulator with no swaps folded in yet
+    pub fn empty(position_id: i64) -> Self {
+        Self {
+            position_id,
+            last_swap_id: 0,
+            swap_count: 0,
+            volume: Decimal::ZERO,
+            fees_earned: Decimal::ZERO,
+            updated_at: Utc::now(),
+            verified_at: None,
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+use std::fmt;
+use std::str::FromStr;
+
+/// An account or contract address
+///
+/// Stored lowercase (`canonical`), the form every table keys addresses by,
+/// and displayed and serialized with its EIP-55 checksum. Parsing accepts any
+/// all-lowercase or all-uppercase address, and a mixed-case one only when its
+/// checksum is right, so a typo in a checksummed address is caught rather
+/// than silently naming another account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Address(alloy::primitives::Address);
+
+/// Why a string isn't an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// Not `0x` followed by 40 hex digits
+    Malformed,
+    /// Mixed case that doesn't match the address's checksum
+    InvalidChecksum,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Malformed => write!(f, "not an address (0x and 40 hex digits)"),
+            AddressError::InvalidChecksum => write!(f, "address checksum doesn't match"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+impl Address {
+    pub const ZERO: Address = Address(alloy::primitives::Address::ZERO);
+
+    /// Parse an address, checking the EIP-55 checksum of mixed-case input
+    pub fn parse(s: &str) -> Result<Self, AddressError> {
+        Self::parse_for_chain(s, None)
+    }
+
+    /// Parse an address whose mixed-case form may carry an EIP-1191 checksum for `chain_id`
+    ///
+    /// EIP-1191 chains (e.g. RSK) mix the chain ID into the checksum; their
+    /// wallets' addresses fail the plain EIP-55 check. Either form is accepted.
+    pub fn parse_for_chain(s: &str, chain_id: Option<u64>) -> Result<Self, AddressError> {
+        let s = s.trim();
+        let hex = s.strip_prefix("0x").ok_or(AddressError::Malformed)?;
+        if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(AddressError::Malformed);
+        }
+        let address =
+            alloy::primitives::Address::from_str(hex).map_err(|_| AddressError::Malformed)?;
+
+        let mixed_case = hex.bytes().any(|b| b.is_ascii_lowercase())
+            && hex.bytes().any(|b| b.is_ascii_uppercase());
+        if mixed_case
+            && s != address.to_checksum(None)
+            && chain_id.is_none_or(|chain_id| s != address.to_checksum(Some(chain_id)))
+        {
+            return Err(AddressError::InvalidChecksum);
+        }
+        Ok(Address(address))
+    }
+
+    /// Lowercase `0x` hex, the form addresses are stored and compared in
+    pub fn canonical(&self) -> String {
+        format!("{:#x}", self.0)
+    }
+
+    /// Mixed-case checksummed form: EIP-55, or EIP-1191 for `chain_id`
+    pub fn checksummed(&self, chain_id: Option<u64>) -> String {
+        self.0.to_checksum(chain_id)
+    }
+
+    /// The wrapped alloy address, for contract calls
+    pub fn into_inner(self) -> alloy::primitives::Address {
+        self.0
+    }
+
+    /// An address of one repeated byte, for tests and examples
+    pub const fn repeat_byte(byte: u8) -> Self {
+        Address(alloy::primitives::Address::repeat_byte(byte))
+    }
+}
+
+/// Lowercase a string holding an address, leaving anything else as it is
+///
+/// For inputs that are usually but not always addresses (e.g. owner columns
+/// of pool syncs, which may be empty), so they match canonical storage.
+pub fn canonical_address(s: &str) -> String {
+    Address::parse(s).map_or_else(|_| s.trim().to_string(), |address| address.canonical())
+}
+
+impl From<alloy::primitives::Address> for Address {
+    fn from(address: alloy::primitives::Address) -> Self {
+        Address(address)
+    }
+}
+
+impl From<Address> for alloy::primitives::Address {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Address::parse(s)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.checksummed(None))
+    }
+}
+
+impl PartialEq<str> for Address {
+    /// Whether `other` names this address, in any case
+    fn eq(&self, other: &str) -> bool {
+        other.trim().eq_ignore_ascii_case(&self.canonical())
+    }
+}
+
+impl PartialEq<&str> for Address {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Address {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Address::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Type<Postgres> for Address {
+    fn type_info() -> PgTypeInfo {
+        <String as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <String as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl PgHasArrayType for Address {
+    fn array_type_info() -> PgTypeInfo {
+        <String as PgHasArrayType>::array_type_info()
+    }
+
+    fn array_compatible(ty: &PgTypeInfo) -> bool {
+        <String as PgHasArrayType>::array_compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for Address {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <String as Encode<Postgres>>::encode(self.canonical(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Address {
+    /// Rows written before addresses were normalized may be in any case
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        let hex = s.trim().strip_prefix("0x").ok_or(AddressError::Malformed)?;
+        let address =
+            alloy::primitives::Address::from_str(hex).map_err(|_| AddressError::Malformed)?;
+        Ok(Address(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// EIP-55 test vector
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    /// The same address with its EIP-1191 checksum for RSK mainnet (chain 30)
+    const RSK_CHECKSUMMED: &str = "0x5aaEB6053f3e94c9b9a09f33669435E7ef1bEAeD";
+    const RSK_CHAIN_ID: u64 = 30;
+
+    #[test]
+    fn test_parse_accepts_single_case() {
+        let lower = Address::parse(&CHECKSUMMED.to_lowercase()).unwrap();
+        let upper = Address::parse(&format!("0x{}", CHECKSUMMED[2..].to_uppercase())).unwrap();
+
+        assert_eq!(lower, upper);
+        assert_eq!(lower.canonical(), CHECKSUMMED.to_lowercase());
+        assert_eq!(lower.to_string(), CHECKSUMMED);
+    }
+
+    #[test]
+    fn test_parse_checks_mixed_case_checksum() {
+        let address = Address::parse(CHECKSUMMED).unwrap();
+        assert_eq!(address.checksummed(None), CHECKSUMMED);
+
+        // One letter's case flipped
+        let typo = CHECKSUMMED.replacen("aA", "Aa", 1);
+        assert_eq!(Address::parse(&typo), Err(AddressError::InvalidChecksum));
+    }
+
+    #[test]
+    fn test_parse_for_chain_accepts_eip1191_checksum() {
+        let address = Address::parse_for_chain(RSK_CHECKSUMMED, Some(RSK_CHAIN_ID)).unwrap();
+
+        assert_eq!(address, Address::parse(CHECKSUMMED).unwrap());
+        assert_eq!(address.checksummed(Some(RSK_CHAIN_ID)), RSK_CHECKSUMMED);
+        // The plain EIP-55 form is still accepted on the chain
+        assert!(Address::parse_for_chain(CHECKSUMMED, Some(RSK_CHAIN_ID)).is_ok());
+        // Without the chain, or for another one, the RSK checksum is wrong
+        assert_eq!(Address::parse(RSK_CHECKSUMMED), Err(AddressError::InvalidChecksum));
+        assert_eq!(
+            Address::parse_for_chain(RSK_CHECKSUMMED, Some(31)),
+            Err(AddressError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_input() {
+        let hex = &CHECKSUMMED[2..];
+        for malformed in [
+            "",
+            "0x",
+            hex,
+            &format!("0X{}", hex),
+            &CHECKSUMMED[..41],
+            &format!("{}0", CHECKSUMMED),
+            &format!("{}g", &CHECKSUMMED[..41]),
+            // 40 bytes after 0x, but not 40 hex digits
+            &format!("{}€", &CHECKSUMMED[..39]),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA d",
+        ] {
+            assert_eq!(
+                Address::parse(malformed),
+                Err(AddressError::Malformed),
+                "{:?} should be malformed",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_trims_whitespace() {
+        let address = Address::parse(&format!("  {}\n", CHECKSUMMED)).unwrap();
+        assert_eq!(address.to_string(), CHECKSUMMED);
+    }
+}
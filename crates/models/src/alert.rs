@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::Position;
+
+/// Severity of an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// An alert to be delivered to one or more sinks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    /// Stable key identifying this alert occurrence (e.g. "health:42:critical:2025-01-01").
+    /// Alerts with the same key are only ever delivered once per sink.
+    pub key: String,
+    pub severity: AlertSeverity,
+    pub title: String,
+    pub message: String,
+    pub position_id: Option<i64>,
+    pub owner: Option<String>,
+    pub pool_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What an alert is about, selecting which of an owner's templates renders it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// A position's pool price moved outside its range
+    RangeExited,
+    /// A position's pool price moved back into its range
+    RangeEntered,
+    /// Unclaimed fees are worth compounding
+    Compound,
+    /// A position's recent fee accrual rate fell well below its baseline
+    FeeVelocityDrop,
+    /// A single swap in a position's pool was large or moved the price far
+    LargeSwap,
+    /// A position's pool changed its LP or protocol fee
+    PoolParamChange,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 6] = [
+        AlertKind::RangeExited,
+        AlertKind::RangeEntered,
+        AlertKind::Compound,
+        AlertKind::FeeVelocityDrop,
+        AlertKind::LargeSwap,
+        AlertKind::PoolParamChange,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::RangeExited => "range_exited",
+            AlertKind::RangeEntered => "range_entered",
+            AlertKind::Compound => "compound",
+            AlertKind::FeeVelocityDrop => "fee_velocity_drop",
+            AlertKind::LargeSwap => "large_swap",
+            AlertKind::PoolParamChange => "pool_param_change",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "range_exited" => Some(AlertKind::RangeExited),
+            "range_entered" => Some(AlertKind::RangeEntered),
+            "compound" => Some(AlertKind::Compound),
+            "fee_velocity_drop" => Some(AlertKind::FeeVelocityDrop),
+            "large_swap" => Some(AlertKind::LargeSwap),
+            "pool_param_change" => Some(AlertKind::PoolParamChange),
+            _ => None,
+        }
+    }
+}
+
+/// An owner's replacement title and message for one kind of alert
+///
+/// Both are minijinja templates rendered against the alert's variables
+/// (`position`, `pnl`, `distance_to_edge`, ...). Without a title template the
+/// default title is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertTemplate {
+    pub owner: String,
+    pub kind: AlertKind,
+    pub title: Option<String>,
+    pub body: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Decides which alerts of one kind fire, about whose positions, and where they go
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: i64,
+    #[serde(flatten)]
+    pub spec: AlertRuleSpec,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The editable part of an alert rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRuleSpec {
+    /// Only this owner's positions (None: every alerted owner's, i.e. watched
+    /// owners and owners named by other rules)
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Only positions in this pool
+    #[serde(default)]
+    pub pool_id: Option<String>,
+    pub condition: AlertKind,
+    /// For `compound`, the multiple of the compounding gas unclaimed fees must
+    /// reach (None: `COMPOUND_GAS_MULTIPLE`); for `fee_velocity_drop`, the
+    /// fraction (0-1) the fee rate must fall by (None: `FEE_VELOCITY_DROP`);
+    /// for `large_swap`, the USD value a swap must reach (None: `LARGE_SWAP_USD`);
+    /// for `pool_param_change`, the fractional fee change that fires (None: any).
+    /// Range conditions take none.
+    #[serde(default)]
+    pub threshold: Option<Decimal>,
+    /// For `large_swap`, the ticks a swap must move the price by (None:
+    /// `LARGE_SWAP_TICKS`). Other conditions take none.
+    #[serde(default)]
+    pub tick_threshold: Option<i32>,
+    /// Sink names (`telegram:<chat_id>`, `webhook:<url>`); empty sends to the configured sinks
+    #[serde(default)]
+    pub sinks: Vec<String>,
+    /// Minimum time between the rule's alerts about the same position
+    #[serde(default)]
+    pub cooldown_seconds: i64,
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+impl AlertRuleSpec {
+    /// Check the rule's fields fit its condition, describing the first problem found
+    ///
+    /// Sink names are checked by the alerts crate, which knows the sink types.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cooldown_seconds < 0 {
+            return Err("cooldown_seconds must not be negative".to_string());
+        }
+        match (self.condition, self.tick_threshold) {
+            (AlertKind::LargeSwap, Some(ticks)) if ticks <= 0 => {
+                return Err("tick_threshold must be positive".to_string());
+            }
+            (AlertKind::LargeSwap, _) | (_, None) => {}
+            (_, Some(_)) => {
+                return Err(format!("{} rules take no tick_threshold", self.condition.as_str()));
+            }
+        }
+        match (self.condition, self.threshold) {
+            (
+                AlertKind::Compound | AlertKind::LargeSwap | AlertKind::PoolParamChange,
+                Some(threshold),
+            ) if threshold <= Decimal::ZERO =>
+            {
+                Err("threshold must be positive".to_string())
+            }
+            (AlertKind::FeeVelocityDrop, Some(threshold))
+                if threshold <= Decimal::ZERO || threshold >= Decimal::ONE =>
+            {
+                Err("threshold must be between 0 and 1".to_string())
+            }
+            (AlertKind::RangeExited | AlertKind::RangeEntered, Some(_)) => {
+                Err(format!("{} rules take no threshold", self.condition.as_str()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the rule covers a position
+    pub fn matches(&self, position: &Position) -> bool {
+        self.owner.as_ref().is_none_or(|o| position.owner == o.as_str())
+            && self.pool_id.as_ref().is_none_or(|p| p.eq_ignore_ascii_case(&position.pool_id))
+    }
+}
+
+/// Delivery state of a queued alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// An alert queued for delivery to a single sink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAlert {
+    pub id: i64,
+    /// Unique per (sink, alert key); also sent to sinks that support idempotent delivery
+    pub idempotency_key: String,
+    pub sink: String,
+    pub alert: Alert,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Spec the sink is rebuilt from (`telegram:<chat_id>` or `webhook:<url>`);
+    /// `None` for rows queued when `sink` itself was the spec
+    #[serde(skip_serializing)]
+    pub target: Option<String>,
+}
@@ -0,0 +1,172 @@
+use alloy::primitives::{Address, U256};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Decimals of the chain's native token (ETH), which v4 pools address as the zero address
+pub const NATIVE_DECIMALS: u8 = 18;
+
+/// Most fractional digits a Decimal holds
+const MAX_SCALE: u8 = 28;
+
+/// A raw token amount together with the token it's counted in and that token's decimals
+///
+/// Keeping the decimals next to the raw amount means whole tokens are only
+/// ever derived from the decimals of the token the amount belongs to, and
+/// arithmetic refuses to mix tokens or decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TokenAmount {
+    /// Amount in the token's smallest unit (e.g. wei)
+    #[serde(with = "crate::position::u256_serde")]
+    pub raw: U256,
+    pub decimals: u8,
+    pub token: Address,
+}
+
+/// Why two token amounts couldn't be combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountError {
+    /// The amounts are of different tokens
+    TokenMismatch { left: Address, right: Address },
+    /// The amounts are of the same token but disagree on its decimals
+    DecimalsMismatch { left: u8, right: u8 },
+    /// The result doesn't fit 256 bits
+    Overflow,
+    /// Subtracting more than the amount holds
+    Underflow,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::TokenMismatch { left, right } => {
+                write!(f, "amounts of different tokens: {} and {}", left, right)
+            }
+            AmountError::DecimalsMismatch { left, right } => {
+                write!(f, "amounts with different decimals: {} and {}", left, right)
+            }
+            AmountError::Overflow => write!(f, "token amount overflow"),
+            AmountError::Underflow => write!(f, "token amount underflow"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+impl TokenAmount {
+    pub fn new(raw: U256, decimals: u8, token: Address) -> Self {
+        Self { raw, decimals, token }
+    }
+
+    /// Nothing of `token`
+    pub fn zero(decimals: u8, token: Address) -> Self {
+        Self::new(U256::ZERO, decimals, token)
+    }
+
+    /// An amount of the native token, e.g. gas paid in wei
+    pub fn native(raw: U256) -> Self {
+        Self::new(raw, NATIVE_DECIMALS, Address::ZERO)
+    }
+
+    /// Whole tokens rounded down to the token's smallest unit, None if negative
+    ///
+    /// Digits past `decimals` places are truncated.
+    pub fn from_decimal(units: Decimal, decimals: u8, token: Address) -> Option<Self> {
+        if units.is_sign_negative() && !units.is_zero() {
+            return None;
+        }
+        let scale = u8::try_from(units.scale()).ok()?;
+        let mantissa = U256::from(units.mantissa().unsigned_abs());
+        let raw = if scale > decimals {
+            mantissa / power_of_ten(scale - decimals)?
+        } else {
+            mantissa.checked_mul(power_of_ten(decimals - scale)?)?
+        };
+        Some(Self::new(raw, decimals, token))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.raw.is_zero()
+    }
+
+    /// Amount in whole tokens
+    ///
+    /// Divides in 256-bit integers before converting, so amounts far beyond what
+    /// Decimal holds in raw units still convert exactly. Digits past 28 decimal
+    /// places are truncated; amounts too large in whole tokens saturate at
+    /// `Decimal::MAX`.
+    pub fn to_decimal(&self) -> Decimal {
+        // Fractional digits beyond Decimal's precision are dropped first
+        let dropped = self.decimals.saturating_sub(MAX_SCALE);
+        let Some(amount) = power_of_ten(dropped).map(|p| self.raw / p) else {
+            return Decimal::ZERO;
+        };
+        let scale = self.decimals - dropped;
+
+        let (whole, fraction) = amount.div_rem(power_of_ten(scale).unwrap_or(U256::from(1u8)));
+        let Some(whole) = decimal_from(whole, 0) else {
+            return Decimal::MAX;
+        };
+        let fraction = decimal_from(fraction, scale).unwrap_or(Decimal::ZERO);
+        whole.checked_add(fraction).unwrap_or(whole)
+    }
+
+    /// Raw amount as a Decimal, saturating at `Decimal::MAX`
+    pub fn raw_decimal(&self) -> Decimal {
+        decimal_from(self.raw, 0).unwrap_or(Decimal::MAX)
+    }
+
+    pub fn checked_add(&self, other: &TokenAmount) -> Result<TokenAmount, AmountError> {
+        self.same_token(other)?;
+        let raw = self.raw.checked_add(other.raw).ok_or(AmountError::Overflow)?;
+        Ok(Self { raw, ..*self })
+    }
+
+    pub fn checked_sub(&self, other: &TokenAmount) -> Result<TokenAmount, AmountError> {
+        self.same_token(other)?;
+        let raw = self.raw.checked_sub(other.raw).ok_or(AmountError::Underflow)?;
+        Ok(Self { raw, ..*self })
+    }
+
+    /// Amount scaled by `numerator / denominator`, rounded down; None on a zero denominator
+    pub fn mul_div(&self, numerator: U256, denominator: U256) -> Option<TokenAmount> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let raw = self.raw.checked_mul(numerator)? / denominator;
+        Some(Self { raw, ..*self })
+    }
+
+    fn same_token(&self, other: &TokenAmount) -> Result<(), AmountError> {
+        if self.token != other.token {
+            return Err(AmountError::TokenMismatch { left: self.token, right: other.token });
+        }
+        if self.decimals != other.decimals {
+            return Err(AmountError::DecimalsMismatch {
+                left: self.decimals,
+                right: other.decimals,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Whole tokens without trailing zeros, e.g. `1.5`
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = self.to_decimal().normalize();
+        match f.precision() {
+            Some(places) => write!(f, "{:.*}", places, units),
+            None => write!(f, "{}", units),
+        }
+    }
+}
+
+fn power_of_ten(exponent: u8) -> Option<U256> {
+    U256::from(10u8).checked_pow(U256::from(exponent))
+}
+
+fn decimal_from(value: U256, scale: u8) -> Option<Decimal> {
+    let value = i128::try_from(value).ok()?;
+    Decimal::try_from_i128_with_scale(value, scale.into()).ok()
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// A stored backtest run, without its equity curve (see `backtests`)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BacktestRun {
+    pub id: i64,
+    pub pool_id: String,
+    pub label: Option<String>,
+    /// Settings the run used
+    pub params: serde_json::Value,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Headline figures of the run
+    pub metrics: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
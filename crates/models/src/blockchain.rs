@@ -1,6 +1,65 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, B256, I256, U256, keccak256};
 use alloy::providers::{Provider, ProviderBuilder, RootProvider};
+use alloy::rpc::types::{BlockTransactionsKind, Filter};
+use alloy::sol_types::{SolEvent, SolValue};
 use alloy::transports::http::{Client, Http};
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use crate::contracts::{
+    IERC20MetadataInstance, IERC20MinimalInstance, IPoolManager, IPositionManager,
+    IPositionManagerInstance, IStateViewInstance,
+};
+use crate::gas::{GasPriceSample, TransactionFees};
+use crate::pool::{PoolFeeGrowth, PoolInitialization};
+
+/// Blocks per `eth_getLogs` request when scanning for position NFTs or pool creations
+pub const LOG_SCAN_CHUNK_BLOCKS: u64 = 10_000;
+
+/// A position NFT as read from the v4 PositionManager
+#[derive(Debug, Clone)]
+pub struct OnchainPosition {
+    pub token_id: U256,
+    /// keccak256 of the ABI-encoded pool key
+    pub pool_id: B256,
+    pub currency0: Address,
+    pub currency1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub hooks: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: U256,
+}
+
+/// A swap as logged by the v4 PoolManager
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnchainSwap {
+    pub pool_id: String,
+    pub tx_hash: Option<String>,
+    pub block_number: u64,
+    pub log_index: u64,
+    /// Token0 into the pool (negative: out of it), raw; the log's swapper delta negated
+    pub amount0: I256,
+    /// Token1 into the pool (negative: out of it), raw
+    pub amount1: I256,
+    /// Pool tick after the swap
+    pub tick: i32,
+    /// LP fee charged, in hundredths of a bip
+    pub fee: u32,
+}
+
+/// Ticks packed in a PositionInfo: tickLower in bits 8-31, tickUpper in bits 32-55
+pub fn unpack_position_ticks(info: U256) -> (i32, i32) {
+    let int24 = |shift: usize| {
+        let raw = ((info >> shift) & U256::from(0xff_ffffu32)).to::<u32>();
+        ((raw << 8) as i32) >> 8
+    };
+    (int24(8), int24(32))
+}
 
 /// Blockchain service for interacting with Ethereum and Uniswap v4
 pub struct BlockchainService {
@@ -21,11 +80,308 @@ impl BlockchainService {
         &self.provider
     }
 
+    /// ID of the chain the RPC serves
+    pub async fn get_chain_id(&self) -> Result<u64> {
+        let chain_id = self.provider.get_chain_id().await?;
+        Ok(chain_id)
+    }
+
     /// Get the current block number
     pub async fn get_block_number(&self) -> Result<u64> {
         let block_number = self.provider.get_block_number().await?;
         Ok(block_number)
     }
+
+    /// Current tick of a v4 pool, read from the StateView contract at `state_view`
+    pub async fn get_pool_tick(&self, state_view: Address, pool_id: B256) -> Result<i32> {
+        let slot0 = IStateViewInstance::new(state_view, &self.provider)
+            .getSlot0(pool_id)
+            .call()
+            .await?;
+        Ok(slot0.tick.as_i32())
+    }
+
+    /// LP fee and packed protocol fee of a v4 pool, read from the StateView contract
+    pub async fn get_pool_fees(&self, state_view: Address, pool_id: B256) -> Result<(i32, i32)> {
+        let slot0 = IStateViewInstance::new(state_view, &self.provider)
+            .getSlot0(pool_id)
+            .call()
+            .await?;
+        Ok((slot0.lpFee.to::<i32>(), slot0.protocolFee.to::<i32>()))
+    }
+
+    /// Fee growth globals and tick of a v4 pool now, read from the StateView contract
+    pub async fn get_pool_fee_growth(
+        &self,
+        state_view: Address,
+        pool_id: B256,
+    ) -> Result<PoolFeeGrowth> {
+        let state = IStateViewInstance::new(state_view, &self.provider);
+        let growth = state.getFeeGrowthGlobals(pool_id).call().await?;
+        let slot0 = state.getSlot0(pool_id).call().await?;
+        Ok(PoolFeeGrowth {
+            pool_id: pool_id.to_string(),
+            sampled_at: Utc::now(),
+            fee_growth_global0: growth.feeGrowthGlobal0,
+            fee_growth_global1: growth.feeGrowthGlobal1,
+            tick: slot0.tick.as_i32(),
+        })
+    }
+
+    /// Position NFTs ever transferred to `owner`, with the block of each transfer
+    ///
+    /// Scans the PositionManager's Transfer logs from `from_block` to the latest
+    /// block in `LOG_SCAN_CHUNK_BLOCKS` chunks. Tokens sent on since are included;
+    /// check `get_position_owner` for current ownership.
+    pub async fn get_received_position_ids(
+        &self,
+        position_manager: Address,
+        owner: Address,
+        from_block: u64,
+    ) -> Result<Vec<(U256, u64)>> {
+        let latest = self.get_block_number().await?;
+        let mut received = Vec::new();
+        let mut start = from_block;
+        while start <= latest {
+            let end = (start + LOG_SCAN_CHUNK_BLOCKS - 1).min(latest);
+            let filter = Filter::new()
+                .address(position_manager)
+                .event_signature(IPositionManager::Transfer::SIGNATURE_HASH)
+                .topic2(owner.into_word())
+                .from_block(start)
+                .to_block(end);
+            for log in self.provider.get_logs(&filter).await? {
+                let block = log.block_number.unwrap_or(end);
+                let transfer = log.log_decode::<IPositionManager::Transfer>()?;
+                received.push((transfer.inner.data.id, block));
+            }
+            start = end + 1;
+        }
+        Ok(received)
+    }
+
+    /// Pools the PoolManager created between two blocks (inclusive), oldest first
+    ///
+    /// Read from `Initialize` logs, with each pool's starting price and the
+    /// timestamp of the block it was created in. Callers keep ranges within
+    /// `LOG_SCAN_CHUNK_BLOCKS`.
+    pub async fn get_pool_initializations(
+        &self,
+        pool_manager: Address,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<PoolInitialization>> {
+        let filter = Filter::new()
+            .address(pool_manager)
+            .event_signature(IPoolManager::Initialize::SIGNATURE_HASH)
+            .from_block(from_block)
+            .to_block(to_block);
+
+        let mut timestamps: HashMap<u64, DateTime<Utc>> = HashMap::new();
+        let mut initializations = Vec::new();
+        for log in self.provider.get_logs(&filter).await? {
+            let block = log.block_number.context("Initialize log has no block number")?;
+            let tx_hash = log.transaction_hash.map(|hash| format!("{:#x}", hash));
+            let event = log.log_decode::<IPoolManager::Initialize>()?.inner.data;
+
+            let created_at = match timestamps.get(&block) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    let timestamp = self.get_block_timestamp(block).await?;
+                    timestamps.insert(block, timestamp);
+                    timestamp
+                }
+            };
+            initializations.push(PoolInitialization {
+                pool_id: format!("{:#x}", event.id),
+                token0: format!("{:#x}", event.currency0),
+                token1: format!("{:#x}", event.currency1),
+                fee_tier: event.fee.to::<u32>() as i32,
+                tick_spacing: event.tickSpacing.as_i32(),
+                hooks: format!("{:#x}", event.hooks),
+                sqrt_price_x96: U256::from(event.sqrtPriceX96),
+                tick: event.tick.as_i32(),
+                block_number: block as i64,
+                tx_hash,
+                created_at,
+            });
+        }
+        Ok(initializations)
+    }
+
+    /// Swaps in the given pools between two blocks (inclusive), in log order
+    ///
+    /// Callers keep ranges within `LOG_SCAN_CHUNK_BLOCKS`.
+    pub async fn get_pool_swaps(
+        &self,
+        pool_manager: Address,
+        pool_ids: &[B256],
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<OnchainSwap>> {
+        if pool_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let filter = Filter::new()
+            .address(pool_manager)
+            .event_signature(IPoolManager::Swap::SIGNATURE_HASH)
+            .topic1(pool_ids.to_vec())
+            .from_block(from_block)
+            .to_block(to_block);
+
+        let mut swaps = Vec::new();
+        for log in self.provider.get_logs(&filter).await? {
+            let block_number = log.block_number.context("Swap log has no block number")?;
+            let log_index = log.log_index.unwrap_or_default();
+            let tx_hash = log.transaction_hash.map(|hash| format!("{:#x}", hash));
+            let event = log.log_decode::<IPoolManager::Swap>()?.inner.data;
+            swaps.push(OnchainSwap {
+                pool_id: format!("{:#x}", event.id),
+                tx_hash,
+                block_number,
+                log_index,
+                amount0: -I256::try_from(event.amount0)?,
+                amount1: -I256::try_from(event.amount1)?,
+                tick: event.tick.as_i32(),
+                fee: event.fee.to::<u32>(),
+            });
+        }
+        Ok(swaps)
+    }
+
+    /// Current owner of a position NFT
+    pub async fn get_position_owner(
+        &self,
+        position_manager: Address,
+        token_id: U256,
+    ) -> Result<Address> {
+        let owner = IPositionManagerInstance::new(position_manager, &self.provider)
+            .ownerOf(token_id)
+            .call()
+            .await?;
+        Ok(owner.owner)
+    }
+
+    /// Pool key, range and liquidity of a position NFT
+    pub async fn get_position(
+        &self,
+        position_manager: Address,
+        token_id: U256,
+    ) -> Result<OnchainPosition> {
+        let manager = IPositionManagerInstance::new(position_manager, &self.provider);
+        let info = manager.getPoolAndPositionInfo(token_id).call().await?;
+        let liquidity = manager.getPositionLiquidity(token_id).call().await?.liquidity;
+
+        let key = info.poolKey;
+        let pool_id = keccak256(key.abi_encode());
+        let (tick_lower, tick_upper) = unpack_position_ticks(info.info);
+        Ok(OnchainPosition {
+            token_id,
+            pool_id,
+            currency0: key.currency0,
+            currency1: key.currency1,
+            fee: key.fee.to::<u32>(),
+            tick_spacing: key.tickSpacing.as_i32(),
+            hooks: key.hooks,
+            tick_lower,
+            tick_upper,
+            liquidity: U256::from(liquidity),
+        })
+    }
+
+    /// ERC20 decimals of a token; the native currency (zero address) has 18
+    pub async fn get_token_decimals(&self, token: Address) -> Result<u8> {
+        if token == Address::ZERO {
+            return Ok(18);
+        }
+        let decimals = IERC20MetadataInstance::new(token, &self.provider).decimals().call().await?;
+        Ok(decimals._0)
+    }
+
+    /// ERC20 balance of `holder`, e.g. a depositor's vault shares
+    pub async fn get_token_balance(&self, token: Address, holder: Address) -> Result<U256> {
+        let balance =
+            IERC20MinimalInstance::new(token, &self.provider).balanceOf(holder).call().await?;
+        Ok(balance._0)
+    }
+
+    /// Total supply of an ERC20 token
+    pub async fn get_token_supply(&self, token: Address) -> Result<U256> {
+        let supply = IERC20MinimalInstance::new(token, &self.provider).totalSupply().call().await?;
+        Ok(supply._0)
+    }
+
+    /// Timestamp of a block
+    pub async fn get_block_timestamp(&self, block: u64) -> Result<DateTime<Utc>> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(block), BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or_else(|| anyhow!("Block {} not found", block))?;
+        DateTime::from_timestamp(block.header.timestamp as i64, 0)
+            .context("Block timestamp out of range")
+    }
+
+    /// Base fee and median priority fee of a block
+    ///
+    /// The tip is the 50th percentile `eth_feeHistory` reports, weighted by
+    /// gas used; RPCs without fee history leave it unknown.
+    pub async fn get_gas_price_sample(&self, chain_id: u64, block: u64) -> Result<GasPriceSample> {
+        let header = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(block), BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or_else(|| anyhow!("Block {} not found", block))?
+            .header;
+        let timestamp = DateTime::from_timestamp(header.timestamp as i64, 0)
+            .context("Block timestamp out of range")?;
+
+        let history =
+            self.provider.get_fee_history(1, BlockNumberOrTag::Number(block), &[50.0]).await;
+        let tip = history
+            .ok()
+            .and_then(|history| history.reward)
+            .and_then(|rewards| rewards.first().and_then(|r| r.first().copied()));
+
+        Ok(GasPriceSample {
+            chain_id: chain_id as i64,
+            block_number: block as i64,
+            timestamp,
+            base_fee_per_gas: header.base_fee_per_gas.map(Decimal::from),
+            priority_fee_per_gas: tip.map(Decimal::from),
+        })
+    }
+
+    /// Fee fields of a transaction's receipt, including L2-specific ones
+    ///
+    /// Reads the raw receipt so chain-specific fields (`l1Fee` on OP-stack
+    /// chains, `gasUsedForL1` on Arbitrum) survive. None if the transaction
+    /// isn't mined.
+    pub async fn get_transaction_fees(&self, tx_hash: B256) -> Result<Option<TransactionFees>> {
+        let receipt: serde_json::Value = self
+            .provider
+            .raw_request("eth_getTransactionReceipt".into(), (tx_hash,))
+            .await?;
+        if receipt.is_null() {
+            return Ok(None);
+        }
+
+        let quantity = |field: &str| -> Result<Option<u128>> {
+            match receipt.get(field).and_then(|v| v.as_str()) {
+                Some(hex) => u128::from_str_radix(hex.trim_start_matches("0x"), 16)
+                    .map(Some)
+                    .with_context(|| format!("Invalid receipt {}: {}", field, hex)),
+                None => Ok(None),
+            }
+        };
+        Ok(Some(TransactionFees {
+            gas_used: quantity("gasUsed")?.context("Receipt has no gasUsed")?,
+            effective_gas_price: quantity("effectiveGasPrice")?
+                .context("Receipt has no effectiveGasPrice")?,
+            l1_fee: quantity("l1Fee")?,
+            gas_used_for_l1: quantity("gasUsedForL1")?,
+        }))
+    }
 }
 
 impl Clone for BlockchainService {
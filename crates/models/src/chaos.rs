@@ -0,0 +1,123 @@
+use anyhow::{Context, Result, anyhow};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// A failure injected into one subgraph request or database connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Wait, then fail as if the request timed out
+    Timeout(Duration),
+    /// Fail with this HTTP status (a dropped connection for the database)
+    Status(u16),
+    /// Cut every list in the response to this many items (for the database,
+    /// close the connection after this many bytes from the server)
+    PartialPage(usize),
+    /// Fail immediately as if the connection was reset
+    ConnectionDrop,
+    /// Wait, then carry on normally
+    Latency(Duration),
+}
+
+impl Fault {
+    /// Parse one step of a fault plan; `ok` is no fault
+    ///
+    /// Steps are `ok`, `timeout:<duration>`, a status code like `500`,
+    /// `partial:<items>`, `drop` and `latency:<duration>`, where durations
+    /// are `<n>ms` or `<n>s`.
+    pub fn parse(step: &str) -> Result<Option<Self>> {
+        let step = step.trim();
+        let (kind, arg) = match step.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (step, None),
+        };
+        let fault = match (kind, arg) {
+            ("ok", None) => return Ok(None),
+            ("drop", None) => Fault::ConnectionDrop,
+            ("timeout", Some(arg)) => Fault::Timeout(parse_duration(arg)?),
+            ("latency", Some(arg)) => Fault::Latency(parse_duration(arg)?),
+            ("partial", Some(arg)) => Fault::PartialPage(
+                arg.parse().with_context(|| format!("Invalid partial page size: {}", arg))?,
+            ),
+            (code, None) => match code.parse::<u16>() {
+                Ok(status) if (400..600).contains(&status) => Fault::Status(status),
+                _ => return Err(anyhow!("Unknown fault: {}", step)),
+            },
+            _ => return Err(anyhow!("Unknown fault: {}", step)),
+        };
+        Ok(Some(fault))
+    }
+}
+
+fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (digits, unit_ms) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1000)
+    } else {
+        return Err(anyhow!("Duration must end in ms or s: {}", s));
+    };
+    let value: u64 = digits.parse().with_context(|| format!("Invalid duration: {}", s))?;
+    Ok(Duration::from_millis(value * unit_ms))
+}
+
+/// A deterministic script of faults, one step per request or connection
+///
+/// Steps play in order and the last one repeats, so `500,500,ok` fails twice
+/// and then recovers for good. With a `cycle:` prefix the whole script loops
+/// instead, e.g. `cycle:ok,ok,drop` drops every third request.
+#[derive(Debug)]
+pub struct FaultPlan {
+    steps: Vec<Option<Fault>>,
+    cycle: bool,
+    position: AtomicUsize,
+}
+
+impl FaultPlan {
+    pub fn new(steps: Vec<Option<Fault>>, cycle: bool) -> Self {
+        Self { steps, cycle, position: AtomicUsize::new(0) }
+    }
+
+    /// Parse a comma-separated script (see `Fault::parse` for the steps)
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (spec, cycle) = match spec.trim().strip_prefix("cycle:") {
+            Some(rest) => (rest, true),
+            None => (spec.trim(), false),
+        };
+        let steps = spec
+            .split(',')
+            .filter(|step| !step.trim().is_empty())
+            .map(Fault::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if steps.is_empty() {
+            return Err(anyhow!("Fault plan has no steps"));
+        }
+        Ok(Self::new(steps, cycle))
+    }
+
+    /// Plan from an environment variable such as `CHAOS_GRAPH_FAULTS`, if set
+    pub fn from_env(var: &str) -> Result<Option<Self>> {
+        match std::env::var(var) {
+            Ok(spec) if !spec.trim().is_empty() => {
+                Self::parse(&spec).with_context(|| format!("Invalid {}", var)).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Fault for the next request or connection
+    pub fn next_fault(&self) -> Option<Fault> {
+        let index = self.position.fetch_add(1, Ordering::Relaxed);
+        if self.steps.is_empty() {
+            return None;
+        }
+        let index =
+            if self.cycle { index % self.steps.len() } else { index.min(self.steps.len() - 1) };
+        self.steps.get(index).copied().flatten()
+    }
+
+    /// Requests or connections the plan has been consulted for
+    pub fn consulted(&self) -> usize {
+        self.position.load(Ordering::Relaxed)
+    }
+}
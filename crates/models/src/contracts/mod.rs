@@ -28,6 +28,12 @@ sol! {
             uint160 sqrtPriceLimitX96;
         }
 
+        /// Emitted once per pool, when it's created with its starting price
+        event Initialize(bytes32 indexed id, address indexed currency0, address indexed currency1, uint24 fee, int24 tickSpacing, address hooks, uint160 sqrtPriceX96, int24 tick);
+
+        /// Emitted per swap, with the swapper's balance deltas and the pool's price and tick after it
+        event Swap(bytes32 indexed id, address indexed sender, int128 amount0, int128 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick, uint24 fee);
+
         function initialize(PoolKey memory key, uint160 sqrtPriceX96) external returns (int24 tick);
         function modifyLiquidity(PoolKey memory key, ModifyLiquidityParams memory params, bytes calldata hookData) external returns (int256, int256);
         function swap(PoolKey memory key, SwapParams memory params, bytes calldata hookData) external returns (int256, int256);
@@ -75,6 +81,7 @@ sol! {
     #[sol(rpc)]
     interface IERC20Minimal {
         function balanceOf(address account) external view returns (uint256);
+        function totalSupply() external view returns (uint256);
         function transfer(address to, uint256 amount) external returns (bool);
         function allowance(address owner, address spender) external view returns (uint256);
         function approve(address spender, uint256 amount) external returns (bool);
@@ -92,6 +99,45 @@ sol! {
     }
 }
 
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IStateView {
+        function getSlot0(bytes32 poolId) external view returns (uint160 sqrtPriceX96, int24 tick, uint24 protocolFee, uint24 lpFee);
+        function getFeeGrowthGlobals(bytes32 poolId) external view returns (uint256 feeGrowthGlobal0, uint256 feeGrowthGlobal1);
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IERC20Metadata {
+        function decimals() external view returns (uint8);
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IPositionManager {
+        struct PoolKey {
+            address currency0;
+            address currency1;
+            uint24 fee;
+            int24 tickSpacing;
+            address hooks;
+        }
+
+        /// ERC721 transfer of a position NFT (`from` is zero on mint)
+        event Transfer(address indexed from, address indexed to, uint256 indexed id);
+
+        function ownerOf(uint256 id) external view returns (address owner);
+        /// `info` is the packed PositionInfo: poolId (200 bits) | tickUpper | tickLower | hasSubscriber
+        function getPoolAndPositionInfo(uint256 tokenId) external view returns (PoolKey memory poolKey, uint256 info);
+        function getPositionLiquidity(uint256 tokenId) external view returns (uint128 liquidity);
+    }
+}
+
 // Re-export the generated types
 // Note: Some function names overlap between interfaces (e.g., transfer, balanceOf)
 // This is intentional as they represent different contract interfaces
@@ -105,3 +151,7 @@ pub use IUnlockCallback::*;
 pub use IERC20Minimal::*;
 #[allow(ambiguous_glob_reexports)]
 pub use IERC6909Claims::*;
+pub use IStateView::IStateViewInstance;
+pub use IERC20Metadata::IERC20MetadataInstance;
+// PoolKey would clash with IPoolManager's, so PositionManager types stay namespaced
+pub use IPositionManager::IPositionManagerInstance;
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the Postgres channel data change events are sent on with `NOTIFY`
+///
+/// `NOTIFY` channels are database-wide, so each schema (see `DB_SCHEMA`) gets
+/// its own channel and instances sharing a database don't hear each other.
+pub const EVENTS_CHANNEL_PREFIX: &str = "stillwater_events_";
+
+/// The events channel for a schema's data (`stillwater_events_<schema>`)
+pub fn events_channel(schema: &str) -> String {
+    format!("{}{}", EVENTS_CHANNEL_PREFIX, schema)
+}
+
+/// A change to stored data, sent as a JSON `NOTIFY` payload so the API and
+/// workers can react without polling tables
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DbEvent {
+    /// A position was inserted (sync or CSV import)
+    PositionInserted { nft_id: String, owner: String, pool_id: String },
+    /// A pool's creation was read from its `Initialize` event
+    PoolCreated { pool_id: String, token0: String, token1: String, block: i64 },
+    /// A new swap was recorded
+    SwapInserted { pool_id: String, tx_hash: String, timestamp: DateTime<Utc> },
+    /// A watched position left or re-entered its range, as seen by the `watch` binary
+    RangeCrossed {
+        position_id: i64,
+        owner: String,
+        pool_id: String,
+        in_range: bool,
+        tick: i32,
+        block: u64,
+    },
+}
+
+impl DbEvent {
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).expect("DbEvent serializes to JSON")
+    }
+
+    /// Parse a `NOTIFY` payload, `None` if it isn't an event
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        serde_json::from_str(payload).ok()
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Gas paid for a transaction affecting a position
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GasExpense {
+    pub id: i64,
+    pub position_id: i64,
+    pub tx_hash: String,
+    /// Cost in the chain's native token
+    pub gas_cost: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Fee fields of a transaction receipt, in wei
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransactionFees {
+    pub gas_used: u128,
+    pub effective_gas_price: u128,
+    /// OP-stack `l1Fee`: L1 data fee charged on top of L2 execution
+    pub l1_fee: Option<u128>,
+    /// Arbitrum `gasUsedForL1`: the part of `gas_used` paying for L1 calldata
+    pub gas_used_for_l1: Option<u128>,
+}
+
+/// Gas price of one block of a chain, in wei
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GasPriceSample {
+    pub chain_id: i64,
+    pub block_number: i64,
+    pub timestamp: DateTime<Utc>,
+    /// None before EIP-1559
+    pub base_fee_per_gas: Option<Decimal>,
+    /// Median priority fee paid in the block
+    pub priority_fee_per_gas: Option<Decimal>,
+}
+
+impl GasPriceSample {
+    /// Price a typical transaction paid per gas: base fee plus median tip
+    ///
+    /// None when neither part is known.
+    pub fn gas_price(&self) -> Option<Decimal> {
+        match (self.base_fee_per_gas, self.priority_fee_per_gas) {
+            (None, None) => None,
+            (base, tip) => Some(base.unwrap_or_default() + tip.unwrap_or_default()),
+        }
+    }
+}
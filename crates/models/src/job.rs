@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Work a background job does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// Re-fetch the swaps of an owner's pools over a longer window than a sync covers
+    Backfill,
+    /// Render an owner's double-entry ledger
+    LedgerExport,
+    /// Sync one owner's or pool's positions and recent swaps now, outside the schedule
+    Sync,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 3] = [JobKind::Backfill, JobKind::LedgerExport, JobKind::Sync];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Backfill => "backfill",
+            JobKind::LedgerExport => "ledger_export",
+            JobKind::Sync => "sync",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "backfill" => Some(JobKind::Backfill),
+            "ledger_export" => Some(JobKind::LedgerExport),
+            "sync" => Some(JobKind::Sync),
+            _ => None,
+        }
+    }
+}
+
+/// Where a job is in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(JobStatus::Pending),
+            "running" => Some(JobStatus::Running),
+            "succeeded" => Some(JobStatus::Succeeded),
+            "failed" => Some(JobStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// Whether the job is done, one way or the other
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Succeeded | JobStatus::Failed)
+    }
+}
+
+/// A queued unit of work, run by a worker process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub kind: JobKind,
+    /// Owner the job works on, and who may see it (empty for syncs of a pool)
+    pub owner: String,
+    /// Kind-specific options
+    pub params: serde_json::Value,
+    pub status: JobStatus,
+    /// Times a worker has claimed the job
+    pub attempts: i32,
+    /// Output of a succeeded job
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Percent done, for jobs that report progress (backfills)
+    pub progress: Option<Decimal>,
+    /// Where the job got to, so it resumes there if its worker stops
+    pub cursor: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    /// Last time the job was claimed or reported progress
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
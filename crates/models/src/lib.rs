@@ -3,17 +3,67 @@ pub mod blockchain;
 pub mod contracts;
 
 // Domain models
+pub mod address;
+pub mod amount;
 pub mod pool;
 pub mod position;
 pub mod swap;
 pub mod snapshot;
 pub mod pnl;
+pub mod gas;
+pub mod alert;
+pub mod account;
+pub mod quality;
+pub mod liquidity;
+pub mod sync;
+pub mod event;
+pub mod query;
+pub mod retention;
+pub mod accumulator;
+pub mod transfer;
+pub mod job;
+pub mod backtest;
+pub mod vault;
+
+// Testing
+pub mod chaos;
 
 // Re-export commonly used types
-pub use blockchain::BlockchainService;
+pub use blockchain::{
+    BlockchainService, LOG_SCAN_CHUNK_BLOCKS, OnchainPosition, OnchainSwap,
+    unpack_position_ticks,
+};
 pub use contracts::*;
-pub use pool::Pool;
-pub use position::Position;
-pub use swap::Swap;
-pub use snapshot::PositionSnapshot;
+pub use address::{Address, AddressError, canonical_address};
+pub use amount::{AmountError, NATIVE_DECIMALS, TokenAmount};
+pub use pool::{
+    Pool, PoolFeeGrowth, PoolFeeOverride, PoolInitialization, PoolParam, PoolParamChange,
+    PoolStats, DYNAMIC_FEE_FLAG, NO_HOOKS,
+};
+pub use position::{Position, PositionHealthRow, PositionLifecycle};
+pub use swap::{Swap, SwapDownsampling, TickSeriesChunk};
+pub use snapshot::{PositionSnapshot, SnapshotWindow};
 pub use pnl::{PositionPnL, HealthStatus};
+pub use gas::{GasExpense, GasPriceSample, TransactionFees};
+pub use alert::{
+    Alert, AlertKind, AlertRule, AlertRuleSpec, AlertSeverity, AlertTemplate, DeliveryStatus,
+    PendingAlert,
+};
+pub use account::{
+    Account, ApiKey, MAX_ACCOUNT_NAME_LEN, QuotePreference, TelegramChat, WatchedAddress,
+    WatchedPair, WatchedToken,
+};
+pub use quality::{DataQualityIssue, DataQualitySummary, IssueKind};
+pub use liquidity::{
+    LiquidityChange, LiquidityEvent, PositionLiquidityEvent, PricedLiquidityEvent, RangeLiquidity,
+};
+pub use sync::{FieldReport, SyncCheckpoint, SyncRun, SyncRunStatus, SyncSchedule, SyncStage};
+pub use event::{DbEvent, EVENTS_CHANNEL_PREFIX, events_channel};
+pub use query::QueryResult;
+pub use retention::RetainedData;
+pub use accumulator::FeeAccumulator;
+pub use transfer::PositionTransfer;
+pub use job::{Job, JobKind, JobStatus};
+pub use backtest::BacktestRun;
+pub use vault::Vault;
+pub use chaos::{Fault, FaultPlan};
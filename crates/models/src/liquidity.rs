@@ -0,0 +1,183 @@
+use alloy::primitives::I256;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+
+/// Direction of a liquidity change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiquidityChange {
+    Add,
+    Remove,
+}
+
+impl LiquidityChange {
+    /// Direction of a signed liquidity delta, None for zero (a fee collect or poke)
+    pub fn of(delta: I256) -> Option<Self> {
+        if delta.is_zero() {
+            None
+        } else if delta.is_negative() {
+            Some(LiquidityChange::Remove)
+        } else {
+            Some(LiquidityChange::Add)
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiquidityChange::Add => "add",
+            LiquidityChange::Remove => "remove",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "add" => Some(LiquidityChange::Add),
+            "remove" => Some(LiquidityChange::Remove),
+            _ => None,
+        }
+    }
+}
+
+/// A v4 ModifyLiquidity event: a signed liquidity delta on an owner's tick range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidityEvent {
+    pub event_id: String,
+    pub owner: Address,
+    pub pool_id: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    #[serde(with = "i256_serde")]
+    pub liquidity_delta: I256,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl LiquidityEvent {
+    /// Direction of the event
+    ///
+    /// Zero deltas are skipped at sync, so a recorded event always moves liquidity.
+    pub fn change(&self) -> LiquidityChange {
+        LiquidityChange::of(self.liquidity_delta).unwrap_or(LiquidityChange::Add)
+    }
+}
+
+/// A recorded liquidity event with the position it was applied to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionLiquidityEvent {
+    #[serde(flatten)]
+    pub event: LiquidityEvent,
+    /// None when a removal matched no open position
+    pub position_id: Option<i64>,
+    /// Whether this is the position's first recorded event, i.e. its mint
+    pub first_for_position: bool,
+}
+
+/// A recorded liquidity event with the pool price when it happened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricedLiquidityEvent {
+    #[serde(flatten)]
+    pub event: LiquidityEvent,
+    /// Raw pool price at the event; None for removals and additions not priced yet
+    pub price: Option<Decimal>,
+}
+
+/// Net recorded liquidity on one tick range of a pool, summed across owners
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeLiquidity {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: I256,
+}
+
+// Custom serialization for I256
+mod i256_serde {
+    use alloy::primitives::I256;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &I256, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<I256, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        // I256 parses an empty string as zero
+        if s.is_empty() {
+            return Err(serde::de::Error::custom("empty liquidity delta"));
+        }
+        s.parse::<I256>().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_event(liquidity_delta: &str) -> serde_json::Value {
+        serde_json::json!({
+            "event_id": "0xevent-1",
+            "owner": "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "pool_id": "0xpool",
+            "tick_lower": -600,
+            "tick_upper": 600,
+            "liquidity_delta": liquidity_delta,
+            "timestamp": "2024-01-01T00:00:00Z",
+        })
+    }
+
+    #[test]
+    fn test_change_follows_the_delta_sign() {
+        assert_eq!(LiquidityChange::of(I256::try_from(1000).unwrap()), Some(LiquidityChange::Add));
+        assert_eq!(
+            LiquidityChange::of(I256::try_from(-1000).unwrap()),
+            Some(LiquidityChange::Remove)
+        );
+        assert_eq!(LiquidityChange::of(I256::MAX), Some(LiquidityChange::Add));
+        assert_eq!(LiquidityChange::of(I256::MIN), Some(LiquidityChange::Remove));
+    }
+
+    #[test]
+    fn test_zero_delta_has_no_direction() {
+        assert_eq!(LiquidityChange::of(I256::ZERO), None);
+    }
+
+    #[test]
+    fn test_change_round_trips_through_its_name() {
+        for change in [LiquidityChange::Add, LiquidityChange::Remove] {
+            assert_eq!(LiquidityChange::parse(change.as_str()), Some(change));
+        }
+        assert_eq!(LiquidityChange::parse("collect"), None);
+    }
+
+    #[test]
+    fn test_signed_deltas_deserialize_from_strings() {
+        let removal: LiquidityEvent =
+            serde_json::from_value(create_test_event("-123456789012345678901234567890")).unwrap();
+        assert_eq!(removal.liquidity_delta.to_string(), "-123456789012345678901234567890");
+        assert_eq!(removal.change(), LiquidityChange::Remove);
+
+        let addition: LiquidityEvent = serde_json::from_value(create_test_event("42")).unwrap();
+        assert_eq!(addition.liquidity_delta, I256::try_from(42).unwrap());
+        assert_eq!(addition.change(), LiquidityChange::Add);
+
+        let extreme = I256::MIN.to_string();
+        let event: LiquidityEvent = serde_json::from_value(create_test_event(&extreme)).unwrap();
+        assert_eq!(event.liquidity_delta, I256::MIN);
+        assert_eq!(serde_json::to_value(&event).unwrap()["liquidity_delta"], extreme);
+    }
+
+    #[test]
+    fn test_malformed_deltas_are_rejected() {
+        for delta in ["", "1.5", "1e18", "twelve"] {
+            let result = serde_json::from_value::<LiquidityEvent>(create_test_event(delta));
+            assert!(result.is_err(), "{:?} parsed", delta);
+        }
+    }
+}
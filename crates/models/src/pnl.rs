@@ -11,14 +11,16 @@ pub struct PositionPnL {
 }
 
 /// Health status of a position
+///
+/// Assigned by configurable health rules; the variant docs describe the defaults.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum HealthStatus {
     /// In range, positive P&L
     Healthy,
-    /// Near out of range (within 10% of range edge)
+    /// Near out of range (within 10% of range edge) or a small loss
     Warning,
-    /// Out of range or negative P&L
+    /// Out of range or lost more than 2% of entry capital
     Critical,
 }
 
@@ -27,8 +29,8 @@ impl HealthStatus {
     pub fn description(&self) -> &'static str {
         match self {
             HealthStatus::Healthy => "Position is in range with positive P&L",
-            HealthStatus::Warning => "Position is near the edge of its range",
-            HealthStatus::Critical => "Position is out of range or has negative P&L",
+            HealthStatus::Warning => "Position is near the edge of its range or slightly down",
+            HealthStatus::Critical => "Position is out of range or losing capital",
         }
     }
 }
@@ -1,14 +1,198 @@
+use alloy::primitives::{Address, U256};
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use crate::amount::TokenAmount;
+
 /// Uniswap v4 pool information
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Pool {
+    pub pool_id: String,
+    pub token0: String,
+    pub token1: String,
+    pub token0_decimals: i16,
+    pub token1_decimals: i16,
+    pub fee_tier: i32,
+    pub tick_spacing: i32,
+    /// Hook contract address (zero address if none)
+    pub hooks: String,
+    /// Packed v4 protocol fee: zeroForOne in the low 12 bits, oneForZero in the high 12 bits
+    pub protocol_fee: i32,
+    /// Block timestamp of the pool's creation (None until the subgraph reports it)
+    pub created_at: Option<DateTime<Utc>>,
+    /// Block number of the pool's creation
+    pub created_at_block: Option<i64>,
+    /// Operator-set LP fee in hundredths of a bip, used where the fee can't be resolved
+    pub fee_override: Option<i32>,
+}
+
+/// An operator-set LP fee for a pool (see `pool_fee_overrides`)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PoolFeeOverride {
+    pub pool_id: String,
+    /// LP fee in hundredths of a bip (3000 = 0.3%)
+    pub fee: i32,
+    /// Where the figure came from
+    pub note: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A pool's creation, from the PoolManager's `Initialize` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolInitialization {
     pub pool_id: String,
     pub token0: String,
     pub token1: String,
     pub fee_tier: i32,
     pub tick_spacing: i32,
+    pub hooks: String,
+    /// Starting price as a Q64.96 square root of token1 per token0 (raw units)
+    #[serde(with = "crate::position::u256_serde")]
+    pub sqrt_price_x96: U256,
+    /// Tick of the starting price
+    pub tick: i32,
+    pub block_number: i64,
+    pub tx_hash: Option<String>,
+    /// Timestamp of the block the pool was created in
     pub created_at: DateTime<Utc>,
 }
+
+/// A pool's cumulative fees per unit of liquidity at one moment, read from pool state
+///
+/// Fee growth is Q128.128 and wraps on overflow, so only differences between
+/// samples are meaningful.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolFeeGrowth {
+    pub pool_id: String,
+    pub sampled_at: DateTime<Utc>,
+    #[serde(with = "crate::position::u256_serde")]
+    pub fee_growth_global0: U256,
+    #[serde(with = "crate::position::u256_serde")]
+    pub fee_growth_global1: U256,
+    /// Pool tick when sampled
+    pub tick: i32,
+}
+
+/// A pool parameter that can change under its LPs
+///
+/// The v4 pool key (fee tier, tick spacing, hooks) is fixed at creation; a
+/// different key is a different pool. What moves is read from pool state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolParam {
+    /// LP fee in hundredths of a bip, set by the hook in dynamic-fee pools
+    LpFee,
+    /// Packed protocol fee, set by protocol governance
+    ProtocolFee,
+}
+
+impl PoolParam {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PoolParam::LpFee => "lp_fee",
+            PoolParam::ProtocolFee => "protocol_fee",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "lp_fee" => Some(PoolParam::LpFee),
+            "protocol_fee" => Some(PoolParam::ProtocolFee),
+            _ => None,
+        }
+    }
+}
+
+/// A change to a pool parameter, seen when `sync` read the pool's state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolParamChange {
+    pub id: i64,
+    pub pool_id: String,
+    pub param: PoolParam,
+    pub old_value: i32,
+    pub new_value: i32,
+    /// When the sync saw the new value; the change happened since the read before
+    pub detected_at: DateTime<Utc>,
+}
+
+impl PoolParamChange {
+    /// Fractional size of the change; for the protocol fee, the larger direction's
+    ///
+    /// None for a fee raised from zero, which no ratio measures.
+    pub fn relative_change(&self) -> Option<Decimal> {
+        let relative = |old: i32, new: i32| match Decimal::from(old) {
+            old_fee if old_fee.is_zero() => (new == old).then_some(Decimal::ZERO),
+            old_fee => Some((Decimal::from(new) - old_fee).abs() / old_fee),
+        };
+        match self.param {
+            PoolParam::LpFee => relative(self.old_value, self.new_value),
+            PoolParam::ProtocolFee => {
+                let zero_for_one = relative(self.old_value & 0xfff, self.new_value & 0xfff)?;
+                let one_for_zero =
+                    relative((self.old_value >> 12) & 0xfff, (self.new_value >> 12) & 0xfff)?;
+                Some(zero_for_one.max(one_for_zero))
+            }
+        }
+    }
+}
+
+/// Recent activity and position counts of a pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub pool_id: String,
+    /// Start of the window the swap figures cover
+    pub since: DateTime<Utc>,
+    pub swap_count: i64,
+    /// Sum of absolute token0 amounts swapped, in raw units
+    pub volume0: String,
+    /// Sum of absolute token1 amounts swapped, in raw units
+    pub volume1: String,
+    pub last_swap_at: Option<DateTime<Utc>>,
+    pub positions: i64,
+    pub open_positions: i64,
+}
+
+/// Hooks address of pools without hooks
+pub const NO_HOOKS: &str = "0x0000000000000000000000000000000000000000";
+
+/// `fee_tier` value marking a pool whose LP fee is set dynamically by its hook
+pub const DYNAMIC_FEE_FLAG: i32 = 0x800000;
+
+impl Pool {
+    /// Whether the pool has a hook contract attached
+    pub fn has_hooks(&self) -> bool {
+        !self.hooks.is_empty() && self.hooks != NO_HOOKS
+    }
+
+    /// Whether the pool's LP fee is dynamic (v4 dynamic fee flag)
+    pub fn is_dynamic_fee(&self) -> bool {
+        self.fee_tier == DYNAMIC_FEE_FLAG
+    }
+
+    /// Protocol fee in hundredths of a bip for swaps in one direction
+    pub fn protocol_fee_for(&self, zero_for_one: bool) -> i32 {
+        if zero_for_one { self.protocol_fee & 0xfff } else { (self.protocol_fee >> 12) & 0xfff }
+    }
+
+    /// A raw amount of token0, with its decimals
+    pub fn amount0(&self, raw: U256) -> TokenAmount {
+        TokenAmount::new(raw, clamp_decimals(self.token0_decimals), token_address(&self.token0))
+    }
+
+    /// A raw amount of token1, with its decimals
+    pub fn amount1(&self, raw: U256) -> TokenAmount {
+        TokenAmount::new(raw, clamp_decimals(self.token1_decimals), token_address(&self.token1))
+    }
+}
+
+/// Decimals clamped to what ERC20 allows
+fn clamp_decimals(decimals: i16) -> u8 {
+    decimals.clamp(0, u8::MAX.into()) as u8
+}
+
+/// A stored token address; unparseable ones read as the zero address
+fn token_address(token: &str) -> Address {
+    token.parse().unwrap_or_default()
+}
@@ -1,23 +1,80 @@
 use alloy::primitives::U256;
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::address::Address;
+
 /// LP position NFT
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub id: i64,
     pub nft_id: String,
-    pub owner: String,
+    pub owner: Address,
     pub pool_id: String,
     pub tick_lower: i32,
     pub tick_upper: i32,
     #[serde(with = "u256_serde")]
     pub liquidity: U256,
     pub created_at: DateTime<Utc>,
+    /// Imported by hand (e.g. from CSV) rather than synced from the subgraph
+    #[serde(default)]
+    pub manual: bool,
+    /// When the position was first seen fully withdrawn (None while it holds liquidity)
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+    /// When the archival job moved it out of default listings
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+impl Position {
+    /// Lifecycle state from archival and remaining liquidity
+    pub fn lifecycle(&self) -> PositionLifecycle {
+        if self.archived_at.is_some() {
+            PositionLifecycle::Archived
+        } else if self.liquidity.is_zero() {
+            PositionLifecycle::Closed
+        } else {
+            PositionLifecycle::Active
+        }
+    }
+}
+
+/// Where a position is in its life: holding liquidity, withdrawn, or archived
+/// after staying withdrawn past `POSITION_ARCHIVE_AFTER_DAYS`
+///
+/// Archived positions keep their history but are left out of listings and
+/// portfolios unless explicitly asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionLifecycle {
+    Active,
+    Closed,
+    Archived,
+}
+
+/// What health is evaluated from for one open position, read for many in one query
+///
+/// Batch evaluation uses what's already stored instead of rescanning swaps per
+/// position: the pool's latest tick rather than its TWAP, and accumulated
+/// lifetime fees.
+#[derive(Debug, Clone)]
+pub struct PositionHealthRow {
+    pub position: Position,
+    /// The pool's latest tick: its last fee growth sample or swap, whichever is newer
+    pub current_tick: Option<i32>,
+    /// Liquidity-weighted tick of the priced deposits since the range was last emptied
+    pub entry_tick: Option<i32>,
+    /// Lifetime fees from the position's fee accumulator (zero before `sync` builds it)
+    pub fees_earned: Decimal,
+    /// Price of the earliest snapshot in the recent window and when it was taken,
+    /// which time to the range edge is extrapolated from
+    pub earlier_price: Option<(Decimal, DateTime<Utc>)>,
 }
 
 // Custom serialization for U256
-mod u256_serde {
+pub(crate) mod u256_serde {
     use alloy::primitives::U256;
     use serde::{Deserialize, Deserializer, Serializer};
 
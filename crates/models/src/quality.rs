@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Kind of anomaly found in indexed swap data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// No swaps for longer than the configured gap
+    TimestampGap,
+    /// Amounts no real swap can produce (both zero, same sign, beyond int128)
+    ImpossibleAmount,
+    /// Same event indexed more than once
+    DuplicateEvent,
+    /// Timestamp goes backwards in insertion order
+    NonMonotonic,
+}
+
+impl IssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueKind::TimestampGap => "timestamp_gap",
+            IssueKind::ImpossibleAmount => "impossible_amount",
+            IssueKind::DuplicateEvent => "duplicate_event",
+            IssueKind::NonMonotonic => "non_monotonic",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "timestamp_gap" => Some(IssueKind::TimestampGap),
+            "impossible_amount" => Some(IssueKind::ImpossibleAmount),
+            "duplicate_event" => Some(IssueKind::DuplicateEvent),
+            "non_monotonic" => Some(IssueKind::NonMonotonic),
+            _ => None,
+        }
+    }
+}
+
+/// A data quality finding tied to the swap that exposed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataQualityIssue {
+    pub pool_id: String,
+    pub kind: IssueKind,
+    pub swap_id: i64,
+    pub tx_hash: String,
+    pub detail: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Issue counts for one pool and kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataQualitySummary {
+    pub pool_id: String,
+    pub kind: IssueKind,
+    pub count: i64,
+    pub last_detected_at: DateTime<Utc>,
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Result of an ad hoc read-only SQL query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    /// One array of JSON values per row, in column order
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// Whether rows beyond the row limit were dropped
+    pub truncated: bool,
+    pub elapsed_ms: i64,
+}
+
+impl QueryResult {
+    /// Render as CSV with a header row
+    ///
+    /// Strings are written as-is, NULLs as empty fields and other values
+    /// (numbers, booleans, arrays, objects) as JSON.
+    pub fn to_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.columns)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|value| match value {
+                serde_json::Value::Null => String::new(),
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }))?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Raw data that retention removes past a horizon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetainedData {
+    /// Swaps, hot and archived (rolled up into `pool_daily_volume` first)
+    Swaps,
+    PositionSnapshots,
+}
+
+impl RetainedData {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetainedData::Swaps => "swaps",
+            RetainedData::PositionSnapshots => "position_snapshots",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "swaps" => Some(RetainedData::Swaps),
+            "position_snapshots" => Some(RetainedData::PositionSnapshots),
+            _ => None,
+        }
+    }
+}
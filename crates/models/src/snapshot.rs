@@ -15,6 +15,20 @@ pub struct PositionSnapshot {
     pub price: Decimal,
 }
 
+/// First and last snapshot values of a position within a time window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotWindow {
+    pub position_id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub start_fees: Decimal,
+    pub end_fees: Decimal,
+    pub start_price: Decimal,
+    pub end_price: Decimal,
+    /// Share (0-1) of the window's snapshots taken with the price in the position's range
+    pub in_range_share: Decimal,
+}
+
 // Custom serialization for U256
 mod u256_serde {
     use alloy::primitives::U256;
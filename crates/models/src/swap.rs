@@ -1,6 +1,7 @@
 use alloy::primitives::I256;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
 
 /// Swap event for fee calculations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,9 +13,40 @@ pub struct Swap {
     pub amount0: I256,
     #[serde(with = "i256_serde")]
     pub amount1: I256,
+    /// Fee charged in hundredths of a bip, if known (varies per swap in dynamic-fee pools)
+    pub fee: Option<i32>,
     pub timestamp: DateTime<Utc>,
 }
 
+/// One UTC month of a pool's swap execution ticks, compressed for long-term storage
+///
+/// `data` is an encoded tick series (see the analytics `tickseries` module);
+/// chunks outlive the raw swaps they were built from.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TickSeriesChunk {
+    pub pool_id: String,
+    pub month_start: DateTime<Utc>,
+    pub point_count: i32,
+    pub first_at: Option<DateTime<Utc>>,
+    pub last_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A pool opted into downsampled swap storage
+///
+/// Each finished UTC day keeps its `keep_largest` largest swaps and stores the
+/// rest as one row per minute (see the analytics `downsample` module).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SwapDownsampling {
+    pub pool_id: String,
+    pub keep_largest: i32,
+    /// Days before this have been downsampled
+    pub downsampled_through: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // Custom serialization for I256
 mod i256_serde {
     use alloy::primitives::I256;
@@ -0,0 +1,237 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Outcome of a sync run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncRunStatus {
+    Succeeded,
+    Failed,
+}
+
+impl SyncRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncRunStatus::Succeeded => "succeeded",
+            SyncRunStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "succeeded" => Some(SyncRunStatus::Succeeded),
+            "failed" => Some(SyncRunStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Stage of a position sync, in execution order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStage {
+    /// Subgraph requests
+    Fetch,
+    /// Converting subgraph events into typed rows
+    Parse,
+    /// Token filtering, dropping repeated events and ordering
+    Dedupe,
+    /// Database writes
+    Insert,
+}
+
+/// Timing breakdown and row counts of one position sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: SyncRunStatus,
+    pub error: Option<String>,
+    pub fetch_ms: i64,
+    pub parse_ms: i64,
+    pub dedupe_ms: i64,
+    pub insert_ms: i64,
+    pub rows_fetched: i32,
+    pub rows_parsed: i32,
+    /// Rows left after dedupe
+    pub rows_kept: i32,
+    pub rows_inserted: i32,
+    /// Liquidity removals applied
+    pub rows_removed: i32,
+    /// Fields the subgraph left out of the fetched events
+    pub fields: FieldReport,
+}
+
+/// Fields a subgraph left out of a response's items, to spot schema drift
+///
+/// Subgraph versions differ in which fields they expose. Items missing an
+/// optional field are kept and counted under `missing`; items missing one the
+/// sync can't do without are skipped and counted under `dropped`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldReport {
+    /// Items the response listed
+    pub items: usize,
+    /// Per optional field (e.g. `transaction.id`), how many kept items lacked it
+    pub missing: BTreeMap<String, usize>,
+    /// Per parse error (e.g. "missing field `amount0`"), how many items were skipped
+    pub dropped: BTreeMap<String, usize>,
+}
+
+impl FieldReport {
+    /// Whether every item had every field
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.dropped.is_empty()
+    }
+
+    /// Items skipped because they couldn't be parsed
+    pub fn dropped_count(&self) -> usize {
+        self.dropped.values().sum()
+    }
+
+    /// Add another batch's counts to this one
+    pub fn merge(&mut self, other: &FieldReport) {
+        self.items += other.items;
+        for (field, count) in &other.missing {
+            *self.missing.entry(field.clone()).or_default() += count;
+        }
+        for (reason, count) in &other.dropped {
+            *self.dropped.entry(reason.clone()).or_default() += count;
+        }
+    }
+}
+
+impl SyncRun {
+    /// A run starting now; `finish` stamps its outcome
+    pub fn start() -> Self {
+        let now = Utc::now();
+        Self {
+            id: 0, // Will be auto-generated
+            started_at: now,
+            finished_at: now,
+            status: SyncRunStatus::Succeeded,
+            error: None,
+            fetch_ms: 0,
+            parse_ms: 0,
+            dedupe_ms: 0,
+            insert_ms: 0,
+            rows_fetched: 0,
+            rows_parsed: 0,
+            rows_kept: 0,
+            rows_inserted: 0,
+            rows_removed: 0,
+            fields: FieldReport::default(),
+        }
+    }
+
+    /// Add time spent in a stage
+    pub fn record(&mut self, stage: SyncStage, elapsed: std::time::Duration) {
+        let ms = elapsed.as_millis() as i64;
+        match stage {
+            SyncStage::Fetch => self.fetch_ms += ms,
+            SyncStage::Parse => self.parse_ms += ms,
+            SyncStage::Dedupe => self.dedupe_ms += ms,
+            SyncStage::Insert => self.insert_ms += ms,
+        }
+    }
+
+    /// Stamp the end of the run, failed if `error` is set
+    pub fn finish(&mut self, error: Option<String>) {
+        self.finished_at = Utc::now();
+        self.status =
+            if error.is_some() { SyncRunStatus::Failed } else { SyncRunStatus::Succeeded };
+        self.error = error;
+    }
+
+    /// Wall-clock duration of the run in milliseconds
+    pub fn total_ms(&self) -> i64 {
+        (self.finished_at - self.started_at).num_milliseconds()
+    }
+}
+
+/// Subgraph head block a successful sync ran up to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCheckpoint {
+    pub block_number: i64,
+    pub block_hash: String,
+    /// None when the subgraph doesn't report block timestamps
+    pub block_timestamp: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Whether scheduled syncs run, as set by an operator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSchedule {
+    pub paused: bool,
+    /// Why syncs were paused, if the operator said
+    pub reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    use std::time::Duration;
+
+    #[test]
+    fn test_start_has_no_time_recorded() {
+        let run = SyncRun::start();
+        assert_eq!(run.started_at, run.finished_at);
+        assert_eq!(run.total_ms(), 0);
+        assert_eq!(run.status, SyncRunStatus::Succeeded);
+        assert_eq!((run.fetch_ms, run.parse_ms, run.dedupe_ms, run.insert_ms), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_record_adds_to_each_stage() {
+        let mut run = SyncRun::start();
+        run.record(SyncStage::Fetch, Duration::from_millis(120));
+        run.record(SyncStage::Fetch, Duration::from_millis(80));
+        run.record(SyncStage::Parse, Duration::from_millis(15));
+        run.record(SyncStage::Insert, Duration::from_secs(2));
+
+        assert_eq!(run.fetch_ms, 200);
+        assert_eq!(run.parse_ms, 15);
+        assert_eq!(run.dedupe_ms, 0);
+        assert_eq!(run.insert_ms, 2000);
+    }
+
+    #[test]
+    fn test_record_truncates_to_whole_milliseconds() {
+        let mut run = SyncRun::start();
+        run.record(SyncStage::Dedupe, Duration::from_micros(999));
+        run.record(SyncStage::Dedupe, Duration::from_micros(1999));
+        assert_eq!(run.dedupe_ms, 1);
+    }
+
+    #[test]
+    fn test_finish_sets_the_outcome() {
+        let mut run = SyncRun::start();
+        run.finish(None);
+        assert_eq!(run.status, SyncRunStatus::Succeeded);
+        assert_eq!(run.error, None);
+        assert!(run.finished_at >= run.started_at);
+
+        run.finish(Some("subgraph unavailable".to_string()));
+        assert_eq!(run.status, SyncRunStatus::Failed);
+        assert_eq!(run.error.as_deref(), Some("subgraph unavailable"));
+    }
+
+    #[test]
+    fn test_total_ms_is_wall_clock_not_stage_sum() {
+        let mut run = SyncRun::start();
+        run.record(SyncStage::Fetch, Duration::from_secs(10));
+        run.finished_at = run.started_at + TimeDelta::milliseconds(1500);
+        assert_eq!(run.total_ms(), 1500);
+    }
+
+    #[test]
+    fn test_status_round_trips_through_its_name() {
+        for status in [SyncRunStatus::Succeeded, SyncRunStatus::Failed] {
+            assert_eq!(SyncRunStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(SyncRunStatus::parse("running"), None);
+    }
+}
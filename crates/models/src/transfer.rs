@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+
+/// A position NFT changing hands between two non-zero addresses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionTransfer {
+    pub transfer_id: String,
+    pub position_id: i64,
+    pub from_owner: Address,
+    pub to_owner: Address,
+    pub tx_hash: Option<String>,
+    /// Block time of the transfer; the new owner holds the position from then
+    pub effective_from: DateTime<Utc>,
+}
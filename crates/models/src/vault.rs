@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::address::Address;
+
+/// A liquidity manager whose contract owns positions on behalf of share holders
+///
+/// Its positions are stored under `address` like any owner's; depositors hold
+/// `share_token` (an ERC20, often the vault itself) and own that fraction of
+/// the supply of every position.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Vault {
+    pub address: Address,
+    pub share_token: Address,
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
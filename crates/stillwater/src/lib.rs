@@ -0,0 +1,238 @@
+//! Embedded stillwater: indexer, database and analytics behind one handle
+//!
+//! For Rust applications that want position tracking without running the
+//! API server and `sync` binary:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let stillwater = stillwater::Stillwater::from_env().await?;
+//! stillwater.track_owner("0xabc...").await?;
+//! stillwater.refresh().await?;
+//! let portfolio = stillwater.portfolio("0xabc...").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use stillwater_analytics::liquidity::range_prices;
+use stillwater_analytics::{
+    HoldingSummary, PoolExposure, TickRange, TwapConfig, amounts_for_liquidity, calculate_twap,
+    position_greeks, summarize_exposure, summarize_holding, tick_to_price,
+};
+use stillwater_db::{
+    PositionFilter, add_to_watchlist, find_positions, get_fee_accumulators, get_last_swap_before,
+    get_snapshots_for_owners, get_swaps_for_pool_between, get_watchlist,
+};
+use stillwater_indexer::GraphIndexer;
+use stillwater_models::{Address, Position, PositionLifecycle};
+use tracing::{info, warn};
+
+pub use stillwater_analytics as analytics;
+pub use stillwater_db as db;
+pub use stillwater_indexer as indexer;
+pub use stillwater_models as models;
+
+/// Watchlist source recorded for owners tracked through the facade
+const TRACK_SOURCE: &str = "embedded";
+
+/// One stillwater instance: a database, a subgraph indexer and analytics settings
+pub struct Stillwater {
+    db_pool: PgPool,
+    indexer: GraphIndexer,
+    twap: TwapConfig,
+}
+
+/// What a `refresh` synced
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefreshReport {
+    pub owners: usize,
+    /// New positions stored across all tracked owners
+    pub positions: usize,
+    /// Pools of tracked positions whose recent swaps were synced
+    pub pools: usize,
+    pub swaps: usize,
+}
+
+/// An owner's positions valued at their pools' TWAPs
+#[derive(Debug, Clone, Serialize)]
+pub struct Portfolio {
+    pub owner: Address,
+    pub as_of: DateTime<Utc>,
+    pub positions: Vec<PortfolioPosition>,
+    pub holding: HoldingSummary,
+    /// Delta/gamma of open positions per pool (pools without swaps are left out)
+    pub exposure: Vec<PoolExposure>,
+}
+
+/// A position with its current standing; price-derived fields are None without recent swaps
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioPosition {
+    #[serde(flatten)]
+    pub position: Position,
+    pub lifecycle: PositionLifecycle,
+    pub current_tick: Option<i32>,
+    pub in_range: Option<bool>,
+    /// Value of the liquidity at the TWAP, in raw token1
+    pub value: Option<Decimal>,
+    /// Fees folded into the position's accumulator (None until the `sync` job builds one)
+    pub fees_earned: Option<Decimal>,
+}
+
+impl Stillwater {
+    /// Wrap an existing pool and indexer; the schema must already be migrated
+    pub fn new(db_pool: PgPool, indexer: GraphIndexer) -> Self {
+        Self { db_pool, indexer, twap: TwapConfig::default() }
+    }
+
+    /// Connect to a database and subgraph endpoint, running pending migrations
+    pub async fn connect(database_url: &str, graph_url: &str) -> Result<Self> {
+        let db_pool = stillwater_db::connect(database_url, 5, None).await?;
+        migrate(&db_pool).await?;
+        Ok(Self::new(db_pool, GraphIndexer::new(graph_url.to_string())))
+    }
+
+    /// Configure from the same environment as the binaries (`DATABASE_URL`,
+    /// `DB_SCHEMA`, `GRAPH_API_URL`, `TWAP_WINDOW_MINUTES`, ...), running pending migrations
+    pub async fn from_env() -> Result<Self> {
+        let db_pool = stillwater_db::get_pool().await?;
+        migrate(&db_pool).await?;
+        let indexer = GraphIndexer::from_env()?;
+        Ok(Self::new(db_pool, indexer).with_twap(TwapConfig::from_env()?))
+    }
+
+    /// Price positions with a different TWAP window
+    pub fn with_twap(mut self, twap: TwapConfig) -> Self {
+        self.twap = twap;
+        self
+    }
+
+    /// The underlying pool, for the `db` functions the facade doesn't wrap
+    pub fn db_pool(&self) -> &PgPool {
+        &self.db_pool
+    }
+
+    pub fn indexer(&self) -> &GraphIndexer {
+        &self.indexer
+    }
+
+    /// Start tracking an owner: watch them and sync their full position history
+    ///
+    /// Returns how many new positions were stored. Tracked owners are the
+    /// watchlist, so the `sync` binary and API cache warming pick them up too.
+    pub async fn track_owner(&self, owner: &str) -> Result<usize> {
+        let owner = Address::parse(owner)?;
+        add_to_watchlist(&self.db_pool, &owner, TRACK_SOURCE).await?;
+        self.indexer.sync_owner_positions(&self.db_pool, &owner.canonical()).await
+    }
+
+    /// Sync every tracked owner's positions, then the last hour of swaps in their pools
+    ///
+    /// Call it periodically; an owner whose sync fails is logged and skipped.
+    pub async fn refresh(&self) -> Result<RefreshReport> {
+        let mut report = RefreshReport::default();
+        let mut pool_ids = BTreeSet::new();
+        for watched in get_watchlist(&self.db_pool).await? {
+            report.owners += 1;
+            let owner = watched.address.canonical();
+            match self.indexer.sync_owner_positions(&self.db_pool, &owner).await {
+                Ok(inserted) => report.positions += inserted,
+                Err(e) => warn!("Failed to sync positions of {}: {}", watched.address, e),
+            }
+            let filter =
+                PositionFilter { owners: Some(vec![watched.address]), ..Default::default() };
+            pool_ids.extend(
+                find_positions(&self.db_pool, &filter).await?.into_iter().map(|p| p.pool_id),
+            );
+        }
+
+        let pool_ids: Vec<String> = pool_ids.into_iter().collect();
+        report.pools = pool_ids.len();
+        if !pool_ids.is_empty() {
+            report.swaps = self.indexer.sync_swaps_for_pools(&self.db_pool, &pool_ids).await?;
+        }
+        info!(
+            "Refreshed {} owners: {} new positions, {} swaps in {} pools",
+            report.owners, report.positions, report.swaps, report.pools
+        );
+        Ok(report)
+    }
+
+    /// An owner's positions (archived ones left out), valued at each pool's TWAP
+    pub async fn portfolio(&self, owner: &str) -> Result<Portfolio> {
+        let owner = Address::parse(owner)?;
+        let filter = PositionFilter { owners: Some(vec![owner]), ..Default::default() };
+        let positions = find_positions(&self.db_pool, &filter).await?;
+        let snapshots = get_snapshots_for_owners(&self.db_pool, &[owner]).await?;
+        let ids: Vec<i64> = positions.iter().map(|p| p.id).collect();
+        let fees: HashMap<i64, Decimal> = get_fee_accumulators(&self.db_pool, &ids)
+            .await?
+            .into_iter()
+            .map(|a| (a.position_id, a.fees_earned))
+            .collect();
+
+        let as_of = Utc::now();
+        let mut ticks: HashMap<String, Option<i32>> = HashMap::new();
+        for position in &positions {
+            if !ticks.contains_key(&position.pool_id) {
+                let tick = self.pool_tick(&position.pool_id, as_of).await?;
+                ticks.insert(position.pool_id.clone(), tick);
+            }
+        }
+
+        let mut greeks = Vec::new();
+        let mut entries = Vec::with_capacity(positions.len());
+        for position in &positions {
+            let tick = ticks[&position.pool_id];
+            let range = TickRange::of(position).ok();
+            let liquidity = Decimal::from_str(&position.liquidity.to_string()).ok();
+            let mut value = None;
+            if let (Some(tick), Some(range), Some(liquidity)) = (tick, range, liquidity) {
+                let price = tick_to_price(tick);
+                let (price_lower, price_upper) = range_prices(range.lower, range.upper);
+                value = Some(
+                    amounts_for_liquidity(liquidity, price, price_lower, price_upper)
+                        .value_in_token1(price),
+                );
+                if !liquidity.is_zero() {
+                    let position_greeks = position_greeks(liquidity, range, price);
+                    greeks.push((position.pool_id.clone(), price, position_greeks));
+                }
+            }
+            entries.push(PortfolioPosition {
+                lifecycle: position.lifecycle(),
+                current_tick: tick,
+                in_range: tick.zip(range).map(|(tick, range)| range.contains(tick)),
+                value,
+                fees_earned: fees.get(&position.id).copied(),
+                position: position.clone(),
+            });
+        }
+
+        Ok(Portfolio {
+            owner,
+            as_of,
+            holding: summarize_holding(&positions, &snapshots, as_of),
+            exposure: summarize_exposure(greeks),
+            positions: entries,
+        })
+    }
+
+    /// A pool's TWAP tick over the configured window ending at `to`, None without swaps
+    async fn pool_tick(&self, pool_id: &str, to: DateTime<Utc>) -> Result<Option<i32>> {
+        let from = to - self.twap.window();
+        let mut swaps = get_swaps_for_pool_between(&self.db_pool, pool_id, from, to).await?;
+        swaps.extend(get_last_swap_before(&self.db_pool, pool_id, from).await?);
+        Ok(calculate_twap(&swaps, from, to).map(|twap| twap.tick))
+    }
+}
+
+/// Apply the workspace's migrations
+async fn migrate(db_pool: &PgPool) -> Result<()> {
+    sqlx::migrate!("../../migrations").run(db_pool).await.context("Failed to run migrations")
+}
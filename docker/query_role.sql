@@ -0,0 +1,69 @@
+-- Read-only role for ad hoc SQL (POST /query and the `query` binary), one per
+-- instance schema (DB_SCHEMA). Run it as a role with CREATEROLE after
+-- migrating, and again after upgrades that add tables:
+--   psql -v schema=public -v role=stillwater_public_readonly -f docker/query_role.sql
+-- then create a login in the role and point QUERY_DATABASE_URL at it:
+--   CREATE ROLE analyst LOGIN PASSWORD '...' IN ROLE stillwater_public_readonly;
+--
+-- Only the tables and columns granted below are readable, and only in the
+-- given schema. API keys, sign-in nonces, Telegram chat links and link codes
+-- are left out, as are alert sinks (webhook URLs can carry tokens) and
+-- delivery errors.
+\set ON_ERROR_STOP on
+
+SELECT format('CREATE ROLE %I NOLOGIN', :'role')
+WHERE NOT EXISTS (SELECT FROM pg_roles WHERE rolname = :'role') \gexec
+
+GRANT USAGE ON SCHEMA :"schema" TO :"role";
+
+GRANT SELECT ON
+    :"schema".pools,
+    :"schema".positions,
+    :"schema".position_snapshots,
+    :"schema".position_transfers,
+    :"schema".position_fee_accumulators,
+    :"schema".quarantined_positions,
+    :"schema".liquidity_events,
+    :"schema".gas_expenses,
+    :"schema".gas_prices,
+    :"schema".swaps,
+    :"schema".swaps_archive,
+    :"schema".swap_minute_aggregates,
+    :"schema".tick_series_chunks,
+    :"schema".pool_daily_volume,
+    :"schema".pool_fee_growth,
+    :"schema".pool_fee_overrides,
+    :"schema".pool_initializations,
+    :"schema".pool_param_changes,
+    :"schema".pool_swap_downsampling,
+    :"schema".data_quality_issues,
+    :"schema".retention_horizons,
+    :"schema".log_scan_cursors,
+    :"schema".sync_runs,
+    :"schema".sync_schedule,
+    :"schema".sync_checkpoints,
+    :"schema".jobs,
+    :"schema".backtests,
+    :"schema".watchlist,
+    :"schema".accounts,
+    :"schema".account_addresses,
+    :"schema".vaults,
+    :"schema".quote_preferences,
+    :"schema".alert_templates,
+    :"schema".alert_rule_firings
+TO :"role";
+
+GRANT SELECT (
+    id, owner, pool_id, condition, threshold, tick_threshold, cooldown_seconds, enabled,
+    created_at, updated_at
+) ON :"schema".alert_rules TO :"role";
+
+GRANT SELECT (owner, token, added_at) ON :"schema".token_watchlist TO :"role";
+
+GRANT SELECT (
+    id, idempotency_key, sink, payload, status, attempts, next_attempt_at, claimed_until,
+    delivered_at, created_at
+) ON :"schema".pending_alerts TO :"role";
+
+-- Shared by every instance in the database, so it stays in `public`
+GRANT SELECT (owner, token0, token1, chain_ids, added_at) ON public.pair_watchlist TO :"role";
@@ -0,0 +1,18 @@
+-- Pending alerts table: per-sink delivery queue with retry state
+-- One row per (sink, alert key); the unique idempotency key guarantees an
+-- alert is never delivered twice to the same sink, even after partial success
+CREATE TABLE pending_alerts (
+    id BIGSERIAL PRIMARY KEY,
+    idempotency_key VARCHAR(255) NOT NULL UNIQUE,
+    sink VARCHAR(255) NOT NULL,            -- Sink identifier (e.g. "telegram:12345")
+    payload JSONB NOT NULL,                -- Serialized alert
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',  -- pending | delivered | failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    delivered_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Partial index for the retry worker's due-alert scan
+CREATE INDEX idx_pending_alerts_due ON pending_alerts(next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,35 @@
+-- Watchlist table: addresses whose positions stillwater tracks
+CREATE TABLE watchlist (
+    address VARCHAR(42) PRIMARY KEY,      -- Lowercase owner address
+    source VARCHAR(32) NOT NULL,          -- How the address was added (e.g. "siwe", "manual")
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- API keys table: only a hash of each key is stored
+CREATE TABLE api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    key_hash VARCHAR(66) NOT NULL UNIQUE,  -- keccak256 of the key (0x-prefixed hex)
+    label VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ
+);
+
+-- Addresses whose ownership an API key holder has proven
+CREATE TABLE api_key_addresses (
+    api_key_id BIGINT NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    address VARCHAR(42) NOT NULL,
+    verified_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (api_key_id, address)
+);
+
+-- Single-use nonces for Sign-In with Ethereum (EIP-4361)
+CREATE TABLE auth_nonces (
+    nonce VARCHAR(64) PRIMARY KEY,
+    address VARCHAR(42) NOT NULL,          -- Lowercase address the nonce was issued for
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_api_key_addresses_address ON api_key_addresses(address);
+CREATE INDEX idx_auth_nonces_expires_at ON auth_nonces(expires_at);
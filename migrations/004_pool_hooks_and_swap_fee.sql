@@ -0,0 +1,8 @@
+-- Hook contract attached to each pool (zero address when the pool has no hooks)
+ALTER TABLE pools ADD COLUMN hooks VARCHAR(42) NOT NULL DEFAULT '0x0000000000000000000000000000000000000000';
+
+-- Fee charged on each swap in hundredths of a bip, when known.
+-- Dynamic-fee pools can charge a different fee on every swap.
+ALTER TABLE swaps ADD COLUMN fee INTEGER;
+
+CREATE INDEX idx_pools_hooks ON pools(hooks);
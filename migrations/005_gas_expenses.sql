@@ -0,0 +1,11 @@
+-- Gas expenses table: transaction costs attributed to positions
+CREATE TABLE gas_expenses (
+    id BIGSERIAL PRIMARY KEY,
+    position_id BIGINT NOT NULL REFERENCES positions(id) ON DELETE CASCADE,
+    tx_hash VARCHAR(66) NOT NULL,
+    gas_cost NUMERIC(78, 18) NOT NULL,    -- Cost in native token (decimal)
+    timestamp TIMESTAMPTZ NOT NULL,
+    UNIQUE(position_id, tx_hash)
+);
+
+CREATE INDEX idx_gas_expenses_position_id ON gas_expenses(position_id, timestamp);
@@ -0,0 +1,3 @@
+-- Flag positions imported by hand (e.g. CSV migration from spreadsheets)
+-- so they can be told apart from positions synced from the subgraph
+ALTER TABLE positions ADD COLUMN manual BOOLEAN NOT NULL DEFAULT FALSE;
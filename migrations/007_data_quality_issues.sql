@@ -0,0 +1,14 @@
+-- Data quality issues: anomalies found in indexed swap data
+-- One row per (pool, kind, swap) so re-running the checker is idempotent
+CREATE TABLE data_quality_issues (
+    id BIGSERIAL PRIMARY KEY,
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL,             -- timestamp_gap | impossible_amount | duplicate_event | non_monotonic
+    swap_id BIGINT NOT NULL REFERENCES swaps(id) ON DELETE CASCADE,
+    tx_hash VARCHAR(66) NOT NULL,
+    detail TEXT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(pool_id, kind, swap_id)
+);
+
+CREATE INDEX idx_data_quality_issues_pool ON data_quality_issues(pool_id, detected_at DESC);
@@ -0,0 +1,24 @@
+-- Quarantined positions: positions whose tick range can't be analyzed
+-- (tick_lower >= tick_upper or ticks out of bounds), kept aside for inspection
+-- instead of producing nonsense health and P&L figures
+CREATE TABLE quarantined_positions (
+    nft_id VARCHAR(78) PRIMARY KEY,
+    owner VARCHAR(42) NOT NULL,
+    pool_id VARCHAR(66) NOT NULL,
+    tick_lower INTEGER NOT NULL,
+    tick_upper INTEGER NOT NULL,
+    liquidity NUMERIC(78, 0) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    reason TEXT NOT NULL,
+    quarantined_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Move invalid positions synced before validation existed
+INSERT INTO quarantined_positions (nft_id, owner, pool_id, tick_lower, tick_upper, liquidity, created_at, reason)
+SELECT nft_id, owner, pool_id, tick_lower, tick_upper, liquidity, created_at,
+       CASE WHEN tick_lower = tick_upper THEN 'zero-width range' ELSE 'invalid range' END
+FROM positions
+WHERE tick_lower >= tick_upper OR tick_lower < -887272 OR tick_upper > 887272;
+
+DELETE FROM positions
+WHERE tick_lower >= tick_upper OR tick_lower < -887272 OR tick_upper > 887272;
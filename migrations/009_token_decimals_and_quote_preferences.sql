@@ -0,0 +1,12 @@
+-- Token decimals, needed to show human-readable prices
+ALTER TABLE pools ADD COLUMN token0_decimals SMALLINT NOT NULL DEFAULT 18;
+ALTER TABLE pools ADD COLUMN token1_decimals SMALLINT NOT NULL DEFAULT 18;
+
+-- Quote token preferences: which token of a pool an owner wants prices quoted in
+CREATE TABLE quote_preferences (
+    owner VARCHAR(42) NOT NULL,
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    quote_token VARCHAR(42) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (owner, pool_id)
+);
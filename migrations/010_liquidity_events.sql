@@ -0,0 +1,17 @@
+-- Liquidity events: every ModifyLiquidity event applied to positions, signed.
+-- Additions create positions; removals reduce the liquidity of the owner's
+-- matching open positions (oldest first). Recording each event once keeps
+-- re-syncing the same window from subtracting a removal twice.
+CREATE TABLE liquidity_events (
+    event_id VARCHAR(78) PRIMARY KEY,
+    owner VARCHAR(42) NOT NULL,
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    tick_lower INTEGER NOT NULL,
+    tick_upper INTEGER NOT NULL,
+    liquidity_delta NUMERIC(78, 0) NOT NULL,   -- Signed: positive adds, negative removes
+    kind VARCHAR(8) NOT NULL,                  -- 'add' or 'remove'
+    position_id BIGINT REFERENCES positions(id) ON DELETE SET NULL,
+    timestamp TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_liquidity_events_position ON liquidity_events (owner, pool_id, tick_lower, tick_upper);
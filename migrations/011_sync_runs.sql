@@ -0,0 +1,20 @@
+-- Sync runs: per-stage timings and row counts for every position sync, so
+-- regressions in sync latency show up as a trend rather than a hunch
+CREATE TABLE sync_runs (
+    id BIGSERIAL PRIMARY KEY,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL,
+    status VARCHAR(16) NOT NULL,           -- succeeded | failed
+    error TEXT,
+    fetch_ms BIGINT NOT NULL DEFAULT 0,    -- Subgraph requests
+    parse_ms BIGINT NOT NULL DEFAULT 0,    -- Converting events to typed rows
+    dedupe_ms BIGINT NOT NULL DEFAULT 0,   -- Token filter, duplicate events, ordering
+    insert_ms BIGINT NOT NULL DEFAULT 0,   -- Database writes
+    rows_fetched INTEGER NOT NULL DEFAULT 0,
+    rows_parsed INTEGER NOT NULL DEFAULT 0,
+    rows_kept INTEGER NOT NULL DEFAULT 0,  -- Left after dedupe
+    rows_inserted INTEGER NOT NULL DEFAULT 0,
+    rows_removed INTEGER NOT NULL DEFAULT 0 -- Liquidity removals applied
+);
+
+CREATE INDEX idx_sync_runs_started ON sync_runs(started_at DESC);
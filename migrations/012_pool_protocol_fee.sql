@@ -0,0 +1,4 @@
+-- v4 protocol fee, read from pool state (StateView.getSlot0). Packed like the
+-- contract's uint24: the low 12 bits are the zeroForOne fee and the high 12 bits
+-- the oneForZero fee, each in hundredths of a bip (max 1000 = 0.1%).
+ALTER TABLE pools ADD COLUMN protocol_fee INTEGER NOT NULL DEFAULT 0;
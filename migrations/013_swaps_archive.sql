@@ -0,0 +1,18 @@
+-- Cold tier for swaps: swaps older than the archive horizon are moved here by
+-- the sync binary (SWAP_ARCHIVE_AFTER_DAYS). Partitioned by month so old
+-- months can be detached, dumped or dropped without touching recent ones.
+-- Partitions (swaps_archive_yYYYYmMM) are created by the archiver as needed.
+-- Data quality issues reference hot swaps and are removed when their swap is archived.
+CREATE TABLE swaps_archive (
+    id BIGINT NOT NULL,                   -- Original swaps.id
+    tx_hash VARCHAR(66) NOT NULL,
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    amount0 NUMERIC(78, 0) NOT NULL,
+    amount1 NUMERIC(78, 0) NOT NULL,
+    fee INTEGER,
+    timestamp TIMESTAMPTZ NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id, timestamp)
+) PARTITION BY RANGE (timestamp);
+
+CREATE INDEX idx_swaps_archive_pool_time ON swaps_archive (pool_id, timestamp);
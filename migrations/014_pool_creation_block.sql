@@ -0,0 +1,8 @@
+-- Pool creation time and block from the subgraph (createdAtTimestamp,
+-- createdAtBlockNumber). Both stay NULL until a sync sees the pool; the
+-- insert time previously stored in created_at wasn't the pool's creation
+-- time, so it's cleared and backfilled on the next sync.
+ALTER TABLE pools ALTER COLUMN created_at DROP NOT NULL;
+ALTER TABLE pools ALTER COLUMN created_at DROP DEFAULT;
+ALTER TABLE pools ADD COLUMN created_at_block BIGINT;
+UPDATE pools SET created_at = NULL;
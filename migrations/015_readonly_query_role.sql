@@ -0,0 +1,23 @@
+-- Group role for ad hoc SQL (GET /query and the `query` binary). Operators
+-- create a login role in it and point QUERY_DATABASE_URL at that login, e.g.
+--   CREATE ROLE analyst LOGIN PASSWORD '...' IN ROLE stillwater_readonly;
+-- It can read every table in the instance schema except API keys and auth
+-- nonces. Tables added by later migrations are readable by default.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'stillwater_readonly') THEN
+        CREATE ROLE stillwater_readonly NOLOGIN;
+    END IF;
+
+    EXECUTE format('GRANT USAGE ON SCHEMA %I TO stillwater_readonly', current_schema());
+    EXECUTE format('GRANT SELECT ON ALL TABLES IN SCHEMA %I TO stillwater_readonly', current_schema());
+    EXECUTE format(
+        'ALTER DEFAULT PRIVILEGES IN SCHEMA %I GRANT SELECT ON TABLES TO stillwater_readonly',
+        current_schema()
+    );
+    EXECUTE format(
+        'REVOKE ALL ON %I.api_keys, %I.api_key_addresses, %I.auth_nonces FROM stillwater_readonly',
+        current_schema(), current_schema(), current_schema()
+    );
+END
+$$;
@@ -0,0 +1,24 @@
+-- Soft delete: positions hidden by their owner stay, with their history,
+-- until the sync purges them after DELETED_POSITION_RETENTION_DAYS
+ALTER TABLE positions ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_positions_deleted_at ON positions (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Daily swap totals, rolled up from raw swaps as retention deletes them
+-- (SWAP_RETENTION_DAYS) and kept forever
+CREATE TABLE pool_daily_volume (
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    day DATE NOT NULL,                    -- UTC day
+    swap_count BIGINT NOT NULL,
+    volume0 NUMERIC(78, 0) NOT NULL,      -- Sum of |amount0|
+    volume1 NUMERIC(78, 0) NOT NULL,      -- Sum of |amount1|
+    PRIMARY KEY (pool_id, day)
+);
+
+-- Oldest retained time per data set ('swaps', 'position_snapshots'); analytics
+-- windows starting earlier are reported as incomplete
+CREATE TABLE retention_horizons (
+    dataset VARCHAR(32) PRIMARY KEY,
+    retained_from TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
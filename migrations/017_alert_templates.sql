@@ -0,0 +1,10 @@
+-- Alert templates: an owner's custom title/message per kind of alert
+-- Rendered with minijinja when an alert about one of the owner's positions fires
+CREATE TABLE alert_templates (
+    owner VARCHAR(42) NOT NULL,
+    kind VARCHAR(32) NOT NULL,             -- range_exited | range_entered | compound
+    title TEXT,                            -- NULL keeps the default title
+    body TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (owner, kind)
+);
@@ -0,0 +1,10 @@
+-- Sync checkpoints: the subgraph head block (number and hash) each successful
+-- sync ran up to. The next sync re-checks the hashes; when one is no longer on
+-- the canonical chain (a reorg), checkpoints are rolled back to the last one
+-- that is and data after it is re-fetched.
+CREATE TABLE sync_checkpoints (
+    block_number BIGINT PRIMARY KEY,
+    block_hash VARCHAR(66) NOT NULL,
+    block_timestamp TIMESTAMPTZ,            -- NULL when the subgraph doesn't report it
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
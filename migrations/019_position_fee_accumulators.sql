@@ -0,0 +1,17 @@
+-- Running fee totals per position, advanced by the sync as new swaps arrive so
+-- refreshing a position's fees doesn't re-read its whole swap history. Swaps
+-- are folded in by ID (insertion order), so late-arriving swaps are still
+-- counted. Each sync re-verifies a few accumulators against a full
+-- recomputation; reorg rollbacks clear them to be rebuilt.
+CREATE TABLE position_fee_accumulators (
+    position_id BIGINT PRIMARY KEY REFERENCES positions(id) ON DELETE CASCADE,
+    last_swap_id BIGINT NOT NULL DEFAULT 0,   -- Highest swap ID folded in
+    swap_count BIGINT NOT NULL DEFAULT 0,
+    volume NUMERIC NOT NULL DEFAULT 0,        -- Sum of swap volumes (abs amount0 + amount1)
+    fees_earned NUMERIC NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    verified_at TIMESTAMPTZ                   -- Last full recomputation check
+);
+
+CREATE INDEX idx_position_fee_accumulators_verified
+    ON position_fee_accumulators (verified_at NULLS FIRST);
@@ -0,0 +1,14 @@
+-- Position NFT transfers, from the PositionManager's Transfer events. A
+-- position's owner follows its latest transfer; the history keeps each owner's
+-- holding period (effective from the transfer's block time) so P&L can be split
+-- between them. Mints and burns (from or to the zero address) aren't recorded.
+CREATE TABLE position_transfers (
+    transfer_id VARCHAR(78) PRIMARY KEY,      -- Subgraph Transfer entity ID
+    position_id BIGINT NOT NULL REFERENCES positions(id) ON DELETE CASCADE,
+    from_owner VARCHAR(42) NOT NULL,
+    to_owner VARCHAR(42) NOT NULL,
+    tx_hash VARCHAR(66),
+    effective_from TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_position_transfers_position ON position_transfers (position_id, effective_from);
@@ -0,0 +1,31 @@
+-- Alert rules: which alerts fire, for whose positions, and where they go.
+-- Managed at runtime through /admin/alert-rules or the `rules` command; the
+-- watch and sync jobs reload them, so changes apply without a restart. The
+-- seeded rules send the same alerts as before rules existed.
+CREATE TABLE alert_rules (
+    id BIGSERIAL PRIMARY KEY,
+    owner VARCHAR(42),                     -- NULL: every watched owner
+    pool_id VARCHAR(66),                   -- NULL: any pool
+    condition VARCHAR(32) NOT NULL,        -- range_exited | range_entered | compound
+    threshold NUMERIC,                     -- compound: fees-to-gas multiple (NULL: COMPOUND_GAS_MULTIPLE)
+    sinks TEXT[] NOT NULL DEFAULT '{}',    -- telegram:<chat_id> | webhook:<url>; empty: the configured sinks
+    cooldown_seconds BIGINT NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_alert_rules_owner ON alert_rules (LOWER(owner));
+
+-- When each rule last fired for each position, for cooldowns
+CREATE TABLE alert_rule_firings (
+    rule_id BIGINT NOT NULL REFERENCES alert_rules(id) ON DELETE CASCADE,
+    position_id BIGINT NOT NULL REFERENCES positions(id) ON DELETE CASCADE,
+    fired_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (rule_id, position_id)
+);
+
+INSERT INTO alert_rules (condition, cooldown_seconds) VALUES
+    ('range_exited', 0),
+    ('range_entered', 0),
+    ('compound', 86400);
@@ -0,0 +1,8 @@
+-- Swap volume is now counted in token1 (both legs valued at the execution
+-- price) instead of adding raw token0 and token1 amounts, which mixed tokens
+-- with different decimals. Accumulated totals used the old measure, so they
+-- are cleared for the next sync to rebuild from full history.
+DELETE FROM position_fee_accumulators;
+
+COMMENT ON COLUMN position_fee_accumulators.volume IS
+    'Sum of swap volumes in raw token1 units (2 * abs amount1)';
@@ -0,0 +1,4 @@
+-- Advisory alerts when a position's recent fee accrual rate falls well below
+-- its baseline (e.g. volume moved to another fee tier or pool). Checked by the
+-- sync; the threshold is the fractional drop (NULL: FEE_VELOCITY_DROP).
+INSERT INTO alert_rules (condition, cooldown_seconds) VALUES ('fee_velocity_drop', 86400);
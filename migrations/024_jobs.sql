@@ -0,0 +1,20 @@
+-- Background jobs: backfills and heavy reports requested through the API,
+-- claimed by `worker` processes with FOR UPDATE SKIP LOCKED so any number of
+-- workers can share the queue without handing out the same job twice
+CREATE TABLE jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR(32) NOT NULL,             -- backfill | ledger_export
+    owner VARCHAR(42) NOT NULL,
+    params JSONB NOT NULL DEFAULT '{}',
+    status VARCHAR(16) NOT NULL DEFAULT 'pending', -- pending | running | succeeded | failed
+    attempts INTEGER NOT NULL DEFAULT 0,   -- Times a worker claimed the job
+    result JSONB,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ
+);
+
+-- Claiming scans pending jobs oldest first
+CREATE INDEX idx_jobs_pending ON jobs (created_at) WHERE status = 'pending';
+CREATE INDEX idx_jobs_owner ON jobs (LOWER(owner), created_at DESC);
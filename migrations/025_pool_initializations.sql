@@ -0,0 +1,29 @@
+-- Pool creations read from the PoolManager's Initialize events, so new pools
+-- are known as soon as they exist (not when a position first appears in
+-- them), with their true creation time and starting price
+CREATE TABLE pool_initializations (
+    pool_id VARCHAR(66) PRIMARY KEY REFERENCES pools(pool_id) ON DELETE CASCADE,
+    sqrt_price_x96 NUMERIC(78, 0) NOT NULL, -- Starting price, Q64.96 sqrt of token1 per token0
+    tick INTEGER NOT NULL,                  -- Tick of the starting price
+    block_number BIGINT NOT NULL,
+    tx_hash VARCHAR(66),
+    created_at TIMESTAMPTZ NOT NULL         -- Block timestamp of the creation
+);
+
+-- Tokens owners want to hear about new pools for
+CREATE TABLE token_watchlist (
+    owner VARCHAR(42) NOT NULL,            -- Lowercase owner address
+    token VARCHAR(42) NOT NULL,            -- Lowercase token address (zero: native currency)
+    sinks TEXT[] NOT NULL DEFAULT '{}',    -- telegram:<chat_id> | webhook:<url>; empty: the configured sinks
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (owner, token)
+);
+
+CREATE INDEX idx_token_watchlist_token ON token_watchlist(token);
+
+-- How far log scans (e.g. pool creations) have read, so restarts resume there
+CREATE TABLE log_scan_cursors (
+    name VARCHAR(64) PRIMARY KEY,
+    block_number BIGINT NOT NULL,          -- Last block scanned
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
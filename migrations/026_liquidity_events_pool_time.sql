@@ -0,0 +1,3 @@
+-- Liquidity events by pool and time, for replaying a pool's liquidity over a
+-- window (JIT liquidity detection).
+CREATE INDEX idx_liquidity_events_pool_time ON liquidity_events (pool_id, timestamp);
@@ -0,0 +1,9 @@
+-- Operator-set LP fee for pools whose fee can't be resolved (dynamic-fee hooks
+-- without recorded swap fees, pools seeded without subgraph data), used by
+-- analytics in place of the 0.3% default
+CREATE TABLE pool_fee_overrides (
+    pool_id VARCHAR(66) PRIMARY KEY REFERENCES pools(pool_id) ON DELETE CASCADE,
+    fee INTEGER NOT NULL CHECK (fee BETWEEN 0 AND 1000000), -- Hundredths of a bip (3000 = 0.3%)
+    note TEXT,                                              -- Where the figure came from
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
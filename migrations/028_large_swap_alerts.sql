@@ -0,0 +1,8 @@
+-- Alerts when a single swap in a position's pool is large (by USD value) or
+-- moves the price by many ticks, often a sign the position is about to leave
+-- its range. Checked by `watch` from PoolManager Swap logs; the threshold is
+-- the USD value (NULL: LARGE_SWAP_USD) and tick_threshold the tick move (NULL:
+-- LARGE_SWAP_TICKS).
+ALTER TABLE alert_rules ADD COLUMN tick_threshold INTEGER;
+
+INSERT INTO alert_rules (condition, cooldown_seconds) VALUES ('large_swap', 3600);
@@ -0,0 +1,16 @@
+-- Stored backtest runs, so runs made while tuning a strategy can be listed and
+-- compared later. The equity curve is kept apart from the headline figures so
+-- listings don't read it.
+CREATE TABLE backtests (
+    id BIGSERIAL PRIMARY KEY,
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    label TEXT,                            -- Free-form note, e.g. the idea being tried
+    params JSONB NOT NULL,                 -- Range, capital, fee and gas assumptions, compounding
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    metrics JSONB NOT NULL,                -- Final equity, fees, IL, P&L, APY, time in range
+    equity_curve JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_backtests_pool ON backtests (pool_id, created_at DESC);
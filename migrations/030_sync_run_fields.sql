@@ -0,0 +1,4 @@
+-- Fields the subgraph left out of each sync's liquidity events: per optional
+-- field the number of events lacking it, and per parse error the number of
+-- events skipped, so schema drift between subgraph versions is visible
+ALTER TABLE sync_runs ADD COLUMN fields JSONB NOT NULL DEFAULT '{}';
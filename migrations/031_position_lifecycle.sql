@@ -0,0 +1,18 @@
+-- Position lifecycle: active (liquidity > 0), closed (closed_at set when the
+-- last liquidity is removed) and archived (closed for longer than
+-- POSITION_ARCHIVE_AFTER_DAYS; hidden from listings unless asked for)
+ALTER TABLE positions ADD COLUMN closed_at TIMESTAMPTZ;
+ALTER TABLE positions ADD COLUMN archived_at TIMESTAMPTZ;
+
+-- Already closed positions date from their last recorded removal, or from
+-- now when none was recorded
+UPDATE positions p
+SET closed_at = COALESCE(
+    (SELECT MAX(e.timestamp) FROM liquidity_events e
+     WHERE e.position_id = p.id AND e.kind = 'remove'),
+    NOW()
+)
+WHERE p.liquidity = 0;
+
+CREATE INDEX idx_positions_closed_at ON positions (closed_at)
+    WHERE closed_at IS NOT NULL AND archived_at IS NULL;
@@ -0,0 +1,14 @@
+-- Compressed swap tick history, one chunk per pool and UTC month, built by
+-- the sync once a month is over and kept after retention deletes raw swaps.
+-- data: version byte, point count, then per swap the delta-of-delta of its
+-- timestamp (seconds) and the delta of its tick, as zigzag varints
+CREATE TABLE tick_series_chunks (
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    month_start TIMESTAMPTZ NOT NULL,
+    point_count INTEGER NOT NULL,
+    first_at TIMESTAMPTZ,
+    last_at TIMESTAMPTZ,
+    data BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (pool_id, month_start)
+);
@@ -0,0 +1,5 @@
+-- Pool price at each liquidity addition: the execution price of the pool's
+-- last swap at or before it, filled in by the sync once that swap is recorded.
+-- Kept on the event so the entry of positions built up over several deposits
+-- can be averaged after retention has deleted the swaps.
+ALTER TABLE liquidity_events ADD COLUMN price NUMERIC;
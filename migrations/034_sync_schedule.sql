@@ -0,0 +1,11 @@
+-- Operator control of scheduled syncs. While paused, the `sync` binary exits
+-- without syncing unless run with --force; syncs queued through /admin/sync
+-- still run in a worker. A single row, created here.
+CREATE TABLE sync_schedule (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    reason TEXT,                            -- Why syncs were paused, for other operators
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO sync_schedule (id) VALUES (TRUE);
@@ -0,0 +1,10 @@
+-- Pools' fee growth globals sampled from pool state by each sync, so fees
+-- attributed to positions can be checked against the pool's own accounting
+CREATE TABLE pool_fee_growth (
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    sampled_at TIMESTAMPTZ NOT NULL,
+    fee_growth_global0 NUMERIC(78, 0) NOT NULL, -- Q128.128 token0 fees per unit of liquidity
+    fee_growth_global1 NUMERIC(78, 0) NOT NULL, -- Q128.128 token1 fees per unit of liquidity
+    tick INTEGER NOT NULL,                      -- Pool tick when sampled
+    PRIMARY KEY (pool_id, sampled_at)
+);
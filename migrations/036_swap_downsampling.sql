@@ -0,0 +1,19 @@
+-- Opt-in downsampling of swap storage for high-volume pools. For every
+-- finished UTC day the sync keeps the pool's largest swaps and replaces the
+-- others with one row per minute in `swaps` (tx_hash 'minute:<unix seconds>')
+-- whose amounts add up the minute's absolute amounts, so analytics read them
+-- as swaps at the minute's volume-weighted price.
+CREATE TABLE pool_swap_downsampling (
+    pool_id VARCHAR(66) PRIMARY KEY REFERENCES pools(pool_id) ON DELETE CASCADE,
+    keep_largest INTEGER NOT NULL CHECK (keep_largest >= 0), -- Largest swaps kept per day
+    downsampled_through TIMESTAMPTZ,                         -- Start of the first day not yet done
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- How many swaps each minute row stands for
+CREATE TABLE swap_minute_aggregates (
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    minute TIMESTAMPTZ NOT NULL,
+    swap_count INTEGER NOT NULL,
+    PRIMARY KEY (pool_id, minute)
+);
@@ -0,0 +1,6 @@
+-- Chats talking to the Telegram bot (`bot` binary) and the owner each follows
+CREATE TABLE telegram_chats (
+    chat_id VARCHAR(64) PRIMARY KEY,
+    owner VARCHAR(42) NOT NULL,
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+-- Progress of long jobs: a backfill saves where it is after each pool, so a
+-- requeued job resumes from `cursor` instead of starting over
+ALTER TABLE jobs ADD COLUMN progress NUMERIC(5, 2);   -- Percent done, for jobs that report it
+ALTER TABLE jobs ADD COLUMN cursor JSONB;             -- Kind-specific resume state
+ALTER TABLE jobs ADD COLUMN heartbeat_at TIMESTAMPTZ; -- Last claim or progress update
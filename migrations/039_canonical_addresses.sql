@@ -0,0 +1,19 @@
+-- Owner addresses are stored lowercase, the form `Address` encodes to, so
+-- lookups compare them with `=` and use the plain owner indexes. Lowercase
+-- any rows written in another case before that.
+UPDATE positions SET owner = LOWER(owner) WHERE owner <> LOWER(owner);
+UPDATE quarantined_positions SET owner = LOWER(owner) WHERE owner <> LOWER(owner);
+UPDATE liquidity_events SET owner = LOWER(owner) WHERE owner <> LOWER(owner);
+UPDATE position_transfers
+SET from_owner = LOWER(from_owner), to_owner = LOWER(to_owner)
+WHERE from_owner <> LOWER(from_owner) OR to_owner <> LOWER(to_owner);
+UPDATE telegram_chats SET owner = LOWER(owner) WHERE owner <> LOWER(owner);
+
+-- The watchlist is keyed by address: keep the earliest entry of any that
+-- differ only in case
+DELETE FROM watchlist w
+USING watchlist other
+WHERE LOWER(other.address) = LOWER(w.address)
+  AND other.address <> w.address
+  AND (other.added_at, other.address) < (w.added_at, w.address);
+UPDATE watchlist SET address = LOWER(address) WHERE address <> LOWER(address);
@@ -0,0 +1,21 @@
+-- Pool parameters that can change under LPs: the LP fee a dynamic-fee hook
+-- sets and the protocol fee. Both are read from pool state (StateView.getSlot0)
+-- by `sync`; lp_fee stays NULL until a pool's state is first read, so that read
+-- only sets the baseline.
+ALTER TABLE pools ADD COLUMN lp_fee INTEGER;
+
+-- Every change `sync` has seen, alerting owners of positions in the pool
+CREATE TABLE pool_param_changes (
+    id BIGSERIAL PRIMARY KEY,
+    pool_id VARCHAR(66) NOT NULL REFERENCES pools(pool_id) ON DELETE CASCADE,
+    param VARCHAR(16) NOT NULL,            -- lp_fee | protocol_fee
+    old_value INTEGER NOT NULL,
+    new_value INTEGER NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_pool_param_changes_pool ON pool_param_changes (pool_id, detected_at DESC);
+
+-- Alerts on changes to a position's pool; the threshold is the relative change
+-- that fires (NULL: any change)
+INSERT INTO alert_rules (condition, cooldown_seconds) VALUES ('pool_param_change', 3600);
@@ -0,0 +1,17 @@
+-- Accounts: several of a user's wallets grouped under one name. Owner-scoped
+-- API routes take `account:<name>` in place of an address and combine the
+-- positions of every address in the account.
+CREATE TABLE accounts (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(64) NOT NULL UNIQUE,     -- Lowercase letters, digits, '-' and '_'
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE account_addresses (
+    account_id BIGINT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    address VARCHAR(42) NOT NULL,          -- Lowercase owner address
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (account_id, address)
+);
+
+CREATE INDEX idx_account_addresses_address ON account_addresses(address);
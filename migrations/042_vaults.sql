@@ -0,0 +1,9 @@
+-- Vaults: liquidity managers (Arrakis/Gamma-style) whose contract owns the
+-- position NFTs while depositors hold its ERC20 share token. A depositor's
+-- exposure is the vault's positions scaled by their share of the supply.
+CREATE TABLE vaults (
+    address VARCHAR(42) PRIMARY KEY,      -- Lowercase manager contract owning the positions
+    share_token VARCHAR(42) NOT NULL,     -- Lowercase ERC20 share token (often the vault itself)
+    name TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
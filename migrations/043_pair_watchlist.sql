@@ -0,0 +1,17 @@
+-- Token pairs owners want to hear about new pools (any fee tier or hooks) for.
+-- Instances for different chains keep their tables in their own schemas
+-- (DB_SCHEMA), so this one lives in `public` where every instance's `watch`
+-- reads it: a pair is watched on every chain sharing the database unless
+-- `chain_ids` narrows it.
+CREATE TABLE IF NOT EXISTS public.pair_watchlist (
+    owner VARCHAR(42) NOT NULL,             -- Lowercase owner address
+    token0 VARCHAR(42) NOT NULL,            -- Lowercase token addresses, token0 < token1
+    token1 VARCHAR(42) NOT NULL,
+    chain_ids BIGINT[] NOT NULL DEFAULT '{}', -- Chains to watch on; empty: every chain
+    sinks TEXT[] NOT NULL DEFAULT '{}',     -- telegram:<chat_id> | webhook:<url>; empty: the configured sinks
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (owner, token0, token1),
+    CHECK (token0 < token1)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pair_watchlist_pair ON public.pair_watchlist(token0, token1);
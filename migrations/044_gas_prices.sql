@@ -0,0 +1,13 @@
+-- Gas price history per chain, sampled by `watch` every few minutes and
+-- backfilled by `gas-history`. Backtests and rebalance policies scale their
+-- per-transaction gas cost by how these prices moved over the replayed window.
+CREATE TABLE gas_prices (
+    chain_id BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    base_fee_per_gas NUMERIC(78, 0),       -- wei; NULL before EIP-1559
+    priority_fee_per_gas NUMERIC(78, 0),   -- wei; the block's median tip, NULL if unknown
+    PRIMARY KEY (chain_id, block_number)
+);
+
+CREATE INDEX idx_gas_prices_time ON gas_prices (chain_id, timestamp);
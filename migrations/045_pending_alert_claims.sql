@@ -0,0 +1,11 @@
+-- Alert queue rows are claimed before delivery: the immediate attempt after
+-- enqueueing and the retry worker lease a row by setting `claimed_until`, and
+-- `FOR UPDATE SKIP LOCKED` keeps two workers from leasing the same row, so an
+-- alert is never sent twice at once. A lease that expires (the worker died
+-- mid-delivery) makes the row due again.
+ALTER TABLE pending_alerts ADD COLUMN claimed_until TIMESTAMPTZ;
+
+-- `sink` now holds a secret-free identifier (webhooks are named by a hash of
+-- their URL), so the spec needed to rebuild the sink is kept alongside it.
+-- NULL for rows queued before this migration, whose `sink` is the spec.
+ALTER TABLE pending_alerts ADD COLUMN target TEXT;
@@ -0,0 +1,8 @@
+-- One-time codes proving whoever links a Telegram chat controls the owner:
+-- issued through the API with the owner's API key, redeemed by `/link <code>`
+CREATE TABLE telegram_link_codes (
+    code_hash VARCHAR(66) PRIMARY KEY,     -- keccak256 of the code; the code itself isn't stored
+    owner VARCHAR(42) NOT NULL,            -- Lowercase owner address the code links to
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,25 @@
+-- Retire the blanket read-only role from 015: it could read every table,
+-- including alert sinks and Telegram links, and picked up new tables by
+-- default. Operators now grant an explicit allowlist with docker/query_role.sql.
+-- The role is shared by every instance in the database, so each instance
+-- revokes its own schema's grants and the last one drops it.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'stillwater_readonly') THEN
+        RETURN;
+    END IF;
+
+    EXECUTE format(
+        'ALTER DEFAULT PRIVILEGES IN SCHEMA %I REVOKE SELECT ON TABLES FROM stillwater_readonly',
+        current_schema()
+    );
+    EXECUTE format('REVOKE SELECT ON ALL TABLES IN SCHEMA %I FROM stillwater_readonly', current_schema());
+    EXECUTE format('REVOKE USAGE ON SCHEMA %I FROM stillwater_readonly', current_schema());
+
+    BEGIN
+        DROP ROLE stillwater_readonly;
+    EXCEPTION WHEN dependent_objects_still_exist THEN
+        RAISE NOTICE 'stillwater_readonly still has grants in another schema or database; left for its instance to drop';
+    END;
+END
+$$;